@@ -140,6 +140,23 @@ extern "C"
     extern int demi_pushto(demi_qtoken_t *qt_out, int sockqd, const demi_sgarray_t *sga,
                            const struct sockaddr *dest_addr, socklen_t size);
 
+    /**
+     * @brief Asynchronously pushes a scatter-gather array to a UDP socket I/O queue, from a given local address.
+     *
+     * @param qt_out    Store location for I/O queue token.
+     * @param sockqd    I/O queue descriptor of the target socket.
+     * @param sga       Scatter-gather array to push.
+     * @param src_addr  Local address that the datagram is sent from. Its port is ignored.
+     * @param src_size  Effective size of the source socket address data structure.
+     * @param dest_addr Address of destination host.
+     * @param dest_size Effective size of the destination socket address data structure.
+     *
+     * @return On successful completion, zero is returned. On failure, a positive error code is returned instead.
+     */
+    extern int demi_pushto_from(demi_qtoken_t *qt_out, int sockqd, const demi_sgarray_t *sga,
+                                const struct sockaddr *src_addr, socklen_t src_size,
+                                const struct sockaddr *dest_addr, socklen_t dest_size);
+
     /**
      * @brief Asynchronously pops a scatter-gather array from an I/O queue.
      *
@@ -150,6 +167,34 @@ extern "C"
      */
     extern int demi_pop(demi_qtoken_t *qt_out, int qd);
 
+    /**
+     * @brief Sets an option on a socket I/O queue.
+     *
+     * @param qd      Target I/O queue descriptor.
+     * @param level   Protocol level at which the option resides (e.g. SOL_SOCKET or IPPROTO_TCP).
+     * @param optname Name of the option (e.g. SO_BROADCAST or TCP_NODELAY).
+     * @param optval  Value of the option.
+     * @param optlen  Size of the option value.
+     *
+     * @return On successful completion, zero is returned. On failure, a positive error code is returned instead.
+     * ENOPROTOOPT is returned for options that are not supported on the target socket.
+     */
+    extern int demi_setsockopt(int qd, int level, int optname, const void *optval, socklen_t optlen);
+
+    /**
+     * @brief Gets the value of an option of a socket I/O queue.
+     *
+     * @param qd      Target I/O queue descriptor.
+     * @param level   Protocol level at which the option resides (e.g. SOL_SOCKET or IPPROTO_TCP).
+     * @param optname Name of the option (e.g. SO_BROADCAST or TCP_NODELAY).
+     * @param optval  Store location for the value of the option.
+     * @param optlen  Size of the store location on entry, and size of the option value on return.
+     *
+     * @return On successful completion, zero is returned. On failure, a positive error code is returned instead.
+     * ENOPROTOOPT is returned for options that are not supported on the target socket.
+     */
+    extern int demi_getsockopt(int qd, int level, int optname, void *optval, socklen_t *optlen);
+
 #ifdef __cplusplus
 }
 #endif
@@ -81,6 +81,7 @@ extern "C"
         DEMI_OPC_CONNECT,     /**< Connect operation. */
         DEMI_OPC_CLOSE,       /**< Close operation. */
         DEMI_OPC_FAILED,      /**< Operation failed.  */
+        DEMI_OPC_RESOLVE,     /**< Resolve operation. */
     } demi_opcode_t;
 
     /**
@@ -101,6 +102,25 @@ extern "C"
     #pragma pack(pop)
     #endif
 
+    /**
+     * @brief Result value for a pop operation on a UDP socket on which IP_PKTINFO is set.
+     */
+    #ifdef _WIN32
+    #pragma pack(push, 1)
+    typedef struct demi_pop_info_result
+    #endif
+    #ifdef __linux__
+    typedef struct __attribute__((__packed__)) demi_pop_info_result
+    #endif
+    {
+        demi_sgarray_t sga;          /**< Popped scatter-gather array.                                */
+        struct sockaddr_in dst_addr; /**< Destination address of the datagram.                        */
+        uint32_t ifindex;            /**< Index of the interface on which the datagram was received. */
+    } demi_pop_info_result_t;
+    #ifdef _WIN32
+    #pragma pack(pop)
+    #endif
+
     /**
      * @brief Result value for an asynchronous I/O operation.
      */
@@ -115,15 +135,16 @@ extern "C"
         enum demi_opcode qr_opcode; /**< Opcode of completed operation.                              */
         int32_t qr_qd;              /**< I/O queue descriptor associated to the completed operation. */
         demi_qtoken_t qr_qt;        /**< I/O queue token of the completed operation.                 */
-        int64_t qr_ret;             /**< Return code.                                                */
+        int64_t qr_ret;             /**< Return code. Number of bytes accepted, for pushes.          */
 
         /**
          * @brief Result value.
          */
         union
         {
-            demi_sgarray_t sga;        /**< Pushed/popped scatter-gather array. */
-            demi_accept_result_t ares; /**< Accept result.                      */
+            demi_sgarray_t sga;           /**< Pushed/popped scatter-gather array.       */
+            demi_accept_result_t ares;    /**< Accept result.                            */
+            demi_pop_info_result_t pinfo; /**< Pop result, along with IP_PKTINFO data. */
         } qr_value;
     } demi_qresult_t;
     #ifdef _WIN32
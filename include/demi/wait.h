@@ -17,9 +17,11 @@ extern "C"
      *
      * @param qr_out  Store location for the result of the completed I/O operation.
      * @param qt      I/O queue token of the target operation to wait for completion.
-     * @param timeout Timeout interval in seconds and nanoseconds.
+     * @param timeout Timeout interval in seconds and nanoseconds, or NULL to wait forever. A zero timeout polls once
+     *                and returns right away.
      *
-     * @return On successful completion, zero is returned. On failure, a positive error code is returned instead.
+     * @return On successful completion, zero is returned. On failure, a positive error code is returned instead. If
+     *         the timeout expires first, ETIMEDOUT is returned and the operation is left pending.
      */
     extern int demi_wait(demi_qresult_t *qr_out, demi_qtoken_t qt, const struct timespec *timeout);
 
@@ -41,9 +43,11 @@ extern "C"
      * @param ready_offset Store location for the offset in the list of I/O queue tokens of the completed I/O operation.
      * @param qts          List of I/O queue tokens to wait for completion.
      * @param num_qts      Length of the list of I/O queue tokens to wait for completion.
-     * @param timeout      Timeout interval in seconds and nanoseconds.
+     * @param timeout      Timeout interval in seconds and nanoseconds, or NULL to wait forever. A zero timeout polls
+     *                     once and returns right away.
      *
-     * @return On successful completion, zero is returned. On failure, a positive error code is returned instead.
+     * @return On successful completion, zero is returned. On failure, a positive error code is returned instead. If
+     *         the timeout expires first, ETIMEDOUT is returned and all operations are left pending.
      */
     extern int demi_wait_any(demi_qresult_t *qr_out, int *ready_offset, const demi_qtoken_t qts[], int num_qts, const struct timespec *timeout);
 
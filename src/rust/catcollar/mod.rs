@@ -131,7 +131,13 @@ impl CatcollarLibOS {
                 trace!("socket: {:?}, domain: {:?}, typ: {:?}", fd, domain, typ);
                 let mut queue: CatcollarQueue = CatcollarQueue::new(qtype);
                 queue.set_fd(fd);
-                Ok(self.runtime.alloc_queue::<CatcollarQueue>(queue))
+                match self.runtime.alloc_queue::<CatcollarQueue>(queue) {
+                    Ok(qd) => Ok(qd),
+                    Err(e) => {
+                        unsafe { libc::close(fd) };
+                        Err(e)
+                    },
+                }
             },
             _ => {
                 let errno: libc::c_int = unsafe { *libc::__errno_location() };
@@ -226,7 +232,13 @@ impl CatcollarLibOS {
                 let mut queue: CatcollarQueue = CatcollarQueue::new(QType::TcpSocket);
                 queue.set_addr(addr);
                 queue.set_fd(new_fd);
-                let new_qd: QDesc = runtime.alloc_queue::<CatcollarQueue>(queue);
+                let new_qd: QDesc = match runtime.alloc_queue::<CatcollarQueue>(queue) {
+                    Ok(new_qd) => new_qd,
+                    Err(e) => {
+                        unsafe { libc::close(new_fd) };
+                        return (qd, OperationResult::Failed(e));
+                    },
+                };
                 (qd, OperationResult::Accept((new_qd, addr)))
             },
             Err(e) => (qd, OperationResult::Failed(e)),
@@ -445,8 +457,9 @@ impl CatcollarLibOS {
         buf: DemiBuffer,
         yielder: Yielder,
     ) -> (QDesc, OperationResult) {
+        let len: usize = buf.len();
         match Self::do_push(rt, fd, buf, yielder).await {
-            Ok(()) => (qd, OperationResult::Push),
+            Ok(()) => (qd, OperationResult::Push(len)),
             Err(e) => (qd, OperationResult::Failed(e)),
         }
     }
@@ -532,8 +545,9 @@ impl CatcollarLibOS {
         buf: DemiBuffer,
         yielder: Yielder,
     ) -> (QDesc, OperationResult) {
+        let len: usize = buf.len();
         match Self::do_pushto(rt, fd, remote, buf, yielder).await {
-            Ok(()) => (qd, OperationResult::Push),
+            Ok(()) => (qd, OperationResult::Push(len)),
             Err(e) => (qd, OperationResult::Failed(e)),
         }
     }
@@ -126,7 +126,7 @@ impl SharedCatloopLibOS {
         let catmem: SharedCatmemLibOS = self.catmem.clone();
         let qd: QDesc = self
             .runtime
-            .alloc_queue::<SharedCatloopQueue>(SharedCatloopQueue::new(qtype, runtime, catmem)?);
+            .alloc_queue::<SharedCatloopQueue>(SharedCatloopQueue::new(qtype, runtime, catmem)?)?;
         Ok(qd)
     }
 
@@ -168,7 +168,7 @@ impl SharedCatloopLibOS {
             return Err(Fail::new(libc::EADDRINUSE, &cause));
         }
         // Check if this is an ephemeral port.
-        if SharedDemiRuntime::is_private_ephemeral_port(local.port()) {
+        if self.runtime.is_private_ephemeral_port(local.port()) {
             // Allocate ephemeral port from the pool, to leave ephemeral port allocator in a consistent state.
             self.runtime.reserve_ephemeral_port(local.port())?;
         }
@@ -233,10 +233,9 @@ impl SharedCatloopLibOS {
         };
         // Wait for the accept to complete.
         let result: Result<SharedCatloopQueue, Fail> = queue.do_accept(new_port, &yielder).await;
-        // Handle result: if successful, borrow the state to update state.
-        match result {
-            Ok(new_queue) => {
-                let new_qd: QDesc = self.runtime.alloc_queue::<SharedCatloopQueue>(new_queue);
+        // Handle result: if successful, allocate a queue descriptor for the new connection.
+        match result.and_then(|new_queue| self.runtime.alloc_queue::<SharedCatloopQueue>(new_queue)) {
+            Ok(new_qd) => {
                 // TODO: insert into socket id to queue descriptor table?
                 let new_addr: SocketAddrV4 = SocketAddrV4::new(
                     *queue
@@ -335,7 +334,7 @@ impl SharedCatloopLibOS {
         match queue.do_close(yielder).await {
             Ok((_, OperationResult::Close)) => {
                 if let Some(addr) = queue.local() {
-                    if SharedDemiRuntime::is_private_ephemeral_port(addr.port()) {
+                    if self.runtime.is_private_ephemeral_port(addr.port()) {
                         if self.runtime.free_ephemeral_port(addr.port()).is_err() {
                             // We fail if and only if we attempted to free a port that was not allocated.
                             // This is unexpected, but if it happens, issue a warning and keep going,
@@ -393,7 +392,7 @@ impl SharedCatloopLibOS {
         // Wait for push to complete.
         match queue.do_push(buf, yielder).await {
             // Reminder to translate the queue descriptor from Catmem to Catloop
-            Ok((_, OperationResult::Push)) => (qd, OperationResult::Push),
+            Ok((_, OperationResult::Push(nbytes))) => (qd, OperationResult::Push(nbytes)),
             Ok((_, OperationResult::Failed(e))) => (qd, OperationResult::Failed(e)),
             Err(e) => {
                 warn!("connect() failed (qd={:?}, error={:?})", qd, e.cause);
@@ -18,7 +18,6 @@ use crate::{
             DemiBuffer,
             MemoryRuntime,
         },
-        queue::downcast_queue,
         scheduler::{
             TaskHandle,
             Yielder,
@@ -78,7 +77,7 @@ impl SharedCatmemLibOS {
         trace!("create_pipe() name={:?}", name);
         let qd: QDesc = self
             .runtime
-            .alloc_queue::<SharedCatmemQueue>(SharedCatmemQueue::create(name)?);
+            .alloc_queue::<SharedCatmemQueue>(SharedCatmemQueue::create(name)?)?;
 
         Ok(qd)
     }
@@ -89,7 +88,7 @@ impl SharedCatmemLibOS {
 
         let qd: QDesc = self
             .runtime
-            .alloc_queue::<SharedCatmemQueue>(SharedCatmemQueue::open(name)?);
+            .alloc_queue::<SharedCatmemQueue>(SharedCatmemQueue::open(name)?)?;
 
         Ok(qd)
     }
@@ -186,8 +185,9 @@ impl SharedCatmemLibOS {
             Err(e) => return (qd, OperationResult::Failed(e)),
         };
         // Handle result.
+        let len: usize = buf.len();
         match queue.do_push(buf, yielder).await {
-            Ok(()) => (qd, OperationResult::Push),
+            Ok(()) => (qd, OperationResult::Push(len)),
             Err(e) => (qd, OperationResult::Failed(e)),
         }
     }
@@ -212,15 +212,16 @@ impl SharedCatmemLibOS {
         queue.pop(coroutine_constructor)
     }
 
-    pub async fn pop_coroutine(self, qd: QDesc, size: Option<usize>, yielder: Yielder) -> (QDesc, OperationResult) {
+    pub async fn pop_coroutine(mut self, qd: QDesc, size: Option<usize>, yielder: Yielder) -> (QDesc, OperationResult) {
         // Make sure the queue still exists.
         let mut queue: SharedCatmemQueue = match self.get_queue(&qd) {
             Ok(queue) => queue,
             Err(e) => return (qd, OperationResult::Failed(e)),
         };
 
-        // Wait for pop to complete.
-        let (buf, _) = match queue.do_pop(size, yielder).await {
+        // Wait for pop to complete, reading into a buffer of the pool.
+        let buf: DemiBuffer = self.runtime.alloc_pop_buffer(size.unwrap_or(limits::RECVBUF_SIZE_MAX));
+        let (buf, _) = match queue.do_pop(buf, yielder).await {
             Ok(result) => result,
             Err(e) => return (qd, OperationResult::Failed(e)),
         };
@@ -253,12 +254,10 @@ impl DerefMut for SharedCatmemLibOS {
 impl Drop for CatmemLibOS {
     // Releases all sockets allocated by Catnap.
     fn drop(&mut self) {
-        for boxed_queue in self.runtime.get_mut_qtable().drain() {
-            if let Ok(mut catmem_queue) = downcast_queue::<SharedCatmemQueue>(boxed_queue) {
-                if let Err(e) = catmem_queue.close() {
-                    error!("push_eof() failed: {:?}", e);
-                    warn!("leaking shared memory region");
-                }
+        for mut catmem_queue in self.runtime.get_mut_qtable().drain_type::<SharedCatmemQueue>() {
+            if let Err(e) = catmem_queue.close() {
+                error!("push_eof() failed: {:?}", e);
+                warn!("leaking shared memory region");
             }
         }
     }
@@ -12,7 +12,6 @@ use crate::{
     },
     runtime::{
         fail::Fail,
-        limits,
         memory::DemiBuffer,
         queue::IoQueue,
         scheduler::{
@@ -153,11 +152,10 @@ impl SharedCatmemQueue {
         self.do_generic_sync_data_path_call(coroutine_constructor)
     }
 
-    /// This function pops a buffer of optional [size] from the queue. If the queue is connected to the push end of a
-    /// shared memory ring, this function returns an error.
-    pub async fn do_pop(&mut self, size: Option<usize>, yielder: Yielder) -> Result<(DemiBuffer, bool), Fail> {
-        let size: usize = size.unwrap_or(limits::RECVBUF_SIZE_MAX);
-        let mut buf: DemiBuffer = DemiBuffer::new(size as u16);
+    /// This function pops data from the queue into [buf], which is trimmed down to the data that was read. If the queue
+    /// is connected to the push end of a shared memory ring, this function returns an error.
+    pub async fn do_pop(&mut self, mut buf: DemiBuffer, yielder: Yielder) -> Result<(DemiBuffer, bool), Fail> {
+        let size: usize = buf.len();
         let eof: bool = loop {
             match self.ring.try_pop(&mut buf) {
                 Ok((len, eof)) => {
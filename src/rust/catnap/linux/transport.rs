@@ -11,8 +11,18 @@ use crate::{
     runtime::{
         fail::Fail,
         limits,
-        memory::DemiBuffer,
-        network::transport::NetworkTransport,
+        memory::{
+            DemiBuffer,
+            SharedBufferPool,
+        },
+        network::{
+            transport::NetworkTransport,
+            types::{
+                PacketInfo,
+                SocketOption,
+                SocketOptionName,
+            },
+        },
         scheduler::{
             Yielder,
             YielderHandle,
@@ -26,6 +36,7 @@ use ::slab::Slab;
 use ::socket2::{
     Domain,
     Protocol,
+    SockAddr,
     Socket,
     Type,
 };
@@ -36,8 +47,9 @@ use ::std::{
         AsRef,
     },
     io,
-    mem::MaybeUninit,
+    mem,
     net::{
+        Ipv4Addr,
         Shutdown,
         SocketAddr,
     },
@@ -49,6 +61,8 @@ use ::std::{
         AsRawFd,
         RawFd,
     },
+    ptr,
+    time::Duration,
 };
 
 //======================================================================================================================
@@ -58,6 +72,17 @@ use ::std::{
 // Set to the max number of file descriptors that can be open without increasing the number on Linux.
 const EPOLL_BATCH_SIZE: usize = 1024;
 
+/// Maximum number of datagrams that can be read from a socket with a single call to recvmmsg(). This matches the
+/// maximum number of messages that Linux accepts in a single call.
+const RECV_BATCH_SIZE_MAX: usize = 1024;
+
+/// Maximum number of datagrams that can be written to a socket with a single call to sendmmsg().
+const SEND_BATCH_SIZE_MAX: usize = 1024;
+
+/// Size, in 64-bit words, of the buffer that holds the ancillary data of a datagram. This fits the control message of
+/// IP_PKTINFO, which is the only ancillary data that datagrams are sent or received with.
+const PKTINFO_CONTROL_LEN: usize = 4;
+
 //======================================================================================================================
 // Structures
 //======================================================================================================================
@@ -72,8 +97,18 @@ pub struct PassiveSocketData {
 /// outgoing messages and incoming ones.
 pub struct ActiveSocketData {
     socket: Socket,
-    send_queue: AsyncQueue<(Option<SocketAddr>, DemiBuffer, YielderHandle)>,
-    recv_queue: AsyncQueue<Result<(Option<SocketAddr>, DemiBuffer), Fail>>,
+    send_queue: AsyncQueue<(Option<SocketAddr>, Option<Ipv4Addr>, DemiBuffer, YielderHandle)>,
+    recv_queue: AsyncQueue<Result<(Option<SocketAddr>, Option<PacketInfo>, DemiBuffer), Fail>>,
+    /// Whether the socket is a datagram one. Zero-length buffers queued for sending are then empty datagrams, whereas
+    /// they are requests to be woken once the socket is writable on stream sockets.
+    is_dgram: bool,
+    /// Whether incoming data may be read in batches, that is, the socket is a datagram one and recvmmsg() is available.
+    batch_recv: bool,
+    /// Pool of buffers that incoming data is received into, and that pops hand over as is.
+    buffer_pool: SharedBufferPool,
+    /// Whether outgoing data may be written in batches, that is, the socket is a datagram one and sendmmsg() is
+    /// available.
+    batch_send: bool,
 }
 
 /// This structure represents the metadata for a socket.
@@ -92,6 +127,12 @@ pub struct CatnapTransport {
     epoll_fd: RawFd,
     socket_table: Slab<SharedSocketData>,
     background_task: YielderHandle,
+    /// Pool of buffers that sockets receive data into.
+    buffer_pool: SharedBufferPool,
+    /// Maximum number of datagrams to read from a socket on each incoming event.
+    recv_batch_size: usize,
+    /// Maximum number of queued datagrams to write to a socket on each outgoing event.
+    send_batch_size: usize,
 }
 
 /// Shared network transport across coroutines.
@@ -135,20 +176,129 @@ impl PassiveSocketData {
 }
 
 impl ActiveSocketData {
+    /// Creates the metadata for an active socket, which receives data into buffers of `buffer_pool`.
+    fn new(socket: Socket, buffer_pool: SharedBufferPool) -> Self {
+        // Stream sockets are always read and written one buffer at a time, as their data has no message boundaries.
+        let is_dgram: bool = matches!(socket.r#type(), Ok(Type::DGRAM));
+        Self {
+            socket,
+            send_queue: AsyncQueue::default(),
+            recv_queue: AsyncQueue::default(),
+            is_dgram,
+            batch_recv: is_dgram,
+            buffer_pool,
+            batch_send: is_dgram,
+        }
+    }
+
     /// Polls the send queue on an outgoing epoll event and send out data if there is any pending. We use an empty
     /// buffer for write to indicate that we want to know when the socket is ready for writing but do not have data to
-    /// write (i.e., to detect when connect finishes).
-    pub fn poll_send(&mut self) {
-        if let Some((addr, mut buf, mut handle)) = self.send_queue.try_pop() {
+    /// write (i.e., to detect when connect finishes). Up to `batch_size` queued datagrams are written at once to
+    /// datagram sockets, so that all datagrams pushed since the last event go out with a single system call.
+    pub fn poll_send(&mut self, batch_size: usize) {
+        if self.batch_send && batch_size > 1 {
+            // Only datagram sockets are written in batches, and they have no dummy requests.
+            let num_pending: usize = min(self.send_queue.len(), batch_size);
+            if num_pending > 1 {
+                match self.poll_send_batch(num_pending) {
+                    Ok(()) => return,
+                    Err(errno) => {
+                        // Fall back to writing datagrams one at a time from now on.
+                        warn!("poll_send(): sendmmsg() is not available (errno={:?})", errno);
+                        self.batch_send = false;
+                    },
+                }
+            }
+        }
+        self.poll_send_one()
+    }
+
+    /// Writes the first `batch_size` datagrams of the outgoing queue with a single call to sendmmsg(). Datagrams that
+    /// were not written stay at the head of the queue, in their original order. Fails only if sendmmsg() is not
+    /// available, in which case nothing was written.
+    fn poll_send_batch(&mut self, batch_size: usize) -> Result<(), i32> {
+        let mut batch: Vec<(Option<SocketAddr>, Option<Ipv4Addr>, DemiBuffer, YielderHandle)> =
+            Vec::with_capacity(batch_size);
+        while batch.len() < batch_size {
+            match self.send_queue.try_pop() {
+                Some(entry) => batch.push(entry),
+                None => break,
+            }
+        }
+        let addrs: Vec<Option<SockAddr>> = batch.iter().map(|(addr, _, _, _)| addr.map(SockAddr::from)).collect();
+        let mut iovecs: Vec<libc::iovec> = batch
+            .iter()
+            .map(|(_, _, buf, _)| libc::iovec {
+                iov_base: buf.as_ptr() as *mut libc::c_void,
+                iov_len: buf.len(),
+            })
+            .collect();
+        let mut controls: Vec<[u64; PKTINFO_CONTROL_LEN]> = vec![[0; PKTINFO_CONTROL_LEN]; batch.len()];
+        let mut msgs: Vec<libc::mmsghdr> = iovecs
+            .iter_mut()
+            .zip(addrs.iter())
+            .zip(controls.iter_mut())
+            .zip(batch.iter())
+            .map(|(((iovec, addr), control), (_, src, _, _))| libc::mmsghdr {
+                msg_hdr: new_send_msghdr(iovec, addr.as_ref(), *src, control),
+                msg_len: 0,
+            })
+            .collect();
+
+        let result: libc::c_int = unsafe {
+            libc::sendmmsg(
+                self.socket.as_raw_fd(),
+                msgs.as_mut_ptr(),
+                batch.len() as libc::c_uint,
+                libc::MSG_DONTWAIT,
+            )
+        };
+        // sendmmsg() only fails if it could not write the first datagram.
+        let num_sent: usize = if result < 0 {
+            let errno: libc::c_int = unsafe { *libc::__errno_location() };
+            if errno == libc::ENOSYS || errno == libc::EOPNOTSUPP || DemiRuntime::should_retry(errno) {
+                // Put the datagrams back, and either try again later or write them one at a time.
+                for entry in batch.into_iter().rev() {
+                    self.send_queue.push_front(entry);
+                }
+                return match errno {
+                    libc::ENOSYS | libc::EOPNOTSUPP => Err(errno),
+                    _ => Ok(()),
+                };
+            }
+            let cause: String = format!("failed to send on socket: {:?}", errno);
+            error!("poll_send_batch(): {}", cause);
+            let (_, _, _, mut handle) = batch.remove(0);
+            handle.wake_with(Err(Fail::new(errno, &cause)));
+            0
+        } else {
+            result as usize
+        };
+
+        trace!("datagrams pushed ({:?}/{:?})", num_sent, batch.len());
+        // Datagrams are written as a whole, so every datagram that was not written goes back to the queue.
+        for entry in batch.drain(num_sent..).rev() {
+            self.send_queue.push_front(entry);
+        }
+        for (_, _, _, mut handle) in batch {
+            handle.wake_with(Ok(()));
+        }
+        Ok(())
+    }
+
+    /// Writes the buffer at the head of the outgoing queue to the socket.
+    fn poll_send_one(&mut self) {
+        if let Some((addr, src, mut buf, mut handle)) = self.send_queue.try_pop() {
             // A dummy request to detect when the socket has connected.
-            if buf.is_empty() {
+            if buf.is_empty() && !self.is_dgram {
                 handle.wake_with(Ok(()));
                 return;
             }
             // Try to send the buffer.
-            let result: Result<usize, io::Error> = match addr {
-                Some(addr) => self.socket.send_to(&buf, &addr.clone().into()),
-                None => self.socket.send(&buf),
+            let result: Result<usize, io::Error> = match (addr, src) {
+                (addr, Some(src)) => self.send_from(&buf, addr, src),
+                (Some(addr), None) => self.socket.send_to(&buf, &addr.clone().into()),
+                (None, None) => self.socket.send(&buf),
             };
             match result {
                 // Operation completed.
@@ -161,14 +311,14 @@ impl ActiveSocketData {
                         handle.wake_with(Ok(()))
                     } else {
                         // Only sent part of the buffer so try again later.
-                        self.send_queue.push_front((addr, buf, handle));
+                        self.send_queue.push_front((addr, src, buf, handle));
                     }
                 },
                 Err(e) => {
                     let errno: i32 = get_libc_err(e);
                     if DemiRuntime::should_retry(errno) {
                         // Put the buffer back and try again later.
-                        self.send_queue.push_front((addr, buf, handle));
+                        self.send_queue.push_front((addr, src, buf, handle));
                     } else {
                         let cause: String = format!("failed to send on socket: {:?}", errno);
                         error!("poll_send(): {}", cause);
@@ -179,26 +329,134 @@ impl ActiveSocketData {
         }
     }
 
+    /// Writes a single datagram to `addr`, or to the remote address of the socket if not given, from the local address
+    /// `src` with sendmsg().
+    fn send_from(&self, buf: &DemiBuffer, addr: Option<SocketAddr>, src: Ipv4Addr) -> Result<usize, io::Error> {
+        let addr: Option<SockAddr> = addr.map(SockAddr::from);
+        let mut iovec: libc::iovec = libc::iovec {
+            iov_base: buf.as_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        };
+        let mut control: [u64; PKTINFO_CONTROL_LEN] = [0; PKTINFO_CONTROL_LEN];
+        let msg_hdr: libc::msghdr = new_send_msghdr(&mut iovec, addr.as_ref(), Some(src), &mut control);
+        match unsafe { libc::sendmsg(self.socket.as_raw_fd(), &msg_hdr, 0) } {
+            nbytes if nbytes >= 0 => Ok(nbytes as usize),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
     /// Polls the socket for incoming data on an incoming epoll event. Inserts any received data into the incoming
-    /// queue.
+    /// queue. Up to `batch_size` datagrams are read at once from datagram sockets, but each of them is still queued
+    /// on its own.
     /// TODO: Incoming queue should possibly be byte oriented.
-    pub fn poll_recv(&mut self) {
-        let mut buf: DemiBuffer = DemiBuffer::new(limits::POP_SIZE_MAX as u16);
-        match self
-            .socket
-            .recv_from(unsafe { std::slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut MaybeUninit<u8>, buf.len()) })
-        {
+    pub fn poll_recv(&mut self, batch_size: usize) {
+        if self.batch_recv && batch_size > 1 {
+            match self.poll_recv_batch(batch_size) {
+                Ok(()) => return,
+                Err(errno) => {
+                    // Fall back to reading datagrams one at a time from now on.
+                    warn!("poll_recv(): recvmmsg() is not available (errno={:?})", errno);
+                    self.batch_recv = false;
+                },
+            }
+        }
+        self.poll_recv_one()
+    }
+
+    /// Reads up to `batch_size` datagrams from the socket with a single call to recvmmsg(), and inserts them into the
+    /// incoming queue. Fails only if recvmmsg() is not available, in which case nothing was read. Buffers that receive
+    /// no datagram go back to the pool.
+    fn poll_recv_batch(&mut self, batch_size: usize) -> Result<(), i32> {
+        let mut bufs: Vec<DemiBuffer> = (0..batch_size)
+            .map(|_| self.buffer_pool.alloc(limits::POP_SIZE_MAX as u16))
+            .collect();
+        let mut iovecs: Vec<libc::iovec> = bufs
+            .iter_mut()
+            .map(|buf| libc::iovec {
+                iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+                iov_len: buf.len(),
+            })
+            .collect();
+        let mut addrs: Vec<libc::sockaddr_storage> = vec![unsafe { mem::zeroed() }; batch_size];
+        let mut controls: Vec<[u64; PKTINFO_CONTROL_LEN]> = vec![[0; PKTINFO_CONTROL_LEN]; batch_size];
+        let mut msgs: Vec<libc::mmsghdr> = iovecs
+            .iter_mut()
+            .zip(addrs.iter_mut())
+            .zip(controls.iter_mut())
+            .map(|((iovec, addr), control)| libc::mmsghdr {
+                msg_hdr: new_recv_msghdr(iovec, addr, control),
+                msg_len: 0,
+            })
+            .collect();
+
+        let result: libc::c_int = unsafe {
+            libc::recvmmsg(
+                self.socket.as_raw_fd(),
+                msgs.as_mut_ptr(),
+                batch_size as libc::c_uint,
+                libc::MSG_DONTWAIT,
+                ptr::null_mut(),
+            )
+        };
+        if result < 0 {
+            let errno: libc::c_int = unsafe { *libc::__errno_location() };
+            for buf in bufs {
+                self.buffer_pool.recycle(buf);
+            }
+            if errno == libc::ENOSYS || errno == libc::EOPNOTSUPP {
+                return Err(errno);
+            }
+            if !DemiRuntime::should_retry(errno) {
+                let cause: String = format!("failed to receive on socket: {:?}", errno);
+                error!("poll_recv_batch(): {}", cause);
+                self.recv_queue.push(Err(Fail::new(errno, &cause)));
+            }
+            return Ok(());
+        }
+
+        let num_msgs: usize = result as usize;
+        trace!("datagrams popped ({:?}/{:?})", num_msgs, batch_size);
+        for buf in bufs.drain(num_msgs..) {
+            self.buffer_pool.recycle(buf);
+        }
+        for ((mut buf, msg), addr) in bufs.into_iter().zip(msgs.iter()).zip(addrs.into_iter()) {
+            let nbytes: usize = msg.msg_len as usize;
+            if let Err(e) = buf.trim(buf.len() - nbytes) {
+                self.recv_queue.push(Err(e));
+                continue;
+            }
+            let info: Option<PacketInfo> = get_pktinfo(&msg.msg_hdr);
+            let addr: SockAddr = unsafe { SockAddr::new(addr, msg.msg_hdr.msg_namelen) };
+            self.recv_queue.push(Ok((addr.as_socket(), info, buf)));
+        }
+        Ok(())
+    }
+
+    /// Reads a single buffer from the socket, and inserts it into the incoming queue.
+    fn poll_recv_one(&mut self) {
+        let mut buf: DemiBuffer = self.buffer_pool.alloc(limits::POP_SIZE_MAX as u16);
+        let mut iovec: libc::iovec = libc::iovec {
+            iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        };
+        let mut addr: libc::sockaddr_storage = unsafe { mem::zeroed() };
+        let mut control: [u64; PKTINFO_CONTROL_LEN] = [0; PKTINFO_CONTROL_LEN];
+        let mut msg_hdr: libc::msghdr = new_recv_msghdr(&mut iovec, &mut addr, &mut control);
+        match unsafe { libc::recvmsg(self.socket.as_raw_fd(), &mut msg_hdr, 0) } {
             // Operation completed.
-            Ok((nbytes, socketaddr)) => {
+            nbytes if nbytes >= 0 => {
                 if let Err(e) = buf.trim(buf.len() - nbytes as usize) {
                     self.recv_queue.push(Err(e));
                 } else {
                     trace!("data popped ({:?} bytes)", nbytes);
-                    self.recv_queue.push(Ok((socketaddr.as_socket(), buf)));
+                    let info: Option<PacketInfo> = get_pktinfo(&msg_hdr);
+                    let addr: SockAddr = unsafe { SockAddr::new(addr, msg_hdr.msg_namelen) };
+                    self.recv_queue.push(Ok((addr.as_socket(), info, buf)));
                 }
             },
-            Err(e) => {
-                let errno: i32 = get_libc_err(e);
+            _ => {
+                self.buffer_pool.recycle(buf);
+                let errno: libc::c_int = unsafe { *libc::__errno_location() };
                 if !DemiRuntime::should_retry(errno) {
                     let cause: String = format!("failed to receive on socket: {:?}", errno);
                     error!("poll_recv(): {}", cause);
@@ -209,37 +467,42 @@ impl ActiveSocketData {
     }
 
     /// Pushes data to the socket. Blocks until completion.
-    pub async fn push(&mut self, addr: Option<SocketAddr>, buf: DemiBuffer, yielder: &Yielder) -> Result<(), Fail> {
-        self.send_queue.push((addr, buf, yielder.get_handle()));
+    pub async fn push(
+        &mut self,
+        addr: Option<SocketAddr>,
+        src: Option<Ipv4Addr>,
+        buf: DemiBuffer,
+        yielder: &Yielder,
+    ) -> Result<(), Fail> {
+        if src.is_some() && !self.is_dgram {
+            let cause: String = format!("cannot pick the source address of a stream (src={:?})", src);
+            error!("push(): {}", cause);
+            return Err(Fail::new(libc::EOPNOTSUPP, &cause));
+        }
+        self.send_queue.push((addr, src, buf, yielder.get_handle()));
         yielder.yield_until_wake().await
     }
 
-    /// Pops data from the socket. Blocks until some data is found but does not wait until the buf has reached [size].
+    /// Pops data from the socket. Blocks until some data is found but does not wait until it has reached [size]. The
+    /// buffer that the data was received into is handed over as is, unless it holds more than [size] bytes.
     pub async fn pop(
         &mut self,
-        buf: &mut DemiBuffer,
         size: usize,
         yielder: &Yielder,
-    ) -> Result<Option<SocketAddr>, Fail> {
-        let (addr, mut incoming_buf): (Option<SocketAddr>, DemiBuffer) = self.recv_queue.pop(&yielder).await??;
-        // Figure out how much data we got.
-        let bytes_read: usize = min(incoming_buf.len(), size);
-        // Trim the buffer down to the amount that we received.
-        buf.trim(buf.len() - bytes_read)
-            .expect("DemiBuffer must be bigger than size");
-        // Move it if the buffer isn't empty.
-        if !incoming_buf.is_empty() {
-            buf.copy_from_slice(&incoming_buf[0..bytes_read]);
-        }
-        // Trim off everything that we moved.
-        incoming_buf
-            .adjust(bytes_read)
-            .expect("bytes_read will be less than incoming buf len because it is a min of incoming buf len and size ");
-        // We didn't consume all of the incoming data.
-        if !incoming_buf.is_empty() {
-            self.recv_queue.push_front(Ok((addr, incoming_buf)));
+    ) -> Result<(Option<SocketAddr>, Option<PacketInfo>, DemiBuffer), Fail> {
+        let (addr, info, mut incoming_buf): (Option<SocketAddr>, Option<PacketInfo>, DemiBuffer) =
+            self.recv_queue.pop(&yielder).await??;
+        if incoming_buf.len() <= size {
+            return Ok((addr, info, incoming_buf));
         }
-        Ok(addr)
+        // Copy out as much data as requested, and keep the rest for the next pop.
+        let mut buf: DemiBuffer = self.buffer_pool.alloc(size as u16);
+        buf.copy_from_slice(&incoming_buf[0..size]);
+        incoming_buf
+            .adjust(size)
+            .expect("size should be less than incoming buf len");
+        self.recv_queue.push_front(Ok((addr, info, incoming_buf)));
+        Ok((addr, info, buf))
     }
 }
 
@@ -250,12 +513,10 @@ impl SharedSocketData {
     }
 
     /// Creates new metadata representing a socket.
-    pub fn new_active(socket: Socket) -> Self {
-        Self(SharedObject::<SocketData>::new(SocketData::Active(ActiveSocketData {
-            socket,
-            send_queue: AsyncQueue::default(),
-            recv_queue: AsyncQueue::default(),
-        })))
+    pub fn new_active(socket: Socket, buffer_pool: SharedBufferPool) -> Self {
+        Self(SharedObject::<SocketData>::new(SocketData::Active(
+            ActiveSocketData::new(socket, buffer_pool),
+        )))
     }
 
     /// Moves an inactive socket to a passive listening socket.
@@ -272,17 +533,13 @@ impl SharedSocketData {
     }
 
     /// Moves an inactive socket to an active established socket.
-    pub fn move_socket_to_active(&mut self) {
+    pub fn move_socket_to_active(&mut self, buffer_pool: SharedBufferPool) {
         let socket: Socket = match self.deref_mut() {
             SocketData::Inactive(socket) => socket.take().expect("should have data"),
             SocketData::Active(_) => return,
             SocketData::Passive(_) => unreachable!("should not be able to move a passive socket to an active one"),
         };
-        self.set_socket_data(SocketData::Active(ActiveSocketData {
-            socket,
-            send_queue: AsyncQueue::default(),
-            recv_queue: AsyncQueue::default(),
-        }));
+        self.set_socket_data(SocketData::Active(ActiveSocketData::new(socket, buffer_pool)));
     }
 
     /// Gets a reference to the actual Socket for reading the socket's metadata (mostly the raw file descriptor).
@@ -313,10 +570,16 @@ impl SharedSocketData {
     }
 
     /// Push some data to an active established connection.
-    pub async fn push(&mut self, addr: Option<SocketAddr>, buf: DemiBuffer, yielder: &Yielder) -> Result<(), Fail> {
+    pub async fn push(
+        &mut self,
+        addr: Option<SocketAddr>,
+        src: Option<Ipv4Addr>,
+        buf: DemiBuffer,
+        yielder: &Yielder,
+    ) -> Result<(), Fail> {
         match self.deref_mut() {
             SocketData::Inactive(_) => unreachable!("Cannot write to an inactive socket"),
-            SocketData::Active(data) => data.push(addr, buf, yielder).await,
+            SocketData::Active(data) => data.push(addr, src, buf, yielder).await,
             SocketData::Passive(_) => unreachable!("Cannot write to a passive socket"),
         }
     }
@@ -333,31 +596,30 @@ impl SharedSocketData {
     /// Pop some data on an active established connection.
     pub async fn pop(
         &mut self,
-        buf: &mut DemiBuffer,
         size: usize,
         yielder: &Yielder,
-    ) -> Result<Option<SocketAddr>, Fail> {
+    ) -> Result<(Option<SocketAddr>, Option<PacketInfo>, DemiBuffer), Fail> {
         match self.deref_mut() {
             SocketData::Inactive(_) => unreachable!("Cannot read on an inactive socket"),
-            SocketData::Active(data) => data.pop(buf, size, yielder).await,
+            SocketData::Active(data) => data.pop(size, yielder).await,
             SocketData::Passive(_) => unreachable!("Cannot read on a passive socket"),
         }
     }
 
-    /// Handle incoming data event.
-    pub fn poll_in(&mut self) {
+    /// Handle incoming data event, reading up to `recv_batch_size` datagrams at once from datagram sockets.
+    pub fn poll_in(&mut self, recv_batch_size: usize) {
         match self.deref_mut() {
             SocketData::Inactive(_) => unreachable!("should only receive incoming events on active or passive sockets"),
-            SocketData::Active(data) => data.poll_recv(),
+            SocketData::Active(data) => data.poll_recv(recv_batch_size),
             SocketData::Passive(data) => data.poll_accept(),
         }
     }
 
-    /// Handle an outgoing data event.
-    pub fn poll_out(&mut self) {
+    /// Handle an outgoing data event, writing up to `send_batch_size` datagrams at once to datagram sockets.
+    pub fn poll_out(&mut self, send_batch_size: usize) {
         match self.deref_mut() {
             SocketData::Inactive(_) => unreachable!("should only receive outgoing events on active or passive sockets"),
-            SocketData::Active(data) => data.poll_send(),
+            SocketData::Active(data) => data.poll_send(send_batch_size),
             // Nothing to do for passive sockets.
             SocketData::Passive(_) => (),
         }
@@ -409,6 +671,8 @@ impl SharedCatnapTransport {
     /// Background function for checking for epoll events.
     async fn poll(&mut self, yielder: Yielder) {
         let mut events: Vec<libc::epoll_event> = Vec::with_capacity(EPOLL_BATCH_SIZE);
+        let recv_batch_size: usize = self.recv_batch_size;
+        let send_batch_size: usize = self.send_batch_size;
         loop {
             match unsafe {
                 libc::epoll_wait(
@@ -443,25 +707,25 @@ impl SharedCatnapTransport {
                     self.socket_table
                         .get_mut(offset)
                         .expect("should have allocated this when epoll was registered")
-                        .poll_in();
+                        .poll_in(recv_batch_size);
                 }
                 if event.events | (libc::EPOLLOUT as u32) != 0 {
                     // Wake push.
                     self.socket_table
                         .get_mut(offset)
                         .expect("should have allocated this when epoll was registered")
-                        .poll_out();
+                        .poll_out(send_batch_size);
                 }
                 if event.events | (libc::EPOLLERR as u32 | libc::EPOLLHUP as u32) != 0 {
                     // Wake both push and pop.
                     self.socket_table
                         .get_mut(offset)
                         .expect("should have allocated this when epoll was registered")
-                        .poll_in();
+                        .poll_in(recv_batch_size);
                     self.socket_table
                         .get_mut(offset)
                         .expect("should have allocated this when epoll was registered")
-                        .poll_out();
+                        .poll_out(send_batch_size);
                 }
             }
             match yielder.yield_once().await {
@@ -471,8 +735,40 @@ impl SharedCatnapTransport {
         }
     }
 
-    /// Internal function to get the raw file descriptor from a socket, given the socket descriptor.
-    fn raw_fd_from_sd(&self, sd: &SockDesc) -> RawFd {
+    /// Blocks until a socket has incoming data or connections, or `timeout` expires, if any. This returns right away if
+    /// any socket has outgoing data pending, since the poller sends it as soon as the socket is writable. Sockets are
+    /// polled for readability only because they are writable most of the time, which would wake us up right away.
+    fn wait_for_input(&self, timeout: Option<Duration>) {
+        let mut fds: Vec<libc::pollfd> = Vec::with_capacity(self.socket_table.len());
+        for (_, data) in self.socket_table.iter() {
+            match data.deref() {
+                SocketData::Inactive(_) => continue,
+                SocketData::Active(active) if active.send_queue.len() > 0 => return,
+                SocketData::Active(_) | SocketData::Passive(_) => fds.push(libc::pollfd {
+                    fd: data.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                }),
+            }
+        }
+        // Do not block forever if there is nothing to wait for.
+        if fds.is_empty() && timeout.is_none() {
+            return;
+        }
+        let timeout_ms: libc::c_int = match timeout {
+            Some(timeout) => timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int,
+            None => -1,
+        };
+        if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout_ms) } < 0 {
+            let errno: libc::c_int = unsafe { *libc::__errno_location() };
+            if errno != libc::EINTR {
+                warn!("wait_for_input(): poll failed (errno={:?})", errno);
+            }
+        }
+    }
+
+    /// Gets the raw file descriptor of a socket, given the socket descriptor.
+    pub fn raw_fd_from_sd(&self, sd: &SockDesc) -> RawFd {
         self.socket_table
             .get(*sd)
             .expect("shoudld have been allocated")
@@ -499,6 +795,79 @@ fn get_libc_err(e: io::Error) -> i32 {
     e.raw_os_error().expect("should have an os error code")
 }
 
+/// Builds the header of a message that is received into `iovec`, with the address of its sender stored in `addr` and
+/// its ancillary data in `control`.
+fn new_recv_msghdr(
+    iovec: &mut libc::iovec,
+    addr: &mut libc::sockaddr_storage,
+    control: &mut [u64; PKTINFO_CONTROL_LEN],
+) -> libc::msghdr {
+    let mut msg_hdr: libc::msghdr = unsafe { mem::zeroed() };
+    msg_hdr.msg_name = addr as *mut libc::sockaddr_storage as *mut libc::c_void;
+    msg_hdr.msg_namelen = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    msg_hdr.msg_iov = iovec as *mut libc::iovec;
+    msg_hdr.msg_iovlen = 1;
+    msg_hdr.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    msg_hdr.msg_controllen = mem::size_of::<[u64; PKTINFO_CONTROL_LEN]>();
+    msg_hdr
+}
+
+/// Builds the header of a message that sends `iovec` to `addr`, or to the remote address of the socket if not given.
+/// The message goes out from the local address `src`, if given, by means of an IP_PKTINFO control message that is
+/// written to `control`.
+fn new_send_msghdr(
+    iovec: &mut libc::iovec,
+    addr: Option<&SockAddr>,
+    src: Option<Ipv4Addr>,
+    control: &mut [u64; PKTINFO_CONTROL_LEN],
+) -> libc::msghdr {
+    let mut msg_hdr: libc::msghdr = unsafe { mem::zeroed() };
+    if let Some(addr) = addr {
+        msg_hdr.msg_name = addr.as_ptr() as *mut libc::c_void;
+        msg_hdr.msg_namelen = addr.len();
+    }
+    msg_hdr.msg_iov = iovec as *mut libc::iovec;
+    msg_hdr.msg_iovlen = 1;
+    if let Some(src) = src {
+        let pktinfo: libc::in_pktinfo = libc::in_pktinfo {
+            ipi_ifindex: 0,
+            ipi_spec_dst: libc::in_addr {
+                s_addr: u32::from(src).to_be(),
+            },
+            ipi_addr: libc::in_addr { s_addr: 0 },
+        };
+        let len: libc::c_uint = mem::size_of::<libc::in_pktinfo>() as libc::c_uint;
+        msg_hdr.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        msg_hdr.msg_controllen = unsafe { libc::CMSG_SPACE(len) } as _;
+        unsafe {
+            let cmsg: *mut libc::cmsghdr = libc::CMSG_FIRSTHDR(&msg_hdr);
+            (*cmsg).cmsg_level = libc::IPPROTO_IP;
+            (*cmsg).cmsg_type = libc::IP_PKTINFO;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(len) as _;
+            ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut libc::in_pktinfo, pktinfo);
+        }
+    }
+    msg_hdr
+}
+
+/// Gets the ancillary data that IP_PKTINFO attached to a received message, if any.
+fn get_pktinfo(msg_hdr: &libc::msghdr) -> Option<PacketInfo> {
+    let mut cmsg: *mut libc::cmsghdr = unsafe { libc::CMSG_FIRSTHDR(msg_hdr) };
+    while !cmsg.is_null() {
+        let (level, typ): (libc::c_int, libc::c_int) = unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type) };
+        if level == libc::IPPROTO_IP && typ == libc::IP_PKTINFO {
+            let pktinfo: libc::in_pktinfo =
+                unsafe { ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::in_pktinfo) };
+            return Some(PacketInfo {
+                dst_addr: Ipv4Addr::from(u32::from_be(pktinfo.ipi_addr.s_addr)),
+                ifindex: pktinfo.ipi_ifindex as u32,
+            });
+        }
+        cmsg = unsafe { libc::CMSG_NXTHDR(msg_hdr, cmsg) };
+    }
+    None
+}
+
 //======================================================================================================================
 // Trait implementation
 //======================================================================================================================
@@ -582,14 +951,31 @@ impl NetworkTransport for SharedCatnapTransport {
     type SocketDescriptor = usize;
 
     /// Create a new Linux-based network transport.
-    fn new(_config: &Config, runtime: &mut SharedDemiRuntime) -> Self {
+    fn new(config: &Config, runtime: &mut SharedDemiRuntime) -> Result<Self, Fail> {
+        let recv_batch_size: usize = config.recv_batch_size()?;
+        if recv_batch_size > RECV_BATCH_SIZE_MAX {
+            warn!(
+                "new(): receive batch size is too large, truncating (recv_batch_size={:?})",
+                recv_batch_size
+            );
+        }
+        let send_batch_size: usize = config.send_batch_size()?;
+        if send_batch_size > SEND_BATCH_SIZE_MAX {
+            warn!(
+                "new(): send batch size is too large, truncating (send_batch_size={:?})",
+                send_batch_size
+            );
+        }
+
         // Create epoll socket.
         // Linux ignores the size argument to epoll, it just has to be more than 0.
         let epoll_fd: RawFd = match unsafe { libc::epoll_create(10) } {
             fd if fd >= 0 => fd.into(),
             _ => {
                 let errno: libc::c_int = unsafe { *libc::__errno_location() };
-                panic!("could not create epoll socket: {:?}", errno);
+                let cause: String = format!("could not create epoll socket: {:?}", errno);
+                error!("new(): {}", cause);
+                return Err(Fail::new(errno, &cause));
             },
         };
 
@@ -600,15 +986,20 @@ impl NetworkTransport for SharedCatnapTransport {
             epoll_fd,
             socket_table: Slab::<SharedSocketData>::new(),
             background_task,
+            buffer_pool: runtime.get_buffer_pool(),
+            recv_batch_size: recv_batch_size.min(RECV_BATCH_SIZE_MAX),
+            send_batch_size: send_batch_size.min(SEND_BATCH_SIZE_MAX),
         }));
         let mut me2: Self = me.clone();
+        let me3: Self = me.clone();
         runtime
-            .insert_background_coroutine(
+            .insert_poller_coroutine_with_idle_hook(
                 "catnap::transport::epoll",
                 Box::pin(async move { me2.poll(yielder).await }),
+                Box::new(move |timeout: Option<Duration>| me3.wait_for_input(timeout)),
             )
             .expect("should be able to insert background coroutine");
-        me
+        Ok(me)
     }
 
     /// Creates a new socket on the underlying network transport. We only support IPv4 and UDP and TCP sockets for now.
@@ -660,7 +1051,8 @@ impl NetworkTransport for SharedCatnapTransport {
         let sd: Self::SocketDescriptor = match typ {
             Type::STREAM => self.socket_table.insert(SharedSocketData::new_inactive(socket)),
             Type::DGRAM => {
-                let new_sd: Self::SocketDescriptor = self.socket_table.insert(SharedSocketData::new_active(socket));
+                let data: SharedSocketData = SharedSocketData::new_active(socket, self.buffer_pool.clone());
+                let new_sd: Self::SocketDescriptor = self.socket_table.insert(data);
                 self.register_epoll(&new_sd, (libc::EPOLLIN | libc::EPOLLOUT) as u32)?;
                 new_sd
             },
@@ -705,8 +1097,25 @@ impl NetworkTransport for SharedCatnapTransport {
         &mut self,
         sd: &mut Self::SocketDescriptor,
         yielder: Yielder,
-    ) -> Result<(Self::SocketDescriptor, SocketAddr), Fail> {
+    ) -> Result<(Self::SocketDescriptor, SocketAddr, SocketAddr), Fail> {
         let (new_socket, addr) = self.data_from_sd(sd).accept(yielder).await?;
+        // Get the local address that the connection landed on, which may differ between connections on multi-homed
+        // hosts.
+        let local: SocketAddr = match new_socket.local_addr().map(|local| local.as_socket()) {
+            Ok(Some(local)) => local,
+            Ok(None) => {
+                let cause: String = format!("accepted socket has no internet address");
+                new_socket.shutdown(Shutdown::Both)?;
+                error!("accept(): {}", cause);
+                return Err(Fail::new(libc::EAFNOSUPPORT, &cause));
+            },
+            Err(e) => {
+                let cause: String = format!("cannot get local address: {:?}", e);
+                new_socket.shutdown(Shutdown::Both)?;
+                error!("accept(): {}", cause);
+                return Err(Fail::new(get_libc_err(e), &cause));
+            },
+        };
         // Set socket options.
         if let Err(e) = new_socket.set_reuse_address(true) {
             let cause: String = format!("cannot set REUSE_ADDRESS option: {:?}", e);
@@ -727,10 +1136,10 @@ impl NetworkTransport for SharedCatnapTransport {
             return Err(Fail::new(get_libc_err(e), &cause));
         }
 
-        let new_data: SharedSocketData = SharedSocketData::new_active(new_socket);
+        let new_data: SharedSocketData = SharedSocketData::new_active(new_socket, self.buffer_pool.clone());
         let new_sd: usize = self.socket_table.insert(new_data);
         self.register_epoll(&new_sd, (libc::EPOLLIN | libc::EPOLLOUT) as u32)?;
-        Ok((new_sd, addr))
+        Ok((new_sd, local, addr))
     }
 
     /// Connect to [remote] through the underlying transport. This function blocks until the connect succeeds or fails
@@ -741,7 +1150,8 @@ impl NetworkTransport for SharedCatnapTransport {
         remote: SocketAddr,
         yielder: Yielder,
     ) -> Result<(), Fail> {
-        self.data_from_sd(sd).move_socket_to_active();
+        let buffer_pool: SharedBufferPool = self.buffer_pool.clone();
+        self.data_from_sd(sd).move_socket_to_active(buffer_pool);
         self.register_epoll(&sd, (libc::EPOLLIN | libc::EPOLLOUT) as u32)?;
 
         loop {
@@ -751,7 +1161,9 @@ impl NetworkTransport for SharedCatnapTransport {
                     // Check the return error code.
                     let errno: i32 = get_libc_err(e);
                     if DemiRuntime::should_retry(errno) {
-                        self.data_from_sd(sd).push(None, DemiBuffer::new(0), &yielder).await?;
+                        self.data_from_sd(sd)
+                            .push(None, None, DemiBuffer::new(0), &yielder)
+                            .await?;
                     } else {
                         let cause: String = format!("failed to connect on socket: {:?}", errno);
                         error!("connect(): {}", cause);
@@ -776,7 +1188,7 @@ impl NetworkTransport for SharedCatnapTransport {
                         libc::ENOTCONN => break,
                         errno if DemiRuntime::should_retry(errno) => {
                             // Wait for a new incoming event.
-                            data.pop(&mut DemiBuffer::new(0), 0, &yielder).await?;
+                            data.pop(0, &yielder).await?;
                             continue;
                         },
                         errno => return Err(Fail::new(errno, "operation failed")),
@@ -795,33 +1207,92 @@ impl NetworkTransport for SharedCatnapTransport {
     }
 
     /// Push [buf] to the underlying transport. This function blocks until the entire buffer has been written to the
-    /// socket. Returns Ok if successfully sent and an error if not.
+    /// socket. Returns Ok if successfully sent and an error if not. Datagrams that are given a source address [src] are
+    /// written with sendmsg() and IP_PKTINFO, so the kernel fails them if [src] is not a local address.
     async fn push(
         &mut self,
         sd: &mut Self::SocketDescriptor,
         buf: &mut DemiBuffer,
         addr: Option<SocketAddr>,
+        src: Option<Ipv4Addr>,
         yielder: Yielder,
     ) -> Result<(), Fail> {
         {
-            self.data_from_sd(sd).push(addr, buf.clone(), &yielder).await?;
+            self.data_from_sd(sd).push(addr, src, buf.clone(), &yielder).await?;
             // Clear out the original buffer.
             buf.trim(buf.len()).expect("Should be able to empty the buffer");
             Ok(())
         }
     }
 
-    /// Pop a [buf] of at most [size] from the underlying transport. This function blocks until the socket has data to
-    /// be read. For connected (i.e., TCP) sockets, this function returns Ok(None). For datagram (i.e., UDP) sockets,
-    /// this function returns the remote address that is the source of the incoming data.
+    /// Pop a buffer of at most [size] from the underlying transport. This function blocks until the socket has data to
+    /// be read. For connected (i.e., TCP) sockets, this function returns no address. For datagram (i.e., UDP) sockets,
+    /// this function returns the remote address that is the source of the incoming data, along with the ancillary data
+    /// of the datagram if IP_PKTINFO is set.
     async fn pop(
         &mut self,
         sd: &mut Self::SocketDescriptor,
-        buf: &mut DemiBuffer,
         size: usize,
         yielder: Yielder,
-    ) -> Result<Option<SocketAddr>, Fail> {
-        self.data_from_sd(sd).pop(buf, size, &yielder).await
+    ) -> Result<(Option<SocketAddr>, Option<PacketInfo>, DemiBuffer), Fail> {
+        self.data_from_sd(sd).pop(size, &yielder).await
+    }
+
+    /// Sets a socket option on the underlying socket. Only IP_PKTINFO is supported for now.
+    fn set_socket_option(&mut self, sd: &mut Self::SocketDescriptor, option: SocketOption) -> Result<(), Fail> {
+        match option {
+            SocketOption::PktInfo(pktinfo) => {
+                let value: libc::c_int = pktinfo as libc::c_int;
+                let ret: libc::c_int = unsafe {
+                    libc::setsockopt(
+                        self.raw_fd_from_sd(sd),
+                        libc::IPPROTO_IP,
+                        libc::IP_PKTINFO,
+                        &value as *const libc::c_int as *const libc::c_void,
+                        mem::size_of::<libc::c_int>() as libc::socklen_t,
+                    )
+                };
+                if ret != 0 {
+                    let errno: libc::c_int = unsafe { *libc::__errno_location() };
+                    let cause: String = format!("cannot set IP_PKTINFO option: {:?}", errno);
+                    error!("set_socket_option(): {}", cause);
+                    return Err(Fail::new(errno, &cause));
+                }
+                Ok(())
+            },
+            _ => Err(Fail::new(libc::ENOPROTOOPT, "unsupported socket option")),
+        }
+    }
+
+    /// Gets the value of a socket option of the underlying socket. Only IP_PKTINFO is supported for now.
+    fn get_socket_option(
+        &mut self,
+        sd: &mut Self::SocketDescriptor,
+        name: SocketOptionName,
+    ) -> Result<SocketOption, Fail> {
+        match name {
+            SocketOptionName::PktInfo => {
+                let mut value: libc::c_int = 0;
+                let mut len: libc::socklen_t = mem::size_of::<libc::c_int>() as libc::socklen_t;
+                let ret: libc::c_int = unsafe {
+                    libc::getsockopt(
+                        self.raw_fd_from_sd(sd),
+                        libc::IPPROTO_IP,
+                        libc::IP_PKTINFO,
+                        &mut value as *mut libc::c_int as *mut libc::c_void,
+                        &mut len,
+                    )
+                };
+                if ret != 0 {
+                    let errno: libc::c_int = unsafe { *libc::__errno_location() };
+                    let cause: String = format!("cannot get IP_PKTINFO option: {:?}", errno);
+                    error!("get_socket_option(): {}", cause);
+                    return Err(Fail::new(errno, &cause));
+                }
+                Ok(SocketOption::PktInfo(value != 0))
+            },
+            _ => Err(Fail::new(libc::ENOPROTOOPT, "unsupported socket option")),
+        }
     }
 
     /// Close the socket on the underlying transport. Also unregisters the socket with epoll.
@@ -852,3 +1323,333 @@ impl NetworkTransport for SharedCatnapTransport {
         Ok(())
     }
 }
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ActiveSocketData,
+        SharedCatnapTransport,
+    };
+    use crate::{
+        demikernel::config::Config,
+        runtime::{
+            memory::{
+                DemiBuffer,
+                SharedBufferPool,
+            },
+            network::{
+                transport::NetworkTransport,
+                types::PacketInfo,
+            },
+            scheduler::{
+                Yielder,
+                YielderHandle,
+            },
+            SharedDemiRuntime,
+        },
+    };
+    use ::anyhow::Result;
+    use ::socket2::{
+        Domain,
+        Protocol,
+        SockAddr,
+        Socket,
+        Type,
+    };
+    use ::std::{
+        mem,
+        net::{
+            Ipv4Addr,
+            SocketAddr,
+            UdpSocket,
+        },
+        os::fd::AsRawFd,
+    };
+    use ::test::{
+        black_box,
+        Bencher,
+    };
+    use ::yaml_rust::YamlLoader;
+
+    /// Number of datagrams that each iteration of the benchmarks floods a socket with.
+    const FLOOD_SIZE: usize = 64;
+
+    /// Size of the datagrams of the benchmarks.
+    const DATAGRAM_SIZE: usize = 64;
+
+    /// Maximum number of buffers of the pools that are enabled in the benchmarks and tests.
+    const MAX_BUFFERS: usize = 2 * FLOOD_SIZE;
+
+    /// Creates a UDP socket bound to an ephemeral port on the loopback interface.
+    fn new_udp_socket() -> Result<Socket> {
+        let socket: Socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        let local: SocketAddr = "127.0.0.1:0".parse()?;
+        socket.bind(&local.into())?;
+        Ok(socket)
+    }
+
+    /// Creates a buffer pool that holds up to `max_buffers` buffers, and is disabled if `max_buffers` is zero.
+    fn new_buffer_pool(max_buffers: usize) -> SharedBufferPool {
+        let mut pool: SharedBufferPool = SharedBufferPool::default();
+        pool.set_max_buffers(max_buffers);
+        pool
+    }
+
+    /// Floods a socket with [FLOOD_SIZE] datagrams on each iteration, and reads them `batch_size` at a time into
+    /// buffers of a pool that holds up to `max_buffers` buffers. Received buffers are freed right away, as an
+    /// application that consumes its pops would.
+    fn bench_poll_recv(b: &mut Bencher, batch_size: usize, max_buffers: usize) {
+        let receiver: Socket = new_udp_socket().expect("should be able to create a socket");
+        let sender: Socket = new_udp_socket().expect("should be able to create a socket");
+        let receiver_addr: SockAddr = receiver.local_addr().expect("socket should be bound");
+        let mut pool: SharedBufferPool = new_buffer_pool(max_buffers);
+        let mut data: ActiveSocketData = ActiveSocketData::new(receiver, pool.clone());
+        b.iter(|| {
+            for _ in 0..FLOOD_SIZE {
+                sender
+                    .send_to(&[0x5a; DATAGRAM_SIZE], &receiver_addr)
+                    .expect("should be able to send");
+            }
+            let mut num_received: usize = 0;
+            while num_received < FLOOD_SIZE {
+                data.poll_recv(batch_size);
+                while let Some(result) = data.recv_queue.try_pop() {
+                    let (_, _, buf): (Option<SocketAddr>, Option<PacketInfo>, DemiBuffer) =
+                        result.expect("should have received a datagram");
+                    pool.recycle(black_box(buf));
+                    num_received += 1;
+                }
+            }
+        });
+    }
+
+    /// Queues [FLOOD_SIZE] datagrams on each iteration, and writes them `batch_size` at a time.
+    fn bench_poll_send(b: &mut Bencher, batch_size: usize) {
+        let receiver: UdpSocket = new_udp_socket().expect("should be able to create a socket").into();
+        let sender: Socket = new_udp_socket().expect("should be able to create a socket");
+        let receiver_addr: SocketAddr = receiver.local_addr().expect("socket should be bound");
+        let buf: DemiBuffer = DemiBuffer::from_slice(&[0x5a; DATAGRAM_SIZE]).expect("should be able to allocate");
+        let yielder: Yielder = Yielder::new();
+        let mut data: ActiveSocketData = ActiveSocketData::new(sender, SharedBufferPool::default());
+        let mut received: [u8; DATAGRAM_SIZE] = [0; DATAGRAM_SIZE];
+        b.iter(|| {
+            for _ in 0..FLOOD_SIZE {
+                data.send_queue
+                    .push((Some(receiver_addr), None, buf.clone(), yielder.get_handle()));
+            }
+            while data.send_queue.len() > 0 {
+                data.poll_send(batch_size);
+            }
+            for _ in 0..FLOOD_SIZE {
+                black_box(receiver.recv(&mut received).expect("should be able to receive"));
+            }
+        });
+    }
+
+    /// Tests that datagrams read in batches are still delivered one at a time, along with their source address, in
+    /// buffers of the pool.
+    #[test]
+    fn test_poll_recv_batch() -> Result<()> {
+        let receiver: Socket = new_udp_socket()?;
+        let sender: Socket = new_udp_socket()?;
+        let sender_addr: Option<SocketAddr> = sender.local_addr()?.as_socket();
+        let receiver_addr: SockAddr = receiver.local_addr()?;
+        for len in 1..=5 {
+            sender.send_to(&vec![len as u8; len], &receiver_addr)?;
+        }
+
+        let pool: SharedBufferPool = new_buffer_pool(MAX_BUFFERS);
+        let mut data: ActiveSocketData = ActiveSocketData::new(receiver, pool.clone());
+        data.poll_recv(4);
+        crate::ensure_eq!(data.recv_queue.len(), 4);
+        data.poll_recv(4);
+        crate::ensure_eq!(data.recv_queue.len(), 5);
+        // The buffers that did not get a datagram go back to the pool.
+        crate::ensure_eq!(pool.len(), 3);
+        crate::ensure_eq!(pool.get_num_allocations(), 8);
+        for len in 1..=5 {
+            let (addr, info, buf): (Option<SocketAddr>, Option<PacketInfo>, DemiBuffer) =
+                match data.recv_queue.try_pop() {
+                    Some(Ok(result)) => result,
+                    _ => anyhow::bail!("should have received a datagram"),
+                };
+            crate::ensure_eq!(addr, sender_addr);
+            // IP_PKTINFO is not set.
+            crate::ensure_eq!(info, None);
+            crate::ensure_eq!(&buf[..], &vec![len as u8; len][..]);
+        }
+
+        Ok(())
+    }
+
+    /// Tests that datagrams received on a socket on which IP_PKTINFO is set carry the local address that they were
+    /// sent to and the index of the loopback interface, whether they are read in batches or one at a time.
+    #[test]
+    fn test_poll_recv_pktinfo() -> Result<()> {
+        let receiver: Socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        let wildcard: SocketAddr = "0.0.0.0:0".parse()?;
+        receiver.bind(&wildcard.into())?;
+        let value: libc::c_int = 1;
+        let ret: libc::c_int = unsafe {
+            libc::setsockopt(
+                receiver.as_raw_fd(),
+                libc::IPPROTO_IP,
+                libc::IP_PKTINFO,
+                &value as *const libc::c_int as *const libc::c_void,
+                mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        crate::ensure_eq!(ret, 0);
+        let port: u16 = match receiver.local_addr()?.as_socket() {
+            Some(local) => local.port(),
+            None => anyhow::bail!("socket should be bound to an internet address"),
+        };
+        let receiver_addr: SocketAddr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port);
+        let sender: Socket = new_udp_socket()?;
+        let loopback_ifindex: u32 = unsafe { libc::if_nametoindex(b"lo\0".as_ptr() as *const libc::c_char) };
+
+        let mut data: ActiveSocketData = ActiveSocketData::new(receiver, new_buffer_pool(MAX_BUFFERS));
+        for batch_size in [4, 1] {
+            sender.send_to(&[0x5a; DATAGRAM_SIZE], &receiver_addr.into())?;
+            data.poll_recv(batch_size);
+            let info: Option<PacketInfo> = match data.recv_queue.try_pop() {
+                Some(Ok((_, info, _))) => info,
+                _ => anyhow::bail!("should have received a datagram"),
+            };
+            crate::ensure_eq!(
+                info,
+                Some(PacketInfo {
+                    dst_addr: Ipv4Addr::LOCALHOST,
+                    ifindex: loopback_ifindex,
+                })
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Tests that queued datagrams are written in batches, in order, and that each of their pushes completes.
+    #[test]
+    fn test_poll_send_batch() -> Result<()> {
+        let receiver: UdpSocket = new_udp_socket()?.into();
+        let sender: Socket = new_udp_socket()?;
+        let receiver_addr: SocketAddr = receiver.local_addr()?;
+
+        let mut data: ActiveSocketData = ActiveSocketData::new(sender, SharedBufferPool::default());
+        let mut handles: Vec<YielderHandle> = Vec::new();
+        for len in 1..=3 {
+            let yielder: Yielder = Yielder::new();
+            handles.push(yielder.get_handle());
+            data.send_queue.push((
+                Some(receiver_addr),
+                None,
+                DemiBuffer::from_slice(&vec![len as u8; len])?,
+                yielder.get_handle(),
+            ));
+        }
+        data.poll_send(4);
+        crate::ensure_eq!(data.send_queue.len(), 0);
+        for handle in handles.iter_mut() {
+            crate::ensure_eq!(handle.get_result().map(|result| result.is_ok()), Some(true));
+        }
+
+        let mut buf: [u8; 16] = [0; 16];
+        for len in 1..=3 {
+            crate::ensure_eq!(receiver.recv(&mut buf)?, len);
+            crate::ensure_eq!(&buf[..len], &vec![len as u8; len][..]);
+        }
+
+        Ok(())
+    }
+
+    /// Tests that datagrams that are given a source address go out from it, rather than from the address that the
+    /// socket is bound to, whether they are written in batches or one at a time.
+    #[test]
+    fn test_poll_send_from() -> Result<()> {
+        let receiver: UdpSocket = new_udp_socket()?.into();
+        let sender: Socket = new_udp_socket()?;
+        let receiver_addr: SocketAddr = receiver.local_addr()?;
+        // Every address of the loopback network is local.
+        let src: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 2);
+        let port: u16 = match sender.local_addr()?.as_socket() {
+            Some(local) => local.port(),
+            None => anyhow::bail!("socket should be bound to an internet address"),
+        };
+
+        let mut data: ActiveSocketData = ActiveSocketData::new(sender, SharedBufferPool::default());
+        for batch_size in [4, 1] {
+            let yielder: Yielder = Yielder::new();
+            for _ in 0..batch_size.min(2) {
+                data.send_queue.push((
+                    Some(receiver_addr),
+                    Some(src),
+                    DemiBuffer::from_slice(&[0x5a; DATAGRAM_SIZE])?,
+                    yielder.get_handle(),
+                ));
+            }
+            while data.send_queue.len() > 0 {
+                data.poll_send(batch_size);
+            }
+        }
+
+        let mut buf: [u8; DATAGRAM_SIZE] = [0; DATAGRAM_SIZE];
+        for _ in 0..3 {
+            let (nbytes, addr): (usize, SocketAddr) = receiver.recv_from(&mut buf)?;
+            crate::ensure_eq!(nbytes, DATAGRAM_SIZE);
+            crate::ensure_eq!(addr, SocketAddr::new(src.into(), port));
+        }
+
+        Ok(())
+    }
+
+    /// Tests that a transport cannot be created with a batch size that is not positive.
+    #[test]
+    fn test_invalid_batch_size() -> Result<()> {
+        for key in ["recv_batch_size", "send_batch_size"] {
+            let yaml: String = format!("catnap:\n  {}: 0", key);
+            let config: Config = Config(YamlLoader::load_from_str(&yaml)?.remove(0));
+            let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+            match SharedCatnapTransport::new(&config, &mut runtime) {
+                Err(e) if e.errno == libc::EINVAL => (),
+                Err(e) => anyhow::bail!("creating the transport should have failed with EINVAL: {:?}", e),
+                Ok(_) => anyhow::bail!("creating the transport should have failed"),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[bench]
+    fn bench_poll_recv_one_at_a_time(b: &mut Bencher) {
+        bench_poll_recv(b, 1, 0);
+    }
+
+    #[bench]
+    fn bench_poll_recv_batched(b: &mut Bencher) {
+        bench_poll_recv(b, 32, 0);
+    }
+
+    #[bench]
+    fn bench_poll_recv_one_at_a_time_with_pool(b: &mut Bencher) {
+        bench_poll_recv(b, 1, MAX_BUFFERS);
+    }
+
+    #[bench]
+    fn bench_poll_recv_batched_with_pool(b: &mut Bencher) {
+        bench_poll_recv(b, 32, MAX_BUFFERS);
+    }
+
+    #[bench]
+    fn bench_poll_send_one_at_a_time(b: &mut Bencher) {
+        bench_poll_send(b, 1);
+    }
+
+    #[bench]
+    fn bench_poll_send_batched(b: &mut Bencher) {
+        bench_poll_send(b, 32);
+    }
+}
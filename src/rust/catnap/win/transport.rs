@@ -12,7 +12,10 @@ mod winsock;
 //==============================================================================
 
 use std::{
-    net::SocketAddr,
+    net::{
+        Ipv4Addr,
+        SocketAddr,
+    },
     pin::Pin,
     time::Duration,
 };
@@ -44,8 +47,14 @@ use crate::{
     demikernel::config::Config,
     runtime::{
         fail::Fail,
-        memory::DemiBuffer,
-        network::transport::NetworkTransport,
+        memory::{
+            DemiBuffer,
+            SharedBufferPool,
+        },
+        network::{
+            transport::NetworkTransport,
+            types::PacketInfo,
+        },
         scheduler::Yielder,
         DemiRuntime,
         SharedDemiRuntime,
@@ -76,6 +85,9 @@ pub struct CatnapTransport {
 
     /// Configuration values.
     config: WinConfig,
+
+    /// Pool of buffers that sockets receive data into.
+    buffer_pool: SharedBufferPool,
 }
 
 /// A network transport built on top of Windows overlapped I/O.
@@ -111,7 +123,7 @@ impl NetworkTransport for SharedCatnapTransport {
     type SocketDescriptor = Socket;
 
     /// Create a new transport instance.
-    fn new(config: &Config, runtime: &mut SharedDemiRuntime) -> Self {
+    fn new(config: &Config, runtime: &mut SharedDemiRuntime) -> Result<Self, Fail> {
         let config: WinConfig = WinConfig {
             keepalive_params: config.tcp_keepalive().expect("failed to load TCP settings"),
             linger_time: config.linger_time().expect("failed to load linger settings"),
@@ -121,10 +133,11 @@ impl NetworkTransport for SharedCatnapTransport {
             winsock: WinsockRuntime::new().expect("failed to initialize WinSock"),
             iocp: IoCompletionPort::new().expect("failed to setup I/O completion port"),
             config,
+            buffer_pool: runtime.get_buffer_pool(),
         }));
 
         runtime
-            .insert_background_coroutine(
+            .insert_poller_coroutine(
                 "catnap::transport::epoll",
                 Box::pin({
                     let mut me: Self = me.clone();
@@ -133,7 +146,7 @@ impl NetworkTransport for SharedCatnapTransport {
             )
             .expect("should be able to insert background coroutine");
 
-        me
+        Ok(me)
     }
 
     /// Create a new socket for the specified domain and type.
@@ -197,7 +210,7 @@ impl NetworkTransport for SharedCatnapTransport {
         &mut self,
         socket: &mut Self::SocketDescriptor,
         yielder: Yielder,
-    ) -> Result<(Socket, SocketAddr), Fail> {
+    ) -> Result<(Socket, SocketAddr, SocketAddr), Fail> {
         let start = |accept_result: Pin<&mut AcceptState>, overlapped: *mut OVERLAPPED| -> Result<(), Fail> {
             socket.start_accept(accept_result, overlapped)
         };
@@ -211,14 +224,12 @@ impl NetworkTransport for SharedCatnapTransport {
             socket.finish_accept(accept_result, &me_finish.0.iocp, result)
         };
 
-        let (socket, _local_addr, remote_addr) = unsafe {
+        unsafe {
             self.0
                 .iocp
                 .do_io_with(AcceptState::new(), &yielder, start, cancel, finish)
         }
-        .await?;
-
-        Ok((socket, remote_addr))
+        .await
     }
 
     /// Connect a socket to a remote address.
@@ -239,14 +250,15 @@ impl NetworkTransport for SharedCatnapTransport {
         .await
     }
 
-    /// Pop data from the socket into `buf`. This method will return the remote address iff the socket is not connected.
+    /// Pop data from the socket into a buffer of the pool. This method will return the remote address iff the socket is
+    /// not connected. IP_PKTINFO is not supported, so no ancillary data is returned.
     async fn pop(
         &mut self,
         socket: &mut Self::SocketDescriptor,
-        buf: &mut DemiBuffer,
         size: usize,
         yielder: Yielder,
-    ) -> Result<Option<SocketAddr>, Fail> {
+    ) -> Result<(Option<SocketAddr>, Option<PacketInfo>, DemiBuffer), Fail> {
+        let mut buf: DemiBuffer = self.0.buffer_pool.alloc(size as u16);
         unsafe {
             self.0.iocp.do_io_with(
                 PopState::new(buf.clone()),
@@ -270,20 +282,25 @@ impl NetworkTransport for SharedCatnapTransport {
             } else {
                 trace!("not data received");
             }
-            Ok(sockaddr)
+            Ok((sockaddr, None, buf))
         })
     }
 
     /// Push `buf` to the remote endpoint. `addr` is used iff the socket is not connection-oriented. For message-
     /// oriented sockets which were previously `connect`ed, `addr` overrides the previously specified remote address.
-    /// For connection-oriented sockets, `addr` is ignored.
+    /// For connection-oriented sockets, `addr` is ignored. Picking the source address `src` of datagrams is not
+    /// supported.
     async fn push(
         &mut self,
         socket: &mut Self::SocketDescriptor,
         buf: &mut DemiBuffer,
         addr: Option<SocketAddr>,
+        src: Option<Ipv4Addr>,
         yielder: Yielder,
     ) -> Result<(), Fail> {
+        if src.is_some() {
+            return Err(Fail::new(ENOTSUP, "cannot pick the source address of datagrams"));
+        }
         loop {
             let result: Result<usize, Fail> = unsafe {
                 self.0.iocp.do_io_with(
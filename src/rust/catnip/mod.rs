@@ -67,7 +67,7 @@ pub struct CatnipLibOS {
 
 /// Associate Functions for Catnip LibOS
 impl CatnipLibOS {
-    pub fn new(config: &Config, runtime: SharedDemiRuntime) -> Self {
+    pub fn new(config: &Config, runtime: SharedDemiRuntime) -> Result<Self, Fail> {
         load_mlx_driver();
         let transport: SharedDPDKRuntime = SharedDPDKRuntime::new(
             config.local_ipv4_addr(),
@@ -79,6 +79,7 @@ impl CatnipLibOS {
             config.mss(),
             config.tcp_checksum_offload(),
             config.udp_checksum_offload(),
+            config.udp_recv_queue_config()?,
         );
         let link_addr: MacAddress = transport.get_link_addr();
         let ip_addr: Ipv4Addr = transport.get_ip_addr();
@@ -86,7 +87,7 @@ impl CatnipLibOS {
         let udp_config: UdpConfig = transport.get_udp_config();
         let tcp_config: TcpConfig = transport.get_tcp_config();
         let rng_seed: [u8; 32] = [0; 32];
-        let inetstack: SharedInetStack = SharedInetStack::new(
+        let mut inetstack: SharedInetStack = SharedInetStack::new(
             runtime.clone(),
             SharedBox::<dyn NetworkRuntime>::new(Box::new(transport.clone())),
             link_addr,
@@ -95,13 +96,23 @@ impl CatnipLibOS {
             tcp_config,
             rng_seed,
             arp_config,
-        )
-        .unwrap();
-        CatnipLibOS {
+            config.pcap_config()?,
+            config.ethernet2_config()?,
+            config.routing_config()?,
+        )?;
+        // Apply addresses learned at startup, if any. This must happen before any socket is created.
+        let local_link_addr: Option<MacAddress> = config.local_link_addr_override()?;
+        let local_ipv4_addr: Option<Ipv4Addr> = config.local_ipv4_addr_override()?;
+        if local_link_addr.is_some() || local_ipv4_addr.is_some() {
+            let local_link_addr: MacAddress = local_link_addr.unwrap_or(link_addr);
+            let local_ipv4_addr: Ipv4Addr = local_ipv4_addr.unwrap_or(ip_addr);
+            inetstack.set_local_addrs(local_link_addr, local_ipv4_addr)?;
+        }
+        Ok(CatnipLibOS {
             runtime,
             inetstack,
             transport,
-        }
+        })
     }
 
     /// Create a push request for Demikernel to asynchronously write data from `sga` to the
@@ -111,9 +122,7 @@ impl CatnipLibOS {
         trace!("push(): qd={:?}", qd);
         match self.transport.clone_sgarray(sga) {
             Ok(buf) => {
-                if buf.len() == 0 {
-                    return Err(Fail::new(libc::EINVAL, "zero-length buffer"));
-                }
+                self.check_push_size(qd, &buf)?;
 
                 self.do_push(qd, buf)
             },
@@ -121,13 +130,26 @@ impl CatnipLibOS {
         }
     }
 
-    pub fn pushto(&mut self, qd: QDesc, sga: &demi_sgarray_t, to: SocketAddr) -> Result<QToken, Fail> {
-        trace!("pushto2(): qd={:?}", qd);
+    /// Like [push](Self::push), but with a hint that more data is coming. The data is held back and sent along with
+    /// the data of the next push on `qd`.
+    pub fn push_more(&mut self, qd: QDesc, sga: &demi_sgarray_t) -> Result<QToken, Fail> {
+        trace!("push_more(): qd={:?}", qd);
         match self.transport.clone_sgarray(sga) {
             Ok(buf) => {
                 if buf.len() == 0 {
                     return Err(Fail::new(libc::EINVAL, "zero-length buffer"));
                 }
+                self.do_push_more(qd, buf)
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn pushto(&mut self, qd: QDesc, sga: &demi_sgarray_t, to: SocketAddr) -> Result<QToken, Fail> {
+        trace!("pushto2(): qd={:?}", qd);
+        match self.transport.clone_sgarray(sga) {
+            Ok(buf) => {
+                self.check_push_size(qd, &buf)?;
                 let handle: TaskHandle = self.do_pushto(qd, buf, to)?;
                 let qt: QToken = handle.get_task_id().into();
                 Ok(qt)
@@ -136,6 +158,26 @@ impl CatnipLibOS {
         }
     }
 
+    /// Like [pushto](Self::pushto), but the datagram goes out from the local address `from`.
+    pub fn pushto_from(
+        &mut self,
+        qd: QDesc,
+        sga: &demi_sgarray_t,
+        from: Ipv4Addr,
+        to: SocketAddr,
+    ) -> Result<QToken, Fail> {
+        trace!("pushto_from(): qd={:?} from={:?}", qd, from);
+        match self.transport.clone_sgarray(sga) {
+            Ok(buf) => {
+                self.check_push_size(qd, &buf)?;
+                let handle: TaskHandle = self.do_pushto_from(qd, buf, from, to)?;
+                let qt: QToken = handle.get_task_id().into();
+                Ok(qt)
+            },
+            Err(e) => Err(e),
+        }
+    }
+
     pub fn schedule(&mut self, qt: QToken) -> Result<TaskHandle, Fail> {
         self.runtime.from_task_id(qt.into())
     }
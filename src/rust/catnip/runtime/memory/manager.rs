@@ -8,7 +8,10 @@
 use super::mempool::MemoryPool;
 use crate::{
     inetstack::protocols::{
-        ethernet2::ETHERNET2_HEADER_SIZE,
+        ethernet2::{
+            vlan::VLAN_TAG_SIZE,
+            ETHERNET2_HEADER_SIZE,
+        },
         ipv4::IPV4_HEADER_MAX_SIZE,
         tcp::MAX_TCP_HEADER_SIZE,
     },
@@ -70,7 +73,9 @@ impl MemoryManager {
     /// Instantiates a memory manager.
     pub fn new(max_body_size: usize) -> Result<Self, Error> {
         let config: MemoryConfig = MemoryConfig::new(None, None, Some(max_body_size), None, None);
-        let header_size: usize = ETHERNET2_HEADER_SIZE + (IPV4_HEADER_MAX_SIZE as usize) + MAX_TCP_HEADER_SIZE;
+        // Leave room for a VLAN tag, in case frames are tagged.
+        let header_size: usize =
+            ETHERNET2_HEADER_SIZE + VLAN_TAG_SIZE + (IPV4_HEADER_MAX_SIZE as usize) + MAX_TCP_HEADER_SIZE;
         let header_mbuf_size: usize = header_size + config.get_inline_body_size();
 
         // Create memory pool for holding packet headers.
@@ -54,6 +54,7 @@ use crate::runtime::{
             ArpConfig,
             TcpConfig,
             UdpConfig,
+            UdpRecvQueueConfig,
         },
         types::MacAddress,
     },
@@ -99,6 +100,8 @@ macro_rules! expect_zero {
 pub struct DPDKRuntime {
     mm: MemoryManager,
     port_id: u16,
+    /// Index of the interface of the host that the DPDK port is bound to, or zero if there is none.
+    ifindex: u32,
     link_addr: MacAddress,
     ipv4_addr: Ipv4Addr,
     arp_config: ArpConfig,
@@ -125,8 +128,9 @@ impl SharedDPDKRuntime {
         mss: usize,
         tcp_checksum_offload: bool,
         udp_checksum_offload: bool,
+        udp_recv_queue_config: UdpRecvQueueConfig,
     ) -> Self {
-        let (mm, port_id, link_addr) = Self::initialize_dpdk(
+        let (mm, port_id, ifindex, link_addr) = Self::initialize_dpdk(
             eal_init_args,
             use_jumbo_frames,
             mtu,
@@ -143,22 +147,24 @@ impl SharedDPDKRuntime {
             Some(disable_arp),
         );
 
-        let tcp_config = TcpConfig::new(
-            Some(mss),
-            None,
-            None,
-            Some(0xffff),
-            Some(0),
-            None,
-            Some(tcp_checksum_offload),
-            Some(tcp_checksum_offload),
+        let tcp_config = TcpConfig::default()
+            .set_advertised_mss(mss)
+            .set_receive_window_size(0xffff)
+            .set_window_scale(0)
+            .set_rx_checksum_offload(tcp_checksum_offload)
+            .set_tx_checksum_offload(tcp_checksum_offload);
+
+        let udp_config = UdpConfig::new(
+            Some(udp_checksum_offload),
+            Some(udp_checksum_offload),
+            Some(mtu as usize),
+            Some(udp_recv_queue_config),
         );
 
-        let udp_config = UdpConfig::new(Some(udp_checksum_offload), Some(udp_checksum_offload));
-
         Self(SharedObject::<DPDKRuntime>::new(DPDKRuntime {
             mm,
             port_id,
+            ifindex,
             link_addr,
             ipv4_addr,
             arp_config,
@@ -174,7 +180,7 @@ impl SharedDPDKRuntime {
         mtu: u16,
         tcp_checksum_offload: bool,
         udp_checksum_offload: bool,
-    ) -> Result<(MemoryManager, u16, MacAddress), Error> {
+    ) -> Result<(MemoryManager, u16, u32, MacAddress), Error> {
         std::env::set_var("MLX5_SHUT_UP_BF", "1");
         std::env::set_var("MLX5_SINGLE_THREADED", "1");
         std::env::set_var("MLX4_SINGLE_THREADED", "1");
@@ -200,7 +206,7 @@ impl SharedDPDKRuntime {
 
         let owner: u64 = RTE_ETH_DEV_NO_OWNER as u64;
         let port_id: u16 = unsafe { rte_eth_find_next_owned_by(0, owner) as u16 };
-        Self::initialize_dpdk_port(
+        let ifindex: u32 = Self::initialize_dpdk_port(
             port_id,
             &memory_manager,
             use_jumbo_frames,
@@ -224,10 +230,11 @@ impl SharedDPDKRuntime {
             Err(format_err!("Invalid mac address"))?;
         }
 
-        Ok((memory_manager, port_id, local_link_addr))
+        Ok((memory_manager, port_id, ifindex, local_link_addr))
     }
 
-    /// Initializes a DPDK port.
+    /// Initializes a DPDK port. Returns the index of the interface of the host that the port is bound to, or zero if
+    /// there is none.
     fn initialize_dpdk_port(
         port_id: u16,
         memory_manager: &MemoryManager,
@@ -235,7 +242,7 @@ impl SharedDPDKRuntime {
         mtu: u16,
         tcp_checksum_offload: bool,
         udp_checksum_offload: bool,
-    ) -> Result<(), Error> {
+    ) -> Result<u32, Error> {
         let rx_rings: u16 = 1;
         let tx_rings: u16 = 1;
         let rx_ring_size: u16 = 2048;
@@ -370,7 +377,7 @@ impl SharedDPDKRuntime {
             retry_count -= 1;
         }
 
-        Ok(())
+        Ok(dev_info.if_index)
     }
 
     pub fn get_link_addr(&self) -> MacAddress {
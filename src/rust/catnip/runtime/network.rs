@@ -153,4 +153,9 @@ impl NetworkRuntime for SharedDPDKRuntime {
 
         out
     }
+
+    /// Returns the index of the interface of the host that the DPDK port is bound to, if any.
+    fn get_ifindex(&self) -> u32 {
+        self.ifindex
+    }
 }
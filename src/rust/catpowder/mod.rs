@@ -15,7 +15,10 @@ use crate::{
     runtime::{
         fail::Fail,
         memory::MemoryRuntime,
-        network::NetworkRuntime,
+        network::{
+            types::MacAddress,
+            NetworkRuntime,
+        },
         scheduler::TaskHandle,
         types::{
             demi_qresult_t,
@@ -29,7 +32,10 @@ use crate::{
 };
 use ::std::{
     collections::HashMap,
-    net::SocketAddr,
+    net::{
+        Ipv4Addr,
+        SocketAddr,
+    },
     ops::{
         Deref,
         DerefMut,
@@ -57,15 +63,16 @@ pub struct CatpowderLibOS {
 /// Associate Functions for Catpowder LibOS
 impl CatpowderLibOS {
     /// Instantiates a Catpowder LibOS.
-    pub fn new(config: &Config, runtime: SharedDemiRuntime) -> Self {
+    pub fn new(config: &Config, runtime: SharedDemiRuntime) -> Result<Self, Fail> {
         let transport: LinuxRuntime = LinuxRuntime::new(
             config.local_link_addr(),
             config.local_ipv4_addr(),
             &config.local_interface_name(),
             HashMap::default(),
+            config.udp_recv_queue_config()?,
         );
         let rng_seed: [u8; 32] = [0; 32];
-        let inetstack: SharedInetStack = SharedInetStack::new(
+        let mut inetstack: SharedInetStack = SharedInetStack::new(
             runtime.clone(),
             SharedBox::<dyn NetworkRuntime>::new(Box::new(transport.clone())),
             transport.get_link_addr(),
@@ -74,13 +81,23 @@ impl CatpowderLibOS {
             transport.get_tcp_config(),
             rng_seed,
             transport.get_arp_config(),
-        )
-        .unwrap();
-        CatpowderLibOS {
+            config.pcap_config()?,
+            config.ethernet2_config()?,
+            config.routing_config()?,
+        )?;
+        // Apply addresses learned at startup, if any. This must happen before any socket is created.
+        let local_link_addr: Option<MacAddress> = config.local_link_addr_override()?;
+        let local_ipv4_addr: Option<Ipv4Addr> = config.local_ipv4_addr_override()?;
+        if local_link_addr.is_some() || local_ipv4_addr.is_some() {
+            let local_link_addr: MacAddress = local_link_addr.unwrap_or(transport.get_link_addr());
+            let local_ipv4_addr: Ipv4Addr = local_ipv4_addr.unwrap_or(transport.get_ip_addr());
+            inetstack.set_local_addrs(local_link_addr, local_ipv4_addr)?;
+        }
+        Ok(CatpowderLibOS {
             runtime,
             inetstack,
             transport,
-        }
+        })
     }
 
     /// Create a push request for Demikernel to asynchronously write data from `sga` to the
@@ -88,12 +105,25 @@ impl CatpowderLibOS {
     /// The data has been written when [`wait`ing](Self::wait) on the QToken returns.
     pub fn push(&mut self, qd: QDesc, sga: &demi_sgarray_t) -> Result<QToken, Fail> {
         trace!("push(): qd={:?}", qd);
+        match self.transport.clone_sgarray(sga) {
+            Ok(buf) => {
+                self.check_push_size(qd, &buf)?;
+                self.do_push(qd, buf)
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Like [push](Self::push), but with a hint that more data is coming. The data is held back and sent along with
+    /// the data of the next push on `qd`.
+    pub fn push_more(&mut self, qd: QDesc, sga: &demi_sgarray_t) -> Result<QToken, Fail> {
+        trace!("push_more(): qd={:?}", qd);
         match self.transport.clone_sgarray(sga) {
             Ok(buf) => {
                 if buf.len() == 0 {
                     return Err(Fail::new(libc::EINVAL, "zero-length buffer"));
                 }
-                self.do_push(qd, buf)
+                self.do_push_more(qd, buf)
             },
             Err(e) => Err(e),
         }
@@ -103,9 +133,7 @@ impl CatpowderLibOS {
         trace!("pushto(): qd={:?}", qd);
         match self.transport.clone_sgarray(sga) {
             Ok(buf) => {
-                if buf.len() == 0 {
-                    return Err(Fail::new(libc::EINVAL, "zero-length buffer"));
-                }
+                self.check_push_size(qd, &buf)?;
                 let handle: TaskHandle = self.do_pushto(qd, buf, to)?;
                 let qt: QToken = handle.get_task_id().into();
                 Ok(qt)
@@ -114,6 +142,26 @@ impl CatpowderLibOS {
         }
     }
 
+    /// Like [pushto](Self::pushto), but the datagram goes out from the local address `from`.
+    pub fn pushto_from(
+        &mut self,
+        qd: QDesc,
+        sga: &demi_sgarray_t,
+        from: Ipv4Addr,
+        to: SocketAddr,
+    ) -> Result<QToken, Fail> {
+        trace!("pushto_from(): qd={:?} from={:?}", qd, from);
+        match self.transport.clone_sgarray(sga) {
+            Ok(buf) => {
+                self.check_push_size(qd, &buf)?;
+                let handle: TaskHandle = self.do_pushto_from(qd, buf, from, to)?;
+                let qt: QToken = handle.get_task_id().into();
+                Ok(qt)
+            },
+            Err(e) => Err(e),
+        }
+    }
+
     pub fn schedule(&mut self, qt: QToken) -> Result<TaskHandle, Fail> {
         self.runtime.from_task_id(qt.into())
     }
@@ -19,6 +19,7 @@ use crate::runtime::{
             ArpConfig,
             TcpConfig,
             UdpConfig,
+            UdpRecvQueueConfig,
         },
         types::MacAddress,
     },
@@ -55,7 +56,13 @@ pub struct LinuxRuntime {
 /// Associate Functions for Linux Runtime
 impl LinuxRuntime {
     /// Instantiates a Linux Runtime.
-    pub fn new(link_addr: MacAddress, ipv4_addr: Ipv4Addr, ifname: &str, arp: HashMap<Ipv4Addr, MacAddress>) -> Self {
+    pub fn new(
+        link_addr: MacAddress,
+        ipv4_addr: Ipv4Addr,
+        ifname: &str,
+        arp: HashMap<Ipv4Addr, MacAddress>,
+        udp_recv_queue_config: UdpRecvQueueConfig,
+    ) -> Self {
         let arp_config: ArpConfig = ArpConfig::new(
             Some(Duration::from_secs(600)),
             Some(Duration::from_secs(1)),
@@ -73,7 +80,7 @@ impl LinuxRuntime {
 
         Self {
             tcp_config: TcpConfig::default(),
-            udp_config: UdpConfig::default(),
+            udp_config: UdpConfig::new(None, None, None, Some(udp_recv_queue_config)),
             arp_config,
             link_addr,
             ipv4_addr,
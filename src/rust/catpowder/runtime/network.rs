@@ -84,4 +84,8 @@ impl NetworkRuntime for LinuxRuntime {
             ArrayVec::new()
         }
     }
+
+    fn get_ifindex(&self) -> u32 {
+        self.ifindex as u32
+    }
 }
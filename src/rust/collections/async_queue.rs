@@ -25,7 +25,6 @@ use ::std::{
         Deref,
         DerefMut,
     },
-    vec::Vec,
 };
 
 //======================================================================================================================
@@ -33,10 +32,12 @@ use ::std::{
 //======================================================================================================================
 
 /// This data structure implements an unbounded asynchronous queue that is hooked into the Demikernel scheduler. On
-/// pop, if the queue is empty, the coroutine will yield until there is data to be read.
+/// pop, if the queue is empty, the coroutine will yield until there is data to be read. Several coroutines may pop
+/// concurrently, in which case they are woken in the order in which they started waiting, and each item goes to a
+/// single one of them.
 pub struct AsyncQueue<T> {
     queue: VecDeque<T>,
-    waiters: Vec<YielderHandle>,
+    waiters: VecDeque<YielderHandle>,
 }
 
 pub struct SharedAsyncQueue<T>(SharedObject<AsyncQueue<T>>);
@@ -51,7 +52,7 @@ impl<T> AsyncQueue<T> {
     pub fn with_capacity(size: usize) -> Self {
         Self {
             queue: VecDeque::<T>::with_capacity(size),
-            waiters: Vec::<YielderHandle>::new(),
+            waiters: VecDeque::<YielderHandle>::new(),
         }
     }
 
@@ -59,40 +60,51 @@ impl<T> AsyncQueue<T> {
     /// add bounds checking in the future.
     pub fn push(&mut self, item: T) {
         self.queue.push_back(item);
-        if let Some(mut handle) = self.waiters.pop() {
-            handle.wake_with(Ok(()));
-        }
+        self.wake_one();
     }
 
     pub fn push_front(&mut self, item: T) {
         self.queue.push_front(item);
-        if let Some(mut handle) = self.waiters.pop() {
-            handle.wake_with(Ok(()));
-        }
+        self.wake_one();
     }
 
     /// Pop from an async queue. If the queue is empty, this function blocks until it finds something in the queue.
     pub async fn pop(&mut self, yielder: &Yielder) -> Result<T, Fail> {
-        match self.queue.pop_front() {
-            Some(item) => Ok(item),
-            None => {
-                let handle: YielderHandle = yielder.get_handle();
-                self.waiters.push(handle);
-                match yielder.yield_until_wake().await {
-                    Ok(()) => match self.queue.pop_front() {
-                        Some(item) => Ok(item),
-                        None => {
-                            let cause: &str = "Spurious wake up!";
-                            warn!("pop(): {}", cause);
-                            Err(Fail::new(libc::EAGAIN, cause))
-                        },
-                    },
-                    Err(e) => Err(e),
-                }
-            },
+        loop {
+            if let Some(item) = self.queue.pop_front() {
+                return Ok(item);
+            }
+            let handle: YielderHandle = yielder.get_handle();
+            // Drop the handles of coroutines that stopped waiting on their own, so they do not pile up.
+            self.waiters.retain(|waiter| !waiter.is_abandoned());
+            self.waiters.push_back(handle);
+            // The item that woke us up may be taken by a pop that did not have to wait before we get to run, in which
+            // case we go back to waiting.
+            yielder.yield_until_wake().await?;
+        }
+    }
+
+    /// Waits for the next push to this queue, even if it is not empty. This lets a coroutine wait for more items while
+    /// leaving those that are queued in place.
+    pub async fn wait(&mut self, yielder: &Yielder) -> Result<(), Fail> {
+        let handle: YielderHandle = yielder.get_handle();
+        self.waiters.retain(|waiter| !waiter.is_abandoned());
+        self.waiters.push_back(handle);
+        yielder.yield_until_wake().await
+    }
+
+    /// Wakes all coroutines blocked on this queue with an error, without pushing anything to the queue.
+    pub fn cancel_waiters(&mut self, cause: Fail) {
+        while let Some(mut handle) = self.waiters.pop_front() {
+            handle.wake_with(Err(cause.clone()));
         }
     }
 
+    /// Checks whether any coroutine is still blocked on this queue.
+    pub fn has_waiters(&self) -> bool {
+        self.waiters.iter().any(|waiter| !waiter.is_abandoned())
+    }
+
     /// Try to get the head of the queue.
     pub fn try_pop(&mut self) -> Option<T> {
         self.queue.pop_front()
@@ -104,7 +116,6 @@ impl<T> AsyncQueue<T> {
     }
 
     /// Check if the queue is empty.
-    #[allow(unused)]
     pub fn is_empty(&self) -> bool {
         self.queue.is_empty()
     }
@@ -119,6 +130,16 @@ impl<T> AsyncQueue<T> {
     pub fn get_mut_values(&mut self) -> IterMut<T> {
         self.queue.iter_mut()
     }
+
+    /// Wakes the oldest coroutine that is still waiting on this queue, if any.
+    fn wake_one(&mut self) {
+        while let Some(mut handle) = self.waiters.pop_front() {
+            if !handle.is_abandoned() {
+                handle.wake_with(Ok(()));
+                break;
+            }
+        }
+    }
 }
 
 impl<T> SharedAsyncQueue<T> {
@@ -138,7 +159,7 @@ impl<T> Default for AsyncQueue<T> {
     fn default() -> Self {
         Self {
             queue: VecDeque::<T>::new(),
-            waiters: Vec::<YielderHandle>::new(),
+            waiters: VecDeque::<YielderHandle>::new(),
         }
     }
 }
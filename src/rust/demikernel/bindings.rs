@@ -14,6 +14,18 @@ use crate::{
         constants::{
             AF_INET,
             AF_INET6,
+            IPPROTO_IP,
+            IPPROTO_TCP,
+            IP_PKTINFO,
+            SOL_SOCKET,
+            SO_ACCEPTCONN,
+            SO_BROADCAST,
+            SO_DONTROUTE,
+            SO_RCVBUF,
+            SO_RCVLOWAT,
+            SO_REUSEADDR,
+            SO_SNDBUF,
+            TCP_NODELAY,
         },
         data_structures::{
             AddressFamily,
@@ -26,6 +38,10 @@ use crate::{
     runtime::{
         fail::Fail,
         logging,
+        network::types::{
+            SocketOption,
+            SocketOptionName,
+        },
         types::{
             demi_qresult_t,
             demi_qtoken_t,
@@ -45,7 +61,10 @@ use ::std::{
     cell::RefCell,
     ffi::CStr,
     mem,
-    net::SocketAddr,
+    net::{
+        Ipv4Addr,
+        SocketAddr,
+    },
     ptr,
     slice,
     time::{
@@ -57,7 +76,6 @@ use ::socket2::SockAddr;
 
 #[cfg(test)]
 use ::std::net::{
-        Ipv4Addr,
         Ipv6Addr,
         SocketAddrV4,
         SocketAddrV6,
@@ -450,6 +468,79 @@ pub extern "C" fn demi_pushto(
     }
 }
 
+//======================================================================================================================
+// pushto_from
+//======================================================================================================================
+
+#[no_mangle]
+pub extern "C" fn demi_pushto_from(
+    qtok_out: *mut demi_qtoken_t,
+    sockqd: c_int,
+    sga: *const demi_sgarray_t,
+    src_addr: *const sockaddr,
+    src_size: Socklen,
+    dest_addr: *const sockaddr,
+    dest_size: Socklen,
+) -> c_int {
+    trace!("demi_pushto_from()");
+
+    // Check for invalid storage location.
+    if qtok_out.is_null() {
+        warn!("demi_pushto_from() qtok_out is a null pointer");
+        return libc::EINVAL;
+    }
+
+    // Check if scatter-gather array is invalid.
+    if sga.is_null() {
+        return libc::EINVAL;
+    }
+
+    // Check if socket addresses are invalid.
+    if src_addr.is_null() || dest_addr.is_null() {
+        return libc::EINVAL;
+    }
+
+    let sga: &demi_sgarray_t = unsafe { &*sga };
+
+    // Get source address. Only its IPv4 address is used.
+    let from: Ipv4Addr = match sockaddr_to_socketaddr(src_addr, src_size) {
+        Ok(SocketAddr::V4(from)) => *from.ip(),
+        Ok(SocketAddr::V6(_)) => {
+            trace!("demi_pushto_from() failed: source is not an IPv4 address");
+            return libc::EAFNOSUPPORT;
+        },
+        Err(e) => {
+            trace!("demi_pushto_from() failed: {:?}", e);
+            return e.errno;
+        },
+    };
+
+    // Get destination address.
+    let endpoint: SocketAddr = match sockaddr_to_socketaddr(dest_addr, dest_size) {
+        Ok(endpoint) => endpoint,
+        Err(e) => {
+            trace!("demi_pushto_from() failed: {:?}", e);
+            return e.errno;
+        },
+    };
+
+    let ret: Result<i32, Fail> = do_syscall(|libos| match libos.pushto_from(sockqd.into(), sga, from, endpoint) {
+        Ok(qt) => {
+            unsafe { *qtok_out = qt.into() };
+            0
+        },
+        Err(e) => {
+            trace!("demi_pushto_from() failed: {:?}", e);
+            e.errno
+        },
+    });
+
+    match ret {
+        Ok(ret) => ret,
+        Err(e) => e.errno,
+    }
+}
+
 //======================================================================================================================
 // push
 //======================================================================================================================
@@ -753,18 +844,39 @@ pub extern "C" fn demi_sgafree(sga: *mut demi_sgarray_t) -> c_int {
 // getsockname
 //======================================================================================================================
 
-#[allow(unused)]
 #[no_mangle]
 pub extern "C" fn demi_getsockname(qd: c_int, saddr: *mut sockaddr, size: *mut Socklen) -> c_int {
-    // TODO: Implement this system call.
-    libc::ENOSYS
+    trace!("demi_getsockname()");
+
+    // Check if socket address is invalid.
+    if saddr.is_null() || size.is_null() {
+        return libc::EINVAL;
+    }
+
+    // Issue getsockname operation.
+    let ret: Result<Result<SocketAddr, Fail>, Fail> = do_syscall(|libos| libos.getsockname(qd.into()));
+    let local: SockAddr = match ret {
+        Ok(Ok(local)) => local.into(),
+        Ok(Err(e)) | Err(e) => {
+            trace!("demi_getsockname() failed: {:?}", e);
+            return e.errno;
+        },
+    };
+
+    // Copy as much of the address as fits, and report its full length.
+    unsafe {
+        let len: usize = (*size as usize).min(local.len() as usize);
+        ptr::copy_nonoverlapping::<u8>(local.as_ptr().cast(), saddr.cast(), len);
+        *size = local.len() as Socklen;
+    }
+
+    0
 }
 
 //======================================================================================================================
 // setsockopt
 //======================================================================================================================
 
-#[allow(unused)]
 #[no_mangle]
 pub extern "C" fn demi_setsockopt(
     qd: c_int,
@@ -773,15 +885,37 @@ pub extern "C" fn demi_setsockopt(
     optval: *const c_void,
     optlen: Socklen,
 ) -> c_int {
-    // TODO: Implement this system call.
-    libc::ENOSYS
+    trace!(
+        "demi_setsockopt() qd={:?}, level={:?}, optname={:?}",
+        qd,
+        level,
+        optname
+    );
+
+    // Convert the option to its typed representation.
+    let option: SocketOption = match sockopt_from_raw(level, optname, optval, optlen) {
+        Ok(option) => option,
+        Err(e) => {
+            trace!("demi_setsockopt() failed: {:?}", e);
+            return e.errno;
+        },
+    };
+
+    // Issue setsockopt operation.
+    let ret: Result<Result<(), Fail>, Fail> = do_syscall(|libos| libos.set_socket_option(qd.into(), option));
+    match ret {
+        Ok(Ok(())) => 0,
+        Ok(Err(e)) | Err(e) => {
+            trace!("demi_setsockopt() failed: {:?}", e);
+            e.errno
+        },
+    }
 }
 
 //======================================================================================================================
 // getsockopt
 //======================================================================================================================
 
-#[allow(unused)]
 #[no_mangle]
 pub extern "C" fn demi_getsockopt(
     qd: c_int,
@@ -790,8 +924,43 @@ pub extern "C" fn demi_getsockopt(
     optval: *mut c_void,
     optlen: *mut Socklen,
 ) -> c_int {
-    // TODO: Implement this system call.
-    libc::ENOSYS
+    trace!(
+        "demi_getsockopt() qd={:?}, level={:?}, optname={:?}",
+        qd,
+        level,
+        optname
+    );
+
+    // Check for invalid storage locations.
+    if optval.is_null() || optlen.is_null() {
+        return libc::EINVAL;
+    }
+
+    let name: SocketOptionName = match sockopt_name_from_raw(level, optname) {
+        Ok(name) => name,
+        Err(e) => {
+            trace!("demi_getsockopt() failed: {:?}", e);
+            return e.errno;
+        },
+    };
+
+    // Issue getsockopt operation.
+    let ret: Result<Result<SocketOption, Fail>, Fail> = do_syscall(|libos| libos.get_socket_option(qd.into(), name));
+    let option: SocketOption = match ret {
+        Ok(Ok(option)) => option,
+        Ok(Err(e)) | Err(e) => {
+            trace!("demi_getsockopt() failed: {:?}", e);
+            return e.errno;
+        },
+    };
+
+    match sockopt_to_raw(option, optval, optlen) {
+        Ok(()) => 0,
+        Err(e) => {
+            trace!("demi_getsockopt() failed: {:?}", e);
+            e.errno
+        },
+    }
 }
 
 //======================================================================================================================
@@ -809,6 +978,83 @@ fn do_syscall<T>(f: impl FnOnce(&mut LibOS) -> T) -> Result<T, Fail> {
     }
 }
 
+/// Converts a (level, optname) pair into a [SocketOptionName].
+fn sockopt_name_from_raw(level: c_int, optname: c_int) -> Result<SocketOptionName, Fail> {
+    match (level, optname) {
+        (IPPROTO_TCP, TCP_NODELAY) => Ok(SocketOptionName::NoDelay),
+        (SOL_SOCKET, SO_BROADCAST) => Ok(SocketOptionName::Broadcast),
+        (SOL_SOCKET, SO_RCVLOWAT) => Ok(SocketOptionName::RcvLowat),
+        (SOL_SOCKET, SO_RCVBUF) => Ok(SocketOptionName::RcvBuf),
+        (SOL_SOCKET, SO_SNDBUF) => Ok(SocketOptionName::SndBuf),
+        (SOL_SOCKET, SO_ACCEPTCONN) => Ok(SocketOptionName::AcceptConn),
+        (SOL_SOCKET, SO_DONTROUTE) => Ok(SocketOptionName::DontRoute),
+        (SOL_SOCKET, SO_REUSEADDR) => Ok(SocketOptionName::ReuseAddr),
+        (IPPROTO_IP, IP_PKTINFO) => Ok(SocketOptionName::PktInfo),
+        _ => Err(Fail::new(libc::ENOPROTOOPT, "unsupported socket option")),
+    }
+}
+
+/// Converts a (level, optname, optval, optlen) quadruple into a [SocketOption].
+fn sockopt_from_raw(
+    level: c_int,
+    optname: c_int,
+    optval: *const c_void,
+    optlen: Socklen,
+) -> Result<SocketOption, Fail> {
+    let name: SocketOptionName = sockopt_name_from_raw(level, optname)?;
+
+    // All supported options are passed as an int, which is non-zero for flags that are set.
+    if optval.is_null() || (optlen as usize) < mem::size_of::<c_int>() {
+        return Err(Fail::new(libc::EINVAL, "bad socket option length"));
+    }
+    let value: c_int = unsafe { ptr::read_unaligned(optval.cast::<c_int>()) };
+
+    Ok(match name {
+        SocketOptionName::NoDelay => SocketOption::NoDelay(value != 0),
+        SocketOptionName::Broadcast => SocketOption::Broadcast(value != 0),
+        SocketOptionName::AcceptConn => SocketOption::AcceptConn(value != 0),
+        SocketOptionName::PktInfo => SocketOption::PktInfo(value != 0),
+        SocketOptionName::DontRoute => SocketOption::DontRoute(value != 0),
+        SocketOptionName::ReuseAddr => SocketOption::ReuseAddr(value != 0),
+        SocketOptionName::RcvLowat => match usize::try_from(value) {
+            Ok(low_watermark) => SocketOption::RcvLowat(low_watermark),
+            Err(_) => return Err(Fail::new(libc::EINVAL, "negative receive low watermark")),
+        },
+        SocketOptionName::RcvBuf => match usize::try_from(value) {
+            Ok(size) => SocketOption::RcvBuf(size),
+            Err(_) => return Err(Fail::new(libc::EINVAL, "negative receive buffer size")),
+        },
+        SocketOptionName::SndBuf => match usize::try_from(value) {
+            Ok(size) => SocketOption::SndBuf(size),
+            Err(_) => return Err(Fail::new(libc::EINVAL, "negative send buffer size")),
+        },
+    })
+}
+
+/// Converts a [SocketOption] into the value that getsockopt() stores in `optval`, and stores its length in `optlen`,
+/// which holds the size of `optval` on entry.
+fn sockopt_to_raw(option: SocketOption, optval: *mut c_void, optlen: *mut Socklen) -> Result<(), Fail> {
+    let value: c_int = match option {
+        SocketOption::NoDelay(value)
+        | SocketOption::Broadcast(value)
+        | SocketOption::AcceptConn(value)
+        | SocketOption::PktInfo(value)
+        | SocketOption::DontRoute(value)
+        | SocketOption::ReuseAddr(value) => value as c_int,
+        SocketOption::RcvLowat(low_watermark) => c_int::try_from(low_watermark).unwrap_or(c_int::MAX),
+        SocketOption::RcvBuf(size) | SocketOption::SndBuf(size) => c_int::try_from(size).unwrap_or(c_int::MAX),
+    };
+    let len: usize = mem::size_of::<c_int>();
+    unsafe {
+        if (*optlen as usize) < len {
+            return Err(Fail::new(libc::EINVAL, "bad socket option length"));
+        }
+        ptr::write_unaligned(optval.cast::<c_int>(), value);
+        *optlen = len as Socklen;
+    }
+    Ok(())
+}
+
 /// Converts a [sockaddr] into a [SocketAddr].
 fn sockaddr_to_socketaddr(saddr: *const sockaddr, size: Socklen) -> Result<SocketAddr, Fail> {
     let check_name_len = |len: usize, exact: bool| {
@@ -907,3 +1153,105 @@ fn test_sockaddr_to_socketaddr_failure() {
         _ => panic!("expected sockaddr_to_socketaddr to fail with ENOTSUP"),
     };
 }
+
+#[test]
+fn test_sockopt_tcp_nodelay() {
+    use crate::{
+        inetstack::{
+            test_helpers::{
+                self,
+                SharedTestRuntime,
+            },
+            SharedInetStack,
+        },
+        pal::constants::{
+            AF_INET_VALUE,
+            SOCK_STREAM,
+        },
+        runtime::{
+            network::config::{
+                ArpConfig,
+                Ethernet2Config,
+                PcapConfig,
+                RoutingConfig,
+                TcpConfig,
+                UdpConfig,
+            },
+            QDesc,
+            SharedBox,
+        },
+    };
+    use ::std::time::Instant;
+
+    let test_rig: SharedTestRuntime = SharedTestRuntime::new(
+        Instant::now(),
+        ArpConfig::default(),
+        UdpConfig::default(),
+        TcpConfig::default(),
+        test_helpers::ALICE_MAC,
+        test_helpers::ALICE_IPV4,
+    );
+    let mut stack: SharedInetStack = SharedInetStack::new(
+        test_rig.get_runtime(),
+        SharedBox::new(Box::new(test_rig.clone())),
+        test_helpers::ALICE_MAC,
+        test_helpers::ALICE_IPV4,
+        UdpConfig::default(),
+        TcpConfig::default(),
+        [0; 32],
+        ArpConfig::default(),
+        PcapConfig::default(),
+        Ethernet2Config::default(),
+        RoutingConfig::default(),
+    )
+    .expect("stack should be created");
+    let qd: QDesc = stack
+        .socket(AF_INET_VALUE, SOCK_STREAM, 0)
+        .expect("socket should be created");
+
+    // Set TCP_NODELAY as a C application would.
+    let enable: c_int = 1;
+    let option: SocketOption = sockopt_from_raw(
+        IPPROTO_TCP,
+        TCP_NODELAY,
+        ptr::addr_of!(enable).cast(),
+        mem::size_of::<c_int>() as Socklen,
+    )
+    .expect("option should be converted");
+    assert_eq!(option, SocketOption::NoDelay(true));
+    stack.set_socket_option(qd, option).expect("option should be set");
+
+    // Read it back as a C application would.
+    let option: SocketOption = stack
+        .get_socket_option(
+            qd,
+            sockopt_name_from_raw(IPPROTO_TCP, TCP_NODELAY).expect("option should be supported"),
+        )
+        .expect("option should be read");
+    let mut value: c_int = 0;
+    let mut len: Socklen = mem::size_of::<c_int>() as Socklen;
+    sockopt_to_raw(option, ptr::addr_of_mut!(value).cast(), &mut len).expect("option should be converted");
+    assert_eq!(value, 1);
+    assert_eq!(len as usize, mem::size_of::<c_int>());
+
+    // Options that a TCP socket does not support, and options that are unknown, fail with ENOPROTOOPT.
+    match stack.set_socket_option(qd, SocketOption::Broadcast(true)) {
+        Err(e) if e.errno == libc::ENOPROTOOPT => (),
+        _ => panic!("expected SO_BROADCAST to fail with ENOPROTOOPT on a TCP socket"),
+    };
+    match sockopt_from_raw(
+        IPPROTO_TCP,
+        -1,
+        ptr::addr_of!(enable).cast(),
+        mem::size_of::<c_int>() as Socklen,
+    ) {
+        Err(e) if e.errno == libc::ENOPROTOOPT => (),
+        _ => panic!("expected an unknown option to fail with ENOPROTOOPT"),
+    };
+
+    // Values that are too short to hold an int are rejected.
+    match sockopt_from_raw(IPPROTO_TCP, TCP_NODELAY, ptr::addr_of!(enable).cast(), 1) {
+        Err(e) if e.errno == libc::EINVAL => (),
+        _ => panic!("expected a short option value to fail with EINVAL"),
+    };
+}
@@ -5,9 +5,17 @@
 // Imports
 //======================================================================================================================
 
+use crate::runtime::{
+    fail::Fail,
+    network::config::{
+        EphemeralPortConfig,
+        EphemeralPortExhaustion,
+    },
+};
 use ::std::{
     fs::File,
     io::Read,
+    time::Duration,
 };
 use ::yaml_rust::{
     Yaml,
@@ -60,4 +68,288 @@ impl Config {
         }
         local_ipv4_addr
     }
+
+    /// Reads the "LOCAL_LINK_ADDR" environment variable, which overrides the local link address at startup.
+    #[cfg(any(feature = "catnip-libos", feature = "catpowder-libos"))]
+    pub fn local_link_addr_override(&self) -> Result<Option<crate::runtime::network::types::MacAddress>, Fail> {
+        use crate::runtime::network::types::MacAddress;
+
+        match ::std::env::var("LOCAL_LINK_ADDR") {
+            Ok(addr) => match MacAddress::parse_str(&addr) {
+                Ok(addr) => Ok(Some(addr)),
+                Err(_) => {
+                    let cause: String = format!("invalid value for LOCAL_LINK_ADDR (value={:?})", addr);
+                    error!("local_link_addr_override(): {}", &cause);
+                    Err(Fail::new(libc::EINVAL, &cause))
+                },
+            },
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Reads the "LOCAL_IPV4_ADDR" environment variable, which overrides the local IPv4 address at startup.
+    #[cfg(any(feature = "catnip-libos", feature = "catpowder-libos"))]
+    pub fn local_ipv4_addr_override(&self) -> Result<Option<::std::net::Ipv4Addr>, Fail> {
+        match ::std::env::var("LOCAL_IPV4_ADDR") {
+            Ok(addr) => match addr.parse::<::std::net::Ipv4Addr>() {
+                Ok(addr) => Ok(Some(addr)),
+                Err(_) => {
+                    let cause: String = format!("invalid value for LOCAL_IPV4_ADDR (value={:?})", addr);
+                    error!("local_ipv4_addr_override(): {}", &cause);
+                    Err(Fail::new(libc::EINVAL, &cause))
+                },
+            },
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Reads the packet capture parameters from the underlying configuration file. Packet capture is disabled unless
+    /// a capture file is given.
+    #[cfg(any(feature = "catnip-libos", feature = "catpowder-libos"))]
+    pub fn pcap_config(&self) -> Result<crate::runtime::network::config::PcapConfig, Fail> {
+        use crate::runtime::network::config::PcapConfig;
+
+        // FIXME: Change the follow key from "catnip" to "demikernel".
+        let path: Option<String> = self.0["catnip"]["pcap_file"].as_str().map(|path| path.to_string());
+        let max_size: Option<usize> = match self.0["catnip"]["pcap_max_size"].as_i64() {
+            None => None,
+            Some(max_size) => match usize::try_from(max_size) {
+                Ok(max_size) => Some(max_size),
+                Err(_) => {
+                    let cause: String = format!("invalid value for pcap_max_size (value={:?})", max_size);
+                    error!("pcap_config(): {}", &cause);
+                    return Err(Fail::new(libc::EINVAL, &cause));
+                },
+            },
+        };
+        Ok(PcapConfig::new(path, max_size))
+    }
+
+    /// Reads the Ethernet parameters from the underlying configuration file. Outgoing frames are padded to the minimum
+    /// frame size and left untagged unless told otherwise.
+    #[cfg(any(feature = "catnip-libos", feature = "catpowder-libos"))]
+    pub fn ethernet2_config(&self) -> Result<crate::runtime::network::config::Ethernet2Config, Fail> {
+        use crate::runtime::network::config::Ethernet2Config;
+
+        // FIXME: Change the follow key from "catnip" to "demikernel".
+        let pad_frames: Option<bool> = self.0["catnip"]["pad_frames"].as_bool();
+        let vlan_id: Option<u16> = match self.0["catnip"]["vlan_id"].as_i64() {
+            None => None,
+            Some(vlan_id) => match u16::try_from(vlan_id) {
+                Ok(vlan_id) => Some(vlan_id),
+                Err(_) => {
+                    let cause: String = format!("invalid value for vlan_id (value={:?})", vlan_id);
+                    error!("ethernet2_config(): {}", &cause);
+                    return Err(Fail::new(libc::EINVAL, &cause));
+                },
+            },
+        };
+        Ethernet2Config::new(pad_frames, vlan_id)
+    }
+
+    /// Reads the routing parameters from the underlying configuration file. All destinations are on the local subnet
+    /// unless told otherwise.
+    #[cfg(any(feature = "catnip-libos", feature = "catpowder-libos"))]
+    pub fn routing_config(&self) -> Result<crate::runtime::network::config::RoutingConfig, Fail> {
+        use crate::runtime::network::config::{
+            Ipv4Route,
+            RoutingConfig,
+        };
+        use ::std::net::Ipv4Addr;
+
+        let section: &Yaml = &self.0["demikernel"]["routing"];
+        let subnet_prefix_len: Option<u8> = match section["subnet_prefix_len"].as_i64() {
+            None => None,
+            Some(prefix_len) => match u8::try_from(prefix_len) {
+                Ok(prefix_len) => Some(prefix_len),
+                Err(_) => return Err(Self::invalid_routing_value("subnet_prefix_len", &prefix_len)),
+            },
+        };
+        let default_gateway: Option<Ipv4Addr> = match section["default_gateway"].as_str() {
+            None => None,
+            Some(addr) => match addr.parse::<Ipv4Addr>() {
+                Ok(addr) => Some(addr),
+                Err(_) => return Err(Self::invalid_routing_value("default_gateway", &addr)),
+            },
+        };
+        let routes: Option<Vec<Ipv4Route>> = match section["routes"].as_vec() {
+            None => None,
+            Some(routes) => {
+                let mut parsed: Vec<Ipv4Route> = Vec::with_capacity(routes.len());
+                for route in routes {
+                    let prefix: Option<(Ipv4Addr, u8)> = route["destination"]
+                        .as_str()
+                        .and_then(|prefix| prefix.split_once('/'))
+                        .and_then(|(destination, prefix_len)| {
+                            Some((destination.parse().ok()?, prefix_len.parse().ok()?))
+                        });
+                    let next_hop: Option<Ipv4Addr> = route["next_hop"].as_str().and_then(|addr| addr.parse().ok());
+                    match (prefix, next_hop) {
+                        (Some((destination, prefix_len)), Some(next_hop)) => {
+                            parsed.push(Ipv4Route::new(destination, prefix_len, next_hop)?)
+                        },
+                        _ => return Err(Self::invalid_routing_value("routes", route)),
+                    }
+                }
+                Some(parsed)
+            },
+        };
+        RoutingConfig::new(subnet_prefix_len, default_gateway, routes)
+    }
+
+    /// Builds the failure for an invalid `value` of the routing parameter `key`.
+    #[cfg(any(feature = "catnip-libos", feature = "catpowder-libos"))]
+    fn invalid_routing_value(key: &str, value: &dyn ::std::fmt::Debug) -> Fail {
+        let cause: String = format!("invalid value for {} (value={:?})", key, value);
+        error!("routing_config(): {}", &cause);
+        Fail::new(libc::EINVAL, &cause)
+    }
+
+    /// Reads the bounds on the receive queue of each UDP socket from the underlying configuration file. Receive queues
+    /// hold up to 1024 datagrams of any total size, and drop incoming datagrams once full, unless told otherwise.
+    #[cfg(any(feature = "catnip-libos", feature = "catpowder-libos"))]
+    pub fn udp_recv_queue_config(&self) -> Result<crate::runtime::network::config::UdpRecvQueueConfig, Fail> {
+        use crate::runtime::network::config::{
+            UdpDropPolicy,
+            UdpRecvQueueConfig,
+        };
+
+        let section: &Yaml = &self.0["demikernel"]["udp_recv_queue"];
+        let max_datagrams: Option<usize> = Self::get_limit(section, "max_datagrams")?;
+        let max_bytes: Option<usize> = Self::get_limit(section, "max_bytes")?;
+        let drop_policy: Option<UdpDropPolicy> = match section["drop_policy"].as_str() {
+            None => None,
+            Some("newest") => Some(UdpDropPolicy::Newest),
+            Some("oldest") => Some(UdpDropPolicy::Oldest),
+            Some(value) => {
+                let cause: String = format!("invalid value for drop_policy (value={:?})", value);
+                error!("udp_recv_queue_config(): {}", &cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            },
+        };
+        UdpRecvQueueConfig::new(max_datagrams, max_bytes, drop_policy)
+    }
+
+    /// Reads the maximum number of datagrams that Catnap reads from a UDP socket with a single system call. Datagrams
+    /// are read one at a time unless told otherwise.
+    pub fn recv_batch_size(&self) -> Result<usize, Fail> {
+        self.get_batch_size("recv_batch_size")
+    }
+
+    /// Reads the maximum number of datagrams that Catnap writes to a UDP socket with a single system call. Datagrams
+    /// are written one at a time unless told otherwise.
+    pub fn send_batch_size(&self) -> Result<usize, Fail> {
+        self.get_batch_size("send_batch_size")
+    }
+
+    /// Reads the ephemeral port parameters from the underlying configuration file. Missing parameters take their
+    /// default values.
+    pub fn ephemeral_port_config(&self) -> Result<EphemeralPortConfig, Fail> {
+        let section: &Yaml = &self.0["demikernel"]["ephemeral_ports"];
+        let first_port: Option<u16> = Self::get_port(section, "first")?;
+        let last_port: Option<u16> = Self::get_port(section, "last")?;
+        let exhaustion: Option<EphemeralPortExhaustion> = match section["on_exhaustion"].as_str() {
+            None => None,
+            Some("fail") => Some(EphemeralPortExhaustion::Fail),
+            Some("wait") => {
+                let timeout: u64 = match section["wait_timeout_millis"].as_i64() {
+                    Some(timeout) if timeout >= 0 => timeout as u64,
+                    _ => {
+                        let cause: String = format!("missing or invalid value for wait_timeout_millis");
+                        error!("ephemeral_port_config(): {}", &cause);
+                        return Err(Fail::new(libc::EINVAL, &cause));
+                    },
+                };
+                Some(EphemeralPortExhaustion::Wait(Duration::from_millis(timeout)))
+            },
+            Some(value) => {
+                let cause: String = format!("invalid value for on_exhaustion (value={:?})", value);
+                error!("ephemeral_port_config(): {}", &cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            },
+        };
+        EphemeralPortConfig::new(first_port, last_port, exhaustion)
+    }
+
+    /// Reads the maximum number of queues that may be open at the same time from the underlying configuration file.
+    /// The number of open queues is not limited unless told otherwise.
+    pub fn max_queues(&self) -> Result<Option<usize>, Fail> {
+        match self.0["demikernel"]["max_queues"].as_i64() {
+            None => Ok(None),
+            Some(max_queues) if max_queues >= 1 => Ok(Some(max_queues as usize)),
+            Some(max_queues) => {
+                let cause: String = format!("invalid value for max_queues (max_queues={:?})", max_queues);
+                error!("max_queues(): {}", &cause);
+                Err(Fail::new(libc::EINVAL, &cause))
+            },
+        }
+    }
+
+    /// Reads the maximum number of buffers that are kept for reuse by pops once the application frees them from the
+    /// underlying configuration file. Defaults to 64 buffers, and zero disables the buffer pool.
+    pub fn pop_buffer_pool_size(&self) -> Result<usize, Fail> {
+        match self.0["demikernel"]["pop_buffer_pool_size"].as_i64() {
+            None => Ok(64),
+            Some(size) if size >= 0 => Ok(size as usize),
+            Some(size) => {
+                let cause: String = format!("invalid value for pop_buffer_pool_size (size={:?})", size);
+                error!("pop_buffer_pool_size(): {}", &cause);
+                Err(Fail::new(libc::EINVAL, &cause))
+            },
+        }
+    }
+
+    /// Reads how long a LibOS that is going away waits for its queues to close from the underlying configuration file.
+    /// Queues that are still open past this timeout are abandoned. Defaults to one second.
+    pub fn close_timeout(&self) -> Result<Duration, Fail> {
+        match self.0["demikernel"]["close_timeout_millis"].as_i64() {
+            None => Ok(Duration::from_secs(1)),
+            Some(timeout) if timeout >= 0 => Ok(Duration::from_millis(timeout as u64)),
+            Some(timeout) => {
+                let cause: String = format!("invalid value for close_timeout_millis (timeout={:?})", timeout);
+                error!("close_timeout(): {}", &cause);
+                Err(Fail::new(libc::EINVAL, &cause))
+            },
+        }
+    }
+
+    /// Reads the Catnap batch size named `key`, which defaults to a single datagram.
+    fn get_batch_size(&self, key: &str) -> Result<usize, Fail> {
+        match self.0["catnap"][key].as_i64() {
+            None => Ok(1),
+            Some(size) if size >= 1 => Ok(size as usize),
+            Some(size) => {
+                let cause: String = format!("{} should be a positive integer (size={:?})", key, size);
+                error!("get_batch_size(): {}", &cause);
+                Err(Fail::new(libc::EINVAL, &cause))
+            },
+        }
+    }
+
+    /// Reads the port number named `key` in `section`, if present.
+    fn get_port(section: &Yaml, key: &str) -> Result<Option<u16>, Fail> {
+        match section[key].as_i64() {
+            None => Ok(None),
+            Some(port) if port >= 1 && port <= u16::MAX as i64 => Ok(Some(port as u16)),
+            Some(port) => {
+                let cause: String = format!("invalid port number for {} (port={:?})", key, port);
+                error!("get_port(): {}", &cause);
+                Err(Fail::new(libc::EINVAL, &cause))
+            },
+        }
+    }
+
+    /// Reads the positive limit named `key` in `section`, if present.
+    #[cfg(any(feature = "catnip-libos", feature = "catpowder-libos"))]
+    fn get_limit(section: &Yaml, key: &str) -> Result<Option<usize>, Fail> {
+        match section[key].as_i64() {
+            None => Ok(None),
+            Some(limit) if limit >= 1 => Ok(Some(limit as usize)),
+            Some(limit) => {
+                let cause: String = format!("invalid value for {} (limit={:?})", key, limit);
+                error!("get_limit(): {}", &cause);
+                Err(Fail::new(libc::EINVAL, &cause))
+            },
+        }
+    }
 }
@@ -15,6 +15,7 @@ use crate::runtime::{
     QDesc,
     QToken,
 };
+use ::std::time::Duration;
 
 #[cfg(feature = "catmem-libos")]
 use crate::{
@@ -122,6 +123,26 @@ impl MemoryLibOS {
         }
     }
 
+    /// Gets the runtime on top of which the target LibOS runs.
+    #[allow(unreachable_patterns)]
+    pub fn get_runtime(&self) -> SharedDemiRuntime {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem { runtime, libos: _ } => runtime.clone(),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
+    /// Waits for any of the operations of `qts` to complete or `timeout` to expire.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn wait_any(&mut self, qts: &[QToken], timeout: Option<Duration>) -> Result<(usize, TaskHandle), Fail> {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem { runtime, libos: _ } => runtime.wait_any(qts, timeout),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
     #[allow(unreachable_patterns, unused_variables)]
     pub fn pack_result(&mut self, handle: TaskHandle, qt: QToken) -> Result<demi_qresult_t, Fail> {
         match self {
@@ -131,12 +152,22 @@ impl MemoryLibOS {
         }
     }
 
-    /// Waits for any operation in an I/O queue.
+    /// Polls the scheduler at most `max_iterations` times and returns the number of tasks that were polled.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn poll(&mut self, max_iterations: usize) -> usize {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem { runtime, libos: _ } => runtime.poll_bounded(max_iterations),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
+    /// Checks whether the underlying runtime is quiescent.
     #[allow(unreachable_patterns, unused_variables)]
-    pub fn poll(&mut self) {
+    pub fn is_quiescent(&self) -> bool {
         match self {
             #[cfg(feature = "catmem-libos")]
-            MemoryLibOS::Catmem { runtime, libos: _ } => runtime.poll(),
+            MemoryLibOS::Catmem { runtime, libos: _ } => runtime.is_quiescent(),
             _ => unreachable!("unknown memory libos"),
         }
     }
@@ -22,8 +22,14 @@ use crate::{
         fail::Fail,
         limits,
         logging,
+        memory::sgarray_capacity,
+        network::types::{
+            SocketOption,
+            SocketOptionName,
+        },
         scheduler::TaskHandle,
         types::{
+            demi_opcode_t,
             demi_qresult_t,
             demi_sgarray_t,
         },
@@ -34,14 +40,19 @@ use crate::{
 };
 use ::std::{
     env,
-    net::SocketAddr,
+    net::{
+        Ipv4Addr,
+        SocketAddr,
+    },
     time::{
         Duration,
-        Instant,
         SystemTime,
     },
 };
 
+#[cfg(target_os = "linux")]
+use ::std::os::fd::RawFd;
+
 #[cfg(feature = "catcollar-libos")]
 use crate::catcollar::CatcollarLibOS;
 #[cfg(feature = "catloop-libos")]
@@ -83,7 +94,28 @@ impl LibOS {
 
         logging::initialize();
 
-        // Read in configuration file.
+        let config: Config = Self::read_config()?;
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        runtime.set_ephemeral_port_config(config.ephemeral_port_config()?)?;
+        runtime.set_max_queues(config.max_queues()?);
+        runtime.set_buffer_pool_size(config.pop_buffer_pool_size()?);
+        Self::new_with_config(libos_name, &config, runtime)
+    }
+
+    /// Instantiates a new LibOS on top of the runtime of an existing one. This lets an application use several
+    /// LibOSes side by side, for instance kernel sockets alongside inetstack sockets, choosing one for each queue when
+    /// creating it. Each LibOS only operates on the queues that it created, and fails with `EINVAL` on the others.
+    /// All of them share queue tokens, so waiting on any of them drives the operations of all of them.
+    pub fn new_with_runtime(libos_name: LibOSName, runtime: SharedDemiRuntime) -> Result<Self, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("demikernel::new_with_runtime");
+
+        let config: Config = Self::read_config()?;
+        Self::new_with_config(libos_name, &config, runtime)
+    }
+
+    /// Reads in the configuration file.
+    fn read_config() -> Result<Config, Fail> {
         let config_path: String = match env::var("CONFIG_PATH") {
             Ok(config_path) => config_path,
             Err(_) => {
@@ -93,40 +125,43 @@ impl LibOS {
                 ))
             },
         };
-        let config: Config = Config::new(config_path);
-        let runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        Ok(Config::new(config_path))
+    }
+
+    /// Instantiates a new LibOS with the configuration `config` on top of `runtime`.
+    fn new_with_config(libos_name: LibOSName, config: &Config, runtime: SharedDemiRuntime) -> Result<Self, Fail> {
         // Instantiate LibOS.
         #[allow(unreachable_patterns)]
         let libos: LibOS = match libos_name {
             #[cfg(all(feature = "catnap-libos"))]
             LibOSName::Catnap => Self::NetworkLibOS(NetworkLibOS::Catnap {
                 runtime: runtime.clone(),
-                libos: SharedNetworkLibOS::<SharedCatnapTransport>::new(&config, runtime.clone()),
+                libos: SharedNetworkLibOS::<SharedCatnapTransport>::new(config, runtime.clone())?,
             }),
             #[cfg(feature = "catcollar-libos")]
             LibOSName::Catcollar => Self::NetworkLibOS(NetworkLibOS::Catcollar {
                 runtime: runtime.clone(),
-                libos: CatcollarLibOS::new(&config, runtime.clone()),
+                libos: CatcollarLibOS::new(config, runtime.clone()),
             }),
             #[cfg(feature = "catpowder-libos")]
             LibOSName::Catpowder => Self::NetworkLibOS(NetworkLibOS::Catpowder {
                 runtime: runtime.clone(),
-                libos: CatpowderLibOS::new(&config, runtime.clone()),
+                libos: CatpowderLibOS::new(config, runtime.clone())?,
             }),
             #[cfg(feature = "catnip-libos")]
             LibOSName::Catnip => Self::NetworkLibOS(NetworkLibOS::Catnip {
                 runtime: runtime.clone(),
-                libos: CatnipLibOS::new(&config, runtime.clone()),
+                libos: CatnipLibOS::new(config, runtime.clone())?,
             }),
             #[cfg(feature = "catmem-libos")]
             LibOSName::Catmem => Self::MemoryLibOS(MemoryLibOS::Catmem {
                 runtime: runtime.clone(),
-                libos: SharedCatmemLibOS::new(&config, runtime.clone()),
+                libos: SharedCatmemLibOS::new(config, runtime.clone()),
             }),
             #[cfg(feature = "catloop-libos")]
             LibOSName::Catloop => Self::NetworkLibOS(NetworkLibOS::Catloop {
                 runtime: runtime.clone(),
-                libos: SharedCatloopLibOS::new(&config, runtime.clone()),
+                libos: SharedCatloopLibOS::new(config, runtime.clone()),
             }),
             _ => panic!("unsupported libos"),
         };
@@ -134,6 +169,14 @@ impl LibOS {
         Ok(libos)
     }
 
+    /// Gets the runtime of the target LibOS, so that other LibOSes can be instantiated on top of it.
+    pub fn get_runtime(&self) -> SharedDemiRuntime {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.get_runtime(),
+            LibOS::MemoryLibOS(libos) => libos.get_runtime(),
+        }
+    }
+
     /// Creates a new memory queue and connect to consumer end.
     pub fn create_pipe(&mut self, name: &str) -> Result<QDesc, Fail> {
         let result: Result<QDesc, Fail> = {
@@ -148,7 +191,7 @@ impl LibOS {
             }
         };
 
-        self.poll();
+        self.poll(1);
 
         result
     }
@@ -167,7 +210,7 @@ impl LibOS {
             }
         };
 
-        self.poll();
+        self.poll(1);
 
         result
     }
@@ -188,7 +231,7 @@ impl LibOS {
             }
         };
 
-        self.poll();
+        self.poll(1);
 
         result
     }
@@ -204,7 +247,7 @@ impl LibOS {
             }
         };
 
-        self.poll();
+        self.poll(1);
 
         result
     }
@@ -220,11 +263,197 @@ impl LibOS {
             }
         };
 
-        self.poll();
+        self.poll(1);
+
+        result
+    }
+
+    /// Changes the maximum length of the queue of pending connections of a passive socket, without recreating it.
+    pub fn set_backlog(&mut self, sockqd: QDesc, backlog: usize) -> Result<(), Fail> {
+        let result: Result<(), Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::set_backlog");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.set_backlog(sockqd, backlog),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "set_backlog() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll(1);
+
+        result
+    }
+
+    /// Sets the idle timeout of a connected TCP socket. Once no data has been sent or received on the connection for
+    /// `timeout`, it is gracefully closed and any pending operation on it fails with `ETIMEDOUT`. Unlike keepalive,
+    /// which probes an idle peer to detect that it is gone, the idle timeout closes connections that are healthy but
+    /// unused. Passing `None` disables the idle timeout, which is the default.
+    pub fn set_idle_timeout(&mut self, sockqd: QDesc, timeout: Option<Duration>) -> Result<(), Fail> {
+        let result: Result<(), Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::set_idle_timeout");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.set_idle_timeout(sockqd, timeout),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "set_idle_timeout() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll(1);
 
         result
     }
 
+    /// Sets the transmit priority of a connected TCP socket, much like `SO_PRIORITY`. When connections contend for the
+    /// network, segments of higher priority connections are sent ahead of those of lower priority ones, so that a
+    /// latency-sensitive flow is not stuck behind a bulk transfer. All connections have priority 0 by default.
+    pub fn set_tx_priority(&mut self, sockqd: QDesc, priority: u8) -> Result<(), Fail> {
+        let result: Result<(), Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::set_tx_priority");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.set_tx_priority(sockqd, priority),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "set_tx_priority() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll(1);
+
+        result
+    }
+
+    /// Sets whether urgent data received on a connected TCP socket is left in the ordinary stream, much like
+    /// `SO_OOBINLINE`. By default, the urgent byte is removed from the stream and delivered out of band instead.
+    pub fn set_oob_inline(&mut self, sockqd: QDesc, oob_inline: bool) -> Result<(), Fail> {
+        let result: Result<(), Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::set_oob_inline");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.set_oob_inline(sockqd, oob_inline),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "set_oob_inline() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll(1);
+
+        result
+    }
+
+    /// Sets an option on a socket, as setsockopt() does. Fails with `ENOPROTOOPT` if the socket does not support the
+    /// option.
+    pub fn set_socket_option(&mut self, sockqd: QDesc, option: SocketOption) -> Result<(), Fail> {
+        let result: Result<(), Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::set_socket_option");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.set_socket_option(sockqd, option),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "set_socket_option() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll(1);
+
+        result
+    }
+
+    /// Gets the value of an option of a socket, as getsockopt() does. Fails with `ENOPROTOOPT` if the socket does not
+    /// support the option.
+    pub fn get_socket_option(&self, sockqd: QDesc, name: SocketOptionName) -> Result<SocketOption, Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.get_socket_option(sockqd, name),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "get_socket_option() is not supported on memory liboses",
+            )),
+        }
+    }
+
+    /// Takes the urgent byte that was received out of band on a connected TCP socket, much like a receive with
+    /// `MSG_OOB`. Fails with `EINVAL` if there is no such byte, or if urgent data is delivered inline.
+    pub fn recv_oob(&mut self, sockqd: QDesc) -> Result<u8, Fail> {
+        let result: Result<u8, Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::recv_oob");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.recv_oob(sockqd),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "recv_oob() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll(1);
+
+        result
+    }
+
+    /// Gets the local address of a socket. For sockets returned by accept, this is the concrete address that the
+    /// connection landed on, which lets multi-homed servers answer from the address that the request arrived on.
+    pub fn getsockname(&self, sockqd: QDesc) -> Result<SocketAddr, Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.getsockname(sockqd),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "getsockname() is not supported on memory liboses",
+            )),
+        }
+    }
+
+    /// Retrieves and clears the pending error of a socket, which is the last error that an asynchronous operation on it
+    /// failed with. This is the equivalent of getsockopt(SO_ERROR), and is mostly useful to find out why a connect
+    /// failed when its completion is not waited on. Returns `None` if there is no pending error.
+    pub fn take_socket_error(&mut self, sockqd: QDesc) -> Result<Option<Fail>, Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.take_socket_error(sockqd),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "take_socket_error() is not supported on memory liboses",
+            )),
+        }
+    }
+
+    /// Gets the file descriptor of the POSIX socket that backs a socket, so that it can be waited on by an external
+    /// event loop (e.g. added to an epoll set). Only Catnap sockets are backed by one. The file descriptor remains
+    /// owned by Demikernel: the caller must neither close it nor change its state (e.g. its options or its blocking
+    /// mode), and it becomes invalid once the socket is closed.
+    #[cfg(target_os = "linux")]
+    pub fn raw_fd(&self, sockqd: QDesc) -> Result<RawFd, Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.raw_fd(sockqd),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(libc::ENOTSUP, "raw_fd() is not supported on memory liboses")),
+        }
+    }
+
+    /// Sets whether a socket is in non-blocking mode, like FIONBIO or O_NONBLOCK do. In non-blocking mode, pops and
+    /// accepts that would block complete with EAGAIN, connects that cannot complete right away complete with
+    /// EINPROGRESS, and pushes complete as soon as their data is queued. The outcome of a connect that is in progress
+    /// can be checked with take_socket_error().
+    #[cfg(target_os = "linux")]
+    pub fn set_nonblocking(&mut self, sockqd: QDesc, nonblocking: bool) -> Result<(), Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.set_nonblocking(sockqd, nonblocking),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "set_nonblocking() is not supported on memory liboses",
+            )),
+        }
+    }
+
     /// Accepts an incoming connection on a TCP socket.
     pub fn accept(&mut self, sockqd: QDesc) -> Result<QToken, Fail> {
         let result: Result<QToken, Fail> = {
@@ -236,7 +465,7 @@ impl LibOS {
             }
         };
 
-        self.poll();
+        self.poll(1);
 
         result
     }
@@ -252,11 +481,74 @@ impl LibOS {
             }
         };
 
-        self.poll();
+        self.poll(1);
 
         result
     }
 
+    /// Resolves the link address of `dest`, or of the gateway towards it, ahead of a latency-critical connect or send.
+    /// The operation is not associated with any queue, and completes as a resolve once the link address is known.
+    pub fn resolve(&mut self, dest: Ipv4Addr) -> Result<QToken, Fail> {
+        let result: Result<QToken, Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::resolve");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.resolve(dest),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(libc::ENOTSUP, "resolve() is not supported on memory liboses")),
+            }
+        };
+
+        self.poll(1);
+
+        result
+    }
+
+    /// Connects a TCP socket to a remote endpoint, and waits for at most `timeout` for the connection to be
+    /// established. This is [connect](Self::connect) followed by [wait](Self::wait), so the runtime runs all of its
+    /// tasks in the meantime: other pending operations make progress, and may complete, but their results are left for
+    /// their own waits. If the connection is not established in time, it is abandoned by closing the socket, and this
+    /// fails with `ETIMEDOUT`.
+    pub fn connect_blocking(
+        &mut self,
+        sockqd: QDesc,
+        remote: SocketAddr,
+        timeout: Option<Duration>,
+    ) -> Result<(), Fail> {
+        trace!(
+            "connect_blocking(): qd={:?}, remote={:?}, timeout={:?}",
+            sockqd,
+            remote,
+            timeout
+        );
+
+        let qt: QToken = self.connect(sockqd, remote)?;
+        let qr: demi_qresult_t = match self.wait(qt, timeout) {
+            Ok(qr) => qr,
+            Err(e) if e.errno == libc::ETIMEDOUT => {
+                // Closing the socket cancels the connect, whose result is then taken, so that it does not linger.
+                self.close(sockqd)?;
+                self.wait(qt, None)?;
+                let cause: String = format!("connection timed out (qd={:?}, remote={:?})", sockqd, remote);
+                error!("connect_blocking(): {}", &cause);
+                return Err(Fail::new(libc::ETIMEDOUT, &cause));
+            },
+            Err(e) => return Err(e),
+        };
+        match qr.qr_opcode {
+            demi_opcode_t::DEMI_OPC_CONNECT => Ok(()),
+            demi_opcode_t::DEMI_OPC_FAILED => {
+                let cause: String = format!(
+                    "failed to establish connection (qd={:?}, remote={:?}, errno={:?})",
+                    sockqd, remote, qr.qr_ret
+                );
+                error!("connect_blocking(): {}", &cause);
+                Err(Fail::new(qr.qr_ret as i32, &cause))
+            },
+            // The token is the one of the connect, so it cannot complete with anything else.
+            _ => unreachable!("unexpected operation on connect (qt={:?})", qt),
+        }
+    }
+
     /// Closes an I/O queue.
     /// async_close() + wait() achieves the same effect as synchronous close.
     pub fn close(&mut self, qd: QDesc) -> Result<(), Fail> {
@@ -281,7 +573,7 @@ impl LibOS {
             }
         };
 
-        self.poll();
+        self.poll(1);
 
         result
     }
@@ -296,7 +588,7 @@ impl LibOS {
             }
         };
 
-        self.poll();
+        self.poll(1);
 
         result
     }
@@ -312,11 +604,63 @@ impl LibOS {
             }
         };
 
-        self.poll();
+        self.poll(1);
 
         result
     }
 
+    /// Pushes a message that is gathered from several byte slices, without building a scatter-gather array first. The
+    /// slices are copied into a single buffer and pushed as one, so the peer receives them concatenated.
+    pub fn push_vectored(&mut self, qd: QDesc, bufs: &[&[u8]]) -> Result<QToken, Fail> {
+        let result: Result<QToken, Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::push_vectored");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.push_vectored(qd, bufs),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "push_vectored() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll(1);
+
+        result
+    }
+
+    /// Pushes a scatter-gather array to a TCP socket with a hint that more data is coming. Like the MSG_MORE flag of
+    /// send(), this holds the data back instead of transmitting it right away, so that it is coalesced with the data of
+    /// the next push on the socket. Closing the socket also sends data that was held back.
+    pub fn push_more(&mut self, qd: QDesc, sga: &demi_sgarray_t) -> Result<QToken, Fail> {
+        let result: Result<QToken, Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::push_more");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.push_more(qd, sga),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "push_more() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll(1);
+
+        result
+    }
+
+    /// Transmits the data that is buffered on a TCP socket but not sent yet, as far as the send and congestion windows
+    /// allow, without closing or shutting down the socket. This sends the data held back by
+    /// [push_more](Self::push_more), as well as small pushes that Nagle's algorithm holds back while data is in flight.
+    pub fn flush(&mut self, qd: QDesc) -> Result<(), Fail> {
+        trace!("flush(): qd={:?}", qd);
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.flush(qd),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(libc::ENOTSUP, "flush() is not supported on memory liboses")),
+        }
+    }
+
     /// Pushes a scatter-gather array to a UDP socket.
     pub fn pushto(&mut self, qd: QDesc, sga: &demi_sgarray_t, to: SocketAddr) -> Result<QToken, Fail> {
         let result: Result<QToken, Fail> = {
@@ -328,12 +672,40 @@ impl LibOS {
             }
         };
 
-        self.poll();
+        self.poll(1);
+
+        result
+    }
+
+    /// Pushes a scatter-gather array to a UDP socket, from the local address `from` rather than from the address that
+    /// the socket is bound to.
+    pub fn pushto_from(
+        &mut self,
+        qd: QDesc,
+        sga: &demi_sgarray_t,
+        from: Ipv4Addr,
+        to: SocketAddr,
+    ) -> Result<QToken, Fail> {
+        let result: Result<QToken, Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::pushto_from");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.pushto_from(qd, sga, from, to),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "pushto_from() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll(1);
 
         result
     }
 
-    /// Pops data from a an I/O queue.
+    /// Pops data from a an I/O queue. On TCP sockets, an empty result denotes end of stream: the remote peer closed its
+    /// side of the connection and all data it sent before has been popped. Every later pop completes the same way, while
+    /// pushes remain allowed until the socket is closed.
     pub fn pop(&mut self, qd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
         let result: Result<QToken, Fail> = {
             #[cfg(feature = "profiler")]
@@ -355,7 +727,61 @@ impl LibOS {
             }
         };
 
-        self.poll();
+        self.poll(1);
+
+        result
+    }
+
+    /// Pops data from an I/O queue directly into the caller-provided `sga`, much like readv() does. The segments of
+    /// `sga` are filled in order and must remain valid until the operation completes. The number of bytes that were
+    /// copied is reported in the `qr_ret` field of the result, where zero means end of stream on TCP sockets.
+    pub fn pop_into(&mut self, qd: QDesc, sga: &demi_sgarray_t) -> Result<QToken, Fail> {
+        let result: Result<QToken, Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::pop_into");
+
+            // Check if the scatter-gather array can hold any data.
+            let capacity: usize = sgarray_capacity(sga)?;
+            if capacity == 0 {
+                let cause: String = format!("invalid pop size (size={:?})", capacity);
+                error!("pop_into(): {:?}", &cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            }
+
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.pop_into(qd, sga),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            }
+        };
+
+        self.poll(1);
+
+        result
+    }
+
+    /// Pops up to `size` bytes from a TCP socket, waiting at most `timeout` for all of them to arrive. When `timeout`
+    /// expires, the operation completes with whatever data arrived before it, rather than failing. If no data arrived
+    /// at all, the operation fails with ETIMEDOUT instead of completing with an empty buffer, because an empty buffer
+    /// denotes end of stream on TCP sockets.
+    pub fn pop_with_timeout(&mut self, qd: QDesc, size: usize, timeout: Duration) -> Result<QToken, Fail> {
+        let result: Result<QToken, Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::pop_with_timeout");
+
+            // Check if size is valid.
+            if !((size > 0) && (size <= limits::POP_SIZE_MAX)) {
+                let cause: String = format!("invalid pop size (size={:?})", size);
+                error!("pop_with_timeout(): {:?}", &cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            }
+
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.pop_with_timeout(qd, size, timeout),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            }
+        };
+
+        self.poll(1);
 
         result
     }
@@ -383,7 +809,7 @@ impl LibOS {
 
         loop {
             // Poll first, so as to give pending operations a chance to complete.
-            self.poll();
+            self.poll(1);
 
             // The operation has completed, so extract the result and return.
             if handle.has_completed() {
@@ -393,39 +819,60 @@ impl LibOS {
             if abstime.is_none() || SystemTime::now() >= abstime.unwrap() {
                 return Err(Fail::new(libc::ETIMEDOUT, "timer expired"));
             }
+
+            // Only external input can complete the operation, so do not spin until it arrives.
+            if self.is_quiescent() {
+                let timeout: Option<Duration> =
+                    abstime.and_then(|abstime| abstime.duration_since(SystemTime::now()).ok());
+                if let Err(e) = self.get_runtime().wait_for_input(timeout) {
+                    let cause: String = format!("operation cannot make progress (qt={:?})", qt);
+                    warn!("timedwait(): {}", cause);
+                    return Err(Fail::new(e.errno, &cause));
+                }
+            }
         }
     }
 
-    /// Waits for any of the given pending I/O operations to complete or a timeout to expire.
+    /// Waits for any of the given pending I/O operations to complete or a timeout to expire. The timeout is measured
+    /// by the runtime timer. When it expires, this fails with `ETIMEDOUT` and all operations are left pending, so they
+    /// can be waited on again. A timeout of zero polls once and returns right away.
     pub fn wait_any(&mut self, qts: &[QToken], timeout: Option<Duration>) -> Result<(usize, demi_qresult_t), Fail> {
         trace!("wait_any(): qts={:?}, timeout={:?}", qts, timeout);
 
-        // Get the wait start time, but only if we have a timeout.  We don't care when we started if we wait forever.
-        let start: Option<Instant> = if timeout.is_none() { None } else { Some(Instant::now()) };
-
-        loop {
-            // Poll first, so as to give pending operations a chance to complete.
-            self.poll();
+        let (i, handle): (usize, TaskHandle) = match self {
+            LibOS::NetworkLibOS(libos) => libos.wait_any(qts, timeout)?,
+            LibOS::MemoryLibOS(libos) => libos.wait_any(qts, timeout)?,
+        };
+        Ok((i, self.pack_result(handle, qts[i])?))
+    }
 
-            // Search for any operation that has completed.
-            for (i, &qt) in qts.iter().enumerate() {
-                // Retrieve associated schedule handle.
-                // TODO: move this out of the loop.
-                let handle: TaskHandle = self.schedule(qt)?;
+    /// Drives the runtime without waiting for any particular operation. The scheduler is polled at most
+    /// `max_iterations` times, which lets a host event loop bound the amount of work done per call and interleave it
+    /// with other work. Polling stops early once no task is runnable, in which case this returns quickly. Returns the
+    /// number of tasks that were polled, which is zero if there was nothing to do.
+    pub fn poll(&mut self, max_iterations: usize) -> usize {
+        #[cfg(feature = "profiler")]
+        timer!("demikernel::poll");
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.poll(max_iterations),
+            LibOS::MemoryLibOS(libos) => libos.poll(max_iterations),
+        }
+    }
 
-                // Found one, so extract the result and return.
-                if handle.has_completed() {
-                    return Ok((i, self.pack_result(handle, qt)?));
-                }
-            }
+    /// Counts the operations issued on `qd` whose result is yet to be waited on, including the ones that already
+    /// completed. This helps spotting leaked queue tokens, and deciding when to stop issuing new operations.
+    pub fn pending_operations(&self, qd: QDesc) -> Result<usize, Fail> {
+        trace!("pending_operations(): qd={:?}", qd);
+        self.get_runtime().pending_operations(qd)
+    }
 
-            // If we have a timeout, check for expiration.
-            if timeout.is_some()
-                && Instant::now().duration_since(start.expect("start should be set if timeout is"))
-                    > timeout.expect("timeout should still be set")
-            {
-                return Err(Fail::new(libc::ETIMEDOUT, "timer expired"));
-            }
+    /// Checks whether the runtime is quiescent, that is, whether no coroutine other than a poller is runnable and no
+    /// timer is pending. Pending operations then only complete on external input, so wait functions block for it
+    /// instead of busy-spinning, and fail with `EDEADLK` if no poller can pick it up.
+    pub fn is_quiescent(&self) -> bool {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.is_quiescent(),
+            LibOS::MemoryLibOS(libos) => libos.is_quiescent(),
         }
     }
 
@@ -440,7 +887,7 @@ impl LibOS {
             }
         };
 
-        self.poll();
+        self.poll(1);
 
         result
     }
@@ -456,7 +903,7 @@ impl LibOS {
             }
         };
 
-        self.poll();
+        self.poll(1);
 
         result
     }
@@ -478,13 +925,83 @@ impl LibOS {
             LibOS::MemoryLibOS(libos) => libos.pack_result(handle, qt),
         }
     }
+}
 
-    fn poll(&mut self) {
-        #[cfg(feature = "profiler")]
-        timer!("demikernel::poll");
-        match self {
-            LibOS::NetworkLibOS(libos) => libos.poll(),
-            LibOS::MemoryLibOS(libos) => libos.poll(),
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(all(test, feature = "catnap-libos"))]
+mod tests {
+    use crate::{
+        demikernel::{
+            config::Config,
+            libos::{
+                name::LibOSName,
+                LibOS,
+            },
+        },
+        runtime::{
+            types::{
+                demi_opcode_t,
+                demi_qresult_t,
+            },
+            QDesc,
+            QToken,
+            SharedDemiRuntime,
+        },
+    };
+    use ::anyhow::Result;
+    use ::std::{
+        net::{
+            SocketAddr,
+            TcpListener,
+        },
+        time::Duration,
+    };
+    use ::yaml_rust::Yaml;
+
+    /// Creates a Catnap LibOS with the default configuration.
+    fn new_libos() -> Result<LibOS> {
+        let config: Config = Config(Yaml::Hash(Default::default()));
+        Ok(LibOS::new_with_config(
+            LibOSName::Catnap,
+            &config,
+            SharedDemiRuntime::default(),
+        )?)
+    }
+
+    /// Tests that a blocking connect to a loopback listener returns once the connection is established, and that an
+    /// accept that is pending on the same runtime makes progress in the meantime.
+    #[test]
+    fn test_connect_blocking() -> Result<()> {
+        let mut libos: LibOS = new_libos()?;
+        let local: SocketAddr = TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+
+        let listen_qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_STREAM, 0)?;
+        libos.bind(listen_qd, local)?;
+        libos.listen(listen_qd, 1)?;
+        let accept_qt: QToken = libos.accept(listen_qd)?;
+
+        let qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_STREAM, 0)?;
+        libos.connect_blocking(qd, local, Some(Duration::from_secs(5)))?;
+
+        let qr: demi_qresult_t = libos.wait(accept_qt, Some(Duration::from_secs(5)))?;
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_ACCEPT);
+
+        Ok(())
+    }
+
+    /// Tests that a blocking connect to a port that nobody listens on fails with the error of the connect.
+    #[test]
+    fn test_connect_blocking_refused() -> Result<()> {
+        let mut libos: LibOS = new_libos()?;
+        let remote: SocketAddr = TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+
+        let qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_STREAM, 0)?;
+        match libos.connect_blocking(qd, remote, Some(Duration::from_secs(5))) {
+            Err(e) if e.errno == libc::ECONNREFUSED => Ok(()),
+            result => anyhow::bail!("connect_blocking() should have failed with ECONNREFUSED: {:?}", result),
         }
     }
 }
@@ -8,30 +8,43 @@
 use crate::{
     demikernel::{
         config::Config,
-        libos::network::queue::SharedNetworkQueue,
+        libos::network::queue::{
+            QueueLogContext,
+            SharedNetworkQueue,
+        },
     },
     pal::constants::SOMAXCONN,
     runtime::{
         fail::Fail,
         limits,
         memory::{
+            sgarray_capacity,
             DemiBuffer,
             MemoryRuntime,
+            SharedBufferPool,
         },
         network::{
             socket::SocketId,
             transport::NetworkTransport,
+            types::{
+                PacketInfo,
+                SocketOption,
+                SocketOptionName,
+            },
             unwrap_socketaddr,
         },
         queue::{
-            downcast_queue,
+            pop_into_coroutine,
+            IoQueue,
             Operation,
             OperationResult,
+            QType,
         },
         scheduler::{
             TaskHandle,
             Yielder,
         },
+        timer::SharedTimer,
         types::demi_sgarray_t,
         QDesc,
         QToken,
@@ -39,12 +52,17 @@ use crate::{
         SharedObject,
     },
 };
+use ::futures::{
+    task::noop_waker_ref,
+    FutureExt,
+};
 use ::socket2::{
     Domain,
     Protocol,
     Type,
 };
 use ::std::{
+    future::Future,
     net::{
         Ipv4Addr,
         SocketAddr,
@@ -55,8 +73,21 @@ use ::std::{
         DerefMut,
     },
     pin::Pin,
+    task::{
+        Context,
+        Poll,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
+#[cfg(target_os = "linux")]
+use crate::catnap::transport::SharedCatnapTransport;
+#[cfg(target_os = "linux")]
+use ::std::os::fd::RawFd;
+
 #[cfg(feature = "profiler")]
 use crate::timer;
 
@@ -73,6 +104,8 @@ pub struct NetworkLibOS<T: NetworkTransport> {
     runtime: SharedDemiRuntime,
     /// Underlying network transport.
     transport: T,
+    /// How long to wait for queues to close when this LibOS is dropped.
+    close_timeout: Duration,
 }
 
 #[derive(Clone)]
@@ -84,12 +117,14 @@ pub struct SharedNetworkLibOS<T: NetworkTransport>(SharedObject<NetworkLibOS<T>>
 
 /// Associate Functions for Catnap LibOS
 impl<T: NetworkTransport> SharedNetworkLibOS<T> {
-    /// Instantiates a Catnap LibOS.
-    pub fn new(config: &Config, mut runtime: SharedDemiRuntime) -> Self {
-        Self(SharedObject::new(NetworkLibOS::<T> {
+    /// Instantiates a Catnap LibOS. Fails if `config` is invalid.
+    pub fn new(config: &Config, mut runtime: SharedDemiRuntime) -> Result<Self, Fail> {
+        let close_timeout: Duration = config.close_timeout()?;
+        Ok(Self(SharedObject::new(NetworkLibOS::<T> {
             runtime: runtime.clone(),
-            transport: T::new(&config, &mut runtime),
-        }))
+            transport: T::new(&config, &mut runtime)?,
+            close_timeout,
+        })))
     }
 
     /// Creates a socket. This function contains the libOS-level functionality needed to create a SharedNetworkQueue that
@@ -103,6 +138,11 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
         }
 
         // Parse socket type.
+        if typ == Type::RAW {
+            let cause: String = format!("raw sockets are only supported by the inetstack");
+            error!("socket(): {}", cause);
+            return Err(Fail::new(libc::ENOTSUP, &cause));
+        }
         if (typ != Type::STREAM) && (typ != Type::DGRAM) {
             let cause: String = format!("socket type not supported (type={:?})", typ);
             error!("socket(): {}", cause);
@@ -111,14 +151,14 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
 
         // Create underlying queue.
         let queue: SharedNetworkQueue<T> = SharedNetworkQueue::new(domain, typ, &mut self.transport)?;
-        let qd: QDesc = self.runtime.alloc_queue(queue);
+        let qd: QDesc = self.runtime.alloc_queue(queue)?;
         Ok(qd)
     }
 
     /// Binds a socket to a local endpoint. This function contains the libOS-level functionality needed to bind a
     /// SharedNetworkQueue to a local address.
     pub fn bind(&mut self, qd: QDesc, local: SocketAddr) -> Result<(), Fail> {
-        trace!("bind() qd={:?}, local={:?}", qd, local);
+        trace!("bind() {}, local={:?}", self.log_context(qd), local);
 
         let localv4: SocketAddrV4 = unwrap_socketaddr(local)?;
         // Check if we are binding to the wildcard address.
@@ -155,7 +195,7 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
     /// Sets a SharedNetworkQueue and its underlying socket as a passive one. This function contains the libOS-level
     /// functionality to move the SharedNetworkQueue and underlying socket into the listen state.
     pub fn listen(&mut self, qd: QDesc, backlog: usize) -> Result<(), Fail> {
-        trace!("listen() qd={:?}, backlog={:?}", qd, backlog);
+        trace!("listen() {}, backlog={:?}", self.log_context(qd), backlog);
 
         // We just assert backlog here, because it was previously checked at PDPIX layer.
         debug_assert!((backlog > 0) && (backlog <= SOMAXCONN as usize));
@@ -166,10 +206,25 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
 
     /// Synchronous cross-queue code to start accepting a connection. This function schedules the asynchronous
     /// coroutine and performs any necessary synchronous, multi-queue operations at the libOS-level before beginning
-    /// the accept.
+    /// the accept. If the listening queue is closed before a connection comes in, the accept fails with `EBADF`.
     pub fn accept(&mut self, qd: QDesc) -> Result<QToken, Fail> {
-        trace!("accept(): qd={:?}", qd);
+        trace!("accept() {}", self.log_context(qd));
+        self.do_accept(qd, None)
+    }
 
+    /// Same as [accept](Self::accept), but the accept fails with `ETIMEDOUT` if no connection comes in by `deadline`.
+    /// Several operations may be given the same deadline, so that they are bounded as a whole.
+    pub fn accept_with_deadline(&mut self, qd: QDesc, deadline: Instant) -> Result<QToken, Fail> {
+        trace!(
+            "accept_with_deadline() {}, deadline={:?}",
+            self.log_context(qd),
+            deadline
+        );
+        self.do_accept(qd, Some(deadline))
+    }
+
+    /// Schedules the coroutine of an accept, bounded by `deadline` if any.
+    fn do_accept(&mut self, qd: QDesc, deadline: Option<Instant>) -> Result<QToken, Fail> {
         let mut queue: SharedNetworkQueue<T> = self.get_shared_queue(&qd)?;
         let coroutine_constructor = || -> Result<TaskHandle, Fail> {
             let task_name: String = format!("NetworkLibOS::accept for qd={:?}", qd);
@@ -177,7 +232,7 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
                 |yielder| -> Pin<Box<Operation>> { Box::pin(self.clone().accept_coroutine(qd, yielder)) };
             self.clone()
                 .runtime
-                .insert_coroutine_with_tracking(&task_name, coroutine_factory, qd)
+                .insert_coroutine_with_deadline(&task_name, coroutine_factory, qd, deadline)
         };
 
         queue.accept(coroutine_constructor)
@@ -192,18 +247,48 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
         // structure and the SharedNetworkQueue will not be freed until this coroutine finishes.
         let mut queue: SharedNetworkQueue<T> = match self.get_shared_queue(&qd) {
             Ok(queue) => queue.clone(),
-            Err(e) => return (qd, OperationResult::Failed(e)),
+            Err(e) => {
+                // The queue was closed and freed before the accept got to run, so its descriptor is no longer valid.
+                warn!(
+                    "accept() {}: listening queue was closed: {:?}",
+                    self.log_context(qd),
+                    &e
+                );
+                return (qd, OperationResult::Failed(e));
+            },
         };
-        // Wait for the accept operation to complete.
-        match queue.accept_coroutine(yielder).await {
-            Ok(new_queue) => {
+        // Wait for the accept operation to complete, or for the deadline of the coroutine to expire.
+        let timer: SharedTimer = self.runtime.get_timer();
+        let result: Result<SharedNetworkQueue<T>, Fail> = match timer
+            .within_deadline(queue.accept_coroutine(yielder.clone()), &yielder)
+            .await
+        {
+            Ok(result) => result,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(mut new_queue) => {
                 // TODO: Do we need to add this to the socket id to queue descriptor table?
                 // It is safe to call except here because the new queue is connected and it should be connected to a
                 // remote address.
                 let addr: SocketAddr = new_queue
                     .remote()
                     .expect("An accepted socket must have a remote address");
-                let new_qd: QDesc = self.runtime.alloc_queue(new_queue);
+                let new_qd: QDesc = match self.runtime.alloc_queue(new_queue.clone()) {
+                    Ok(new_qd) => new_qd,
+                    Err(e) => {
+                        // Refuse the connection by closing it right away.
+                        if let Err(close_e) = new_queue.hard_close() {
+                            warn!("accept_coroutine(): failed to close refused connection: {:?}", close_e);
+                        }
+                        return (qd, OperationResult::Failed(e));
+                    },
+                };
+                trace!(
+                    "accept() {}: accepted {}",
+                    queue.log_context(qd),
+                    new_queue.log_context(new_qd)
+                );
                 // FIXME: add IPv6 support; https://github.com/microsoft/demikernel/issues/935
                 (
                     qd,
@@ -211,7 +296,7 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
                 )
             },
             Err(e) => {
-                warn!("accept() listening_qd={:?}: {:?}", qd, &e);
+                warn!("accept() {}: {:?}", queue.log_context(qd), &e);
                 (qd, OperationResult::Failed(e))
             },
         }
@@ -221,8 +306,25 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
     /// coroutine and performs any necessary synchronous, multi-queue operations at the libOS-level before beginning
     /// the connect.
     pub fn connect(&mut self, qd: QDesc, remote: SocketAddr) -> Result<QToken, Fail> {
-        trace!("connect() qd={:?}, remote={:?}", qd, remote);
+        trace!("connect() {}, remote={:?}", self.log_context(qd), remote);
+        self.do_connect(qd, remote, None)
+    }
 
+    /// Same as [connect](Self::connect), but the connect fails with `ETIMEDOUT` if it is not established by
+    /// `deadline`, in which case the queue is left closed, as on any other failed connect. Several operations may be
+    /// given the same deadline, so that they are bounded as a whole.
+    pub fn connect_with_deadline(&mut self, qd: QDesc, remote: SocketAddr, deadline: Instant) -> Result<QToken, Fail> {
+        trace!(
+            "connect_with_deadline() {}, remote={:?}, deadline={:?}",
+            self.log_context(qd),
+            remote,
+            deadline
+        );
+        self.do_connect(qd, remote, Some(deadline))
+    }
+
+    /// Schedules the coroutine of a connect to `remote`, bounded by `deadline` if any.
+    fn do_connect(&mut self, qd: QDesc, remote: SocketAddr, deadline: Option<Instant>) -> Result<QToken, Fail> {
         // FIXME: add IPv6 support; https://github.com/microsoft/demikernel/issues/935
         let mut queue: SharedNetworkQueue<T> = self.get_shared_queue(&qd)?;
         let coroutine_constructor = || -> Result<TaskHandle, Fail> {
@@ -231,7 +333,7 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
                 |yielder| -> Pin<Box<Operation>> { Box::pin(self.clone().connect_coroutine(qd, remote, yielder)) };
             self.clone()
                 .runtime
-                .insert_coroutine_with_tracking(&task_name, coroutine_factory, qd)
+                .insert_coroutine_with_deadline(&task_name, coroutine_factory, qd, deadline)
         };
 
         queue.connect(coroutine_constructor)
@@ -248,14 +350,52 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
             Ok(queue) => queue.clone(),
             Err(e) => return (qd, OperationResult::Failed(e)),
         };
-        // Wait for connect operation to complete.
-        match queue.connect_coroutine(remote, yielder).await {
+        // In non-blocking mode, the connect only gets one chance to complete right away. Otherwise, it keeps going in
+        // the background, and its outcome is reported through the state and the pending error of the queue.
+        if queue.is_nonblocking() {
+            let mut connect_future: Pin<Box<dyn Future<Output = Result<(), Fail>>>> =
+                Box::pin(async move { queue.connect_coroutine(remote, yielder).await });
+            return match (&mut connect_future).now_or_never() {
+                Some(Ok(())) => (qd, OperationResult::Connect),
+                Some(Err(e)) => {
+                    warn!("connect() failed ({}, error={:?})", self.log_context(qd), e.cause);
+                    (qd, OperationResult::Failed(e))
+                },
+                None => {
+                    let task_name: String = format!("NetworkLibOS::connect::background for qd={:?}", qd);
+                    let background_connect = async move {
+                        let _ = connect_future.await;
+                    };
+                    if let Err(e) = self
+                        .runtime
+                        .clone()
+                        .insert_background_coroutine(&task_name, Box::pin(background_connect))
+                    {
+                        return (qd, OperationResult::Failed(e));
+                    }
+                    let cause: String = format!("connection is in progress (qd={:?})", qd);
+                    debug!("connect(): {}", cause);
+                    (qd, OperationResult::Failed(Fail::new(libc::EINPROGRESS, &cause)))
+                },
+            };
+        }
+        // Wait for connect operation to complete, or for the deadline of the coroutine to expire.
+        let timer: SharedTimer = self.runtime.get_timer();
+        let result: Result<(), Fail> = match timer
+            .within_deadline(queue.connect_coroutine(remote, yielder.clone()), &yielder)
+            .await
+        {
+            Ok(result) => result,
+            Err(e) => Err(queue.abort_connect(e)),
+        };
+        match result {
             Ok(()) => {
                 // TODO: Do we need to add this to socket id to queue descriptor table?
+                trace!("connect() {}: connected", queue.log_context(qd));
                 (qd, OperationResult::Connect)
             },
             Err(e) => {
-                warn!("connect() failed (qd={:?}, error={:?})", qd, e.cause);
+                warn!("connect() failed ({}, error={:?})", queue.log_context(qd), e.cause);
                 (qd, OperationResult::Failed(e))
             },
         }
@@ -264,7 +404,7 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
     /// Synchronous code to asynchronously close a queue. This function schedules the coroutine that asynchronously
     /// runs the close and any synchronous multi-queue functionality before the close begins.
     pub fn async_close(&mut self, qd: QDesc) -> Result<QToken, Fail> {
-        trace!("async_close() qd={:?}", qd);
+        trace!("async_close() {}", self.log_context(qd));
 
         let mut queue: SharedNetworkQueue<T> = self.get_shared_queue(&qd)?;
         let coroutine_constructor = || -> Result<TaskHandle, Fail> {
@@ -293,12 +433,15 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
         // Wait for close operation to complete.
         match queue.close_coroutine(yielder).await {
             Ok(()) => {
-                // If the queue was bound, remove from the socket id to queue descriptor table.
+                // If the queue was bound, remove from the socket id to queue descriptor table. Accepted queues share
+                // their local address with the listening queue, so they do not have an entry of their own.
                 if let Some(local) = queue.local() {
                     // FIXME: add IPv6 support; https://github.com/microsoft/demikernel/issues/935
-                    self.runtime.remove_socket_id_to_qd(&SocketId::Passive(
-                        unwrap_socketaddr(local).expect("we only support IPv4"),
-                    ));
+                    let socket_id: SocketId =
+                        SocketId::Passive(unwrap_socketaddr(local).expect("we only support IPv4"));
+                    if self.runtime.get_qd_from_socket_id(&socket_id) == Some(qd) {
+                        self.runtime.remove_socket_id_to_qd(&socket_id);
+                    }
                 }
                 // Remove the queue from the queue table. Expect is safe here because we looked up the queue to
                 // schedule this coroutine and no other close coroutine should be able to run due to state machine
@@ -309,7 +452,7 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
                 (qd, OperationResult::Close)
             },
             Err(e) => {
-                warn!("async_close() qd={:?}: {:?}", qd, &e);
+                warn!("async_close() {}: {:?}", queue.log_context(qd), &e);
                 (qd, OperationResult::Failed(e))
             },
         }
@@ -319,14 +462,25 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
     /// coroutine that asynchronously runs the push and any synchronous multi-queue functionality before the push
     /// begins.
     pub fn push(&mut self, qd: QDesc, sga: &demi_sgarray_t) -> Result<QToken, Fail> {
-        trace!("push() qd={:?}", qd);
+        trace!("push() {}", self.log_context(qd));
 
         let buf: DemiBuffer = self.runtime.clone_sgarray(sga)?;
-        if buf.len() == 0 {
-            return Err(Fail::new(libc::EINVAL, "zero-length buffer"));
-        };
+        self.do_push(qd, buf)
+    }
+
+    /// Like [push](Self::push), but the message is gathered from several byte slices, which are copied into a single
+    /// buffer and pushed as one.
+    pub fn push_vectored(&mut self, qd: QDesc, bufs: &[&[u8]]) -> Result<QToken, Fail> {
+        trace!("push_vectored() {}, nbufs={:?}", self.log_context(qd), bufs.len());
 
+        let buf: DemiBuffer = DemiBuffer::from_slices(bufs)?;
+        self.do_push(qd, buf)
+    }
+
+    /// Schedules the coroutine that pushes [buf] to a SharedNetworkQueue.
+    fn do_push(&mut self, qd: QDesc, buf: DemiBuffer) -> Result<QToken, Fail> {
         let mut queue: SharedNetworkQueue<T> = self.get_shared_queue(&qd)?;
+        Self::check_push_len(&queue, &buf)?;
         let coroutine_constructor = || -> Result<TaskHandle, Fail> {
             let task_name: String = format!("NetworkLibOS::push for qd={:?}", qd);
             let coroutine_factory =
@@ -339,6 +493,15 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
         queue.push(coroutine_constructor)
     }
 
+    /// Checks that [buf] may be pushed to [queue]. A zero-length buffer is sent as an empty datagram on a UDP socket,
+    /// but there is nothing to send on a TCP one.
+    fn check_push_len(queue: &SharedNetworkQueue<T>, buf: &DemiBuffer) -> Result<(), Fail> {
+        if buf.len() == 0 && queue.get_qtype() != QType::UdpSocket {
+            return Err(Fail::new(libc::EINVAL, "zero-length buffer"));
+        }
+        Ok(())
+    }
+
     /// Asynchronous code to push [buf] to a SharedNetworkQueue and its underlying POSIX socket. This function returns a
     /// coroutine that runs asynchronously to push a queue and its underlying POSIX socket and performs any necessary
     /// multi-queue operations at the libOS-level after the push succeeds or fails.
@@ -351,10 +514,10 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
             Err(e) => return (qd, OperationResult::Failed(e)),
         };
         // Wait for push to complete.
-        match queue.push_coroutine(&mut buf, None, yielder).await {
-            Ok(()) => (qd, OperationResult::Push),
+        match queue.push_coroutine(&mut buf, None, None, yielder).await {
+            Ok(nbytes) => (qd, OperationResult::Push(nbytes)),
             Err(e) => {
-                warn!("push() qd={:?}: {:?}", qd, &e);
+                warn!("push() {}: {:?}", queue.log_context(qd), &e);
                 (qd, OperationResult::Failed(e))
             },
         }
@@ -364,18 +527,49 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
     /// function schedules the coroutine that asynchronously runs the pushto and any synchronous multi-queue
     /// functionality after pushto begins.
     pub fn pushto(&mut self, qd: QDesc, sga: &demi_sgarray_t, remote: SocketAddr) -> Result<QToken, Fail> {
-        trace!("pushto() qd={:?}", qd);
+        trace!("pushto() {}, remote={:?}", self.log_context(qd), remote);
 
         let buf: DemiBuffer = self.runtime.clone_sgarray(sga)?;
-        if buf.len() == 0 {
-            return Err(Fail::new(libc::EINVAL, "zero-length buffer"));
-        }
-
         let mut queue: SharedNetworkQueue<T> = self.get_shared_queue(&qd)?;
+        Self::check_push_len(&queue, &buf)?;
         let coroutine_constructor = || -> Result<TaskHandle, Fail> {
             let task_name: String = format!("NetworkLibOS::pushto for qd={:?}", qd);
-            let coroutine_factory =
-                |yielder| -> Pin<Box<Operation>> { Box::pin(self.clone().pushto_coroutine(qd, buf, remote, yielder)) };
+            let coroutine_factory = |yielder| -> Pin<Box<Operation>> {
+                Box::pin(self.clone().pushto_coroutine(qd, buf, remote, None, yielder))
+            };
+            self.clone()
+                .runtime
+                .insert_coroutine_with_tracking(&task_name, coroutine_factory, qd)
+        };
+
+        queue.push(coroutine_constructor)
+    }
+
+    /// Like [pushto](Self::pushto), but the datagram goes out from the local address `src` rather than from the
+    /// address that the SharedNetworkQueue is bound to, as with sendmsg() and IP_PKTINFO. The underlying transport
+    /// fails the push if `src` is not a local address.
+    pub fn pushto_from(
+        &mut self,
+        qd: QDesc,
+        sga: &demi_sgarray_t,
+        src: Ipv4Addr,
+        remote: SocketAddr,
+    ) -> Result<QToken, Fail> {
+        trace!(
+            "pushto_from() {}, src={:?}, remote={:?}",
+            self.log_context(qd),
+            src,
+            remote
+        );
+
+        let buf: DemiBuffer = self.runtime.clone_sgarray(sga)?;
+        let mut queue: SharedNetworkQueue<T> = self.get_shared_queue(&qd)?;
+        Self::check_push_len(&queue, &buf)?;
+        let coroutine_constructor = || -> Result<TaskHandle, Fail> {
+            let task_name: String = format!("NetworkLibOS::pushto_from for qd={:?}", qd);
+            let coroutine_factory = |yielder| -> Pin<Box<Operation>> {
+                Box::pin(self.clone().pushto_coroutine(qd, buf, remote, Some(src), yielder))
+            };
             self.clone()
                 .runtime
                 .insert_coroutine_with_tracking(&task_name, coroutine_factory, qd)
@@ -386,12 +580,14 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
 
     /// Asynchronous code to pushto [buf] to [remote] on a SharedNetworkQueue and its underlying POSIX socket. This function
     /// returns a coroutine that runs asynchronously to pushto a queue and its underlying POSIX socket and performs any
-    /// necessary multi-queue operations at the libOS-level after the pushto succeeds or fails.
+    /// necessary multi-queue operations at the libOS-level after the pushto succeeds or fails. The datagram goes out from
+    /// `src`, if given.
     async fn pushto_coroutine(
         self,
         qd: QDesc,
         mut buf: DemiBuffer,
         remote: SocketAddr,
+        src: Option<Ipv4Addr>,
         yielder: Yielder,
     ) -> (QDesc, OperationResult) {
         // Grab the queue, make sure it hasn't been closed in the meantime.
@@ -402,10 +598,10 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
             Err(e) => return (qd, OperationResult::Failed(e)),
         };
         // Wait for push to complete.
-        match queue.push_coroutine(&mut buf, Some(remote), yielder).await {
-            Ok(()) => (qd, OperationResult::Push),
+        match queue.push_coroutine(&mut buf, Some(remote), src, yielder).await {
+            Ok(nbytes) => (qd, OperationResult::Push(nbytes)),
             Err(e) => {
-                warn!("pushto() qd={:?}: {:?}", qd, &e);
+                warn!("pushto() {}: {:?}", queue.log_context(qd), &e);
                 (qd, OperationResult::Failed(e))
             },
         }
@@ -415,8 +611,24 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
     /// function schedules the asynchronous coroutine and performs any necessary synchronous, multi-queue operations
     /// at the libOS-level before beginning the pop.
     pub fn pop(&mut self, qd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
-        trace!("pop() qd={:?}, size={:?}", qd, size);
+        trace!("pop() {}, size={:?}", self.log_context(qd), size);
+        self.do_pop(qd, size, None)
+    }
+
+    /// Same as [pop](Self::pop), but the pop fails with `ETIMEDOUT` if no data comes in by `deadline`. Several
+    /// operations may be given the same deadline, so that they are bounded as a whole.
+    pub fn pop_with_deadline(&mut self, qd: QDesc, size: Option<usize>, deadline: Instant) -> Result<QToken, Fail> {
+        trace!(
+            "pop_with_deadline() {}, size={:?}, deadline={:?}",
+            self.log_context(qd),
+            size,
+            deadline
+        );
+        self.do_pop(qd, size, Some(deadline))
+    }
 
+    /// Schedules the coroutine of a pop of optional `size`, bounded by `deadline` if any.
+    fn do_pop(&mut self, qd: QDesc, size: Option<usize>, deadline: Option<Instant>) -> Result<QToken, Fail> {
         // We just assert 'size' here, because it was previously checked at PDPIX layer.
         debug_assert!(size.is_none() || ((size.unwrap() > 0) && (size.unwrap() <= limits::POP_SIZE_MAX)));
 
@@ -425,6 +637,36 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
             let task_name: String = format!("NetworkLibOS::pop for qd={:?}", qd);
             let coroutine_factory =
                 |yielder| -> Pin<Box<Operation>> { Box::pin(self.clone().pop_coroutine(qd, size, yielder)) };
+            self.clone()
+                .runtime
+                .insert_coroutine_with_deadline(&task_name, coroutine_factory, qd, deadline)
+        };
+
+        queue.pop(coroutine_constructor)
+    }
+
+    /// Synchronous code to pop data from a SharedNetworkQueue and its underlying POSIX socket directly into the
+    /// caller-provided `sga`, which must remain valid until the operation completes. This function schedules the
+    /// asynchronous coroutine, which copies the popped data into `sga` once the pop succeeds.
+    pub fn pop_into(&mut self, qd: QDesc, sga: &demi_sgarray_t) -> Result<QToken, Fail> {
+        trace!("pop_into() {}", self.log_context(qd));
+
+        let size: usize = sgarray_capacity(sga)?.min(limits::POP_SIZE_MAX);
+        // We just assert 'size' here, because it was previously checked at PDPIX layer.
+        debug_assert!(size > 0);
+
+        let sga: demi_sgarray_t = *sga;
+        let mut queue: SharedNetworkQueue<T> = self.get_shared_queue(&qd)?;
+        let buffer_pool: SharedBufferPool = self.runtime.get_buffer_pool();
+        let coroutine_constructor = || -> Result<TaskHandle, Fail> {
+            let task_name: String = format!("NetworkLibOS::pop_into for qd={:?}", qd);
+            let coroutine_factory = |yielder| -> Pin<Box<Operation>> {
+                Box::pin(pop_into_coroutine(
+                    self.clone().pop_coroutine(qd, Some(size), yielder),
+                    sga,
+                    buffer_pool,
+                ))
+            };
             self.clone()
                 .runtime
                 .insert_coroutine_with_tracking(&task_name, coroutine_factory, qd)
@@ -445,26 +687,115 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
             Err(e) => return (qd, OperationResult::Failed(e)),
         };
 
-        // Wait for pop to complete.
-        match queue.pop_coroutine(size, yielder).await {
+        // Wait for pop to complete, or for the deadline of the coroutine to expire.
+        let timer: SharedTimer = self.runtime.get_timer();
+        let result: Result<(Option<SocketAddr>, Option<PacketInfo>, DemiBuffer), Fail> = match timer
+            .within_deadline(queue.pop_coroutine(size, yielder.clone()), &yielder)
+            .await
+        {
+            Ok(result) => result,
+            Err(e) => Err(e),
+        };
+        match result {
             // FIXME: add IPv6 support; https://github.com/microsoft/demikernel/issues/935
-            Ok((Some(addr), buf)) => (
+            Ok((Some(addr), Some(info), buf)) => (
+                qd,
+                OperationResult::PopWithInfo(unwrap_socketaddr(addr).expect("we only support IPv4"), info, buf),
+            ),
+            Ok((Some(addr), None, buf)) => (
                 qd,
                 OperationResult::Pop(Some(unwrap_socketaddr(addr).expect("we only support IPv4")), buf),
             ),
-            Ok((None, buf)) => (qd, OperationResult::Pop(None, buf)),
+            Ok((None, _, buf)) => (qd, OperationResult::Pop(None, buf)),
             Err(e) => {
-                warn!("pop() qd={:?}: {:?}", qd, &e);
+                warn!("pop() {}: {:?}", queue.log_context(qd), &e);
                 (qd, OperationResult::Failed(e))
             },
         }
     }
 
+    /// Gets the local address of the SharedNetworkQueue `qd`. For accepted queues, this is the concrete address that
+    /// the connection landed on.
+    pub fn getsockname(&self, qd: QDesc) -> Result<SocketAddr, Fail> {
+        trace!("getsockname() {}", self.log_context(qd));
+        match self.get_shared_queue(&qd)?.local() {
+            Some(local) => Ok(local),
+            None => {
+                let cause: String = format!("socket is not bound (qd={:?})", qd);
+                error!("getsockname(): {}", cause);
+                Err(Fail::new(libc::EINVAL, &cause))
+            },
+        }
+    }
+
+    /// Sets the socket option `option` on the SharedNetworkQueue `qd`, as with setsockopt().
+    pub fn set_socket_option(&mut self, qd: QDesc, option: SocketOption) -> Result<(), Fail> {
+        trace!("set_socket_option() {}, option={:?}", self.log_context(qd), option);
+        let mut queue: SharedNetworkQueue<T> = self.get_shared_queue(&qd)?;
+        queue.set_socket_option(option)
+    }
+
+    /// Gets the value of the socket option `name` of the SharedNetworkQueue `qd`, as with getsockopt().
+    pub fn get_socket_option(&self, qd: QDesc, name: SocketOptionName) -> Result<SocketOption, Fail> {
+        trace!("get_socket_option() {}, name={:?}", self.log_context(qd), name);
+        let mut queue: SharedNetworkQueue<T> = self.get_shared_queue(&qd)?;
+        queue.get_socket_option(name)
+    }
+
+    /// Retrieves and clears the pending error of the SharedNetworkQueue `qd`, which is the last error that an
+    /// asynchronous operation on it failed with (e.g. a connect that was refused). This is the equivalent of
+    /// getsockopt(SO_ERROR).
+    pub fn take_socket_error(&mut self, qd: QDesc) -> Result<Option<Fail>, Fail> {
+        trace!("take_socket_error() {}", self.log_context(qd));
+        let mut queue: SharedNetworkQueue<T> = self.get_shared_queue(&qd)?;
+        Ok(queue.take_error())
+    }
+
     /// This function gets a shared queue reference out of the I/O queue table. The type if a ref counted pointer to the
     /// queue itself.
     fn get_shared_queue(&self, qd: &QDesc) -> Result<SharedNetworkQueue<T>, Fail> {
         self.runtime.get_shared_queue::<SharedNetworkQueue<T>>(qd)
     }
+
+    /// Gets the fields that identify the SharedNetworkQueue `qd` in log lines. Only the queue descriptor is known for
+    /// queues that do not exist.
+    fn log_context(&self, qd: QDesc) -> QueueLogContext {
+        match self.get_shared_queue(&qd) {
+            Ok(queue) => queue.log_context(qd),
+            Err(_) => QueueLogContext {
+                qd,
+                local: None,
+                remote: None,
+            },
+        }
+    }
+}
+
+/// Associate Functions for Catnap LibOS on Linux
+#[cfg(target_os = "linux")]
+impl SharedNetworkLibOS<SharedCatnapTransport> {
+    /// Gets the file descriptor of the POSIX socket that backs the SharedNetworkQueue `qd`. The file descriptor is
+    /// owned by the transport, so it must not be closed or changed by the caller.
+    pub fn raw_fd(&self, qd: QDesc) -> Result<RawFd, Fail> {
+        trace!("raw_fd() {}", self.log_context(qd));
+        let queue: SharedNetworkQueue<SharedCatnapTransport> = self.get_shared_queue(&qd)?;
+        Ok(self.transport.raw_fd_from_sd(queue.socket()))
+    }
+
+    /// Sets whether the SharedNetworkQueue `qd` is in non-blocking mode, like FIONBIO does. In non-blocking mode, pops
+    /// and accepts that would block fail with EAGAIN, and connects that cannot complete right away fail with
+    /// EINPROGRESS while the connection keeps being established in the background. Pushes complete as soon as their
+    /// data is queued for transmission.
+    pub fn set_nonblocking(&mut self, qd: QDesc, nonblocking: bool) -> Result<(), Fail> {
+        trace!(
+            "set_nonblocking() {}, nonblocking={:?}",
+            self.log_context(qd),
+            nonblocking
+        );
+        let mut queue: SharedNetworkQueue<SharedCatnapTransport> = self.get_shared_queue(&qd)?;
+        queue.set_nonblocking(nonblocking);
+        Ok(())
+    }
 }
 
 //======================================================================================================================
@@ -472,19 +803,43 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
 //======================================================================================================================
 
 impl<T: NetworkTransport> Drop for NetworkLibOS<T> {
-    // Releases all sockets allocated by Catnap.
+    // Releases all sockets allocated by this LibOS. Queues of other LibOSes that share the runtime are left alone.
+    // Queues are closed concurrently, and those that do not close within the close timeout are abandoned, so that a
+    // stuck transport cannot hang the shutdown.
     fn drop(&mut self) {
-        for boxed_queue in self.runtime.get_mut_qtable().drain() {
-            match downcast_queue::<SharedNetworkQueue<T>>(boxed_queue) {
-                Ok(mut queue) => {
-                    if let Err(e) = queue.hard_close() {
-                        error!("close() failed (error={:?}", e);
-                    }
-                },
-                Err(_) => {
-                    error!("drop(): attempting to drop something that is not a SharedNetworkQueue");
+        let deadline: Instant = Instant::now() + self.close_timeout;
+        let mut closes: Vec<Pin<Box<dyn Future<Output = Result<(), Fail>>>>> = self
+            .runtime
+            .get_mut_qtable()
+            .drain_type::<SharedNetworkQueue<T>>()
+            .into_iter()
+            .map(|mut queue| -> Pin<Box<dyn Future<Output = Result<(), Fail>>>> {
+                Box::pin(async move { queue.shutdown_close(Yielder::new()).await })
+            })
+            .collect();
+        let mut ctx: Context = Context::from_waker(noop_waker_ref());
+        loop {
+            closes.retain_mut(|close| match close.poll_unpin(&mut ctx) {
+                Poll::Ready(Ok(())) => false,
+                Poll::Ready(Err(e)) => {
+                    error!("close() failed (error={:?})", e);
+                    false
                 },
+                Poll::Pending => true,
+            });
+            if closes.is_empty() {
+                break;
             }
+            if Instant::now() >= deadline {
+                warn!(
+                    "drop(): abandoning queues that did not close in time (num_queues={:?}, timeout={:?})",
+                    closes.len(),
+                    self.close_timeout
+                );
+                break;
+            }
+            // Let the transport make progress on the closes.
+            self.runtime.poll();
         }
     }
 }
@@ -502,3 +857,858 @@ impl<T: NetworkTransport> DerefMut for SharedNetworkLibOS<T> {
         self.0.deref_mut()
     }
 }
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use crate::{
+        catnap::transport::SharedCatnapTransport,
+        demikernel::{
+            config::Config,
+            libos::network::{
+                libos::SharedNetworkLibOS,
+                queue::{
+                    QueueLogContext,
+                    SharedNetworkQueue,
+                },
+            },
+        },
+        runtime::{
+            fail::Fail,
+            memory::{
+                DemiBuffer,
+                MemoryRuntime,
+            },
+            network::{
+                transport::NetworkTransport,
+                types::{
+                    PacketInfo,
+                    SocketOption,
+                    SocketOptionName,
+                },
+            },
+            scheduler::{
+                TaskHandle,
+                Yielder,
+            },
+            types::demi_sgarray_t,
+            OperationResult,
+            QDesc,
+            QToken,
+            SharedDemiRuntime,
+        },
+    };
+    use ::anyhow::Result;
+    use ::socket2::{
+        Domain,
+        Protocol,
+        Socket,
+        Type,
+    };
+    use ::std::{
+        cell::RefCell,
+        cmp::min,
+        collections::VecDeque,
+        io::{
+            ErrorKind,
+            Read,
+            Write,
+        },
+        mem::ManuallyDrop,
+        net::{
+            Ipv4Addr,
+            SocketAddr,
+            TcpListener,
+            TcpStream,
+            UdpSocket,
+        },
+        os::fd::{
+            FromRawFd,
+            RawFd,
+        },
+        rc::Rc,
+        time::{
+            Duration,
+            Instant,
+        },
+    };
+    use ::yaml_rust::{
+        Yaml,
+        YamlLoader,
+    };
+
+    /// Transport that delivers the datagrams pushed on any of its sockets back to them, in memory.
+    #[derive(Clone)]
+    struct EchoTransport {
+        datagrams: Rc<RefCell<VecDeque<DemiBuffer>>>,
+    }
+
+    impl NetworkTransport for EchoTransport {
+        type SocketDescriptor = ();
+
+        fn new(_config: &Config, _runtime: &mut SharedDemiRuntime) -> Result<Self, Fail> {
+            Ok(Self {
+                datagrams: Rc::new(RefCell::new(VecDeque::new())),
+            })
+        }
+
+        fn socket(&mut self, _domain: Domain, _typ: Type) -> Result<(), Fail> {
+            Ok(())
+        }
+
+        fn bind(&mut self, _sd: &mut (), _local: SocketAddr) -> Result<(), Fail> {
+            Ok(())
+        }
+
+        fn listen(&mut self, _sd: &mut (), _backlog: usize) -> Result<(), Fail> {
+            Ok(())
+        }
+
+        fn hard_close(&mut self, _sd: &mut ()) -> Result<(), Fail> {
+            Ok(())
+        }
+
+        async fn accept(&mut self, _sd: &mut (), _yielder: Yielder) -> Result<((), SocketAddr, SocketAddr), Fail> {
+            Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+        }
+
+        async fn connect(&mut self, _sd: &mut (), _remote: SocketAddr, _yielder: Yielder) -> Result<(), Fail> {
+            Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+        }
+
+        async fn push(
+            &mut self,
+            _sd: &mut (),
+            buf: &mut DemiBuffer,
+            _addr: Option<SocketAddr>,
+            _src: Option<Ipv4Addr>,
+            _yielder: Yielder,
+        ) -> Result<(), Fail> {
+            self.datagrams.borrow_mut().push_back(DemiBuffer::from_slice(&buf[..])?);
+            buf.adjust(buf.len())?;
+            Ok(())
+        }
+
+        async fn pop(
+            &mut self,
+            _sd: &mut (),
+            size: usize,
+            yielder: Yielder,
+        ) -> Result<(Option<SocketAddr>, Option<PacketInfo>, DemiBuffer), Fail> {
+            loop {
+                if let Some(mut datagram) = self.datagrams.borrow_mut().pop_front() {
+                    let nbytes: usize = min(datagram.len(), size);
+                    datagram.trim(datagram.len() - nbytes)?;
+                    return Ok((None, None, datagram));
+                }
+                yielder.yield_once().await?;
+            }
+        }
+
+        async fn close(&mut self, _sd: &mut (), _yielder: Yielder) -> Result<(), Fail> {
+            Ok(())
+        }
+    }
+
+    /// Transport whose sockets never finish closing.
+    #[derive(Clone)]
+    struct StuckTransport {}
+
+    impl NetworkTransport for StuckTransport {
+        type SocketDescriptor = ();
+
+        fn new(_config: &Config, _runtime: &mut SharedDemiRuntime) -> Result<Self, Fail> {
+            Ok(Self {})
+        }
+
+        fn socket(&mut self, _domain: Domain, _typ: Type) -> Result<(), Fail> {
+            Ok(())
+        }
+
+        fn bind(&mut self, _sd: &mut (), _local: SocketAddr) -> Result<(), Fail> {
+            Ok(())
+        }
+
+        fn listen(&mut self, _sd: &mut (), _backlog: usize) -> Result<(), Fail> {
+            Ok(())
+        }
+
+        fn hard_close(&mut self, _sd: &mut ()) -> Result<(), Fail> {
+            panic!("a synchronous close would block forever")
+        }
+
+        async fn accept(&mut self, _sd: &mut (), _yielder: Yielder) -> Result<((), SocketAddr, SocketAddr), Fail> {
+            Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+        }
+
+        async fn connect(&mut self, _sd: &mut (), _remote: SocketAddr, _yielder: Yielder) -> Result<(), Fail> {
+            Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+        }
+
+        async fn push(
+            &mut self,
+            _sd: &mut (),
+            _buf: &mut DemiBuffer,
+            _addr: Option<SocketAddr>,
+            _src: Option<Ipv4Addr>,
+            _yielder: Yielder,
+        ) -> Result<(), Fail> {
+            Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+        }
+
+        async fn pop(
+            &mut self,
+            _sd: &mut (),
+            _size: usize,
+            _yielder: Yielder,
+        ) -> Result<(Option<SocketAddr>, Option<PacketInfo>, DemiBuffer), Fail> {
+            Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+        }
+
+        async fn close(&mut self, _sd: &mut (), yielder: Yielder) -> Result<(), Fail> {
+            loop {
+                yielder.yield_once().await?;
+            }
+        }
+    }
+
+    /// Waits for the operation `qt` to complete, and returns its result.
+    fn wait(runtime: &mut SharedDemiRuntime, qt: QToken) -> Result<OperationResult> {
+        let handle: TaskHandle = runtime.from_task_id(qt)?;
+        while !handle.has_completed() {
+            runtime.poll();
+        }
+        match runtime.remove_coroutine(&handle).get_result() {
+            Some((_, result)) => Ok(result),
+            None => anyhow::bail!("operation should have completed"),
+        }
+    }
+
+    /// Tests that the file descriptor of a queue refers to the socket that backs it.
+    #[test]
+    fn test_raw_fd() -> Result<()> {
+        let config: Config = Config(Yaml::Hash(Default::default()));
+        let mut libos: SharedNetworkLibOS<SharedCatnapTransport> =
+            SharedNetworkLibOS::new(&config, SharedDemiRuntime::default())?;
+        // Pick a local address that is not in use.
+        let local: SocketAddr = TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+
+        let tcp_qd: QDesc = libos.socket(Domain::IPV4, Type::STREAM, Protocol::TCP)?;
+        libos.bind(tcp_qd, local)?;
+        let udp_qd: QDesc = libos.socket(Domain::IPV4, Type::DGRAM, Protocol::UDP)?;
+
+        // Wrap the file descriptors without taking their ownership, so that they are not closed.
+        let tcp_fd: RawFd = libos.raw_fd(tcp_qd)?;
+        let tcp_socket: ManuallyDrop<Socket> = ManuallyDrop::new(unsafe { Socket::from_raw_fd(tcp_fd) });
+        crate::ensure_eq!(tcp_socket.r#type()?, Type::STREAM);
+        crate::ensure_eq!(tcp_socket.local_addr()?.as_socket(), Some(local));
+        let udp_fd: RawFd = libos.raw_fd(udp_qd)?;
+        let udp_socket: ManuallyDrop<Socket> = ManuallyDrop::new(unsafe { Socket::from_raw_fd(udp_fd) });
+        crate::ensure_eq!(udp_socket.r#type()?, Type::DGRAM);
+
+        // Queues that do not exist have no file descriptor.
+        crate::ensure_eq!(libos.raw_fd(QDesc::from(u32::MAX)).is_err(), true);
+
+        Ok(())
+    }
+
+    /// Tests that a connect that is refused in the background sets the pending error of the socket, and that
+    /// retrieving it clears it.
+    #[test]
+    fn test_take_socket_error() -> Result<()> {
+        let config: Config = Config(Yaml::Hash(Default::default()));
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut libos: SharedNetworkLibOS<SharedCatnapTransport> = SharedNetworkLibOS::new(&config, runtime.clone())?;
+        // Pick a remote address on which nobody listens.
+        let remote: SocketAddr = TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+
+        let qd: QDesc = libos.socket(Domain::IPV4, Type::STREAM, Protocol::TCP)?;
+        crate::ensure_eq!(libos.take_socket_error(qd)?.is_none(), true);
+
+        let qt: QToken = libos.connect(qd, remote)?;
+        let handle: TaskHandle = runtime.from_task_id(qt)?;
+        while !handle.has_completed() {
+            runtime.poll();
+        }
+        match runtime.remove_coroutine(&handle).get_result() {
+            Some((_, OperationResult::Failed(e))) if e.errno == libc::ECONNREFUSED => (),
+            Some((_, result)) => anyhow::bail!("connect should have been refused: {:?}", result),
+            None => anyhow::bail!("connect should have completed"),
+        }
+
+        match libos.take_socket_error(qd)? {
+            Some(e) if e.errno == libc::ECONNREFUSED => (),
+            e => anyhow::bail!("pending error should be ECONNREFUSED: {:?}", e),
+        }
+        crate::ensure_eq!(libos.take_socket_error(qd)?.is_none(), true);
+
+        Ok(())
+    }
+
+    /// Tests that the local address of an accepted queue is the address that the client connected to, on a host with
+    /// several addresses.
+    #[test]
+    fn test_getsockname_accepted() -> Result<()> {
+        let config: Config = Config(Yaml::Hash(Default::default()));
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut libos: SharedNetworkLibOS<SharedCatnapTransport> = SharedNetworkLibOS::new(&config, runtime.clone())?;
+        // Listen on a loopback address other than the default one, on a port that is not in use.
+        let local: SocketAddr = TcpListener::bind("127.0.0.2:0")?.local_addr()?;
+
+        let listen_qd: QDesc = libos.socket(Domain::IPV4, Type::STREAM, Protocol::TCP)?;
+        libos.bind(listen_qd, local)?;
+        libos.listen(listen_qd, 1)?;
+        crate::ensure_eq!(libos.getsockname(listen_qd)?, local);
+
+        let qt: QToken = libos.accept(listen_qd)?;
+        let client: TcpStream = TcpStream::connect(local)?;
+        let handle: TaskHandle = runtime.from_task_id(qt)?;
+        while !handle.has_completed() {
+            runtime.poll();
+        }
+        let accepted_qd: QDesc = match runtime.remove_coroutine(&handle).get_result() {
+            Some((_, OperationResult::Accept((qd, remote)))) => {
+                crate::ensure_eq!(SocketAddr::V4(remote), client.local_addr()?);
+                qd
+            },
+            Some((_, result)) => anyhow::bail!("accept should have succeeded: {:?}", result),
+            None => anyhow::bail!("accept should have completed"),
+        };
+        crate::ensure_eq!(libos.getsockname(accepted_qd)?, client.peer_addr()?);
+
+        // Sockets that are not bound have no local address.
+        let unbound_qd: QDesc = libos.socket(Domain::IPV4, Type::STREAM, Protocol::TCP)?;
+        crate::ensure_eq!(libos.getsockname(unbound_qd).is_err(), true);
+
+        Ok(())
+    }
+
+    /// Tests that accepts fail with EAGAIN on a non-blocking queue while no connection is pending, and that connects
+    /// on a non-blocking queue complete in the background once they fail with EINPROGRESS.
+    #[test]
+    fn test_nonblocking_accept_and_connect() -> Result<()> {
+        let config: Config = Config(Yaml::Hash(Default::default()));
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut libos: SharedNetworkLibOS<SharedCatnapTransport> = SharedNetworkLibOS::new(&config, runtime.clone())?;
+        // Pick a local address that is not in use.
+        let local: SocketAddr = TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+
+        let listen_qd: QDesc = libos.socket(Domain::IPV4, Type::STREAM, Protocol::TCP)?;
+        libos.bind(listen_qd, local)?;
+        libos.listen(listen_qd, 1)?;
+        libos.set_nonblocking(listen_qd, true)?;
+        let qt: QToken = libos.accept(listen_qd)?;
+        match wait(&mut runtime, qt)? {
+            OperationResult::Failed(e) if e.errno == libc::EAGAIN => (),
+            result => anyhow::bail!("accept should have failed with EAGAIN: {:?}", result),
+        }
+
+        let client_qd: QDesc = libos.socket(Domain::IPV4, Type::STREAM, Protocol::TCP)?;
+        libos.set_nonblocking(client_qd, true)?;
+        let qt: QToken = libos.connect(client_qd, local)?;
+        match wait(&mut runtime, qt)? {
+            OperationResult::Connect => (),
+            OperationResult::Failed(e) if e.errno == libc::EINPROGRESS => (),
+            result => anyhow::bail!("connect should have succeeded or be in progress: {:?}", result),
+        }
+
+        // The connection completes in the background, and can then be accepted.
+        let accepted_qd: QDesc = loop {
+            let qt: QToken = libos.accept(listen_qd)?;
+            match wait(&mut runtime, qt)? {
+                OperationResult::Accept((qd, _)) => break qd,
+                OperationResult::Failed(e) if e.errno == libc::EAGAIN => (),
+                result => anyhow::bail!("accept should have succeeded or failed with EAGAIN: {:?}", result),
+            }
+        };
+        loop {
+            match libos.connect(client_qd, local) {
+                Err(e) if e.errno == libc::EISCONN => break,
+                Err(e) if e.errno == libc::EINPROGRESS => runtime.poll(),
+                result => anyhow::bail!("connect should be in progress or completed: {:?}", result),
+            };
+        }
+        crate::ensure_eq!(libos.take_socket_error(client_qd)?.is_none(), true);
+        crate::ensure_eq!(libos.getsockname(accepted_qd)?, local);
+
+        Ok(())
+    }
+
+    /// Tests that pops fail with EAGAIN on a non-blocking queue while there is no data to read, and that pushes
+    /// complete right away.
+    #[test]
+    fn test_nonblocking_push_and_pop() -> Result<()> {
+        let config: Config = Config(Yaml::Hash(Default::default()));
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut libos: SharedNetworkLibOS<SharedCatnapTransport> = SharedNetworkLibOS::new(&config, runtime.clone())?;
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0")?;
+
+        let qd: QDesc = libos.socket(Domain::IPV4, Type::STREAM, Protocol::TCP)?;
+        let qt: QToken = libos.connect(qd, listener.local_addr()?)?;
+        match wait(&mut runtime, qt)? {
+            OperationResult::Connect => (),
+            result => anyhow::bail!("connect should have succeeded: {:?}", result),
+        }
+        let (mut stream, _): (TcpStream, SocketAddr) = listener.accept()?;
+        libos.set_nonblocking(qd, true)?;
+
+        let qt: QToken = libos.pop(qd, None)?;
+        match wait(&mut runtime, qt)? {
+            OperationResult::Failed(e) if e.errno == libc::EAGAIN => (),
+            result => anyhow::bail!("pop should have failed with EAGAIN: {:?}", result),
+        }
+
+        // The push completes once its data is queued, and the data is written in the background.
+        let sga: demi_sgarray_t = runtime.into_sgarray(DemiBuffer::from_slice(&[0x5a; 32])?)?;
+        let qt: QToken = libos.push(qd, &sga)?;
+        runtime.sgafree(sga)?;
+        let handle: TaskHandle = runtime.from_task_id(qt)?;
+        runtime.poll();
+        crate::ensure_eq!(handle.has_completed(), true);
+        match runtime.remove_coroutine(&handle).get_result() {
+            Some((_, OperationResult::Push(_))) => (),
+            result => anyhow::bail!("push should have succeeded: {:?}", result),
+        }
+        let mut received: [u8; 32] = [0; 32];
+        let mut num_received: usize = 0;
+        stream.set_nonblocking(true)?;
+        while num_received < received.len() {
+            runtime.poll();
+            match stream.read(&mut received[num_received..]) {
+                Ok(n) => num_received += n,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => (),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        crate::ensure_eq!(received, [0x5a; 32]);
+
+        // Data that arrives becomes readable with a later pop.
+        stream.write_all(&[0xa5; 16])?;
+        let buf: DemiBuffer = loop {
+            let qt: QToken = libos.pop(qd, None)?;
+            match wait(&mut runtime, qt)? {
+                OperationResult::Pop(_, buf) => break buf,
+                OperationResult::Failed(e) if e.errno == libc::EAGAIN => (),
+                result => anyhow::bail!("pop should have returned data or failed with EAGAIN: {:?}", result),
+            }
+        };
+        crate::ensure_eq!(&buf[..], &[0xa5; 16]);
+
+        Ok(())
+    }
+
+    /// Tests that a vectored push sends its slices as one message, in order, and that it fails with EINVAL if all of
+    /// them are empty.
+    #[test]
+    fn test_push_vectored() -> Result<()> {
+        let config: Config = Config(Yaml::Hash(Default::default()));
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut libos: SharedNetworkLibOS<SharedCatnapTransport> = SharedNetworkLibOS::new(&config, runtime.clone())?;
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0")?;
+
+        let qd: QDesc = libos.socket(Domain::IPV4, Type::STREAM, Protocol::TCP)?;
+        let qt: QToken = libos.connect(qd, listener.local_addr()?)?;
+        match wait(&mut runtime, qt)? {
+            OperationResult::Connect => (),
+            result => anyhow::bail!("connect should have succeeded: {:?}", result),
+        }
+        let (mut stream, _): (TcpStream, SocketAddr) = listener.accept()?;
+
+        let header: &[u8] = b"Content-Length: 4\r\n\r\n";
+        let body: &[u8] = b"body";
+        let qt: QToken = libos.push_vectored(qd, &[header, body])?;
+        match wait(&mut runtime, qt)? {
+            OperationResult::Push(_) => (),
+            result => anyhow::bail!("push should have succeeded: {:?}", result),
+        }
+        let mut received: Vec<u8> = vec![0; header.len() + body.len()];
+        stream.read_exact(&mut received)?;
+        crate::ensure_eq!(&received[..header.len()], header);
+        crate::ensure_eq!(&received[header.len()..], body);
+
+        match libos.push_vectored(qd, &[&[], &[]]) {
+            Err(e) if e.errno == libc::EINVAL => Ok(()),
+            result => anyhow::bail!("push of empty slices should have failed with EINVAL: {:?}", result),
+        }
+    }
+
+    /// Tests that a zero-length buffer pushed to a UDP socket is sent as an empty datagram.
+    #[test]
+    fn test_pushto_empty_datagram() -> Result<()> {
+        let config: Config = Config(Yaml::Hash(Default::default()));
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut libos: SharedNetworkLibOS<SharedCatnapTransport> = SharedNetworkLibOS::new(&config, runtime.clone())?;
+        let receiver: UdpSocket = UdpSocket::bind("127.0.0.1:0")?;
+
+        let qd: QDesc = libos.socket(Domain::IPV4, Type::DGRAM, Protocol::UDP)?;
+        let sga: demi_sgarray_t = runtime.into_sgarray(DemiBuffer::new(0))?;
+        let qt: QToken = libos.pushto(qd, &sga, receiver.local_addr()?)?;
+        runtime.sgafree(sga)?;
+        match wait(&mut runtime, qt)? {
+            OperationResult::Push(nbytes) => crate::ensure_eq!(nbytes, 0),
+            result => anyhow::bail!("pushto should have succeeded: {:?}", result),
+        }
+
+        // The datagram should arrive, rather than be skipped.
+        let mut buf: [u8; 16] = [0xff; 16];
+        receiver.set_read_timeout(Some(Duration::from_secs(1)))?;
+        crate::ensure_eq!(receiver.recv(&mut buf)?, 0);
+
+        Ok(())
+    }
+
+    /// Tests that a reply that is pushed from a given local address carries it as its source address, rather than the
+    /// address that the queue is bound to.
+    #[test]
+    fn test_pushto_from() -> Result<()> {
+        let config: Config = Config(Yaml::Hash(Default::default()));
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut libos: SharedNetworkLibOS<SharedCatnapTransport> = SharedNetworkLibOS::new(&config, runtime.clone())?;
+        // Pick a local address that is not in use.
+        let local: SocketAddr = UdpSocket::bind("127.0.0.1:0")?.local_addr()?;
+        let receiver: UdpSocket = UdpSocket::bind("127.0.0.1:0")?;
+        // Every address of the loopback network is local.
+        let src: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 2);
+
+        let qd: QDesc = libos.socket(Domain::IPV4, Type::DGRAM, Protocol::UDP)?;
+        libos.bind(qd, local)?;
+        let sga: demi_sgarray_t = runtime.into_sgarray(DemiBuffer::from_slice(b"hello")?)?;
+        let qt: QToken = libos.pushto_from(qd, &sga, src, receiver.local_addr()?)?;
+        runtime.sgafree(sga)?;
+        match wait(&mut runtime, qt)? {
+            OperationResult::Push(nbytes) => crate::ensure_eq!(nbytes, 5),
+            result => anyhow::bail!("pushto_from should have succeeded: {:?}", result),
+        }
+
+        let mut buf: [u8; 16] = [0; 16];
+        receiver.set_read_timeout(Some(Duration::from_secs(1)))?;
+        let (nbytes, addr): (usize, SocketAddr) = receiver.recv_from(&mut buf)?;
+        crate::ensure_eq!(&buf[..nbytes], b"hello");
+        crate::ensure_eq!(addr, SocketAddr::new(src.into(), local.port()));
+
+        Ok(())
+    }
+
+    /// Tests that pops on a UDP queue on which IP_PKTINFO is set carry the address that datagrams were sent to and the
+    /// index of the interface that they were received on.
+    #[test]
+    fn test_pop_with_pktinfo() -> Result<()> {
+        let config: Config = Config(Yaml::Hash(Default::default()));
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut libos: SharedNetworkLibOS<SharedCatnapTransport> = SharedNetworkLibOS::new(&config, runtime.clone())?;
+        // Pick a local address that is not in use.
+        let local: SocketAddr = UdpSocket::bind("127.0.0.1:0")?.local_addr()?;
+        let sender: UdpSocket = UdpSocket::bind("127.0.0.1:0")?;
+
+        let qd: QDesc = libos.socket(Domain::IPV4, Type::DGRAM, Protocol::UDP)?;
+        libos.bind(qd, local)?;
+        crate::ensure_eq!(
+            libos.get_socket_option(qd, SocketOptionName::PktInfo)?,
+            SocketOption::PktInfo(false)
+        );
+        libos.set_socket_option(qd, SocketOption::PktInfo(true))?;
+        crate::ensure_eq!(
+            libos.get_socket_option(qd, SocketOptionName::PktInfo)?,
+            SocketOption::PktInfo(true)
+        );
+
+        sender.send_to(b"hello", local)?;
+        let qt: QToken = libos.pop(qd, None)?;
+        match wait(&mut runtime, qt)? {
+            OperationResult::PopWithInfo(addr, info, buf) => {
+                crate::ensure_eq!(SocketAddr::V4(addr), sender.local_addr()?);
+                crate::ensure_eq!(info.dst_addr, Ipv4Addr::LOCALHOST);
+                crate::ensure_eq!(info.ifindex, unsafe {
+                    libc::if_nametoindex(b"lo\0".as_ptr() as *const libc::c_char)
+                });
+                crate::ensure_eq!(&buf[..], b"hello");
+            },
+            result => anyhow::bail!("pop should have carried the ancillary data: {:?}", result),
+        }
+
+        Ok(())
+    }
+
+    /// Tests that closing a listening queue fails the accepts that are outstanding on it with EBADF, both when an accept
+    /// is already waiting for a connection and when it has not run yet.
+    #[test]
+    fn test_accept_on_closed_listener() -> Result<()> {
+        let config: Config = Config(Yaml::Hash(Default::default()));
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut libos: SharedNetworkLibOS<SharedCatnapTransport> = SharedNetworkLibOS::new(&config, runtime.clone())?;
+
+        for waiting in [true, false] {
+            // Pick a local address that is not in use.
+            let local: SocketAddr = TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+            let listen_qd: QDesc = libos.socket(Domain::IPV4, Type::STREAM, Protocol::TCP)?;
+            libos.bind(listen_qd, local)?;
+            libos.listen(listen_qd, 1)?;
+            let accept_qt: QToken = libos.accept(listen_qd)?;
+            if waiting {
+                runtime.poll();
+                crate::ensure_eq!(runtime.from_task_id(accept_qt)?.has_completed(), false);
+            }
+
+            let close_qt: QToken = libos.async_close(listen_qd)?;
+            match wait(&mut runtime, close_qt)? {
+                OperationResult::Close => (),
+                result => anyhow::bail!("close should have succeeded: {:?}", result),
+            }
+            match wait(&mut runtime, accept_qt)? {
+                OperationResult::Failed(e) if e.errno == libc::EBADF => (),
+                result => anyhow::bail!(
+                    "accept should have failed with EBADF (waiting={:?}): {:?}",
+                    waiting,
+                    result
+                ),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Tests that the log context of a queue picks up its addresses as it is connected, so that the log lines about
+    /// the connection on either end carry the same identifying fields.
+    #[test]
+    fn test_log_context() -> Result<()> {
+        let config: Config = Config(Yaml::Hash(Default::default()));
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut libos: SharedNetworkLibOS<SharedCatnapTransport> = SharedNetworkLibOS::new(&config, runtime.clone())?;
+        // Pick a local address that is not in use.
+        let local: SocketAddr = TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+
+        let listen_qd: QDesc = libos.socket(Domain::IPV4, Type::STREAM, Protocol::TCP)?;
+        libos.bind(listen_qd, local)?;
+        libos.listen(listen_qd, 1)?;
+        let accept_qt: QToken = libos.accept(listen_qd)?;
+        let client_qd: QDesc = libos.socket(Domain::IPV4, Type::STREAM, Protocol::TCP)?;
+        crate::ensure_eq!(libos.log_context(client_qd).to_string(), format!("qd={:?}", client_qd));
+
+        let connect_qt: QToken = libos.connect(client_qd, local)?;
+        match wait(&mut runtime, connect_qt)? {
+            OperationResult::Connect => (),
+            result => anyhow::bail!("connect should have succeeded: {:?}", result),
+        }
+        let (accepted_qd, client_addr): (QDesc, SocketAddr) = match wait(&mut runtime, accept_qt)? {
+            OperationResult::Accept((qd, remote)) => (qd, SocketAddr::V4(remote)),
+            result => anyhow::bail!("accept should have succeeded: {:?}", result),
+        };
+
+        crate::ensure_eq!(
+            libos.log_context(client_qd),
+            QueueLogContext {
+                qd: client_qd,
+                local: None,
+                remote: Some(local),
+            }
+        );
+        crate::ensure_eq!(
+            libos.log_context(accepted_qd).to_string(),
+            format!("qd={:?} local={} remote={}", accepted_qd, local, client_addr)
+        );
+        crate::ensure_eq!(
+            libos.log_context(listen_qd).to_string(),
+            format!("qd={:?} local={}", listen_qd, local)
+        );
+
+        // Queues that do not exist are only identified by their descriptor.
+        let unknown_qd: QDesc = QDesc::from(u32::MAX);
+        crate::ensure_eq!(
+            libos.log_context(unknown_qd).to_string(),
+            format!("qd={:?}", unknown_qd)
+        );
+
+        Ok(())
+    }
+
+    /// Tests that two LibOSes with different transports share a runtime: each one only operates on its own queues,
+    /// their operations make progress together, and dropping one of them leaves the queues of the other in place.
+    #[test]
+    fn test_two_transports_share_runtime() -> Result<()> {
+        let config: Config = Config(Yaml::Hash(Default::default()));
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut catnap: SharedNetworkLibOS<SharedCatnapTransport> = SharedNetworkLibOS::new(&config, runtime.clone())?;
+        let mut echo: SharedNetworkLibOS<EchoTransport> = SharedNetworkLibOS::new(&config, runtime.clone())?;
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0")?;
+        let echo_addr: SocketAddr = "127.0.0.1:8080".parse()?;
+
+        let catnap_qd: QDesc = catnap.socket(Domain::IPV4, Type::STREAM, Protocol::TCP)?;
+        let echo_qd: QDesc = echo.socket(Domain::IPV4, Type::DGRAM, Protocol::UDP)?;
+        echo.bind(echo_qd, echo_addr)?;
+
+        // Queues are routed to the LibOS that created them.
+        match echo.pop(catnap_qd, None) {
+            Err(e) if e.errno == libc::EINVAL => (),
+            result => anyhow::bail!("pop should have failed with EINVAL: {:?}", result),
+        }
+        match catnap.pop(echo_qd, None) {
+            Err(e) if e.errno == libc::EINVAL => (),
+            result => anyhow::bail!("pop should have failed with EINVAL: {:?}", result),
+        }
+
+        // Operations on both LibOSes interleave on the shared runtime.
+        let connect_qt: QToken = catnap.connect(catnap_qd, listener.local_addr()?)?;
+        let pop_qt: QToken = echo.pop(echo_qd, None)?;
+        let sga: demi_sgarray_t = runtime.into_sgarray(DemiBuffer::from_slice(&[0x5a; 32])?)?;
+        let push_qt: QToken = echo.pushto(echo_qd, &sga, echo_addr)?;
+        runtime.sgafree(sga)?;
+        match wait(&mut runtime, connect_qt)? {
+            OperationResult::Connect => (),
+            result => anyhow::bail!("connect should have succeeded: {:?}", result),
+        }
+        match wait(&mut runtime, push_qt)? {
+            OperationResult::Push(nbytes) => crate::ensure_eq!(nbytes, 32),
+            result => anyhow::bail!("push should have succeeded: {:?}", result),
+        }
+        match wait(&mut runtime, pop_qt)? {
+            OperationResult::Pop(_, buf) => crate::ensure_eq!(&buf[..], &[0x5a; 32]),
+            result => anyhow::bail!("pop should have returned data: {:?}", result),
+        }
+        let _: (TcpStream, SocketAddr) = listener.accept()?;
+
+        // Dropping a LibOS only releases its own queues.
+        drop(echo);
+        crate::ensure_eq!(
+            runtime
+                .get_qtable()
+                .get::<SharedNetworkQueue<EchoTransport>>(&echo_qd)
+                .is_err(),
+            true
+        );
+        crate::ensure_eq!(catnap.raw_fd(catnap_qd).is_ok(), true);
+
+        Ok(())
+    }
+
+    /// Tests that dropping a LibOS whose queues never finish closing returns once the close timeout expires, and that
+    /// the queues are released regardless.
+    #[test]
+    fn test_drop_abandons_stuck_queues() -> Result<()> {
+        let timeout: Duration = Duration::from_millis(50);
+        let config: Config = Config(YamlLoader::load_from_str("demikernel:\n  close_timeout_millis: 50")?.remove(0));
+        crate::ensure_eq!(config.close_timeout()?, timeout);
+        let runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut libos: SharedNetworkLibOS<StuckTransport> = SharedNetworkLibOS::new(&config, runtime.clone())?;
+        let qd: QDesc = libos.socket(Domain::IPV4, Type::DGRAM, Protocol::UDP)?;
+
+        let start: Instant = Instant::now();
+        drop(libos);
+        let elapsed: Duration = start.elapsed();
+        crate::ensure_eq!(elapsed >= timeout, true);
+        crate::ensure_eq!(elapsed < 20 * timeout, true);
+        crate::ensure_eq!(
+            runtime
+                .get_qtable()
+                .get::<SharedNetworkQueue<StuckTransport>>(&qd)
+                .is_err(),
+            true
+        );
+
+        Ok(())
+    }
+
+    /// Tests that a LibOS cannot be created with a negative close timeout.
+    #[test]
+    fn test_invalid_close_timeout() -> Result<()> {
+        let config: Config = Config(YamlLoader::load_from_str("demikernel:\n  close_timeout_millis: -1")?.remove(0));
+        match SharedNetworkLibOS::<StuckTransport>::new(&config, SharedDemiRuntime::default()) {
+            Err(e) if e.errno == libc::EINVAL => (),
+            Err(e) => anyhow::bail!("creating the LibOS should have failed with EINVAL: {:?}", e),
+            Ok(_) => anyhow::bail!("creating the LibOS should have failed"),
+        }
+
+        Ok(())
+    }
+
+    /// Tests that a connect and a pop that are given the same deadline are bounded by it as a whole, and that a wait
+    /// with an explicit timeout on the pop ends at whichever of the timeout and the deadline expires first.
+    #[test]
+    fn test_deadline_bounds_connect_and_pop() -> Result<()> {
+        let config: Config = Config(Yaml::Hash(Default::default()));
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut libos: SharedNetworkLibOS<SharedCatnapTransport> = SharedNetworkLibOS::new(&config, runtime.clone())?;
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0")?;
+        let timeout: Duration = Duration::from_millis(500);
+        let start: Instant = Instant::now();
+        let deadline: Instant = start + timeout;
+
+        let qd: QDesc = libos.socket(Domain::IPV4, Type::STREAM, Protocol::TCP)?;
+        let qt: QToken = libos.connect_with_deadline(qd, listener.local_addr()?, deadline)?;
+        match wait(&mut runtime, qt)? {
+            OperationResult::Connect => (),
+            result => anyhow::bail!("connect should have succeeded: {:?}", result),
+        }
+        // The server never sends anything, so the pop can only time out.
+        let (_server, _) = listener.accept()?;
+        let qt: QToken = libos.pop_with_deadline(qd, None, deadline)?;
+
+        // The explicit timeout expires first, so the wait fails and leaves the pop pending.
+        match runtime.wait_any(&[qt], Some(Duration::from_millis(10))) {
+            Err(e) if e.errno == libc::ETIMEDOUT => (),
+            result => anyhow::bail!("wait should have timed out: {:?}", result.map(|(i, _)| i)),
+        }
+
+        // The deadline expires first, so the pop fails.
+        let (_, handle): (usize, TaskHandle) = runtime.wait_any(&[qt], Some(Duration::from_secs(60)))?;
+        crate::ensure_eq!(start.elapsed() >= timeout, true);
+        crate::ensure_eq!(start.elapsed() < 20 * timeout, true);
+        match runtime.remove_coroutine(&handle).get_result() {
+            Some((_, OperationResult::Failed(e))) if e.errno == libc::ETIMEDOUT => (),
+            Some((_, result)) => anyhow::bail!("pop should have timed out: {:?}", result),
+            None => anyhow::bail!("pop should have completed"),
+        }
+
+        Ok(())
+    }
+
+    /// Gets the CPU time that the calling thread has consumed so far.
+    fn thread_cpu_time() -> Duration {
+        let mut ts: libc::timespec = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+        unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut ts) };
+        Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+    }
+
+    /// Tests that waiting on a pop for an idle socket blocks on the socket instead of spinning on the runtime, and that
+    /// the wait wakes up as soon as data arrives.
+    #[test]
+    fn test_wait_on_idle_socket_blocks() -> Result<()> {
+        let config: Config = Config(Yaml::Hash(Default::default()));
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut libos: SharedNetworkLibOS<SharedCatnapTransport> = SharedNetworkLibOS::new(&config, runtime.clone())?;
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0")?;
+
+        let qd: QDesc = libos.socket(Domain::IPV4, Type::STREAM, Protocol::TCP)?;
+        let qt: QToken = libos.connect(qd, listener.local_addr()?)?;
+        match wait(&mut runtime, qt)? {
+            OperationResult::Connect => (),
+            result => anyhow::bail!("connect should have succeeded: {:?}", result),
+        }
+        let (mut server, _) = listener.accept()?;
+        let qt: QToken = libos.pop(qd, None)?;
+
+        // Nothing arrives, so the wait times out without burning the CPU in the meantime.
+        let timeout: Duration = Duration::from_millis(200);
+        let cpu_start: Duration = thread_cpu_time();
+        match runtime.wait_any(&[qt], Some(timeout)) {
+            Err(e) if e.errno == libc::ETIMEDOUT => (),
+            result => anyhow::bail!("wait should have timed out: {:?}", result.map(|(i, _)| i)),
+        }
+        crate::ensure_eq!(thread_cpu_time() - cpu_start < timeout / 2, true);
+
+        // Data arrives, so the pop completes long before the timeout expires.
+        server.write_all(b"hello")?;
+        let start: Instant = Instant::now();
+        let (_, handle): (usize, TaskHandle) = runtime.wait_any(&[qt], Some(Duration::from_secs(60)))?;
+        crate::ensure_eq!(start.elapsed() < Duration::from_secs(10), true);
+        match runtime.remove_coroutine(&handle).get_result() {
+            Some((_, OperationResult::Pop(_, buf))) => crate::ensure_eq!(&buf[..], b"hello"),
+            Some((_, result)) => anyhow::bail!("pop should have succeeded: {:?}", result),
+            None => anyhow::bail!("pop should have completed"),
+        }
+
+        Ok(())
+    }
+}
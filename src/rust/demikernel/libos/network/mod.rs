@@ -19,6 +19,10 @@ use crate::{
     runtime::{
         fail::Fail,
         memory::MemoryRuntime,
+        network::types::{
+            SocketOption,
+            SocketOptionName,
+        },
         scheduler::TaskHandle,
         types::{
             demi_qresult_t,
@@ -29,7 +33,16 @@ use crate::{
         SharedDemiRuntime,
     },
 };
-use ::std::net::SocketAddr;
+use ::std::{
+    net::{
+        Ipv4Addr,
+        SocketAddr,
+    },
+    time::Duration,
+};
+
+#[cfg(target_os = "linux")]
+use ::std::os::fd::RawFd;
 
 #[cfg(feature = "catcollar-libos")]
 use crate::catcollar::CatcollarLibOS;
@@ -151,6 +164,225 @@ impl NetworkLibOS {
         }
     }
 
+    /// Changes the backlog of a passive socket.
+    pub fn set_backlog(&mut self, sockqd: QDesc, mut backlog: usize) -> Result<(), Fail> {
+        // Truncate backlog length.
+        if backlog > SOMAXCONN as usize {
+            let cause: String = format!(
+                "backlog length is too large, truncating (qd={:?}, backlog={:?})",
+                sockqd, backlog
+            );
+            debug!("set_backlog(): {}", &cause);
+            backlog = SOMAXCONN as usize;
+        }
+
+        // Round up backlog length.
+        if backlog == 0 {
+            backlog = 1;
+        }
+
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.set_backlog(sockqd, backlog),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+            },
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.set_backlog(sockqd, backlog),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Sets the idle timeout of a connected TCP socket.
+    pub fn set_idle_timeout(&mut self, sockqd: QDesc, timeout: Option<Duration>) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.set_idle_timeout(sockqd, timeout),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+            },
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.set_idle_timeout(sockqd, timeout),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Sets the transmit priority of a connected TCP socket.
+    pub fn set_tx_priority(&mut self, sockqd: QDesc, priority: u8) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.set_tx_priority(sockqd, priority),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+            },
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.set_tx_priority(sockqd, priority),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Sets whether urgent data received on a connected TCP socket is delivered inline.
+    pub fn set_oob_inline(&mut self, sockqd: QDesc, oob_inline: bool) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.set_oob_inline(sockqd, oob_inline),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+            },
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.set_oob_inline(sockqd, oob_inline),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Sets an option on a socket.
+    pub fn set_socket_option(&mut self, sockqd: QDesc, option: SocketOption) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.set_socket_option(sockqd, option),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos } => libos.set_socket_option(sockqd, option),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+            },
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.set_socket_option(sockqd, option),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Gets the value of an option of a socket.
+    pub fn get_socket_option(&self, sockqd: QDesc, name: SocketOptionName) -> Result<SocketOption, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.get_socket_option(sockqd, name),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos } => libos.get_socket_option(sockqd, name),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+            },
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.get_socket_option(sockqd, name),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Takes the urgent byte that was received out of band on a connected TCP socket.
+    pub fn recv_oob(&mut self, sockqd: QDesc) -> Result<u8, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.recv_oob(sockqd),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+            },
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.recv_oob(sockqd),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Gets the local address of a socket.
+    pub fn getsockname(&self, sockqd: QDesc) -> Result<SocketAddr, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.getsockname(sockqd),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos } => libos.getsockname(sockqd),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+            },
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.getsockname(sockqd),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Retrieves and clears the pending error of a socket.
+    pub fn take_socket_error(&mut self, sockqd: QDesc) -> Result<Option<Fail>, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.take_socket_error(sockqd),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos } => libos.take_socket_error(sockqd),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+            },
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.take_socket_error(sockqd),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Gets the file descriptor of the POSIX socket that backs a socket. Only Catnap sockets are backed by one.
+    #[cfg(target_os = "linux")]
+    pub fn raw_fd(&self, sockqd: QDesc) -> Result<RawFd, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+            },
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos } => libos.raw_fd(sockqd),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+            },
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Sets whether operations on a socket fail instead of blocking. Only Catnap sockets support non-blocking mode.
+    #[cfg(target_os = "linux")]
+    pub fn set_nonblocking(&mut self, sockqd: QDesc, nonblocking: bool) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+            },
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos } => libos.set_nonblocking(sockqd, nonblocking),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+            },
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
     /// Accepts an incoming connection on a TCP socket.
     pub fn accept(&mut self, sockqd: QDesc) -> Result<QToken, Fail> {
         match self {
@@ -183,6 +415,24 @@ impl NetworkLibOS {
         }
     }
 
+    /// Resolves the link address of the next hop towards a remote host.
+    pub fn resolve(&mut self, dest: Ipv4Addr) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.resolve(dest),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+            },
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.resolve(dest),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
     pub fn async_close(&mut self, sockqd: QDesc) -> Result<QToken, Fail> {
         match self {
             #[cfg(feature = "catpowder-libos")]
@@ -214,6 +464,60 @@ impl NetworkLibOS {
         }
     }
 
+    /// Pushes a message that is gathered from several byte slices to a socket.
+    pub fn push_vectored(&mut self, sockqd: QDesc, bufs: &[&[u8]]) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.push_vectored(sockqd, bufs),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos } => libos.push_vectored(sockqd, bufs),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+            },
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.push_vectored(sockqd, bufs),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Pushes a scatter-gather array to a TCP socket, holding the data back until the next push.
+    pub fn push_more(&mut self, sockqd: QDesc, sga: &demi_sgarray_t) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.push_more(sockqd, sga),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+            },
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.push_more(sockqd, sga),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Sends the data that is buffered on a TCP socket right away.
+    pub fn flush(&mut self, sockqd: QDesc) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.flush(sockqd),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+            },
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.flush(sockqd),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
     /// Pushes a scatter-gather array to a UDP socket.
     pub fn pushto(&mut self, sockqd: QDesc, sga: &demi_sgarray_t, to: SocketAddr) -> Result<QToken, Fail> {
         match self {
@@ -230,6 +534,30 @@ impl NetworkLibOS {
         }
     }
 
+    /// Pushes a scatter-gather array to a UDP socket, from the local address `from`.
+    pub fn pushto_from(
+        &mut self,
+        sockqd: QDesc,
+        sga: &demi_sgarray_t,
+        from: Ipv4Addr,
+        to: SocketAddr,
+    ) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.pushto_from(sockqd, sga, from, to),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos } => libos.pushto_from(sockqd, sga, from, to),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+            },
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.pushto_from(sockqd, sga, from, to),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
     /// Pops data from a socket.
     pub fn pop(&mut self, sockqd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
         match self {
@@ -246,19 +574,71 @@ impl NetworkLibOS {
         }
     }
 
-    /// Waits for any operation in an I/O queue.
-    pub fn poll(&mut self) {
+    /// Pops data from a socket directly into a caller-provided scatter-gather array.
+    pub fn pop_into(&mut self, sockqd: QDesc, sga: &demi_sgarray_t) -> Result<QToken, Fail> {
         match self {
             #[cfg(feature = "catpowder-libos")]
-            NetworkLibOS::Catpowder { runtime, libos: _ } => runtime.poll_and_advance_clock(),
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.pop_into(sockqd, sga),
             #[cfg(all(feature = "catnap-libos"))]
-            NetworkLibOS::Catnap { runtime, libos: _ } => runtime.poll_and_advance_clock(),
+            NetworkLibOS::Catnap { runtime: _, libos } => libos.pop_into(sockqd, sga),
             #[cfg(feature = "catcollar-libos")]
-            NetworkLibOS::Catcollar { runtime, libos: _ } => runtime.poll_and_advance_clock(),
+            NetworkLibOS::Catcollar { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+            },
             #[cfg(feature = "catnip-libos")]
-            NetworkLibOS::Catnip { runtime, libos: _ } => runtime.poll_and_advance_clock(),
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.pop_into(sockqd, sga),
             #[cfg(feature = "catloop-libos")]
-            NetworkLibOS::Catloop { runtime, libos: _ } => runtime.poll_and_advance_clock(),
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Pops up to `size` bytes from a socket, returning the data that arrived when `timeout` expires.
+    pub fn pop_with_timeout(&mut self, sockqd: QDesc, size: usize, timeout: Duration) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.pop_with_timeout(sockqd, size, timeout),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+            },
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.pop_with_timeout(sockqd, size, timeout),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Polls the scheduler at most `max_iterations` times and returns the number of tasks that were polled.
+    pub fn poll(&mut self, max_iterations: usize) -> usize {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime, libos: _ } => runtime.poll_bounded(max_iterations),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime, libos: _ } => runtime.poll_bounded(max_iterations),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime, libos: _ } => runtime.poll_bounded(max_iterations),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime, libos: _ } => runtime.poll_bounded(max_iterations),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime, libos: _ } => runtime.poll_bounded(max_iterations),
+        }
+    }
+
+    /// Checks whether the underlying runtime is quiescent.
+    pub fn is_quiescent(&self) -> bool {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime, libos: _ } => runtime.is_quiescent(),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime, libos: _ } => runtime.is_quiescent(),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime, libos: _ } => runtime.is_quiescent(),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime, libos: _ } => runtime.is_quiescent(),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime, libos: _ } => runtime.is_quiescent(),
         }
     }
 
@@ -278,6 +658,38 @@ impl NetworkLibOS {
         }
     }
 
+    /// Gets the runtime on top of which the target LibOS runs.
+    pub fn get_runtime(&self) -> SharedDemiRuntime {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime, libos: _ } => runtime.clone(),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime, libos: _ } => runtime.clone(),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime, libos: _ } => runtime.clone(),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime, libos: _ } => runtime.clone(),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime, libos: _ } => runtime.clone(),
+        }
+    }
+
+    /// Waits for any of the operations of `qts` to complete or `timeout` to expire.
+    pub fn wait_any(&mut self, qts: &[QToken], timeout: Option<Duration>) -> Result<(usize, TaskHandle), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime, libos: _ } => runtime.wait_any(qts, timeout),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime, libos: _ } => runtime.wait_any(qts, timeout),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime, libos: _ } => runtime.wait_any(qts, timeout),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime, libos: _ } => runtime.wait_any(qts, timeout),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime, libos: _ } => runtime.wait_any(qts, timeout),
+        }
+    }
+
     pub fn pack_result(&mut self, handle: TaskHandle, qt: QToken) -> Result<demi_qresult_t, Fail> {
         match self {
             #[cfg(feature = "catpowder-libos")]
@@ -15,6 +15,11 @@ use crate::runtime::{
             state::SocketStateMachine,
         },
         transport::NetworkTransport,
+        types::{
+            PacketInfo,
+            SocketOption,
+            SocketOptionName,
+        },
     },
     queue::{
         IoQueue,
@@ -24,22 +29,37 @@ use crate::runtime::{
         TaskHandle,
         Yielder,
     },
+    QDesc,
     QToken,
     SharedObject,
 };
+use ::futures::FutureExt;
 use ::socket2::{
     Domain,
     Type,
 };
 use ::std::{
     any::Any,
-    net::SocketAddr,
+    fmt,
+    future::Future,
+    net::{
+        Ipv4Addr,
+        SocketAddr,
+    },
     ops::{
         Deref,
         DerefMut,
     },
 };
 
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Number of times that a push is retried after the transport ran out of buffers. Retries back off exponentially, so
+/// a push gives up after yielding for about `2^MAX_PUSH_RETRIES` scheduler quanta.
+const MAX_PUSH_RETRIES: usize = 8;
+
 //======================================================================================================================
 // Structures
 //======================================================================================================================
@@ -59,11 +79,25 @@ pub struct NetworkQueue<T: NetworkTransport> {
     remote: Option<SocketAddr>,
     /// Underlying network transport.
     transport: T,
+    /// Last error that an asynchronous operation failed with, until it is retrieved.
+    pending_error: Option<Fail>,
+    /// Do operations fail instead of blocking?
+    nonblocking: bool,
 }
 
 #[derive(Clone)]
 pub struct SharedNetworkQueue<T: NetworkTransport>(SharedObject<NetworkQueue<T>>);
 
+/// Fields that identify a queue in log lines. The addresses are filled in as the queue is bound and connected, so all
+/// the lines about a connection carry the same fields once it is established, and can be told apart from the lines
+/// about other connections.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueLogContext {
+    pub qd: QDesc,
+    pub local: Option<SocketAddr>,
+    pub remote: Option<SocketAddr>,
+}
+
 //======================================================================================================================
 // Associated Functions
 //======================================================================================================================
@@ -89,6 +123,8 @@ impl<T: NetworkTransport> SharedNetworkQueue<T> {
             local: None,
             remote: None,
             transport: transport.clone(),
+            pending_error: None,
+            nonblocking: false,
         })))
     }
 
@@ -139,25 +175,38 @@ impl<T: NetworkTransport> SharedNetworkQueue<T> {
 
     /// Asynchronously accepts a new connection on the queue. This function contains all of the single-queue,
     /// asynchronous code necessary to run an accept and any single-queue functionality after the accept completes.
+    /// If the queue is closed while the accept is waiting, the accept fails with `EBADF`, whatever error the close
+    /// caused underneath.
     pub async fn accept_coroutine(&mut self, yielder: Yielder) -> Result<Self, Fail> {
         self.state_machine.may_accept()?;
-        match self.transport.clone().accept(&mut self.socket, yielder).await {
+        let nonblocking: bool = self.nonblocking;
+        let mut transport: T = self.transport.clone();
+        let accept_future = transport.accept(&mut self.socket, yielder);
+        match run_or_would_block(nonblocking, accept_future, libc::EAGAIN, "accept").await {
             // Operation completed.
-            Ok((new_socket, saddr)) => {
+            Ok((new_socket, local, saddr)) => {
                 trace!("connection accepted ({:?})", new_socket);
                 Ok(Self(SharedObject::new(NetworkQueue {
                     qtype: self.qtype,
                     state_machine: SocketStateMachine::new_established(),
                     socket: new_socket,
-                    local: None,
+                    local: Some(local),
                     remote: Some(saddr),
                     transport: self.transport.clone(),
+                    pending_error: None,
+                    nonblocking: false,
                 })))
             },
             Err(Fail { errno, cause: _ }) if errno == libc::EBADF => {
                 // Socket has been closed.
                 Err(Fail::new(errno, "socket was closed"))
             },
+            // The close either cancelled the accept or made the underlying accept fail.
+            Err(e) if !self.state_machine.is_listening() => {
+                let cause: String = format!("socket was closed while accepting (error={:?})", e.errno);
+                warn!("accept_coroutine(): {}", &cause);
+                Err(Fail::new(libc::EBADF, &cause))
+            },
             Err(e) => Err(e),
         }
     }
@@ -191,11 +240,24 @@ impl<T: NetworkTransport> SharedNetworkQueue<T> {
                 // If connect does not succeed, we close the socket.
                 self.state_machine.prepare(SocketOp::Closed)?;
                 self.state_machine.commit();
+                self.pending_error = Some(e.clone());
                 Err(e)
             },
         }
     }
 
+    /// Fails a connect that was given up on before the transport was done with it, as when its deadline expires. The
+    /// queue is left closed, as on any other failed connect, and `e` is returned back.
+    pub fn abort_connect(&mut self, e: Fail) -> Fail {
+        if let Err(state_error) = self.state_machine.prepare(SocketOp::Closed) {
+            warn!("abort_connect(): {:?}", state_error);
+        } else {
+            self.state_machine.commit();
+        }
+        self.pending_error = Some(e.clone());
+        e
+    }
+
     /// Start an asynchronous coroutine to close this queue.
     pub fn close<F>(&mut self, coroutine_constructor: F) -> Result<QToken, Fail>
     where
@@ -220,6 +282,14 @@ impl<T: NetworkTransport> SharedNetworkQueue<T> {
         }
     }
 
+    /// Asynchronously closes this queue on behalf of a LibOS that is going away. Unlike [close](Self::close), this
+    /// does not schedule a coroutine, so the caller decides how long to wait for the close to complete.
+    pub async fn shutdown_close(&mut self, yielder: Yielder) -> Result<(), Fail> {
+        self.state_machine.prepare(SocketOp::Close)?;
+        self.state_machine.commit();
+        self.close_coroutine(yielder).await
+    }
+
     /// Asynchronously closes this queue. This function contains all of the single-queue, asynchronous code necessary
     /// to close a queue and any single-queue functionality after the close completes.
     pub async fn close_coroutine(&mut self, yielder: Yielder) -> Result<(), Fail> {
@@ -244,20 +314,64 @@ impl<T: NetworkTransport> SharedNetworkQueue<T> {
     }
 
     /// Asynchronously push data to the queue. This function contains all of the single-queue, asynchronous code
-    /// necessary to push to the queue and any single-queue functionality after the push completes.
+    /// necessary to push to the queue and any single-queue functionality after the push completes. Running out of
+    /// transmit buffers (`ENOBUFS`) is transient, so the push is retried with an exponential backoff. It fails with
+    /// `EAGAIN` if buffers are still exhausted after [MAX_PUSH_RETRIES] retries, or right away in non-blocking mode.
+    /// Returns the number of bytes that were accepted, which is less than the length of `buf` if the transport only
+    /// took part of it. The data that was not accepted is left in `buf`. Datagrams go out from the local address `src`,
+    /// if given, rather than from the address that the socket is bound to.
     pub async fn push_coroutine(
         &mut self,
         buf: &mut DemiBuffer,
         addr: Option<SocketAddr>,
+        src: Option<Ipv4Addr>,
         yielder: Yielder,
-    ) -> Result<(), Fail> {
+    ) -> Result<usize, Fail> {
         self.state_machine.may_push()?;
-        match self.transport.clone().push(&mut self.socket, buf, addr, yielder).await {
+        let len: usize = buf.len();
+        let nonblocking: bool = self.nonblocking;
+        let mut queued: bool = false;
+        let mut retries: usize = 0;
+        let result: Result<(), Fail> = loop {
+            let mut transport: T = self.transport.clone();
+            let push_future = transport.push(&mut self.socket, buf, addr, src, yielder.clone());
+            let result: Result<(), Fail> = if nonblocking {
+                // Data that cannot be written right away stays queued in the transport, and is written once the
+                // socket becomes writable. Pushes in non-blocking mode thus complete as soon as their data is queued.
+                push_future.now_or_never().unwrap_or_else(|| {
+                    queued = true;
+                    Ok(())
+                })
+            } else {
+                push_future.await
+            };
+            match result {
+                Err(e) if e.errno == libc::ENOBUFS => {
+                    if nonblocking || retries == MAX_PUSH_RETRIES {
+                        let cause: String = format!("transmit buffers are exhausted (retries={:?})", retries);
+                        warn!("push_coroutine(): {}", cause);
+                        break Err(Fail::new(libc::EAGAIN, &cause));
+                    }
+                    if let Err(e) = yielder.yield_times(1 << retries).await {
+                        break Err(e);
+                    }
+                    retries += 1;
+                },
+                result => break result,
+            }
+        };
+        match result {
             Ok(()) => {
-                debug_assert_eq!(buf.len(), 0);
-                Ok(())
+                // A push that was left queued did not get to empty the buffer, but all of its data was accepted.
+                if queued && !buf.is_empty() {
+                    buf.trim(buf.len()).expect("should be able to trim the whole buffer");
+                }
+                Ok(len - buf.len())
+            },
+            Err(e) => {
+                self.pending_error = Some(e.clone());
+                Err(e)
             },
-            Err(e) => return Err(e),
         }
     }
 
@@ -278,21 +392,18 @@ impl<T: NetworkTransport> SharedNetworkQueue<T> {
         &mut self,
         size: Option<usize>,
         yielder: Yielder,
-    ) -> Result<(Option<SocketAddr>, DemiBuffer), Fail> {
+    ) -> Result<(Option<SocketAddr>, Option<PacketInfo>, DemiBuffer), Fail> {
         self.state_machine.may_pop()?;
         let size: usize = size.unwrap_or(limits::RECVBUF_SIZE_MAX);
-        let mut buf: DemiBuffer = DemiBuffer::new(size as u16);
-
-        // Check that we allocated a DemiBuffer that is big enough.
-        debug_assert_eq!(buf.len(), size);
-        match self
-            .transport
-            .clone()
-            .pop(&mut self.socket, &mut buf, size, yielder)
-            .await
-        {
-            Ok(addr) => Ok((addr, buf)),
-            Err(e) => Err(e),
+        let nonblocking: bool = self.nonblocking;
+        let mut transport: T = self.transport.clone();
+        let pop_future = transport.pop(&mut self.socket, size, yielder);
+        match run_or_would_block(nonblocking, pop_future, libc::EAGAIN, "pop").await {
+            Ok(result) => Ok(result),
+            Err(e) => {
+                self.pending_error = Some(e.clone());
+                Err(e)
+            },
         }
     }
 
@@ -334,6 +445,72 @@ impl<T: NetworkTransport> SharedNetworkQueue<T> {
     pub fn remote(&self) -> Option<SocketAddr> {
         self.remote
     }
+
+    /// Gets the fields that identify the target queue, whose descriptor is `qd`, in log lines.
+    pub fn log_context(&self, qd: QDesc) -> QueueLogContext {
+        QueueLogContext {
+            qd,
+            local: self.local,
+            remote: self.remote,
+        }
+    }
+
+    /// Sets whether operations on the target queue complete right away with an error instead of blocking, like
+    /// O_NONBLOCK does.
+    pub fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.nonblocking = nonblocking;
+    }
+
+    /// Checks whether the target queue is in non-blocking mode.
+    pub fn is_nonblocking(&self) -> bool {
+        self.nonblocking
+    }
+
+    /// Sets the socket option `option` on the underlying socket in the network transport.
+    pub fn set_socket_option(&mut self, option: SocketOption) -> Result<(), Fail> {
+        self.transport.clone().set_socket_option(&mut self.socket, option)
+    }
+
+    /// Gets the value of the socket option `name` of the underlying socket in the network transport.
+    pub fn get_socket_option(&mut self, name: SocketOptionName) -> Result<SocketOption, Fail> {
+        self.transport.clone().get_socket_option(&mut self.socket, name)
+    }
+
+    /// Takes the last error that an asynchronous operation on the target queue failed with, so that it is only
+    /// reported once.
+    pub fn take_error(&mut self) -> Option<Fail> {
+        self.pending_error.take()
+    }
+
+    /// Gets the descriptor of the underlying socket in the network transport.
+    pub fn socket(&self) -> &T::SocketDescriptor {
+        &self.socket
+    }
+}
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Runs `future` to completion. In non-blocking mode, `future` is only polled once instead, and the operation `op`
+/// fails with `errno` if it would have blocked.
+async fn run_or_would_block<R>(
+    nonblocking: bool,
+    future: impl Future<Output = Result<R, Fail>>,
+    errno: i32,
+    op: &str,
+) -> Result<R, Fail> {
+    if !nonblocking {
+        return future.await;
+    }
+    match future.now_or_never() {
+        Some(result) => result,
+        None => {
+            let cause: String = format!("{} would block", op);
+            debug!("run_or_would_block(): {}", cause);
+            Err(Fail::new(errno, &cause))
+        },
+    }
 }
 
 //======================================================================================================================
@@ -358,6 +535,19 @@ impl<T: NetworkTransport> IoQueue for SharedNetworkQueue<T> {
     }
 }
 
+impl fmt::Display for QueueLogContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "qd={:?}", self.qd)?;
+        if let Some(local) = self.local {
+            write!(f, " local={}", local)?;
+        }
+        if let Some(remote) = self.remote {
+            write!(f, " remote={}", remote)?;
+        }
+        Ok(())
+    }
+}
+
 impl<T: NetworkTransport> Deref for SharedNetworkQueue<T> {
     type Target = NetworkQueue<T>;
 
@@ -371,3 +561,184 @@ impl<T: NetworkTransport> DerefMut for SharedNetworkQueue<T> {
         self.0.deref_mut()
     }
 }
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::SharedNetworkQueue;
+    use crate::{
+        demikernel::config::Config,
+        runtime::{
+            fail::Fail,
+            memory::DemiBuffer,
+            network::{
+                transport::NetworkTransport,
+                types::PacketInfo,
+            },
+            scheduler::Yielder,
+            SharedDemiRuntime,
+        },
+    };
+    use ::anyhow::Result;
+    use ::futures::{
+        task::noop_waker_ref,
+        FutureExt,
+    };
+    use ::socket2::{
+        Domain,
+        Type,
+    };
+    use ::std::{
+        cell::Cell,
+        cmp::min,
+        net::{
+            Ipv4Addr,
+            SocketAddr,
+        },
+        rc::Rc,
+        task::{
+            Context,
+            Poll,
+        },
+    };
+
+    /// Maximum number of times that a push is polled before giving up on it.
+    const MAX_POLLS: usize = 1024;
+
+    /// Size of the buffers that are pushed.
+    const BUFFER_SIZE: usize = 32;
+
+    /// Transport whose pushes fail with `ENOBUFS` a given number of times before they succeed, and accept at most a
+    /// given number of bytes.
+    #[derive(Clone)]
+    struct FlakyTransport {
+        failures: Rc<Cell<usize>>,
+        attempts: Rc<Cell<usize>>,
+        max_accepted: usize,
+    }
+
+    impl FlakyTransport {
+        fn new(failures: usize, max_accepted: usize) -> Self {
+            Self {
+                failures: Rc::new(Cell::new(failures)),
+                attempts: Rc::new(Cell::new(0)),
+                max_accepted,
+            }
+        }
+    }
+
+    impl NetworkTransport for FlakyTransport {
+        type SocketDescriptor = ();
+
+        fn new(_config: &Config, _runtime: &mut SharedDemiRuntime) -> Result<Self, Fail> {
+            unreachable!("flaky transports are built by the tests")
+        }
+
+        fn socket(&mut self, _domain: Domain, _typ: Type) -> Result<(), Fail> {
+            Ok(())
+        }
+
+        fn bind(&mut self, _sd: &mut (), _local: SocketAddr) -> Result<(), Fail> {
+            Ok(())
+        }
+
+        fn listen(&mut self, _sd: &mut (), _backlog: usize) -> Result<(), Fail> {
+            Ok(())
+        }
+
+        fn hard_close(&mut self, _sd: &mut ()) -> Result<(), Fail> {
+            Ok(())
+        }
+
+        async fn accept(&mut self, _sd: &mut (), _yielder: Yielder) -> Result<((), SocketAddr, SocketAddr), Fail> {
+            Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+        }
+
+        async fn connect(&mut self, _sd: &mut (), _remote: SocketAddr, _yielder: Yielder) -> Result<(), Fail> {
+            Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+        }
+
+        async fn push(
+            &mut self,
+            _sd: &mut (),
+            buf: &mut DemiBuffer,
+            _addr: Option<SocketAddr>,
+            _src: Option<Ipv4Addr>,
+            _yielder: Yielder,
+        ) -> Result<(), Fail> {
+            self.attempts.set(self.attempts.get() + 1);
+            if self.failures.get() > 0 {
+                self.failures.set(self.failures.get() - 1);
+                return Err(Fail::new(libc::ENOBUFS, "no buffer space available"));
+            }
+            buf.adjust(min(buf.len(), self.max_accepted))?;
+            Ok(())
+        }
+
+        async fn pop(
+            &mut self,
+            _sd: &mut (),
+            _size: usize,
+            _yielder: Yielder,
+        ) -> Result<(Option<SocketAddr>, Option<PacketInfo>, DemiBuffer), Fail> {
+            Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+        }
+
+        async fn close(&mut self, _sd: &mut (), _yielder: Yielder) -> Result<(), Fail> {
+            Ok(())
+        }
+    }
+
+    /// Pushes a buffer through `transport`, polling the push until it completes, and returns its result.
+    fn push(transport: &mut FlakyTransport) -> Result<Result<usize, Fail>> {
+        let mut queue: SharedNetworkQueue<FlakyTransport> =
+            SharedNetworkQueue::new(Domain::IPV4, Type::DGRAM, transport)?;
+        let remote: SocketAddr = "127.0.0.1:8080".parse()?;
+        let mut buf: DemiBuffer = DemiBuffer::from_slice(&[0xab; BUFFER_SIZE])?;
+        let mut ctx: Context = Context::from_waker(noop_waker_ref());
+        let mut push_future = queue
+            .push_coroutine(&mut buf, Some(remote), None, Yielder::new())
+            .boxed_local();
+        for _ in 0..MAX_POLLS {
+            if let Poll::Ready(result) = push_future.poll_unpin(&mut ctx) {
+                return Ok(result);
+            }
+        }
+        anyhow::bail!("push should have completed")
+    }
+
+    /// Tests that a push that runs out of transmit buffers is retried until it goes through.
+    #[test]
+    fn test_push_retries_on_enobufs() -> Result<()> {
+        let mut transport: FlakyTransport = FlakyTransport::new(3, BUFFER_SIZE);
+        crate::ensure_eq!(push(&mut transport)?.ok(), Some(BUFFER_SIZE));
+        crate::ensure_eq!(transport.attempts.get(), 4);
+
+        Ok(())
+    }
+
+    /// Tests that a push that the transport only partially accepts reports how many bytes were accepted.
+    #[test]
+    fn test_push_partial() -> Result<()> {
+        let mut transport: FlakyTransport = FlakyTransport::new(0, BUFFER_SIZE / 4);
+        crate::ensure_eq!(push(&mut transport)?.ok(), Some(BUFFER_SIZE / 4));
+
+        Ok(())
+    }
+
+    /// Tests that a push gives up with EAGAIN once transmit buffers stay exhausted for too long.
+    #[test]
+    fn test_push_gives_up_on_enobufs() -> Result<()> {
+        let mut transport: FlakyTransport = FlakyTransport::new(usize::MAX, BUFFER_SIZE);
+        match push(&mut transport)? {
+            Err(e) => crate::ensure_eq!(e.errno, libc::EAGAIN),
+            Ok(_) => anyhow::bail!("push should have failed"),
+        }
+        crate::ensure_eq!(transport.attempts.get(), super::MAX_PUSH_RETRIES + 1);
+
+        Ok(())
+    }
+}
@@ -6,45 +6,90 @@
 //======================================================================================================================
 
 use crate::{
-    inetstack::protocols::{
-        arp::SharedArpPeer,
-        ethernet2::{
-            EtherType2,
-            Ethernet2Header,
+    inetstack::{
+        pcap::{
+            PcapTransport,
+            SharedPcapWriter,
+        },
+        protocols::{
+            arp::SharedArpPeer,
+            ethernet2::{
+                padding::PaddingTransport,
+                vlan::VlanTransport,
+                EtherType2,
+                Ethernet2Header,
+            },
+            ipv4::{
+                egress::{
+                    Ipv4EgressHook,
+                    SharedEgressTransport,
+                },
+                ingress::Ipv4IngressHook,
+                loopback::LoopbackTransport,
+            },
+            raw::queue::SharedRawQueue,
+            tcp::{
+                queue::SharedTcpQueue,
+                AcceptFilter,
+                TcpAcceptReadyHook,
+                TcpInfo,
+                TcpStateCounts,
+            },
+            udp::queue::SharedUdpQueue,
+            Peer,
+            ProtocolHandler,
         },
-        Peer,
     },
     pal::constants::{
         AF_INET_VALUE,
         SOCK_DGRAM,
+        SOCK_RAW,
         SOCK_STREAM,
     },
     runtime::{
         fail::Fail,
         limits,
-        memory::DemiBuffer,
+        memory::{
+            sgarray_capacity,
+            DemiBuffer,
+        },
         network::{
             config::{
                 ArpConfig,
+                Ethernet2Config,
+                PcapConfig,
+                RoutingConfig,
                 TcpConfig,
                 UdpConfig,
             },
-            types::MacAddress,
+            types::{
+                BufferUsage,
+                ErrorCounters,
+                MacAddress,
+                Readiness,
+                SocketOption,
+                SocketOptionName,
+            },
             unwrap_socketaddr,
             NetworkRuntime,
         },
         queue::{
+            pop_into_coroutine,
+            IoQueue,
+            NetworkQueue,
             Operation,
             OperationResult,
             OperationTask,
             QDesc,
             QToken,
             QType,
+            QueueInfo,
         },
         scheduler::{
             TaskHandle,
             Yielder,
         },
+        types::demi_sgarray_t,
         SharedBox,
         SharedDemiRuntime,
         SharedObject,
@@ -52,6 +97,7 @@ use crate::{
 };
 use ::libc::c_int;
 use ::std::{
+    convert::TryFrom,
     net::{
         Ipv4Addr,
         SocketAddr,
@@ -62,6 +108,7 @@ use ::std::{
         DerefMut,
     },
     pin::Pin,
+    time::Duration,
 };
 
 #[cfg(feature = "profiler")]
@@ -76,7 +123,10 @@ pub mod test_helpers;
 
 pub mod collections;
 pub mod options;
+pub mod pcap;
+pub mod pool;
 pub mod protocols;
+pub mod steering;
 
 //======================================================================================================================
 // Constants
@@ -93,6 +143,7 @@ pub struct InetStack {
     ipv4: Peer,
     runtime: SharedDemiRuntime,
     transport: SharedBox<dyn NetworkRuntime>,
+    egress: SharedEgressTransport,
     local_link_addr: MacAddress,
 }
 
@@ -106,20 +157,43 @@ pub struct SharedInetStack(SharedObject<InetStack>);
 impl SharedInetStack {
     pub fn new(
         mut runtime: SharedDemiRuntime,
-        transport: SharedBox<dyn NetworkRuntime>,
+        mut transport: SharedBox<dyn NetworkRuntime>,
         local_link_addr: MacAddress,
         local_ipv4_addr: Ipv4Addr,
         udp_config: UdpConfig,
         tcp_config: TcpConfig,
         rng_seed: [u8; 32],
         arp_config: ArpConfig,
+        pcap_config: PcapConfig,
+        ethernet2_config: Ethernet2Config,
+        routing_config: RoutingConfig,
     ) -> Result<Self, Fail> {
+        // Tap the transport before handing it out, so that every frame sent or received by the stack is captured.
+        if pcap_config.get_path().is_some() {
+            let writer: SharedPcapWriter = SharedPcapWriter::new(runtime.clone(), &pcap_config)?;
+            transport = SharedBox::<dyn NetworkRuntime>::new(Box::new(PcapTransport::new(transport, writer)));
+        }
+        // Tag frames on top of the tap, so that captures show the tags that are put on the wire.
+        if let Some(vlan_id) = ethernet2_config.get_vlan_id() {
+            transport = SharedBox::<dyn NetworkRuntime>::new(Box::new(VlanTransport::new(transport, vlan_id)));
+        }
+        // Pad frames on top of the tap, so that captures show frames as they are put on the wire.
+        if ethernet2_config.get_pad_frames() {
+            transport = SharedBox::<dyn NetworkRuntime>::new(Box::new(PaddingTransport::new(transport)));
+        }
+        // Loop packets for the loopback network back on top of everything else, so that they are neither captured nor
+        // padded.
+        transport = SharedBox::<dyn NetworkRuntime>::new(Box::new(LoopbackTransport::new(transport)));
+        // Hand packets to the egress hook before anything else, so that it sees them as the stack built them.
+        let egress: SharedEgressTransport = SharedEgressTransport::new(runtime.clone(), transport);
+        transport = SharedBox::<dyn NetworkRuntime>::new(Box::new(egress.clone()));
         let arp: SharedArpPeer = SharedArpPeer::new(
             runtime.clone(),
             transport.clone(),
             local_link_addr,
             local_ipv4_addr,
             arp_config,
+            routing_config,
         )?;
         let ipv4: Peer = Peer::new(
             runtime.clone(),
@@ -136,14 +210,132 @@ impl SharedInetStack {
             ipv4,
             runtime: runtime.clone(),
             transport,
+            egress,
             local_link_addr,
         }));
         let yielder: Yielder = Yielder::new();
         let background_task: String = format!("inetstack::poll_recv");
-        runtime.insert_background_coroutine(&background_task, Box::pin(me.clone().poll(yielder)))?;
+        runtime.insert_poller_coroutine(&background_task, Box::pin(me.clone().poll(yielder)))?;
         Ok(me)
     }
 
+    /// Returns the local link address of the target stack.
+    pub fn get_local_link_addr(&self) -> MacAddress {
+        self.local_link_addr
+    }
+
+    /// Returns the local IPv4 address of the target stack.
+    pub fn get_local_ipv4_addr(&self) -> Ipv4Addr {
+        self.ipv4.get_local_ipv4_addr()
+    }
+
+    /// Returns the number of inbound IPv4 packets that the target stack dropped because of a malformed header, such as
+    /// one with malformed options.
+    pub fn get_ipv4_header_errors(&self) -> usize {
+        self.ipv4.get_header_errors()
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Installs a hook that is handed every outgoing IPv4 packet before it reaches the transport, and that may modify,
+    /// drop or delay it. This is meant for network functions, such as NAT, and for injecting packet loss and latency
+    /// in tests. Passing `None` removes the hook, and packets are not serialized for inspection while none is
+    /// installed.
+    ///
+    pub fn set_egress_hook(&mut self, hook: Option<Ipv4EgressHook>) {
+        trace!("set_egress_hook()");
+        self.egress.set_hook(hook)
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Installs a hook that is handed every incoming IPv4 packet, starting at its header, before it is dispatched to
+    /// the protocol it belongs to. The hook may modify, drop or delay the packet, which is meant for firewalling and
+    /// for injecting packet loss, reordering and duplication in tests. Passing `None` removes the hook.
+    ///
+    pub fn set_ingress_hook(&mut self, hook: Option<Ipv4IngressHook>) {
+        trace!("set_ingress_hook()");
+        self.ipv4.set_ingress_hook(hook)
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Changes the local link and IPv4 addresses of the stack. This is meant for deployments that learn their
+    /// addressing at startup, after the stack has been created. Addresses may only be changed before any socket is
+    /// created, because existing sockets would otherwise keep using the old ones.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, `Ok(())` is returned. Upon failure, `Fail` is returned instead.
+    ///
+    pub fn set_local_addrs(&mut self, local_link_addr: MacAddress, local_ipv4_addr: Ipv4Addr) -> Result<(), Fail> {
+        trace!(
+            "set_local_addrs(): local_link_addr={:?} local_ipv4_addr={:?}",
+            local_link_addr,
+            local_ipv4_addr
+        );
+        if !local_link_addr.is_unicast() || local_link_addr.is_nil() {
+            let cause: String = format!("invalid local link address (local_link_addr={:?})", local_link_addr);
+            error!("set_local_addrs(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        if local_ipv4_addr.is_unspecified() || local_ipv4_addr.is_broadcast() || local_ipv4_addr.is_multicast() {
+            let cause: String = format!("invalid local IPv4 address (local_ipv4_addr={:?})", local_ipv4_addr);
+            error!("set_local_addrs(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        if self.runtime.get_qtable().get_values().next().is_some() {
+            let cause: String = format!("cannot change local addresses once sockets exist");
+            error!("set_local_addrs(): {}", cause);
+            return Err(Fail::new(libc::EBUSY, &cause));
+        }
+
+        self.local_link_addr = local_link_addr;
+        self.arp.set_local_addrs(local_link_addr, local_ipv4_addr);
+        self.ipv4.set_local_addrs(local_link_addr, local_ipv4_addr);
+        Ok(())
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Reseeds the random number generators of the stack from `rng_seed`, as if it had been given at creation. These
+    /// generators draw the secret nonce that the initial sequence numbers of TCP connections are derived from, and the
+    /// identifiers of ICMP echo requests. Processes that fork should reseed in the child, since it would otherwise
+    /// pick the same sequence numbers as its parent. Reseeding periodically limits how long a leaked nonce lets an
+    /// attacker predict sequence numbers.
+    ///
+    /// **Security Implications**
+    ///
+    /// The unpredictability of sequence numbers is only as good as `rng_seed`, which should come from a
+    /// cryptographically secure source. Reseeding with a seed that was already used makes the stack replay the
+    /// sequence numbers that it picked back then. The new nonce only applies to connections that are opened
+    /// afterwards, and sequence numbers are no longer guaranteed to grow across reseeding for a given pair of
+    /// addresses, so that a new incarnation of a connection in TIME-WAIT could accept old duplicate segments.
+    ///
+    pub fn reseed(&mut self, rng_seed: [u8; 32]) {
+        trace!("reseed()");
+        self.ipv4.reseed(rng_seed)
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Registers `handler` for inbound IPv4 packets of the protocol number `protocol`. This allows protocols that are
+    /// not built into the stack to be implemented on top of it. Packets of protocols without a handler are dropped.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, `Ok(())` is returned. Upon failure, `Fail` is returned instead.
+    ///
+    pub fn register_protocol(&mut self, protocol: u8, handler: Box<dyn ProtocolHandler>) -> Result<(), Fail> {
+        trace!("register_protocol(): protocol={:?}", protocol);
+        self.ipv4.register_protocol(protocol, handler)
+    }
+
     ///
     /// **Brief**
     ///
@@ -158,17 +350,24 @@ impl SharedInetStack {
     ///
     /// - AF_INET Internet Protocol Version 4 (IPv4)
     ///
+    /// The socket type argument selects the communication semantics. Currently, the following types are supported:
+    ///
+    /// - SOCK_STREAM TCP sockets.
+    /// - SOCK_DGRAM UDP sockets.
+    /// - SOCK_RAW Raw IPv4 sockets for the protocol number given in `protocol`. Only the protocol numbers reserved for
+    ///   experimentation and testing (253 and 254) are supported.
+    ///
     /// **Return Vale**
     ///
     /// Upon successful completion, a file descriptor for the newly created
     /// socket is returned. Upon failure, `Fail` is returned instead.
     ///
-    pub fn socket(&mut self, domain: c_int, socket_type: c_int, _protocol: c_int) -> Result<QDesc, Fail> {
+    pub fn socket(&mut self, domain: c_int, socket_type: c_int, protocol: c_int) -> Result<QDesc, Fail> {
         trace!(
             "socket(): domain={:?} type={:?} protocol={:?}",
             domain,
             socket_type,
-            _protocol
+            protocol
         );
         if domain != AF_INET_VALUE as i32 {
             return Err(Fail::new(libc::ENOTSUP, "address family not supported"));
@@ -176,6 +375,17 @@ impl SharedInetStack {
         match socket_type {
             SOCK_STREAM => self.ipv4.tcp.socket(),
             SOCK_DGRAM => self.ipv4.udp.socket(),
+            SOCK_RAW => {
+                let protocol: u8 = match u8::try_from(protocol) {
+                    Ok(protocol) => protocol,
+                    Err(_) => {
+                        let cause: String = format!("protocol not supported (protocol={:?})", protocol);
+                        error!("socket(): {}", cause);
+                        return Err(Fail::new(libc::EPROTONOSUPPORT, &cause));
+                    },
+                };
+                self.ipv4.raw.socket(protocol)
+            },
             _ => Err(Fail::new(libc::ENOTSUP, "socket type not supported")),
         }
     }
@@ -204,6 +414,279 @@ impl SharedInetStack {
         }
     }
 
+    ///
+    /// **Brief**
+    ///
+    /// Sets or clears the SO_BROADCAST option on the socket referred to by `qd`. Only UDP sockets support this option.
+    /// A socket may only send to and receive from the limited broadcast address (255.255.255.255) while the option is
+    /// set.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, `Ok(())` is returned. Upon failure, `Fail` is
+    /// returned instead.
+    ///
+    pub fn set_broadcast(&mut self, qd: QDesc, broadcast: bool) -> Result<(), Fail> {
+        trace!("set_broadcast(): qd={:?} broadcast={:?}", qd, broadcast);
+        match self.runtime.get_queue_type(&qd)? {
+            QType::UdpSocket => self.ipv4.udp.set_broadcast(qd, broadcast),
+            _ => Err(Fail::new(libc::ENOPROTOOPT, "option not supported by queue type")),
+        }
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Sets the socket option `option` on the socket referred to by `qd`, as with setsockopt().
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, `Ok(())` is returned. If the socket does not support the option, `Fail` is
+    /// returned with `ENOPROTOOPT`, and with another error code upon any other failure.
+    ///
+    pub fn set_socket_option(&mut self, qd: QDesc, option: SocketOption) -> Result<(), Fail> {
+        trace!("set_socket_option(): qd={:?} option={:?}", qd, option);
+        match (self.runtime.get_queue_type(&qd)?, option) {
+            (QType::TcpSocket, SocketOption::NoDelay(nodelay)) => self.ipv4.tcp.set_nodelay(qd, nodelay),
+            (QType::TcpSocket, SocketOption::RcvLowat(low_watermark)) => self.ipv4.tcp.set_rcvlowat(qd, low_watermark),
+            (QType::TcpSocket, SocketOption::SndBuf(size)) => self.ipv4.tcp.set_send_buffer_size(qd, size),
+            (QType::UdpSocket, SocketOption::Broadcast(broadcast)) => self.ipv4.udp.set_broadcast(qd, broadcast),
+            (QType::UdpSocket, SocketOption::RcvBuf(size)) => self.ipv4.udp.set_recv_buffer_size(qd, size),
+            (QType::UdpSocket, SocketOption::PktInfo(pktinfo)) => self.ipv4.udp.set_pktinfo(qd, pktinfo),
+            (QType::UdpSocket, SocketOption::DontRoute(dontroute)) => self.ipv4.udp.set_dontroute(qd, dontroute),
+            (QType::UdpSocket, SocketOption::ReuseAddr(reuse_addr)) => self.ipv4.udp.set_reuse_addr(qd, reuse_addr),
+            (_, SocketOption::AcceptConn(_)) => Err(Fail::new(libc::ENOPROTOOPT, "option is read-only")),
+            _ => Err(Fail::new(libc::ENOPROTOOPT, "option not supported by queue type")),
+        }
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Gets the value of the socket option `name` of the socket referred to by `qd`, as with getsockopt().
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, the option is returned along with its value. If the socket does not support the
+    /// option, `Fail` is returned with `ENOPROTOOPT`, and with another error code upon any other failure.
+    ///
+    pub fn get_socket_option(&self, qd: QDesc, name: SocketOptionName) -> Result<SocketOption, Fail> {
+        trace!("get_socket_option(): qd={:?} name={:?}", qd, name);
+        match (self.runtime.get_queue_type(&qd)?, name) {
+            (QType::TcpSocket, SocketOptionName::NoDelay) => Ok(SocketOption::NoDelay(self.ipv4.tcp.get_nodelay(qd)?)),
+            (QType::TcpSocket, SocketOptionName::RcvLowat) => {
+                Ok(SocketOption::RcvLowat(self.ipv4.tcp.get_rcvlowat(qd)?))
+            },
+            (QType::TcpSocket, SocketOptionName::SndBuf) => {
+                Ok(SocketOption::SndBuf(self.ipv4.tcp.get_send_buffer_size(qd)?))
+            },
+            (QType::UdpSocket, SocketOptionName::Broadcast) => {
+                Ok(SocketOption::Broadcast(self.ipv4.udp.get_broadcast(qd)?))
+            },
+            (QType::UdpSocket, SocketOptionName::RcvBuf) => {
+                Ok(SocketOption::RcvBuf(self.ipv4.udp.get_recv_buffer_size(qd)?))
+            },
+            (QType::UdpSocket, SocketOptionName::PktInfo) => Ok(SocketOption::PktInfo(self.ipv4.udp.get_pktinfo(qd)?)),
+            (QType::UdpSocket, SocketOptionName::DontRoute) => {
+                Ok(SocketOption::DontRoute(self.ipv4.udp.get_dontroute(qd)?))
+            },
+            (QType::UdpSocket, SocketOptionName::ReuseAddr) => {
+                Ok(SocketOption::ReuseAddr(self.ipv4.udp.get_reuse_addr(qd)?))
+            },
+            (QType::TcpSocket, SocketOptionName::AcceptConn) => {
+                Ok(SocketOption::AcceptConn(self.ipv4.tcp.is_listening(qd)?))
+            },
+            // UDP sockets never listen.
+            (QType::UdpSocket, SocketOptionName::AcceptConn) => Ok(SocketOption::AcceptConn(false)),
+            _ => Err(Fail::new(libc::ENOPROTOOPT, "option not supported by queue type")),
+        }
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Reports how many bytes are currently held in the send and receive buffers of the socket referred to by `qd`.
+    /// Unlike cumulative counters, this reflects the instantaneous depth of the buffers, and changes as data is pushed,
+    /// sent, acknowledged and popped.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, the buffer usage of the socket is returned. Upon failure, `Fail` is returned
+    /// instead.
+    ///
+    pub fn buffer_usage(&self, qd: QDesc) -> Result<BufferUsage, Fail> {
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.buffer_usage(qd),
+            QType::UdpSocket => self.ipv4.udp.buffer_usage(qd),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Reads the cumulative error counters of the socket referred to by `qd`, and clears them afterwards if `reset` is
+    /// set. TCP connections count retransmitted segments, segments received out of order and ICMP errors that lowered
+    /// their path MTU. UDP sockets count malformed datagrams and ICMP errors. Unlike the buffer usage, these are not
+    /// affected by the traffic that goes through fine.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, the error counters of the socket, as they were before any reset, are returned. Upon
+    /// failure, `Fail` is returned instead, which happens when a TCP connection is not established.
+    ///
+    pub fn error_counters(&self, qd: QDesc, reset: bool) -> Result<ErrorCounters, Fail> {
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.error_counters(qd, reset),
+            QType::UdpSocket => self.ipv4.udp.error_counters(qd, reset),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Reports how many incoming datagrams the UDP socket referred to by `qd` dropped because its receive queue was
+    /// full, as bounded by its maximum number of datagrams and by SO_RCVBUF.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, the number of dropped datagrams is returned. Upon failure, `Fail` is returned
+    /// instead.
+    ///
+    pub fn get_udp_recv_dropped(&self, qd: QDesc) -> Result<usize, Fail> {
+        match self.runtime.get_queue_type(&qd)? {
+            QType::UdpSocket => self.ipv4.udp.recv_dropped(qd),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Gets the local link address of the TCP connection referred to by `qd`, and the link address of the next hop
+    /// towards its remote peer, as resolved by ARP. This helps debugging address resolution and routing issues.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, the local and remote link addresses are returned. Upon failure, `Fail` is returned
+    /// instead, which happens when the link address of the next hop is not resolved.
+    ///
+    pub fn link_addrs(&self, qd: QDesc) -> Result<(MacAddress, MacAddress), Fail> {
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.link_addrs(qd),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Gathers diagnostics of the TCP connection referred to by `qd` in a single structure modeled on Linux's
+    /// `tcp_info`: its state, RTT estimates, retransmission timeout, congestion and flow control windows, and counts of
+    /// acknowledged bytes, retransmitted segments and unacknowledged segments.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, the diagnostics of the connection are returned. Upon failure, `Fail` is returned
+    /// instead, which happens when the connection is not established.
+    ///
+    pub fn tcp_info(&self, qd: QDesc) -> Result<TcpInfo, Fail> {
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.tcp_info(qd),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Gets the largest payload that the socket referred to by `qd` can send in a single packet without it being
+    /// fragmented, so that applications can size their pushes. For TCP connections, this is the MSS that the remote
+    /// peer advertised, capped by the MTU and lowered by path MTU discovery, minus the TCP options that go in each
+    /// segment. For UDP sockets, this is the largest datagram that the MTU allows. TCP sockets that are not connected
+    /// yet only account for the MTU.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, the largest payload size is returned, in bytes. Upon failure, `Fail` is returned
+    /// instead.
+    ///
+    pub fn path_mss(&self, qd: QDesc) -> Result<usize, Fail> {
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp_path_mss(qd),
+            QType::UdpSocket => self.ipv4.udp.max_payload_size(qd),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Reports whether operations on the socket referred to by `qd` would complete right away, so that it can be
+    /// integrated with a poller. A TCP connection that was closed by the remote peer but not locally is reported as
+    /// readable with the end of the stream and still writable, rather than as hung up.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, the readiness of the socket is returned. Upon failure, `Fail` is returned instead.
+    ///
+    pub fn readiness(&self, qd: QDesc) -> Result<Readiness, Fail> {
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.readiness(qd),
+            QType::UdpSocket => self.ipv4.udp.readiness(qd),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Retrieves and clears the error that a connect on the TCP socket referred to by `qd` failed with. This is the
+    /// equivalent of getsockopt(SO_ERROR), and lets an application that polls for the readiness of a connecting socket,
+    /// instead of waiting for the connect to complete, find out why it failed.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, the pending error of the socket, if any, is returned. Upon failure, `Fail` is
+    /// returned instead.
+    ///
+    pub fn take_socket_error(&mut self, qd: QDesc) -> Result<Option<Fail>, Fail> {
+        trace!("take_socket_error(): qd={:?}", qd);
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.take_socket_error(qd),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Gets the local address of the socket referred to by `qd`. For sockets that were returned by
+    /// [accept](Self::accept), this is the address that the incoming connection was destined to.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, the local address of the socket is returned. Upon failure, `Fail` is returned
+    /// instead.
+    ///
+    pub fn getsockname(&self, qd: QDesc) -> Result<SocketAddr, Fail> {
+        trace!("getsockname(): qd={:?}", qd);
+        let local: Option<SocketAddrV4> = match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.local(qd)?,
+            QType::UdpSocket => self.ipv4.udp.local(qd)?,
+            _ => return Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        };
+        match local {
+            Some(local) => Ok(SocketAddr::V4(local)),
+            None => {
+                let cause: String = format!("socket is not bound (qd={:?})", qd);
+                error!("getsockname(): {}", cause);
+                Err(Fail::new(libc::EINVAL, &cause))
+            },
+        }
+    }
+
     ///
     /// **Brief**
     ///
@@ -223,13 +706,129 @@ impl SharedInetStack {
     pub fn listen(&mut self, qd: QDesc, backlog: usize) -> Result<(), Fail> {
         trace!("listen() qd={:?}, backlog={:?}", qd, backlog);
 
-        // FIXME: https://github.com/demikernel/demikernel/issues/584
-        if backlog == 0 {
-            return Err(Fail::new(libc::EINVAL, "invalid backlog length"));
+        // FIXME: https://github.com/demikernel/demikernel/issues/584
+        if backlog == 0 {
+            return Err(Fail::new(libc::EINVAL, "invalid backlog length"));
+        }
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.listen(qd, backlog),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    /// Changes the maximum length of the queue of pending connections of the listening socket `qd` to `backlog`.
+    /// Connections that are already pending are kept if the queue shrinks below their number, but new connection
+    /// requests are refused until enough of them are accepted.
+    pub fn set_backlog(&mut self, qd: QDesc, backlog: usize) -> Result<(), Fail> {
+        trace!("set_backlog() qd={:?}, backlog={:?}", qd, backlog);
+
+        if backlog == 0 {
+            return Err(Fail::new(libc::EINVAL, "invalid backlog length"));
+        }
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.set_backlog(qd, backlog),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    /// Installs a hook on the listening socket `qd` that is invoked once for every connection that becomes ready to be
+    /// accepted, including the ones that are already pending. This lets event-driven applications issue an accept
+    /// only when it completes right away, instead of keeping one outstanding per listener. Passing `None` removes the
+    /// hook.
+    pub fn set_accept_ready_hook(&mut self, qd: QDesc, hook: Option<TcpAcceptReadyHook>) -> Result<(), Fail> {
+        trace!("set_accept_ready_hook() qd={:?}", qd);
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.set_accept_ready_hook(qd, hook),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    /// Installs a filter on the listening socket `qd` of the remote addresses that it accepts connections from.
+    /// Connection requests from other addresses are refused with a RST during the handshake, so that they never reach
+    /// the application. Passing `None` removes the filter.
+    pub fn set_accept_filter(&mut self, qd: QDesc, filter: Option<AcceptFilter>) -> Result<(), Fail> {
+        trace!("set_accept_filter() qd={:?}, filter={:?}", qd, filter);
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.set_accept_filter(qd, filter),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    /// Gracefully closes the TCP connection `qd` once no data has been sent or received on it for `timeout`, failing
+    /// any pending operation with `ETIMEDOUT`. Activity restarts the timer. Passing `None` disables the idle timeout.
+    pub fn set_idle_timeout(&mut self, qd: QDesc, timeout: Option<Duration>) -> Result<(), Fail> {
+        trace!("set_idle_timeout() qd={:?}, timeout={:?}", qd, timeout);
+
+        if timeout == Some(Duration::ZERO) {
+            return Err(Fail::new(libc::EINVAL, "invalid idle timeout"));
+        }
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.set_idle_timeout(qd, timeout),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    /// Sets the transmit priority of the TCP connection `qd`. When connections contend for the transport, segments of
+    /// higher priority connections are sent ahead of those of lower priority ones. All connections have priority 0 by
+    /// default, in which case segments are sent in the order in which they are produced.
+    pub fn set_tx_priority(&mut self, qd: QDesc, priority: u8) -> Result<(), Fail> {
+        trace!("set_tx_priority() qd={:?}, priority={:?}", qd, priority);
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.set_tx_priority(qd, priority),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    /// Sets the key that the segments of the TCP socket `qd` are signed and checked with, as described in RFC 2385
+    /// (TCP-MD5), or removes it if `key` is `None`. Segments that arrive without a valid signature are then dropped. A
+    /// listening socket hands its key down to the connections that it accepts. The key should be set on both ends
+    /// before connecting, so that the handshake is signed too.
+    #[cfg(feature = "tcp-md5")]
+    pub fn set_tcp_md5_key(&mut self, qd: QDesc, key: Option<&[u8]>) -> Result<(), Fail> {
+        trace!("set_tcp_md5_key() qd={:?}", qd);
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.set_md5_key(qd, key),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    /// Sets whether urgent data received on the TCP connection `qd` is left in the ordinary stream, as with
+    /// `SO_OOBINLINE`, or delivered out of band, which is the default.
+    pub fn set_oob_inline(&mut self, qd: QDesc, oob_inline: bool) -> Result<(), Fail> {
+        trace!("set_oob_inline() qd={:?}, oob_inline={:?}", qd, oob_inline);
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.set_oob_inline(qd, oob_inline),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    /// Sets whether data that arrives on the TCP connection `qd` once it was closed locally, but before the remote peer
+    /// closed its side, is discarded. Discarding it resets the connection, which fails pending pops with
+    /// `ECONNRESET`. By default, it is delivered to pops that were pending when the connection was closed.
+    pub fn set_discard_late_data(&mut self, qd: QDesc, discard: bool) -> Result<(), Fail> {
+        trace!("set_discard_late_data() qd={:?}, discard={:?}", qd, discard);
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.set_discard_late_data(qd, discard),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
         }
+    }
+
+    /// Takes the urgent byte that was received out of band on the TCP connection `qd`, much like a receive with
+    /// `MSG_OOB`. Fails with `EINVAL` if there is no such byte, or if urgent data is delivered inline.
+    pub fn recv_oob(&mut self, qd: QDesc) -> Result<u8, Fail> {
+        trace!("recv_oob() qd={:?}", qd);
 
         match self.runtime.get_queue_type(&qd)? {
-            QType::TcpSocket => self.ipv4.tcp.listen(qd, backlog),
+            QType::TcpSocket => self.ipv4.tcp.recv_oob(qd),
             _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
         }
     }
@@ -257,10 +856,24 @@ impl SharedInetStack {
         }
     }
 
+    /// Accepts a connection that is already ready on the listening socket `qd`, and returns the result right away
+    /// instead of a queue token, skipping the scheduler. Returns `None` if no connection is ready, in which case
+    /// nothing was done and [accept](Self::accept) should be used instead.
+    pub fn try_accept(&mut self, qd: QDesc) -> Result<Option<OperationResult>, Fail> {
+        trace!("try_accept(): {:?}", qd);
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.try_accept(qd),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
     ///
     /// **Brief**
     ///
-    /// Connects the socket referred to by `qd` to the remote endpoint specified by `remote`.
+    /// Connects the socket referred to by `qd` to the remote endpoint specified by `remote`. Connecting a UDP socket
+    /// completes right away, and makes it report the ICMP errors triggered by the datagrams that it sends, such as
+    /// ECONNREFUSED for a port that is unreachable, on its next operation.
     ///
     /// **Return Value**
     ///
@@ -277,10 +890,48 @@ impl SharedInetStack {
 
         match self.runtime.get_queue_type(&qd)? {
             QType::TcpSocket => self.ipv4.tcp.connect(qd, remote),
+            QType::UdpSocket => {
+                self.ipv4.udp.connect(qd, remote)?;
+                let coroutine: Pin<Box<Operation>> = Box::pin(async move { (qd, OperationResult::Connect) });
+                let task_id: String = format!("Inetstack::UDP::connect for qd={:?}", qd);
+                let handle: TaskHandle = self.runtime.insert_coroutine(task_id.as_str(), coroutine)?;
+                Ok(handle.get_task_id().into())
+            },
             _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
         }
     }
 
+    ///
+    /// **Brief**
+    ///
+    /// Resolves the link address of `dest`, or of the gateway towards it, ahead of a latency-critical connect or
+    /// send, so that the first packet to `dest` goes out without waiting for an ARP round trip.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, a queue token is returned. The operation completes as a resolve, along with the
+    /// link address of the next hop, once it is known, or fails with EHOSTUNREACH if the next hop does not answer. It
+    /// is not associated with any queue, so it completes on an invalid queue descriptor. Upon failure, `Fail` is
+    /// returned instead.
+    ///
+    pub fn resolve(&mut self, dest: Ipv4Addr) -> Result<QToken, Fail> {
+        trace!("resolve(): dest={:?}", dest);
+
+        let mut arp: SharedArpPeer = self.arp.clone();
+        let yielder: Yielder = Yielder::new();
+        let coroutine: Pin<Box<Operation>> = Box::pin(async move {
+            // There is no queue to complete on.
+            let qd: QDesc = QDesc::from(QDesc::MAX);
+            match arp.resolve(dest, &yielder).await {
+                Ok(link_addr) => (qd, OperationResult::Resolve(link_addr)),
+                Err(e) => (qd, OperationResult::Failed(e)),
+            }
+        });
+        let task_id: String = format!("Inetstack::ARP::resolve for dest={:?}", dest);
+        let handle: TaskHandle = self.runtime.insert_coroutine(task_id.as_str(), coroutine)?;
+        Ok(handle.get_task_id().into())
+    }
+
     ///
     /// **Brief**
     ///
@@ -297,15 +948,115 @@ impl SharedInetStack {
         match self.runtime.get_queue_type(&qd)? {
             QType::TcpSocket => self.ipv4.tcp.async_close(qd),
             QType::UdpSocket => self.ipv4.udp.async_close(qd),
+            QType::RawSocket => self.ipv4.raw.async_close(qd),
             _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
         }
     }
 
-    /// Pushes a buffer to a TCP socket.
+    ///
+    /// **Brief**
+    ///
+    /// Lists the TCP, UDP and raw sockets of the stack, along with their local and remote addresses.
+    ///
+    /// **Return Value**
+    ///
+    /// A summary of each of the sockets is returned, in ascending order of queue descriptors.
+    ///
+    pub fn queues(&self) -> Vec<QueueInfo> {
+        self.runtime
+            .get_qtable()
+            .iter()
+            .filter_map(|(qd, queue)| match queue.get_qtype() {
+                QType::TcpSocket => network_queue_info::<SharedTcpQueue>(qd, queue.as_ref()),
+                QType::UdpSocket => network_queue_info::<SharedUdpQueue>(qd, queue.as_ref()),
+                QType::RawSocket => network_queue_info::<SharedRawQueue>(qd, queue.as_ref()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Counts the TCP sockets of the stack in each state, including the connections in TIME-WAIT, much like the summary
+    /// of `ss -s`.
+    pub fn tcp_state_counts(&self) -> TcpStateCounts {
+        self.ipv4.tcp.state_counts()
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Asynchronously closes every socket for which `pred` holds, such as all connections to a given remote subnet.
+    /// Sockets are closed one at a time, and failing to close one of them does not prevent the others from being
+    /// closed.
+    ///
+    /// **Return Value**
+    ///
+    /// The outcome of closing each of the matching sockets is returned, along with its queue descriptor. It is either
+    /// a qtoken that can be used to wait until the close completes, or the `Fail` that prevented the close.
+    ///
+    pub fn close_where<F: Fn(&QueueInfo) -> bool>(&mut self, pred: F) -> Vec<(QDesc, Result<QToken, Fail>)> {
+        let qds: Vec<QDesc> = self
+            .queues()
+            .into_iter()
+            .filter(|info| pred(info))
+            .map(|info| info.qd)
+            .collect();
+        trace!("close_where(): qds={:?}", qds);
+        qds.into_iter().map(|qd| (qd, self.async_close(qd))).collect()
+    }
+
+    /// Pushes a buffer to a TCP socket, or to a connected UDP socket.
     /// TODO: Rename this function to push() once we have a common representation across all libOSes.
     pub fn do_push(&mut self, qd: QDesc, buf: DemiBuffer) -> Result<QToken, Fail> {
         match self.runtime.get_queue_type(&qd)? {
             QType::TcpSocket => self.ipv4.tcp.push(qd, buf),
+            QType::UdpSocket => {
+                let coroutine: Pin<Box<Operation>> = self.ipv4.udp.push(qd, buf)?;
+                let task_id: String = format!("Inetstack::UDP::push for qd={:?}", qd);
+                let handle: TaskHandle = self.runtime.insert_coroutine(task_id.as_str(), coroutine)?;
+                Ok(handle.get_task_id().into())
+            },
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    /// Pushes a buffer to a TCP socket with a hint that more data is coming. The buffer is not transmitted right away,
+    /// but held back and coalesced with the data of the next push on the socket.
+    pub fn do_push_more(&mut self, qd: QDesc, buf: DemiBuffer) -> Result<QToken, Fail> {
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.push_more(qd, buf),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    /// Pushes a message that is gathered from several byte slices to a TCP socket, or to a connected UDP socket. The
+    /// slices are copied into a single buffer, so they go out as if they had been pushed at once.
+    pub fn push_vectored(&mut self, qd: QDesc, bufs: &[&[u8]]) -> Result<QToken, Fail> {
+        trace!("push_vectored(): qd={:?} nbufs={:?}", qd, bufs.len());
+        let buf: DemiBuffer = DemiBuffer::from_slices(bufs)?;
+        self.check_push_size(qd, &buf)?;
+        self.do_push(qd, buf)
+    }
+
+    /// Transmits the data that is buffered on a TCP socket but not sent yet, as far as the send and congestion windows
+    /// allow. This covers data held back by [do_push_more](Self::do_push_more) and small pushes held back by Nagle's
+    /// algorithm, so the last segment may be smaller than the MSS. Unlike a close or a shutdown, the connection stays
+    /// open for more pushes.
+    pub fn flush(&mut self, qd: QDesc) -> Result<(), Fail> {
+        trace!("flush(): qd={:?}", qd);
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.flush(qd),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    /// Pushes a buffer to a TCP socket, and returns the result right away instead of a queue token, skipping the
+    /// scheduler. TCP pushes complete as soon as their data is queued for sending, so the result is always available.
+    pub fn try_push(&mut self, qd: QDesc, buf: DemiBuffer) -> Result<Option<OperationResult>, Fail> {
+        trace!("try_push(): qd={:?}", qd);
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.try_push(qd, buf),
+            QType::UdpSocket | QType::RawSocket => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
             _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
         }
     }
@@ -317,14 +1068,30 @@ impl SharedInetStack {
 
         // Convert raw data to a buffer representation.
         let buf: DemiBuffer = DemiBuffer::from_slice(data)?;
-        if buf.is_empty() {
-            return Err(Fail::new(libc::EINVAL, "zero-length buffer"));
-        }
+        self.check_push_size(qd, &buf)?;
 
         // Issue operation.
         self.do_push(qd, buf)
     }
 
+    /// Pushes a buffer that the caller already owns to a TCP socket, or to a connected UDP socket. The buffer is moved
+    /// into the push as it is, so that, unlike [push2](Self::push2), its data is neither copied nor described through
+    /// a scatter-gather array.
+    pub fn push_buffer(&mut self, qd: QDesc, buf: DemiBuffer) -> Result<QToken, Fail> {
+        trace!("push_buffer(): qd={:?}, len={:?}", qd, buf.len());
+        self.check_push_size(qd, &buf)?;
+        self.do_push(qd, buf)
+    }
+
+    /// Checks whether `buf` may be pushed to `qd`. Zero-length buffers are rejected, except on UDP sockets, where they
+    /// are sent as empty datagrams. On TCP sockets, they would not send anything.
+    pub fn check_push_size(&self, qd: QDesc, buf: &DemiBuffer) -> Result<(), Fail> {
+        if buf.is_empty() && !matches!(self.runtime.get_queue_type(&qd), Ok(QType::UdpSocket)) {
+            return Err(Fail::new(libc::EINVAL, "zero-length buffer"));
+        }
+        Ok(())
+    }
+
     /// Pushes a buffer to a UDP socket.
     /// TODO: Rename this function to pushto() once we have a common buffer representation across all libOSes.
     pub fn do_pushto(&mut self, qd: QDesc, buf: DemiBuffer, to: SocketAddr) -> Result<TaskHandle, Fail> {
@@ -337,6 +1104,11 @@ impl SharedInetStack {
                 let task_id: String = format!("Inetstack::UDP::pushto for qd={:?}", qd);
                 self.runtime.insert_coroutine(task_id.as_str(), coroutine)
             },
+            QType::RawSocket => {
+                let coroutine: Pin<Box<Operation>> = self.ipv4.raw.pushto(qd, buf, to)?;
+                let task_id: String = format!("Inetstack::raw::pushto for qd={:?}", qd);
+                self.runtime.insert_coroutine(task_id.as_str(), coroutine)
+            },
             _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
         }
     }
@@ -348,9 +1120,7 @@ impl SharedInetStack {
 
         // Convert raw data to a buffer representation.
         let buf: DemiBuffer = DemiBuffer::from_slice(data)?;
-        if buf.is_empty() {
-            return Err(Fail::new(libc::EINVAL, "zero-length buffer"));
-        }
+        self.check_push_size(qd, &buf)?;
         // Issue operation.
         let handle: TaskHandle = self.do_pushto(qd, buf, remote)?;
         let qt: QToken = handle.get_task_id().into();
@@ -358,6 +1128,44 @@ impl SharedInetStack {
         Ok(qt)
     }
 
+    /// Pushes a buffer to a UDP socket, from the local address `from` rather than from the address that the socket is
+    /// bound to. This lets sockets bound to the wildcard address reply from the address that a request was sent to,
+    /// as learnt with IP_PKTINFO.
+    pub fn do_pushto_from(
+        &mut self,
+        qd: QDesc,
+        buf: DemiBuffer,
+        from: Ipv4Addr,
+        to: SocketAddr,
+    ) -> Result<TaskHandle, Fail> {
+        // FIXME: add IPv6 support; https://github.com/microsoft/demikernel/issues/935
+        let to: SocketAddrV4 = unwrap_socketaddr(to)?;
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::UdpSocket => {
+                let coroutine: Pin<Box<Operation>> = self.ipv4.udp.pushto_from(qd, buf, from, to)?;
+                let task_id: String = format!("Inetstack::UDP::pushto_from for qd={:?}", qd);
+                self.runtime.insert_coroutine(task_id.as_str(), coroutine)
+            },
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    /// Pushes raw data to a UDP socket, from the local address `from`.
+    /// TODO: Move this function to demikernel repo once we have a common buffer representation across all libOSes.
+    pub fn pushto_from2(&mut self, qd: QDesc, data: &[u8], from: Ipv4Addr, remote: SocketAddr) -> Result<QToken, Fail> {
+        trace!("pushto_from2(): qd={:?} from={:?}", qd, from);
+
+        // Convert raw data to a buffer representation.
+        let buf: DemiBuffer = DemiBuffer::from_slice(data)?;
+        self.check_push_size(qd, &buf)?;
+        // Issue operation.
+        let handle: TaskHandle = self.do_pushto_from(qd, buf, from, remote)?;
+        let qt: QToken = handle.get_task_id().into();
+        trace!("pushto_from2() qt={:?}", qt);
+        Ok(qt)
+    }
+
     /// Create a pop request to write data from IO connection represented by `qd` into a buffer
     /// allocated by the application.
     pub fn pop(&mut self, qd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
@@ -376,10 +1184,79 @@ impl SharedInetStack {
                 trace!("pop() qt={:?}", qt);
                 Ok(qt)
             },
+            QType::RawSocket => {
+                let task_id: String = format!("Inetstack::raw::pop for qd={:?}", qd);
+                let coroutine: Pin<Box<Operation>> = self.ipv4.raw.pop(qd, size)?;
+                let handle: TaskHandle = self.runtime.insert_coroutine(task_id.as_str(), coroutine)?;
+                let qt: QToken = handle.get_task_id().into();
+                trace!("pop() qt={:?}", qt);
+                Ok(qt)
+            },
             _ => return Err(Fail::new(libc::EINVAL, "invalid queue type")),
         }
     }
 
+    /// Pops data that was already received on a TCP socket, and returns the result right away instead of a queue token,
+    /// skipping the scheduler. Returns `None` if no data was received yet, in which case nothing was done and
+    /// [pop](Self::pop) should be used instead.
+    pub fn try_pop(&mut self, qd: QDesc, size: Option<usize>) -> Result<Option<OperationResult>, Fail> {
+        trace!("try_pop() qd={:?}, size={:?}", qd, size);
+
+        // We just assert 'size' here, because it was previously checked at PDPIX layer.
+        debug_assert!(size.is_none() || ((size.unwrap() > 0) && (size.unwrap() <= limits::POP_SIZE_MAX)));
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.try_pop(qd, size),
+            QType::UdpSocket | QType::RawSocket => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    /// Pops up to `size` bytes from a TCP socket, waiting at most `timeout` for them to arrive. Once `timeout`
+    /// expires, the operation completes with the data that arrived so far, or fails with ETIMEDOUT if none did.
+    pub fn pop_with_timeout(&mut self, qd: QDesc, size: usize, timeout: Duration) -> Result<QToken, Fail> {
+        trace!("pop_with_timeout() qd={:?}, size={:?}, timeout={:?}", qd, size, timeout);
+
+        // We just assert 'size' here, because it was previously checked at PDPIX layer.
+        debug_assert!((size > 0) && (size <= limits::POP_SIZE_MAX));
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.pop_with_timeout(qd, size, timeout),
+            QType::UdpSocket | QType::RawSocket => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    /// Pops data from a socket directly into the caller-provided `sga`, which must remain valid until the operation
+    /// completes. At most as many bytes as `sga` can hold are popped, and the number of bytes that were copied is
+    /// reported in the result of the operation.
+    pub fn pop_into(&mut self, qd: QDesc, sga: &demi_sgarray_t) -> Result<QToken, Fail> {
+        trace!("pop_into() qd={:?}", qd);
+
+        let size: usize = sgarray_capacity(sga)?.min(limits::POP_SIZE_MAX);
+        // We just assert 'size' here, because it was previously checked at PDPIX layer.
+        debug_assert!(size > 0);
+
+        let (task_id, coroutine): (String, Pin<Box<Operation>>) = match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => return self.ipv4.tcp.pop_into(qd, sga),
+            QType::UdpSocket => (
+                format!("Inetstack::UDP::pop_into for qd={:?}", qd),
+                self.ipv4.udp.pop(qd, Some(size))?,
+            ),
+            QType::RawSocket => (
+                format!("Inetstack::raw::pop_into for qd={:?}", qd),
+                self.ipv4.raw.pop(qd, Some(size))?,
+            ),
+            _ => return Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        };
+        let coroutine: Pin<Box<Operation>> =
+            Box::pin(pop_into_coroutine(coroutine, *sga, self.runtime.get_buffer_pool()));
+        let handle: TaskHandle = self.runtime.insert_coroutine(task_id.as_str(), coroutine)?;
+        let qt: QToken = handle.get_task_id().into();
+        trace!("pop_into() qt={:?}", qt);
+        Ok(qt)
+    }
+
     /// Waits for an operation to complete.
     /// This function is deprecated, do not use.
     /// FIXME: https://github.com/microsoft/demikernel/issues/889
@@ -485,6 +1362,8 @@ impl SharedInetStack {
                     }
                 }
             }
+            // Receive the packets that the ingress hook delayed, if any.
+            self.ipv4.poll_ingress();
             match yielder.yield_once().await {
                 Ok(()) => continue,
                 Err(_) => break,
@@ -493,6 +1372,21 @@ impl SharedInetStack {
     }
 }
 
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Summarizes the network queue `queue` of type `T`, which is referred to by `qd`.
+fn network_queue_info<T: NetworkQueue>(qd: QDesc, queue: &dyn IoQueue) -> Option<QueueInfo> {
+    let queue: &T = queue.as_any_ref().downcast_ref::<T>()?;
+    Some(QueueInfo {
+        qd,
+        qtype: queue.get_qtype(),
+        local: queue.local(),
+        remote: queue.remote(),
+    })
+}
+
 //======================================================================================================================
 // Trait Implementation
 //======================================================================================================================
@@ -510,3 +1404,480 @@ impl DerefMut for SharedInetStack {
         self.0.deref_mut()
     }
 }
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        inetstack::{
+            pool::{
+                ConnectionPool,
+                PooledConnection,
+            },
+            protocols::tcp::TcpState,
+            test_helpers::{
+                self,
+                SharedEngine,
+                SharedTestRuntime,
+            },
+            SharedInetStack,
+        },
+        pal::constants::{
+            AF_INET_VALUE,
+            SOCK_DGRAM,
+            SOCK_STREAM,
+        },
+        runtime::{
+            fail::Fail,
+            memory::DemiBuffer,
+            network::{
+                config::{
+                    ArpConfig,
+                    Ethernet2Config,
+                    PcapConfig,
+                    RoutingConfig,
+                    TcpConfig,
+                    UdpConfig,
+                },
+                consts::RECEIVE_BATCH_SIZE,
+                types::{
+                    MacAddress,
+                    SocketOption,
+                    SocketOptionName,
+                },
+                NetworkRuntime,
+                PacketBuf,
+            },
+            scheduler::TaskHandle,
+            OperationResult,
+            QDesc,
+            QToken,
+            SharedBox,
+            SharedDemiRuntime,
+        },
+    };
+    use ::anyhow::Result;
+    use ::arrayvec::ArrayVec;
+    use ::std::{
+        cell::RefCell,
+        collections::HashMap,
+        net::{
+            Ipv4Addr,
+            SocketAddr,
+            SocketAddrV4,
+        },
+        rc::Rc,
+        time::{
+            Duration,
+            Instant,
+        },
+    };
+
+    /// Maximum number of times that the scheduler is polled while waiting for an operation.
+    const MAX_POLLS: usize = 100;
+
+    /// Number of times that the scheduler is polled to let a FIN reach the other end of a connection.
+    const FIN_POLLS: usize = 10;
+
+    /// Network runtime that records where the body of every packet that it transmits is stored, before handing the
+    /// packet over to the test rig.
+    #[derive(Clone)]
+    struct BodyRecorder {
+        test_rig: SharedTestRuntime,
+        bodies: Rc<RefCell<Vec<*const u8>>>,
+    }
+
+    impl NetworkRuntime for BodyRecorder {
+        fn transmit(&mut self, pkt: Box<dyn PacketBuf>) {
+            if let Some(body) = pkt.take_body() {
+                self.bodies.borrow_mut().push(body.as_ptr());
+            }
+            self.test_rig.transmit(pkt)
+        }
+
+        fn receive(&mut self) -> ArrayVec<DemiBuffer, RECEIVE_BATCH_SIZE> {
+            self.test_rig.receive()
+        }
+
+        fn get_ifindex(&self) -> u32 {
+            self.test_rig.get_ifindex()
+        }
+    }
+
+    /// Creates a stack that only talks to itself, over the loopback network.
+    fn new_stack() -> Result<(SharedInetStack, SharedTestRuntime)> {
+        let test_rig: SharedTestRuntime = SharedTestRuntime::new(
+            Instant::now(),
+            ArpConfig::default(),
+            UdpConfig::default(),
+            TcpConfig::default(),
+            test_helpers::ALICE_MAC,
+            test_helpers::ALICE_IPV4,
+        );
+        let stack: SharedInetStack = SharedInetStack::new(
+            test_rig.get_runtime(),
+            SharedBox::new(Box::new(test_rig.clone())),
+            test_helpers::ALICE_MAC,
+            test_helpers::ALICE_IPV4,
+            UdpConfig::default(),
+            TcpConfig::default(),
+            [0; 32],
+            ArpConfig::default(),
+            PcapConfig::default(),
+            Ethernet2Config::default(),
+            RoutingConfig::default(),
+        )?;
+        Ok((stack, test_rig))
+    }
+
+    /// Polls `test_rig` until the operation `qt` of `stack` completes, and returns its result.
+    fn wait(stack: &mut SharedInetStack, test_rig: &mut SharedTestRuntime, qt: QToken) -> Result<OperationResult> {
+        let runtime: SharedDemiRuntime = test_rig.get_runtime();
+        let handle: TaskHandle = runtime.from_task_id(qt)?;
+        for _ in 0..MAX_POLLS {
+            if handle.has_completed() {
+                return Ok(stack.take_operation(handle).1);
+            }
+            test_rig.poll_scheduler();
+        }
+        anyhow::bail!("operation should have completed")
+    }
+
+    /// Listens on `local`, and connects a socket to it. Returns the listening, accepted and connected sockets.
+    fn connect(
+        stack: &mut SharedInetStack,
+        test_rig: &mut SharedTestRuntime,
+        local: SocketAddrV4,
+    ) -> Result<(QDesc, QDesc, QDesc)> {
+        let listen_qd: QDesc = stack.socket(AF_INET_VALUE, SOCK_STREAM, 0)?;
+        stack.bind(listen_qd, SocketAddr::V4(local))?;
+        stack.listen(listen_qd, 1)?;
+        let accept_qt: QToken = stack.accept(listen_qd)?;
+        let client_qd: QDesc = stack.socket(AF_INET_VALUE, SOCK_STREAM, 0)?;
+        let connect_qt: QToken = stack.connect(client_qd, SocketAddr::V4(local))?;
+
+        match wait(stack, test_rig, connect_qt)? {
+            OperationResult::Connect => (),
+            result => anyhow::bail!("connect should have succeeded: {:?}", result),
+        }
+        match wait(stack, test_rig, accept_qt)? {
+            OperationResult::Accept((server_qd, _)) => Ok((listen_qd, server_qd, client_qd)),
+            result => anyhow::bail!("accept should have succeeded: {:?}", result),
+        }
+    }
+
+    /// Tests that closing the connections to one remote leaves the connections to other remotes intact.
+    #[test]
+    fn test_close_where_remote() -> Result<()> {
+        let (mut stack, mut test_rig): (SharedInetStack, SharedTestRuntime) = new_stack()?;
+        let first: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 80);
+        let second: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 81);
+        let (_, first_server_qd, first_client_qd): (QDesc, QDesc, QDesc) = connect(&mut stack, &mut test_rig, first)?;
+        let (_, second_server_qd, second_client_qd): (QDesc, QDesc, QDesc) =
+            connect(&mut stack, &mut test_rig, second)?;
+        crate::ensure_eq!(stack.queues().len(), 6);
+
+        let results: Vec<(QDesc, Result<QToken, Fail>)> = stack.close_where(|info| info.remote == Some(first));
+        crate::ensure_eq!(results.len(), 1);
+        crate::ensure_eq!(results[0].0, first_client_qd);
+        crate::ensure_eq!(results[0].1.is_ok(), true);
+        for _ in 0..FIN_POLLS {
+            test_rig.poll_scheduler();
+        }
+
+        crate::ensure_neq!(stack.tcp_info(first_client_qd)?.state, TcpState::Established);
+        crate::ensure_eq!(stack.tcp_info(second_client_qd)?.state, TcpState::Established);
+        crate::ensure_eq!(stack.tcp_info(second_server_qd)?.state, TcpState::Established);
+        crate::ensure_neq!(stack.tcp_info(first_server_qd)?.state, TcpState::Established);
+
+        Ok(())
+    }
+
+    /// Tests that sockets that cannot be closed are reported, and do not prevent other matching sockets from being
+    /// closed.
+    #[test]
+    fn test_close_where_partial_failure() -> Result<()> {
+        let (mut stack, mut test_rig): (SharedInetStack, SharedTestRuntime) = new_stack()?;
+        let local: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 80);
+        let (listen_qd, server_qd, client_qd): (QDesc, QDesc, QDesc) = connect(&mut stack, &mut test_rig, local)?;
+
+        // Listening sockets cannot be closed asynchronously, unlike the accepted connection that shares their address.
+        let results: Vec<(QDesc, Result<QToken, Fail>)> = stack.close_where(|info| info.local == Some(local));
+        crate::ensure_eq!(results.len(), 2);
+        for (qd, result) in results {
+            match result {
+                Err(e) if qd == listen_qd && e.errno == libc::ENOTSUP => (),
+                Ok(_) if qd == server_qd => (),
+                result => anyhow::bail!("unexpected result for qd={:?}: {:?}", qd, result),
+            }
+        }
+        crate::ensure_eq!(stack.tcp_info(client_qd)?.state, TcpState::Established);
+
+        Ok(())
+    }
+
+    /// Tests that a zero-length UDP datagram is sent, and delivered as an empty datagram along with its source address.
+    #[test]
+    fn test_zero_length_udp_datagram() -> Result<()> {
+        let (mut stack, mut test_rig): (SharedInetStack, SharedTestRuntime) = new_stack()?;
+        let local: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 80);
+        let remote: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 81);
+        let local_qd: QDesc = stack.socket(AF_INET_VALUE, SOCK_DGRAM, 0)?;
+        stack.bind(local_qd, SocketAddr::V4(local))?;
+        let remote_qd: QDesc = stack.socket(AF_INET_VALUE, SOCK_DGRAM, 0)?;
+        stack.bind(remote_qd, SocketAddr::V4(remote))?;
+
+        let pop_qt: QToken = stack.pop(local_qd, None)?;
+        let push_qt: QToken = stack.pushto2(remote_qd, &[], SocketAddr::V4(local))?;
+        match wait(&mut stack, &mut test_rig, push_qt)? {
+            OperationResult::Push(0) => (),
+            result => anyhow::bail!("pushto should have sent an empty datagram: {:?}", result),
+        }
+        match wait(&mut stack, &mut test_rig, pop_qt)? {
+            OperationResult::Pop(addr, buf) => {
+                crate::ensure_eq!(addr, Some(remote));
+                crate::ensure_eq!(buf.len(), 0);
+            },
+            result => anyhow::bail!("pop should have returned an empty datagram: {:?}", result),
+        }
+
+        Ok(())
+    }
+
+    /// Tests that zero-length pushes are still rejected on TCP sockets.
+    #[test]
+    fn test_zero_length_tcp_push() -> Result<()> {
+        let (mut stack, mut test_rig): (SharedInetStack, SharedTestRuntime) = new_stack()?;
+        let local: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 80);
+        let (_, _, client_qd): (QDesc, QDesc, QDesc) = connect(&mut stack, &mut test_rig, local)?;
+
+        match stack.push2(client_qd, &[]) {
+            Err(e) if e.errno == libc::EINVAL => Ok(()),
+            Err(e) => anyhow::bail!("push should have failed with EINVAL: {:?}", e),
+            Ok(_) => anyhow::bail!("push should have failed"),
+        }
+    }
+
+    /// Tests that a buffer pushed with push_buffer is delivered intact, and that zero-length buffers are rejected on
+    /// TCP sockets, as with any other push.
+    #[test]
+    fn test_push_buffer() -> Result<()> {
+        let (mut stack, mut test_rig): (SharedInetStack, SharedTestRuntime) = new_stack()?;
+        let local: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 80);
+        let (_, server_qd, client_qd): (QDesc, QDesc, QDesc) = connect(&mut stack, &mut test_rig, local)?;
+
+        let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let push_qt: QToken = stack.push_buffer(client_qd, DemiBuffer::from_slice(&data)?)?;
+        match wait(&mut stack, &mut test_rig, push_qt)? {
+            OperationResult::Push(_) => (),
+            result => anyhow::bail!("push should have succeeded: {:?}", result),
+        }
+        let pop_qt: QToken = stack.pop(server_qd, None)?;
+        match wait(&mut stack, &mut test_rig, pop_qt)? {
+            OperationResult::Pop(_, buf) => crate::ensure_eq!(&buf[..], &data[..]),
+            result => anyhow::bail!("pop should have returned the pushed data: {:?}", result),
+        }
+
+        match stack.push_buffer(client_qd, DemiBuffer::new(0)) {
+            Err(e) if e.errno == libc::EINVAL => Ok(()),
+            Err(e) => anyhow::bail!("push_buffer should have failed with EINVAL: {:?}", e),
+            Ok(_) => anyhow::bail!("push_buffer should have failed"),
+        }
+    }
+
+    /// Tests that the buffer pushed with push_buffer is the one that reaches the transport, rather than a copy of it.
+    #[test]
+    fn test_push_buffer_no_copy() -> Result<()> {
+        let mut arp: HashMap<Ipv4Addr, MacAddress> = HashMap::new();
+        arp.insert(test_helpers::BOB_IPV4, test_helpers::BOB_MAC);
+        let arp_config: ArpConfig = ArpConfig::new(None, None, None, Some(arp), None);
+        let mut test_rig: SharedTestRuntime = SharedTestRuntime::new(
+            Instant::now(),
+            arp_config.clone(),
+            UdpConfig::default(),
+            TcpConfig::default(),
+            test_helpers::ALICE_MAC,
+            test_helpers::ALICE_IPV4,
+        );
+        let bodies: Rc<RefCell<Vec<*const u8>>> = Rc::new(RefCell::new(Vec::new()));
+        let transport: BodyRecorder = BodyRecorder {
+            test_rig: test_rig.clone(),
+            bodies: bodies.clone(),
+        };
+        let mut stack: SharedInetStack = SharedInetStack::new(
+            test_rig.get_runtime(),
+            SharedBox::new(Box::new(transport)),
+            test_helpers::ALICE_MAC,
+            test_helpers::ALICE_IPV4,
+            UdpConfig::default(),
+            TcpConfig::default(),
+            [0; 32],
+            arp_config,
+            PcapConfig::default(),
+            Ethernet2Config::default(),
+            RoutingConfig::default(),
+        )?;
+        let qd: QDesc = stack.socket(AF_INET_VALUE, SOCK_DGRAM, 0)?;
+        stack.bind(qd, SocketAddr::V4(SocketAddrV4::new(test_helpers::ALICE_IPV4, 80)))?;
+        let connect_qt: QToken = stack.connect(qd, SocketAddr::V4(SocketAddrV4::new(test_helpers::BOB_IPV4, 80)))?;
+        match wait(&mut stack, &mut test_rig, connect_qt)? {
+            OperationResult::Connect => (),
+            result => anyhow::bail!("connect should have succeeded: {:?}", result),
+        }
+
+        let buf: DemiBuffer = DemiBuffer::from_slice(&[0x5a; 1000])?;
+        let data: *const u8 = buf.as_ptr();
+        let push_qt: QToken = stack.push_buffer(qd, buf)?;
+        match wait(&mut stack, &mut test_rig, push_qt)? {
+            OperationResult::Push(_) => (),
+            result => anyhow::bail!("push should have succeeded: {:?}", result),
+        }
+        crate::ensure_eq!(bodies.borrow().as_slice(), &[data]);
+        crate::ensure_eq!(test_rig.pop_all_frames().len(), 1);
+
+        Ok(())
+    }
+
+    /// Tests that SO_ACCEPTCONN is only set on listening sockets, and that it cannot be changed.
+    #[test]
+    fn test_accept_conn() -> Result<()> {
+        let (mut stack, mut test_rig): (SharedInetStack, SharedTestRuntime) = new_stack()?;
+        let local: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 80);
+        let (listen_qd, server_qd, client_qd): (QDesc, QDesc, QDesc) = connect(&mut stack, &mut test_rig, local)?;
+
+        let accept_conn = |qd: QDesc| stack.get_socket_option(qd, SocketOptionName::AcceptConn);
+        crate::ensure_eq!(accept_conn(listen_qd)?, SocketOption::AcceptConn(true));
+        crate::ensure_eq!(accept_conn(server_qd)?, SocketOption::AcceptConn(false));
+        crate::ensure_eq!(accept_conn(client_qd)?, SocketOption::AcceptConn(false));
+
+        match stack.set_socket_option(listen_qd, SocketOption::AcceptConn(false)) {
+            Err(e) if e.errno == libc::ENOPROTOOPT => Ok(()),
+            Err(e) => anyhow::bail!("setting SO_ACCEPTCONN should have failed with ENOPROTOOPT: {:?}", e),
+            Ok(_) => anyhow::bail!("setting SO_ACCEPTCONN should have failed"),
+        }
+    }
+
+    /// Tests that a resolution completes as such, along with the link address of the remote host, once it answers.
+    #[test]
+    fn test_resolve() -> Result<()> {
+        let (mut stack, mut test_rig): (SharedInetStack, SharedTestRuntime) = new_stack()?;
+        let now: Instant = Instant::now();
+        let mut bob: SharedEngine = test_helpers::new_bob(now);
+
+        let qt: QToken = stack.resolve(test_helpers::BOB_IPV4)?;
+        test_rig.poll_scheduler();
+        bob.receive(test_rig.pop_frame())?;
+        bob.advance_clock(now);
+        test_rig.push_frame(bob.get_test_rig().pop_frame());
+        match wait(&mut stack, &mut test_rig, qt)? {
+            OperationResult::Resolve(link_addr) => crate::ensure_eq!(link_addr, test_helpers::BOB_MAC),
+            result => anyhow::bail!("resolve should have succeeded: {:?}", result),
+        }
+
+        Ok(())
+    }
+
+    /// Tests that a resolution fails with EHOSTUNREACH if the remote host never answers.
+    #[test]
+    fn test_resolve_unreachable() -> Result<()> {
+        let (mut stack, mut test_rig): (SharedInetStack, SharedTestRuntime) = new_stack()?;
+        let arp_config: ArpConfig = test_rig.get_arp_config();
+        let mut now: Instant = Instant::now();
+
+        let qt: QToken = stack.resolve(test_helpers::BOB_IPV4)?;
+        for _ in 0..arp_config.get_retry_count() + 1 {
+            test_rig.poll_scheduler();
+            now += arp_config.get_request_timeout();
+            test_rig.advance_clock(now);
+        }
+        match wait(&mut stack, &mut test_rig, qt)? {
+            OperationResult::Failed(e) if e.errno == libc::EHOSTUNREACH => Ok(()),
+            result => anyhow::bail!("resolve should have failed with EHOSTUNREACH: {:?}", result),
+        }
+    }
+
+    /// Has `pool` open a new connection to `local`, on which `listen_qd` listens, and returns the connected and
+    /// accepted sockets.
+    fn pool_connect(
+        stack: &mut SharedInetStack,
+        test_rig: &mut SharedTestRuntime,
+        pool: &mut ConnectionPool,
+        listen_qd: QDesc,
+        local: SocketAddrV4,
+    ) -> Result<(QDesc, QDesc)> {
+        let accept_qt: QToken = stack.accept(listen_qd)?;
+        let client_qd: QDesc = match pool.get(local)? {
+            PooledConnection::Connecting(qd, qt) => match wait(stack, test_rig, qt)? {
+                OperationResult::Connect => qd,
+                result => anyhow::bail!("connect should have succeeded: {:?}", result),
+            },
+            connection => anyhow::bail!("pool should have opened a new connection: {:?}", connection),
+        };
+        match wait(stack, test_rig, accept_qt)? {
+            OperationResult::Accept((server_qd, _)) => Ok((client_qd, server_qd)),
+            result => anyhow::bail!("accept should have succeeded: {:?}", result),
+        }
+    }
+
+    /// Tests that a connection that is returned to a pool is handed out again for the same remote, instead of a new one.
+    #[test]
+    fn test_connection_pool_reuse() -> Result<()> {
+        let (mut stack, mut test_rig): (SharedInetStack, SharedTestRuntime) = new_stack()?;
+        let local: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 80);
+        let listen_qd: QDesc = stack.socket(AF_INET_VALUE, SOCK_STREAM, 0)?;
+        stack.bind(listen_qd, SocketAddr::V4(local))?;
+        stack.listen(listen_qd, 1)?;
+        let mut pool: ConnectionPool = ConnectionPool::new(stack.clone(), 4, Duration::from_secs(60));
+
+        let (client_qd, _): (QDesc, QDesc) = pool_connect(&mut stack, &mut test_rig, &mut pool, listen_qd, local)?;
+        for _ in 0..3 {
+            pool.put(client_qd)?;
+            crate::ensure_eq!(pool.num_idle(), 1);
+            crate::ensure_eq!(pool.get(local)?, PooledConnection::Reused(client_qd));
+            crate::ensure_eq!(pool.num_idle(), 0);
+        }
+
+        // Connections that the pool did not hand out cannot be returned to it.
+        pool.put(client_qd)?;
+        match pool.put(client_qd) {
+            Err(e) if e.errno == libc::EBADF => Ok(()),
+            result => anyhow::bail!("put should have failed with EBADF: {:?}", result),
+        }
+    }
+
+    /// Tests that a pool closes the idle connections that the peer closed, and the ones that stay idle for too long.
+    #[test]
+    fn test_connection_pool_discard() -> Result<()> {
+        let idle_timeout: Duration = Duration::from_secs(60);
+        let (mut stack, mut test_rig): (SharedInetStack, SharedTestRuntime) = new_stack()?;
+        let now: Instant = test_rig.get_runtime().get_now();
+        let local: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 80);
+        let listen_qd: QDesc = stack.socket(AF_INET_VALUE, SOCK_STREAM, 0)?;
+        stack.bind(listen_qd, SocketAddr::V4(local))?;
+        stack.listen(listen_qd, 1)?;
+        let mut pool: ConnectionPool = ConnectionPool::new(stack.clone(), 4, idle_timeout);
+
+        // The peer closes the connection while it is idle, so the next get opens a new one.
+        let (client_qd, server_qd): (QDesc, QDesc) =
+            pool_connect(&mut stack, &mut test_rig, &mut pool, listen_qd, local)?;
+        pool.put(client_qd)?;
+        let _close_qt: QToken = stack.async_close(server_qd)?;
+        for _ in 0..FIN_POLLS {
+            test_rig.poll_scheduler();
+        }
+        let (new_client_qd, _): (QDesc, QDesc) = pool_connect(&mut stack, &mut test_rig, &mut pool, listen_qd, local)?;
+        crate::ensure_neq!(new_client_qd, client_qd);
+
+        // The connection stays idle for longer than the timeout.
+        pool.put(new_client_qd)?;
+        crate::ensure_eq!(pool.num_idle(), 1);
+        test_rig.advance_clock(now + idle_timeout);
+        pool.evict_expired();
+        crate::ensure_eq!(pool.num_idle(), 0);
+
+        Ok(())
+    }
+}
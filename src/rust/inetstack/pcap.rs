@@ -0,0 +1,355 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::runtime::{
+    fail::Fail,
+    memory::DemiBuffer,
+    network::{
+        config::PcapConfig,
+        consts::RECEIVE_BATCH_SIZE,
+        NetworkRuntime,
+        PacketBuf,
+    },
+    SharedBox,
+    SharedDemiRuntime,
+    SharedObject,
+};
+use ::arrayvec::ArrayVec;
+use ::std::{
+    fs::File,
+    io::Write,
+    net::SocketAddrV4,
+    ops::{
+        Deref,
+        DerefMut,
+    },
+    time::{
+        Duration,
+        Instant,
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Magic number of a libpcap file with microsecond timestamps.
+const PCAP_MAGIC: u32 = 0xa1b2c3d4;
+/// Major version of the libpcap file format.
+const PCAP_VERSION_MAJOR: u16 = 2;
+/// Minor version of the libpcap file format.
+const PCAP_VERSION_MINOR: u16 = 4;
+/// Maximum number of bytes captured per frame.
+const PCAP_SNAPLEN: u32 = 65535;
+/// Link-layer header type for Ethernet.
+const PCAP_LINKTYPE_ETHERNET: u32 = 1;
+/// Size of the libpcap global header.
+pub const PCAP_GLOBAL_HEADER_SIZE: usize = 24;
+/// Size of a libpcap record header.
+pub const PCAP_RECORD_HEADER_SIZE: usize = 16;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Writes Ethernet frames to a capture file in the libpcap format.
+pub struct PcapWriter {
+    runtime: SharedDemiRuntime,
+    /// The capture file. This is set to `None` once capturing stops.
+    file: Option<File>,
+    /// Number of bytes written to the capture file so far.
+    size: usize,
+    max_size: usize,
+    /// Wall-clock time that corresponds to `start_instant`, used to timestamp frames.
+    start_time: SystemTime,
+    start_instant: Instant,
+}
+
+#[derive(Clone)]
+pub struct SharedPcapWriter(SharedObject<PcapWriter>);
+
+/// Network runtime that captures every frame that is transmitted or received by the underlying transport.
+pub struct PcapTransport {
+    transport: SharedBox<dyn NetworkRuntime>,
+    writer: SharedPcapWriter,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl SharedPcapWriter {
+    /// Creates a capture file at the path given in `config` and writes the libpcap global header to it.
+    pub fn new(runtime: SharedDemiRuntime, config: &PcapConfig) -> Result<Self, Fail> {
+        let path: &str = match config.get_path() {
+            Some(path) => path,
+            None => {
+                let cause: String = format!("packet capture is not enabled");
+                error!("new(): {}", cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            },
+        };
+        let header: [u8; PCAP_GLOBAL_HEADER_SIZE] = Self::global_header();
+        if header.len() > config.get_max_size() {
+            let cause: String = format!(
+                "capture file size limit is too small (max_size={})",
+                config.get_max_size()
+            );
+            error!("new(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        let mut file: File = match File::create(path) {
+            Ok(file) => file,
+            Err(e) => {
+                let cause: String = format!("failed to create capture file (path={:?}, error={:?})", path, e);
+                error!("new(): {}", cause);
+                return Err(Fail::new(libc::EIO, &cause));
+            },
+        };
+
+        if let Err(e) = file.write_all(&header) {
+            let cause: String = format!("failed to write capture file header (path={:?}, error={:?})", path, e);
+            error!("new(): {}", cause);
+            return Err(Fail::new(libc::EIO, &cause));
+        }
+
+        let start_instant: Instant = runtime.get_now();
+        Ok(Self(SharedObject::<PcapWriter>::new(PcapWriter {
+            runtime,
+            file: Some(file),
+            size: header.len(),
+            max_size: config.get_max_size(),
+            start_time: SystemTime::now(),
+            start_instant,
+        })))
+    }
+
+    /// Appends an Ethernet frame to the capture file. Frames are dropped from the capture once the file reaches its
+    /// size limit or if writing to the file fails.
+    pub fn write_frame(&mut self, frame: &[u8]) {
+        if self.file.is_none() {
+            return;
+        }
+
+        let record_size: usize = PCAP_RECORD_HEADER_SIZE + frame.len().min(PCAP_SNAPLEN as usize);
+        if self.size + record_size > self.max_size {
+            warn!(
+                "write_frame(): capture file reached its size limit, stopping capture (max_size={})",
+                self.max_size
+            );
+            self.file = None;
+            return;
+        }
+
+        // Assemble the whole record before writing it, so that the capture file never holds a partial record.
+        let timestamp: Duration = self.timestamp();
+        let mut record: Vec<u8> = Vec::with_capacity(record_size);
+        record.extend_from_slice(&(timestamp.as_secs() as u32).to_le_bytes());
+        record.extend_from_slice(&timestamp.subsec_micros().to_le_bytes());
+        record.extend_from_slice(&((record_size - PCAP_RECORD_HEADER_SIZE) as u32).to_le_bytes());
+        record.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        record.extend_from_slice(&frame[..(record_size - PCAP_RECORD_HEADER_SIZE)]);
+
+        let file: &mut File = self.file.as_mut().expect("capture file should be open");
+        match file.write_all(&record) {
+            Ok(()) => self.size += record_size,
+            Err(e) => {
+                warn!(
+                    "write_frame(): failed to write to capture file, stopping capture (error={:?})",
+                    e
+                );
+                self.file = None;
+            },
+        }
+    }
+
+    /// Serializes a packet buffer and appends the resulting Ethernet frame to the capture file.
+    pub fn write_packet(&mut self, pkt: &dyn PacketBuf) {
+        if self.file.is_none() {
+            return;
+        }
+
+        let header_size: usize = pkt.header_size();
+        let mut frame: Vec<u8> = vec![0; header_size];
+        pkt.write_header(&mut frame[..header_size]);
+        if let Some(body) = pkt.take_body() {
+            frame.extend_from_slice(&body[..]);
+        }
+        self.write_frame(&frame);
+    }
+
+    /// Returns the time elapsed since the UNIX epoch, according to the runtime's clock.
+    fn timestamp(&self) -> Duration {
+        let now: Instant = self.runtime.get_now();
+        let elapsed: Duration = now.saturating_duration_since(self.start_instant);
+        (self.start_time + elapsed)
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO)
+    }
+
+    /// Builds the libpcap global header.
+    fn global_header() -> [u8; PCAP_GLOBAL_HEADER_SIZE] {
+        let mut header: [u8; PCAP_GLOBAL_HEADER_SIZE] = [0; PCAP_GLOBAL_HEADER_SIZE];
+        header[0..4].copy_from_slice(&PCAP_MAGIC.to_le_bytes());
+        header[4..6].copy_from_slice(&PCAP_VERSION_MAJOR.to_le_bytes());
+        header[6..8].copy_from_slice(&PCAP_VERSION_MINOR.to_le_bytes());
+        // Bytes 8 to 16 hold the timezone offset and timestamp accuracy, which are always zero.
+        header[16..20].copy_from_slice(&PCAP_SNAPLEN.to_le_bytes());
+        header[20..24].copy_from_slice(&PCAP_LINKTYPE_ETHERNET.to_le_bytes());
+        header
+    }
+}
+
+impl PcapTransport {
+    pub fn new(transport: SharedBox<dyn NetworkRuntime>, writer: SharedPcapWriter) -> Self {
+        Self { transport, writer }
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl Deref for SharedPcapWriter {
+    type Target = PcapWriter;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.deref()
+    }
+}
+
+impl DerefMut for SharedPcapWriter {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0.deref_mut()
+    }
+}
+
+impl NetworkRuntime for PcapTransport {
+    fn transmit(&mut self, pkt: Box<dyn PacketBuf>) {
+        self.writer.write_packet(pkt.as_ref());
+        self.transport.transmit(pkt);
+    }
+
+    fn receive(&mut self) -> ArrayVec<DemiBuffer, RECEIVE_BATCH_SIZE> {
+        let batch: ArrayVec<DemiBuffer, RECEIVE_BATCH_SIZE> = self.transport.receive();
+        for frame in batch.iter() {
+            self.writer.write_frame(&frame[..]);
+        }
+        batch
+    }
+
+    fn register_flow(&mut self, local: SocketAddrV4) -> Result<(), Fail> {
+        self.transport.register_flow(local)
+    }
+
+    fn unregister_flow(&mut self, local: SocketAddrV4) -> Result<(), Fail> {
+        self.transport.unregister_flow(local)
+    }
+
+    fn get_ifindex(&self) -> u32 {
+        self.transport.get_ifindex()
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        SharedPcapWriter,
+        PCAP_GLOBAL_HEADER_SIZE,
+        PCAP_LINKTYPE_ETHERNET,
+        PCAP_MAGIC,
+        PCAP_RECORD_HEADER_SIZE,
+    };
+    use crate::runtime::{
+        network::config::PcapConfig,
+        SharedDemiRuntime,
+    };
+    use ::anyhow::Result;
+    use ::std::{
+        fs,
+        path::PathBuf,
+        time::{
+            Duration,
+            Instant,
+        },
+    };
+
+    /// Returns a path for a capture file that is unique to the calling test.
+    fn capture_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("demikernel-{}-{}.pcap", name, std::process::id()))
+    }
+
+    /// Reads a little-endian 32-bit integer from `bytes` at `offset`.
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+    }
+
+    /// Tests that frames are written as libpcap records, after the global header.
+    #[test]
+    fn test_pcap_writer_format() -> Result<()> {
+        let now: Instant = Instant::now();
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::new(now);
+        let path: PathBuf = capture_path("format");
+        let config: PcapConfig = PcapConfig::new(Some(path.to_string_lossy().to_string()), None);
+        let mut writer: SharedPcapWriter = SharedPcapWriter::new(runtime.clone(), &config)?;
+
+        writer.write_frame(&[0xaa; 60]);
+        runtime.advance_clock(now + Duration::from_micros(1500));
+        writer.write_frame(&[0xbb; 64]);
+
+        let bytes: Vec<u8> = fs::read(&path)?;
+        fs::remove_file(&path)?;
+        crate::ensure_eq!(
+            bytes.len(),
+            PCAP_GLOBAL_HEADER_SIZE + 2 * PCAP_RECORD_HEADER_SIZE + 60 + 64
+        );
+        crate::ensure_eq!(read_u32(&bytes, 0), PCAP_MAGIC);
+        crate::ensure_eq!(read_u32(&bytes, 20), PCAP_LINKTYPE_ETHERNET);
+
+        let first: usize = PCAP_GLOBAL_HEADER_SIZE;
+        crate::ensure_eq!(read_u32(&bytes, first + 8), 60);
+        crate::ensure_eq!(read_u32(&bytes, first + 12), 60);
+        crate::ensure_eq!(bytes[first + PCAP_RECORD_HEADER_SIZE], 0xaa);
+
+        let second: usize = first + PCAP_RECORD_HEADER_SIZE + 60;
+        crate::ensure_eq!(read_u32(&bytes, second + 8), 64);
+        crate::ensure_eq!(bytes[second + PCAP_RECORD_HEADER_SIZE], 0xbb);
+
+        // Timestamps should follow the runtime's clock.
+        let first_us: u64 = read_u32(&bytes, first) as u64 * 1_000_000 + read_u32(&bytes, first + 4) as u64;
+        let second_us: u64 = read_u32(&bytes, second) as u64 * 1_000_000 + read_u32(&bytes, second + 4) as u64;
+        crate::ensure_eq!(second_us - first_us, 1500);
+
+        Ok(())
+    }
+
+    /// Tests that the writer stops capturing once the capture file reaches its size limit.
+    #[test]
+    fn test_pcap_writer_max_size() -> Result<()> {
+        let runtime: SharedDemiRuntime = SharedDemiRuntime::new(Instant::now());
+        let path: PathBuf = capture_path("max-size");
+        let max_size: usize = PCAP_GLOBAL_HEADER_SIZE + 2 * (PCAP_RECORD_HEADER_SIZE + 60);
+        let config: PcapConfig = PcapConfig::new(Some(path.to_string_lossy().to_string()), Some(max_size));
+        let mut writer: SharedPcapWriter = SharedPcapWriter::new(runtime, &config)?;
+
+        for _ in 0..4 {
+            writer.write_frame(&[0; 60]);
+        }
+
+        let len: u64 = fs::metadata(&path)?.len();
+        fs::remove_file(&path)?;
+        crate::ensure_eq!(len as usize, max_size);
+
+        Ok(())
+    }
+}
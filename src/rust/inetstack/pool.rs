@@ -0,0 +1,201 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::SharedInetStack,
+    pal::constants::{
+        AF_INET_VALUE,
+        SOCK_STREAM,
+    },
+    runtime::{
+        fail::Fail,
+        network::types::Readiness,
+        QDesc,
+        QToken,
+    },
+};
+use ::std::{
+    collections::{
+        HashMap,
+        VecDeque,
+    },
+    net::{
+        SocketAddr,
+        SocketAddrV4,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Connection that is handed out by a [ConnectionPool].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PooledConnection {
+    /// An idle connection of the pool, which can be used right away.
+    Reused(QDesc),
+    /// A new connection, which can be used once its connect operation completes.
+    Connecting(QDesc, QToken),
+}
+
+/// Pool of TCP connections, for clients that make many short exchanges with the same servers. Connections that are
+/// returned to the pool are kept open, and handed out again instead of connecting anew. Idle connections are checked
+/// before they are kept or reused, and closed if the peer closed its side, reset them, or sent data that nobody asked
+/// for. The pool holds at most `max_idle` idle connections, and closes the ones that stay idle for longer than
+/// `idle_timeout`.
+pub struct ConnectionPool {
+    stack: SharedInetStack,
+    /// Maximum number of idle connections, over all remotes.
+    max_idle: usize,
+    /// Time for which a connection may stay idle in the pool before it is closed.
+    idle_timeout: Duration,
+    /// Idle connections to each remote, along with the time at which they were returned, from oldest to newest.
+    idle: HashMap<SocketAddrV4, VecDeque<(QDesc, Instant)>>,
+    /// Number of idle connections, over all remotes.
+    num_idle: usize,
+    /// Remote of each connection that is handed out.
+    in_use: HashMap<QDesc, SocketAddrV4>,
+    /// Close operations of the connections that the pool discarded, which are yet to complete.
+    closing: Vec<QToken>,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl ConnectionPool {
+    /// Creates an empty pool of connections of `stack`.
+    pub fn new(stack: SharedInetStack, max_idle: usize, idle_timeout: Duration) -> Self {
+        Self {
+            stack,
+            max_idle,
+            idle_timeout,
+            idle: HashMap::new(),
+            num_idle: 0,
+            in_use: HashMap::new(),
+            closing: Vec::new(),
+        }
+    }
+
+    /// Hands out a connection to `remote`. The most recently returned idle connection that is still healthy is reused,
+    /// and a new one is opened if there is none.
+    pub fn get(&mut self, remote: SocketAddrV4) -> Result<PooledConnection, Fail> {
+        self.evict_expired();
+
+        while let Some((qd, _)) = self
+            .idle
+            .get_mut(&remote)
+            .and_then(|connections| connections.pop_back())
+        {
+            self.num_idle -= 1;
+            if self.is_healthy(qd) {
+                self.in_use.insert(qd, remote);
+                return Ok(PooledConnection::Reused(qd));
+            }
+            self.discard(qd);
+        }
+        self.idle.remove(&remote);
+
+        let qd: QDesc = self.stack.socket(AF_INET_VALUE, SOCK_STREAM, 0)?;
+        let qt: QToken = match self.stack.connect(qd, SocketAddr::V4(remote)) {
+            Ok(qt) => qt,
+            Err(e) => {
+                self.discard(qd);
+                return Err(e);
+            },
+        };
+        self.in_use.insert(qd, remote);
+        Ok(PooledConnection::Connecting(qd, qt))
+    }
+
+    /// Returns the connection `qd`, which [get](Self::get) handed out, to the pool. The connection is closed instead of
+    /// being kept if it is not healthy, or if the pool is full. Fails with `EBADF` if the connection does not belong to
+    /// the pool.
+    pub fn put(&mut self, qd: QDesc) -> Result<(), Fail> {
+        let remote: SocketAddrV4 = match self.in_use.remove(&qd) {
+            Some(remote) => remote,
+            None => {
+                let cause: String = format!("connection was not handed out by the pool (qd={:?})", qd);
+                error!("put(): {}", &cause);
+                return Err(Fail::new(libc::EBADF, &cause));
+            },
+        };
+        self.evict_expired();
+
+        if self.num_idle >= self.max_idle || !self.is_healthy(qd) {
+            self.discard(qd);
+            return Ok(());
+        }
+        let now: Instant = self.stack.runtime.get_now();
+        self.idle.entry(remote).or_default().push_back((qd, now));
+        self.num_idle += 1;
+        Ok(())
+    }
+
+    /// Closes the idle connections that were returned to the pool longer than the idle timeout ago.
+    pub fn evict_expired(&mut self) {
+        self.reap_closed();
+
+        let now: Instant = self.stack.runtime.get_now();
+        let idle_timeout: Duration = self.idle_timeout;
+        let mut expired: Vec<QDesc> = Vec::new();
+        self.idle.retain(|_, connections| {
+            while let Some((qd, since)) = connections.front() {
+                if now.saturating_duration_since(*since) < idle_timeout {
+                    break;
+                }
+                expired.push(*qd);
+                connections.pop_front();
+            }
+            !connections.is_empty()
+        });
+        self.num_idle -= expired.len();
+        for qd in expired {
+            self.discard(qd);
+        }
+    }
+
+    /// Returns the number of idle connections in the pool.
+    pub fn num_idle(&self) -> usize {
+        self.num_idle
+    }
+
+    /// Checks whether the idle connection `qd` can be handed out. The connection should accept pushes, and nothing
+    /// should be pending on it: no data, no end of stream and no error.
+    fn is_healthy(&self, qd: QDesc) -> bool {
+        let readiness: Readiness = match self.stack.readiness(qd) {
+            Ok(readiness) => readiness,
+            Err(_) => return false,
+        };
+        readiness.writable && !(readiness.readable || readiness.read_closed || readiness.hangup || readiness.error)
+    }
+
+    /// Closes the connection `qd` in the background.
+    fn discard(&mut self, qd: QDesc) {
+        match self.stack.async_close(qd) {
+            Ok(qt) => self.closing.push(qt),
+            Err(e) => warn!("discard(): failed to close connection (qd={:?}): {:?}", qd, e),
+        }
+    }
+
+    /// Takes the results of the close operations that completed, so that they do not pile up in the scheduler.
+    fn reap_closed(&mut self) {
+        let mut stack: SharedInetStack = self.stack.clone();
+        self.closing.retain(|qt| match stack.runtime.from_task_id(*qt) {
+            Ok(handle) if handle.has_completed() => {
+                stack.take_operation(handle);
+                false
+            },
+            Ok(_) => true,
+            Err(_) => false,
+        });
+    }
+}
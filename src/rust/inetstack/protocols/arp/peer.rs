@@ -19,7 +19,10 @@ use crate::{
         fail::Fail,
         memory::DemiBuffer,
         network::{
-            config::ArpConfig,
+            config::{
+                ArpConfig,
+                RoutingConfig,
+            },
             types::MacAddress,
             NetworkRuntime,
         },
@@ -39,7 +42,10 @@ use ::futures::{
     select_biased,
     FutureExt,
 };
-use ::libc::ETIMEDOUT;
+use ::libc::{
+    EHOSTUNREACH,
+    ETIMEDOUT,
+};
 use ::std::{
     collections::{
         HashMap,
@@ -72,6 +78,7 @@ pub struct ArpPeer {
     cache: ArpCache,
     waiters: HashMap<Ipv4Addr, LinkedList<Sender<MacAddress>>>,
     arp_config: ArpConfig,
+    routing_config: RoutingConfig,
     recv_queue: AsyncQueue<DemiBuffer>,
 }
 
@@ -92,6 +99,7 @@ impl SharedArpPeer {
         local_link_addr: MacAddress,
         local_ipv4_addr: Ipv4Addr,
         arp_config: ArpConfig,
+        routing_config: RoutingConfig,
     ) -> Result<Self, Fail> {
         let cache: ArpCache = ArpCache::new(
             runtime.get_timer(),
@@ -108,6 +116,7 @@ impl SharedArpPeer {
             cache,
             waiters: HashMap::default(),
             arp_config,
+            routing_config,
             recv_queue: AsyncQueue::<DemiBuffer>::default(),
         }));
         // This is a future returned by the async function.
@@ -115,6 +124,12 @@ impl SharedArpPeer {
         Ok(peer.clone())
     }
 
+    /// Changes the addresses that the target peer answers requests for and sends its own requests from.
+    pub fn set_local_addrs(&mut self, local_link_addr: MacAddress, local_ipv4_addr: Ipv4Addr) {
+        self.local_link_addr = local_link_addr;
+        self.local_ipv4_addr = local_ipv4_addr;
+    }
+
     /// Insert a packet for processing.
     pub fn receive(&mut self, buf: DemiBuffer) {
         self.recv_queue.push(buf)
@@ -253,11 +268,51 @@ impl SharedArpPeer {
         }
     }
 
+    /// Picks the next hop towards `ipv4_addr`, that is, the address whose link address packets for `ipv4_addr` are
+    /// sent to.
+    pub fn next_hop(&self, ipv4_addr: Ipv4Addr) -> Ipv4Addr {
+        self.routing_config.next_hop(self.local_ipv4_addr, ipv4_addr)
+    }
+
     pub fn try_query(&self, ipv4_addr: Ipv4Addr) -> Option<MacAddress> {
-        self.cache.get(ipv4_addr).cloned()
+        if ipv4_addr.is_loopback() {
+            return Some(self.local_link_addr);
+        }
+        self.cache.get(self.next_hop(ipv4_addr)).cloned()
     }
 
+    /// Resolves the link address of the next hop towards `ipv4_addr`. Addresses of the loopback network resolve to
+    /// the local link address, as packets for them never leave the stack. Link-local addresses (169.254.0.0/16) are
+    /// always resolved themselves, on the local link. Other addresses resolve to the route or gateway that covers
+    /// them, as [next_hop](Self::next_hop) picks it.
     pub async fn query(&mut self, ipv4_addr: Ipv4Addr, yielder: &Yielder) -> Result<MacAddress, Fail> {
+        if ipv4_addr.is_loopback() {
+            return Ok(self.local_link_addr);
+        }
+        let next_hop: Ipv4Addr = self.next_hop(ipv4_addr);
+        self.do_query(next_hop, yielder).await
+    }
+
+    /// Resolves the link address of `ipv4_addr` itself, bypassing any gateway or route that covers it (i.e. as
+    /// SO_DONTROUTE does). The destination is asked for on the local link even when it is off the local subnet, so
+    /// this fails with EHOSTUNREACH if it is not attached to the link and hence does not answer.
+    pub async fn query_direct(&mut self, ipv4_addr: Ipv4Addr, yielder: &Yielder) -> Result<MacAddress, Fail> {
+        if ipv4_addr.is_loopback() {
+            return Ok(self.local_link_addr);
+        }
+        match self.do_query(ipv4_addr, yielder).await {
+            Ok(link_addr) => Ok(link_addr),
+            Err(e) if e.errno == ETIMEDOUT => {
+                let cause: String = format!("destination is not on the local link (ipv4_addr={:?})", ipv4_addr);
+                error!("query_direct(): {}", cause);
+                Err(Fail::new(EHOSTUNREACH, &cause))
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Resolves the link address of `ipv4_addr` on the local link, either from the cache or through ARP requests.
+    async fn do_query(&mut self, ipv4_addr: Ipv4Addr, yielder: &Yielder) -> Result<MacAddress, Fail> {
         if let Some(&link_addr) = self.cache.get(ipv4_addr) {
             return Ok(link_addr);
         }
@@ -303,6 +358,20 @@ impl SharedArpPeer {
         result
     }
 
+    /// Resolves the link address of the next hop towards `ipv4_addr` ahead of sending anything to it, so that the
+    /// first packet does not wait for an ARP round trip. Fails with EHOSTUNREACH if the next hop does not answer.
+    pub async fn resolve(&mut self, ipv4_addr: Ipv4Addr, yielder: &Yielder) -> Result<MacAddress, Fail> {
+        match self.query(ipv4_addr, yielder).await {
+            Ok(link_addr) => Ok(link_addr),
+            Err(e) if e.errno == ETIMEDOUT => {
+                let cause: String = format!("next hop is unreachable (ipv4_addr={:?})", ipv4_addr);
+                error!("resolve(): {}", cause);
+                Err(Fail::new(EHOSTUNREACH, &cause))
+            },
+            Err(e) => Err(e),
+        }
+    }
+
     #[cfg(test)]
     pub fn export_cache(&self) -> HashMap<Ipv4Addr, MacAddress> {
         self.cache.export()
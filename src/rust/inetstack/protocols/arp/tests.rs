@@ -7,13 +7,35 @@ use super::packet::{
 };
 use crate::{
     inetstack::{
-        protocols::ethernet2::Ethernet2Header,
+        protocols::{
+            ethernet2::{
+                EtherType2,
+                Ethernet2Header,
+            },
+            ipv4::Ipv4Header,
+            tcp::segment::TcpHeader,
+        },
         test_helpers::{
             self,
             SharedEngine,
+            SharedTestRuntime,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        network::{
+            config::{
+                ArpConfig,
+                RoutingConfig,
+                TcpConfig,
+                UdpConfig,
+            },
+            types::MacAddress,
         },
+        Operation,
+        OperationResult,
+        QDesc,
     },
-    runtime::network::types::MacAddress,
 };
 use ::anyhow::Result;
 use ::futures::{
@@ -23,9 +45,18 @@ use ::futures::{
     },
     FutureExt,
 };
-use ::libc::ETIMEDOUT;
+use ::libc::{
+    EHOSTUNREACH,
+    ETIMEDOUT,
+};
 use ::std::{
+    collections::HashMap,
     future::Future,
+    net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    },
+    pin::Pin,
     task::Poll,
     time::{
         Duration,
@@ -207,3 +238,182 @@ fn no_reply() -> Result<()> {
         _ => anyhow::bail!("poll should have succeeded"),
     }
 }
+
+/// Tests that resolving an address off the local subnet resolves the link address of the default gateway instead.
+#[test]
+fn off_subnet_resolves_gateway() -> Result<()> {
+    let now = Instant::now();
+    let arp_config: ArpConfig = ArpConfig::new(
+        Some(Duration::from_secs(600)),
+        Some(Duration::from_secs(1)),
+        Some(2),
+        Some(HashMap::new()),
+        Some(false),
+    );
+    let test_rig: SharedTestRuntime = SharedTestRuntime::new(
+        now,
+        arp_config,
+        UdpConfig::default(),
+        TcpConfig::default(),
+        test_helpers::ALICE_MAC,
+        test_helpers::ALICE_IPV4,
+    );
+    let routing_config: RoutingConfig = RoutingConfig::new(Some(24), Some(test_helpers::CARRIE_IPV4), None)?;
+    let mut alice: SharedEngine = SharedEngine::new_with_routing_config(test_rig, routing_config)?;
+    let mut carrie: SharedEngine = test_helpers::new_carrie(now);
+    let remote: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut alice2 = alice.clone();
+    let mut fut = alice2.arp_query(remote).boxed_local();
+    crate::ensure_eq!(Future::poll(fut.as_mut(), &mut ctx).is_pending(), true);
+
+    // The request asks for the gateway, not for the remote address.
+    let request = alice.get_test_rig().pop_frame();
+    let payload = match Ethernet2Header::parse(request.clone()) {
+        Ok((_, payload)) => payload,
+        Err(e) => anyhow::bail!("Could not parse ethernet header: {:?}", e),
+    };
+    let arp = match ArpHeader::parse(payload) {
+        Ok(arp) => arp,
+        Err(e) => anyhow::bail!("Could not parse arp header: {:?}", e),
+    };
+    crate::ensure_eq!(arp.get_operation(), ArpOperation::Request);
+    crate::ensure_eq!(arp.get_destination_protocol_addr(), test_helpers::CARRIE_IPV4);
+
+    carrie.receive(request)?;
+    carrie.advance_clock(now);
+    let reply = carrie.get_test_rig().pop_frame();
+    alice.receive(reply)?;
+    alice.advance_clock(now + Duration::from_micros(1));
+    let link_addr: MacAddress = match Future::poll(fut.as_mut(), &mut ctx) {
+        Poll::Ready(Ok(link_addr)) => link_addr,
+        _ => anyhow::bail!("poll should succeed"),
+    };
+    crate::ensure_eq!(link_addr, test_helpers::CARRIE_MAC);
+    crate::ensure_eq!(alice.export_arp_cache().get(&remote), None);
+
+    Ok(())
+}
+
+/// Tests that a UDP socket with SO_DONTROUTE set asks for a destination off the local subnet on the link itself,
+/// instead of sending to the default gateway, and that its pushes fail with EHOSTUNREACH when the destination does not
+/// answer.
+#[test]
+fn off_subnet_dontroute_resolves_destination() -> Result<()> {
+    let mut now = Instant::now();
+    let mut arp: HashMap<Ipv4Addr, MacAddress> = HashMap::new();
+    arp.insert(test_helpers::CARRIE_IPV4, test_helpers::CARRIE_MAC);
+    let arp_config: ArpConfig = ArpConfig::new(
+        Some(Duration::from_secs(600)),
+        Some(Duration::from_secs(1)),
+        Some(2),
+        Some(arp),
+        Some(false),
+    );
+    let test_rig: SharedTestRuntime = SharedTestRuntime::new(
+        now,
+        arp_config,
+        UdpConfig::default(),
+        TcpConfig::default(),
+        test_helpers::ALICE_MAC,
+        test_helpers::ALICE_IPV4,
+    );
+    let routing_config: RoutingConfig = RoutingConfig::new(Some(24), Some(test_helpers::CARRIE_IPV4), None)?;
+    let mut alice: SharedEngine = SharedEngine::new_with_routing_config(test_rig, routing_config)?;
+    let qd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(qd, SocketAddrV4::new(test_helpers::ALICE_IPV4, 80))?;
+    let remote: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+    let remote_addr: SocketAddrV4 = SocketAddrV4::new(remote, 80);
+    let buf: DemiBuffer = DemiBuffer::from_slice(&[0x5a; 32])?;
+    let mut ctx = Context::from_waker(noop_waker_ref());
+
+    // Without SO_DONTROUTE, the datagram goes to the gateway, whose link address is known.
+    let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(qd, buf.clone(), remote_addr)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Push(_))) => {},
+        _ => anyhow::bail!("pushto through the gateway should have succeeded"),
+    };
+    let (eth2_header, _) = Ethernet2Header::parse(alice.get_test_rig().pop_frame())?;
+    crate::ensure_eq!(eth2_header.dst_addr(), test_helpers::CARRIE_MAC);
+
+    // With SO_DONTROUTE, the requests ask for the remote address, not for the gateway.
+    alice.udp_set_dontroute(qd, true)?;
+    let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(qd, buf, remote_addr)?;
+    for _ in 0..alice.get_test_rig().get_arp_config().get_retry_count() + 1 {
+        crate::ensure_eq!(Future::poll(coroutine.as_mut(), &mut ctx).is_pending(), true);
+        let (_, payload) = Ethernet2Header::parse(alice.get_test_rig().pop_frame())?;
+        let arp: ArpHeader = ArpHeader::parse(payload)?;
+        crate::ensure_eq!(arp.get_operation(), ArpOperation::Request);
+        crate::ensure_eq!(arp.get_destination_protocol_addr(), remote);
+        now += alice.get_test_rig().get_arp_config().get_request_timeout();
+        alice.advance_clock(now);
+    }
+
+    // The remote address is not on the link, so it never answers.
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Failed(e))) if e.errno == EHOSTUNREACH => Ok(()),
+        _ => anyhow::bail!("pushto should have failed with EHOSTUNREACH"),
+    }
+}
+
+/// Tests that a connect that follows a resolution sends its SYN right away, without an ARP round trip.
+#[test]
+fn resolve_before_connect() -> Result<()> {
+    let now = Instant::now();
+    let mut alice: SharedEngine = test_helpers::new_alice(now);
+    let mut carrie: SharedEngine = test_helpers::new_carrie(now);
+
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut alice2 = alice.clone();
+    let mut fut = alice2.arp_resolve(test_helpers::CARRIE_IPV4).boxed_local();
+    crate::ensure_eq!(Future::poll(fut.as_mut(), &mut ctx).is_pending(), true);
+
+    let request = alice.get_test_rig().pop_frame();
+    carrie.receive(request)?;
+    carrie.advance_clock(now);
+    let reply = carrie.get_test_rig().pop_frame();
+    alice.receive(reply)?;
+    alice.advance_clock(now + Duration::from_micros(1));
+    let link_addr: MacAddress = match Future::poll(fut.as_mut(), &mut ctx) {
+        Poll::Ready(Ok(link_addr)) => link_addr,
+        _ => anyhow::bail!("poll should succeed"),
+    };
+    crate::ensure_eq!(link_addr, test_helpers::CARRIE_MAC);
+
+    // The first frame of the connect is the SYN itself.
+    let qd: QDesc = alice.tcp_socket()?;
+    alice.tcp_connect(qd, SocketAddrV4::new(test_helpers::CARRIE_IPV4, 80))?;
+    alice.get_test_rig().poll_scheduler();
+    let (eth2_header, eth2_payload) = Ethernet2Header::parse(alice.get_test_rig().pop_frame())?;
+    crate::ensure_eq!(eth2_header.dst_addr(), test_helpers::CARRIE_MAC);
+    crate::ensure_eq!(eth2_header.ether_type(), EtherType2::Ipv4);
+    let (ipv4_header, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+    let (tcp_header, _) = TcpHeader::parse(&ipv4_header, ipv4_payload, false)?;
+    crate::ensure_eq!(tcp_header.syn, true);
+    crate::ensure_eq!(tcp_header.ack, false);
+
+    Ok(())
+}
+
+/// Tests that resolving an address whose next hop never answers fails with EHOSTUNREACH.
+#[test]
+fn resolve_unreachable() -> Result<()> {
+    let mut now = Instant::now();
+    let mut alice: SharedEngine = test_helpers::new_alice(now);
+
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut alice2 = alice.clone();
+    let mut fut = alice2.arp_resolve(test_helpers::CARRIE_IPV4).boxed_local();
+    crate::ensure_eq!(Future::poll(fut.as_mut(), &mut ctx).is_pending(), true);
+
+    for _ in 0..alice.get_test_rig().get_arp_config().get_retry_count() + 1 {
+        now += alice.get_test_rig().get_arp_config().get_request_timeout();
+        alice.advance_clock(now);
+        let _ = Future::poll(fut.as_mut(), &mut ctx);
+    }
+    match Future::poll(fut.as_mut(), &mut ctx) {
+        Poll::Ready(Err(error)) if error.errno == EHOSTUNREACH => Ok(()),
+        result => anyhow::bail!("resolve should have failed with EHOSTUNREACH: {:?}", result),
+    }
+}
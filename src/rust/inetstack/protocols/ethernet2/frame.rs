@@ -17,6 +17,8 @@ use ::std::convert::{
 
 pub const ETHERNET2_HEADER_SIZE: usize = 14;
 pub const MIN_PAYLOAD_SIZE: usize = 46;
+/// Minimum size of an Ethernet frame, excluding the frame check sequence.
+pub const MIN_FRAME_SIZE: usize = ETHERNET2_HEADER_SIZE + MIN_PAYLOAD_SIZE;
 
 #[derive(Clone, Debug)]
 pub struct Ethernet2Header {
@@ -2,12 +2,15 @@
 // Licensed under the MIT license.
 
 mod frame;
+pub mod padding;
 mod protocol;
+pub mod vlan;
 
 pub use self::{
     frame::{
         Ethernet2Header,
         ETHERNET2_HEADER_SIZE,
+        MIN_FRAME_SIZE,
         MIN_PAYLOAD_SIZE,
     },
     protocol::EtherType2,
@@ -0,0 +1,245 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::protocols::ethernet2::MIN_FRAME_SIZE,
+    runtime::{
+        fail::Fail,
+        memory::DemiBuffer,
+        network::{
+            consts::RECEIVE_BATCH_SIZE,
+            NetworkRuntime,
+            PacketBuf,
+        },
+        SharedBox,
+    },
+};
+use ::arrayvec::ArrayVec;
+use ::std::net::{
+    Ipv4Addr,
+    SocketAddrV4,
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Packet buffer that zero-pads the body of the underlying packet, so that the whole frame is at least
+/// [MIN_FRAME_SIZE] bytes long. Headers are written by the underlying packet, so length fields and checksums only
+/// cover the original payload.
+pub struct PaddedPacket {
+    pkt: Box<dyn PacketBuf>,
+}
+
+/// Network runtime that pads every frame transmitted through the underlying transport to the minimum Ethernet frame
+/// size. Received frames are passed through untouched, as upper layers trim padding using their own length fields.
+pub struct PaddingTransport {
+    transport: SharedBox<dyn NetworkRuntime>,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl PaddedPacket {
+    pub fn new(pkt: Box<dyn PacketBuf>) -> Self {
+        Self { pkt }
+    }
+}
+
+impl PaddingTransport {
+    pub fn new(transport: SharedBox<dyn NetworkRuntime>) -> Self {
+        Self { transport }
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl PacketBuf for PaddedPacket {
+    fn header_size(&self) -> usize {
+        self.pkt.header_size()
+    }
+
+    fn write_header(&self, buf: &mut [u8]) {
+        self.pkt.write_header(buf)
+    }
+
+    fn body_size(&self) -> usize {
+        self.pkt
+            .body_size()
+            .max(MIN_FRAME_SIZE.saturating_sub(self.pkt.header_size()))
+    }
+
+    fn ipv4_dst_addr(&self) -> Option<Ipv4Addr> {
+        self.pkt.ipv4_dst_addr()
+    }
+
+    fn tx_priority(&self) -> u8 {
+        self.pkt.tx_priority()
+    }
+
+    fn take_body(&self) -> Option<DemiBuffer> {
+        let body: Option<DemiBuffer> = self.pkt.take_body();
+        let body_size: usize = self.body_size();
+        let unpadded_size: usize = body.as_ref().map_or(0, |body| body.len());
+        if unpadded_size >= body_size {
+            return body;
+        }
+
+        // Copy the body into a zeroed buffer that is large enough to reach the minimum frame size.
+        let mut padded: Vec<u8> = vec![0; body_size];
+        if let Some(body) = body {
+            padded[..unpadded_size].copy_from_slice(&body[..]);
+        }
+        Some(DemiBuffer::from_slice(&padded).expect("padded body should be shorter than u16::MAX"))
+    }
+}
+
+impl NetworkRuntime for PaddingTransport {
+    fn transmit(&mut self, pkt: Box<dyn PacketBuf>) {
+        if pkt.header_size() + pkt.body_size() < MIN_FRAME_SIZE {
+            self.transport.transmit(Box::new(PaddedPacket::new(pkt)));
+        } else {
+            self.transport.transmit(pkt);
+        }
+    }
+
+    fn receive(&mut self) -> ArrayVec<DemiBuffer, RECEIVE_BATCH_SIZE> {
+        self.transport.receive()
+    }
+
+    fn register_flow(&mut self, local: SocketAddrV4) -> Result<(), Fail> {
+        self.transport.register_flow(local)
+    }
+
+    fn unregister_flow(&mut self, local: SocketAddrV4) -> Result<(), Fail> {
+        self.transport.unregister_flow(local)
+    }
+
+    fn get_ifindex(&self) -> u32 {
+        self.transport.get_ifindex()
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::PaddingTransport;
+    use crate::{
+        inetstack::{
+            protocols::{
+                ethernet2::{
+                    EtherType2,
+                    Ethernet2Header,
+                    MIN_FRAME_SIZE,
+                },
+                ip::IpProtocol,
+                ipv4::Ipv4Header,
+                tcp::{
+                    segment::{
+                        TcpHeader,
+                        TcpSegment,
+                    },
+                    SeqNumber,
+                },
+            },
+            test_helpers::{
+                self,
+                SharedTestRuntime,
+            },
+        },
+        runtime::{
+            memory::DemiBuffer,
+            network::{
+                config::{
+                    ArpConfig,
+                    TcpConfig,
+                    UdpConfig,
+                },
+                NetworkRuntime,
+            },
+            SharedBox,
+        },
+    };
+    use ::anyhow::Result;
+    use ::std::time::Instant;
+
+    /// Builds a padding transport on top of a test runtime, and returns both.
+    fn new_transport() -> (PaddingTransport, SharedTestRuntime) {
+        let test_rig: SharedTestRuntime = SharedTestRuntime::new(
+            Instant::now(),
+            ArpConfig::default(),
+            UdpConfig::default(),
+            TcpConfig::default(),
+            test_helpers::ALICE_MAC,
+            test_helpers::ALICE_IPV4,
+        );
+        let transport: PaddingTransport =
+            PaddingTransport::new(SharedBox::<dyn NetworkRuntime>::new(Box::new(test_rig.clone())));
+        (transport, test_rig)
+    }
+
+    /// Builds a TCP segment from Alice to Bob.
+    fn new_segment(data: Option<DemiBuffer>) -> TcpSegment {
+        let mut tcp_hdr: TcpHeader = TcpHeader::new(49152, 80);
+        tcp_hdr.ack = true;
+        tcp_hdr.seq_num = SeqNumber::from(1);
+        tcp_hdr.ack_num = SeqNumber::from(1);
+        tcp_hdr.window_size = 1024;
+        TcpSegment {
+            ethernet2_hdr: Ethernet2Header::new(test_helpers::BOB_MAC, test_helpers::ALICE_MAC, EtherType2::Ipv4),
+            ipv4_hdr: Ipv4Header::new(test_helpers::ALICE_IPV4, test_helpers::BOB_IPV4, IpProtocol::TCP),
+            tcp_hdr,
+            data,
+            tx_checksum_offload: false,
+        }
+    }
+
+    /// Tests that a pure TCP ACK is zero-padded to the minimum frame size, and still parses to an empty segment.
+    #[test]
+    fn test_padding_tcp_ack() -> Result<()> {
+        let (mut transport, mut test_rig) = new_transport();
+        let segment: TcpSegment = new_segment(None);
+        let unpadded_size: usize =
+            segment.ethernet2_hdr.compute_size() + segment.ipv4_hdr.compute_size() + segment.tcp_hdr.compute_size();
+        crate::ensure_eq!(unpadded_size < MIN_FRAME_SIZE, true);
+        transport.transmit(Box::new(segment));
+
+        let frame: DemiBuffer = test_rig.pop_frame();
+        crate::ensure_eq!(frame.len(), MIN_FRAME_SIZE);
+        crate::ensure_eq!(frame[unpadded_size..].iter().all(|b| *b == 0), true);
+
+        let (_, eth2_payload) = Ethernet2Header::parse(frame)?;
+        let (ipv4_hdr, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+        let (tcp_hdr, tcp_payload): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_hdr, ipv4_payload, false)?;
+        crate::ensure_eq!(tcp_hdr.ack, true);
+        crate::ensure_eq!(tcp_payload.len(), 0);
+
+        Ok(())
+    }
+
+    /// Tests that frames that already have the minimum frame size are transmitted unchanged.
+    #[test]
+    fn test_padding_large_frame() -> Result<()> {
+        let (mut transport, mut test_rig) = new_transport();
+        let segment: TcpSegment = new_segment(Some(DemiBuffer::from_slice(&[0xff; 100])?));
+        let size: usize = segment.ethernet2_hdr.compute_size()
+            + segment.ipv4_hdr.compute_size()
+            + segment.tcp_hdr.compute_size()
+            + 100;
+        transport.transmit(Box::new(segment));
+
+        crate::ensure_eq!(test_rig.pop_frame().len(), size);
+
+        Ok(())
+    }
+}
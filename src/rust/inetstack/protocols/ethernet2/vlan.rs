@@ -0,0 +1,291 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::runtime::{
+    fail::Fail,
+    memory::DemiBuffer,
+    network::{
+        consts::RECEIVE_BATCH_SIZE,
+        NetworkRuntime,
+        PacketBuf,
+    },
+    SharedBox,
+};
+use ::arrayvec::ArrayVec;
+use ::std::net::{
+    Ipv4Addr,
+    SocketAddrV4,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Size of an 802.1Q tag.
+pub const VLAN_TAG_SIZE: usize = 4;
+
+/// Tag protocol identifier of 802.1Q tags. It takes the place of the EtherType in tagged frames.
+const TPID_8021Q: u16 = 0x8100;
+
+/// Offset of the 802.1Q tag in an Ethernet frame, right after the destination and source addresses.
+const VLAN_TAG_OFFSET: usize = 12;
+
+/// Mask of the VLAN identifier in the tag control information.
+const VLAN_ID_MASK: u16 = 0x0fff;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Packet buffer that inserts an 802.1Q tag into the Ethernet header of the underlying packet.
+pub struct TaggedPacket {
+    pkt: Box<dyn PacketBuf>,
+    vlan_id: u16,
+}
+
+/// Network runtime that tags every frame transmitted through the underlying transport with a VLAN identifier, and
+/// strips the tag from received frames. Received frames that are untagged or carry another VLAN identifier are
+/// dropped.
+pub struct VlanTransport {
+    transport: SharedBox<dyn NetworkRuntime>,
+    vlan_id: u16,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl TaggedPacket {
+    pub fn new(pkt: Box<dyn PacketBuf>, vlan_id: u16) -> Self {
+        Self { pkt, vlan_id }
+    }
+}
+
+impl VlanTransport {
+    pub fn new(transport: SharedBox<dyn NetworkRuntime>, vlan_id: u16) -> Self {
+        Self { transport, vlan_id }
+    }
+
+    /// Strips the 802.1Q tag of `frame`, if it carries the VLAN identifier of this transport.
+    fn untag(&self, mut frame: DemiBuffer) -> Option<DemiBuffer> {
+        if frame.len() < VLAN_TAG_OFFSET + VLAN_TAG_SIZE {
+            warn!("receive(): dropping frame: frame too small");
+            return None;
+        }
+        let tpid: u16 = u16::from_be_bytes([frame[VLAN_TAG_OFFSET], frame[VLAN_TAG_OFFSET + 1]]);
+        let tci: u16 = u16::from_be_bytes([frame[VLAN_TAG_OFFSET + 2], frame[VLAN_TAG_OFFSET + 3]]);
+        if tpid != TPID_8021Q || tci & VLAN_ID_MASK != self.vlan_id {
+            let cause: String = format!("mismatched VLAN tag (tpid={:#x}, tci={:#x})", tpid, tci);
+            debug!("receive(): dropping frame: {}", cause);
+            return None;
+        }
+
+        // Move the addresses over the tag, and drop the bytes before them.
+        frame.copy_within(..VLAN_TAG_OFFSET, VLAN_TAG_SIZE);
+        frame
+            .adjust(VLAN_TAG_SIZE)
+            .expect("frame should be longer than the VLAN tag");
+        Some(frame)
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl PacketBuf for TaggedPacket {
+    fn header_size(&self) -> usize {
+        self.pkt.header_size() + VLAN_TAG_SIZE
+    }
+
+    fn write_header(&self, buf: &mut [u8]) {
+        // Write the untagged header after room for the tag, and then move the addresses in front of it.
+        self.pkt.write_header(&mut buf[VLAN_TAG_SIZE..]);
+        buf.copy_within(VLAN_TAG_SIZE..VLAN_TAG_SIZE + VLAN_TAG_OFFSET, 0);
+        buf[VLAN_TAG_OFFSET..VLAN_TAG_OFFSET + 2].copy_from_slice(&TPID_8021Q.to_be_bytes());
+        buf[VLAN_TAG_OFFSET + 2..VLAN_TAG_OFFSET + 4].copy_from_slice(&self.vlan_id.to_be_bytes());
+    }
+
+    fn body_size(&self) -> usize {
+        self.pkt.body_size()
+    }
+
+    fn ipv4_dst_addr(&self) -> Option<Ipv4Addr> {
+        self.pkt.ipv4_dst_addr()
+    }
+
+    fn tx_priority(&self) -> u8 {
+        self.pkt.tx_priority()
+    }
+
+    fn take_body(&self) -> Option<DemiBuffer> {
+        self.pkt.take_body()
+    }
+}
+
+impl NetworkRuntime for VlanTransport {
+    fn transmit(&mut self, pkt: Box<dyn PacketBuf>) {
+        self.transport.transmit(Box::new(TaggedPacket::new(pkt, self.vlan_id)));
+    }
+
+    fn receive(&mut self) -> ArrayVec<DemiBuffer, RECEIVE_BATCH_SIZE> {
+        self.transport
+            .receive()
+            .into_iter()
+            .filter_map(|frame| self.untag(frame))
+            .collect()
+    }
+
+    fn register_flow(&mut self, local: SocketAddrV4) -> Result<(), Fail> {
+        self.transport.register_flow(local)
+    }
+
+    fn unregister_flow(&mut self, local: SocketAddrV4) -> Result<(), Fail> {
+        self.transport.unregister_flow(local)
+    }
+
+    fn get_ifindex(&self) -> u32 {
+        self.transport.get_ifindex()
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        VlanTransport,
+        VLAN_TAG_SIZE,
+    };
+    use crate::{
+        inetstack::{
+            protocols::{
+                ethernet2::{
+                    EtherType2,
+                    Ethernet2Header,
+                },
+                ip::IpProtocol,
+                ipv4::Ipv4Header,
+                tcp::{
+                    segment::{
+                        TcpHeader,
+                        TcpSegment,
+                    },
+                    SeqNumber,
+                },
+            },
+            test_helpers::{
+                self,
+                SharedTestRuntime,
+            },
+        },
+        runtime::{
+            memory::DemiBuffer,
+            network::{
+                config::{
+                    ArpConfig,
+                    TcpConfig,
+                    UdpConfig,
+                },
+                consts::RECEIVE_BATCH_SIZE,
+                NetworkRuntime,
+            },
+            SharedBox,
+        },
+    };
+    use ::anyhow::Result;
+    use ::arrayvec::ArrayVec;
+    use ::std::time::Instant;
+
+    /// VLAN identifier of the transport under test.
+    const VLAN_ID: u16 = 42;
+
+    /// Builds a VLAN transport with `vlan_id` on top of a test runtime, and returns both.
+    fn new_transport(vlan_id: u16) -> (VlanTransport, SharedTestRuntime) {
+        let test_rig: SharedTestRuntime = SharedTestRuntime::new(
+            Instant::now(),
+            ArpConfig::default(),
+            UdpConfig::default(),
+            TcpConfig::default(),
+            test_helpers::ALICE_MAC,
+            test_helpers::ALICE_IPV4,
+        );
+        let transport: VlanTransport = VlanTransport::new(
+            SharedBox::<dyn NetworkRuntime>::new(Box::new(test_rig.clone())),
+            vlan_id,
+        );
+        (transport, test_rig)
+    }
+
+    /// Builds a TCP segment from Alice to Bob.
+    fn new_segment() -> TcpSegment {
+        let mut tcp_hdr: TcpHeader = TcpHeader::new(49152, 80);
+        tcp_hdr.ack = true;
+        tcp_hdr.seq_num = SeqNumber::from(1);
+        tcp_hdr.ack_num = SeqNumber::from(1);
+        tcp_hdr.window_size = 1024;
+        TcpSegment {
+            ethernet2_hdr: Ethernet2Header::new(test_helpers::BOB_MAC, test_helpers::ALICE_MAC, EtherType2::Ipv4),
+            ipv4_hdr: Ipv4Header::new(test_helpers::ALICE_IPV4, test_helpers::BOB_IPV4, IpProtocol::TCP),
+            tcp_hdr,
+            data: None,
+            tx_checksum_offload: false,
+        }
+    }
+
+    /// Tests that transmitted frames are tagged, and that received frames with the same tag are untagged.
+    #[test]
+    fn test_vlan_round_trip() -> Result<()> {
+        let (mut transport, mut test_rig) = new_transport(VLAN_ID);
+        transport.transmit(Box::new(new_segment()));
+
+        let frame: DemiBuffer = test_rig.pop_frame();
+        crate::ensure_eq!(&frame[12..14], &0x8100u16.to_be_bytes()[..]);
+        crate::ensure_eq!(&frame[14..16], &VLAN_ID.to_be_bytes()[..]);
+        let tagged_len: usize = frame.len();
+
+        test_rig.push_frame(frame);
+        let mut frames: ArrayVec<DemiBuffer, RECEIVE_BATCH_SIZE> = transport.receive();
+        crate::ensure_eq!(frames.len(), 1);
+        let frame: DemiBuffer = frames.remove(0);
+        crate::ensure_eq!(frame.len(), tagged_len - VLAN_TAG_SIZE);
+        let (eth2_hdr, eth2_payload) = Ethernet2Header::parse(frame)?;
+        crate::ensure_eq!(eth2_hdr.dst_addr(), test_helpers::BOB_MAC);
+        crate::ensure_eq!(eth2_hdr.src_addr(), test_helpers::ALICE_MAC);
+        crate::ensure_eq!(eth2_hdr.ether_type(), EtherType2::Ipv4);
+        let (ipv4_hdr, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+        crate::ensure_eq!(ipv4_hdr.get_dest_addr(), test_helpers::BOB_IPV4);
+        let (tcp_hdr, _): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_hdr, ipv4_payload, false)?;
+        crate::ensure_eq!(tcp_hdr.dst_port, 80);
+
+        Ok(())
+    }
+
+    /// Tests that received frames that carry another VLAN identifier, or no tag at all, are dropped.
+    #[test]
+    fn test_vlan_mismatched_tag_dropped() -> Result<()> {
+        // Tag a frame with another VLAN identifier.
+        let (mut other_transport, mut other_test_rig) = new_transport(VLAN_ID + 1);
+        other_transport.transmit(Box::new(new_segment()));
+        let mismatched: DemiBuffer = other_test_rig.pop_frame();
+
+        // Build an untagged frame.
+        let (_, mut untagged_test_rig) = new_transport(VLAN_ID);
+        untagged_test_rig.transmit(Box::new(new_segment()));
+        let untagged: DemiBuffer = untagged_test_rig.pop_frame();
+
+        let (mut transport, mut test_rig) = new_transport(VLAN_ID);
+        test_rig.push_frame(mismatched);
+        crate::ensure_eq!(transport.receive().len(), 0);
+        test_rig.push_frame(untagged);
+        crate::ensure_eq!(transport.receive().len(), 0);
+
+        Ok(())
+    }
+}
@@ -12,6 +12,7 @@ use crate::{
         network::PacketBuf,
     },
 };
+use ::std::net::Ipv4Addr;
 
 /// Message for ICMP
 pub struct Icmpv4Message {
@@ -41,6 +42,10 @@ impl Icmpv4Message {
 
 /// PacketBuf Trait Implementation for Icmpv4Message
 impl PacketBuf for Icmpv4Message {
+    fn ipv4_dst_addr(&self) -> Option<Ipv4Addr> {
+        Some(self.ipv4_hdr.get_dest_addr())
+    }
+
     fn header_size(&self) -> usize {
         self.ethernet2_hdr.compute_size() + self.ipv4_hdr.compute_size() + self.icmpv4_hdr.size()
     }
@@ -5,7 +5,10 @@ mod header;
 mod message;
 mod protocol;
 
-pub use self::protocol::ICMPV4_ECHO_REQUEST_MESSAGE_SIZE;
+pub use self::protocol::{
+    ICMPV4_ECHO_REQUEST_MAX_DATA_SIZE,
+    ICMPV4_ECHO_REQUEST_MESSAGE_SIZE,
+};
 pub use header::Icmpv4Header;
 pub use message::Icmpv4Message;
 pub use protocol::Icmpv4Type2;
@@ -1,7 +1,11 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
-use crate::runtime::fail::Fail;
+use super::header::ICMPV4_HEADER_SIZE;
+use crate::{
+    inetstack::protocols::ipv4::IPV4_HEADER_MIN_SIZE,
+    runtime::fail::Fail,
+};
 use ::libc::EBADMSG;
 
 //==============================================================================
@@ -11,14 +15,20 @@ use ::libc::EBADMSG;
 /// Size of ICMPv4 Echo Request Message (in bytes)
 pub const ICMPV4_ECHO_REQUEST_MESSAGE_SIZE: u16 = 56;
 
+/// Maximum size of the data of an ICMPv4 Echo Request Message (in bytes), so that the message fits in a 1500-byte
+/// Ethernet MTU along with the IPv4 and ICMPv4 headers.
+pub const ICMPV4_ECHO_REQUEST_MAX_DATA_SIZE: usize = 1500 - IPV4_HEADER_MIN_SIZE as usize - ICMPV4_HEADER_SIZE;
+
 //==============================================================================
 // Icmpv4Type2
 //==============================================================================
 
+/// Type of an ICMP message. The next-hop MTU of Destination Unreachable messages is only set in Fragmentation Needed
+/// ones (i.e. with code 4), as per RFC 1191.
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum Icmpv4Type2 {
     EchoReply { id: u16, seq_num: u16 },
-    DestinationUnreachable,
+    DestinationUnreachable { next_hop_mtu: u16 },
     SourceQuench,
     RedirectMessage,
     EchoRequest { id: u16, seq_num: u16 },
@@ -39,7 +49,10 @@ impl Icmpv4Type2 {
                 let seq_num: u16 = u16::from_be_bytes([rest_of_header[2], rest_of_header[3]]);
                 Ok(EchoReply { id, seq_num })
             },
-            3 => Ok(DestinationUnreachable),
+            3 => {
+                let next_hop_mtu: u16 = u16::from_be_bytes([rest_of_header[2], rest_of_header[3]]);
+                Ok(DestinationUnreachable { next_hop_mtu })
+            },
             4 => Ok(SourceQuench),
             5 => Ok(RedirectMessage),
             8 => {
@@ -66,7 +79,10 @@ impl Icmpv4Type2 {
                 let [seq1, seq2] = seq_num.to_be_bytes();
                 (0, [id1, id2, seq1, seq2])
             },
-            DestinationUnreachable => (3, zero),
+            DestinationUnreachable { next_hop_mtu } => {
+                let [mtu1, mtu2] = next_hop_mtu.to_be_bytes();
+                (3, [0, 0, mtu1, mtu2])
+            },
             SourceQuench => (4, zero),
             RedirectMessage => (5, zero),
             EchoRequest { id, seq_num } => {
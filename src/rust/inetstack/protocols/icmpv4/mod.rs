@@ -8,3 +8,10 @@ mod peer;
 mod tests;
 
 pub use peer::SharedIcmpv4Peer;
+
+#[cfg(test)]
+pub use datagram::{
+    Icmpv4Header,
+    Icmpv4Message,
+    Icmpv4Type2,
+};
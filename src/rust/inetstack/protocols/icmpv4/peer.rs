@@ -19,7 +19,12 @@ use crate::{
             Icmpv4Type2,
         },
         ip::IpProtocol,
-        ipv4::Ipv4Header,
+        ipv4::{
+            Ipv4Header,
+            IPV4_HEADER_MIN_SIZE,
+        },
+        tcp::SharedTcpPeer,
+        udp::SharedUdpPeer,
     },
     runtime::{
         fail::Fail,
@@ -87,6 +92,12 @@ pub struct Icmpv4Peer {
     /// Underlying ARP Peer
     arp: SharedArpPeer,
 
+    /// UDP peer, to which errors for UDP datagrams are routed.
+    udp: SharedUdpPeer,
+
+    /// TCP peer, to which the next-hop MTUs learned from errors for TCP segments are routed.
+    tcp: SharedTcpPeer,
+
     /// Incoming packets
     recv_queue: AsyncQueue<(Ipv4Header, DemiBuffer)>,
 
@@ -99,8 +110,8 @@ pub struct Icmpv4Peer {
     /// Polling coroutine yielder
     yielder_handle: YielderHandle,
 
-    /// Inflight ping requests.
-    inflight: HashMap<(u16, u16), AsyncValue<Result<(), Fail>>>,
+    /// Inflight ping requests, which complete with the data of the matching echo reply.
+    inflight: HashMap<(u16, u16), AsyncValue<DemiBuffer>>,
 }
 
 #[derive(Clone)]
@@ -113,6 +124,8 @@ impl SharedIcmpv4Peer {
         local_link_addr: MacAddress,
         local_ipv4_addr: Ipv4Addr,
         arp: SharedArpPeer,
+        udp: SharedUdpPeer,
+        tcp: SharedTcpPeer,
         rng_seed: [u8; 32],
     ) -> Result<Self, Fail> {
         let rng: SmallRng = SmallRng::from_seed(rng_seed);
@@ -123,16 +136,30 @@ impl SharedIcmpv4Peer {
             local_link_addr,
             local_ipv4_addr,
             arp: arp.clone(),
+            udp,
+            tcp,
             recv_queue: AsyncQueue::<(Ipv4Header, DemiBuffer)>::default(),
             seq: Wrapping(0),
             rng,
             yielder_handle: yielder.get_handle(),
-            inflight: HashMap::<(u16, u16), AsyncValue<Result<(), Fail>>>::new(),
+            inflight: HashMap::<(u16, u16), AsyncValue<DemiBuffer>>::new(),
         }));
         runtime.insert_background_coroutine("Inetstack::ICMP::background", Box::pin(peer.clone().poll(yielder)))?;
         Ok(peer)
     }
 
+    /// Changes the addresses that the target peer sends echo requests and replies from.
+    pub fn set_local_addrs(&mut self, local_link_addr: MacAddress, local_ipv4_addr: Ipv4Addr) {
+        self.local_link_addr = local_link_addr;
+        self.local_ipv4_addr = local_ipv4_addr;
+    }
+
+    /// Replaces the random number generator of the target peer, which picks the identifiers of echo requests, with
+    /// one seeded by `rng_seed`.
+    pub fn reseed(&mut self, rng_seed: [u8; 32]) {
+        self.rng = SmallRng::from_seed(rng_seed);
+    }
+
     /// Background task for replying to ICMP messages.
     async fn poll(mut self, yielder: Yielder) {
         loop {
@@ -154,10 +181,14 @@ impl SharedIcmpv4Peer {
                 Icmpv4Type2::EchoReply { id, seq_num } => {
                     if let Some(result) = self.inflight.get_mut(&(id, seq_num)) {
                         trace!("Received reply to ping");
-                        result.set(Ok(()));
+                        result.set(data);
                     }
                     continue;
                 },
+                Icmpv4Type2::DestinationUnreachable { .. } | Icmpv4Type2::TimeExceeded => {
+                    self.receive_error(&icmpv4_hdr, data);
+                    continue;
+                },
                 _ => {
                     warn!("Unsupported ICMPv4 message: {:?}", icmpv4_hdr);
                     continue;
@@ -185,6 +216,64 @@ impl SharedIcmpv4Peer {
         }
     }
 
+    /// Routes an ICMP error to the peer of the protocol of the datagram that triggered it. As per RFC 792, `data`
+    /// holds the IPv4 header of that datagram, followed by at least the first 8 bytes of its payload.
+    fn receive_error(&mut self, icmpv4_hdr: &Icmpv4Header, mut data: DemiBuffer) {
+        // The embedded datagram is truncated, so its header cannot be fully parsed and validated.
+        let header_size: usize = data.first().map_or(0, |byte| ((byte & 0x0f) as usize) * 4);
+        if header_size < IPV4_HEADER_MIN_SIZE as usize || data.len() < header_size {
+            warn!("receive_error(): dropping ICMP error with a malformed embedded datagram");
+            return;
+        }
+        let protocol: u8 = data[9];
+        let src_addr: Ipv4Addr = Ipv4Addr::new(data[12], data[13], data[14], data[15]);
+        let dst_addr: Ipv4Addr = Ipv4Addr::new(data[16], data[17], data[18], data[19]);
+        // Only this host should have sent the datagram.
+        if src_addr != self.local_ipv4_addr && !src_addr.is_loopback() {
+            warn!(
+                "receive_error(): dropping ICMP error for a datagram from another host: {:?}",
+                src_addr
+            );
+            return;
+        }
+        if let Err(e) = data.adjust(header_size) {
+            warn!("receive_error(): {:?}", e);
+            return;
+        }
+        match (protocol, icmpv4_hdr.get_protocol(), icmpv4_hdr.get_code()) {
+            (protocol, _, _) if protocol == IpProtocol::UDP as u8 => {
+                let errno: libc::c_int = Self::error_to_errno(icmpv4_hdr);
+                let cause: String = format!(
+                    "ICMP error (type={:?}, code={:?})",
+                    icmpv4_hdr.get_protocol(),
+                    icmpv4_hdr.get_code()
+                );
+                self.udp
+                    .receive_icmp_error(src_addr, dst_addr, data, Fail::new(errno, &cause));
+            },
+            // TCP recovers from other errors on its own, through retransmissions, so it is only told about smaller
+            // path MTUs (RFC 1191). Routers that predate RFC 1191 leave the next-hop MTU unset, so nothing is learned.
+            (protocol, Icmpv4Type2::DestinationUnreachable { next_hop_mtu }, 4)
+                if protocol == IpProtocol::TCP as u8 && next_hop_mtu != 0 =>
+            {
+                self.tcp
+                    .receive_path_mtu(src_addr, dst_addr, data, next_hop_mtu as usize);
+            },
+            _ => debug!("receive_error(): ignoring ICMP error (protocol={:?})", protocol),
+        }
+    }
+
+    /// Maps an ICMP error to the error number that POSIX sockets report for it.
+    fn error_to_errno(icmpv4_hdr: &Icmpv4Header) -> libc::c_int {
+        match (icmpv4_hdr.get_protocol(), icmpv4_hdr.get_code()) {
+            (Icmpv4Type2::DestinationUnreachable { .. }, 0) => libc::ENETUNREACH,
+            (Icmpv4Type2::DestinationUnreachable { .. }, 2) => libc::ENOPROTOOPT,
+            (Icmpv4Type2::DestinationUnreachable { .. }, 3) => libc::ECONNREFUSED,
+            (Icmpv4Type2::DestinationUnreachable { .. }, 4) => libc::EMSGSIZE,
+            _ => libc::EHOSTUNREACH,
+        }
+    }
+
     /// Parses and handles a ICMP message.
     pub fn receive(&mut self, ipv4_hdr: Ipv4Header, buf: DemiBuffer) {
         self.recv_queue.push((ipv4_hdr, buf));
@@ -220,9 +309,43 @@ impl SharedIcmpv4Peer {
 
     /// Sends a ping to a remote peer.
     pub async fn ping(&mut self, dst_ipv4_addr: Ipv4Addr, timeout: Option<Duration>) -> Result<Duration, Fail> {
+        let (latency, _): (Duration, DemiBuffer) = self.ping_with_payload(dst_ipv4_addr, None, None, timeout).await?;
+        Ok(latency)
+    }
+
+    /// Sends a ping carrying `payload` to a remote peer, and returns the latency along with the data of the matching
+    /// echo reply. If no payload is given, a zeroed one of [datagram::ICMPV4_ECHO_REQUEST_MESSAGE_SIZE] bytes is
+    /// sent. If no identifier and sequence number are given, they are generated by the target peer.
+    pub async fn ping_with_payload(
+        &mut self,
+        dst_ipv4_addr: Ipv4Addr,
+        payload: Option<DemiBuffer>,
+        id_seq_num: Option<(u16, u16)>,
+        timeout: Option<Duration>,
+    ) -> Result<(Duration, DemiBuffer), Fail> {
         let timeout: Duration = timeout.unwrap_or_else(|| Duration::from_millis(5000));
-        let id: u16 = self.make_id();
-        let seq_num: u16 = self.make_seq_num();
+        let data: DemiBuffer = match payload {
+            Some(payload) if payload.len() > datagram::ICMPV4_ECHO_REQUEST_MAX_DATA_SIZE => {
+                let cause: String = format!(
+                    "payload is too large (len={:?}, max={:?})",
+                    payload.len(),
+                    datagram::ICMPV4_ECHO_REQUEST_MAX_DATA_SIZE
+                );
+                error!("ping(): {}", cause);
+                return Err(Fail::new(libc::EMSGSIZE, &cause));
+            },
+            Some(payload) => payload,
+            None => DemiBuffer::new(datagram::ICMPV4_ECHO_REQUEST_MESSAGE_SIZE),
+        };
+        let (id, seq_num): (u16, u16) = match id_seq_num {
+            Some(id_seq_num) => id_seq_num,
+            None => (self.make_id(), self.make_seq_num()),
+        };
+        if self.inflight.contains_key(&(id, seq_num)) {
+            let cause: String = format!("ping is already in flight (id={:?}, seq_num={:?})", id, seq_num);
+            error!("ping(): {}", cause);
+            return Err(Fail::new(libc::EEXIST, &cause));
+        }
         let echo_request: Icmpv4Type2 = Icmpv4Type2::EchoRequest { id, seq_num };
 
         let t0: Instant = self.runtime.get_now();
@@ -230,8 +353,6 @@ impl SharedIcmpv4Peer {
         let dst_link_addr: MacAddress = self.arp.query(dst_ipv4_addr, &Yielder::new()).await?;
         debug!("ARP query complete ({} -> {})", dst_ipv4_addr, dst_link_addr);
 
-        let data: DemiBuffer = DemiBuffer::new(datagram::ICMPV4_ECHO_REQUEST_MESSAGE_SIZE);
-
         let msg: Icmpv4Message = Icmpv4Message::new(
             Ethernet2Header::new(dst_link_addr, self.local_link_addr, EtherType2::Ipv4),
             Ipv4Header::new(self.local_ipv4_addr, dst_ipv4_addr, IpProtocol::ICMPv4),
@@ -250,18 +371,20 @@ impl SharedIcmpv4Peer {
             .get(Yielder::new())
             .fuse();
         pin_mut!(result);
-        match result.with_timeout(timeout).await? {
+        let result: Result<Result<DemiBuffer, Fail>, Fail> = result.with_timeout(timeout).await;
+        self.inflight.remove(&(id, seq_num));
+        match result {
             // Request completed successfully.
-            Ok(_) => {
-                self.inflight.remove(&(id, seq_num));
-                Ok(self.runtime.get_now() - t0)
+            Ok(Ok(reply)) => Ok((self.runtime.get_now() - t0, reply)),
+            // Request failed.
+            Ok(Err(e)) => {
+                error!("ping(): {:?}", e);
+                Err(e)
             },
             // Request expired.
-            Err(_) => {
-                let message: String = format!("timer expired");
-                self.inflight.remove(&(id, seq_num));
-                error!("ping(): {}", message);
-                Err(Fail::new(libc::ETIMEDOUT, &message))
+            Err(e) => {
+                error!("ping(): {:?}", e);
+                Err(e)
             },
         }
     }
@@ -1,9 +1,37 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
-use crate::inetstack::test_helpers::{
-    self,
-    SharedEngine,
+use super::datagram::{
+    Icmpv4Header,
+    Icmpv4Message,
+    Icmpv4Type2,
+    ICMPV4_ECHO_REQUEST_MAX_DATA_SIZE,
+};
+use crate::{
+    inetstack::{
+        protocols::{
+            ethernet2::{
+                EtherType2,
+                Ethernet2Header,
+            },
+            ip::IpProtocol,
+            ipv4::{
+                Ipv4Header,
+                IPV4_HEADER_MIN_SIZE,
+            },
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        network::PacketBuf,
+        Operation,
+        OperationResult,
+        QDesc,
+    },
 };
 use ::anyhow::Result;
 use ::futures::task::{
@@ -12,6 +40,7 @@ use ::futures::task::{
 };
 use ::std::{
     future::Future,
+    net::SocketAddrV4,
     pin::Pin,
     task::Poll,
     time::{
@@ -111,3 +140,172 @@ fn ipv4_ping_loop() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn ipv4_ping_with_payload() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    let mut alice: SharedEngine = test_helpers::new_alice2(now);
+
+    let mut bob: SharedEngine = test_helpers::new_bob2(now);
+
+    // Alice pings Bob with a custom payload, identifier and sequence number.
+    let payload: Vec<u8> = (0..200).map(|i| i as u8).collect();
+    let mut alice2 = alice.clone();
+    let mut ping_fut = Box::pin(alice2.ipv4_ping_with_payload(
+        test_helpers::BOB_IPV4,
+        Some(DemiBuffer::from_slice(&payload)?),
+        Some((0x1234, 7)),
+        None,
+    ));
+    match Future::poll(Pin::new(&mut ping_fut), &mut ctx) {
+        Poll::Pending => {},
+        _ => anyhow::bail!("Ping should not complete"),
+    };
+
+    // The echo request should carry the identifier, sequence number and payload.
+    let request: DemiBuffer = alice.get_test_rig().pop_frame();
+    let (_, eth2_payload) = Ethernet2Header::parse(request.clone())?;
+    let (_, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+    let (icmpv4_hdr, data): (Icmpv4Header, DemiBuffer) = Icmpv4Header::parse(ipv4_payload)?;
+    match icmpv4_hdr.get_protocol() {
+        Icmpv4Type2::EchoRequest { id, seq_num } => {
+            crate::ensure_eq!(id, 0x1234);
+            crate::ensure_eq!(seq_num, 7);
+        },
+        protocol => anyhow::bail!("unexpected ICMPv4 message: {:?}", protocol),
+    };
+    crate::ensure_eq!(&data[..], &payload[..]);
+
+    now += Duration::from_secs(1);
+    alice.advance_clock(now);
+    bob.advance_clock(now);
+
+    // Bob receives ping request from Alice and replies.
+    bob.receive(request)?;
+    bob.get_test_rig().poll_scheduler();
+
+    // Alice receives reply from Bob.
+    alice.receive(bob.get_test_rig().pop_frame())?;
+    alice.get_test_rig().poll_scheduler();
+    let (latency, reply): (Duration, DemiBuffer) = match Future::poll(Pin::new(&mut ping_fut), &mut ctx) {
+        Poll::Ready(Ok(result)) => result,
+        _ => anyhow::bail!("Ping should have completed"),
+    };
+    crate::ensure_eq!(latency, Duration::from_secs(1));
+    crate::ensure_eq!(&reply[..], &payload[..]);
+
+    Ok(())
+}
+
+#[test]
+fn ipv4_ping_with_payload_too_large() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let now = Instant::now();
+
+    let mut alice: SharedEngine = test_helpers::new_alice2(now);
+
+    let payload: DemiBuffer = DemiBuffer::from_slice(&vec![0; ICMPV4_ECHO_REQUEST_MAX_DATA_SIZE + 1])?;
+    let mut alice2 = alice.clone();
+    let mut ping_fut = Box::pin(alice2.ipv4_ping_with_payload(test_helpers::BOB_IPV4, Some(payload), None, None));
+    match Future::poll(Pin::new(&mut ping_fut), &mut ctx) {
+        Poll::Ready(Err(e)) if e.errno == libc::EMSGSIZE => {},
+        _ => anyhow::bail!("Ping should have failed"),
+    };
+    crate::ensure_eq!(alice.get_test_rig().pop_all_frames().len(), 0);
+
+    Ok(())
+}
+
+//==============================================================================
+// ICMP Errors
+//==============================================================================
+
+/// Has Alice send a datagram from `alice_fd` to an unreachable port of Bob, and delivers to Alice the ICMP Port
+/// Unreachable message that Bob would reply with.
+fn send_to_unreachable_port(alice: &mut SharedEngine, alice_fd: QDesc, bob_addr: SocketAddrV4) -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let buf: DemiBuffer = DemiBuffer::from_slice(&[0x5a; 32])?;
+    let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf, bob_addr)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Push(_))) => {},
+        _ => anyhow::bail!("Push should have completed"),
+    };
+
+    // The message embeds the IPv4 header of the datagram and the first 8 bytes of its payload.
+    let (_, mut embedded): (Ethernet2Header, DemiBuffer) = Ethernet2Header::parse(alice.get_test_rig().pop_frame())?;
+    embedded.trim(embedded.len() - (IPV4_HEADER_MIN_SIZE as usize + 8))?;
+    let msg: Icmpv4Message = Icmpv4Message::new(
+        Ethernet2Header::new(test_helpers::ALICE_MAC, test_helpers::BOB_MAC, EtherType2::Ipv4),
+        Ipv4Header::new(test_helpers::BOB_IPV4, test_helpers::ALICE_IPV4, IpProtocol::ICMPv4),
+        Icmpv4Header::new(Icmpv4Type2::DestinationUnreachable { next_hop_mtu: 0 }, 3),
+        embedded,
+    );
+    let header_size: usize = msg.header_size();
+    let mut frame: DemiBuffer = DemiBuffer::new((header_size + msg.body_size()) as u16);
+    msg.write_header(&mut frame[..header_size]);
+    if let Some(body) = msg.take_body() {
+        frame[header_size..].copy_from_slice(&body[..]);
+    }
+    alice.receive(frame)?;
+    alice.get_test_rig().poll_scheduler();
+
+    Ok(())
+}
+
+/// Pushes a datagram from `alice_fd` to `bob_addr`, and returns the result of the operation.
+fn pushto(alice: &mut SharedEngine, alice_fd: QDesc, bob_addr: SocketAddrV4) -> Result<OperationResult> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let buf: DemiBuffer = DemiBuffer::from_slice(&[0x5a; 32])?;
+    let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf, bob_addr)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, result)) => Ok(result),
+        Poll::Pending => anyhow::bail!("Push should have completed"),
+    }
+}
+
+/// Tests that a connected UDP socket fails its next operation with ECONNREFUSED after an ICMP Port Unreachable, and
+/// that the error is only reported once.
+#[test]
+fn udp_connected_port_unreachable() -> Result<()> {
+    let now = Instant::now();
+    let mut alice: SharedEngine = test_helpers::new_alice2(now);
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 9000);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_connect(alice_fd, bob_addr)?;
+
+    send_to_unreachable_port(&mut alice, alice_fd, bob_addr)?;
+    crate::ensure_eq!(alice.udp_readiness(alice_fd)?.error, true);
+
+    match pushto(&mut alice, alice_fd, bob_addr)? {
+        OperationResult::Failed(e) if e.errno == libc::ECONNREFUSED => {},
+        result => anyhow::bail!("Push should have failed with ECONNREFUSED: {:?}", result),
+    };
+    crate::ensure_eq!(alice.udp_readiness(alice_fd)?.error, false);
+    match pushto(&mut alice, alice_fd, bob_addr)? {
+        OperationResult::Push(_) => {},
+        result => anyhow::bail!("Push should have succeeded: {:?}", result),
+    };
+
+    Ok(())
+}
+
+/// Tests that a UDP socket that is not connected ignores ICMP errors, as POSIX does.
+#[test]
+fn udp_unconnected_port_unreachable() -> Result<()> {
+    let now = Instant::now();
+    let mut alice: SharedEngine = test_helpers::new_alice2(now);
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 9000);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, SocketAddrV4::new(test_helpers::ALICE_IPV4, 8000))?;
+
+    send_to_unreachable_port(&mut alice, alice_fd, bob_addr)?;
+    crate::ensure_eq!(alice.udp_readiness(alice_fd)?.error, false);
+    match pushto(&mut alice, alice_fd, bob_addr)? {
+        OperationResult::Push(_) => {},
+        result => anyhow::bail!("Push should have succeeded: {:?}", result),
+    };
+
+    Ok(())
+}
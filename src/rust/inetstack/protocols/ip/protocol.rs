@@ -22,6 +22,10 @@ pub enum IpProtocol {
     TCP = 0x06,
     /// User Datagram Protocol
     UDP = 0x11,
+    /// Reserved for Experimentation and Testing (RFC 3692)
+    Experimental1 = 0xfd,
+    /// Reserved for Experimentation and Testing (RFC 3692)
+    Experimental2 = 0xfe,
 }
 
 //======================================================================================================================
@@ -37,6 +41,8 @@ impl TryFrom<u8> for IpProtocol {
             0x01 => Ok(IpProtocol::ICMPv4),
             0x06 => Ok(IpProtocol::TCP),
             0x11 => Ok(IpProtocol::UDP),
+            0xfd => Ok(IpProtocol::Experimental1),
+            0xfe => Ok(IpProtocol::Experimental2),
             _ => Err(Fail::new(libc::ENOTSUP, "unsupported IPv4 protocol")),
         }
     }
@@ -6,21 +6,25 @@
 //==============================================================================
 
 use crate::{
-    inetstack::protocols::ip::IpProtocol,
+    inetstack::protocols::{
+        ip::IpProtocol,
+        ipv4::options::{
+            Ipv4Option,
+            IPV4_OPTIONS_MAX_SIZE,
+        },
+    },
     runtime::{
         fail::Fail,
         memory::DemiBuffer,
     },
 };
+use ::arrayvec::ArrayVec;
 use ::libc::{
     EBADMSG,
     ENOTSUP,
 };
 use ::std::{
-    convert::{
-        TryFrom,
-        TryInto,
-    },
+    convert::TryFrom,
     net::Ipv4Addr,
 };
 
@@ -82,7 +86,7 @@ pub struct Ipv4Header {
     /// Time to Live indicates the maximum remaining time the datagram is allowed to be in the network (8 bits).
     ttl: u8,
     /// Protocol used in the data portion of the datagram (8 bits).
-    protocol: IpProtocol,
+    protocol: u8,
     /// Header-only checksum for error detection (16 bits).
     #[allow(unused)]
     header_checksum: u16,
@@ -90,6 +94,8 @@ pub struct Ipv4Header {
     src_addr: Ipv4Addr,
     /// Destination IP address (32 bits).
     dst_addr: Ipv4Addr,
+    /// Options, which take up the header beyond its minimum size (0 to 320 bits).
+    options: [u8; IPV4_OPTIONS_MAX_SIZE],
 }
 
 //==============================================================================
@@ -100,6 +106,12 @@ pub struct Ipv4Header {
 impl Ipv4Header {
     /// Instantiates an empty IPv4 header.
     pub fn new(src_addr: Ipv4Addr, dst_addr: Ipv4Addr, protocol: IpProtocol) -> Self {
+        Self::new_with_protocol_number(src_addr, dst_addr, protocol as u8)
+    }
+
+    /// Instantiates an empty IPv4 header for an arbitrary protocol number, including protocols that are not built into
+    /// the stack.
+    pub fn new_with_protocol_number(src_addr: Ipv4Addr, dst_addr: Ipv4Addr, protocol: u8) -> Self {
         Self {
             version: IPV4_VERSION,
             ihl: IPV4_IHL_NO_OPTIONS,
@@ -114,6 +126,7 @@ impl Ipv4Header {
             header_checksum: 0,
             src_addr,
             dst_addr,
+            options: [0; IPV4_OPTIONS_MAX_SIZE],
         }
     }
 
@@ -208,8 +221,8 @@ impl Ipv4Header {
             return Err(Fail::new(EBADMSG, "ipv4 datagram too old"));
         }
 
-        // Protocol.
-        let protocol: IpProtocol = IpProtocol::try_from(hdr_buf[9])?;
+        // Protocol. This is not checked here, as protocols may be handled outside the stack.
+        let protocol: u8 = hdr_buf[9];
 
         // Header checksum.
         let header_checksum: u16 = u16::from_be_bytes([hdr_buf[10], hdr_buf[11]]);
@@ -226,6 +239,14 @@ impl Ipv4Header {
         // Destination address.
         let dst_addr: Ipv4Addr = Ipv4Addr::new(hdr_buf[16], hdr_buf[17], hdr_buf[18], hdr_buf[19]);
 
+        // Options. These are checked here, so that malformed ones never reach upper layers.
+        let options_buf: &[u8] = &hdr_buf[(IPV4_HEADER_MIN_SIZE as usize)..];
+        if !options_buf.is_empty() {
+            Ipv4Option::parse_all(options_buf)?;
+        }
+        let mut options: [u8; IPV4_OPTIONS_MAX_SIZE] = [0; IPV4_OPTIONS_MAX_SIZE];
+        options[..options_buf.len()].copy_from_slice(options_buf);
+
         // Truncate datagram.
         let padding_bytes: usize = buf.len() - (total_length as usize);
         buf.adjust(hdr_size as usize)?;
@@ -245,16 +266,20 @@ impl Ipv4Header {
             header_checksum,
             src_addr,
             dst_addr,
+            options,
         };
 
         Ok((header, buf))
     }
 
-    /// Serializes the target IPv4 header.
+    /// Serializes the target IPv4 header, including its options.
     pub fn serialize(&self, buf: &mut [u8], payload_len: usize) {
-        let buf: &mut [u8; IPV4_HEADER_MIN_SIZE as usize] = buf
-            .try_into()
-            .expect("buffer should be large enough to hold an IPv4 header");
+        let hdr_size: usize = self.compute_size();
+        assert_eq!(
+            buf.len(),
+            hdr_size,
+            "buffer should be large enough to hold an IPv4 header"
+        );
 
         // Version + IHL.
         buf[0] = (self.version << 4) | self.ihl;
@@ -263,7 +288,7 @@ impl Ipv4Header {
         buf[1] = (self.dscp << 2) | (self.ecn & 3);
 
         // Total Length.
-        buf[2..4].copy_from_slice(&(IPV4_HEADER_MIN_SIZE + (payload_len as u16)).to_be_bytes());
+        buf[2..4].copy_from_slice(&((hdr_size + payload_len) as u16).to_be_bytes());
 
         // Identification.
         buf[4..6].copy_from_slice(&self.identification.to_be_bytes());
@@ -275,7 +300,7 @@ impl Ipv4Header {
         buf[8] = self.ttl;
 
         // Protocol.
-        buf[9] = self.protocol as u8;
+        buf[9] = self.protocol;
 
         // Skip the checksum (bytes 10..12) until we finish writing the header.
 
@@ -285,6 +310,9 @@ impl Ipv4Header {
         // Destination Address.
         buf[16..20].copy_from_slice(&self.dst_addr.octets());
 
+        // Options.
+        buf[(IPV4_HEADER_MIN_SIZE as usize)..].copy_from_slice(&self.options[..self.options_size()]);
+
         // Header Checksum.
         let checksum: u16 = Self::compute_checksum(buf);
         buf[10..12].copy_from_slice(&checksum.to_be_bytes());
@@ -300,12 +328,39 @@ impl Ipv4Header {
         self.dst_addr
     }
 
-    /// Returns the protocol field stored in the target IPv4 header.
-    pub fn get_protocol(&self) -> IpProtocol {
+    /// Sets the source address field stored in the target IPv4 header. Checksums of upper layer protocols that cover
+    /// the address are not updated.
+    pub fn set_src_addr(&mut self, src_addr: Ipv4Addr) {
+        self.src_addr = src_addr;
+    }
+
+    /// Sets the destination address field stored in the target IPv4 header. Checksums of upper layer protocols that
+    /// cover the address are not updated.
+    pub fn set_dest_addr(&mut self, dst_addr: Ipv4Addr) {
+        self.dst_addr = dst_addr;
+    }
+
+    /// Returns the protocol field stored in the target IPv4 header, if the stack supports it.
+    pub fn get_protocol(&self) -> Result<IpProtocol, Fail> {
+        IpProtocol::try_from(self.protocol)
+    }
+
+    /// Returns the protocol number stored in the target IPv4 header.
+    pub fn get_protocol_number(&self) -> u8 {
         self.protocol
     }
 
-    /// Computes the checksum of the target IPv4 header.
+    /// Returns the options stored in the target IPv4 header.
+    pub fn get_options(&self) -> ArrayVec<Ipv4Option, IPV4_OPTIONS_MAX_SIZE> {
+        Ipv4Option::parse_all(&self.options[..self.options_size()]).expect("options should have been checked")
+    }
+
+    /// Returns the size of the options stored in the target IPv4 header.
+    fn options_size(&self) -> usize {
+        self.compute_size() - (IPV4_HEADER_MIN_SIZE as usize)
+    }
+
+    /// Computes the checksum of the IPv4 header in `buf`, including its options.
     pub fn compute_checksum(buf: &[u8]) -> u16 {
         let mut state: u32 = 0xffff;
 
@@ -321,7 +376,7 @@ impl Ipv4Header {
         }
         // Skip the 5th u16 since octets 10-12 are the header checksum, whose value should be zero when
         // computing a checksum.
-        for i in 6..10 {
+        for i in 6..(buf.len() / 2) {
             state += u16::from_be_bytes([buf[2 * i], buf[2 * i + 1]]) as u32;
         }
         while state > 0xffff {
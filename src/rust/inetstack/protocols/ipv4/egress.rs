@@ -0,0 +1,246 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::protocols::{
+        ethernet2::Ethernet2Header,
+        ipv4::Ipv4Header,
+    },
+    runtime::{
+        fail::Fail,
+        memory::DemiBuffer,
+        network::{
+            consts::RECEIVE_BATCH_SIZE,
+            NetworkRuntime,
+            PacketBuf,
+        },
+        SharedBox,
+        SharedDemiRuntime,
+        SharedObject,
+    },
+};
+use ::arrayvec::ArrayVec;
+use ::std::{
+    collections::VecDeque,
+    net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    },
+    ops::{
+        Deref,
+        DerefMut,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Fate of an outgoing IPv4 packet, as decided by an egress hook.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ipv4EgressVerdict {
+    /// Transmits the packet, with any changes made by the hook.
+    Pass,
+    /// Drops the packet.
+    Drop,
+    /// Transmits the packet, with any changes made by the hook, once the given duration has elapsed. Delayed packets
+    /// are transmitted in the order in which they become due.
+    Delay(Duration),
+}
+
+/// Callback that is invoked for every outgoing IPv4 packet, with its header and payload. The hook may modify both,
+/// but it is responsible for fixing the checksums of upper layer protocols that cover what it changes (e.g. the
+/// addresses, which are part of the TCP and UDP pseudo-headers).
+pub type Ipv4EgressHook = Box<dyn FnMut(&mut Ipv4Header, &mut DemiBuffer) -> Ipv4EgressVerdict>;
+
+/// Outgoing IPv4 packet that was rebuilt after being handed to an egress hook.
+pub struct Ipv4EgressPacket {
+    ethernet2_hdr: Ethernet2Header,
+    ipv4_hdr: Ipv4Header,
+    payload: DemiBuffer,
+    tx_priority: u8,
+}
+
+/// Network runtime that hands every outgoing IPv4 packet to an egress hook, if one is installed, before it reaches
+/// the underlying transport. This is meant for network functions, such as NAT, and for fault injection in tests. When
+/// no hook is installed, packets are passed through untouched and without being serialized.
+pub struct EgressTransport {
+    runtime: SharedDemiRuntime,
+    transport: SharedBox<dyn NetworkRuntime>,
+    hook: Option<Ipv4EgressHook>,
+    /// Packets that were delayed by the hook, along with the time at which they are due.
+    delayed: VecDeque<(Instant, Ipv4EgressPacket)>,
+}
+
+#[derive(Clone)]
+pub struct SharedEgressTransport(SharedObject<EgressTransport>);
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl SharedEgressTransport {
+    pub fn new(runtime: SharedDemiRuntime, transport: SharedBox<dyn NetworkRuntime>) -> Self {
+        Self(SharedObject::<EgressTransport>::new(EgressTransport {
+            runtime,
+            transport,
+            hook: None,
+            delayed: VecDeque::new(),
+        }))
+    }
+
+    /// Installs `hook`, replacing any previously installed one. Passing `None` removes the hook. Packets that were
+    /// delayed by a previous hook are still transmitted when due.
+    pub fn set_hook(&mut self, hook: Option<Ipv4EgressHook>) {
+        self.hook = hook;
+    }
+
+    /// Serializes `pkt` and hands it to the installed hook. Returns the rebuilt packet, along with the verdict of the
+    /// hook, or `None` if the packet could not be parsed back.
+    fn filter(&mut self, pkt: Box<dyn PacketBuf>) -> Option<(Ipv4EgressVerdict, Ipv4EgressPacket)> {
+        let header_size: usize = pkt.header_size();
+        let tx_priority: u8 = pkt.tx_priority();
+        let frame_size: usize = header_size + pkt.body_size();
+        if frame_size > u16::MAX as usize {
+            let cause: String = format!("frame is too large (frame_size={:?})", frame_size);
+            warn!("transmit(): dropping frame: {}", cause);
+            return None;
+        }
+        let mut frame: DemiBuffer = DemiBuffer::new(frame_size as u16);
+        pkt.write_header(&mut frame[..header_size]);
+        if let Some(body) = pkt.take_body() {
+            frame[header_size..].copy_from_slice(&body[..]);
+        }
+
+        let (ethernet2_hdr, ipv4_packet): (Ethernet2Header, DemiBuffer) = match Ethernet2Header::parse(frame) {
+            Ok(result) => result,
+            Err(e) => {
+                warn!("transmit(): dropping frame: {:?}", e);
+                return None;
+            },
+        };
+        let (mut ipv4_hdr, mut payload): (Ipv4Header, DemiBuffer) = match Ipv4Header::parse(ipv4_packet) {
+            Ok(result) => result,
+            Err(e) => {
+                warn!("transmit(): dropping packet: {:?}", e);
+                return None;
+            },
+        };
+        let hook: &mut Ipv4EgressHook = self.hook.as_mut().expect("hook should be installed");
+        let verdict: Ipv4EgressVerdict = hook(&mut ipv4_hdr, &mut payload);
+        Some((
+            verdict,
+            Ipv4EgressPacket {
+                ethernet2_hdr,
+                ipv4_hdr,
+                payload,
+                tx_priority,
+            },
+        ))
+    }
+
+    /// Transmits the delayed packets that are due.
+    fn transmit_due(&mut self) {
+        if self.delayed.is_empty() {
+            return;
+        }
+        let now: Instant = self.runtime.get_now();
+        while self.delayed.front().map_or(false, |(deadline, _)| *deadline <= now) {
+            let (_, pkt): (Instant, Ipv4EgressPacket) = self.delayed.pop_front().expect("queue should not be empty");
+            self.transport.transmit(Box::new(pkt));
+        }
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl PacketBuf for Ipv4EgressPacket {
+    fn header_size(&self) -> usize {
+        self.ethernet2_hdr.compute_size() + self.ipv4_hdr.compute_size()
+    }
+
+    fn write_header(&self, buf: &mut [u8]) {
+        let eth_hdr_size: usize = self.ethernet2_hdr.compute_size();
+        self.ethernet2_hdr.serialize(&mut buf[..eth_hdr_size]);
+        self.ipv4_hdr.serialize(&mut buf[eth_hdr_size..], self.payload.len());
+    }
+
+    fn body_size(&self) -> usize {
+        self.payload.len()
+    }
+
+    fn take_body(&self) -> Option<DemiBuffer> {
+        Some(self.payload.clone())
+    }
+
+    fn ipv4_dst_addr(&self) -> Option<Ipv4Addr> {
+        Some(self.ipv4_hdr.get_dest_addr())
+    }
+
+    fn tx_priority(&self) -> u8 {
+        self.tx_priority
+    }
+}
+
+impl NetworkRuntime for SharedEgressTransport {
+    fn transmit(&mut self, pkt: Box<dyn PacketBuf>) {
+        // Frames that do not carry IPv4 packets, such as ARP ones, are not handed to the hook.
+        if self.hook.is_none() || pkt.ipv4_dst_addr().is_none() {
+            self.transport.transmit(pkt);
+            return;
+        }
+        self.transmit_due();
+        match self.filter(pkt) {
+            Some((Ipv4EgressVerdict::Pass, pkt)) => self.transport.transmit(Box::new(pkt)),
+            Some((Ipv4EgressVerdict::Drop, _)) | None => (),
+            Some((Ipv4EgressVerdict::Delay(delay), pkt)) => {
+                let deadline: Instant = self.runtime.get_now() + delay;
+                // Keep the queue sorted by deadline, and packets with the same deadline in order.
+                let index: usize = self.delayed.partition_point(|(other, _)| *other <= deadline);
+                self.delayed.insert(index, (deadline, pkt));
+            },
+        }
+    }
+
+    fn receive(&mut self) -> ArrayVec<DemiBuffer, RECEIVE_BATCH_SIZE> {
+        // The stack polls for received frames continuously, so this is where delayed packets are released.
+        self.transmit_due();
+        self.transport.receive()
+    }
+
+    fn register_flow(&mut self, local: SocketAddrV4) -> Result<(), Fail> {
+        self.transport.register_flow(local)
+    }
+
+    fn unregister_flow(&mut self, local: SocketAddrV4) -> Result<(), Fail> {
+        self.transport.unregister_flow(local)
+    }
+
+    fn get_ifindex(&self) -> u32 {
+        self.transport.get_ifindex()
+    }
+}
+
+impl Deref for SharedEgressTransport {
+    type Target = EgressTransport;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.deref()
+    }
+}
+
+impl DerefMut for SharedEgressTransport {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0.deref_mut()
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::runtime::{
+    memory::DemiBuffer,
+    SharedDemiRuntime,
+};
+use ::std::{
+    collections::VecDeque,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Fate of an incoming IPv4 packet, as decided by an ingress hook.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ipv4IngressVerdict {
+    /// Hands the packet, with any changes made by the hook, to the stack.
+    Pass,
+    /// Drops the packet.
+    Drop,
+    /// Hands the packet, with any changes made by the hook, to the stack once the given duration has elapsed. Delayed
+    /// packets are handed over in the order in which they become due, after the packet that is being received.
+    Delay(Duration),
+}
+
+/// Callback that is invoked for every incoming IPv4 packet, before it is parsed. The hook is given the raw packet,
+/// starting at the IPv4 header, so that it can match on any header, and it may modify it.
+pub type Ipv4IngressHook = Box<dyn FnMut(&mut DemiBuffer) -> Ipv4IngressVerdict>;
+
+/// Filter that hands every incoming IPv4 packet to an ingress hook, if one is installed, and holds the packets that
+/// the hook delays until they are due.
+pub struct Ipv4IngressFilter {
+    runtime: SharedDemiRuntime,
+    hook: Option<Ipv4IngressHook>,
+    /// Packets that were delayed by the hook, along with the time at which they are due.
+    delayed: VecDeque<(Instant, DemiBuffer)>,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl Ipv4IngressFilter {
+    pub fn new(runtime: SharedDemiRuntime) -> Self {
+        Self {
+            runtime,
+            hook: None,
+            delayed: VecDeque::new(),
+        }
+    }
+
+    /// Installs `hook`, replacing any previously installed one. Passing `None` removes the hook. Packets that were
+    /// delayed by a previous hook are still handed over when due.
+    pub fn set_hook(&mut self, hook: Option<Ipv4IngressHook>) {
+        self.hook = hook;
+    }
+
+    /// Checks whether a hook is installed.
+    pub fn has_hook(&self) -> bool {
+        self.hook.is_some()
+    }
+
+    /// Hands `buf` to the installed hook. Returns the packet if it should be received right away, or the verdict of
+    /// the hook otherwise. Delayed packets are held by the target filter.
+    pub fn filter(&mut self, mut buf: DemiBuffer) -> Result<DemiBuffer, Ipv4IngressVerdict> {
+        let hook: &mut Ipv4IngressHook = self.hook.as_mut().expect("hook should be installed");
+        match hook(&mut buf) {
+            Ipv4IngressVerdict::Pass => Ok(buf),
+            Ipv4IngressVerdict::Drop => Err(Ipv4IngressVerdict::Drop),
+            Ipv4IngressVerdict::Delay(delay) => {
+                let deadline: Instant = self.runtime.get_now() + delay;
+                // Keep the queue sorted by deadline, and packets with the same deadline in order.
+                let index: usize = self.delayed.partition_point(|(other, _)| *other <= deadline);
+                self.delayed.insert(index, (deadline, buf));
+                Err(Ipv4IngressVerdict::Delay(delay))
+            },
+        }
+    }
+
+    /// Takes the next delayed packet that is due, if any.
+    pub fn take_due(&mut self) -> Option<DemiBuffer> {
+        let (deadline, _): &(Instant, DemiBuffer) = self.delayed.front()?;
+        if *deadline > self.runtime.get_now() {
+            return None;
+        }
+        self.delayed.pop_front().map(|(_, buf)| buf)
+    }
+}
@@ -0,0 +1,267 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::protocols::ethernet2::{
+        EtherType2,
+        ETHERNET2_HEADER_SIZE,
+    },
+    runtime::{
+        fail::Fail,
+        memory::DemiBuffer,
+        network::{
+            consts::RECEIVE_BATCH_SIZE,
+            NetworkRuntime,
+            PacketBuf,
+        },
+        SharedBox,
+    },
+};
+use ::arrayvec::ArrayVec;
+use ::std::{
+    cmp::min,
+    collections::VecDeque,
+    net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    },
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Offset of the EtherType field in an Ethernet frame.
+const ETHERTYPE_OFFSET: usize = 12;
+
+/// Offset of the destination address in an IPv4 header. It does not depend on the options of the header.
+const IPV4_DST_ADDR_OFFSET: usize = 16;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Network runtime that delivers IPv4 packets sent to the loopback network (127.0.0.0/8) back to the stack, instead
+/// of transmitting them through the underlying transport. Packets for the loopback network that are received from the
+/// underlying transport are dropped, as no legitimate peer can send them.
+pub struct LoopbackTransport {
+    transport: SharedBox<dyn NetworkRuntime>,
+    /// Frames that were sent to the loopback network and have yet to be received.
+    looped: VecDeque<DemiBuffer>,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl LoopbackTransport {
+    pub fn new(transport: SharedBox<dyn NetworkRuntime>) -> Self {
+        Self {
+            transport,
+            looped: VecDeque::new(),
+        }
+    }
+
+    /// Checks whether `frame` carries an IPv4 packet for the loopback network.
+    fn is_loopback_frame(frame: &DemiBuffer) -> bool {
+        let dst_addr_offset: usize = ETHERNET2_HEADER_SIZE + IPV4_DST_ADDR_OFFSET;
+        if frame.len() < dst_addr_offset + 4 {
+            return false;
+        }
+        let ether_type: u16 = u16::from_be_bytes([frame[ETHERTYPE_OFFSET], frame[ETHERTYPE_OFFSET + 1]]);
+        let dst_addr: Ipv4Addr = Ipv4Addr::new(
+            frame[dst_addr_offset],
+            frame[dst_addr_offset + 1],
+            frame[dst_addr_offset + 2],
+            frame[dst_addr_offset + 3],
+        );
+        ether_type == EtherType2::Ipv4 as u16 && dst_addr.is_loopback()
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl NetworkRuntime for LoopbackTransport {
+    fn transmit(&mut self, pkt: Box<dyn PacketBuf>) {
+        if !pkt.ipv4_dst_addr().map_or(false, |addr| addr.is_loopback()) {
+            self.transport.transmit(pkt);
+            return;
+        }
+
+        let header_size: usize = pkt.header_size();
+        let frame_size: usize = header_size + pkt.body_size();
+        if frame_size > u16::MAX as usize {
+            let cause: String = format!("frame is too large (frame_size={:?})", frame_size);
+            warn!("transmit(): dropping loopback frame: {}", cause);
+            return;
+        }
+        let mut frame: DemiBuffer = DemiBuffer::new(frame_size as u16);
+        pkt.write_header(&mut frame[..header_size]);
+        if let Some(body) = pkt.take_body() {
+            frame[header_size..].copy_from_slice(&body[..]);
+        }
+        self.looped.push_back(frame);
+    }
+
+    fn receive(&mut self) -> ArrayVec<DemiBuffer, RECEIVE_BATCH_SIZE> {
+        // Looped frames are received first, so that the underlying transport is only polled once they are drained.
+        if !self.looped.is_empty() {
+            let batch_size: usize = min(self.looped.len(), RECEIVE_BATCH_SIZE);
+            return self.looped.drain(..batch_size).collect();
+        }
+        self.transport
+            .receive()
+            .into_iter()
+            .filter(|frame| !Self::is_loopback_frame(frame))
+            .collect()
+    }
+
+    fn register_flow(&mut self, local: SocketAddrV4) -> Result<(), Fail> {
+        // Packets for the loopback network never come from the underlying transport.
+        if local.ip().is_loopback() {
+            return Ok(());
+        }
+        self.transport.register_flow(local)
+    }
+
+    fn unregister_flow(&mut self, local: SocketAddrV4) -> Result<(), Fail> {
+        if local.ip().is_loopback() {
+            return Ok(());
+        }
+        self.transport.unregister_flow(local)
+    }
+
+    fn get_ifindex(&self) -> u32 {
+        self.transport.get_ifindex()
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        inetstack::{
+            test_helpers::{
+                self,
+                SharedTestRuntime,
+            },
+            SharedInetStack,
+        },
+        pal::constants::{
+            AF_INET_VALUE,
+            SOCK_STREAM,
+        },
+        runtime::{
+            network::config::{
+                ArpConfig,
+                Ethernet2Config,
+                PcapConfig,
+                RoutingConfig,
+                TcpConfig,
+                UdpConfig,
+            },
+            scheduler::TaskHandle,
+            OperationResult,
+            QDesc,
+            QToken,
+            SharedBox,
+            SharedDemiRuntime,
+        },
+    };
+    use ::anyhow::Result;
+    use ::std::{
+        net::{
+            Ipv4Addr,
+            SocketAddr,
+            SocketAddrV4,
+        },
+        time::Instant,
+    };
+
+    /// Maximum number of times that the scheduler is polled while waiting for an operation.
+    const MAX_POLLS: usize = 100;
+
+    /// Polls `test_rig` until the operation `qt` of `stack` completes, and returns its result.
+    fn wait(stack: &mut SharedInetStack, test_rig: &mut SharedTestRuntime, qt: QToken) -> Result<OperationResult> {
+        let runtime: SharedDemiRuntime = test_rig.get_runtime();
+        let handle: TaskHandle = runtime.from_task_id(qt)?;
+        for _ in 0..MAX_POLLS {
+            if handle.has_completed() {
+                return Ok(stack.take_operation(handle).1);
+            }
+            test_rig.poll_scheduler();
+        }
+        anyhow::bail!("operation should have completed")
+    }
+
+    /// Tests that two sockets of the same stack connect and exchange data over the loopback network, without any
+    /// frame reaching the wire.
+    #[test]
+    fn test_loopback_connect() -> Result<()> {
+        let mut test_rig: SharedTestRuntime = SharedTestRuntime::new(
+            Instant::now(),
+            ArpConfig::default(),
+            UdpConfig::default(),
+            TcpConfig::default(),
+            test_helpers::ALICE_MAC,
+            test_helpers::ALICE_IPV4,
+        );
+        let mut stack: SharedInetStack = SharedInetStack::new(
+            test_rig.get_runtime(),
+            SharedBox::new(Box::new(test_rig.clone())),
+            test_helpers::ALICE_MAC,
+            test_helpers::ALICE_IPV4,
+            UdpConfig::default(),
+            TcpConfig::default(),
+            [0; 32],
+            ArpConfig::default(),
+            PcapConfig::default(),
+            Ethernet2Config::default(),
+            RoutingConfig::default(),
+        )?;
+        let local: SocketAddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 80));
+
+        let listen_qd: QDesc = stack.socket(AF_INET_VALUE, SOCK_STREAM, 0)?;
+        stack.bind(listen_qd, local)?;
+        stack.listen(listen_qd, 1)?;
+        let accept_qt: QToken = stack.accept(listen_qd)?;
+        let client_qd: QDesc = stack.socket(AF_INET_VALUE, SOCK_STREAM, 0)?;
+        let connect_qt: QToken = stack.connect(client_qd, local)?;
+
+        match wait(&mut stack, &mut test_rig, connect_qt)? {
+            OperationResult::Connect => (),
+            result => anyhow::bail!("connect should have succeeded: {:?}", result),
+        }
+        let server_qd: QDesc = match wait(&mut stack, &mut test_rig, accept_qt)? {
+            OperationResult::Accept((qd, remote)) => {
+                crate::ensure_eq!(*remote.ip(), Ipv4Addr::LOCALHOST);
+                qd
+            },
+            result => anyhow::bail!("accept should have succeeded: {:?}", result),
+        };
+
+        let push_qt: QToken = stack.push2(client_qd, &[0x5a; 64])?;
+        match wait(&mut stack, &mut test_rig, push_qt)? {
+            OperationResult::Push(_) => (),
+            result => anyhow::bail!("push should have succeeded: {:?}", result),
+        }
+        let pop_qt: QToken = stack.pop(server_qd, None)?;
+        match wait(&mut stack, &mut test_rig, pop_qt)? {
+            OperationResult::Pop(_, buf) => crate::ensure_eq!(&buf[..], &[0x5a; 64][..]),
+            result => anyhow::bail!("pop should have returned data: {:?}", result),
+        }
+
+        crate::ensure_eq!(test_rig.pop_all_frames().len(), 0);
+
+        Ok(())
+    }
+}
@@ -2,6 +2,10 @@
 // Licensed under the MIT license.
 
 mod datagram;
+pub mod egress;
+pub mod ingress;
+pub mod loopback;
+mod options;
 
 #[cfg(test)]
 mod tests;
@@ -10,8 +14,14 @@ mod tests;
 // Exports
 //==============================================================================
 
-pub use self::datagram::{
-    Ipv4Header,
-    IPV4_HEADER_MIN_SIZE,
-    IPV4_HEADER_MAX_SIZE,
+pub use self::{
+    datagram::{
+        Ipv4Header,
+        IPV4_HEADER_MIN_SIZE,
+        IPV4_HEADER_MAX_SIZE,
+    },
+    options::{
+        Ipv4Option,
+        IPV4_OPTIONS_MAX_SIZE,
+    },
 };
@@ -0,0 +1,178 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::runtime::fail::Fail;
+use ::arrayvec::ArrayVec;
+use ::libc::EBADMSG;
+use ::std::net::Ipv4Addr;
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+/// Maximum size of the options of an IPv4 header (in bytes).
+pub const IPV4_OPTIONS_MAX_SIZE: usize = 40;
+
+/// Maximum number of addresses in a Record Route option.
+const MAX_RECORD_ROUTE_ADDRS: usize = (IPV4_OPTIONS_MAX_SIZE - 3) / 4;
+
+/// Maximum number of entries in a Timestamp option.
+const MAX_TIMESTAMP_ENTRIES: usize = (IPV4_OPTIONS_MAX_SIZE - 4) / 4;
+
+/// Option Type: End of Option List.
+const IPV4_OPTION_END_OF_LIST: u8 = 0;
+
+/// Option Type: No Operation.
+const IPV4_OPTION_NO_OPERATION: u8 = 1;
+
+/// Option Type: Record Route.
+const IPV4_OPTION_RECORD_ROUTE: u8 = 7;
+
+/// Option Type: Internet Timestamp.
+const IPV4_OPTION_TIMESTAMP: u8 = 68;
+
+/// Timestamp Flag: Timestamps only.
+const IPV4_TIMESTAMP_ONLY: u8 = 0;
+
+/// Timestamp Flag: Each timestamp is preceded by the address of the registering entity.
+const IPV4_TIMESTAMP_WITH_ADDR: u8 = 1;
+
+/// Timestamp Flag: Timestamps are only registered by entities with prespecified addresses.
+const IPV4_TIMESTAMP_PRESPECIFIED: u8 = 3;
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// IPv4 Header Option
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Ipv4Option {
+    /// No Operation.
+    NoOperation,
+    /// Record Route (RFC 791), with the addresses recorded so far.
+    RecordRoute {
+        route: ArrayVec<Ipv4Addr, MAX_RECORD_ROUTE_ADDRS>,
+    },
+    /// Internet Timestamp (RFC 791), with the entries recorded so far. Entries only carry an address for the address
+    /// flavors of the option.
+    Timestamp {
+        overflow: u8,
+        flags: u8,
+        entries: ArrayVec<(Option<Ipv4Addr>, u32), MAX_TIMESTAMP_ENTRIES>,
+    },
+    /// Option that is not interpreted by the stack.
+    Unknown {
+        kind: u8,
+        data: ArrayVec<u8, IPV4_OPTIONS_MAX_SIZE>,
+    },
+}
+
+//==============================================================================
+// Associated Functions
+//==============================================================================
+
+impl Ipv4Option {
+    /// Parses the options field of an IPv4 header. Parsing stops at the End of Option List option, so any padding
+    /// after it is ignored. Options with malformed lengths or pointers are rejected.
+    pub fn parse_all(buf: &[u8]) -> Result<ArrayVec<Ipv4Option, IPV4_OPTIONS_MAX_SIZE>, Fail> {
+        if buf.len() > IPV4_OPTIONS_MAX_SIZE {
+            return Err(Fail::new(EBADMSG, "ipv4 options are too long"));
+        }
+
+        let mut options: ArrayVec<Ipv4Option, IPV4_OPTIONS_MAX_SIZE> = ArrayVec::new();
+        let mut offset: usize = 0;
+        while offset < buf.len() {
+            let kind: u8 = buf[offset];
+            match kind {
+                IPV4_OPTION_END_OF_LIST => break,
+                IPV4_OPTION_NO_OPERATION => {
+                    options.push(Ipv4Option::NoOperation);
+                    offset += 1;
+                    continue;
+                },
+                _ => (),
+            }
+
+            // All other options carry their own length, which includes the type and length octets.
+            if offset + 1 >= buf.len() {
+                return Err(Fail::new(EBADMSG, "ipv4 option is missing its length"));
+            }
+            let length: usize = buf[offset + 1] as usize;
+            if length < 2 || offset + length > buf.len() {
+                return Err(Fail::new(EBADMSG, "ipv4 option has an invalid length"));
+            }
+            let option: &[u8] = &buf[offset..(offset + length)];
+            options.push(match kind {
+                IPV4_OPTION_RECORD_ROUTE => Self::parse_record_route(option)?,
+                IPV4_OPTION_TIMESTAMP => Self::parse_timestamp(option)?,
+                _ => Ipv4Option::Unknown {
+                    kind,
+                    data: option[2..].iter().copied().collect(),
+                },
+            });
+            offset += length;
+        }
+
+        Ok(options)
+    }
+
+    /// Parses a Record Route option, including its type and length octets.
+    fn parse_record_route(option: &[u8]) -> Result<Ipv4Option, Fail> {
+        // The pointer is one-based and counts from the type octet, so the first address slot is at 4.
+        if option.len() < 3 || (option.len() - 3) % 4 != 0 {
+            return Err(Fail::new(EBADMSG, "ipv4 record route option has an invalid length"));
+        }
+        let pointer: usize = option[2] as usize;
+        if pointer < 4 || (pointer - 4) % 4 != 0 || pointer > option.len() + 1 {
+            return Err(Fail::new(EBADMSG, "ipv4 record route option has an invalid pointer"));
+        }
+
+        let route: ArrayVec<Ipv4Addr, MAX_RECORD_ROUTE_ADDRS> = option[3..(pointer - 1)]
+            .chunks_exact(4)
+            .map(|addr| Ipv4Addr::new(addr[0], addr[1], addr[2], addr[3]))
+            .collect();
+        Ok(Ipv4Option::RecordRoute { route })
+    }
+
+    /// Parses a Timestamp option, including its type and length octets.
+    fn parse_timestamp(option: &[u8]) -> Result<Ipv4Option, Fail> {
+        if option.len() < 4 {
+            return Err(Fail::new(EBADMSG, "ipv4 timestamp option has an invalid length"));
+        }
+        let overflow: u8 = option[3] >> 4;
+        let flags: u8 = option[3] & 0xf;
+        let entry_size: usize = match flags {
+            IPV4_TIMESTAMP_ONLY => 4,
+            IPV4_TIMESTAMP_WITH_ADDR | IPV4_TIMESTAMP_PRESPECIFIED => 8,
+            _ => return Err(Fail::new(EBADMSG, "ipv4 timestamp option has invalid flags")),
+        };
+        if (option.len() - 4) % entry_size != 0 {
+            return Err(Fail::new(EBADMSG, "ipv4 timestamp option has an invalid length"));
+        }
+        // The pointer is one-based and counts from the type octet, so the first entry is at 5.
+        let pointer: usize = option[2] as usize;
+        if pointer < 5 || (pointer - 5) % entry_size != 0 || pointer > option.len() + 1 {
+            return Err(Fail::new(EBADMSG, "ipv4 timestamp option has an invalid pointer"));
+        }
+
+        let entries: ArrayVec<(Option<Ipv4Addr>, u32), MAX_TIMESTAMP_ENTRIES> = option[4..(pointer - 1)]
+            .chunks_exact(entry_size)
+            .map(|entry| match entry_size {
+                4 => (None, u32::from_be_bytes([entry[0], entry[1], entry[2], entry[3]])),
+                _ => (
+                    Some(Ipv4Addr::new(entry[0], entry[1], entry[2], entry[3])),
+                    u32::from_be_bytes([entry[4], entry[5], entry[6], entry[7]]),
+                ),
+            })
+            .collect();
+        Ok(Ipv4Option::Timestamp {
+            overflow,
+            flags,
+            entries,
+        })
+    }
+}
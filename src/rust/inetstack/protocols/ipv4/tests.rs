@@ -8,17 +8,48 @@
 use crate::{
     inetstack::{
         protocols::{
+            ethernet2::{
+                EtherType2,
+                Ethernet2Header,
+                ETHERNET2_HEADER_SIZE,
+            },
             ip::IpProtocol,
-            ipv4::Ipv4Header,
+            ipv4::{
+                Ipv4Header,
+                Ipv4Option,
+            },
+            ProtocolHandler,
         },
         test_helpers::{
+            self,
+            SharedEngine,
             ALICE_IPV4,
             BOB_IPV4,
         },
     },
-    runtime::memory::DemiBuffer,
+    runtime::{
+        memory::DemiBuffer,
+        SharedObject,
+    },
 };
 use ::anyhow::Result;
+use ::std::{
+    net::Ipv4Addr,
+    time::Instant,
+};
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Protocol handler that records the source address and payload of every packet that it receives.
+struct CaptureHandler(SharedObject<Vec<(Ipv4Addr, DemiBuffer)>>);
+
+impl ProtocolHandler for CaptureHandler {
+    fn receive(&mut self, ipv4_hdr: Ipv4Header, buf: DemiBuffer) {
+        self.0.push((ipv4_hdr.get_src_addr(), buf));
+    }
+}
 
 //==============================================================================
 // Helper Functions
@@ -78,6 +109,41 @@ fn build_ipv4_header(
     buf[10..12].copy_from_slice(&checksum.unwrap().to_be_bytes());
 }
 
+/// Builds an Ethernet frame from Alice to Bob that carries an IPv4 packet of protocol number `protocol`.
+fn build_frame(protocol: u8, payload: &[u8]) -> Result<DemiBuffer> {
+    build_frame_with_options(protocol, &[], payload)
+}
+
+/// Builds an Ethernet frame from Alice to Bob that carries an IPv4 packet of protocol number `protocol`, with the
+/// given header options. The options must be a multiple of 4 bytes long.
+fn build_frame_with_options(protocol: u8, options: &[u8], payload: &[u8]) -> Result<DemiBuffer> {
+    let header_size: usize = 20 + options.len();
+    let datagram_size: usize = header_size + payload.len();
+    let mut buf: Vec<u8> = vec![0; ETHERNET2_HEADER_SIZE + datagram_size];
+    Ethernet2Header::new(test_helpers::BOB_MAC, test_helpers::ALICE_MAC, EtherType2::Ipv4)
+        .serialize(&mut buf[..ETHERNET2_HEADER_SIZE]);
+    // Options must be in place before building the header, so that they are covered by the checksum.
+    buf[(ETHERNET2_HEADER_SIZE + 20)..(ETHERNET2_HEADER_SIZE + header_size)].copy_from_slice(options);
+    build_ipv4_header(
+        &mut buf[ETHERNET2_HEADER_SIZE..(ETHERNET2_HEADER_SIZE + header_size)],
+        4,
+        (header_size / 4) as u8,
+        0,
+        0,
+        datagram_size as u16,
+        0,
+        0x2,
+        0,
+        1,
+        protocol,
+        &ALICE_IPV4.octets(),
+        &BOB_IPV4.octets(),
+        None,
+    );
+    buf[(ETHERNET2_HEADER_SIZE + header_size)..].copy_from_slice(payload);
+    Ok(DemiBuffer::from_slice(&buf)?)
+}
+
 //==============================================================================
 // Unit-Tests for Happy Path
 //==============================================================================
@@ -124,7 +190,7 @@ fn test_ipv4_header_parse_good() -> Result<()> {
             Ok((ipv4_hdr, datagram)) => {
                 assert_eq!(ipv4_hdr.get_src_addr(), ALICE_IPV4);
                 assert_eq!(ipv4_hdr.get_dest_addr(), BOB_IPV4);
-                assert_eq!(ipv4_hdr.get_protocol(), IpProtocol::UDP);
+                assert_eq!(ipv4_hdr.get_protocol()?, IpProtocol::UDP);
                 assert_eq!(datagram.len(), PAYLOAD_SIZE);
                 assert_eq!(datagram[..], data_bytes[..]);
             },
@@ -342,7 +408,7 @@ fn test_ipv4_header_parse_invalid_ttl() -> Result<()> {
     }
 }
 
-/// Parses a malformed IPv4 header with invalid protocol field.
+/// Parses an IPv4 header with an unassigned protocol field. Parsing succeeds, but the protocol is not a built-in one.
 #[test]
 fn test_ipv4_header_parse_invalid_protocol() -> Result<()> {
     const HEADER_SIZE: usize = 20;
@@ -375,9 +441,17 @@ fn test_ipv4_header_parse_invalid_protocol() -> Result<()> {
             Err(e) => anyhow::bail!("'buf' should fit: {:?}", e),
         };
 
+        // The header should parse, so that the datagram can be handed to a registered protocol handler.
         match Ipv4Header::parse(buf_bytes) {
-            Ok(_) => anyhow::ensure!(false, "parsed ipv4 header with invalid protocol={:?}", protocol),
-            Err(_) => {},
+            Ok((ipv4_hdr, _)) => {
+                crate::ensure_eq!(ipv4_hdr.get_protocol_number(), protocol);
+                anyhow::ensure!(
+                    ipv4_hdr.get_protocol().is_err(),
+                    "unknown protocol={:?} should not map to a built-in protocol",
+                    protocol
+                );
+            },
+            Err(e) => anyhow::bail!("failed to parse ipv4 header with protocol={:?}: {:?}", protocol, e),
         };
     }
 
@@ -587,7 +661,7 @@ fn test_ipv4_header_parse_unsupported_fragmentation() -> Result<()> {
     }
 }
 
-/// Parses a malformed IPv4 header with unsupported protocol field.
+/// Parses an IPv4 header with an unsupported protocol field. Parsing succeeds, but the protocol is not a built-in one.
 ///
 /// TODO: Drop this test once we support them.
 #[test]
@@ -627,8 +701,15 @@ fn test_ipv4_header_parse_unsupported_protocol() -> Result<()> {
                 };
 
                 match Ipv4Header::parse(buf_bytes) {
-                    Ok(_) => anyhow::bail!("parsed ipv4 header with protocol={:?}. Do we support it now?", protocol,),
-                    Err(_) => {},
+                    Ok((ipv4_hdr, _)) => {
+                        crate::ensure_eq!(ipv4_hdr.get_protocol_number(), protocol);
+                        anyhow::ensure!(
+                            ipv4_hdr.get_protocol().is_err(),
+                            "protocol={:?} is supported. Do we support it now?",
+                            protocol
+                        );
+                    },
+                    Err(e) => anyhow::bail!("failed to parse ipv4 header with protocol={:?}: {:?}", protocol, e),
                 };
             },
         };
@@ -636,3 +717,127 @@ fn test_ipv4_header_parse_unsupported_protocol() -> Result<()> {
 
     Ok(())
 }
+
+/// Tests that a handler registered for a custom protocol receives the packets of that protocol only.
+#[test]
+fn test_ipv4_register_protocol() -> Result<()> {
+    let mut bob: SharedEngine = test_helpers::new_bob2(Instant::now());
+    let packets: SharedObject<Vec<(Ipv4Addr, DemiBuffer)>> = SharedObject::new(Vec::new());
+    bob.register_protocol(200, Box::new(CaptureHandler(packets.clone())))?;
+
+    bob.receive(build_frame(200, &[0x5a; 32])?)?;
+    crate::ensure_eq!(packets.len(), 1);
+    crate::ensure_eq!(packets[0].0, ALICE_IPV4);
+    crate::ensure_eq!(&packets[0].1[..], &[0x5a; 32][..]);
+
+    // Packets of protocols without a handler should be dropped.
+    bob.receive(build_frame(201, &[0xa5; 32])?)?;
+    crate::ensure_eq!(packets.len(), 1);
+
+    Ok(())
+}
+
+/// Tests that protocols that already have a handler cannot be registered again.
+#[test]
+fn test_ipv4_register_protocol_twice() -> Result<()> {
+    let mut bob: SharedEngine = test_helpers::new_bob2(Instant::now());
+    let packets: SharedObject<Vec<(Ipv4Addr, DemiBuffer)>> = SharedObject::new(Vec::new());
+
+    // Built-in protocols are registered up front.
+    match bob.register_protocol(IpProtocol::TCP as u8, Box::new(CaptureHandler(packets.clone()))) {
+        Err(e) if e.errno == libc::EEXIST => {},
+        result => anyhow::bail!("registering TCP should have failed with EEXIST: {:?}", result),
+    };
+
+    bob.register_protocol(200, Box::new(CaptureHandler(packets.clone())))?;
+    match bob.register_protocol(200, Box::new(CaptureHandler(packets.clone()))) {
+        Err(e) if e.errno == libc::EEXIST => {},
+        result => anyhow::bail!(
+            "registering a protocol twice should have failed with EEXIST: {:?}",
+            result
+        ),
+    };
+
+    Ok(())
+}
+
+/// Parses an IPv4 header with Record Route and Timestamp options, and checks that the options are surfaced and skipped
+/// over correctly.
+#[test]
+fn test_ipv4_header_parse_options() -> Result<()> {
+    // Record Route, with room for two addresses and one recorded.
+    let record_route: [u8; 11] = [7, 11, 8, 192, 168, 1, 1, 0, 0, 0, 0];
+    // Timestamp, with one address and timestamp recorded.
+    let timestamp: [u8; 12] = [68, 12, 13, 0x01, 192, 168, 1, 2, 0, 0, 0x03, 0xe8];
+    // End of Option List.
+    let options: Vec<u8> = [&record_route[..], &timestamp[..], &[0]].concat();
+    let payload: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+    let frame: DemiBuffer = build_frame_with_options(IpProtocol::UDP as u8, &options, &payload)?;
+    let (_, datagram) = Ethernet2Header::parse(frame.clone())?;
+    let (ipv4_hdr, ipv4_payload) = Ipv4Header::parse(datagram)?;
+    crate::ensure_eq!(ipv4_hdr.compute_size(), 44);
+    crate::ensure_eq!(&ipv4_payload[..], &payload[..]);
+
+    let mut options = ipv4_hdr.get_options().into_iter();
+    match options.next() {
+        Some(Ipv4Option::RecordRoute { route }) => crate::ensure_eq!(&route[..], &[ALICE_IPV4]),
+        option => anyhow::bail!("expected a record route option: {:?}", option),
+    };
+    match options.next() {
+        Some(Ipv4Option::Timestamp {
+            overflow,
+            flags,
+            entries,
+        }) => {
+            crate::ensure_eq!(overflow, 0);
+            crate::ensure_eq!(flags, 1);
+            crate::ensure_eq!(&entries[..], &[(Some(BOB_IPV4), 1000)]);
+        },
+        option => anyhow::bail!("expected a timestamp option: {:?}", option),
+    };
+    crate::ensure_eq!(options.next(), None);
+
+    // Serializing the header should reproduce it, options included.
+    let mut buf: [u8; 44] = [0; 44];
+    ipv4_hdr.serialize(&mut buf, payload.len());
+    crate::ensure_eq!(&buf[..], &frame[ETHERNET2_HEADER_SIZE..(ETHERNET2_HEADER_SIZE + 44)]);
+
+    Ok(())
+}
+
+/// Tests that packets with malformed option lengths are dropped and counted as header errors.
+#[test]
+fn test_ipv4_header_parse_malformed_options() -> Result<()> {
+    let mut bob: SharedEngine = test_helpers::new_bob2(Instant::now());
+    let packets: SharedObject<Vec<(Ipv4Addr, DemiBuffer)>> = SharedObject::new(Vec::new());
+    bob.register_protocol(200, Box::new(CaptureHandler(packets.clone())))?;
+
+    let malformed_options: [[u8; 4]; 4] = [
+        // Length runs past the end of the header.
+        [7, 8, 4, 0],
+        // Length is too small to hold the option type and length.
+        [130, 1, 0, 0],
+        // Length is missing.
+        [1, 1, 1, 68],
+        // Timestamp with invalid flags.
+        [68, 4, 5, 0x02],
+    ];
+    for options in malformed_options.iter() {
+        let frame: DemiBuffer = build_frame_with_options(200, options, &[0x5a; 8])?;
+        let (_, datagram) = Ethernet2Header::parse(frame.clone())?;
+        match Ipv4Header::parse(datagram) {
+            Err(e) if e.errno == libc::EBADMSG => {},
+            result => anyhow::bail!("parsing options {:?} should have failed: {:?}", options, result),
+        };
+        bob.receive(frame)?;
+    }
+    crate::ensure_eq!(packets.len(), 0);
+    crate::ensure_eq!(bob.ipv4_header_errors(), malformed_options.len());
+
+    // Well-formed options should not get in the way.
+    bob.receive(build_frame_with_options(200, &[1, 1, 1, 0], &[0x5a; 8])?)?;
+    crate::ensure_eq!(packets.len(), 1);
+    crate::ensure_eq!(bob.ipv4_header_errors(), malformed_options.len());
+
+    Ok(())
+}
@@ -7,10 +7,14 @@ pub mod icmpv4;
 pub mod ip;
 pub mod ipv4;
 mod peer;
+pub mod raw;
 pub mod tcp;
 pub mod udp;
 
-pub use peer::Peer;
+pub use peer::{
+    Peer,
+    ProtocolHandler,
+};
 
 use ::std::slice::ChunksExact;
 
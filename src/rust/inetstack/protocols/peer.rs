@@ -6,7 +6,15 @@ use crate::{
         arp::SharedArpPeer,
         icmpv4::SharedIcmpv4Peer,
         ip::IpProtocol,
-        ipv4::Ipv4Header,
+        ipv4::{
+            ingress::{
+                Ipv4IngressFilter,
+                Ipv4IngressHook,
+                Ipv4IngressVerdict,
+            },
+            Ipv4Header,
+        },
+        raw::SharedRawPeer,
         tcp::SharedTcpPeer,
         udp::SharedUdpPeer,
     },
@@ -21,23 +29,37 @@ use crate::{
             types::MacAddress,
             NetworkRuntime,
         },
+        QDesc,
         SharedBox,
         SharedDemiRuntime,
     },
 };
 use ::std::{
+    collections::HashMap,
     net::Ipv4Addr,
     time::Duration,
 };
 
-#[cfg(test)]
-use crate::runtime::QDesc;
+/// Handler for inbound IPv4 packets of a given protocol number.
+pub trait ProtocolHandler {
+    /// Handles an inbound IPv4 packet, given its header and payload.
+    fn receive(&mut self, ipv4_hdr: Ipv4Header, buf: DemiBuffer);
+}
 
 pub struct Peer {
     local_ipv4_addr: Ipv4Addr,
     icmpv4: SharedIcmpv4Peer,
     pub tcp: SharedTcpPeer,
     pub udp: SharedUdpPeer,
+    pub raw: SharedRawPeer,
+    /// Handlers of inbound packets, keyed by protocol number.
+    protocols: HashMap<u8, Box<dyn ProtocolHandler>>,
+    /// Number of inbound packets dropped because of a malformed header, including malformed options.
+    header_errors: usize,
+    /// Filter of inbound packets, which hands them to the ingress hook, if any.
+    ingress: Ipv4IngressFilter,
+    /// Number of inbound packets dropped by the ingress hook.
+    filtered_packets: usize,
 }
 
 impl Peer {
@@ -58,59 +80,174 @@ impl Peer {
             local_link_addr,
             local_ipv4_addr,
             udp_offload_checksum,
+            udp_config.get_mtu(),
+            udp_config.get_recv_queue_config(),
             arp.clone(),
         )?;
-        let icmpv4: SharedIcmpv4Peer = SharedIcmpv4Peer::new(
+        let tcp: SharedTcpPeer = SharedTcpPeer::new(
             runtime.clone(),
             transport.clone(),
             local_link_addr,
             local_ipv4_addr,
+            tcp_config,
             arp.clone(),
             rng_seed,
         )?;
-        let tcp: SharedTcpPeer = SharedTcpPeer::new(
+        let icmpv4: SharedIcmpv4Peer = SharedIcmpv4Peer::new(
             runtime.clone(),
             transport.clone(),
             local_link_addr,
             local_ipv4_addr,
-            tcp_config,
-            arp,
+            arp.clone(),
+            udp.clone(),
+            tcp.clone(),
             rng_seed,
         )?;
+        let ingress: Ipv4IngressFilter = Ipv4IngressFilter::new(runtime.clone());
+        let raw: SharedRawPeer = SharedRawPeer::new(runtime, transport, local_link_addr, local_ipv4_addr, arp)?;
+
+        let mut protocols: HashMap<u8, Box<dyn ProtocolHandler>> = HashMap::new();
+        protocols.insert(IpProtocol::ICMPv4 as u8, Box::new(icmpv4.clone()));
+        protocols.insert(IpProtocol::TCP as u8, Box::new(tcp.clone()));
+        protocols.insert(IpProtocol::UDP as u8, Box::new(udp.clone()));
 
         Ok(Peer {
             local_ipv4_addr,
             icmpv4,
             tcp,
             udp,
+            raw,
+            protocols,
+            header_errors: 0,
+            ingress,
+            filtered_packets: 0,
         })
     }
 
+    /// Registers `handler` for inbound packets of the IPv4 protocol number `protocol`. Protocols that already have a
+    /// handler, including those built into the stack, cannot be registered again.
+    pub fn register_protocol(&mut self, protocol: u8, handler: Box<dyn ProtocolHandler>) -> Result<(), Fail> {
+        if self.protocols.contains_key(&protocol) {
+            let cause: String = format!("protocol already has a handler (protocol={:?})", protocol);
+            error!("register_protocol(): {}", cause);
+            return Err(Fail::new(libc::EEXIST, &cause));
+        }
+        self.protocols.insert(protocol, handler);
+        Ok(())
+    }
+
+    /// Returns the local IPv4 address of the target peer.
+    pub fn get_local_ipv4_addr(&self) -> Ipv4Addr {
+        self.local_ipv4_addr
+    }
+
+    /// Returns the number of inbound packets that the target peer dropped because of a malformed header.
+    pub fn get_header_errors(&self) -> usize {
+        self.header_errors
+    }
+
+    /// Returns the number of inbound packets that the ingress hook of the target peer dropped.
+    pub fn get_filtered_packets(&self) -> usize {
+        self.filtered_packets
+    }
+
+    /// Installs a hook that is handed every inbound IPv4 packet before it is parsed, replacing any previously
+    /// installed one. Passing `None` removes the hook.
+    pub fn set_ingress_hook(&mut self, hook: Option<Ipv4IngressHook>) {
+        self.ingress.set_hook(hook)
+    }
+
+    /// Reseeds the random number generators of all protocol peers from `rng_seed`, as they were seeded at creation.
+    pub fn reseed(&mut self, rng_seed: [u8; 32]) {
+        self.icmpv4.reseed(rng_seed);
+        self.tcp.reseed(rng_seed);
+    }
+
+    /// Changes the local link and IPv4 addresses of all protocol peers, and the IPv4 address that inbound packets must
+    /// be sent to.
+    pub fn set_local_addrs(&mut self, local_link_addr: MacAddress, local_ipv4_addr: Ipv4Addr) {
+        self.local_ipv4_addr = local_ipv4_addr;
+        self.icmpv4.set_local_addrs(local_link_addr, local_ipv4_addr);
+        self.tcp.set_local_addrs(local_link_addr, local_ipv4_addr);
+        self.udp.set_local_addrs(local_link_addr, local_ipv4_addr);
+        self.raw.set_local_addrs(local_link_addr, local_ipv4_addr);
+    }
+
     pub fn receive(&mut self, buf: DemiBuffer) {
+        if !self.ingress.has_hook() {
+            self.dispatch(buf);
+            return;
+        }
+        match self.ingress.filter(buf) {
+            Ok(buf) => self.dispatch(buf),
+            Err(Ipv4IngressVerdict::Drop) => {
+                self.filtered_packets += 1;
+                warn!("dropping packet: rejected by ingress hook");
+            },
+            Err(_) => (),
+        }
+        self.poll_ingress();
+    }
+
+    /// Receives the inbound packets that were delayed by the ingress hook and are now due.
+    pub fn poll_ingress(&mut self) {
+        while let Some(buf) = self.ingress.take_due() {
+            self.dispatch(buf);
+        }
+    }
+
+    /// Parses the inbound packet `buf` and hands it to the handler of its protocol.
+    fn dispatch(&mut self, buf: DemiBuffer) {
         let (header, payload) = match Ipv4Header::parse(buf) {
             Ok(result) => result,
             Err(e) => {
+                // Unsupported features, such as fragmentation, are not header errors.
+                if e.errno == libc::EBADMSG {
+                    self.header_errors += 1;
+                }
                 let cause: String = format!("Invalid destination address: {:?}", e);
                 warn!("dropping packet: {}", cause);
                 return;
             },
         };
         debug!("Ipv4 received {:?}", header);
-        if header.get_dest_addr() != self.local_ipv4_addr && !header.get_dest_addr().is_broadcast() {
+        // Packets for the loopback network only come from the stack itself.
+        if header.get_dest_addr() != self.local_ipv4_addr
+            && !header.get_dest_addr().is_broadcast()
+            && !header.get_dest_addr().is_loopback()
+        {
             let cause: String = format!("Invalid destination address");
             warn!("dropping packet: {}", cause);
             return;
         }
-        match header.get_protocol() {
-            IpProtocol::ICMPv4 => self.icmpv4.receive(header, payload),
-            IpProtocol::TCP => self.tcp.receive(header, payload),
-            IpProtocol::UDP => self.udp.receive(header, payload),
+        // Protocols without a handler are left to raw sockets.
+        match self.protocols.get_mut(&header.get_protocol_number()) {
+            Some(handler) => handler.receive(header, payload),
+            None => self.raw.receive(header, payload),
         }
     }
 
     pub async fn ping(&mut self, dest_ipv4_addr: Ipv4Addr, timeout: Option<Duration>) -> Result<Duration, Fail> {
         self.icmpv4.ping(dest_ipv4_addr, timeout).await
     }
+
+    pub async fn ping_with_payload(
+        &mut self,
+        dest_ipv4_addr: Ipv4Addr,
+        payload: Option<DemiBuffer>,
+        id_seq_num: Option<(u16, u16)>,
+        timeout: Option<Duration>,
+    ) -> Result<(Duration, DemiBuffer), Fail> {
+        self.icmpv4
+            .ping_with_payload(dest_ipv4_addr, payload, id_seq_num, timeout)
+            .await
+    }
+
+    /// Gets the largest payload that a segment of the TCP socket `qd` can carry without being fragmented. Links are
+    /// assumed to have the MTU that is configured for UDP.
+    pub fn tcp_path_mss(&self, qd: QDesc) -> Result<usize, Fail> {
+        self.tcp.path_mss(qd, self.udp.get_mtu())
+    }
 }
 
 #[cfg(test)]
@@ -122,4 +259,30 @@ impl Peer {
     pub fn tcp_rto(&self, fd: QDesc) -> Result<Duration, Fail> {
         self.tcp.current_rto(fd)
     }
+
+    pub fn tcp_send_window(&self, fd: QDesc) -> Result<u32, Fail> {
+        self.tcp.send_window(fd)
+    }
+
+    pub fn tcp_set_receive_window(&mut self, fd: QDesc, window_size: u32) -> Result<(), Fail> {
+        self.tcp.set_receive_window(fd, window_size)
+    }
+}
+
+impl ProtocolHandler for SharedIcmpv4Peer {
+    fn receive(&mut self, ipv4_hdr: Ipv4Header, buf: DemiBuffer) {
+        SharedIcmpv4Peer::receive(self, ipv4_hdr, buf)
+    }
+}
+
+impl ProtocolHandler for SharedTcpPeer {
+    fn receive(&mut self, ipv4_hdr: Ipv4Header, buf: DemiBuffer) {
+        SharedTcpPeer::receive(self, ipv4_hdr, buf)
+    }
+}
+
+impl ProtocolHandler for SharedUdpPeer {
+    fn receive(&mut self, ipv4_hdr: Ipv4Header, buf: DemiBuffer) {
+        SharedUdpPeer::receive(self, ipv4_hdr, buf)
+    }
 }
@@ -0,0 +1,92 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::{
+    inetstack::protocols::{
+        ethernet2::Ethernet2Header,
+        ipv4::Ipv4Header,
+    },
+    runtime::{
+        memory::DemiBuffer,
+        network::PacketBuf,
+    },
+};
+use ::std::net::Ipv4Addr;
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Raw IPv4 Datagram
+#[derive(Debug)]
+pub struct RawDatagram {
+    /// Ethernet header.
+    ethernet2_hdr: Ethernet2Header,
+    /// IPv4 header.
+    ipv4_hdr: Ipv4Header,
+    /// Caller-supplied IPv4 payload.
+    data: DemiBuffer,
+}
+
+//==============================================================================
+// Associate Functions
+//==============================================================================
+
+// Associate Functions for Raw Datagrams
+impl RawDatagram {
+    /// Creates a raw IPv4 packet.
+    pub fn new(ethernet2_hdr: Ethernet2Header, ipv4_hdr: Ipv4Header, data: DemiBuffer) -> Self {
+        Self {
+            ethernet2_hdr,
+            ipv4_hdr,
+            data,
+        }
+    }
+}
+
+//==============================================================================
+// Trait Implementations
+//==============================================================================
+
+/// Packet Buffer Trait Implementation for Raw Datagrams
+impl PacketBuf for RawDatagram {
+    /// Returns the IPv4 destination address of the target raw datagram.
+    fn ipv4_dst_addr(&self) -> Option<Ipv4Addr> {
+        Some(self.ipv4_hdr.get_dest_addr())
+    }
+
+    /// Computes the header size of the target raw datagram.
+    fn header_size(&self) -> usize {
+        self.ethernet2_hdr.compute_size() + self.ipv4_hdr.compute_size()
+    }
+
+    /// Computes the payload size of the target raw datagram.
+    fn body_size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Serializes the header of the target raw datagram.
+    fn write_header(&self, buf: &mut [u8]) {
+        let mut cur_pos: usize = 0;
+        let eth_hdr_size: usize = self.ethernet2_hdr.compute_size();
+        let ipv4_hdr_size: usize = self.ipv4_hdr.compute_size();
+
+        // Ethernet header.
+        self.ethernet2_hdr
+            .serialize(&mut buf[cur_pos..(cur_pos + eth_hdr_size)]);
+        cur_pos += eth_hdr_size;
+
+        // IPV4 header.
+        self.ipv4_hdr
+            .serialize(&mut buf[cur_pos..(cur_pos + ipv4_hdr_size)], self.data.len());
+    }
+
+    /// Returns the payload of the target raw datagram.
+    fn take_body(&self) -> Option<DemiBuffer> {
+        Some(self.data.clone())
+    }
+}
@@ -0,0 +1,24 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! # Raw IPv4 Sockets
+//!
+//! Raw sockets let applications send and receive IPv4 payloads for a protocol number that is not handled by the
+//! stack itself. The stack builds the IPv4 header on transmission and strips it on reception.
+//!
+//! # References
+//!
+//! - https://datatracker.ietf.org/doc/html/rfc3692.
+
+mod datagram;
+pub mod peer;
+pub mod queue;
+
+#[cfg(test)]
+mod tests;
+
+//==============================================================================
+// Exports
+//==============================================================================
+
+pub use self::peer::SharedRawPeer;
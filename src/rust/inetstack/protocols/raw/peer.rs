@@ -0,0 +1,227 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use super::queue::SharedRawQueue;
+use crate::{
+    inetstack::protocols::{
+        arp::SharedArpPeer,
+        ip::IpProtocol,
+        ipv4::Ipv4Header,
+    },
+    runtime::{
+        fail::Fail,
+        memory::DemiBuffer,
+        network::{
+            types::MacAddress,
+            NetworkRuntime,
+        },
+        queue::{
+            downcast_queue_ptr,
+            OperationResult,
+            QDesc,
+        },
+        scheduler::{
+            TaskHandle,
+            Yielder,
+        },
+        Operation,
+        SharedBox,
+        SharedDemiRuntime,
+        SharedObject,
+    },
+    QToken,
+};
+use ::std::{
+    net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    },
+    ops::{
+        Deref,
+        DerefMut,
+    },
+    pin::Pin,
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Raw IPv4 Peer
+pub struct RawPeer {
+    /// Shared Demikernel runtime.
+    runtime: SharedDemiRuntime,
+    /// Underlying transport.
+    transport: SharedBox<dyn NetworkRuntime>,
+    /// Underlying ARP peer.
+    arp: SharedArpPeer,
+    /// Local link address.
+    local_link_addr: MacAddress,
+    /// Local IPv4 address.
+    local_ipv4_addr: Ipv4Addr,
+}
+
+#[derive(Clone)]
+pub struct SharedRawPeer(SharedObject<RawPeer>);
+
+//======================================================================================================================
+// Associate Functions
+//======================================================================================================================
+
+/// Associate functions for [SharedRawPeer].
+impl SharedRawPeer {
+    pub fn new(
+        runtime: SharedDemiRuntime,
+        transport: SharedBox<dyn NetworkRuntime>,
+        local_link_addr: MacAddress,
+        local_ipv4_addr: Ipv4Addr,
+        arp: SharedArpPeer,
+    ) -> Result<Self, Fail> {
+        Ok(Self(SharedObject::<RawPeer>::new(RawPeer {
+            runtime,
+            transport,
+            arp,
+            local_link_addr,
+            local_ipv4_addr,
+        })))
+    }
+
+    /// Changes the addresses that new sockets of the target peer send their packets from.
+    pub fn set_local_addrs(&mut self, local_link_addr: MacAddress, local_ipv4_addr: Ipv4Addr) {
+        self.local_link_addr = local_link_addr;
+        self.local_ipv4_addr = local_ipv4_addr;
+    }
+
+    /// Opens a raw socket for the IPv4 protocol number `protocol`, which may be any protocol but ICMP, TCP and UDP.
+    pub fn socket(&mut self, protocol: u8) -> Result<QDesc, Fail> {
+        // Protocols that are handled by the stack itself cannot be opened as raw sockets.
+        if matches!(
+            IpProtocol::try_from(protocol),
+            Ok(IpProtocol::ICMPv4 | IpProtocol::TCP | IpProtocol::UDP)
+        ) {
+            let cause: String = format!("protocol not supported for raw sockets (protocol={:?})", protocol);
+            error!("socket(): {}", cause);
+            return Err(Fail::new(libc::EPROTONOSUPPORT, &cause));
+        }
+        let new_queue: SharedRawQueue = SharedRawQueue::new(
+            protocol,
+            self.local_ipv4_addr,
+            self.local_link_addr,
+            self.transport.clone(),
+            self.arp.clone(),
+        )?;
+        let new_qd: QDesc = self.runtime.alloc_queue::<SharedRawQueue>(new_queue)?;
+        trace!("socket(): qd={:?} protocol={:?}", new_qd, protocol);
+        Ok(new_qd)
+    }
+
+    /// Closes a raw socket.
+    pub fn close(&mut self, qd: QDesc) -> Result<(), Fail> {
+        trace!("close(): qd={:?}", qd);
+        self.runtime.free_queue::<SharedRawQueue>(&qd)?;
+        Ok(())
+    }
+
+    /// Closes a raw socket asynchronously.
+    pub fn async_close(&mut self, qd: QDesc) -> Result<QToken, Fail> {
+        let mut runtime: SharedDemiRuntime = self.runtime.clone();
+        let task_name: String = format!("inetstack::raw::close for qd={:?}", qd);
+        let coroutine_factory = |_yielder| -> Pin<Box<Operation>> {
+            Box::pin(async move {
+                // Expect is safe here because we looked up the queue to schedule this coroutine and no
+                // other close coroutine should be able to run due to state machine checks.
+                runtime.free_queue::<SharedRawQueue>(&qd).expect("queue should exist");
+                (qd, OperationResult::Close)
+            })
+        };
+        let task_handle: TaskHandle =
+            self.clone()
+                .runtime
+                .insert_coroutine_with_tracking(&task_name, coroutine_factory, qd)?;
+
+        let qt: QToken = task_handle.get_task_id().into();
+
+        trace!("async_close() qt={:?}", qt);
+        Ok(qt)
+    }
+
+    /// Pushes an IPv4 payload to a remote host. The port of `remote` is ignored.
+    pub fn pushto(&mut self, qd: QDesc, buf: DemiBuffer, remote: SocketAddrV4) -> Result<Pin<Box<Operation>>, Fail> {
+        trace!("pushto(): qd={:?} remote={:?} bytes={:?}", qd, remote, buf.len());
+        let mut queue: SharedRawQueue = self.get_shared_queue(&qd)?;
+        let yielder: Yielder = Yielder::new();
+        let len: usize = buf.len();
+        Ok(Box::pin(async move {
+            match queue.pushto(remote.ip().clone(), buf, yielder).await {
+                Ok(()) => (qd, OperationResult::Push(len)),
+                Err(e) => (qd, OperationResult::Failed(e)),
+            }
+        }))
+    }
+
+    /// Pops an IPv4 payload from a raw socket. The remote address is reported with a zero port.
+    pub fn pop(&mut self, qd: QDesc, size: Option<usize>) -> Result<Pin<Box<Operation>>, Fail> {
+        let yielder: Yielder = Yielder::new();
+        let mut queue: SharedRawQueue = self.get_shared_queue(&qd)?;
+
+        Ok(Box::pin(async move {
+            match queue.pop(size, yielder).await {
+                Ok((addr, buf)) => (qd, OperationResult::Pop(Some(SocketAddrV4::new(addr, 0)), buf)),
+                Err(e) => (qd, OperationResult::Failed(e)),
+            }
+        }))
+    }
+
+    /// Delivers the payload of an inbound IPv4 packet to every raw socket opened for its protocol.
+    pub fn receive(&mut self, ipv4_hdr: Ipv4Header, buf: DemiBuffer) {
+        let protocol: u8 = ipv4_hdr.get_protocol_number();
+        let remote: Ipv4Addr = ipv4_hdr.get_src_addr();
+        let mut queues: Vec<SharedRawQueue> = self.get_queues_from_protocol(protocol);
+        if queues.is_empty() {
+            let cause: String = format!("dropping packet: no raw socket for protocol");
+            warn!("{}: {:?}", cause, protocol);
+            return;
+        }
+        for queue in queues.iter_mut() {
+            queue.receive(remote, buf.clone());
+        }
+    }
+
+    fn get_queues_from_protocol(&self, protocol: u8) -> Vec<SharedRawQueue> {
+        let mut queues: Vec<SharedRawQueue> = Vec::new();
+        for (_, boxed_queue) in self.runtime.get_qtable().get_values() {
+            match downcast_queue_ptr::<SharedRawQueue>(boxed_queue) {
+                Ok(queue) if queue.get_protocol() == protocol => queues.push(queue.clone()),
+                _ => continue,
+            }
+        }
+
+        queues
+    }
+
+    fn get_shared_queue(&self, qd: &QDesc) -> Result<SharedRawQueue, Fail> {
+        Ok(self.runtime.get_shared_queue::<SharedRawQueue>(qd)?.clone())
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl Deref for SharedRawPeer {
+    type Target = RawPeer;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.deref()
+    }
+}
+
+impl DerefMut for SharedRawPeer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0.deref_mut()
+    }
+}
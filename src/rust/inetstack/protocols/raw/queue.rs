@@ -0,0 +1,171 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    collections::async_queue::AsyncQueue,
+    inetstack::protocols::{
+        arp::SharedArpPeer,
+        ethernet2::{
+            EtherType2,
+            Ethernet2Header,
+        },
+        ipv4::Ipv4Header,
+        raw::datagram::RawDatagram,
+    },
+    runtime::{
+        fail::Fail,
+        memory::DemiBuffer,
+        network::{
+            types::MacAddress,
+            NetworkRuntime,
+        },
+        queue::{
+            IoQueue,
+            NetworkQueue,
+        },
+        scheduler::Yielder,
+        SharedBox,
+        SharedObject,
+    },
+};
+use ::std::{
+    any::Any,
+    net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    },
+    ops::{
+        Deref,
+        DerefMut,
+    },
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Per-queue metadata for a raw IPv4 socket.
+pub struct RawQueue {
+    /// IPv4 protocol number that this socket sends and receives.
+    protocol: u8,
+    local_ipv4_addr: Ipv4Addr,
+    local_link_addr: MacAddress,
+    transport: SharedBox<dyn NetworkRuntime>,
+    // A queue of incoming packets as remote address and IPv4 payload pairs.
+    recv_queue: AsyncQueue<(Ipv4Addr, DemiBuffer)>,
+    arp: SharedArpPeer,
+}
+
+#[derive(Clone)]
+pub struct SharedRawQueue(SharedObject<RawQueue>);
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl SharedRawQueue {
+    pub fn new(
+        protocol: u8,
+        local_ipv4_addr: Ipv4Addr,
+        local_link_addr: MacAddress,
+        transport: SharedBox<dyn NetworkRuntime>,
+        arp: SharedArpPeer,
+    ) -> Result<Self, Fail> {
+        Ok(Self(SharedObject::new(RawQueue {
+            protocol,
+            local_ipv4_addr,
+            local_link_addr,
+            transport,
+            recv_queue: AsyncQueue::<(Ipv4Addr, DemiBuffer)>::default(),
+            arp,
+        })))
+    }
+
+    /// Sends `buf` as the payload of an IPv4 packet addressed to `remote`.
+    pub async fn pushto(&mut self, remote: Ipv4Addr, buf: DemiBuffer, yielder: Yielder) -> Result<(), Fail> {
+        let remote_link_addr: MacAddress = self.arp.query(remote, &yielder).await?;
+        let datagram: RawDatagram = RawDatagram::new(
+            Ethernet2Header::new(remote_link_addr, self.local_link_addr, EtherType2::Ipv4),
+            Ipv4Header::new_with_protocol_number(self.local_ipv4_addr, remote, self.protocol),
+            buf,
+        );
+        self.transport.transmit(Box::new(datagram));
+        Ok(())
+    }
+
+    /// Pops the payload of the next inbound IPv4 packet, along with the address of its sender.
+    pub async fn pop(&mut self, size: Option<usize>, yielder: Yielder) -> Result<(Ipv4Addr, DemiBuffer), Fail> {
+        const MAX_POP_SIZE: usize = 9000;
+        let size: usize = size.unwrap_or(MAX_POP_SIZE);
+
+        let (remote, mut buf): (Ipv4Addr, DemiBuffer) = self.recv_queue.pop(&yielder).await?;
+        // We got more bytes than expected, so we trim the buffer.
+        if size < buf.len() {
+            buf.trim(buf.len() - size)?;
+        }
+        Ok((remote, buf))
+    }
+
+    pub fn receive(&mut self, remote: Ipv4Addr, buf: DemiBuffer) {
+        // Push data to the receiver-side shared queue. This will cause the
+        // associated pop operation to be ready.
+        self.recv_queue.push((remote, buf));
+    }
+
+    pub fn get_protocol(&self) -> u8 {
+        self.protocol
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+/// IoQueue Trait Implementation for Raw Queues.
+impl IoQueue for SharedRawQueue {
+    fn get_qtype(&self) -> crate::QType {
+        crate::QType::RawSocket
+    }
+
+    fn as_any_ref(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn as_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+impl Deref for SharedRawQueue {
+    type Target = RawQueue;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.deref()
+    }
+}
+
+impl DerefMut for SharedRawQueue {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0.deref_mut()
+    }
+}
+
+impl NetworkQueue for SharedRawQueue {
+    /// Raw sockets are not bound to a transport-level endpoint.
+    fn local(&self) -> Option<SocketAddrV4> {
+        None
+    }
+
+    /// Raw sockets are not connected to a transport-level endpoint.
+    fn remote(&self) -> Option<SocketAddrV4> {
+        None
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::ip::IpProtocol,
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        queue::{
+            Operation,
+            OperationResult,
+            QDesc,
+        },
+    },
+};
+use ::anyhow::Result;
+use ::futures::task::{
+    noop_waker_ref,
+    Context,
+};
+use ::std::{
+    future::Future,
+    net::SocketAddrV4,
+    pin::Pin,
+    task::Poll,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Protocol number of SCTP, which the stack does not handle itself.
+const SCTP_PROTOCOL: u8 = 132;
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Sends a payload on a raw socket for `protocol`, and checks that it is delivered to raw sockets of the same protocol
+/// only, and not to those for `other_protocol`.
+fn do_raw_push_pop(protocol: u8, other_protocol: u8) -> Result<()> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    let now: Instant = Instant::now();
+
+    // Setup Alice.
+    let mut alice: SharedEngine = test_helpers::new_alice2(now);
+    let alice_fd: QDesc = alice.raw_socket(protocol)?;
+
+    // Setup Bob, with one socket for the protocol in use and another one for a different protocol.
+    let mut bob: SharedEngine = test_helpers::new_bob2(now);
+    let bob_fd: QDesc = bob.raw_socket(protocol)?;
+    let other_fd: QDesc = bob.raw_socket(other_protocol)?;
+
+    // Send data to Bob.
+    let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 32][..]).expect("slice should fit in DemiBuffer");
+    let mut coroutine: Pin<Box<Operation>> = alice.raw_pushto(alice_fd, buf.clone(), test_helpers::BOB_IPV4)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Push(_))) => {},
+        _ => anyhow::bail!("push should complete"),
+    };
+    alice.get_test_rig().poll_scheduler();
+
+    // Receive data from Alice.
+    bob.receive(alice.get_test_rig().pop_frame())?;
+    let mut coroutine: Pin<Box<Operation>> = bob.raw_pop(bob_fd)?;
+    let (remote_addr, received_buf): (Option<SocketAddrV4>, DemiBuffer) =
+        match Future::poll(coroutine.as_mut(), &mut ctx) {
+            Poll::Ready((_, OperationResult::Pop(addr, buf))) => (addr, buf),
+            _ => anyhow::bail!("pop should complete"),
+        };
+    crate::ensure_eq!(remote_addr, Some(SocketAddrV4::new(test_helpers::ALICE_IPV4, 0)));
+    crate::ensure_eq!(received_buf[..], buf[..]);
+
+    // The socket for the other protocol should not have received anything.
+    let mut coroutine: Pin<Box<Operation>> = bob.raw_pop(other_fd)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Pending => {},
+        _ => anyhow::bail!("pop should not complete"),
+    };
+
+    // Close peers.
+    alice.raw_close(alice_fd)?;
+    bob.raw_close(bob_fd)?;
+    bob.raw_close(other_fd)?;
+
+    Ok(())
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that a payload sent on a raw socket for an experimental protocol is delivered to raw sockets of the same
+/// protocol only.
+#[test]
+fn raw_push_pop() -> Result<()> {
+    do_raw_push_pop(IpProtocol::Experimental1 as u8, IpProtocol::Experimental2 as u8)
+}
+
+/// Tests that raw sockets can be opened for any protocol number that the stack does not handle itself.
+#[test]
+fn raw_push_pop_custom_protocol() -> Result<()> {
+    do_raw_push_pop(SCTP_PROTOCOL, IpProtocol::Experimental1 as u8)
+}
+
+/// Tests that raw sockets cannot be opened for protocols that are handled by the stack.
+#[test]
+fn raw_socket_unsupported_protocol() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut alice: SharedEngine = test_helpers::new_alice2(now);
+
+    for protocol in [IpProtocol::ICMPv4, IpProtocol::TCP, IpProtocol::UDP] {
+        match alice.raw_socket(protocol as u8) {
+            Err(e) if e.errno == libc::EPROTONOSUPPORT => {},
+            Err(e) => anyhow::bail!("socket() failed with unexpected error: {:?}", e),
+            Ok(_) => anyhow::bail!("socket() should fail for {:?}", protocol),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,113 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::runtime::fail::Fail;
+use ::std::net::Ipv4Addr;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Whether the prefixes of an [AcceptFilter] list the sources that are allowed or the ones that are denied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AcceptFilterPolicy {
+    /// Only connections from the listed prefixes are accepted.
+    Allow,
+    /// Connections from the listed prefixes are refused.
+    Deny,
+}
+
+/// Filter of the remote addresses that a listening socket accepts connections from. Connections that the filter
+/// refuses are reset during the handshake, and are never surfaced to the application.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AcceptFilter {
+    /// Whether the prefixes are allowed or denied.
+    policy: AcceptFilterPolicy,
+    /// IPv4 prefixes, along with their lengths.
+    prefixes: Vec<(Ipv4Addr, u8)>,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl AcceptFilter {
+    /// Creates a filter that applies `policy` to the remote addresses covered by `prefixes`. Fails with `EINVAL` if
+    /// the length of a prefix is larger than 32.
+    pub fn new(policy: AcceptFilterPolicy, prefixes: Vec<(Ipv4Addr, u8)>) -> Result<Self, Fail> {
+        if let Some((addr, len)) = prefixes.iter().find(|(_, len)| *len > 32) {
+            let cause: String = format!("invalid prefix length (prefix={:?}/{:?})", addr, len);
+            error!("new(): {}", &cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        Ok(Self { policy, prefixes })
+    }
+
+    /// Checks whether the target [AcceptFilter] lets connections from `remote` through.
+    pub fn permits(&self, remote: Ipv4Addr) -> bool {
+        let listed: bool = self.prefixes.iter().any(|(addr, len)| {
+            let mask: u32 = u32::MAX.checked_shl(32 - *len as u32).unwrap_or(0);
+            (u32::from(*addr) & mask) == (u32::from(remote) & mask)
+        });
+        match self.policy {
+            AcceptFilterPolicy::Allow => listed,
+            AcceptFilterPolicy::Deny => !listed,
+        }
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AcceptFilter,
+        AcceptFilterPolicy,
+    };
+    use ::anyhow::Result;
+    use ::std::net::Ipv4Addr;
+
+    /// Tests that an allow list only lets the addresses of its prefixes through.
+    #[test]
+    fn test_accept_filter_allow() -> Result<()> {
+        let filter: AcceptFilter = AcceptFilter::new(
+            AcceptFilterPolicy::Allow,
+            vec![(Ipv4Addr::new(10, 0, 0, 0), 8), (Ipv4Addr::new(192, 168, 1, 7), 32)],
+        )?;
+        crate::ensure_eq!(filter.permits(Ipv4Addr::new(10, 1, 2, 3)), true);
+        crate::ensure_eq!(filter.permits(Ipv4Addr::new(192, 168, 1, 7)), true);
+        crate::ensure_eq!(filter.permits(Ipv4Addr::new(192, 168, 1, 8)), false);
+        crate::ensure_eq!(filter.permits(Ipv4Addr::new(11, 0, 0, 1)), false);
+
+        Ok(())
+    }
+
+    /// Tests that a deny list lets every address through except the ones of its prefixes, and that a zero-length
+    /// prefix covers all addresses.
+    #[test]
+    fn test_accept_filter_deny() -> Result<()> {
+        let filter: AcceptFilter = AcceptFilter::new(AcceptFilterPolicy::Deny, vec![(Ipv4Addr::new(10, 0, 0, 0), 8)])?;
+        crate::ensure_eq!(filter.permits(Ipv4Addr::new(10, 1, 2, 3)), false);
+        crate::ensure_eq!(filter.permits(Ipv4Addr::new(192, 168, 1, 1)), true);
+
+        let filter: AcceptFilter = AcceptFilter::new(AcceptFilterPolicy::Deny, vec![(Ipv4Addr::UNSPECIFIED, 0)])?;
+        crate::ensure_eq!(filter.permits(Ipv4Addr::new(192, 168, 1, 1)), false);
+
+        Ok(())
+    }
+
+    /// Tests that a prefix that is longer than an IPv4 address is rejected.
+    #[test]
+    fn test_accept_filter_invalid_prefix() -> Result<()> {
+        match AcceptFilter::new(AcceptFilterPolicy::Allow, vec![(Ipv4Addr::new(10, 0, 0, 0), 33)]) {
+            Err(e) if e.errno == libc::EINVAL => Ok(()),
+            result => anyhow::bail!("creating the filter should have failed with EINVAL: {:?}", result),
+        }
+    }
+}
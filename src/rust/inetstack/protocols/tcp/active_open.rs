@@ -29,6 +29,10 @@ use crate::{
                 TcpOptions2,
                 TcpSegment,
             },
+            trace::{
+                SharedTcpTracer,
+                TcpSegmentDirection,
+            },
             SeqNumber,
         },
     },
@@ -61,6 +65,9 @@ use ::std::{
     },
 };
 
+#[cfg(feature = "tcp-md5")]
+use crate::inetstack::protocols::tcp::TcpMd5Key;
+
 //======================================================================================================================
 // Structures
 //======================================================================================================================
@@ -75,7 +82,10 @@ pub struct ActiveOpenSocket {
     local_link_addr: MacAddress,
     tcp_config: TcpConfig,
     arp: SharedArpPeer,
+    tracer: SharedTcpTracer,
     dead_socket_tx: mpsc::UnboundedSender<QDesc>,
+    #[cfg(feature = "tcp-md5")]
+    md5_key: Option<TcpMd5Key>,
 }
 
 #[derive(Clone)]
@@ -96,6 +106,7 @@ impl SharedActiveOpenSocket {
         tcp_config: TcpConfig,
         local_link_addr: MacAddress,
         arp: SharedArpPeer,
+        tracer: SharedTcpTracer,
         dead_socket_tx: mpsc::UnboundedSender<QDesc>,
     ) -> Result<Self, Fail> {
         // TODO: Add fast path here when remote is already in the ARP cache (and subtract one retry).
@@ -110,10 +121,20 @@ impl SharedActiveOpenSocket {
             local_link_addr,
             tcp_config,
             arp,
+            tracer,
             dead_socket_tx,
+            #[cfg(feature = "tcp-md5")]
+            md5_key: None,
         })))
     }
 
+    /// Sets the key that the segments of the handshake are signed with, and that the connection inherits once it is
+    /// established.
+    #[cfg(feature = "tcp-md5")]
+    pub fn set_md5_key(&mut self, key: Option<TcpMd5Key>) {
+        self.md5_key = key;
+    }
+
     fn process_ack(&mut self, header: TcpHeader) -> Result<EstablishedSocket, Fail> {
         let expected_seq: SeqNumber = self.local_isn + SeqNumber::from(1);
 
@@ -155,6 +176,10 @@ impl SharedActiveOpenSocket {
         tcp_hdr.ack_num = remote_seq_num;
         tcp_hdr.window_size = self.tcp_config.get_receive_window_size();
         tcp_hdr.seq_num = self.local_isn + SeqNumber::from(1);
+        #[cfg(feature = "tcp-md5")]
+        if let Some(key) = self.md5_key.as_ref() {
+            tcp_hdr.sign_md5(key.clone());
+        }
         debug!("Sending ACK: {:?}", tcp_hdr);
 
         let segment = TcpSegment {
@@ -164,23 +189,18 @@ impl SharedActiveOpenSocket {
             data: None,
             tx_checksum_offload: self.tcp_config.get_rx_checksum_offload(),
         };
+        self.tracer.trace(TcpSegmentDirection::Sent, &segment.tcp_hdr, 0);
         self.transport.transmit(Box::new(segment));
 
         let mut remote_window_scale = None;
-        let mut mss = FALLBACK_MSS;
         for option in header.iter_options() {
-            match option {
-                TcpOptions2::WindowScale(w) => {
-                    info!("Received window scale: {}", w);
-                    remote_window_scale = Some(*w);
-                },
-                TcpOptions2::MaximumSegmentSize(m) => {
-                    info!("Received advertised MSS: {}", m);
-                    mss = *m as usize;
-                },
-                _ => continue,
+            if let TcpOptions2::WindowScale(w) = option {
+                info!("Received window scale: {}", w);
+                remote_window_scale = Some(*w);
             }
         }
+        let mss: usize = header.get_mss().unwrap_or(FALLBACK_MSS);
+        info!("Received advertised MSS: {}", mss);
 
         let (local_window_scale, remote_window_scale) = match remote_window_scale {
             Some(w) => (self.tcp_config.get_window_scale() as u32, w),
@@ -207,7 +227,8 @@ impl SharedActiveOpenSocket {
             "Window scale: local {}, remote {}",
             local_window_scale, remote_window_scale
         );
-        Ok(EstablishedSocket::new(
+        #[allow(unused_mut)]
+        let mut socket: EstablishedSocket = EstablishedSocket::new(
             self.local,
             self.remote,
             self.runtime.clone(),
@@ -216,6 +237,7 @@ impl SharedActiveOpenSocket {
             self.local_link_addr,
             self.tcp_config.clone(),
             self.arp.clone(),
+            self.tracer.clone(),
             remote_seq_num,
             self.tcp_config.get_ack_delay_timeout(),
             rx_window_size,
@@ -227,14 +249,18 @@ impl SharedActiveOpenSocket {
             congestion_control::None::new,
             None,
             self.dead_socket_tx.clone(),
-        )?)
+        )?;
+        #[cfg(feature = "tcp-md5")]
+        socket.set_md5_key(self.md5_key.clone());
+        Ok(socket)
     }
 
     pub async fn connect(mut self, yielder: Yielder) -> Result<EstablishedSocket, Fail> {
-        // Start connection handshake.
-        let handshake_retries: usize = self.tcp_config.get_handshake_retries();
+        // Start connection handshake. We send the initial SYN plus up to `syn_retries` retransmissions, each spaced
+        // by a fixed handshake timeout.
+        let syn_retries: usize = self.tcp_config.get_syn_retries();
         let handshake_timeout = self.tcp_config.get_handshake_timeout();
-        for _ in 0..handshake_retries {
+        for _ in 0..(syn_retries + 1) {
             // Look up remote MAC address.
             // TODO: Do we need to do this every iteration?
             let remote_link_addr = match self.clone().arp.query(self.remote.ip().clone(), &yielder).await {
@@ -249,7 +275,7 @@ impl SharedActiveOpenSocket {
             let mut tcp_hdr = TcpHeader::new(self.local.port(), self.remote.port());
             tcp_hdr.syn = true;
             tcp_hdr.seq_num = self.local_isn;
-            tcp_hdr.window_size = self.tcp_config.get_receive_window_size();
+            tcp_hdr.window_size = self.tcp_config.get_initial_receive_window();
 
             let mss = self.tcp_config.get_advertised_mss() as u16;
             tcp_hdr.push_option(TcpOptions2::MaximumSegmentSize(mss));
@@ -258,6 +284,11 @@ impl SharedActiveOpenSocket {
             tcp_hdr.push_option(TcpOptions2::WindowScale(self.tcp_config.get_window_scale()));
             info!("Advertising window scale: {}", self.tcp_config.get_window_scale());
 
+            #[cfg(feature = "tcp-md5")]
+            if let Some(key) = self.md5_key.as_ref() {
+                tcp_hdr.sign_md5(key.clone());
+            }
+
             debug!("Sending SYN {:?}", tcp_hdr);
             let segment = TcpSegment {
                 ethernet2_hdr: Ethernet2Header::new(remote_link_addr, self.local_link_addr, EtherType2::Ipv4),
@@ -267,10 +298,11 @@ impl SharedActiveOpenSocket {
                 tx_checksum_offload: self.tcp_config.get_rx_checksum_offload(),
             };
             // Send SYN.
+            self.tracer.trace(TcpSegmentDirection::Sent, &segment.tcp_hdr, 0);
             self.transport.transmit(Box::new(segment));
 
-            // Wait for either a response or timeout.
-            let yielder2: Yielder = Yielder::new();
+            // Wait for either a response or timeout. The deadline of the coroutine, if any, cuts the wait short.
+            let yielder2: Yielder = yielder.new_child();
             let timeout_future = self.runtime.get_timer().wait(handshake_timeout, &yielder2).fuse();
             let mut me: Self = self.clone();
             let ack_future = me.recv_queue.pop(&yielder).fuse();
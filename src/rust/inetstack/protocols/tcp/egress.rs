@@ -0,0 +1,188 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::runtime::{
+    fail::Fail,
+    memory::DemiBuffer,
+    network::{
+        consts::RECEIVE_BATCH_SIZE,
+        NetworkRuntime,
+        PacketBuf,
+    },
+    scheduler::TaskHandle,
+    SharedBox,
+    SharedDemiRuntime,
+    SharedObject,
+};
+use ::arrayvec::ArrayVec;
+use ::std::{
+    cmp::Reverse,
+    net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    },
+    ops::{
+        Deref,
+        DerefMut,
+    },
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Packet buffer that tags the underlying packet with the transmit priority of the socket that sent it.
+pub struct PrioritizedPacket {
+    pkt: Box<dyn PacketBuf>,
+    priority: u8,
+}
+
+/// Send scheduler shared by all TCP sockets of a peer. Segments of the default priority are handed to the underlying
+/// transport right away. Segments of a higher priority, and any segment sent while others are held, are held until the
+/// next scheduler quantum and then flushed highest priority first, so that urgent flows are not queued behind bulk
+/// transfers. Segments of the same priority keep the order in which they were sent. Once the held segments are flushed,
+/// segments of the default priority go straight to the transport again.
+pub struct TcpEgress {
+    runtime: SharedDemiRuntime,
+    transport: SharedBox<dyn NetworkRuntime>,
+    /// Segments that are waiting for the next flush.
+    queued: Vec<Box<dyn PacketBuf>>,
+    /// Coroutine that flushes the queued segments, if one was ever scheduled.
+    flush: Option<TaskHandle>,
+}
+
+#[derive(Clone)]
+pub struct SharedTcpEgress(SharedObject<TcpEgress>);
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl PrioritizedPacket {
+    pub fn new(pkt: Box<dyn PacketBuf>, priority: u8) -> Self {
+        Self { pkt, priority }
+    }
+}
+
+impl SharedTcpEgress {
+    pub fn new(runtime: SharedDemiRuntime, transport: SharedBox<dyn NetworkRuntime>) -> Self {
+        Self(SharedObject::<TcpEgress>::new(TcpEgress {
+            runtime,
+            transport,
+            queued: Vec::new(),
+            flush: None,
+        }))
+    }
+
+    /// Transmits all queued segments, highest priority first.
+    async fn flush(mut self) {
+        let mut queued: Vec<Box<dyn PacketBuf>> = self.queued.drain(..).collect();
+        // Sorting is stable, so segments of the same connection are not reordered.
+        queued.sort_by_key(|pkt| Reverse(pkt.tx_priority()));
+        for pkt in queued {
+            self.transport.transmit(pkt);
+        }
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl PacketBuf for PrioritizedPacket {
+    fn header_size(&self) -> usize {
+        self.pkt.header_size()
+    }
+
+    fn write_header(&self, buf: &mut [u8]) {
+        self.pkt.write_header(buf)
+    }
+
+    fn body_size(&self) -> usize {
+        self.pkt.body_size()
+    }
+
+    fn take_body(&self) -> Option<DemiBuffer> {
+        self.pkt.take_body()
+    }
+
+    fn ipv4_dst_addr(&self) -> Option<Ipv4Addr> {
+        self.pkt.ipv4_dst_addr()
+    }
+
+    fn tx_priority(&self) -> u8 {
+        self.priority
+    }
+}
+
+impl NetworkRuntime for SharedTcpEgress {
+    fn transmit(&mut self, pkt: Box<dyn PacketBuf>) {
+        // Segments of the default priority may not overtake the ones that are already held.
+        if pkt.tx_priority() == 0 && self.queued.is_empty() {
+            self.transport.transmit(pkt);
+            return;
+        }
+
+        self.queued.push(pkt);
+        if let Some(handle) = self.flush.as_ref() {
+            if !handle.has_completed() {
+                return;
+            }
+        }
+
+        // Reclaim the coroutine of the previous flush before scheduling the next one.
+        if let Some(handle) = self.flush.take() {
+            if let Err(e) = self.runtime.remove_background_coroutine(&handle) {
+                warn!("transmit(): cannot remove flush coroutine: {:?}", e);
+            }
+        }
+        let flush = self.clone().flush();
+        match self
+            .runtime
+            .insert_background_coroutine("Inetstack::TCP::egress::flush", Box::pin(flush))
+        {
+            Ok(handle) => self.flush = Some(handle),
+            Err(e) => {
+                // Fall back to transmitting the queued segments in order.
+                warn!("transmit(): cannot schedule flush: {:?}", e);
+                for pkt in self.queued.drain(..).collect::<Vec<Box<dyn PacketBuf>>>() {
+                    self.transport.transmit(pkt);
+                }
+            },
+        }
+    }
+
+    fn receive(&mut self) -> ArrayVec<DemiBuffer, RECEIVE_BATCH_SIZE> {
+        self.transport.receive()
+    }
+
+    fn register_flow(&mut self, local: SocketAddrV4) -> Result<(), Fail> {
+        self.transport.register_flow(local)
+    }
+
+    fn unregister_flow(&mut self, local: SocketAddrV4) -> Result<(), Fail> {
+        self.transport.unregister_flow(local)
+    }
+
+    fn get_ifindex(&self) -> u32 {
+        self.transport.get_ifindex()
+    }
+}
+
+impl Deref for SharedTcpEgress {
+    type Target = TcpEgress;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.deref()
+    }
+}
+
+impl DerefMut for SharedTcpEgress {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0.deref_mut()
+    }
+}
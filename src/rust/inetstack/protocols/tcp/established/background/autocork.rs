@@ -0,0 +1,46 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use crate::{
+    inetstack::protocols::tcp::established::ctrlblk::SharedControlBlock,
+    runtime::{
+        fail::Fail,
+        scheduler::Yielder,
+        timer::SharedTimer,
+        watched::SharedWatchedValue,
+    },
+};
+use ::futures::future::{
+    self,
+    Either,
+    FutureExt,
+};
+use ::std::time::Instant;
+
+/// Flushes the data that autocorking holds back once its coalescing window is over.
+pub async fn autocork_timer(mut cb: SharedControlBlock, yielder: Yielder) -> Result<!, Fail> {
+    loop {
+        let mut cork_deadline_watched: SharedWatchedValue<Option<Instant>> = cb.watch_cork_deadline();
+        let cork_yielder: Yielder = Yielder::new();
+        let cork_deadline: Option<Instant> = cork_deadline_watched.get();
+        let cork_deadline_changed = cork_deadline_watched.watch(cork_yielder).fuse();
+        futures::pin_mut!(cork_deadline_changed);
+
+        let clock_ref: SharedTimer = cb.get_timer();
+        let cork_future = match cork_deadline {
+            Some(t) => Either::Left(clock_ref.wait_until(t, &yielder).fuse()),
+            None => Either::Right(future::pending()),
+        };
+        futures::pin_mut!(cork_future);
+
+        futures::select_biased! {
+            _ = cork_deadline_changed => continue,
+            _ = cork_future => {
+                match cb.get_cork_deadline() {
+                    Some(deadline) if deadline <= cb.get_now() => cb.flush()?,
+                    _ => continue,
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,51 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use crate::{
+    inetstack::protocols::tcp::established::ctrlblk::SharedControlBlock,
+    runtime::{
+        fail::Fail,
+        scheduler::Yielder,
+        timer::SharedTimer,
+        watched::SharedWatchedValue,
+    },
+};
+use ::futures::future::{
+    self,
+    Either,
+    FutureExt,
+};
+use ::std::time::{
+    Duration,
+    Instant,
+};
+
+/// Closes the connection once no data has been sent or received for its idle timeout. Unlike a keepalive, which probes
+/// the remote peer to detect a dead connection, this gracefully closes a connection that is alive but unused.
+pub async fn idle_timer(mut cb: SharedControlBlock, yielder: Yielder) -> Result<!, Fail> {
+    loop {
+        let mut idle_timeout_watched: SharedWatchedValue<Option<Duration>> = cb.watch_idle_timeout();
+        let idle_yielder: Yielder = Yielder::new();
+        let idle_timeout_changed = idle_timeout_watched.watch(idle_yielder).fuse();
+        futures::pin_mut!(idle_timeout_changed);
+
+        let clock_ref: SharedTimer = cb.get_timer();
+        let idle_deadline: Option<Instant> = cb.get_idle_deadline();
+        let idle_future = match idle_deadline {
+            Some(t) => Either::Left(clock_ref.wait_until(t, &yielder).fuse()),
+            None => Either::Right(future::pending()),
+        };
+        futures::pin_mut!(idle_future);
+
+        futures::select_biased! {
+            _ = idle_timeout_changed => continue,
+            _ = idle_future => {
+                // Data may have been sent or received since we started waiting, which pushes the deadline back.
+                match cb.get_idle_deadline() {
+                    Some(deadline) if deadline <= cb.get_now() => cb.idle_close(),
+                    _ => continue,
+                }
+            },
+        }
+    }
+}
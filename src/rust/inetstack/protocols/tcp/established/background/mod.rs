@@ -2,11 +2,17 @@
 // Licensed under the MIT license.
 
 mod acknowledger;
+mod autocork;
+mod idle;
+mod reassembly;
 mod retransmitter;
 mod sender;
 
 use self::{
     acknowledger::acknowledger,
+    autocork::autocork_timer,
+    idle::idle_timer,
+    reassembly::reassembly_timer,
     retransmitter::retransmitter,
     sender::sender,
 };
@@ -36,6 +42,18 @@ pub async fn background(cb: SharedControlBlock, _dead_socket_tx: mpsc::Unbounded
     let sender = sender(cb.clone(), yielder_sender).fuse();
     futures::pin_mut!(sender);
 
+    let yielder_idle_timer: Yielder = Yielder::new();
+    let idle_timer = idle_timer(cb.clone(), yielder_idle_timer).fuse();
+    futures::pin_mut!(idle_timer);
+
+    let yielder_reassembly_timer: Yielder = Yielder::new();
+    let reassembly_timer = reassembly_timer(cb.clone(), yielder_reassembly_timer).fuse();
+    futures::pin_mut!(reassembly_timer);
+
+    let yielder_autocork_timer: Yielder = Yielder::new();
+    let autocork_timer = autocork_timer(cb.clone(), yielder_autocork_timer).fuse();
+    futures::pin_mut!(autocork_timer);
+
     let yielder_receiver: Yielder = Yielder::new();
     let mut cb2: SharedControlBlock = cb.clone();
     let receiver = cb2.poll(yielder_receiver).fuse();
@@ -46,6 +64,9 @@ pub async fn background(cb: SharedControlBlock, _dead_socket_tx: mpsc::Unbounded
         r = acknowledger => r,
         r = retransmitter => r,
         r = sender => r,
+        r = idle_timer => r,
+        r = reassembly_timer => r,
+        r = autocork_timer => r,
     };
     error!("Connection terminated: {:?}", r);
 }
@@ -0,0 +1,47 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use crate::{
+    inetstack::protocols::tcp::established::ctrlblk::SharedControlBlock,
+    runtime::{
+        fail::Fail,
+        scheduler::Yielder,
+        timer::SharedTimer,
+        watched::SharedWatchedValue,
+    },
+};
+use ::futures::future::{
+    self,
+    Either,
+    FutureExt,
+};
+use ::std::time::Instant;
+
+/// Resets the connection once out-of-order segments have waited for the reassembly timeout without the hole before
+/// them being filled. This bounds how long a remote peer that went away can make us hold out-of-order data.
+pub async fn reassembly_timer(mut cb: SharedControlBlock, yielder: Yielder) -> Result<!, Fail> {
+    loop {
+        let mut reassembly_deadline_watched: SharedWatchedValue<Option<Instant>> = cb.watch_reassembly_deadline();
+        let reassembly_yielder: Yielder = Yielder::new();
+        let reassembly_deadline: Option<Instant> = reassembly_deadline_watched.get();
+        let reassembly_deadline_changed = reassembly_deadline_watched.watch(reassembly_yielder).fuse();
+        futures::pin_mut!(reassembly_deadline_changed);
+
+        let clock_ref: SharedTimer = cb.get_timer();
+        let reassembly_future = match reassembly_deadline {
+            Some(t) => Either::Left(clock_ref.wait_until(t, &yielder).fuse()),
+            None => Either::Right(future::pending()),
+        };
+        futures::pin_mut!(reassembly_future);
+
+        futures::select_biased! {
+            _ = reassembly_deadline_changed => continue,
+            _ = reassembly_future => {
+                match cb.get_reassembly_deadline() {
+                    Some(deadline) if deadline <= cb.get_now() => return Err(cb.reassembly_timeout()),
+                    _ => continue,
+                }
+            },
+        }
+    }
+}
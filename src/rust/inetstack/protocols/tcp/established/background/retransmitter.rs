@@ -2,7 +2,10 @@
 // Licensed under the MIT license.
 
 use crate::{
-    inetstack::protocols::tcp::established::ctrlblk::SharedControlBlock,
+    inetstack::protocols::tcp::{
+        established::ctrlblk::SharedControlBlock,
+        SeqNumber,
+    },
     runtime::{
         fail::Fail,
         scheduler::Yielder,
@@ -20,7 +23,13 @@ use ::std::time::{
     Instant,
 };
 
+/// Retransmits unacknowledged data on timeout, following the schedule of RFC 6298: every retransmission doubles the
+/// RTO (up to 60 seconds), and the RTO is recomputed from fresh samples once new data is acknowledged. After
+/// `data_retries` consecutive timeouts without progress, the connection is aborted with `ETIMEDOUT`.
 pub async fn retransmitter(mut cb: SharedControlBlock, yielder: Yielder) -> Result<!, Fail> {
+    // Number of consecutive timeout retransmissions of the segment at SND.UNA.
+    let mut retries: usize = 0;
+    let mut last_send_unacknowledged: SeqNumber = cb.get_send_unacked().get();
     loop {
         // Pin future for timeout retransmission.
         let mut rtx_deadline_watched: SharedWatchedValue<Option<Instant>> = cb.watch_retransmit_deadline();
@@ -66,6 +75,21 @@ pub async fn retransmitter(mut cb: SharedControlBlock, yielder: Yielder) -> Resu
                 // TODO: Is this the best place for this?
                 // TODO: Why call into ControlBlock to get SND.UNA when congestion_control_on_rto() has access to it?
                 let send_unacknowledged = cb.get_send_unacked();
+
+                // Give up if we have already exhausted our retransmissions without the remote making any progress.
+                if send_unacknowledged.get() != last_send_unacknowledged {
+                    last_send_unacknowledged = send_unacknowledged.get();
+                    retries = 0;
+                }
+                if retries >= cb.get_data_retries() {
+                    let cause: String = format!("connection timed out after {} retransmissions", retries);
+                    error!("retransmitter(): {}", cause);
+                    let fail: Fail = Fail::new(libc::ETIMEDOUT, &cause);
+                    cb.abort(fail.clone());
+                    return Err(fail);
+                }
+                retries += 1;
+
                 cb.congestion_control_on_rto(send_unacknowledged.get());
 
                 // RFC 6298 Section 5.4: Retransmit earliest unacknowledged segment.
@@ -71,10 +71,7 @@ pub async fn sender(mut cb: SharedControlBlock, yielder: Yielder) -> Result<!, F
             cb.modify_send_next(|s| s + SeqNumber::from(1));
 
             // Add the probe byte (as a new separate buffer) to our unacknowledged queue.
-            let unacked_segment = UnackedSegment {
-                bytes: buf.clone(),
-                initial_tx: Some(cb.get_now()),
-            };
+            let unacked_segment = UnackedSegment::new(buf.clone(), cb.get_now());
             cb.push_unacked_segment(unacked_segment);
 
             let mut header: TcpHeader = cb.tcp_header();
@@ -141,9 +138,22 @@ pub async fn sender(mut cb: SharedControlBlock, yielder: Yielder) -> Result<!, F
             }
         }
 
+        // Nagle's algorithm: while data is in flight, less than a full segment of queued data waits for an ACK, or for
+        // enough data to fill a segment, so that small buffers queued in the meantime go out together. The end-of-send
+        // marker does not wait.
+        let unsent_len: usize = u32::from(unsent_seq - send_next) as usize;
+        if !cb.get_nodelay() && sent_data > 0 && next_buf_size > 0 && unsent_len < cb.get_mss() {
+            futures::select_biased! {
+                _ = send_unacked_changed => continue 'top,
+                _ = send_next_changed => continue 'top,
+                _ = unsent_seq_changed => continue 'top,
+            }
+        }
+
         // Past this point we have data to send and it's valid to send it!
 
-        // TODO: Nagle's algorithm - We need to coalese small buffers together to send MSS sized packets.
+        // Small buffers that were queued while data was in flight are coalesced into a single segment of up to
+        // `max_size` bytes below.
         // TODO: Silly window syndrome - See RFC 1122's discussion of the SWS avoidance algorithm.
 
         // TODO: Link-level concerns don't belong here, we should call an IP-level send routine below.
@@ -155,9 +165,11 @@ pub async fn sender(mut cb: SharedControlBlock, yielder: Yielder) -> Result<!, F
             cmp::min((win_sz - sent_data) as usize, cb.get_mss()),
             (effective_cwnd - sent_data) as usize,
         );
-        let (segment_data, do_push): (DemiBuffer, bool) = cb
-            .pop_unsent_segment(max_size)
-            .expect("No unsent data with sequence number gap?");
+        // The data may have been sent by a flush while the link address was being resolved.
+        let (segment_data, do_push): (DemiBuffer, bool) = match cb.pop_unsent_segment(max_size) {
+            Some(segment) => segment,
+            None => continue 'top,
+        };
         let mut segment_data_len: u32 = segment_data.len() as u32;
 
         let rto: Duration = cb.rto();
@@ -181,10 +193,7 @@ pub async fn sender(mut cb: SharedControlBlock, yielder: Yielder) -> Result<!, F
         cb.modify_send_next(|s| s + SeqNumber::from(segment_data_len));
 
         // Put this segment on the unacknowledged list.
-        let unacked_segment = UnackedSegment {
-            bytes: segment_data,
-            initial_tx: Some(cb.get_now()),
-        };
+        let unacked_segment = UnackedSegment::new(segment_data, cb.get_now());
         cb.push_unacked_segment(unacked_segment);
 
         // Set the retransmit timer.
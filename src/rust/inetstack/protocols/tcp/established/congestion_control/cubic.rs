@@ -18,7 +18,10 @@ use super::{
 };
 use crate::{
     inetstack::protocols::tcp::SeqNumber,
-    runtime::watched::SharedWatchedValue,
+    runtime::{
+        timer::SharedTimer,
+        watched::SharedWatchedValue,
+    },
 };
 use ::std::{
     cell::Cell,
@@ -37,6 +40,7 @@ use ::std::{
 #[derive(Debug)]
 pub struct Cubic {
     pub mss: u32, // Just for convenience, otherwise we have `as u32` or `.try_into().unwrap()` scattered everywhere...
+    pub timer: SharedTimer, // The timer of the runtime, which all times are read from.
     // Slow Start / Congestion Avoidance State.
     pub ca_start: Cell<Instant>, // The time we started the current congestion avoidance.
     pub cwnd: SharedWatchedValue<u32>, // Congestion window: Max number of bytes that may be in flight ot prevent congestion.
@@ -48,6 +52,9 @@ pub struct Cubic {
     pub rtt_at_last_send: Cell<Duration>,           // The RTT at the moment we last sent data.
     pub ssthresh: Cell<u32>, // The size of cwnd at which we will change from using slow start to congestion avoidance.
     pub w_max: Cell<u32>,    // The size of cwnd before the previous congestion event.
+    pub abc: bool, // Should cwnd grow with the number of bytes acknowledged rather than the number of ACKs received (RFC 3465).
+    pub abc_limit: u32, // The most that cwnd may grow by on a single ACK during slow start, when counting bytes (L in RFC 3465).
+    pub bytes_acked: Cell<u32>, // Bytes acknowledged during congestion avoidance that have yet to grow cwnd.
 
     // Fast Recovery / Fast Retransmit State
     pub duplicate_ack_count: Cell<u32>, // The number of consecutive duplicate ACKs we've received.
@@ -60,7 +67,7 @@ pub struct Cubic {
 }
 
 impl CongestionControl for Cubic {
-    fn new(mss: usize, seq_no: SeqNumber, options: Option<Options>) -> Box<dyn CongestionControl> {
+    fn new(mss: usize, seq_no: SeqNumber, options: Option<Options>, timer: SharedTimer) -> Box<dyn CongestionControl> {
         let mss: u32 = mss.try_into().unwrap();
         // The initial value of cwnd is set according to RFC5681, section 3.1, page 7.
         let initial_cwnd: u32 = match mss {
@@ -71,19 +78,34 @@ impl CongestionControl for Cubic {
 
         let options: Options = options.unwrap_or_default();
         let fast_convergence: bool = options.get_bool("fast_convergence").unwrap_or(true);
+        let abc: bool = options.get_bool("abc").unwrap_or(true);
+        let abc_limit: u32 = match options.get_int("abc_limit") {
+            Some(limit) if limit > 0 => (limit as u32).saturating_mul(mss),
+            _ => Self::ABC_DEFAULT_LIMIT * mss,
+        };
+        // According to RFC5681 ssthresh should be initialised 'arbitrarily high', unless told otherwise.
+        let ssthresh: u32 = match options.get_int("initial_ssthresh") {
+            Some(ssthresh) if ssthresh > 0 => ssthresh.try_into().unwrap_or(u32::MAX),
+            _ => u32::MAX,
+        };
 
+        let now: Instant = timer.now();
         Box::new(Self {
             mss,
+            timer,
             // Slow Start / Congestion Avoidance State
-            ca_start: Cell::new(Instant::now()), // Record the start time of the congestion avoidance period.
+            ca_start: Cell::new(now), // Record the start time of the congestion avoidance period.
             cwnd: SharedWatchedValue::new(initial_cwnd),
             fast_convergence,
             initial_cwnd,
-            last_send_time: Cell::new(Instant::now()),
+            last_send_time: Cell::new(now),
             retransmitted_packets_in_flight: Cell::new(0),
             rtt_at_last_send: Cell::new(Duration::new(1, 0)), // The default RTT is 1 sec.
-            ssthresh: Cell::new(u32::MAX), // According to RFC5681 ssthresh should be initialised 'arbitrarily high'.
-            w_max: Cell::new(0), // Because ssthresh is u32::MAX, this will be set appropriately during the 1st congestion event.
+            ssthresh: Cell::new(ssthresh),
+            w_max: Cell::new(0), // This will be set appropriately during the 1st congestion event.
+            abc,
+            abc_limit,
+            bytes_acked: Cell::new(0),
             last_congestion_was_rto: Cell::new(false),
 
             in_fast_recovery: Cell::new(false),
@@ -98,6 +120,8 @@ impl CongestionControl for Cubic {
 }
 
 impl Cubic {
+    // Default per-ACK growth limit of slow start, in segments, as recommended by RFC 3465.
+    const ABC_DEFAULT_LIMIT: u32 = 2;
     const BETA_CUBIC: f32 = 0.7;
     // Cubic const parameters.
     const C: f32 = 0.4;
@@ -167,7 +191,7 @@ impl Cubic {
             self.cwnd
                 .set(min(self.ssthresh.get(), max(bytes_outstanding, mss) + mss));
             // Record the time we go back into congestion avoidance.
-            self.ca_start.set(Instant::now());
+            self.ca_start.set(self.timer.now());
             // Record that we didn't enter CA from a timeout.
             self.last_congestion_was_rto.set(false);
             self.in_fast_recovery.set(false);
@@ -213,28 +237,56 @@ impl Cubic {
         let cwnd: u32 = self.cwnd.get();
         let ssthresh: u32 = self.ssthresh.get();
 
+        if cwnd >= ssthresh && self.w_max.get() == 0 {
+            // Slow start ended on the initial ssthresh rather than on a congestion event, so there is no w_max yet.
+            // Start the cubic epoch at the current cwnd, as if it were the reduced window of a congestion event.
+            self.w_max.set((cwnd as f32 / Self::BETA_CUBIC) as u32);
+            self.ca_start.set(self.timer.now());
+        }
+
         if cwnd < ssthresh {
-            // Slow start.
-            self.cwnd.modify(|c| c + min(bytes_acknowledged, mss));
-        } else {
-            // Congestion avoidance.
-            let t: f32 = self.ca_start.get().elapsed().as_secs_f32();
-            let rtt: f32 = rto.as_secs_f32();
-            let mss_f32: f32 = mss as f32;
-            let normalised_w_max: f32 = self.w_max.get() as f32 / mss_f32;
-            let k: f32 = self.k(normalised_w_max);
-            let w_est: f32 = self.w_est(normalised_w_max, t, rtt);
-            if self.w_cubic(normalised_w_max, t, k) < w_est {
-                // w_est return units of MSS which we multiply back up to get bytes.
-                self.cwnd.set((w_est * mss_f32) as u32);
+            // Slow start. When counting bytes, growth is bounded by L, which falls back to a single segment after a
+            // timeout, as RFC 3465 requires.
+            let limit: u32 = if self.abc && !self.last_congestion_was_rto.get() {
+                self.abc_limit
             } else {
-                let cwnd_f32: f32 = cwnd as f32;
-                // Again, do everything in terms of units of MSS.
-                let normalised_cwnd: f32 = cwnd_f32 / mss_f32;
-                let cwnd_inc: f32 =
-                    ((self.w_cubic(normalised_w_max, t + rtt, k) - normalised_cwnd) / normalised_cwnd) * mss_f32;
-                self.cwnd.modify(|c| c + cwnd_inc as u32);
+                mss
+            };
+            self.cwnd.modify(|c| c + min(bytes_acknowledged, limit));
+        } else if self.abc {
+            // Congestion avoidance. Grow cwnd once per segment worth of acknowledged data, so that an ACK that covers
+            // several segments counts for all of them, and splitting ACKs does not speed growth up.
+            let bytes_acked: u32 = self.bytes_acked.get() + bytes_acknowledged;
+            self.bytes_acked.set(bytes_acked % mss);
+            for _ in 0..(bytes_acked / mss) {
+                self.on_segment_acked_ca(rto);
             }
+        } else {
+            // Congestion avoidance.
+            self.on_segment_acked_ca(rto);
+        }
+    }
+
+    // Grows cwnd in congestion avoidance, as the acknowledgement of a single segment does in RFC 8312.
+    fn on_segment_acked_ca(&mut self, rto: Duration) {
+        let mss: u32 = self.mss;
+        let cwnd: u32 = self.cwnd.get();
+        let t: f32 = self.timer.now().duration_since(self.ca_start.get()).as_secs_f32();
+        let rtt: f32 = rto.as_secs_f32();
+        let mss_f32: f32 = mss as f32;
+        let normalised_w_max: f32 = self.w_max.get() as f32 / mss_f32;
+        let k: f32 = self.k(normalised_w_max);
+        let w_est: f32 = self.w_est(normalised_w_max, t, rtt);
+        if self.w_cubic(normalised_w_max, t, k) < w_est {
+            // w_est return units of MSS which we multiply back up to get bytes.
+            self.cwnd.set((w_est * mss_f32) as u32);
+        } else {
+            let cwnd_f32: f32 = cwnd as f32;
+            // Again, do everything in terms of units of MSS.
+            let normalised_cwnd: f32 = cwnd_f32 / mss_f32;
+            let cwnd_inc: f32 =
+                ((self.w_cubic(normalised_w_max, t + rtt, k) - normalised_cwnd) / normalised_cwnd) * mss_f32;
+            self.cwnd.modify(|c| c + cwnd_inc as u32);
         }
     }
 
@@ -247,6 +299,7 @@ impl Cubic {
             self.w_max.set(cwnd);
         }
         self.cwnd.set(self.mss);
+        self.bytes_acked.set(0);
 
         let rpif: u32 = self.retransmitted_packets_in_flight.get();
         if rpif == 0 {
@@ -276,9 +329,13 @@ impl SlowStartCongestionAvoidance for Cubic {
         self.cwnd.clone()
     }
 
+    fn get_ssthresh(&self) -> u32 {
+        self.ssthresh.get()
+    }
+
     fn on_cwnd_check_before_send(&mut self) {
         let long_time_since_send: bool =
-            Instant::now().duration_since(self.last_send_time.get()) > self.rtt_at_last_send.get();
+            self.timer.now().duration_since(self.last_send_time.get()) > self.rtt_at_last_send.get();
         if long_time_since_send {
             let restart_window: u32 = min(self.initial_cwnd, self.cwnd.get());
             self.cwnd.set(restart_window);
@@ -287,7 +344,7 @@ impl SlowStartCongestionAvoidance for Cubic {
     }
 
     fn on_send(&mut self, rto: Duration, num_bytes_sent: u32) {
-        self.last_send_time.set(Instant::now());
+        self.last_send_time.set(self.timer.now());
         self.rtt_at_last_send.set(rto);
         self.limited_transmit_cwnd_increase
             .set_without_notify(self.limited_transmit_cwnd_increase.get().saturating_sub(num_bytes_sent));
@@ -345,3 +402,182 @@ impl LimitedTransmit for Cubic {
         self.limited_transmit_cwnd_increase.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CongestionControl,
+        Cubic,
+        Options,
+    };
+    use crate::{
+        inetstack::protocols::tcp::SeqNumber,
+        runtime::timer::SharedTimer,
+    };
+    use ::anyhow::Result;
+    use ::std::time::{
+        Duration,
+        Instant,
+    };
+
+    /// Maximum segment size used by the tests. It yields an initial cwnd of four segments.
+    const MSS: u32 = 1000;
+
+    /// Retransmission timeout used by the tests.
+    const RTO: Duration = Duration::from_secs(1);
+
+    /// Builds a congestion controller that counts bytes if `abc` is set, and bounds slow start growth to `abc_limit`
+    /// segments, if given.
+    fn new_cubic(abc: bool, abc_limit: Option<i64>) -> (Box<dyn CongestionControl>, SeqNumber) {
+        let mut options: Options = Options::default();
+        options.insert_bool("abc".to_string(), abc);
+        if let Some(abc_limit) = abc_limit {
+            options.insert_int("abc_limit".to_string(), abc_limit);
+        }
+        let seq_no: SeqNumber = SeqNumber::from(1);
+        (
+            Cubic::new(MSS as usize, seq_no, Some(options), SharedTimer::default()),
+            seq_no,
+        )
+    }
+
+    /// Builds a congestion controller that starts with an initial ssthresh of `initial_ssthresh` bytes.
+    fn new_cubic_with_ssthresh(initial_ssthresh: i64) -> (Box<dyn CongestionControl>, SeqNumber) {
+        let mut options: Options = Options::default();
+        options.insert_int("initial_ssthresh".to_string(), initial_ssthresh);
+        let seq_no: SeqNumber = SeqNumber::from(1);
+        (
+            Cubic::new(MSS as usize, seq_no, Some(options), SharedTimer::default()),
+            seq_no,
+        )
+    }
+
+    /// Acknowledges `nbytes` bytes past `send_unacked`, and advances the latter.
+    fn ack(cc: &mut Box<dyn CongestionControl>, send_unacked: &mut SeqNumber, nbytes: u32) {
+        let send_next: SeqNumber = *send_unacked + SeqNumber::from(64 * MSS);
+        let ack_seq_no: SeqNumber = *send_unacked + SeqNumber::from(nbytes);
+        cc.on_ack_received(RTO, *send_unacked, send_next, ack_seq_no);
+        *send_unacked = ack_seq_no;
+    }
+
+    /// Times out, and slow starts back up to the reduced ssthresh, so that the next ACK is handled by congestion
+    /// avoidance.
+    fn enter_congestion_avoidance(cc: &mut Box<dyn CongestionControl>, send_unacked: &mut SeqNumber) {
+        cc.on_rto(*send_unacked);
+        ack(cc, send_unacked, MSS);
+        ack(cc, send_unacked, MSS);
+    }
+
+    /// Tests that splitting the acknowledgement of a segment into many small ACKs does not grow cwnd by more than the
+    /// number of bytes acknowledged during slow start.
+    #[test]
+    fn test_abc_slow_start_small_acks() -> Result<()> {
+        for abc in [true, false] {
+            let (mut cc, mut send_unacked) = new_cubic(abc, None);
+            let initial_cwnd: u32 = cc.get_cwnd().get();
+            for _ in 0..40 {
+                ack(&mut cc, &mut send_unacked, MSS / 40);
+            }
+            crate::ensure_eq!(cc.get_cwnd().get(), initial_cwnd + MSS);
+        }
+
+        Ok(())
+    }
+
+    /// Tests that an ACK that covers several segments grows cwnd by at most L during slow start.
+    #[test]
+    fn test_abc_slow_start_limit() -> Result<()> {
+        for (abc, abc_limit, growth) in [(true, None, 2 * MSS), (true, Some(3), 3 * MSS), (false, None, MSS)] {
+            let (mut cc, mut send_unacked) = new_cubic(abc, abc_limit);
+            let initial_cwnd: u32 = cc.get_cwnd().get();
+            ack(&mut cc, &mut send_unacked, 4 * MSS);
+            crate::ensure_eq!(cc.get_cwnd().get(), initial_cwnd + growth);
+        }
+
+        Ok(())
+    }
+
+    /// Tests that many small ACKs grow cwnd no faster than a single ACK for the same bytes during congestion avoidance,
+    /// unless byte counting is disabled.
+    #[test]
+    fn test_abc_congestion_avoidance_small_acks() -> Result<()> {
+        // The reference is built first, so that it is at least as far along the cubic curve as the others are.
+        let (mut single, mut single_unacked) = new_cubic(true, None);
+        let (mut split, mut split_unacked) = new_cubic(true, None);
+        let (mut legacy, mut legacy_unacked) = new_cubic(false, None);
+        enter_congestion_avoidance(&mut single, &mut single_unacked);
+        enter_congestion_avoidance(&mut split, &mut split_unacked);
+        enter_congestion_avoidance(&mut legacy, &mut legacy_unacked);
+        let split_start: u32 = split.get_cwnd().get();
+        let legacy_start: u32 = legacy.get_cwnd().get();
+        let single_start: u32 = single.get_cwnd().get();
+
+        // Less than a segment worth of data does not grow cwnd at all.
+        for _ in 0..9 {
+            ack(&mut split, &mut split_unacked, MSS / 10);
+        }
+        crate::ensure_eq!(split.get_cwnd().get(), split_start);
+        ack(&mut split, &mut split_unacked, MSS / 10);
+        for _ in 0..10 {
+            ack(&mut legacy, &mut legacy_unacked, MSS / 10);
+        }
+        ack(&mut single, &mut single_unacked, MSS);
+
+        let split_growth: u32 = split.get_cwnd().get() - split_start;
+        let legacy_growth: u32 = legacy.get_cwnd().get() - legacy_start;
+        let single_growth: u32 = single.get_cwnd().get() - single_start;
+        crate::ensure_eq!(single_growth > 0, true);
+        crate::ensure_eq!(split_growth > 0, true);
+        crate::ensure_eq!(split_growth <= single_growth, true);
+        crate::ensure_eq!(legacy_growth > single_growth, true);
+
+        Ok(())
+    }
+
+    /// Tests that a low initial ssthresh makes the connection leave slow start as soon as cwnd reaches it, whereas the
+    /// default one keeps it in slow start.
+    #[test]
+    fn test_initial_ssthresh() -> Result<()> {
+        let (mut default, mut default_unacked) = new_cubic(true, None);
+        let (mut low, mut low_unacked) = new_cubic_with_ssthresh((5 * MSS) as i64);
+        let initial_cwnd: u32 = low.get_cwnd().get();
+        crate::ensure_eq!(default.get_cwnd().get(), initial_cwnd);
+
+        // Both slow start up to the low ssthresh.
+        ack(&mut default, &mut default_unacked, MSS);
+        ack(&mut low, &mut low_unacked, MSS);
+        crate::ensure_eq!(default.get_cwnd().get(), initial_cwnd + MSS);
+        crate::ensure_eq!(low.get_cwnd().get(), initial_cwnd + MSS);
+
+        // Past it, only the default one keeps growing by a segment per segment acknowledged.
+        ack(&mut default, &mut default_unacked, MSS);
+        ack(&mut low, &mut low_unacked, MSS);
+        crate::ensure_eq!(default.get_cwnd().get(), initial_cwnd + 2 * MSS);
+        crate::ensure_eq!(low.get_cwnd().get() < initial_cwnd + 2 * MSS, true);
+
+        Ok(())
+    }
+
+    /// Tests that cwnd restarts from the initial window once the connection has been idle for longer than the RTT, as
+    /// measured by the timer of the runtime rather than by the wall clock.
+    #[test]
+    fn test_restart_after_idle_follows_timer() -> Result<()> {
+        let mut timer: SharedTimer = SharedTimer::new(Instant::now());
+        let mut send_unacked: SeqNumber = SeqNumber::from(1);
+        let mut cc: Box<dyn CongestionControl> = Cubic::new(MSS as usize, send_unacked, None, timer.clone());
+        let initial_cwnd: u32 = cc.get_cwnd().get();
+        ack(&mut cc, &mut send_unacked, MSS);
+        cc.on_send(RTO, MSS);
+
+        // However long the test takes, no time passes for the congestion controller until the timer moves.
+        cc.on_cwnd_check_before_send();
+        crate::ensure_eq!(cc.get_cwnd().get(), initial_cwnd + MSS);
+
+        let now: Instant = timer.now();
+        timer.advance_clock(now + 2 * RTO);
+        cc.on_cwnd_check_before_send();
+        crate::ensure_eq!(cc.get_cwnd().get(), initial_cwnd);
+
+        Ok(())
+    }
+}
@@ -7,7 +7,10 @@ mod options;
 
 use crate::{
     inetstack::protocols::tcp::SeqNumber,
-    runtime::watched::SharedWatchedValue,
+    runtime::{
+        timer::SharedTimer,
+        watched::SharedWatchedValue,
+    },
 };
 use ::std::{
     fmt::Debug,
@@ -26,6 +29,11 @@ pub use self::{
 pub trait SlowStartCongestionAvoidance {
     fn get_cwnd(&self) -> SharedWatchedValue<u32>;
 
+    // Slow start threshold, which is unbounded for algorithms that do not have one.
+    fn get_ssthresh(&self) -> u32 {
+        u32::MAX
+    }
+
     // Called immediately before the cwnd check is performed before data is sent.
     fn on_cwnd_check_before_send(&mut self) {}
 
@@ -66,9 +74,16 @@ where
 }
 
 pub trait CongestionControl: SlowStartCongestionAvoidance + FastRetransmitRecovery + LimitedTransmit + Debug {
-    fn new(mss: usize, seq_no: SeqNumber, options: Option<options::Options>) -> Box<dyn CongestionControl>
+    // The congestion controller reads the time from `timer`, so that it follows the clock of the runtime.
+    fn new(
+        mss: usize,
+        seq_no: SeqNumber,
+        options: Option<options::Options>,
+        timer: SharedTimer,
+    ) -> Box<dyn CongestionControl>
     where
         Self: Sized;
 }
 
-pub type CongestionControlConstructor = fn(usize, SeqNumber, Option<options::Options>) -> Box<dyn CongestionControl>;
+pub type CongestionControlConstructor =
+    fn(usize, SeqNumber, Option<options::Options>, SharedTimer) -> Box<dyn CongestionControl>;
@@ -10,7 +10,10 @@ use super::{
 };
 use crate::{
     inetstack::protocols::tcp::SeqNumber,
-    runtime::watched::SharedWatchedValue,
+    runtime::{
+        timer::SharedTimer,
+        watched::SharedWatchedValue,
+    },
 };
 use ::std::fmt::Debug;
 
@@ -23,7 +26,12 @@ pub struct None {
 }
 
 impl CongestionControl for None {
-    fn new(_mss: usize, _seq_no: SeqNumber, _options: Option<Options>) -> Box<dyn CongestionControl> {
+    fn new(
+        _mss: usize,
+        _seq_no: SeqNumber,
+        _options: Option<Options>,
+        _timer: SharedTimer,
+    ) -> Box<dyn CongestionControl> {
         Box::new(Self {
             cwnd: SharedWatchedValue::new(u32::MAX),
             fast_retransmit_flag: SharedWatchedValue::new(false),
@@ -11,6 +11,8 @@ use super::{
         Sender,
         UnackedSegment,
     },
+    NegotiatedOptions,
+    TcpInfo,
 };
 use crate::{
     collections::async_queue::{
@@ -24,21 +26,39 @@ use crate::{
             Ethernet2Header,
         },
         ip::IpProtocol,
-        ipv4::Ipv4Header,
+        ipv4::{
+            Ipv4Header,
+            IPV4_HEADER_MIN_SIZE,
+        },
         tcp::{
+            constants::MIN_MSS,
+            egress::PrioritizedPacket,
             segment::{
                 TcpHeader,
                 TcpSegment,
+                MIN_TCP_HEADER_SIZE,
+            },
+            trace::{
+                SharedTcpTracer,
+                TcpSegmentDirection,
             },
             SeqNumber,
         },
     },
     runtime::{
         fail::Fail,
-        memory::DemiBuffer,
+        memory::{
+            DemiBuffer,
+            SharedBufferPool,
+        },
         network::{
             config::TcpConfig,
-            types::MacAddress,
+            types::{
+                BufferUsage,
+                ErrorCounters,
+                MacAddress,
+                Readiness,
+            },
             NetworkRuntime,
         },
         scheduler::Yielder,
@@ -49,7 +69,12 @@ use crate::{
         SharedObject,
     },
 };
+use ::futures::{
+    future::FutureExt,
+    select_biased,
+};
 use ::std::{
+    cmp,
     collections::VecDeque,
     convert::TryInto,
     net::SocketAddrV4,
@@ -63,17 +88,18 @@ use ::std::{
     },
 };
 
+#[cfg(any(test, feature = "tcp-diagnostics"))]
+use super::UnackedSegmentInfo;
+
+#[cfg(feature = "tcp-md5")]
+use crate::inetstack::protocols::tcp::TcpMd5Key;
+
 // TODO: Review this value (and its purpose).  It (2048 segments) of 8 KB jumbo packets would limit the unread data to
 // just 16 MB.  If we don't want to lie, that is also about the max window size we should ever advertise.  Whereas TCP
 // with the window scale option allows for window sizes of up to 1 GB.  This value appears to exist more because of the
 // mechanism used to manage the receive queue (a VecDeque) than anything else.
 const RECV_QUEUE_SZ: usize = 2048;
 
-// TODO: Review this value (and its purpose).  It (16 segments) seems awfully small (would make fast retransmit less
-// useful), and this mechanism isn't the best way to protect ourselves against deliberate out-of-order segment attacks.
-// Ideally, we'd limit out-of-order data to that which (along with the unread data) will fit in the receive window.
-const MAX_OUT_OF_ORDER: usize = 16;
-
 // TCP Connection State.
 // Note: This ControlBlock structure is only used after we've reached the ESTABLISHED state, so states LISTEN,
 // SYN_RCVD, and SYN_SENT aren't included here.
@@ -113,29 +139,176 @@ struct Receiver {
 
     // Receive queue.  Contains in-order received (and acknowledged) data ready for the application to read.
     recv_queue: AsyncQueue<DemiBuffer>,
+
+    // Number of bytes that a pop waits for before it completes (i.e. SO_RCVLOWAT).
+    low_watermark: usize,
+
+    // Sequence numbers that follow the data of segments that had PSH set, and that the reader has yet to reach.
+    push_points: VecDeque<SeqNumber>,
+
+    // Pool of buffers that the data of several segments is joined into when a single pop hands it out.
+    buffer_pool: SharedBufferPool,
 }
 
 impl Receiver {
-    pub fn new(reader_next: SeqNumber, receive_next: SeqNumber) -> Self {
+    pub fn new(reader_next: SeqNumber, receive_next: SeqNumber, buffer_pool: SharedBufferPool) -> Self {
         Self {
             reader_next,
             receive_next,
             recv_queue: AsyncQueue::with_capacity(RECV_QUEUE_SZ),
+            low_watermark: 1,
+            push_points: VecDeque::new(),
+            buffer_pool,
         }
     }
 
+    /// Waits for at least as many bytes as the low watermark, or for the end of data that the remote peer pushed (i.e.
+    /// sent with PSH), whichever comes first, and pops up to `size` of them. Pushed data overrides the low watermark,
+    /// so that messages that are shorter than it are delivered promptly. A pop for fewer bytes than the low watermark
+    /// waits for that many bytes only. The end of the stream and errors complete the pop with the data received
+    /// before them, if any.
     pub async fn pop(&mut self, size: Option<usize>, yielder: Yielder) -> Result<DemiBuffer, Fail> {
-        let buf: DemiBuffer = if let Some(size) = size {
-            let mut buf: DemiBuffer = self.recv_queue.pop(&yielder).await?;
-            // Split the buffer if it's too big.
+        self.pop_at_least(self.low_watermark, size, yielder).await
+    }
+
+    /// Like [pop](Self::pop), but waits for `low_watermark` bytes instead of the current low watermark.
+    pub async fn pop_at_least(
+        &mut self,
+        low_watermark: usize,
+        size: Option<usize>,
+        yielder: Yielder,
+    ) -> Result<DemiBuffer, Fail> {
+        let low_watermark: usize = cmp::min(low_watermark, size.unwrap_or(usize::MAX));
+        // Received data stays queued while waiting, so that nothing is lost if the pop is abandoned.
+        while !self.is_ready(low_watermark) {
+            match self.recv_queue.wait(&yielder).await {
+                Ok(()) => (),
+                Err(e) if self.recv_queue.is_empty() => return Err(e),
+                Err(_) => break,
+            }
+        }
+        self.take_ready(low_watermark, size)
+    }
+
+    /// Pops up to `size` bytes that were already received, without waiting. Returns `None` if a [pop](Self::pop)
+    /// would have to wait.
+    pub fn try_pop(&mut self, size: Option<usize>) -> Result<Option<DemiBuffer>, Fail> {
+        let low_watermark: usize = cmp::min(self.low_watermark, size.unwrap_or(usize::MAX));
+        if !self.is_ready(low_watermark) {
+            return Ok(None);
+        }
+        Ok(Some(self.take_ready(low_watermark, size)?))
+    }
+
+    /// Pops up to `size` bytes out of the data that is queued, stopping once it reaches `low_watermark` bytes, the end
+    /// of pushed data, or the end of the stream.
+    fn take_ready(&mut self, low_watermark: usize, size: Option<usize>) -> Result<DemiBuffer, Fail> {
+        let mut held: Vec<DemiBuffer> = Vec::new();
+        let mut held_len: usize = 0;
+        while held.is_empty() || (held_len < low_watermark && !self.is_pushed(held_len)) {
+            match self.recv_queue.try_pop() {
+                Some(buf) if buf.is_empty() => {
+                    if held.is_empty() {
+                        return self.take(buf, size);
+                    }
+                    self.recv_queue.push_front(buf);
+                    break;
+                },
+                Some(buf) => {
+                    held_len += buf.len();
+                    held.push(buf);
+                },
+                None if held.is_empty() => {
+                    let cause: &str = "no data to pop";
+                    warn!("take_ready(): {}", cause);
+                    return Err(Fail::new(libc::EAGAIN, cause));
+                },
+                None => break,
+            }
+        }
+        let buf: DemiBuffer = self.concat(held, held_len)?;
+        self.take(buf, size)
+    }
+
+    /// Checks whether a pop that waits for `low_watermark` bytes would complete right away.
+    fn is_ready(&self, low_watermark: usize) -> bool {
+        let mut queued: usize = 0;
+        for buf in self.recv_queue.get_values() {
+            // The end of the stream completes any pop.
+            if buf.is_empty() {
+                return true;
+            }
+            queued += buf.len();
+        }
+        queued > 0 && (queued >= low_watermark || self.is_pushed(queued))
+    }
+
+    /// Checks whether a pop is ready given the current low watermark.
+    pub fn is_readable(&self) -> bool {
+        self.is_ready(self.low_watermark)
+    }
+
+    /// Checks whether the next `len` bytes of the reader include the end of data that the remote peer pushed.
+    fn is_pushed(&self, len: usize) -> bool {
+        let end: SeqNumber = self.reader_next + SeqNumber::from(len as u32);
+        self.push_points
+            .iter()
+            .any(|push_point| *push_point > self.reader_next && *push_point <= end)
+    }
+
+    /// Remembers that the data that precedes `push_point` was pushed by the remote peer.
+    pub fn add_push_point(&mut self, push_point: SeqNumber) {
+        let reader_next: SeqNumber = self.reader_next;
+        self.push_points.retain(|point| *point > reader_next);
+        if push_point > reader_next && !self.push_points.contains(&push_point) {
+            self.push_points.push_back(push_point);
+        }
+    }
+
+    pub fn set_low_watermark(&mut self, low_watermark: usize) {
+        // As with SO_RCVLOWAT, a low watermark of zero is the same as one.
+        self.low_watermark = cmp::max(low_watermark, 1);
+    }
+
+    pub fn get_low_watermark(&self) -> usize {
+        self.low_watermark
+    }
+
+    /// Joins the `len` bytes of the buffers in `bufs`. Data is only copied if there are several of them, into a buffer
+    /// of the pool.
+    fn concat(&mut self, mut bufs: Vec<DemiBuffer>, len: usize) -> Result<DemiBuffer, Fail> {
+        if bufs.len() == 1 {
+            return Ok(bufs.pop().expect("there should be one buffer"));
+        }
+        if len >= u16::MAX as usize {
+            let cause: String = format!("data is larger than a DemiBuffer can hold (len={:?})", len);
+            error!("concat(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        let mut data: DemiBuffer = self.buffer_pool.alloc(len as u16);
+        let mut offset: usize = 0;
+        for buf in bufs.iter() {
+            data[offset..offset + buf.len()].copy_from_slice(&buf[..]);
+            offset += buf.len();
+        }
+        Ok(data)
+    }
+
+    /// Hands up to `size` bytes of `buf`, which was just popped from the receive queue, to the reader.
+    fn take(&mut self, mut buf: DemiBuffer, size: Option<usize>) -> Result<DemiBuffer, Fail> {
+        // Split the buffer if it's too big, and keep the rest of it for the next pop.
+        if let Some(size) = size {
             if buf.len() > size {
-                buf.split_front(size)?
-            } else {
-                buf
+                let front: DemiBuffer = buf.split_front(size)?;
+                self.recv_queue.push_front(buf);
+                buf = front;
             }
-        } else {
-            self.recv_queue.pop(&yielder).await?
-        };
+        }
+
+        // The zero-length buffer that marks the end of the stream stays queued, so that every later pop returns it too.
+        if buf.is_empty() {
+            self.recv_queue.push_front(buf.clone());
+        }
 
         self.reader_next = self.reader_next + SeqNumber::from(buf.len() as u32);
 
@@ -147,6 +320,12 @@ impl Receiver {
         self.recv_queue.push(buf);
         self.receive_next = self.receive_next + SeqNumber::from(buf_len as u32);
     }
+
+    // Consumes `nbytes` of in-order data that is not handed to the reader, such as urgent data received out of band.
+    pub fn skip(&mut self, nbytes: u32) {
+        self.reader_next = self.reader_next + SeqNumber::from(nbytes);
+        self.receive_next = self.receive_next + SeqNumber::from(nbytes);
+    }
 }
 
 /// Transmission control block for representing our TCP connection.
@@ -165,6 +344,9 @@ pub struct ControlBlock {
     // this along with other remote IP information (such as routing, path MTU, etc).
     arp: SharedArpPeer,
 
+    // Hook for tracing every segment sent on this connection.
+    tracer: SharedTcpTracer,
+
     // Send-side state information.  TODO: Consider incorporating this directly into ControlBlock.
     sender: Sender,
 
@@ -192,10 +374,16 @@ pub struct ControlBlock {
     //
     out_of_order: VecDeque<(SeqNumber, DemiBuffer)>,
 
+    // Cumulative counts of retransmissions, out-of-order segments and ICMP errors, which the application may reset.
+    error_counters: ErrorCounters,
+
     // The sequence number of the FIN, if we received it out-of-order.
     // Note: This could just be a boolean to remember if we got a FIN; the sequence number is for checking correctness.
     pub out_of_order_fin: Option<SeqNumber>,
 
+    // Time by which the hole before the out-of-order segments must be filled, if any segments are held.
+    reassembly_deadline: SharedWatchedValue<Option<Instant>>,
+
     // Receive-side state information.  TODO: Consider incorporating this directly into ControlBlock.
     receiver: Receiver,
 
@@ -212,6 +400,36 @@ pub struct ControlBlock {
 
     // Incoming packets for this connection.
     recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)>,
+
+    // Error that caused this connection to be aborted, if any.  Once set, all further operations fail with it.
+    error: Option<Fail>,
+
+    // Duration without any data sent or received after which the connection is closed, if any.
+    idle_timeout: SharedWatchedValue<Option<Duration>>,
+
+    // Last time that data was sent or received on this connection.
+    last_activity: Instant,
+
+    // Whether the FIN was sent because the connection was idle for too long.  The rest of the close protocol runs
+    // once the application closes the socket.
+    idle_closed: bool,
+
+    // Priority of the segments sent on this connection, relative to the other connections of the peer.
+    tx_priority: u8,
+
+    // Whether urgent data is left in the ordinary stream (SO_OOBINLINE), rather than being delivered out of band.
+    oob_inline: bool,
+
+    // Urgent byte that was received out of band and has yet to be read, if any.
+    oob_byte: Option<u8>,
+
+    // Whether data that arrives after our FIN was sent is discarded by resetting the connection, rather than being
+    // delivered to pending pops.
+    discard_late_data: bool,
+
+    // Key that the segments sent on this connection are signed with (TCP-MD5), if any.
+    #[cfg(feature = "tcp-md5")]
+    md5_key: Option<TcpMd5Key>,
 }
 
 #[derive(Clone)]
@@ -227,6 +445,7 @@ impl SharedControlBlock {
         local_link_addr: MacAddress,
         tcp_config: TcpConfig,
         arp: SharedArpPeer,
+        tracer: SharedTcpTracer,
         receiver_seq_no: SeqNumber,
         ack_delay_timeout: Duration,
         receiver_window_size: u32,
@@ -239,7 +458,22 @@ impl SharedControlBlock {
         congestion_control_options: Option<congestion_control::Options>,
         recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)>,
     ) -> Self {
-        let sender: Sender = Sender::new(sender_seq_no, sender_window_size, sender_window_scale, sender_mss);
+        let mut sender: Sender = Sender::new(sender_seq_no, sender_window_size, sender_window_scale, sender_mss);
+        // Do not send segments larger than the ones that we advertise, whatever the remote peer can take (as TCP_MAXSEG
+        // does on Linux). The remote MSS is still reported as advertised.
+        sender.reduce_mss(tcp_config.get_advertised_mss());
+        sender.set_autocork(tcp_config.get_autocork_window());
+        let now: Instant = runtime.get_now();
+        let timer: SharedTimer = runtime.get_timer();
+        let buffer_pool: SharedBufferPool = runtime.get_buffer_pool();
+        // The initial slow start threshold comes from the configuration, unless the options of the congestion
+        // controller override it.
+        let mut congestion_control_options: congestion_control::Options =
+            congestion_control_options.unwrap_or_default();
+        if congestion_control_options.get_int("initial_ssthresh").is_none() {
+            congestion_control_options
+                .insert_int("initial_ssthresh".to_string(), tcp_config.get_initial_ssthresh() as i64);
+        }
         Self(SharedObject::<ControlBlock>::new(ControlBlock {
             local,
             remote,
@@ -248,6 +482,7 @@ impl SharedControlBlock {
             local_link_addr,
             tcp_config,
             arp,
+            tracer,
             sender,
             state: State::Established,
             ack_delay_timeout,
@@ -255,12 +490,24 @@ impl SharedControlBlock {
             receive_buffer_size: receiver_window_size,
             window_scale: receiver_window_scale,
             out_of_order: VecDeque::new(),
+            error_counters: ErrorCounters::default(),
             out_of_order_fin: Option::None,
-            receiver: Receiver::new(receiver_seq_no, receiver_seq_no),
-            cc: cc_constructor(sender_mss, sender_seq_no, congestion_control_options),
+            reassembly_deadline: SharedWatchedValue::new(None),
+            receiver: Receiver::new(receiver_seq_no, receiver_seq_no, buffer_pool),
+            cc: cc_constructor(sender_mss, sender_seq_no, Some(congestion_control_options), timer),
             retransmit_deadline: SharedWatchedValue::new(None),
             rto_calculator: RtoCalculator::new(),
             recv_queue,
+            error: None,
+            idle_timeout: SharedWatchedValue::new(None),
+            last_activity: now,
+            idle_closed: false,
+            tx_priority: 0,
+            oob_inline: false,
+            oob_byte: None,
+            discard_late_data: false,
+            #[cfg(feature = "tcp-md5")]
+            md5_key: None,
         }))
     }
 
@@ -268,24 +515,95 @@ impl SharedControlBlock {
         self.local
     }
 
+    pub fn get_state(&self) -> State {
+        self.state
+    }
+
     pub fn get_remote(&self) -> SocketAddrV4 {
         self.remote
     }
 
+    pub fn get_local_link_addr(&self) -> MacAddress {
+        self.local_link_addr
+    }
+
     // TODO: Remove this.  ARP doesn't belong at this layer.
     pub fn arp(&self) -> SharedArpPeer {
         self.arp.clone()
     }
 
     pub fn send(&mut self, buf: DemiBuffer) -> Result<(), Fail> {
+        if let Some(cause) = self.error.as_ref() {
+            return Err(cause.clone());
+        }
         let self_: Self = self.clone();
         self.sender.send(buf, self_)
     }
 
+    /// Checks whether a send of `len` bytes may be queued right away. It may not if it does not fit in the send buffer,
+    /// or if earlier sends are already waiting for room, as it would otherwise overtake them.
+    pub fn can_send_now(&self, len: usize) -> bool {
+        !self.sender.has_waiting_sends() && self.sender.has_send_room(len)
+    }
+
+    /// Takes a ticket for a send that cannot be queued right away. See [wait_for_send_turn](Self::wait_for_send_turn).
+    pub fn take_send_ticket(&mut self) -> u64 {
+        self.sender.take_send_ticket()
+    }
+
+    /// Waits until it is the turn of the send that holds `ticket`, and until its `len` bytes fit in the send buffer.
+    /// The caller then queues its data, and hands the turn over with [release_send_ticket](Self::release_send_ticket).
+    pub async fn wait_for_send_turn(&mut self, ticket: u64, len: usize, yielder: &Yielder) -> Result<(), Fail> {
+        while !self.sender.is_send_turn(ticket) || !self.sender.has_send_room(len) {
+            if let Some(cause) = self.error.as_ref() {
+                return Err(cause.clone());
+            }
+            self.sender.add_send_waiter(yielder.get_handle());
+            yielder.yield_until_wake().await?;
+        }
+        Ok(())
+    }
+
+    /// Gives `ticket` back, handing the turn over to the next send that is waiting for room in the send buffer. A
+    /// ticket released before its turn came is skipped.
+    pub fn release_send_ticket(&mut self, ticket: u64) {
+        self.sender.release_send_ticket(ticket)
+    }
+
+    /// Sets the largest number of bytes held in the send buffer, including data that was sent but not acknowledged
+    /// yet (SO_SNDBUF). Pushes that do not fit wait until acknowledgements free enough room.
+    pub fn set_send_buffer_size(&mut self, size: usize) {
+        self.sender.set_send_buffer_size(size)
+    }
+
+    pub fn get_send_buffer_size(&self) -> usize {
+        self.sender.get_send_buffer_size()
+    }
+
+    pub fn send_more(&mut self, buf: DemiBuffer) -> Result<(), Fail> {
+        if let Some(cause) = self.error.as_ref() {
+            return Err(cause.clone());
+        }
+        self.sender.send_more(buf)
+    }
+
+    pub fn flush(&mut self) -> Result<(), Fail> {
+        if let Some(cause) = self.error.as_ref() {
+            return Err(cause.clone());
+        }
+        let self_: Self = self.clone();
+        self.sender.flush(self_)
+    }
+
     pub fn retransmit(&self) {
         self.sender.retransmit(self.clone())
     }
 
+    /// Counts a segment that the sender retransmitted.
+    pub fn count_retransmit(&mut self) {
+        self.error_counters.retransmits += 1;
+    }
+
     pub fn congestion_control_watch_retransmit_now_flag(&self) -> SharedWatchedValue<bool> {
         self.cc.get_retransmit_now_flag()
     }
@@ -378,6 +696,180 @@ impl SharedControlBlock {
         self.sender.pop_one_unsent_byte()
     }
 
+    pub fn get_data_retries(&self) -> usize {
+        self.tcp_config.get_data_retries()
+    }
+
+    /// Aborts this connection, failing any pending and future operations with `cause`.
+    pub fn abort(&mut self, cause: Fail) {
+        warn!(
+            "abort(): aborting connection (local={:?}, remote={:?}): {:?}",
+            self.local, self.remote, cause
+        );
+        self.state = State::Closed;
+        self.retransmit_deadline.set(None);
+        self.ack_deadline.set(None);
+        // Nobody is going to read the out-of-order segments anymore.
+        self.out_of_order.clear();
+        self.reassembly_deadline.set(None);
+        self.error = Some(cause.clone());
+        // Wake up any pending pop and close operations.
+        self.receiver.recv_queue.cancel_waiters(cause.clone());
+        self.recv_queue.cancel_waiters(cause);
+    }
+
+    /// Resets this connection, sending a RST to the remote peer and failing any pending and future operations with
+    /// `cause`.
+    pub fn reset(&mut self, cause: Fail) {
+        let mut header: TcpHeader = self.tcp_header();
+        header.seq_num = self.get_send_next().get();
+        header.rst = true;
+        if let Some(remote_link_addr) = self.arp().try_query(self.remote.ip().clone()) {
+            self.emit(header, None, remote_link_addr);
+        }
+        self.abort(cause);
+    }
+
+    /// Sets the duration without any data sent or received after which this connection is closed, or disables the
+    /// idle timeout if `timeout` is `None`. The connection is considered active at the time the timeout is set.
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.last_activity = self.get_now();
+        self.idle_timeout.set(timeout);
+    }
+
+    /// Sets the priority of the segments sent on this connection. When connections contend for the transport,
+    /// segments of higher priority connections are sent first. All connections have priority 0 by default.
+    pub fn set_tx_priority(&mut self, priority: u8) {
+        self.tx_priority = priority;
+    }
+
+    /// Sets whether urgent data is left in the ordinary stream (SO_OOBINLINE), or delivered out of band. Urgent data
+    /// is delivered out of band by default. This only applies to urgent data that is received afterwards.
+    pub fn set_oob_inline(&mut self, oob_inline: bool) {
+        self.oob_inline = oob_inline;
+    }
+
+    /// Sets whether small sends go out right away while data is in flight (TCP_NODELAY), instead of being coalesced.
+    pub fn set_nodelay(&mut self, nodelay: bool) {
+        self.sender.set_nodelay(nodelay)
+    }
+
+    pub fn get_nodelay(&self) -> bool {
+        self.sender.get_nodelay()
+    }
+
+    /// Sets whether data that arrives once this connection was closed locally, but before the remote peer closed its
+    /// side, is discarded. As per RFC 1122, the connection is then reset, since the remote peer would otherwise keep
+    /// retransmitting data that nobody reads. Such data is delivered to pending pops by default.
+    pub fn set_discard_late_data(&mut self, discard: bool) {
+        self.discard_late_data = discard;
+    }
+
+    /// Sets the key that the segments sent on this connection are signed with. Passing `None` stops signing them.
+    #[cfg(feature = "tcp-md5")]
+    pub fn set_md5_key(&mut self, key: Option<TcpMd5Key>) {
+        self.md5_key = key;
+    }
+
+    /// Sets the number of bytes that pops wait for (SO_RCVLOWAT). Pops complete with fewer bytes if the remote peer
+    /// pushed them (i.e. sent them with PSH), if they are all that the pop asked for, or at the end of the stream. A
+    /// low watermark of zero is the same as one, which is the default.
+    pub fn set_rcvlowat(&mut self, low_watermark: usize) {
+        self.receiver.set_low_watermark(low_watermark);
+    }
+
+    pub fn get_rcvlowat(&self) -> usize {
+        self.receiver.get_low_watermark()
+    }
+
+    /// Takes the urgent byte that was received out of band. Only the latest urgent byte is kept, so any earlier one
+    /// that was not read is lost.
+    pub fn recv_oob(&mut self) -> Result<u8, Fail> {
+        if self.oob_inline {
+            let cause: String = format!("urgent data is delivered inline");
+            warn!("recv_oob(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        match self.oob_byte.take() {
+            Some(byte) => Ok(byte),
+            None => {
+                let cause: String = format!("no urgent data to read");
+                warn!("recv_oob(): {}", cause);
+                Err(Fail::new(libc::EINVAL, &cause))
+            },
+        }
+    }
+
+    pub fn watch_idle_timeout(&self) -> SharedWatchedValue<Option<Duration>> {
+        self.idle_timeout.clone()
+    }
+
+    /// Returns the time at which this connection becomes idle for too long, if it has an idle timeout and has not been
+    /// closed for being idle yet.
+    pub fn get_idle_deadline(&self) -> Option<Instant> {
+        match self.idle_timeout.get() {
+            Some(timeout) if !self.idle_closed => Some(self.last_activity + timeout),
+            _ => None,
+        }
+    }
+
+    /// Closes this connection because no data was sent or received for its idle timeout. Unlike an abort, this sends a
+    /// FIN, so the remote peer sees a graceful close. Pending and future operations fail with `ETIMEDOUT`, except for
+    /// the close of the socket, which completes the close protocol.
+    pub fn idle_close(&mut self) {
+        self.state = match self.state {
+            State::Established => State::FinWait1,
+            State::CloseWait => State::LastAck,
+            // The close protocol is already running.
+            _ => return,
+        };
+        let cause: String = format!(
+            "connection was idle for too long (local={:?}, remote={:?}, timeout={:?})",
+            self.local,
+            self.remote,
+            self.idle_timeout.get()
+        );
+        warn!("idle_close(): {}", cause);
+        self.send_fin();
+        let fail: Fail = Fail::new(libc::ETIMEDOUT, &cause);
+        self.idle_closed = true;
+        self.error = Some(fail.clone());
+        // Wake up any pending pop operations.
+        self.receiver.recv_queue.cancel_waiters(fail);
+    }
+
+    pub fn watch_cork_deadline(&self) -> SharedWatchedValue<Option<Instant>> {
+        self.sender.watch_cork_deadline()
+    }
+
+    pub fn get_cork_deadline(&self) -> Option<Instant> {
+        self.sender.get_cork_deadline()
+    }
+
+    pub fn watch_reassembly_deadline(&self) -> SharedWatchedValue<Option<Instant>> {
+        self.reassembly_deadline.clone()
+    }
+
+    pub fn get_reassembly_deadline(&self) -> Option<Instant> {
+        self.reassembly_deadline.get()
+    }
+
+    /// Resets this connection because the hole before its out-of-order segments was not filled before the reassembly
+    /// timeout. Pending and future operations fail with `ETIMEDOUT`.
+    pub fn reassembly_timeout(&mut self) -> Fail {
+        let cause: String = format!(
+            "missing data was not received in time (local={:?}, remote={:?}, timeout={:?}, out_of_order={:?})",
+            self.local,
+            self.remote,
+            self.tcp_config.get_reassembly_timeout(),
+            self.out_of_order_bytes()
+        );
+        warn!("reassembly_timeout(): {}", cause);
+        let fail: Fail = Fail::new(libc::ETIMEDOUT, &cause);
+        self.reset(fail.clone());
+        fail
+    }
+
     pub fn get_timer(&self) -> SharedTimer {
         self.runtime.get_timer()
     }
@@ -395,7 +887,9 @@ impl SharedControlBlock {
         // Normal data processing in the Established state.
         loop {
             let (header, data): (TcpHeader, DemiBuffer) = match self.recv_queue.pop(&yielder).await {
-                Ok((_, header, data)) if self.state == State::Established => (header, data),
+                Ok((_, header, data)) if self.state == State::Established || self.state == State::CloseWait => {
+                    (header, data)
+                },
                 Ok(result) => {
                     self.recv_queue.push_front(result);
                     let cause: String = format!(
@@ -421,17 +915,20 @@ impl SharedControlBlock {
                 data.len(),
                 header
             );
+            if !data.is_empty() || header.fin {
+                self.last_activity = self.get_now();
+            }
 
             match self.process_packet(header, data) {
                 Ok(()) => (),
+                // The remote only closed its sending side, so keep processing segments: the application may still
+                // push data, which needs to be acknowledged.
                 Err(e) if e.errno == libc::ECONNRESET => {
                     self.state = State::CloseWait;
-                    let cause: String = format!(
-                        "remote closed connection, stopping processing (local={:?}, remote={:?})",
+                    info!(
+                        "poll(): remote closed its side of the connection (local={:?}, remote={:?})",
                         self.local, self.remote
                     );
-                    error!("poll(): {}", cause);
-                    return Err(Fail::new(libc::ECANCELED, &cause));
                 },
                 Err(e) => debug!("Dropped packet: {:?}", e),
             }
@@ -447,19 +944,22 @@ impl SharedControlBlock {
         let mut seg_end: SeqNumber = seg_start;
         let mut seg_len: u32 = data.len() as u32;
 
+        // The urgent pointer is relative to the start of the segment, which may be trimmed below. As in BSD, it points
+        // at the byte that follows the urgent one.
+        let urgent_seq_num: Option<SeqNumber> = if header.urg && header.urgent_pointer > 0 {
+            Some(seg_start + SeqNumber::from(header.urgent_pointer as u32 - 1))
+        } else {
+            None
+        };
+
         // Check if the segment is in the receive window and trim off everything else.
         self.check_segment_in_window(&mut header, &mut data, &mut seg_start, &mut seg_end, &mut seg_len)?;
         self.check_rst(&header)?;
         self.check_syn(&header)?;
         self.process_ack(&header)?;
 
-        // TODO: Check the URG bit.  If we decide to support this, how should we do it?
-        if header.urg {
-            warn!("Got packet with URG bit set!");
-        }
-
         if data.len() > 0 {
-            self.process_data(&mut header, data, seg_start, seg_end, seg_len)?;
+            self.process_data(&mut header, data, seg_start, seg_end, seg_len, urgent_seq_num)?;
         }
         self.process_remote_close(&header)?;
         // We should ACK this segment, preferably via piggybacking on a response.
@@ -717,11 +1217,19 @@ impl SharedControlBlock {
     fn process_data(
         &mut self,
         header: &mut TcpHeader,
-        data: DemiBuffer,
-        seg_start: SeqNumber,
+        mut data: DemiBuffer,
+        mut seg_start: SeqNumber,
         mut seg_end: SeqNumber,
         mut seg_len: u32,
+        urgent_seq_num: Option<SeqNumber>,
     ) -> Result<(), Fail> {
+        // PSH marks the end of the data of this segment as a point up to which pops complete, regardless of the low
+        // watermark. This holds for out-of-order segments too, once the data before them arrives.
+        if header.psh && !data.is_empty() {
+            self.receiver
+                .add_push_point(seg_start + SeqNumber::from(data.len() as u32));
+        }
+
         // We can only process in-order data (or FIN).  Check for out-of-order segment.
         if seg_start != self.receiver.receive_next {
             debug!("Received out-of-order segment");
@@ -751,6 +1259,23 @@ impl SharedControlBlock {
             return Ok(());
         }
 
+        // Pull the urgent byte out of the stream, unless it is to be delivered inline. Urgent data that arrives out of
+        // order is always delivered inline.
+        if let Some(urgent_seq_num) = urgent_seq_num {
+            let data_end: SeqNumber = seg_start + SeqNumber::from(data.len() as u32);
+            if !self.oob_inline && urgent_seq_num >= seg_start && urgent_seq_num < data_end {
+                let offset: usize = u32::from(urgent_seq_num - seg_start) as usize;
+                if offset > 0 {
+                    let front: DemiBuffer = data.split_front(offset)?;
+                    self.receiver.push(front);
+                }
+                self.oob_byte = Some(data[0]);
+                data.adjust(1)?;
+                self.receiver.skip(1);
+                seg_start = self.receiver.receive_next;
+            }
+        }
+
         // We can only legitimately receive data in ESTABLISHED, FIN-WAIT-1, and FIN-WAIT-2.
         header.fin |= self.receive_data(seg_start, data);
         Ok(())
@@ -801,6 +1326,16 @@ impl SharedControlBlock {
 
         let sent_fin: bool = header.fin;
 
+        // Sign the segment if the connection has a key.
+        #[cfg(feature = "tcp-md5")]
+        let header: TcpHeader = {
+            let mut header: TcpHeader = header;
+            if let Some(key) = self.md5_key.as_ref() {
+                header.sign_md5(key.clone());
+            }
+            header
+        };
+
         // Prepare description of TCP segment to send.
         // TODO: Change this to call lower levels to fill in their header information, handle routing, ARPing, etc.
         let segment = TcpSegment {
@@ -812,7 +1347,17 @@ impl SharedControlBlock {
         };
 
         // Call the runtime to send the segment.
-        self.transport.transmit(Box::new(segment));
+        let len: usize = segment.data.as_ref().map_or(0, |data| data.len());
+        self.tracer.trace(TcpSegmentDirection::Sent, &segment.tcp_hdr, len);
+        if self.tx_priority == 0 {
+            self.transport.transmit(Box::new(segment));
+        } else {
+            let pkt: PrioritizedPacket = PrioritizedPacket::new(Box::new(segment), self.tx_priority);
+            self.transport.transmit(Box::new(pkt));
+        }
+        if len > 0 || sent_fin {
+            self.last_activity = self.get_now();
+        }
 
         // Post-send operations follow.
         // Review: We perform these after the send, in order to keep send latency as low as possible.
@@ -839,6 +1384,56 @@ impl SharedControlBlock {
         self.sender.remote_mss()
     }
 
+    /// Lowers the MSS in use on this connection to fit in `path_mtu`, which path MTU discovery learned from an ICMP
+    /// error. The MSS is not lowered below [MIN_MSS], so that a forged error cannot make us send tiny segments.
+    pub fn set_path_mtu(&mut self, path_mtu: usize) {
+        self.error_counters.icmp_errors += 1;
+        let mss: usize = path_mtu.saturating_sub(IPV4_HEADER_MIN_SIZE as usize + MIN_TCP_HEADER_SIZE);
+        self.sender.reduce_mss(cmp::max(mss, MIN_MSS));
+    }
+
+    /// Gets the largest payload that a segment of this connection can carry on a link with the given `mtu`. This is
+    /// the MSS in use, capped by what fits in `mtu`, minus the TCP options that we put in each segment.
+    pub fn path_mss(&self, mtu: usize) -> usize {
+        let options_size: usize = self.tcp_header().compute_size() - MIN_TCP_HEADER_SIZE;
+        let mtu_mss: usize = mtu.saturating_sub(IPV4_HEADER_MIN_SIZE as usize + MIN_TCP_HEADER_SIZE);
+        cmp::min(self.sender.get_mss(), mtu_mss).saturating_sub(options_size)
+    }
+
+    /// Gets the TCP options that were negotiated during the handshake.
+    pub fn negotiated_options(&self) -> NegotiatedOptions {
+        NegotiatedOptions {
+            mss: self.sender.remote_mss(),
+            local_window_scale: self.window_scale as u8,
+            remote_window_scale: self.sender.get_window_scale(),
+            sack_permitted: false,
+            timestamps: false,
+        }
+    }
+
+    /// Lists the segments that were sent but not acknowledged yet, from the oldest to the newest.
+    #[cfg(any(test, feature = "tcp-diagnostics"))]
+    pub fn outstanding_segments(&self) -> Vec<UnackedSegmentInfo> {
+        self.sender.outstanding_segments()
+    }
+
+    /// Gathers diagnostics of this connection from the congestion controller, the RTO estimator and the buffers.
+    pub fn tcp_info(&self) -> TcpInfo {
+        TcpInfo {
+            state: self.state,
+            rtt: self.rto_calculator.srtt(),
+            rttvar: self.rto_calculator.rttvar(),
+            rto: self.rto_calculator.rto(),
+            cwnd: self.cc.get_cwnd().get(),
+            ssthresh: self.cc.get_ssthresh(),
+            snd_wnd: self.sender.get_send_window().get(),
+            rcv_wnd: self.get_receive_window_size(),
+            bytes_acked: self.sender.get_bytes_acked(),
+            retransmits: self.sender.get_retransmits(),
+            unacked: self.sender.unacked_segments(),
+        }
+    }
+
     pub fn get_ack_deadline(&self) -> SharedWatchedValue<Option<Instant>> {
         self.ack_deadline.clone()
     }
@@ -847,9 +1442,57 @@ impl SharedControlBlock {
         self.ack_deadline.set(when);
     }
 
+    /// Reports how many bytes are currently held in the send and receive buffers of this connection.
+    pub fn buffer_usage(&self) -> BufferUsage {
+        BufferUsage {
+            send_queued: self.sender.unsent_bytes(),
+            send_unacked: self.sender.unacked_bytes(),
+            recv_queued: self.receiver.recv_queue.get_values().map(|buf| buf.len()).sum(),
+            recv_out_of_order: self.out_of_order_bytes(),
+        }
+    }
+
+    /// Reads the error counters of this connection, and clears them afterwards if `reset` is set.
+    pub fn error_counters(&mut self, reset: bool) -> ErrorCounters {
+        let counters: ErrorCounters = self.error_counters;
+        if reset {
+            self.error_counters = ErrorCounters::default();
+        }
+        counters
+    }
+
+    /// Reports whether operations on this connection would complete right away. A connection that was closed by the
+    /// remote peer but not locally is readable, as pops return the end of the stream, and still writable.
+    pub fn readiness(&self) -> Readiness {
+        let error: bool = self.error.is_some();
+        let read_closed: bool = match self.state {
+            State::CloseWait | State::LastAck | State::Closing | State::TimeWait | State::Closed => true,
+            State::Established | State::FinWait1 | State::FinWait2 => false,
+        };
+        let write_closed: bool = match self.state {
+            State::Established | State::CloseWait => false,
+            _ => true,
+        };
+        Readiness {
+            readable: self.receiver.is_readable() || error,
+            writable: !write_closed && !error && !self.sender.is_unsent_queue_full() && self.can_send_now(1),
+            read_closed,
+            hangup: (read_closed && write_closed) || error,
+            error,
+        }
+    }
+
     pub fn get_receive_window_size(&self) -> u32 {
         let bytes_unread: u32 = (self.receiver.receive_next - self.receiver.reader_next).into();
-        self.receive_buffer_size - bytes_unread
+        self.receive_buffer_size.saturating_sub(bytes_unread)
+    }
+
+    /// Forces the size of the receive buffer, and thus of the receive window that is advertised to the remote peer,
+    /// and sends a window update right away. This is meant for testing and experimentation with flow control, as
+    /// shrinking the window below what was already advertised is discouraged by RFC 9293.
+    pub fn set_receive_window(&mut self, window_size: u32) {
+        self.receive_buffer_size = window_size;
+        self.send_ack();
     }
 
     pub fn hdr_window_size(&self) -> u16 {
@@ -867,14 +1510,98 @@ impl SharedControlBlock {
     }
 
     pub async fn pop(&mut self, size: Option<usize>, yielder: Yielder) -> Result<DemiBuffer, Fail> {
-        // TODO: Need to add a way to indicate that the other side closed (i.e. that we've received a FIN).
-        // Should we do this via a zero-sized buffer?  Same as with the unsent and unacked queues on the send side?
+        // Once the other side has closed (i.e. we've received a FIN) and all data before it has been popped, this returns
+        // a zero-length buffer, on this and every later call. The sending side stays open until we close it.
         //
         // This code was checking for an empty receive queue by comparing sequence numbers, as in:
         //  if self.receiver.reader_next.get() == self.receiver.receive_next.get() {
         // But that will think data is available to be read once we've received a FIN, because FINs consume sequence
         // number space.  Now we call is_empty() on the receive queue instead.
-        self.receiver.pop(size, yielder).await
+        if self.receiver.recv_queue.is_empty() {
+            if let Some(cause) = self.error.as_ref() {
+                return Err(cause.clone());
+            }
+        }
+        // The wait for data is bounded by the deadline of the coroutine, if it has one.
+        let timer: SharedTimer = self.get_timer();
+        timer
+            .within_deadline(self.receiver.pop(size, yielder.clone()), &yielder)
+            .await?
+    }
+
+    /// Like [pop](Self::pop), but returns `None` instead of waiting if no data was received yet.
+    pub fn try_pop(&mut self, size: Option<usize>) -> Result<Option<DemiBuffer>, Fail> {
+        if self.receiver.recv_queue.is_empty() {
+            if let Some(cause) = self.error.as_ref() {
+                return Err(cause.clone());
+            }
+        }
+        self.receiver.try_pop(size)
+    }
+
+    /// Pops up to `size` bytes, waiting until either that many bytes have arrived or `deadline` expires, whichever
+    /// comes first. Once the deadline expires, this returns whatever data arrived before it, or fails with ETIMEDOUT
+    /// if none did. Data is only copied when it spans several received segments. Each wait uses its own yielder, as
+    /// waiting on the queue is abandoned whenever the deadline expires first.
+    pub async fn pop_until(&mut self, size: usize, deadline: Instant) -> Result<DemiBuffer, Fail> {
+        let mut bufs: Vec<DemiBuffer> = Vec::new();
+        let mut len: usize = 0;
+        while len < size {
+            if self.receiver.recv_queue.is_empty() {
+                if let Some(cause) = self.error.as_ref() {
+                    if bufs.is_empty() {
+                        return Err(cause.clone());
+                    }
+                    break;
+                }
+                if self.get_now() >= deadline {
+                    break;
+                }
+            }
+
+            // Wait for either more data or the deadline. Data that is already queued wins over an expired deadline.
+            let result: Option<Result<DemiBuffer, Fail>> = {
+                let timeout_yielder: Yielder = Yielder::new();
+                let timeout_future = self.get_timer().wait_until(deadline, &timeout_yielder).fuse();
+                // The deadline takes over the low watermark, so any data that arrives is taken right away.
+                let pop_future = self.receiver.pop_at_least(1, Some(size - len), Yielder::new()).fuse();
+                futures::pin_mut!(timeout_future);
+                futures::pin_mut!(pop_future);
+                select_biased! {
+                    result = pop_future => Some(result),
+                    result = timeout_future => match result {
+                        Ok(()) => None,
+                        Err(e) => return Err(e),
+                    },
+                }
+            };
+            match result {
+                // A zero-length buffer marks the end of the stream, it stays queued for the next pop.
+                Some(Ok(buf)) if buf.is_empty() => {
+                    if bufs.is_empty() {
+                        return Ok(buf);
+                    }
+                    break;
+                },
+                Some(Ok(buf)) => {
+                    len += buf.len();
+                    bufs.push(buf);
+                },
+                // Hand out the data that arrived before the error, the next pop reports it.
+                Some(Err(_)) if !bufs.is_empty() => break,
+                Some(Err(e)) => return Err(e),
+                None => break,
+            }
+        }
+
+        match bufs.len() {
+            0 => {
+                let cause: String = format!("no data arrived before the deadline (size={:?})", size);
+                warn!("pop_until(): {}", &cause);
+                Err(Fail::new(libc::ETIMEDOUT, &cause))
+            },
+            _ => self.receiver.concat(bufs, len),
+        }
     }
 
     // This routine remembers that we have received an out-of-order FIN.
@@ -893,6 +1620,7 @@ impl SharedControlBlock {
         mut new_end: SeqNumber,
         mut buf: DemiBuffer,
     ) {
+        self.error_counters.out_of_order += 1;
         let mut action_index: usize = self.out_of_order.len();
         let mut another_pass_neeeded: bool = true;
 
@@ -979,12 +1707,33 @@ impl SharedControlBlock {
         // Insert the new segment into the correct position.
         self.out_of_order.insert(action_index, (new_start, buf));
 
-        // If the out-of-order store now contains too many entries, delete the later entries.
-        // TODO: The out-of-order store is already limited (in size) by our receive window, while the below check
-        // imposes a limit on the number of entries.  Do we need this?  Presumably for attack mitigation?
-        while self.out_of_order.len() > MAX_OUT_OF_ORDER {
-            self.out_of_order.pop_back();
+        // If the out-of-order store now holds too many entries or bytes, delete the later entries.  These are the
+        // furthest away from filling the hole, so they are the least useful ones, and the remote peer will retransmit
+        // them anyway.  This bounds the memory that a lossy path (or an attacker) can make us hold.
+        let max_segments: usize = self.tcp_config.get_out_of_order_max_segments();
+        let max_bytes: usize = self.tcp_config.get_out_of_order_max_bytes();
+        let mut num_bytes: usize = self.out_of_order_bytes();
+        while self.out_of_order.len() > max_segments || num_bytes > max_bytes {
+            if let Some((dropped_start, dropped_buf)) = self.out_of_order.pop_back() {
+                debug!(
+                    "Dropping out-of-order segment at {} ({} bytes)",
+                    dropped_start,
+                    dropped_buf.len()
+                );
+                num_bytes -= dropped_buf.len();
+            }
         }
+
+        // Give the remote peer a bounded time to fill the hole that was just opened.
+        if !self.out_of_order.is_empty() && self.reassembly_deadline.get().is_none() {
+            let deadline: Instant = self.get_now() + self.tcp_config.get_reassembly_timeout();
+            self.reassembly_deadline.set(Some(deadline));
+        }
+    }
+
+    /// Returns the number of bytes held in the out-of-order store.
+    fn out_of_order_bytes(&self) -> usize {
+        self.out_of_order.iter().map(|(_, buf)| buf.len()).sum()
     }
 
     // This routine takes an incoming in-order TCP segment and adds the data to the user's receive queue.  If the new
@@ -1001,10 +1750,13 @@ impl SharedControlBlock {
         // This routine should only be called with in-order segment data.
         debug_assert_eq!(seg_start, recv_next);
 
-        // Push the new segment data onto the end of the receive queue.
+        // Push the new segment data onto the end of the receive queue. Empty buffers mark the end of the stream, so
+        // they are never pushed here.
         let mut recv_next: SeqNumber = recv_next + SeqNumber::from(buf.len() as u32);
-        // This inserts the segment and wakes a waiting pop coroutine.
-        self.receiver.push(buf);
+        if !buf.is_empty() {
+            // This inserts the segment and wakes a waiting pop coroutine.
+            self.receiver.push(buf);
+        }
 
         // Okay, we've successfully received some new data.  Check if any of the formerly out-of-order data waiting in
         // the out-of-order queue is now in-order.  If so, we can move it to the receive queue.
@@ -1028,6 +1780,15 @@ impl SharedControlBlock {
             }
         }
 
+        // The hole is gone along with the out-of-order segments. Otherwise, the data that was recovered filled a hole,
+        // and the one that follows gets a full timeout of its own.
+        if self.out_of_order.is_empty() {
+            self.reassembly_deadline.set(None);
+        } else if added_out_of_order {
+            let deadline: Instant = self.get_now() + self.tcp_config.get_reassembly_timeout();
+            self.reassembly_deadline.set(Some(deadline));
+        }
+
         // TODO: Review recent change to update control block copy of recv_next upon each push to the receiver.
         // When receiving a retransmitted segment that fills a "hole" in the receive space, thus allowing a number
         // (potentially large number) of out-of-order segments to be added, we'll be modifying the TCB copy of
@@ -1054,8 +1815,7 @@ impl SharedControlBlock {
     fn process_remote_close(&mut self, header: &TcpHeader) -> Result<(), Fail> {
         if header.fin {
             trace!("Received FIN");
-            // 2. Push empty buffer to indicate EOF.
-            // TODO: set err bit and wake.
+            // 2. Push empty buffer to indicate EOF, once all data before it has been popped.
             self.receiver.push(DemiBuffer::new(0));
 
             // 3. Advance RCV.NXT over the FIN.
@@ -1079,11 +1839,29 @@ impl SharedControlBlock {
         // Send.
         if let Err(e) = self.send(fin_buf) {
             warn!("send_fin(): failed to send fin ({:?})", e);
+            return;
+        }
+        // While data is in flight, small segments wait for an ACK, whether they are held by Nagle's algorithm or by the
+        // background sender, and a peer that delays its ACKs stalls them. Nothing follows the FIN, so there is nothing
+        // to wait for: send the last data and the FIN right away, as far as the windows allow.
+        if let Err(e) = self.flush() {
+            warn!("send_fin(): failed to flush ({:?})", e);
         }
     }
 
     // This coroutine runs the close protocol.
     pub async fn close(&mut self, yielder: Yielder) -> Result<(), Fail> {
+        // A connection that was idle for too long already sent its FIN, so only wait for the rest of the close protocol.
+        if self.idle_closed {
+            return match self.state {
+                State::FinWait1 | State::FinWait2 | State::Closing => self.wait_local_close(yielder).await,
+                State::LastAck => self.wait_remote_already_closed(yielder).await,
+                _ => Ok(()),
+            };
+        }
+        if let Some(cause) = self.error.as_ref() {
+            return Err(cause.clone());
+        }
         // Assert we are in a valid state and move to new state.
         match self.state {
             State::Established => self.local_close(yielder).await,
@@ -1101,25 +1879,60 @@ impl SharedControlBlock {
         self.state = State::FinWait1;
         // 1. Send FIN.
         self.send_fin();
+        // 2. Wait for the FIN to be acknowledged and for the remote to close.
+        self.wait_local_close(yielder).await
+    }
 
+    async fn wait_local_close(&mut self, yielder: Yielder) -> Result<(), Fail> {
+        let deadline: Instant = self.get_now() + self.tcp_config.get_orphan_timeout();
         while self.state != State::TimeWait {
             // Wait for next packet.
-            let (_, header, _) = self.recv_queue.pop(&yielder).await?;
+            let (mut header, data): (TcpHeader, DemiBuffer) = match self.pop_closing_segment(deadline).await? {
+                Some(segment) => segment,
+                None => {
+                    self.orphan_expired();
+                    return Ok(());
+                },
+            };
 
-            // Check ACK.
-            self.state = match self.process_ack(&header) {
-                // Got ACK to our FIN.
-                Ok(()) => match self.state {
+            // Check ACK. When both ends close at the same time, the FIN of the remote peer does not acknowledge ours, as
+            // it was sent before ours arrived, so we only move on once everything up to our FIN is acknowledged.
+            if self.process_ack(&header).is_ok() && self.sender.is_all_acked() {
+                self.state = match self.state {
+                    // Got ACK to our FIN.
                     State::FinWait1 => State::FinWait2,
                     State::FinWait2 => State::FinWait2,
                     State::Closing => State::TimeWait,
                     state => unreachable!("Cannot be in any other state at this point: {:?}", state),
-                },
-                // Don't do anything if this is an unexpected message.
-                Err(_) => self.state,
-            };
+                };
+            }
 
-            // TODO: Receive data in the FINWAIT-1 and FINWAIT-2 states.
+            // The FIN of the remote peer was already received. If it comes again, our ACK of it was lost, so ACK it
+            // again.
+            if self.state == State::Closing || self.state == State::TimeWait {
+                if header.fin {
+                    self.send_ack();
+                }
+                continue;
+            }
+
+            // The remote peer may keep sending until it closes its side, so data is still received in the FIN-WAIT-1
+            // and FIN-WAIT-2 states, unless it is to be discarded.
+            if !data.is_empty() {
+                if self.discard_late_data {
+                    let cause: String = format!(
+                        "data received after the connection was closed (local={:?}, remote={:?})",
+                        self.local, self.remote
+                    );
+                    warn!("wait_local_close(): {}", cause);
+                    self.reset(Fail::new(libc::ECONNRESET, &cause));
+                    return Ok(());
+                }
+                if let Err(e) = self.receive_late_data(&mut header, data) {
+                    debug!("Dropped packet: {:?}", e);
+                    continue;
+                }
+            }
 
             // Check FIN.
             self.state = match self.process_remote_close(&header) {
@@ -1136,13 +1949,12 @@ impl SharedControlBlock {
             }
         }
 
-        // TODO: Get 2MSL value or linger option if set.
-        // TODO: Turn this on when our tests support it.
-        // let timeout_yielder: Yielder = Yielder::new();
-        // self.runtime
-        //     .get_timer()
-        //     .wait(Duration::from_secs(1), &timeout_yielder)
-        //     .await?;
+        // Linger for 2*MSL, so that segments of this connection that are still in the network expire before the
+        // connection can be used again.
+        // TODO: Use the linger option if set.
+        self.get_timer()
+            .wait(self.tcp_config.get_time_wait_timeout(), &yielder)
+            .await?;
 
         self.state = State::Closed;
         Ok(())
@@ -1153,10 +1965,21 @@ impl SharedControlBlock {
         self.state = State::LastAck;
         // 1. Send FIN.
         self.send_fin();
-        // Wait for ACK of FIN.
+        // 2. Wait for ACK of FIN.
+        self.wait_remote_already_closed(yielder).await
+    }
+
+    async fn wait_remote_already_closed(&mut self, _yielder: Yielder) -> Result<(), Fail> {
+        let deadline: Instant = self.get_now() + self.tcp_config.get_orphan_timeout();
         loop {
             // Wait for next packet.
-            let (_, header, _) = self.recv_queue.pop(&yielder).await?;
+            let (header, _): (TcpHeader, DemiBuffer) = match self.pop_closing_segment(deadline).await? {
+                Some(segment) => segment,
+                None => {
+                    self.orphan_expired();
+                    return Ok(());
+                },
+            };
 
             // Check ACK.
             match self.process_ack(&header) {
@@ -1166,6 +1989,49 @@ impl SharedControlBlock {
         }
         Ok(())
     }
+
+    /// Waits for the next segment received while the connection is closing, or returns `None` if `deadline` expires
+    /// first. Each wait uses its own yielders, as the one that loses the race is abandoned.
+    async fn pop_closing_segment(&mut self, deadline: Instant) -> Result<Option<(TcpHeader, DemiBuffer)>, Fail> {
+        let mut recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)> = self.recv_queue.clone();
+        let pop_yielder: Yielder = Yielder::new();
+        let timeout_yielder: Yielder = Yielder::new();
+        let pop_future = recv_queue.pop(&pop_yielder).fuse();
+        let timeout_future = self.get_timer().wait_until(deadline, &timeout_yielder).fuse();
+        futures::pin_mut!(pop_future);
+        futures::pin_mut!(timeout_future);
+        select_biased! {
+            result = pop_future => result.map(|(_, header, data)| Some((header, data))),
+            result = timeout_future => result.map(|()| None),
+        }
+    }
+
+    /// Receives the data of a segment that arrived once our FIN was sent, and acknowledges it right away, unless it
+    /// also carries a FIN, which is acknowledged along with the data when it is processed. Fails if the segment is not
+    /// in the receive window.
+    fn receive_late_data(&mut self, header: &mut TcpHeader, mut data: DemiBuffer) -> Result<(), Fail> {
+        let mut seg_start: SeqNumber = header.seq_num;
+        let mut seg_end: SeqNumber = seg_start;
+        let mut seg_len: u32 = data.len() as u32;
+        self.check_segment_in_window(header, &mut data, &mut seg_start, &mut seg_end, &mut seg_len)?;
+        if !data.is_empty() {
+            self.process_data(header, data, seg_start, seg_end, seg_len, None)?;
+        }
+        if !header.fin {
+            self.send_ack();
+        }
+        Ok(())
+    }
+
+    /// Gives up on a connection that the remote peer did not finish closing within the orphan timeout.
+    fn orphan_expired(&mut self) {
+        let cause: String = format!(
+            "remote did not finish closing the connection in time (local={:?}, remote={:?}, state={:?})",
+            self.local, self.remote, self.state
+        );
+        warn!("orphan_expired(): {}", cause);
+        self.state = State::Closed;
+    }
 }
 
 //======================================================================================================================
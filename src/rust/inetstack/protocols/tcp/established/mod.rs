@@ -7,6 +7,8 @@ mod ctrlblk;
 mod rto;
 mod sender;
 
+pub use self::ctrlblk::State;
+
 use crate::{
     collections::async_queue::SharedAsyncQueue,
     inetstack::{
@@ -16,6 +18,7 @@ use crate::{
                 congestion_control::CongestionControlConstructor,
                 established::ctrlblk::SharedControlBlock,
                 segment::TcpHeader,
+                trace::SharedTcpTracer,
                 SeqNumber,
             },
         },
@@ -26,7 +29,14 @@ use crate::{
     runtime::{
         fail::Fail,
         memory::DemiBuffer,
-        network::NetworkRuntime,
+        network::{
+            types::{
+                BufferUsage,
+                ErrorCounters,
+                Readiness,
+            },
+            NetworkRuntime,
+        },
         scheduler::{
             TaskHandle,
             Yielder,
@@ -38,10 +48,82 @@ use crate::{
 };
 use ::futures::channel::mpsc;
 use ::std::{
-    net::SocketAddrV4,
-    time::Duration,
+    net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
+#[cfg(feature = "tcp-md5")]
+use crate::inetstack::protocols::tcp::TcpMd5Key;
+
+/// TCP options that were negotiated during the handshake of a connection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NegotiatedOptions {
+    /// Maximum segment size used to send to the remote peer.
+    pub mss: usize,
+    /// Shift applied to the windows advertised by the local peer. It is zero if window scaling was not negotiated.
+    pub local_window_scale: u8,
+    /// Shift applied to the windows advertised by the remote peer. It is zero if window scaling was not negotiated.
+    pub remote_window_scale: u8,
+    /// Whether selective acknowledgements are permitted. They are not supported yet, so this is never set.
+    pub sack_permitted: bool,
+    /// Whether timestamps are in use. They are not supported yet, so this is never set.
+    pub timestamps: bool,
+}
+
+/// Diagnostics of a TCP connection, modeled on the `tcp_info` structure of Linux.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TcpInfo {
+    /// State of the connection.
+    pub state: State,
+    /// Smoothed round-trip time. It is zero until the first sample is taken.
+    pub rtt: Duration,
+    /// Round-trip time variation. It is zero until the first sample is taken.
+    pub rttvar: Duration,
+    /// Current retransmission timeout.
+    pub rto: Duration,
+    /// Congestion window, in bytes.
+    pub cwnd: u32,
+    /// Slow start threshold, in bytes. It is `u32::MAX` if the congestion controller has none.
+    pub ssthresh: u32,
+    /// Send window advertised by the remote peer, in bytes.
+    pub snd_wnd: u32,
+    /// Receive window advertised to the remote peer, in bytes.
+    pub rcv_wnd: u32,
+    /// Number of bytes of data acknowledged by the remote peer so far.
+    pub bytes_acked: u64,
+    /// Number of segments retransmitted so far.
+    pub retransmits: u64,
+    /// Number of segments sent but not acknowledged yet.
+    pub unacked: usize,
+}
+
+/// Segment that was sent on a TCP connection but not acknowledged yet, as reported for diagnostics.
+#[cfg(any(test, feature = "tcp-diagnostics"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnackedSegmentInfo {
+    /// Sequence number of the first byte of the segment that is not acknowledged yet.
+    pub start: SeqNumber,
+    /// Sequence number that follows the segment.
+    pub end: SeqNumber,
+    /// Time at which the segment was last sent.
+    pub last_tx: Instant,
+    /// Number of times that the segment was retransmitted.
+    pub retransmits: u32,
+}
+
+/// Ticket of a send that did not fit in the send buffer, which waits for its turn to be queued. Dropping it hands the
+/// turn over, so that later sends do not wait forever on a send that failed or was cancelled.
+pub struct SendTicket {
+    cb: SharedControlBlock,
+    ticket: u64,
+}
+
 #[derive(Clone)]
 pub struct EstablishedSocket {
     pub cb: SharedControlBlock,
@@ -65,6 +147,7 @@ impl EstablishedSocket {
         local_link_addr: MacAddress,
         tcp_config: TcpConfig,
         arp: SharedArpPeer,
+        tracer: SharedTcpTracer,
         receiver_seq_no: SeqNumber,
         ack_delay_timeout: Duration,
         receiver_window_size: u32,
@@ -86,6 +169,7 @@ impl EstablishedSocket {
             local_link_addr,
             tcp_config,
             arp,
+            tracer,
             receiver_seq_no,
             ack_delay_timeout,
             receiver_window_size,
@@ -118,10 +202,49 @@ impl EstablishedSocket {
         self.cb.send(buf)
     }
 
+    pub fn send_more(&mut self, buf: DemiBuffer) -> Result<(), Fail> {
+        self.cb.send_more(buf)
+    }
+
+    pub fn flush(&mut self) -> Result<(), Fail> {
+        self.cb.flush()
+    }
+
+    pub fn can_send_now(&self, len: usize) -> bool {
+        self.cb.can_send_now(len)
+    }
+
+    pub fn take_send_ticket(&mut self) -> SendTicket {
+        SendTicket {
+            cb: self.cb.clone(),
+            ticket: self.cb.take_send_ticket(),
+        }
+    }
+
+    pub async fn wait_for_send_turn(&mut self, ticket: &SendTicket, len: usize, yielder: &Yielder) -> Result<(), Fail> {
+        self.cb.wait_for_send_turn(ticket.ticket, len, yielder).await
+    }
+
+    pub fn set_send_buffer_size(&mut self, size: usize) {
+        self.cb.set_send_buffer_size(size)
+    }
+
+    pub fn get_send_buffer_size(&self) -> usize {
+        self.cb.get_send_buffer_size()
+    }
+
     pub async fn pop(&mut self, size: Option<usize>, yielder: Yielder) -> Result<DemiBuffer, Fail> {
         self.cb.pop(size, yielder).await
     }
 
+    pub fn try_pop(&mut self, size: Option<usize>) -> Result<Option<DemiBuffer>, Fail> {
+        self.cb.try_pop(size)
+    }
+
+    pub async fn pop_until(&mut self, size: usize, deadline: Instant) -> Result<DemiBuffer, Fail> {
+        self.cb.pop_until(size, deadline).await
+    }
+
     pub async fn close(&mut self, yielder: Yielder) -> Result<(), Fail> {
         self.cb.close(yielder).await
     }
@@ -130,13 +253,113 @@ impl EstablishedSocket {
         self.cb.remote_mss()
     }
 
+    pub fn set_path_mtu(&mut self, path_mtu: usize) {
+        self.cb.set_path_mtu(path_mtu)
+    }
+
+    pub fn path_mss(&self, mtu: usize) -> usize {
+        self.cb.path_mss(mtu)
+    }
+
     pub fn current_rto(&self) -> Duration {
         self.cb.rto()
     }
 
+    pub fn send_window(&self) -> u32 {
+        self.cb.get_send_window().get()
+    }
+
+    pub fn set_receive_window(&mut self, window_size: u32) {
+        self.cb.set_receive_window(window_size)
+    }
+
     pub fn endpoints(&self) -> (SocketAddrV4, SocketAddrV4) {
         (self.cb.get_local(), self.cb.get_remote())
     }
+
+    pub fn state(&self) -> State {
+        self.cb.get_state()
+    }
+
+    pub fn buffer_usage(&self) -> BufferUsage {
+        self.cb.buffer_usage()
+    }
+
+    pub fn error_counters(&mut self, reset: bool) -> ErrorCounters {
+        self.cb.error_counters(reset)
+    }
+
+    /// Gets the local link address and the link address of the next hop towards the remote peer, as resolved by ARP.
+    /// Fails if the link address of the next hop is not resolved.
+    pub fn link_addrs(&self) -> Result<(MacAddress, MacAddress), Fail> {
+        let next_hop: Ipv4Addr = self.cb.arp().next_hop(*self.cb.get_remote().ip());
+        match self.cb.arp().try_query(next_hop) {
+            Some(remote_link_addr) => Ok((self.cb.get_local_link_addr(), remote_link_addr)),
+            None => {
+                let cause: String = format!("link address of next hop is not resolved (next_hop={:?})", next_hop);
+                error!("link_addrs(): {}", cause);
+                Err(Fail::new(libc::EAGAIN, &cause))
+            },
+        }
+    }
+
+    pub fn reset(&mut self, cause: Fail) {
+        self.cb.reset(cause)
+    }
+
+    pub fn readiness(&self) -> Readiness {
+        self.cb.readiness()
+    }
+
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.cb.set_idle_timeout(timeout)
+    }
+
+    pub fn set_tx_priority(&mut self, priority: u8) {
+        self.cb.set_tx_priority(priority)
+    }
+
+    #[cfg(feature = "tcp-md5")]
+    pub fn set_md5_key(&mut self, key: Option<TcpMd5Key>) {
+        self.cb.set_md5_key(key)
+    }
+
+    pub fn set_oob_inline(&mut self, oob_inline: bool) {
+        self.cb.set_oob_inline(oob_inline)
+    }
+
+    pub fn set_nodelay(&mut self, nodelay: bool) {
+        self.cb.set_nodelay(nodelay)
+    }
+
+    pub fn set_discard_late_data(&mut self, discard: bool) {
+        self.cb.set_discard_late_data(discard)
+    }
+
+    pub fn recv_oob(&mut self) -> Result<u8, Fail> {
+        self.cb.recv_oob()
+    }
+
+    pub fn set_rcvlowat(&mut self, low_watermark: usize) {
+        self.cb.set_rcvlowat(low_watermark)
+    }
+
+    pub fn get_rcvlowat(&self) -> usize {
+        self.cb.get_rcvlowat()
+    }
+
+    pub fn negotiated_options(&self) -> NegotiatedOptions {
+        self.cb.negotiated_options()
+    }
+
+    pub fn tcp_info(&self) -> TcpInfo {
+        self.cb.tcp_info()
+    }
+
+    #[cfg(any(test, feature = "tcp-diagnostics"))]
+    pub fn outstanding_segments(&self) -> Vec<UnackedSegmentInfo> {
+        self.cb.outstanding_segments()
+    }
 }
 
 //======================================================================================================================
@@ -152,3 +375,9 @@ impl EstablishedSocket {
 //         }
 //     }
 // }
+
+impl Drop for SendTicket {
+    fn drop(&mut self) {
+        self.cb.release_send_ticket(self.ticket);
+    }
+}
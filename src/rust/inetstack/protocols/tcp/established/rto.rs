@@ -87,4 +87,22 @@ impl RtoCalculator {
     pub fn rto(&self) -> Duration {
         Duration::from_secs_f64(self.rto)
     }
+
+    /// Gets the smoothed round-trip time, which is zero until the first RTT sample is received.
+    pub fn srtt(&self) -> Duration {
+        if self.received_sample {
+            Duration::from_secs_f64(self.srtt)
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    /// Gets the round-trip time variation, which is zero until the first RTT sample is received.
+    pub fn rttvar(&self) -> Duration {
+        if self.received_sample {
+            Duration::from_secs_f64(self.rttvar)
+        } else {
+            Duration::ZERO
+        }
+    }
 }
@@ -10,6 +10,8 @@ use crate::{
     runtime::{
         fail::Fail,
         memory::DemiBuffer,
+        network::types::MacAddress,
+        scheduler::YielderHandle,
         watched::SharedWatchedValue,
     },
 };
@@ -22,7 +24,11 @@ use ::std::{
         Cell,
         RefCell,
     },
-    collections::VecDeque,
+    cmp,
+    collections::{
+        HashSet,
+        VecDeque,
+    },
     convert::TryInto,
     fmt,
     time::{
@@ -31,6 +37,9 @@ use ::std::{
     },
 };
 
+#[cfg(any(test, feature = "tcp-diagnostics"))]
+use crate::inetstack::protocols::tcp::established::UnackedSegmentInfo;
+
 // Structure of entries on our unacknowledged queue.
 // TODO: We currently allocate these on the fly when we add a buffer to the queue.  Would be more efficient to have a
 // buffer structure that held everything we need directly, thus avoiding this extra wrapper.
@@ -39,6 +48,26 @@ pub struct UnackedSegment {
     pub bytes: DemiBuffer,
     // Set to `None` on retransmission to implement Karn's algorithm.
     pub initial_tx: Option<Instant>,
+    // Time of the last (re)transmission, which is only tracked for diagnostics.
+    #[cfg(any(test, feature = "tcp-diagnostics"))]
+    pub last_tx: Instant,
+    // Number of retransmissions, which is only tracked for diagnostics.
+    #[cfg(any(test, feature = "tcp-diagnostics"))]
+    pub retransmits: u32,
+}
+
+impl UnackedSegment {
+    /// Creates an entry for `bytes`, which were first sent at `now`.
+    pub fn new(bytes: DemiBuffer, now: Instant) -> Self {
+        Self {
+            bytes,
+            initial_tx: Some(now),
+            #[cfg(any(test, feature = "tcp-diagnostics"))]
+            last_tx: now,
+            #[cfg(any(test, feature = "tcp-diagnostics"))]
+            retransmits: 0,
+        }
+    }
 }
 
 /// Hard limit for unsent queue.
@@ -77,6 +106,9 @@ pub struct Sender {
     // TODO: Remove this as soon as sender.rs is fixed to not use it to tell if there is unsent data.
     unsent_seq_no: SharedWatchedValue<SeqNumber>,
 
+    // User data pushed with a hint that more is coming. It is held back and coalesced with the data of the next send.
+    held: Option<DemiBuffer>,
+
     // Available window to send into, as advertised by our peer.  In RFC 793 terms, this is SND.WND.
     send_window: SharedWatchedValue<u32>,
     send_window_last_update_seq: Cell<SeqNumber>, // SND.WL1
@@ -85,9 +117,41 @@ pub struct Sender {
     // RFC 1323: Number of bits to shift advertised window, defaults to zero.
     window_scale: u8,
 
-    // Maximum Segment Size currently in use for this connection.
-    // TODO: Revisit this once we support path MTU discovery.
+    // Maximum Segment Size currently in use for this connection. It starts at the MSS of our peer, and goes down if
+    // path MTU discovery finds a smaller MTU on the way to our peer.
     mss: usize,
+
+    // Maximum Segment Size that our peer advertised.
+    remote_mss: usize,
+
+    // Number of bytes of data that our peer acknowledged over the lifetime of this connection.
+    bytes_acked: Cell<u64>,
+
+    // Number of segments that were retransmitted over the lifetime of this connection.
+    retransmits: Cell<u64>,
+
+    // Whether small sends go out right away, even while data is in flight (i.e. whether TCP_NODELAY is set).
+    nodelay: bool,
+
+    // Coalescing window of autocorking, if enabled. Sends smaller than the MSS are held back for this long, so that
+    // the ones that follow within the window go out in the same segment.
+    autocork: Option<Duration>,
+
+    // When the data that is corked gets flushed, if any is.
+    cork_deadline: SharedWatchedValue<Option<Instant>>,
+
+    // Largest number of bytes held in the send buffer, whether unsent or unacknowledged (i.e. SO_SNDBUF), if any.
+    send_buffer_size: Option<usize>,
+
+    // Sends that do not fit in the send buffer wait for their turn, so that they are queued in the order in which they
+    // were issued. Each one takes the next ticket, and it is its turn once all the tickets before it were served.
+    // Tickets given up before their turn came are skipped.
+    next_send_ticket: u64,
+    send_turn: u64,
+    abandoned_send_tickets: HashSet<u64>,
+
+    // Sends that are waiting for their turn, or for room in the send buffer.
+    send_waiters: RefCell<Vec<YielderHandle>>,
 }
 
 impl fmt::Debug for Sender {
@@ -111,6 +175,7 @@ impl Sender {
             send_next: SharedWatchedValue::new(seq_no),
             unsent_queue: RefCell::new(VecDeque::new()),
             unsent_seq_no: SharedWatchedValue::new(seq_no),
+            held: None,
 
             send_window: SharedWatchedValue::new(send_window),
             send_window_last_update_seq: Cell::new(seq_no),
@@ -118,6 +183,18 @@ impl Sender {
 
             window_scale,
             mss,
+            remote_mss: mss,
+
+            bytes_acked: Cell::new(0),
+            retransmits: Cell::new(0),
+            nodelay: false,
+            autocork: None,
+            cork_deadline: SharedWatchedValue::new(None),
+            send_buffer_size: None,
+            next_send_ticket: 0,
+            send_turn: 0,
+            abandoned_send_tickets: HashSet::new(),
+            send_waiters: RefCell::new(Vec::new()),
         }
     }
 
@@ -125,6 +202,12 @@ impl Sender {
         self.mss
     }
 
+    // Lowers the MSS in use to `mss`, if it is smaller. The MSS never grows back.
+    //
+    pub fn reduce_mss(&mut self, mss: usize) {
+        self.mss = cmp::min(self.mss, mss);
+    }
+
     pub fn get_send_window(&self) -> SharedWatchedValue<u32> {
         self.send_window.clone()
     }
@@ -149,11 +232,152 @@ impl Sender {
         self.unacked_queue.borrow_mut().push_back(segment)
     }
 
+    // Sets whether small sends go out right away, instead of being queued while data is in flight.
+    //
+    pub fn set_nodelay(&mut self, nodelay: bool) {
+        self.nodelay = nodelay;
+    }
+
+    pub fn get_nodelay(&self) -> bool {
+        self.nodelay
+    }
+
+    // Sets the coalescing window of autocorking, or disables it.
+    //
+    pub fn set_autocork(&mut self, autocork: Option<Duration>) {
+        self.autocork = autocork;
+    }
+
+    pub fn watch_cork_deadline(&self) -> SharedWatchedValue<Option<Instant>> {
+        self.cork_deadline.clone()
+    }
+
+    pub fn get_cork_deadline(&self) -> Option<Instant> {
+        self.cork_deadline.get()
+    }
+
+    // Sets the largest number of bytes held in the send buffer. Data that is already queued is kept, even past the
+    // new size.
+    //
+    pub fn set_send_buffer_size(&mut self, size: usize) {
+        self.send_buffer_size = Some(size);
+        // A larger send buffer may make room for the sends that are waiting.
+        self.wake_send_waiters();
+    }
+
+    // Gets the largest number of bytes held in the send buffer. The send buffer is only bounded by its number of
+    // buffers unless told otherwise.
+    //
+    pub fn get_send_buffer_size(&self) -> usize {
+        self.send_buffer_size.unwrap_or(usize::MAX)
+    }
+
+    // Whether a send of `len` bytes fits in the send buffer. A send always fits in an empty send buffer, so that sends
+    // larger than the send buffer are not stuck forever.
+    //
+    pub fn has_send_room(&self, len: usize) -> bool {
+        let used: usize = self.unsent_bytes() + self.unacked_bytes() + self.held.as_ref().map_or(0, |buf| buf.len());
+        self.send_buffer_size
+            .map_or(true, |max_bytes| used == 0 || used + len <= max_bytes)
+    }
+
+    // Whether some sends are waiting for room in the send buffer.
+    //
+    pub fn has_waiting_sends(&self) -> bool {
+        self.next_send_ticket != self.send_turn
+    }
+
+    // Takes a ticket for a send that has to wait for room in the send buffer.
+    //
+    pub fn take_send_ticket(&mut self) -> u64 {
+        let ticket: u64 = self.next_send_ticket;
+        self.next_send_ticket += 1;
+        ticket
+    }
+
+    // Whether it is the turn of the send that holds `ticket`.
+    //
+    pub fn is_send_turn(&self, ticket: u64) -> bool {
+        self.send_turn == ticket
+    }
+
+    // Gives `ticket` back once its send is done with it, whether it went through, failed or was cancelled. The turn
+    // is handed over to the next waiting send if it was the turn of `ticket`, and is skipped when it comes otherwise.
+    //
+    pub fn release_send_ticket(&mut self, ticket: u64) {
+        if !self.is_send_turn(ticket) {
+            self.abandoned_send_tickets.insert(ticket);
+            return;
+        }
+        self.send_turn += 1;
+        while self.abandoned_send_tickets.remove(&self.send_turn) {
+            self.send_turn += 1;
+        }
+        self.wake_send_waiters();
+    }
+
+    // Registers a send that waits for its turn, or for room in the send buffer.
+    //
+    pub fn add_send_waiter(&self, handle: YielderHandle) {
+        self.send_waiters.borrow_mut().push(handle);
+    }
+
+    // Wakes all the waiting sends, so that they check whether they may go on.
+    //
+    fn wake_send_waiters(&self) {
+        let waiters: Vec<YielderHandle> = self.send_waiters.borrow_mut().drain(..).collect();
+        for mut handle in waiters {
+            handle.wake_with(Ok(()));
+        }
+    }
+
+    // Holds back `buf` instead of sending it, so that it is coalesced with the data of the next send.
+    //
+    pub fn send_more(&mut self, buf: DemiBuffer) -> Result<(), Fail> {
+        let held: DemiBuffer = match self.held.take() {
+            Some(held) => Self::concat(&held, &buf)?,
+            None => buf,
+        };
+        self.held = Some(held);
+        Ok(())
+    }
+
     // This is the main TCP send routine.
     //
-    pub fn send(&mut self, buf: DemiBuffer, mut cb: SharedControlBlock) -> Result<(), Fail> {
+    pub fn send(&mut self, buf: DemiBuffer, cb: SharedControlBlock) -> Result<(), Fail> {
         // If the user is done sending (i.e. has called close on this connection), then they shouldn't be sending.
 
+        // Coalesce any data that was held back with this buffer. The end-of-send marker has to stay empty, so the held
+        // data is sent on its own before it.
+        let buf: DemiBuffer = match self.held.take() {
+            Some(held) if buf.len() == 0 => {
+                self.cork_deadline.set(None);
+                self.send_now(held, cb.clone())?;
+                buf
+            },
+            Some(held) => Self::concat(&held, &buf)?,
+            None => buf,
+        };
+
+        // Autocorking: a send smaller than a full segment is held back until the coalescing window that the first
+        // corked send opened is over, unless enough data comes in the meantime to fill a segment.
+        if let Some(window) = self.autocork {
+            if buf.len() > 0 && buf.len() < self.mss {
+                self.held = Some(buf);
+                if self.cork_deadline.get().is_none() {
+                    self.cork_deadline.set(Some(cb.get_timer().now() + window));
+                }
+                return Ok(());
+            }
+        }
+        self.cork_deadline.set(None);
+
+        self.send_now(buf, cb)
+    }
+
+    // Sends `buf` right away if the windows allow, or queues it for the background sender otherwise.
+    //
+    fn send_now(&mut self, buf: DemiBuffer, mut cb: SharedControlBlock) -> Result<(), Fail> {
         // Our API supports send buffers up to usize (variable, depends upon architecture) in size.  While we could
         // allow for larger send buffers, it is simpler and more practical to limit a single send to 1 GiB, which is
         // also the maximum value a TCP can advertise as its receive window (with maximum window scaling).
@@ -203,7 +427,11 @@ impl Sender {
 
             let win_sz: u32 = self.send_window.get();
 
-            if win_sz > 0 && win_sz >= in_flight_after_send && effective_cwnd >= in_flight_after_send {
+            // Nagle's algorithm: while data is in flight, a send smaller than a full segment is queued instead, so
+            // that the background sender coalesces it with the sends that follow.
+            let nagle: bool = !self.nodelay && buf_len > 0 && sent_data > 0 && (buf_len as usize) < self.mss;
+
+            if !nagle && win_sz > 0 && win_sz >= in_flight_after_send && effective_cwnd >= in_flight_after_send {
                 if let Some(remote_link_addr) = cb.arp().try_query(cb.get_remote().ip().clone()) {
                     // This hook is primarily intended to record the last time we sent data, so we can later tell if
                     // the connection has been idle.
@@ -231,10 +459,7 @@ impl Sender {
                     self.unsent_seq_no.modify(|s| s + SeqNumber::from(buf_len));
 
                     // Put the segment we just sent on the retransmission queue.
-                    let unacked_segment = UnackedSegment {
-                        bytes: buf,
-                        initial_tx: Some(cb.get_timer().now()),
-                    };
+                    let unacked_segment = UnackedSegment::new(buf, cb.get_timer().now());
                     self.unacked_queue.borrow_mut().push_back(unacked_segment);
 
                     // Start the retransmission timer if it isn't already running.
@@ -252,7 +477,7 @@ impl Sender {
 
         // Too fast.
         // TODO: We need to fix this the correct way: limit our send buffer size to the amount we're willing to buffer.
-        if self.unsent_queue.borrow().len() > UNSENT_QUEUE_CUTOFF {
+        if self.is_unsent_queue_full() {
             return Err(Fail::new(EBUSY, "too many packets to send"));
         }
 
@@ -264,6 +489,79 @@ impl Sender {
         Ok(())
     }
 
+    // Transmits the data that is buffered but not sent yet, as far as the send and congestion windows allow, instead of
+    // leaving it to the background sender. This includes the data held back by push_more or by autocorking, and data
+    // queued by Nagle's algorithm, so the last segment may be smaller than the MSS.
+    //
+    pub fn flush(&mut self, mut cb: SharedControlBlock) -> Result<(), Fail> {
+        self.cork_deadline.set(None);
+        if let Some(held) = self.held.take() {
+            let held_len: u32 = held
+                .len()
+                .try_into()
+                .map_err(|_| Fail::new(EINVAL, "buffer too large"))?;
+            self.unsent_queue.borrow_mut().push_back(held);
+            self.unsent_seq_no.modify(|s| s + SeqNumber::from(held_len));
+        }
+
+        // Without a link address, the background sender resolves it, and sends the data once it is known.
+        let remote_link_addr: MacAddress = match cb.arp().try_query(cb.get_remote().ip().clone()) {
+            Some(remote_link_addr) => remote_link_addr,
+            None => return Ok(()),
+        };
+
+        loop {
+            let send_next: SeqNumber = self.send_next.get();
+            let sent_data: u32 = (send_next - self.send_unacked.get()).into();
+            cb.congestion_control_on_cwnd_check_before_send();
+            let effective_cwnd: u32 = cb.congestion_control_get_cwnd().get()
+                + cb.congestion_control_get_limited_transmit_cwnd_increase().get();
+            let max_size: usize = cmp::min(
+                cmp::min(self.send_window.get().saturating_sub(sent_data) as usize, self.mss),
+                effective_cwnd.saturating_sub(sent_data) as usize,
+            );
+            if max_size == 0 {
+                return Ok(());
+            }
+            let (buf, do_push): (DemiBuffer, bool) = match self.pop_unsent(max_size) {
+                Some(segment) => segment,
+                None => return Ok(()),
+            };
+
+            let rto: Duration = cb.rto();
+            cb.congestion_control_on_send(rto, sent_data);
+
+            let mut header: TcpHeader = cb.tcp_header();
+            header.seq_num = send_next;
+            let mut buf_len: u32 = buf.len() as u32;
+            if buf_len == 0 {
+                // This buffer is the end-of-send marker.
+                header.fin = true;
+                buf_len = 1;
+            } else {
+                header.psh = do_push;
+            }
+            trace!("Send flushed");
+            cb.emit(header, Some(buf.clone()), remote_link_addr);
+            self.send_next.modify(|s| s + SeqNumber::from(buf_len));
+            self.unacked_queue
+                .borrow_mut()
+                .push_back(UnackedSegment::new(buf, cb.get_timer().now()));
+            if cb.get_retransmit_deadline().is_none() {
+                let rto: Duration = cb.rto();
+                cb.set_retransmit_deadline(Some(cb.get_timer().now() + rto));
+            }
+        }
+    }
+
+    /// Copies `first` and `second` into a single buffer.
+    fn concat(first: &DemiBuffer, second: &DemiBuffer) -> Result<DemiBuffer, Fail> {
+        let mut data: Vec<u8> = Vec::with_capacity(first.len() + second.len());
+        data.extend_from_slice(&first[..]);
+        data.extend_from_slice(&second[..]);
+        DemiBuffer::from_slice(&data)
+    }
+
     /// Retransmits the earliest segment that has not (yet) been acknowledged by our peer.
     pub fn retransmit(&self, mut cb: SharedControlBlock) {
         // Check that we have an unacknowledged segment.
@@ -271,9 +569,16 @@ impl Sender {
             // We're retransmitting this, so we can no longer use an ACK for it as an RTT measurement (as we can't tell
             // if the ACK is for the original or the retransmission).  Remove the transmission timestamp from the entry.
             segment.initial_tx.take();
+            #[cfg(any(test, feature = "tcp-diagnostics"))]
+            {
+                segment.last_tx = cb.get_timer().now();
+                segment.retransmits += 1;
+            }
 
             // Clone the segment data for retransmission.
             let data: DemiBuffer = segment.bytes.clone();
+            self.retransmits.set(self.retransmits.get() + 1);
+            cb.count_retransmit();
 
             // TODO: Issue #198 Repacketization - we should send a full MSS (and set the FIN flag if applicable).
 
@@ -318,6 +623,7 @@ impl Sender {
                         .adjust(bytes_remaining)
                         .expect("'segment' should contain at least 'bytes_remaining'");
                     segment.initial_tx = None;
+                    self.bytes_acked.set(self.bytes_acked.get() + bytes_remaining as u64);
 
                     // Leave this segment on the unacknowledged queue.
                     break;
@@ -331,6 +637,8 @@ impl Sender {
                 }
 
                 bytes_remaining -= segment.bytes.len();
+                self.bytes_acked
+                    .set(self.bytes_acked.get() + segment.bytes.len() as u64);
             } else {
                 debug_assert!(false); // Shouldn't have bytes_remaining with no segments remaining in unacked_queue.
             }
@@ -339,6 +647,72 @@ impl Sender {
             // TODO: Mark the send operation associated with this buffer as complete, so the user can reuse the buffer.
             self.unacked_queue.borrow_mut().pop_front();
         }
+
+        // Acknowledged data frees room in the send buffer.
+        if bytes_acknowledged > 0 {
+            self.wake_send_waiters();
+        }
+    }
+
+    // Whether the unsent queue is too long to accept more data.
+    pub fn is_unsent_queue_full(&self) -> bool {
+        self.unsent_queue.borrow().len() > UNSENT_QUEUE_CUTOFF
+    }
+
+    // Number of bytes in the unsent queue.
+    pub fn unsent_bytes(&self) -> usize {
+        self.unsent_queue.borrow().iter().map(|buf| buf.len()).sum()
+    }
+
+    // Whether everything that was queued for sending, including any FIN, was sent and acknowledged.
+    pub fn is_all_acked(&self) -> bool {
+        self.unsent_queue.borrow().is_empty() && self.unacked_queue.borrow().is_empty()
+    }
+
+    // Number of segments that were sent but not acknowledged yet.
+    pub fn unacked_segments(&self) -> usize {
+        self.unacked_queue.borrow().len()
+    }
+
+    // Number of bytes of data that our peer acknowledged so far.
+    pub fn get_bytes_acked(&self) -> u64 {
+        self.bytes_acked.get()
+    }
+
+    // Number of segments that were retransmitted so far.
+    pub fn get_retransmits(&self) -> u64 {
+        self.retransmits.get()
+    }
+
+    // Segments in the unacknowledged (a.k.a. retransmission) queue, from the oldest to the newest.
+    #[cfg(any(test, feature = "tcp-diagnostics"))]
+    pub fn outstanding_segments(&self) -> Vec<UnackedSegmentInfo> {
+        let mut seq_num: SeqNumber = self.send_unacked.get();
+        self.unacked_queue
+            .borrow()
+            .iter()
+            .map(|segment| {
+                // The end-of-send marker holds no data, but its FIN takes up one sequence number.
+                let len: u32 = cmp::max(segment.bytes.len(), 1) as u32;
+                let start: SeqNumber = seq_num;
+                seq_num = seq_num + SeqNumber::from(len);
+                UnackedSegmentInfo {
+                    start,
+                    end: seq_num,
+                    last_tx: segment.last_tx,
+                    retransmits: segment.retransmits,
+                }
+            })
+            .collect()
+    }
+
+    // Number of bytes in the unacknowledged (a.k.a. retransmission) queue.
+    pub fn unacked_bytes(&self) -> usize {
+        self.unacked_queue
+            .borrow()
+            .iter()
+            .map(|segment| segment.bytes.len())
+            .sum()
     }
 
     pub fn pop_one_unsent_byte(&self) -> Option<DemiBuffer> {
@@ -358,30 +732,44 @@ impl Sender {
     }
 
     pub fn pop_unsent(&self, max_bytes: usize) -> Option<(DemiBuffer, bool)> {
-        // TODO: Use a scatter/gather array to coalesce multiple buffers into a single segment.
+        // TODO: Use a scatter/gather array to coalesce multiple buffers into a single segment without copying them.
         let mut unsent_queue = self.unsent_queue.borrow_mut();
-        let mut buf: DemiBuffer = unsent_queue.pop_front()?;
-        let mut do_push: bool = true;
-        let buf_len: usize = buf.len();
-
-        if buf_len > max_bytes {
-            let mut cloned_buf: DemiBuffer = buf.clone();
-
-            buf.adjust(max_bytes)
-                .expect("'buf' should contain at least 'max_bytes'");
-            cloned_buf
-                .trim(buf_len - max_bytes)
-                .expect("'cloned_buf' should contain at least less than its length");
-
-            unsent_queue.push_front(buf);
-            buf = cloned_buf;
+        let (mut buf, mut do_push): (DemiBuffer, bool) = Self::pop_unsent_front(&mut unsent_queue, max_bytes)?;
 
-            // Suppress PSH flag for partial buffers.
-            do_push = false;
+        // Coalesce the buffers that follow into the same segment, up to `max_bytes`. The end-of-send marker has to stay
+        // empty, so it is never coalesced.
+        while buf.len() > 0 && buf.len() < max_bytes {
+            if unsent_queue.front().map_or(true, |next| next.len() == 0) {
+                break;
+            }
+            let (next, next_push): (DemiBuffer, bool) =
+                Self::pop_unsent_front(&mut unsent_queue, max_bytes - buf.len())
+                    .expect("'unsent_queue' should not be empty");
+            buf = Self::concat(&buf, &next).expect("coalesced segment should fit in a buffer");
+            do_push = next_push;
         }
         Some((buf, do_push))
     }
 
+    // Pops up to `max_bytes` off the first buffer of the unsent queue. Also returns whether the whole buffer was
+    // popped, as the PSH flag is suppressed for partial buffers.
+    fn pop_unsent_front(unsent_queue: &mut VecDeque<DemiBuffer>, max_bytes: usize) -> Option<(DemiBuffer, bool)> {
+        let mut buf: DemiBuffer = unsent_queue.pop_front()?;
+        let buf_len: usize = buf.len();
+        if buf_len <= max_bytes {
+            return Some((buf, true));
+        }
+
+        let mut cloned_buf: DemiBuffer = buf.clone();
+        buf.adjust(max_bytes)
+            .expect("'buf' should contain at least 'max_bytes'");
+        cloned_buf
+            .trim(buf_len - max_bytes)
+            .expect("'cloned_buf' should contain at least less than its length");
+        unsent_queue.push_front(buf);
+        Some((cloned_buf, false))
+    }
+
     pub fn top_size_unsent(&self) -> Option<usize> {
         let unsent_queue = self.unsent_queue.borrow_mut();
         Some(unsent_queue.front()?.len())
@@ -410,6 +798,10 @@ impl Sender {
     }
 
     pub fn remote_mss(&self) -> usize {
-        self.mss
+        self.remote_mss
+    }
+
+    pub fn get_window_scale(&self) -> u8 {
+        self.window_scale
     }
 }
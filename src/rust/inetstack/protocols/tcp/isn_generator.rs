@@ -1,35 +1,108 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
-use crate::inetstack::protocols::tcp::SeqNumber;
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::protocols::tcp::SeqNumber,
+    runtime::SharedObject,
+};
 #[allow(unused_imports)]
-use std::{
+use ::std::{
     hash::Hasher,
     net::SocketAddrV4,
     num::Wrapping,
 };
 
-#[allow(dead_code)]
-pub struct IsnGenerator {
+//======================================================================================================================
+// Traits
+//======================================================================================================================
+
+/// Strategy for choosing the initial sequence number (ISN) of new TCP connections.
+///
+/// ISNs must be hard to predict for off-path attackers, otherwise they can inject segments into or reset connections
+/// of others (see RFC 6528). Implementations that return predictable values, such as [DeterministicIsnGenerator], are
+/// insecure and should only be used for testing and research.
+pub trait IsnGenerator {
+    /// Returns the ISN of a new connection from `local` to `remote`.
+    fn next_isn(&mut self, local: &SocketAddrV4, remote: &SocketAddrV4) -> u32;
+
+    /// Replaces the secret nonce that ISNs are derived from. Strategies that do not rely on a secret ignore this.
+    fn reseed(&mut self, _nonce: u32) {}
+}
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Default ISN generator, which hashes the connection addresses with a secret nonce in the spirit of RFC 6528.
+pub struct HashedIsnGenerator {
     nonce: u32,
     counter: Wrapping<u16>,
 }
 
-impl IsnGenerator {
+/// ISN generator that returns `first_isn` for the first connection and increments it by `increment` for every
+/// following one, regardless of addresses. This is insecure, see [IsnGenerator].
+pub struct DeterministicIsnGenerator {
+    next_isn: Wrapping<u32>,
+    increment: Wrapping<u32>,
+}
+
+/// ISN generator shared by all TCP sockets of a peer.
+#[derive(Clone)]
+pub struct SharedIsnGenerator(SharedObject<Box<dyn IsnGenerator>>);
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl HashedIsnGenerator {
     pub fn new(nonce: u32) -> Self {
         Self {
             nonce,
             counter: Wrapping(0),
         }
     }
+}
+
+impl DeterministicIsnGenerator {
+    pub fn new(first_isn: u32, increment: u32) -> Self {
+        Self {
+            next_isn: Wrapping(first_isn),
+            increment: Wrapping(increment),
+        }
+    }
+}
+
+impl SharedIsnGenerator {
+    pub fn new(generator: Box<dyn IsnGenerator>) -> Self {
+        Self(SharedObject::<Box<dyn IsnGenerator>>::new(generator))
+    }
 
-    #[cfg(test)]
-    pub fn generate(&mut self, _local: &SocketAddrV4, _remote: &SocketAddrV4) -> SeqNumber {
-        SeqNumber::from(0)
+    /// Replaces the generation strategy. This affects all sockets sharing the target generator.
+    pub fn set(&mut self, generator: Box<dyn IsnGenerator>) {
+        *self.0 = generator;
     }
 
-    #[cfg(not(test))]
+    /// Replaces the secret nonce of the generation strategy, if it has one.
+    pub fn reseed(&mut self, nonce: u32) {
+        self.0.reseed(nonce)
+    }
+
+    /// Generates the ISN of a new connection from `local` to `remote`.
     pub fn generate(&mut self, local: &SocketAddrV4, remote: &SocketAddrV4) -> SeqNumber {
+        SeqNumber::from(self.0.next_isn(local, remote))
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl IsnGenerator for HashedIsnGenerator {
+    fn next_isn(&mut self, local: &SocketAddrV4, remote: &SocketAddrV4) -> u32 {
         let crc: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_CKSUM);
         let mut digest = crc.digest();
         digest.update(&remote.ip().octets());
@@ -40,8 +113,21 @@ impl IsnGenerator {
         digest.update(&local_port.to_be_bytes());
         digest.update(&self.nonce.to_be_bytes());
         let digest = digest.finalize();
-        let isn = SeqNumber::from(digest + self.counter.0 as u32);
+        let isn: u32 = digest.wrapping_add(self.counter.0 as u32);
         self.counter += Wrapping(1);
         isn
     }
+
+    fn reseed(&mut self, nonce: u32) {
+        self.nonce = nonce;
+        self.counter = Wrapping(0);
+    }
+}
+
+impl IsnGenerator for DeterministicIsnGenerator {
+    fn next_isn(&mut self, _local: &SocketAddrV4, _remote: &SocketAddrV4) -> u32 {
+        let isn: Wrapping<u32> = self.next_isn;
+        self.next_isn += self.increment;
+        isn.0
+    }
 }
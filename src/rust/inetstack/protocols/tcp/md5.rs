@@ -0,0 +1,178 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::protocols::{
+        ip::IpProtocol,
+        ipv4::Ipv4Header,
+        tcp::segment::{
+            TcpHeader,
+            TcpOptions2,
+            MIN_TCP_HEADER_SIZE,
+        },
+    },
+    runtime::fail::Fail,
+};
+use ::arrayvec::ArrayVec;
+use ::md5::{
+    Digest,
+    Md5,
+};
+use ::std::fmt;
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Maximum length of a TCP-MD5 key, which is the same as the limit of Linux (i.e. TCP_MD5SIG_MAXKEYLEN).
+pub const TCP_MD5_MAX_KEY_LEN: usize = 80;
+
+/// Length of the digest that is carried in the TCP-MD5 signature option.
+pub const TCP_MD5_DIGEST_LEN: usize = 16;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Key that the segments of a connection are signed with, as described in RFC 2385. Both ends of the connection must be
+/// configured with the same key.
+#[derive(Clone)]
+pub struct TcpMd5Key(ArrayVec<u8, TCP_MD5_MAX_KEY_LEN>);
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl TcpMd5Key {
+    /// Creates a key out of `key`. Fails with `EINVAL` if the key is empty, or longer than [TCP_MD5_MAX_KEY_LEN].
+    pub fn new(key: &[u8]) -> Result<Self, Fail> {
+        if key.is_empty() || key.len() > TCP_MD5_MAX_KEY_LEN {
+            let cause: String = format!("invalid TCP-MD5 key length (len={:?})", key.len());
+            error!("new(): {}", &cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        let mut bytes: ArrayVec<u8, TCP_MD5_MAX_KEY_LEN> = ArrayVec::new();
+        bytes
+            .try_extend_from_slice(key)
+            .expect("key length should have been checked");
+        Ok(Self(bytes))
+    }
+}
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Computes the digest of a TCP segment, whose header (options included) is `header` and whose payload is `data`. As
+/// RFC 2385 prescribes, the digest covers the pseudo-header, the fixed part of the TCP header with a zero checksum, the
+/// payload and the key, in that order. Options are left out, so that the digest can be written in place afterwards.
+pub fn compute_digest(ipv4_hdr: &Ipv4Header, header: &[u8], data: &[u8], key: &TcpMd5Key) -> [u8; TCP_MD5_DIGEST_LEN] {
+    let mut hasher: Md5 = Md5::new();
+
+    // Pseudo-header: source address, destination address, zero, protocol number and segment length.
+    hasher.update(ipv4_hdr.get_src_addr().octets());
+    hasher.update(ipv4_hdr.get_dest_addr().octets());
+    hasher.update([0, IpProtocol::TCP as u8]);
+    hasher.update(((header.len() + data.len()) as u16).to_be_bytes());
+
+    let mut fixed_header: [u8; MIN_TCP_HEADER_SIZE] = [0; MIN_TCP_HEADER_SIZE];
+    fixed_header.copy_from_slice(&header[..MIN_TCP_HEADER_SIZE]);
+    fixed_header[16] = 0;
+    fixed_header[17] = 0;
+    hasher.update(fixed_header);
+
+    hasher.update(data);
+    hasher.update(&key.0[..]);
+
+    let mut digest: [u8; TCP_MD5_DIGEST_LEN] = [0; TCP_MD5_DIGEST_LEN];
+    digest.copy_from_slice(&hasher.finalize());
+    digest
+}
+
+/// Checks the signature of an incoming TCP segment, whose parsed header is `tcp_hdr` and whose raw bytes are `segment`,
+/// against `key`. Segments of a connection that has a key must carry a valid signature, and segments of a connection
+/// that has none must not carry one at all. Fails with `EBADMSG` otherwise.
+pub fn verify_signature(
+    ipv4_hdr: &Ipv4Header,
+    tcp_hdr: &TcpHeader,
+    segment: &[u8],
+    key: Option<&TcpMd5Key>,
+) -> Result<(), Fail> {
+    let signature: Option<&[u8; TCP_MD5_DIGEST_LEN]> = tcp_hdr.iter_options().find_map(|option| match option {
+        TcpOptions2::Md5Signature(signature) => Some(signature),
+        _ => None,
+    });
+    let (key, signature): (&TcpMd5Key, &[u8; TCP_MD5_DIGEST_LEN]) = match (key, signature) {
+        (None, None) => return Ok(()),
+        (Some(key), Some(signature)) => (key, signature),
+        (Some(_), None) => return Err(Fail::new(libc::EBADMSG, "missing TCP-MD5 signature")),
+        (None, Some(_)) => return Err(Fail::new(libc::EBADMSG, "unexpected TCP-MD5 signature")),
+    };
+
+    // The header was parsed already, so the data offset is known to be within the segment.
+    let data_offset: usize = (segment[12] >> 4) as usize * 4;
+    let (header, data): (&[u8], &[u8]) = segment.split_at(data_offset);
+    let digest: [u8; TCP_MD5_DIGEST_LEN] = compute_digest(ipv4_hdr, header, data, key);
+
+    // Compare the whole digest regardless of where it differs, so that the time taken does not tell how much of a
+    // forged signature is right.
+    let diff: u8 = digest
+        .iter()
+        .zip(signature.iter())
+        .fold(0, |diff, (a, b)| diff | (a ^ b));
+    if diff != 0 {
+        return Err(Fail::new(libc::EBADMSG, "invalid TCP-MD5 signature"));
+    }
+    Ok(())
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl fmt::Debug for TcpMd5Key {
+    /// Leaves the key itself out, so that it does not end up in logs.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TcpMd5Key(len={})", self.0.len())
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        TcpMd5Key,
+        TCP_MD5_MAX_KEY_LEN,
+    };
+    use ::anyhow::Result;
+
+    /// Tests that keys that are empty or too long are rejected.
+    #[test]
+    fn test_md5_key_length() -> Result<()> {
+        TcpMd5Key::new(&[0x5a; TCP_MD5_MAX_KEY_LEN])?;
+        for len in [0, TCP_MD5_MAX_KEY_LEN + 1] {
+            match TcpMd5Key::new(&vec![0x5a; len]) {
+                Err(e) if e.errno == libc::EINVAL => (),
+                result => anyhow::bail!("creating the key should have failed with EINVAL: {:?}", result),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Tests that the key is left out of debug output.
+    #[test]
+    fn test_md5_key_debug() -> Result<()> {
+        let key: TcpMd5Key = TcpMd5Key::new(b"secret")?;
+        crate::ensure_eq!(format!("{:?}", key), "TcpMd5Key(len=6)");
+
+        Ok(())
+    }
+}
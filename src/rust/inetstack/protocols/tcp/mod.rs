@@ -1,25 +1,50 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
+mod accept_filter;
 mod active_open;
 pub mod constants;
+mod egress;
 mod established;
-mod isn_generator;
+pub mod isn_generator;
+#[cfg(feature = "tcp-md5")]
+pub mod md5;
 mod passive_open;
 pub mod peer;
 pub mod queue;
 pub mod segment;
 mod sequence_number;
+mod state_counts;
+mod syn_cookie;
+pub mod trace;
 
 #[cfg(test)]
 mod tests;
 
 pub use self::{
-    established::congestion_control,
+    accept_filter::{
+        AcceptFilter,
+        AcceptFilterPolicy,
+    },
+    established::{
+        congestion_control,
+        NegotiatedOptions,
+        State as TcpState,
+        TcpInfo,
+    },
+    passive_open::TcpAcceptReadyHook,
     peer::SharedTcpPeer,
     segment::{
         MAX_TCP_HEADER_SIZE,
         MIN_TCP_HEADER_SIZE,
     },
     sequence_number::SeqNumber,
+    state_counts::TcpStateCounts,
+    syn_cookie::SynCookieStats,
 };
+
+#[cfg(feature = "tcp-md5")]
+pub use self::md5::TcpMd5Key;
+
+#[cfg(any(test, feature = "tcp-diagnostics"))]
+pub use self::established::UnackedSegmentInfo;
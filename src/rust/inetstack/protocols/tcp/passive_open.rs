@@ -19,19 +19,29 @@ use crate::{
         ip::IpProtocol,
         ipv4::Ipv4Header,
         tcp::{
+            accept_filter::AcceptFilter,
             constants::FALLBACK_MSS,
             established::{
                 congestion_control,
                 congestion_control::CongestionControl,
                 EstablishedSocket,
             },
-            isn_generator::IsnGenerator,
+            isn_generator::SharedIsnGenerator,
             segment::{
                 TcpHeader,
                 TcpOptions2,
                 TcpSegment,
             },
+            syn_cookie::{
+                SynCookieGenerator,
+                SynCookieStats,
+            },
+            trace::{
+                SharedTcpTracer,
+                TcpSegmentDirection,
+            },
             SeqNumber,
+            TcpStateCounts,
         },
     },
     runtime::{
@@ -74,25 +84,40 @@ use ::std::{
     time::Duration,
 };
 
+#[cfg(feature = "tcp-md5")]
+use crate::inetstack::protocols::tcp::TcpMd5Key;
+
 //======================================================================================================================
 // Structures
 //======================================================================================================================
 
+/// Callback that is invoked every time that a connection becomes ready to be accepted on a listening socket.
+pub type TcpAcceptReadyHook = Box<dyn Fn()>;
+
 pub struct PassiveSocket {
     connections: HashMap<SocketAddrV4, SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)>>,
     recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)>,
     ready: AsyncQueue<Result<EstablishedSocket, Fail>>,
     max_backlog: usize,
-    isn_generator: IsnGenerator,
+    isn_generator: SharedIsnGenerator,
+    syn_cookie_generator: SynCookieGenerator,
+    syn_cookie_stats: SynCookieStats,
     local: SocketAddrV4,
     runtime: SharedDemiRuntime,
     transport: SharedBox<dyn NetworkRuntime>,
     tcp_config: TcpConfig,
     local_link_addr: MacAddress,
     arp: SharedArpPeer,
+    tracer: SharedTcpTracer,
     dead_socket_tx: mpsc::UnboundedSender<QDesc>,
     yielder_handle: YielderHandle,
     task_handle: Option<TaskHandle>,
+    accept_ready_hook: Option<TcpAcceptReadyHook>,
+    accept_filter: Option<AcceptFilter>,
+    /// Number of handshakes under way, which have sent a SYN+ACK and wait for the ACK.
+    num_syn_received: usize,
+    #[cfg(feature = "tcp-md5")]
+    md5_key: Option<TcpMd5Key>,
 }
 
 #[derive(Clone)]
@@ -112,8 +137,9 @@ impl SharedPassiveSocket {
         tcp_config: TcpConfig,
         local_link_addr: MacAddress,
         arp: SharedArpPeer,
+        tracer: SharedTcpTracer,
         dead_socket_tx: mpsc::UnboundedSender<QDesc>,
-        nonce: u32,
+        isn_generator: SharedIsnGenerator,
     ) -> Result<Self, Fail> {
         let yielder: Yielder = Yielder::new();
         let mut me: Self = Self(SharedObject::<PassiveSocket>::new(PassiveSocket {
@@ -121,16 +147,24 @@ impl SharedPassiveSocket {
             recv_queue,
             ready: AsyncQueue::<Result<EstablishedSocket, Fail>>::default(),
             max_backlog,
-            isn_generator: IsnGenerator::new(nonce),
+            isn_generator,
+            syn_cookie_generator: SynCookieGenerator::new(runtime.get_now()),
+            syn_cookie_stats: SynCookieStats::default(),
             local,
             local_link_addr,
             runtime: runtime.clone(),
             transport,
             tcp_config,
             arp,
+            tracer,
             dead_socket_tx,
             yielder_handle: yielder.get_handle(),
             task_handle: None,
+            accept_ready_hook: None,
+            accept_filter: None,
+            num_syn_received: 0,
+            #[cfg(feature = "tcp-md5")]
+            md5_key: None,
         }));
         let handle =
             runtime.insert_background_coroutine("passive_listening::poll", Box::pin(me.clone().poll(yielder)))?;
@@ -143,11 +177,116 @@ impl SharedPassiveSocket {
         self.local
     }
 
+    /// Checks whether a connection is ready to be accepted, so that an accept would complete right away.
+    pub fn has_ready_connection(&self) -> bool {
+        !self.ready.is_empty()
+    }
+
+    /// Adds the socket, the handshakes that are under way on it and its connections that are ready to be accepted to
+    /// `counts`.
+    pub fn count_states(&self, counts: &mut TcpStateCounts) {
+        counts.listen += 1;
+        counts.syn_received += self.num_syn_received;
+        for result in self.ready.get_values() {
+            if let Ok(socket) = result {
+                counts.add(socket.state());
+            }
+        }
+    }
+
+    /// Returns statistics on the SYN cookies of the socket.
+    pub fn syn_cookie_stats(&self) -> SynCookieStats {
+        self.syn_cookie_stats
+    }
+
+    /// Changes the maximum number of pending connections of the socket. If the backlog shrinks below the number of
+    /// connections that are already pending, these are kept, but new ones are refused until enough of them drain.
+    pub fn set_backlog(&mut self, max_backlog: usize) {
+        self.max_backlog = max_backlog;
+    }
+
+    /// Installs a hook that is invoked once for every connection that becomes ready to be accepted, so that the
+    /// application can accept only when an accept completes right away. A burst of connections invokes the hook once
+    /// per connection, in the order in which they are queued. Failed handshakes that an accept would report also
+    /// invoke the hook. Connections that are already ready when the hook is installed invoke it right away. Passing
+    /// `None` removes the hook.
+    pub fn set_accept_ready_hook(&mut self, hook: Option<TcpAcceptReadyHook>) {
+        self.accept_ready_hook = hook;
+        if let Some(hook) = self.accept_ready_hook.as_ref() {
+            for _ in 0..self.ready.len() {
+                hook();
+            }
+        }
+    }
+
+    /// Installs a filter of the remote addresses that the socket accepts connections from. Connection requests that
+    /// the filter refuses are answered with a RST. Connections that are already established, but not accepted yet, and
+    /// that the filter refuses are reset. Passing `None` removes the filter.
+    pub fn set_accept_filter(&mut self, filter: Option<AcceptFilter>) {
+        self.accept_filter = filter;
+        for _ in 0..self.ready.len() {
+            match self.ready.try_pop() {
+                Some(Ok(socket)) if !self.permits(&socket.endpoints().1) => self.refuse(socket),
+                Some(result) => self.ready.push(result),
+                None => break,
+            }
+        }
+    }
+
+    /// Sets the key that the segments sent by the socket are signed with, and that the connections it accepts inherit.
+    /// Connections that are already established, but not accepted yet, pick the new key up once they are accepted.
+    #[cfg(feature = "tcp-md5")]
+    pub fn set_md5_key(&mut self, key: Option<TcpMd5Key>) {
+        self.md5_key = key;
+    }
+
+    /// Resets an established connection that the accept filter refuses, and forgets about it.
+    fn refuse(&mut self, mut socket: EstablishedSocket) {
+        let remote: SocketAddrV4 = socket.endpoints().1;
+        warn!(
+            "refuse(): remote address refused by the accept filter (remote={:?})",
+            remote
+        );
+        socket.reset(Fail::new(
+            libc::ECONNREFUSED,
+            "remote address refused by the accept filter",
+        ));
+        self.connections.remove(&remote);
+    }
+
+    /// Checks whether the installed filter, if any, lets connections from `remote` through.
+    fn permits(&self, remote: &SocketAddrV4) -> bool {
+        match self.accept_filter.as_ref() {
+            Some(filter) => filter.permits(*remote.ip()),
+            None => true,
+        }
+    }
+
+    /// Queues the outcome of a connection request for accept, and notifies the installed hook, if any. Connections
+    /// from remote addresses that the accept filter refuses are reset instead, which only happens to handshakes that
+    /// were under way when the filter was installed.
+    fn push_ready(&mut self, result: Result<EstablishedSocket, Fail>) {
+        match result {
+            Ok(socket) if !self.permits(&socket.endpoints().1) => self.refuse(socket),
+            result => {
+                self.ready.push(result);
+                if let Some(hook) = self.accept_ready_hook.as_ref() {
+                    hook();
+                }
+            },
+        }
+    }
+
     /// Accept a new connection by fetching one from the queue of requests, blocking if there are no new requests.
     pub async fn do_accept(&mut self, yielder: Yielder) -> Result<EstablishedSocket, Fail> {
         self.ready.pop(&yielder).await?
     }
 
+    /// Takes a connection that is ready to be accepted, without waiting. Returns `None` if there is none.
+    pub fn try_accept(&mut self) -> Result<Option<EstablishedSocket>, Fail> {
+        self.ready.try_pop().transpose()
+    }
+
     async fn poll(mut self, yielder: Yielder) {
         loop {
             let (ipv4_hdr, tcp_hdr, buf) = match self.recv_queue.pop(&yielder).await {
@@ -161,6 +300,24 @@ impl SharedPassiveSocket {
                 continue;
             }
 
+            // Refuse new connections from remote addresses that the accept filter does not let through.
+            if !self.permits(&remote) {
+                warn!(
+                    "poll(): remote address refused by the accept filter (remote={:?})",
+                    remote
+                );
+                if !tcp_hdr.rst {
+                    self.send_rst(&remote, tcp_hdr);
+                }
+                continue;
+            }
+
+            // An ACK may complete a connection for which we sent a SYN cookie.
+            if self.tcp_config.get_syn_cookies() && tcp_hdr.ack && !tcp_hdr.syn && !tcp_hdr.rst {
+                self.handle_syn_cookie_ack(remote, ipv4_hdr, tcp_hdr, buf);
+                continue;
+            }
+
             // If not a SYN, then this packet is not for a new connection and we throw it away.
             if !tcp_hdr.syn || tcp_hdr.ack || tcp_hdr.rst {
                 let cause: String = format!(
@@ -181,6 +338,12 @@ impl SharedPassiveSocket {
         debug!("Received SYN: {:?}", tcp_hdr);
         let inflight_len: usize = self.connections.len();
         if inflight_len + self.ready.len() >= self.max_backlog {
+            // Answer with a SYN cookie, so that no state is kept until the connection is established. This is pointless
+            // if no established connection can be queued anyway.
+            if self.tcp_config.get_syn_cookies() && self.ready.len() < self.max_backlog {
+                self.send_syn_cookie(remote, tcp_hdr);
+                return;
+            }
             let cause: String = format!(
                 "backlog full (inflight={}, ready={}, backlog={})",
                 inflight_len,
@@ -201,14 +364,15 @@ impl SharedPassiveSocket {
         let yielder: Yielder = Yielder::new();
         let recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)> =
             SharedAsyncQueue::<(Ipv4Header, TcpHeader, DemiBuffer)>::default();
-        let future = self.clone().send_syn_ack_and_wait_for_ack(
-            remote,
-            remote_isn,
-            local_isn,
-            tcp_hdr,
-            recv_queue.clone(),
-            yielder,
-        );
+        let mut me: Self = self.clone();
+        let handshake_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)> = recv_queue.clone();
+        let future = async move {
+            me.clone()
+                .send_syn_ack_and_wait_for_ack(remote, remote_isn, local_isn, tcp_hdr, handshake_queue, yielder)
+                .await;
+            // The handshake is over, whether the connection was established or not.
+            me.num_syn_received -= 1;
+        };
         match self
             .runtime
             .insert_background_coroutine("Inetstack::TCP::passiveopen::background", Box::pin(future))
@@ -220,10 +384,89 @@ impl SharedPassiveSocket {
                 return;
             },
         };
-        // TODO: Clean up the connections table once we have merged all of the routing tables into one.
+        self.num_syn_received += 1;
+        // TODO: Clean up the connections table once we have merged all of the routing tables into one. Only half-open
+        // connections whose handshake times out are removed for now.
         self.connections.insert(remote, recv_queue);
     }
 
+    /// Sends a SYN+ACK segment to `remote` whose initial sequence number is a SYN cookie, in response to the SYN
+    /// segment described by `tcp_hdr`.
+    fn send_syn_cookie(&mut self, remote: SocketAddrV4, tcp_hdr: TcpHeader) {
+        // Query link address for destination, without waiting as we do not keep any state for this connection.
+        let remote_link_addr: MacAddress = match self.arp.try_query(remote.ip().clone()) {
+            Some(link_addr) => link_addr,
+            None => {
+                let cause: String = format!("missing ARP entry (remote={})", remote.ip());
+                warn!("send_syn_cookie(): {}", &cause);
+                return;
+            },
+        };
+
+        let mss: usize = tcp_hdr.get_mss().unwrap_or(FALLBACK_MSS);
+        let local_isn: SeqNumber =
+            self.syn_cookie_generator
+                .generate(&self.local, &remote, tcp_hdr.seq_num, mss, self.runtime.get_now());
+        self.syn_cookie_stats.issued += 1;
+
+        // The window scale cannot be recovered from the cookie, so do not offer it.
+        let segment: TcpSegment = self.new_syn_ack_segment(local_isn, tcp_hdr.seq_num, remote, remote_link_addr, false);
+        debug!("Sending SYN+ACK with SYN cookie: {:?}", segment.tcp_hdr);
+        self.tracer.trace(TcpSegmentDirection::Sent, &segment.tcp_hdr, 0);
+        self.transport.transmit(Box::new(segment));
+    }
+
+    /// Establishes a connection with `remote` if the ACK segment described by `tcp_hdr` acknowledges a valid SYN
+    /// cookie. Otherwise, resets it.
+    fn handle_syn_cookie_ack(
+        &mut self,
+        remote: SocketAddrV4,
+        ipv4_hdr: Ipv4Header,
+        tcp_hdr: TcpHeader,
+        buf: DemiBuffer,
+    ) {
+        let local_isn: SeqNumber = tcp_hdr.ack_num - SeqNumber::from(1);
+        let remote_isn: SeqNumber = tcp_hdr.seq_num - SeqNumber::from(1);
+        let mss: Option<usize> =
+            self.syn_cookie_generator
+                .validate(&self.local, &remote, remote_isn, local_isn, self.runtime.get_now());
+        let mss: usize = match mss {
+            Some(mss) if self.ready.len() < self.max_backlog => mss,
+            _ => {
+                let cause: String = format!("invalid or unexpected SYN cookie (remote={:?})", remote);
+                warn!("handle_syn_cookie_ack(): {}", cause);
+                self.syn_cookie_stats.rejected += 1;
+                self.send_rst(&remote, tcp_hdr);
+                return;
+            },
+        };
+
+        let window_size: u16 = tcp_hdr.window_size;
+        let mut recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)> =
+            SharedAsyncQueue::<(Ipv4Header, TcpHeader, DemiBuffer)>::default();
+        // If there is data with the ACK, deliver it.
+        if !buf.is_empty() {
+            recv_queue.push((ipv4_hdr, tcp_hdr, buf));
+        }
+        match self.new_established_socket(
+            remote,
+            local_isn,
+            remote_isn,
+            window_size,
+            None,
+            mss,
+            recv_queue.clone(),
+        ) {
+            Ok(socket) => {
+                self.syn_cookie_stats.validated += 1;
+                // TODO: Clean up the connections table once we have merged all of the routing tables into one.
+                self.connections.insert(remote, recv_queue);
+                self.push_ready(Ok(socket));
+            },
+            Err(e) => self.push_ready(Err(e)),
+        }
+    }
+
     /// Sends a RST segment to `remote`.
     fn send_rst(&mut self, remote: &SocketAddrV4, tcp_hdr: TcpHeader) {
         debug!("send_rst(): sending RST to {:?}", remote);
@@ -265,6 +508,10 @@ impl SharedPassiveSocket {
                 tcp_hdr.ack = true;
                 tcp_hdr.ack_num = ack_num;
             }
+            #[cfg(feature = "tcp-md5")]
+            if let Some(key) = self.md5_key.as_ref() {
+                tcp_hdr.sign_md5(key.clone());
+            }
             TcpSegment {
                 ethernet2_hdr: Ethernet2Header::new(dst_link_addr, self.local_link_addr, EtherType2::Ipv4),
                 ipv4_hdr: Ipv4Header::new(self.local.ip().clone(), remote.ip().clone(), IpProtocol::TCP),
@@ -275,6 +522,7 @@ impl SharedPassiveSocket {
         };
 
         // Send it.
+        self.tracer.trace(TcpSegmentDirection::Sent, &segment.tcp_hdr, 0);
         let pkt: Box<TcpSegment> = Box::new(segment);
         self.transport.transmit(pkt);
     }
@@ -290,28 +538,24 @@ impl SharedPassiveSocket {
     ) {
         // Set up new inflight accept connection.
         let mut remote_window_scale = None;
-        let mut mss = FALLBACK_MSS;
         for option in tcp_hdr.iter_options() {
-            match option {
-                TcpOptions2::WindowScale(w) => {
-                    info!("Received window scale: {:?}", w);
-                    remote_window_scale = Some(*w);
-                },
-                TcpOptions2::MaximumSegmentSize(m) => {
-                    info!("Received advertised MSS: {}", m);
-                    mss = *m as usize;
-                },
-                _ => continue,
+            if let TcpOptions2::WindowScale(w) = option {
+                info!("Received window scale: {:?}", w);
+                remote_window_scale = Some(*w);
             }
         }
+        let mss: usize = tcp_hdr.get_mss().unwrap_or(FALLBACK_MSS);
+        info!("Received advertised MSS: {}", mss);
 
-        let mut handshake_retries: usize = self.tcp_config.get_handshake_retries();
+        // We send the initial SYN+ACK plus up to `syn_ack_retries` retransmissions, each spaced by a fixed handshake
+        // timeout.
+        let mut syn_ack_retries: usize = self.tcp_config.get_syn_ack_retries();
         let handshake_timeout: Duration = self.tcp_config.get_handshake_timeout();
 
         loop {
             // Send the SYN + ACK.
             if let Err(e) = self.send_syn_ack(local_isn, remote_isn, remote).await {
-                self.ready.push(Err(e));
+                self.push_ready(Err(e));
                 return;
             }
 
@@ -345,25 +589,32 @@ impl SharedPassiveSocket {
                 r = ack => match r {
                     // Got an ack
                     Ok(socket) => {
-                        self.ready.push(Ok(socket));
+                        self.push_ready(Ok(socket));
                         return;
                     },
                     Err(e) => {
-                        self.ready.push(Err(e));
+                        self.push_ready(Err(e));
                         return;
                     }
                 },
                 r = timeout => match r {
-                    Ok(()) if handshake_retries > 0  => {
-                        handshake_retries = handshake_retries - 1;
+                    Ok(()) if syn_ack_retries > 0  => {
+                        syn_ack_retries = syn_ack_retries - 1;
                         continue;
                     },
                     Ok(()) => {
-                        self.ready.push(Err(Fail::new(ETIMEDOUT, "handshake timeout")));
+                        // Evict the half-open connection, so that it no longer takes a slot of the backlog. The
+                        // failure is only reported to an accept that is already waiting, as it would otherwise take a
+                        // slot of the backlog until it is accepted.
+                        warn!("send_syn_ack_and_wait_for_ack(): handshake timeout (remote={:?})", remote);
+                        self.connections.remove(&remote);
+                        if self.ready.has_waiters() {
+                            self.push_ready(Err(Fail::new(ETIMEDOUT, "handshake timeout")));
+                        }
                         return;
                     },
                     Err(e) => {
-                        self.ready.push(Err(e));
+                        self.push_ready(Err(e));
                         return;
                     }
                 },
@@ -378,30 +629,50 @@ impl SharedPassiveSocket {
         remote: SocketAddrV4,
     ) -> Result<(), Fail> {
         let remote_link_addr = self.arp.query(remote.ip().clone(), &Yielder::new()).await?;
+        let segment: TcpSegment = self.new_syn_ack_segment(local_isn, remote_isn, remote, remote_link_addr, true);
+        debug!("Sending SYN+ACK: {:?}", segment.tcp_hdr);
+        self.tracer.trace(TcpSegmentDirection::Sent, &segment.tcp_hdr, 0);
+        self.transport.transmit(Box::new(segment));
+        Ok(())
+    }
+
+    /// Builds a SYN+ACK segment for a connection with `remote`, optionally offering window scaling.
+    fn new_syn_ack_segment(
+        &self,
+        local_isn: SeqNumber,
+        remote_isn: SeqNumber,
+        remote: SocketAddrV4,
+        remote_link_addr: MacAddress,
+        window_scale: bool,
+    ) -> TcpSegment {
         let mut tcp_hdr = TcpHeader::new(self.local.port(), remote.port());
         tcp_hdr.syn = true;
         tcp_hdr.seq_num = local_isn;
         tcp_hdr.ack = true;
         tcp_hdr.ack_num = remote_isn + SeqNumber::from(1);
-        tcp_hdr.window_size = self.tcp_config.get_receive_window_size();
+        tcp_hdr.window_size = self.tcp_config.get_initial_receive_window();
 
         let mss = self.tcp_config.get_advertised_mss() as u16;
         tcp_hdr.push_option(TcpOptions2::MaximumSegmentSize(mss));
         info!("Advertising MSS: {}", mss);
 
-        tcp_hdr.push_option(TcpOptions2::WindowScale(self.tcp_config.get_window_scale()));
-        info!("Advertising window scale: {}", self.tcp_config.get_window_scale());
+        if window_scale {
+            tcp_hdr.push_option(TcpOptions2::WindowScale(self.tcp_config.get_window_scale()));
+            info!("Advertising window scale: {}", self.tcp_config.get_window_scale());
+        }
+
+        #[cfg(feature = "tcp-md5")]
+        if let Some(key) = self.md5_key.as_ref() {
+            tcp_hdr.sign_md5(key.clone());
+        }
 
-        debug!("Sending SYN+ACK: {:?}", tcp_hdr);
-        let segment = TcpSegment {
+        TcpSegment {
             ethernet2_hdr: Ethernet2Header::new(remote_link_addr, self.local_link_addr, EtherType2::Ipv4),
             ipv4_hdr: Ipv4Header::new(self.local.ip().clone(), remote.ip().clone(), IpProtocol::TCP),
             tcp_hdr,
             data: None,
             tx_checksum_offload: self.tcp_config.get_rx_checksum_offload(),
-        };
-        self.transport.transmit(Box::new(segment));
-        Ok(())
+        }
     }
 
     async fn wait_for_ack(
@@ -423,6 +694,33 @@ impl SharedPassiveSocket {
             return Err(Fail::new(EBADMSG, "invalid SYN+ACK seq num"));
         }
 
+        // If there is data with the SYN+ACK, deliver it.
+        if !buf.is_empty() {
+            recv_queue.push((ipv4_hdr, tcp_hdr, buf));
+        }
+
+        self.new_established_socket(
+            remote,
+            local_isn,
+            remote_isn,
+            header_window_size,
+            remote_window_scale,
+            mss,
+            recv_queue,
+        )
+    }
+
+    /// Creates the established socket for a connection with `remote` whose three-way handshake has completed.
+    fn new_established_socket(
+        &self,
+        remote: SocketAddrV4,
+        local_isn: SeqNumber,
+        remote_isn: SeqNumber,
+        header_window_size: u16,
+        remote_window_scale: Option<u8>,
+        mss: usize,
+        recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)>,
+    ) -> Result<EstablishedSocket, Fail> {
         let (local_window_scale, remote_window_scale) = match remote_window_scale {
             Some(w) => (self.tcp_config.get_window_scale() as u32, w),
             None => (0, 0),
@@ -444,12 +742,8 @@ impl SharedPassiveSocket {
             local_window_scale, remote_window_scale
         );
 
-        // If there is data with the SYN+ACK, deliver it.
-        if !buf.is_empty() {
-            recv_queue.push((ipv4_hdr, tcp_hdr, buf));
-        }
-
-        let new_socket: EstablishedSocket = EstablishedSocket::new(
+        #[allow(unused_mut)]
+        let mut new_socket: EstablishedSocket = EstablishedSocket::new(
             self.local,
             remote,
             self.runtime.clone(),
@@ -458,6 +752,7 @@ impl SharedPassiveSocket {
             self.local_link_addr,
             self.tcp_config.clone(),
             self.arp.clone(),
+            self.tracer.clone(),
             remote_isn + SeqNumber::from(1),
             self.tcp_config.get_ack_delay_timeout(),
             local_window_size,
@@ -470,6 +765,8 @@ impl SharedPassiveSocket {
             None,
             self.dead_socket_tx.clone(),
         )?;
+        #[cfg(feature = "tcp-md5")]
+        new_socket.set_md5_key(self.md5_key.clone());
 
         Ok(new_socket)
     }
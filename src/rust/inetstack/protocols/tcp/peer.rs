@@ -10,26 +10,61 @@ use crate::{
         arp::SharedArpPeer,
         ipv4::Ipv4Header,
         tcp::{
-            isn_generator::IsnGenerator,
+            egress::SharedTcpEgress,
+            established::SendTicket,
+            isn_generator::{
+                HashedIsnGenerator,
+                IsnGenerator,
+                SharedIsnGenerator,
+            },
             queue::SharedTcpQueue,
             segment::TcpHeader,
+            trace::{
+                SharedTcpTracer,
+                TcpSegmentDirection,
+                TcpTraceHook,
+            },
+            AcceptFilter,
+            NegotiatedOptions,
             SeqNumber,
+            SynCookieStats,
+            TcpAcceptReadyHook,
+            TcpInfo,
+            TcpStateCounts,
         },
     },
     runtime::{
         fail::Fail,
-        memory::DemiBuffer,
+        limits,
+        memory::{
+            sgarray_capacity,
+            DemiBuffer,
+            SharedBufferPool,
+        },
         network::{
-            config::TcpConfig,
+            config::{
+                EphemeralPortExhaustion,
+                TcpConfig,
+            },
             socket::SocketId,
-            types::MacAddress,
+            types::{
+                BufferUsage,
+                ErrorCounters,
+                MacAddress,
+                Readiness,
+            },
             NetworkRuntime,
         },
-        queue::NetworkQueue,
+        queue::{
+            downcast_queue_ptr,
+            pop_into_coroutine,
+            NetworkQueue,
+        },
         scheduler::{
             TaskHandle,
             Yielder,
         },
+        types::demi_sgarray_t,
         Operation,
         OperationResult,
         QDesc,
@@ -56,26 +91,46 @@ use ::std::{
         DerefMut,
     },
     pin::Pin,
-    time::Duration,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 #[cfg(feature = "profiler")]
 use crate::timer;
 
+#[cfg(any(test, feature = "tcp-diagnostics"))]
+use crate::inetstack::protocols::tcp::UnackedSegmentInfo;
+
+#[cfg(feature = "tcp-md5")]
+use crate::inetstack::protocols::tcp::{
+    md5,
+    TcpMd5Key,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Maximum number of ephemeral ports that connect() tries before giving up because all of them collide with an existing
+/// connection to the same remote address.
+const MAX_CONNECT_PORT_ATTEMPTS: usize = 16;
+
 //======================================================================================================================
 // Structures
 //======================================================================================================================
 
 pub struct TcpPeer {
     runtime: SharedDemiRuntime,
-    isn_generator: IsnGenerator,
+    isn_generator: SharedIsnGenerator,
     transport: SharedBox<dyn NetworkRuntime>,
     local_link_addr: MacAddress,
     local_ipv4_addr: Ipv4Addr,
     tcp_config: TcpConfig,
     arp: SharedArpPeer,
-    rng: SmallRng,
     dead_socket_tx: mpsc::UnboundedSender<QDesc>,
+    tracer: SharedTcpTracer,
 }
 
 #[derive(Clone)]
@@ -98,19 +153,48 @@ impl SharedTcpPeer {
         let mut rng: SmallRng = SmallRng::from_seed(rng_seed);
         let nonce: u32 = rng.gen();
         let (tx, _) = mpsc::unbounded();
+        // Route all segments through the send scheduler, so that they can be ordered by the priority of their socket.
+        let egress: SharedTcpEgress = SharedTcpEgress::new(runtime.clone(), transport);
+        let transport: SharedBox<dyn NetworkRuntime> = SharedBox::<dyn NetworkRuntime>::new(Box::new(egress));
         Ok(Self(SharedObject::<TcpPeer>::new(TcpPeer {
-            isn_generator: IsnGenerator::new(nonce),
+            isn_generator: SharedIsnGenerator::new(Box::new(HashedIsnGenerator::new(nonce))),
             runtime,
             transport,
             local_link_addr,
             local_ipv4_addr,
             tcp_config,
             arp,
-            rng,
             dead_socket_tx: tx,
+            tracer: SharedTcpTracer::new(),
         })))
     }
 
+    /// Changes the addresses that the target peer connects from and checks binds against.
+    pub fn set_local_addrs(&mut self, local_link_addr: MacAddress, local_ipv4_addr: Ipv4Addr) {
+        self.local_link_addr = local_link_addr;
+        self.local_ipv4_addr = local_ipv4_addr;
+    }
+
+    /// Installs a hook that is invoked with a record of every TCP segment sent or received by this peer, on all
+    /// sockets. Passing `None` removes the hook. When no hook is installed, no records are built.
+    pub fn set_trace_hook(&mut self, hook: Option<TcpTraceHook>) {
+        self.tracer.set_hook(hook);
+    }
+
+    /// Replaces the strategy that chooses the initial sequence numbers of new connections, on all sockets. By default,
+    /// sequence numbers are hashed from the connection addresses and a nonce drawn from `rng_seed`. Strategies that
+    /// return predictable sequence numbers are insecure, see [IsnGenerator].
+    pub fn set_isn_generator(&mut self, generator: Box<dyn IsnGenerator>) {
+        self.isn_generator.set(generator);
+    }
+
+    /// Draws a new nonce from `rng_seed` for the strategy that chooses the initial sequence numbers of new
+    /// connections. Existing connections are left untouched.
+    pub fn reseed(&mut self, rng_seed: [u8; 32]) {
+        let mut rng: SmallRng = SmallRng::from_seed(rng_seed);
+        self.isn_generator.reseed(rng.gen());
+    }
+
     /// Creates a TCP socket.
     pub fn socket(&mut self) -> Result<QDesc, Fail> {
         let new_queue: SharedTcpQueue = SharedTcpQueue::new(
@@ -119,9 +203,10 @@ impl SharedTcpPeer {
             self.local_link_addr,
             self.tcp_config.clone(),
             self.arp.clone(),
+            self.tracer.clone(),
             self.dead_socket_tx.clone(),
         );
-        let new_qd: QDesc = self.runtime.alloc_queue::<SharedTcpQueue>(new_queue);
+        let new_qd: QDesc = self.runtime.alloc_queue::<SharedTcpQueue>(new_queue)?;
         Ok(new_qd)
     }
 
@@ -144,7 +229,7 @@ impl SharedTcpPeer {
         }
 
         // TODO: Check if we are binding to a non-local address.
-        if *local.ip() != self.local_ipv4_addr {
+        if *local.ip() != self.local_ipv4_addr && !local.ip().is_loopback() {
             let cause: String = format!("cannot bind to non-local address (qd={:?})", qd);
             error!("bind(): {}", cause);
             return Err(Fail::new(libc::EADDRNOTAVAIL, &cause));
@@ -157,13 +242,24 @@ impl SharedTcpPeer {
         }
 
         // Check if this is an ephemeral port.
-        if SharedDemiRuntime::is_private_ephemeral_port(local.port()) {
+        if self.runtime.is_private_ephemeral_port(local.port()) {
             // Allocate ephemeral port from the pool, to leave  ephemeral port allocator in a consistent state.
             self.runtime.reserve_ephemeral_port(local.port())?
         }
 
-        // Issue operation.
-        let ret: Result<(), Fail> = self.get_shared_queue(&qd)?.bind(local);
+        // Issue operation, once the address is claimed on the transport, so that the packets destined to it are
+        // delivered to this stack.
+        let mut queue: SharedTcpQueue = self.get_shared_queue(&qd)?;
+        let ret: Result<(), Fail> = match self.transport.register_flow(local) {
+            Ok(()) => {
+                let ret: Result<(), Fail> = queue.bind(local);
+                if ret.is_err() {
+                    self.unregister_flow(local);
+                }
+                ret
+            },
+            Err(e) => Err(e),
+        };
 
         // Handle return value.
         match ret {
@@ -173,7 +269,7 @@ impl SharedTcpPeer {
             },
             Err(e) => {
                 // Rollback ephemeral port allocation.
-                if SharedDemiRuntime::is_private_ephemeral_port(local.port()) {
+                if self.runtime.is_private_ephemeral_port(local.port()) {
                     if self.runtime.free_ephemeral_port(local.port()).is_err() {
                         warn!("bind(): leaking ephemeral port (port={})", local.port());
                     }
@@ -197,13 +293,27 @@ impl SharedTcpPeer {
                     ));
                 }
             }
-            let nonce: u32 = self.rng.gen();
-            queue.listen(backlog, nonce)
+            queue.listen(backlog, self.isn_generator.clone())
         } else {
             Err(Fail::new(libc::EDESTADDRREQ, "socket is not bound to a local address"))
         }
     }
 
+    /// Changes the backlog of the listening socket `qd`.
+    pub fn set_backlog(&mut self, qd: QDesc, backlog: usize) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.set_backlog(backlog)
+    }
+
+    /// Installs a hook on the listening socket `qd` that is invoked whenever a connection becomes ready to be accepted.
+    pub fn set_accept_ready_hook(&mut self, qd: QDesc, hook: Option<TcpAcceptReadyHook>) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.set_accept_ready_hook(hook)
+    }
+
+    /// Installs a filter of the remote addresses that the listening socket `qd` accepts connections from.
+    pub fn set_accept_filter(&mut self, qd: QDesc, filter: Option<AcceptFilter>) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.set_accept_filter(filter)
+    }
+
     /// Sets up the coroutine for accepting a new connection.
     pub fn accept(&mut self, qd: QDesc) -> Result<QToken, Fail> {
         trace!("accept(): qd={:?}", qd);
@@ -231,29 +341,64 @@ impl SharedTcpPeer {
             Err(e) => return (qd, OperationResult::Failed(e)),
         };
         // Wait for accept to complete.
-        match queue.accept_coroutine(yielder).await {
-            Ok(new_queue) => {
-                // Handle result: If successful, allocate a new queue.
-                let endpoints: (SocketAddrV4, SocketAddrV4) = match new_queue.endpoints() {
-                    Ok(endpoints) => endpoints,
-                    Err(e) => return (qd, OperationResult::Failed(e)),
-                };
-                let new_qd: QDesc = self.runtime.alloc_queue::<SharedTcpQueue>(new_queue.clone());
-                if let Some(existing_qd) = self
-                    .runtime
-                    .insert_socket_id_to_qd(SocketId::Active(endpoints.0, endpoints.1), new_qd)
-                {
-                    // We should panic here because the ephemeral port allocator should not allocate the same port more than
-                    // once.
-                    unreachable!(
-                        "There is already a queue listening on this queue descriptor {:?}",
-                        existing_qd
-                    );
+        let result: Result<(QDesc, SocketAddrV4), Fail> = match queue.accept_coroutine(yielder).await {
+            Ok(new_queue) => self.alloc_accepted_queue(new_queue),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(accepted) => (qd, OperationResult::Accept(accepted)),
+            Err(e) => (qd, OperationResult::Failed(e)),
+        }
+    }
+
+    /// Accepts a connection that is already ready on the listening socket `qd`, without creating a coroutine. Returns
+    /// `None` if no connection is ready, in which case nothing was done.
+    pub fn try_accept(&mut self, qd: QDesc) -> Result<Option<OperationResult>, Fail> {
+        trace!("try_accept(): qd={:?}", qd);
+
+        match self.get_shared_queue(&qd)?.try_accept()? {
+            Some(new_queue) => Ok(Some(OperationResult::Accept(self.alloc_accepted_queue(new_queue)?))),
+            None => Ok(None),
+        }
+    }
+
+    /// Allocates a queue descriptor for the connection `new_queue` that was just accepted, and returns it along with
+    /// the address of the remote peer.
+    fn alloc_accepted_queue(&mut self, mut new_queue: SharedTcpQueue) -> Result<(QDesc, SocketAddrV4), Fail> {
+        let endpoints: (SocketAddrV4, SocketAddrV4) = new_queue.endpoints()?;
+        // The connection holds its own claim on the local address, so that it keeps receiving once the listening
+        // socket is closed.
+        let result: Result<QDesc, Fail> = match self.transport.register_flow(endpoints.0) {
+            Ok(()) => {
+                let result: Result<QDesc, Fail> = self.runtime.alloc_queue::<SharedTcpQueue>(new_queue.clone());
+                if result.is_err() {
+                    self.unregister_flow(endpoints.0);
                 }
-                (qd, OperationResult::Accept((new_qd, endpoints.1)))
+                result
             },
-            Err(e) => (qd, OperationResult::Failed(e)),
+            Err(e) => Err(e),
+        };
+        let new_qd: QDesc = match result {
+            Ok(new_qd) => new_qd,
+            Err(e) => {
+                // Refuse the connection, so that the remote peer does not wait for an application that will never see
+                // it.
+                new_queue.reset(e.clone());
+                return Err(e);
+            },
+        };
+        if let Some(existing_qd) = self
+            .runtime
+            .insert_socket_id_to_qd(SocketId::Active(endpoints.0, endpoints.1), new_qd)
+        {
+            // We should panic here because the ephemeral port allocator should not allocate the same port more than
+            // once.
+            unreachable!(
+                "There is already a queue listening on this queue descriptor {:?}",
+                existing_qd
+            );
         }
+        Ok((new_qd, endpoints.1))
     }
 
     /// Sets up the coroutine for connecting the socket to [remote].
@@ -262,12 +407,32 @@ impl SharedTcpPeer {
         let mut queue: SharedTcpQueue = self.get_shared_queue(&qd)?;
         // Check whether we need to allocate an ephemeral port.
         let local: SocketAddrV4 = match queue.local() {
-            Some(addr) => addr,
-            None => {
-                // TODO: we should free this when closing.
-                // FIXME: https://github.com/microsoft/demikernel/issues/236
-                let local_port: u16 = self.runtime.alloc_ephemeral_port()?;
-                SocketAddrV4::new(self.local_ipv4_addr, local_port)
+            Some(addr) => {
+                // The local address was chosen by the caller, so we cannot pick another one on a collision.
+                if self
+                    .runtime
+                    .get_qd_from_socket_id(&SocketId::Active(addr, remote))
+                    .is_some()
+                {
+                    let cause: String = format!("connection already exists (local={:?}, remote={:?})", addr, remote);
+                    error!("connect(): {}", cause);
+                    return Err(Fail::new(libc::EADDRNOTAVAIL, &cause));
+                }
+                addr
+            },
+            // TODO: we should free this when closing.
+            // FIXME: https://github.com/microsoft/demikernel/issues/236
+            None => match self.alloc_connect_local_addr(remote) {
+                Ok(local) => local,
+                Err(e) => match self.runtime.get_ephemeral_port_exhaustion() {
+                    EphemeralPortExhaustion::Wait(timeout) if e.errno == libc::EADDRNOTAVAIL => {
+                        return self.connect_after_port_wait(qd, remote, timeout)
+                    },
+                    _ => {
+                        error!("connect(): {:?}", e);
+                        return Err(e);
+                    },
+                },
             },
         };
         // Insert the connection to receive incoming packets for this address pair.
@@ -296,6 +461,157 @@ impl SharedTcpPeer {
         queue.connect(local, remote, local_isn, coroutine_constructor)
     }
 
+    /// Picks the local IPv4 address of a connection to `remote`. Connections to the loopback network originate from the
+    /// loopback network too, so that they never leave the stack.
+    fn connect_local_ipv4_addr(&self, remote: SocketAddrV4) -> Ipv4Addr {
+        if remote.ip().is_loopback() {
+            Ipv4Addr::LOCALHOST
+        } else {
+            self.local_ipv4_addr
+        }
+    }
+
+    /// Allocates an ephemeral local address for a connection to `remote`, and claims it on the transport. A connection
+    /// that still lingers (e.g. in TIME_WAIT) may already use the allocated port with the same remote address, in which
+    /// case we retry with a fresh port, up to [MAX_CONNECT_PORT_ATTEMPTS] times.
+    fn alloc_connect_local_addr(&mut self, remote: SocketAddrV4) -> Result<SocketAddrV4, Fail> {
+        // Ports that collide are kept until we are done, so that the allocator does not hand them out again.
+        let mut colliding_ports: Vec<u16> = Vec::new();
+        let mut result: Result<SocketAddrV4, Fail> = {
+            let cause: String = format!("no ephemeral port available for remote address (remote={:?})", remote);
+            Err(Fail::new(libc::EADDRNOTAVAIL, &cause))
+        };
+        for _ in 0..MAX_CONNECT_PORT_ATTEMPTS {
+            let local_port: u16 = match self.runtime.alloc_ephemeral_port() {
+                Ok(port) => port,
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                },
+            };
+            let local: SocketAddrV4 = SocketAddrV4::new(self.connect_local_ipv4_addr(remote), local_port);
+            if self
+                .runtime
+                .get_qd_from_socket_id(&SocketId::Active(local, remote))
+                .is_some()
+            {
+                warn!(
+                    "alloc_connect_local_addr(): connection already exists, retrying (local={:?}, remote={:?})",
+                    local, remote
+                );
+                colliding_ports.push(local_port);
+                continue;
+            }
+            // Another stack that shares the NIC may use the same port, so it collides as well.
+            if let Err(e) = self.transport.register_flow(local) {
+                warn!(
+                    "alloc_connect_local_addr(): cannot claim local address, retrying (local={:?}): {:?}",
+                    local, e
+                );
+                colliding_ports.push(local_port);
+                continue;
+            }
+            result = Ok(local);
+            break;
+        }
+
+        for port in colliding_ports {
+            if let Err(e) = self.runtime.free_ephemeral_port(port) {
+                warn!(
+                    "alloc_connect_local_addr(): failed to release ephemeral port (port={:?}): {:?}",
+                    port, e
+                );
+            }
+        }
+
+        result
+    }
+
+    /// Releases the claim on the local address `local` that was made on the transport.
+    fn unregister_flow(&mut self, local: SocketAddrV4) {
+        if let Err(e) = self.transport.unregister_flow(local) {
+            warn!(
+                "unregister_flow(): leaking claim on local address (local={:?}): {:?}",
+                local, e
+            );
+        }
+    }
+
+    /// Schedules a connect to `remote` that first waits up to `timeout` for an ephemeral port to become available.
+    fn connect_after_port_wait(&mut self, qd: QDesc, remote: SocketAddrV4, timeout: Duration) -> Result<QToken, Fail> {
+        let mut queue: SharedTcpQueue = self.get_shared_queue(&qd)?;
+        let coroutine_constructor = || -> Result<TaskHandle, Fail> {
+            let task_name: String = format!("inetstack::tcp::connect for qd={:?}", qd);
+            let coroutine_factory = |yielder| -> Pin<Box<Operation>> {
+                Box::pin(
+                    self.clone()
+                        .connect_after_port_wait_coroutine(qd, remote, timeout, yielder),
+                )
+            };
+            self.clone()
+                .runtime
+                .insert_coroutine_with_tracking(&task_name, coroutine_factory, qd)
+        };
+
+        queue.connect_deferred(coroutine_constructor)
+    }
+
+    /// Waits until an ephemeral port is available or `timeout` expires, and then runs until the connect to remote is
+    /// made or times out.
+    async fn connect_after_port_wait_coroutine(
+        mut self,
+        qd: QDesc,
+        remote: SocketAddrV4,
+        timeout: Duration,
+        yielder: Yielder,
+    ) -> (QDesc, OperationResult) {
+        let mut queue: SharedTcpQueue = match self.runtime.get_shared_queue(&qd) {
+            Ok(queue) => queue,
+            Err(e) => return (qd, OperationResult::Failed(e)),
+        };
+        let deadline: Instant = self.runtime.get_now() + timeout;
+        let local: SocketAddrV4 = loop {
+            // Ports may also be unavailable because they all collide with lingering connections, so keep waiting.
+            if self.runtime.has_free_ephemeral_ports() {
+                match self.alloc_connect_local_addr(remote) {
+                    Ok(local) => break local,
+                    Err(e) if e.errno == libc::EADDRNOTAVAIL => (),
+                    Err(e) => return self.abort_connect(qd, &mut queue, e),
+                }
+            }
+            if self.runtime.get_now() >= deadline {
+                let cause: String = format!("timed out waiting for an ephemeral port (remote={:?})", remote);
+                error!("connect(): {}", cause);
+                return self.abort_connect(qd, &mut queue, Fail::new(libc::EADDRNOTAVAIL, &cause));
+            }
+            if let Err(e) = yielder.yield_once().await {
+                return self.abort_connect(qd, &mut queue, e);
+            }
+        };
+
+        if let Some(existing_qd) = self.runtime.insert_socket_id_to_qd(SocketId::Active(local, remote), qd) {
+            unreachable!(
+                "There is already a queue listening on this queue descriptor {:?}",
+                existing_qd
+            );
+        }
+        let local_isn: SeqNumber = self.isn_generator.generate(&local, &remote);
+        if let Err(e) = queue.start_connect(local, remote, local_isn) {
+            self.runtime.remove_socket_id_to_qd(&SocketId::Active(local, remote));
+            self.unregister_flow(local);
+            return self.abort_connect(qd, &mut queue, e);
+        }
+        self.connect_coroutine(qd, yielder).await
+    }
+
+    /// Fails a connect on `queue` that never got a local address.
+    fn abort_connect(&self, qd: QDesc, queue: &mut SharedTcpQueue, e: Fail) -> (QDesc, OperationResult) {
+        if let Err(e) = queue.abort_connect() {
+            warn!("connect(): failed to abort connect (qd={:?}): {:?}", qd, e);
+        }
+        (qd, OperationResult::Failed(e))
+    }
+
     /// Runs until the connect to remote is made or times out.
     async fn connect_coroutine(mut self, qd: QDesc, yielder: Yielder) -> (QDesc, OperationResult) {
         // Grab the queue, make sure it hasn't been closed in the meantime.
@@ -313,6 +629,7 @@ impl SharedTcpPeer {
             Ok(()) => (qd, OperationResult::Connect),
             Err(e) => {
                 self.runtime.remove_socket_id_to_qd(&SocketId::Active(local, remote));
+                self.unregister_flow(local);
                 (qd, OperationResult::Failed(e))
             },
         }
@@ -321,10 +638,11 @@ impl SharedTcpPeer {
     /// Pushes immediately to the socket and returns the result asynchronously.
     pub fn push(&mut self, qd: QDesc, buf: DemiBuffer) -> Result<QToken, Fail> {
         let mut queue: SharedTcpQueue = self.get_shared_queue(&qd)?;
-        let coroutine_constructor = || -> Result<TaskHandle, Fail> {
+        let len: usize = buf.len();
+        let coroutine_constructor = |pending: Option<(SendTicket, DemiBuffer)>| -> Result<TaskHandle, Fail> {
             let task_name: String = format!("inetstack::tcp::push for qd={:?}", qd);
             let coroutine_factory =
-                |yielder| -> Pin<Box<Operation>> { Box::pin(self.clone().push_coroutine(qd, yielder)) };
+                |yielder| -> Pin<Box<Operation>> { Box::pin(self.clone().push_coroutine(qd, len, pending, yielder)) };
             self.clone()
                 .runtime
                 .insert_coroutine_with_tracking(&task_name, coroutine_factory, qd)
@@ -333,7 +651,48 @@ impl SharedTcpPeer {
         queue.push(buf, coroutine_constructor)
     }
 
-    async fn push_coroutine(self, qd: QDesc, yielder: Yielder) -> (QDesc, OperationResult) {
+    /// Pushes to the socket without creating a coroutine to report the result. TCP pushes complete as soon as their
+    /// data is queued for sending, so this always returns the result of the push.
+    pub fn try_push(&mut self, qd: QDesc, buf: DemiBuffer) -> Result<Option<OperationResult>, Fail> {
+        let len: usize = buf.len();
+        self.get_shared_queue(&qd)?.try_push(buf)?;
+        Ok(Some(OperationResult::Push(len)))
+    }
+
+    /// Sends the data that is buffered on the socket `qd` right away, instead of waiting for more data to coalesce it
+    /// with.
+    pub fn flush(&mut self, qd: QDesc) -> Result<(), Fail> {
+        trace!("flush(): qd={:?}", qd);
+        self.get_shared_queue(&qd)?.flush()
+    }
+
+    /// Pushes to the socket with a hint that more data is coming, so the data is held back and sent along with the
+    /// data of the next push. The result is returned asynchronously.
+    pub fn push_more(&mut self, qd: QDesc, buf: DemiBuffer) -> Result<QToken, Fail> {
+        let mut queue: SharedTcpQueue = self.get_shared_queue(&qd)?;
+        let len: usize = buf.len();
+        let coroutine_constructor = || -> Result<TaskHandle, Fail> {
+            let task_name: String = format!("inetstack::tcp::push_more for qd={:?}", qd);
+            let coroutine_factory =
+                |yielder| -> Pin<Box<Operation>> { Box::pin(self.clone().push_coroutine(qd, len, None, yielder)) };
+            self.clone()
+                .runtime
+                .insert_coroutine_with_tracking(&task_name, coroutine_factory, qd)
+        };
+
+        queue.push_more(buf, coroutine_constructor)
+    }
+
+    /// Completes a push of `len` bytes. TCP queues all the data of a push for sending, so pushes are never partial. The
+    /// data is `pending` if it did not fit in the send buffer when it was pushed, in which case the push completes once
+    /// it is queued.
+    async fn push_coroutine(
+        self,
+        qd: QDesc,
+        len: usize,
+        pending: Option<(SendTicket, DemiBuffer)>,
+        yielder: Yielder,
+    ) -> (QDesc, OperationResult) {
         // Grab the queue, make sure it hasn't been closed in the meantime.
         // This will bump the Rc refcount so the coroutine can have it's own reference to the shared queue data
         // structure and the SharedTcpQueue will not be freed until this coroutine finishes.
@@ -342,8 +701,8 @@ impl SharedTcpPeer {
             Err(e) => return (qd, OperationResult::Failed(e)),
         };
         // Wait for push to complete.
-        match queue.push_coroutine(yielder).await {
-            Ok(()) => (qd, OperationResult::Push),
+        match queue.push_coroutine(pending, yielder).await {
+            Ok(()) => (qd, OperationResult::Push(len)),
             Err(e) => {
                 warn!("push() qd={:?}: {:?}", qd, &e);
                 (qd, OperationResult::Failed(e))
@@ -367,6 +726,57 @@ impl SharedTcpPeer {
         queue.pop(coroutine_constructor)
     }
 
+    /// Pops up to `size` bytes that were already received on the socket, without creating a coroutine. Returns `None`
+    /// if no data was received yet, in which case nothing was done.
+    pub fn try_pop(&mut self, qd: QDesc, size: Option<usize>) -> Result<Option<OperationResult>, Fail> {
+        match self.get_shared_queue(&qd)?.try_pop(size)? {
+            Some(buf) => Ok(Some(OperationResult::Pop(None, buf))),
+            None => Ok(None),
+        }
+    }
+
+    /// Sets up a coroutine for popping up to `size` bytes from the socket, which returns the data that arrived when
+    /// `timeout` expires instead of waiting for all of it.
+    pub fn pop_with_timeout(&mut self, qd: QDesc, size: usize, timeout: Duration) -> Result<QToken, Fail> {
+        let deadline: Instant = self.runtime.get_now() + timeout;
+        let mut queue: SharedTcpQueue = self.get_shared_queue(&qd)?;
+        let coroutine_constructor = || -> Result<TaskHandle, Fail> {
+            let task_name: String = format!("inetstack::tcp::pop_with_timeout for qd={:?}", qd);
+            let coroutine_factory =
+                |_yielder| -> Pin<Box<Operation>> { Box::pin(self.clone().pop_until_coroutine(qd, size, deadline)) };
+            self.clone()
+                .runtime
+                .insert_coroutine_with_tracking(&task_name, coroutine_factory, qd)
+        };
+
+        queue.pop(coroutine_constructor)
+    }
+
+    /// Pops data from a TCP socket directly into the caller-provided `sga`, which must remain valid until the
+    /// operation completes.
+    pub fn pop_into(&mut self, qd: QDesc, sga: &demi_sgarray_t) -> Result<QToken, Fail> {
+        let size: usize = sgarray_capacity(sga)?.min(limits::POP_SIZE_MAX);
+        debug_assert!(size > 0);
+        let sga: demi_sgarray_t = *sga;
+        let mut queue: SharedTcpQueue = self.get_shared_queue(&qd)?;
+        let buffer_pool: SharedBufferPool = self.runtime.get_buffer_pool();
+        let coroutine_constructor = || -> Result<TaskHandle, Fail> {
+            let task_name: String = format!("inetstack::tcp::pop_into for qd={:?}", qd);
+            let coroutine_factory = |yielder| -> Pin<Box<Operation>> {
+                Box::pin(pop_into_coroutine(
+                    self.clone().pop_coroutine(qd, Some(size), yielder),
+                    sga,
+                    buffer_pool,
+                ))
+            };
+            self.clone()
+                .runtime
+                .insert_coroutine_with_tracking(&task_name, coroutine_factory, qd)
+        };
+
+        queue.pop(coroutine_constructor)
+    }
+
     async fn pop_coroutine(self, qd: QDesc, size: Option<usize>, yielder: Yielder) -> (QDesc, OperationResult) {
         // Grab the queue, make sure it hasn't been closed in the meantime.
         // This will bump the Rc refcount so the coroutine can have it's own reference to the shared queue data
@@ -382,6 +792,17 @@ impl SharedTcpPeer {
         }
     }
 
+    async fn pop_until_coroutine(self, qd: QDesc, size: usize, deadline: Instant) -> (QDesc, OperationResult) {
+        let mut queue: SharedTcpQueue = match self.get_shared_queue(&qd) {
+            Ok(queue) => queue,
+            Err(e) => return (qd, OperationResult::Failed(e)),
+        };
+        match queue.pop_until_coroutine(size, deadline).await {
+            Ok(buf) => (qd, OperationResult::Pop(None, buf)),
+            Err(e) => (qd, OperationResult::Failed(e)),
+        }
+    }
+
     /// Closes a TCP socket.
     pub fn async_close(&mut self, qd: QDesc) -> Result<QToken, Fail> {
         trace!("Closing socket: qd={:?}", qd);
@@ -421,6 +842,9 @@ impl SharedTcpPeer {
                             )
                         },
                     }
+                    match socket_id {
+                        SocketId::Active(local, _) | SocketId::Passive(local) => self.unregister_flow(local),
+                    }
                 }
                 // Free the queue.
                 self.runtime
@@ -437,10 +861,172 @@ impl SharedTcpPeer {
         self.get_shared_queue(&qd)?.remote_mss()
     }
 
+    /// Gets the largest payload that a segment of the socket `qd` can carry without being fragmented on a link with
+    /// the given `mtu`. This accounts for the negotiated MSS, for options and for what path MTU discovery learned.
+    pub fn path_mss(&self, qd: QDesc, mtu: usize) -> Result<usize, Fail> {
+        self.get_shared_queue(&qd)?.path_mss(mtu)
+    }
+
     pub fn current_rto(&self, qd: QDesc) -> Result<Duration, Fail> {
         self.get_shared_queue(&qd)?.current_rto()
     }
 
+    /// Gets the send window of the established connection `qd`, that is, how many bytes the remote peer last said it
+    /// is willing to receive. This is meant for testing and experimentation.
+    pub fn send_window(&self, qd: QDesc) -> Result<u32, Fail> {
+        self.get_shared_queue(&qd)?.send_window()
+    }
+
+    /// Forces the receive window that is advertised to the remote peer on the established connection `qd`. This is
+    /// meant for testing and experimentation with flow control and zero window conditions. If `qd` is listening, the
+    /// connections that it accepts start with this receive window.
+    pub fn set_receive_window(&mut self, qd: QDesc, window_size: u32) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.set_receive_window(window_size)
+    }
+
+    /// Reports how many bytes are currently held in the send and receive buffers of the connection `qd`.
+    pub fn buffer_usage(&self, qd: QDesc) -> Result<BufferUsage, Fail> {
+        self.get_shared_queue(&qd)?.buffer_usage()
+    }
+
+    /// Reads the error counters of the connection `qd`, and clears them afterwards if `reset` is set.
+    pub fn error_counters(&self, qd: QDesc, reset: bool) -> Result<ErrorCounters, Fail> {
+        self.get_shared_queue(&qd)?.error_counters(reset)
+    }
+
+    /// Gets the local link address of the established connection `qd`, and the link address of the next hop towards
+    /// its remote peer. This is meant for debugging address resolution.
+    pub fn link_addrs(&self, qd: QDesc) -> Result<(MacAddress, MacAddress), Fail> {
+        self.get_shared_queue(&qd)?.link_addrs()
+    }
+
+    /// Gathers diagnostics of the connection `qd`, such as its RTT estimates, congestion window and retransmissions.
+    pub fn tcp_info(&self, qd: QDesc) -> Result<TcpInfo, Fail> {
+        self.get_shared_queue(&qd)?.tcp_info()
+    }
+
+    /// Lists the segments of the connection `qd` that were sent but not acknowledged yet, along with when they were last
+    /// sent and how many times they were retransmitted. This is meant for diagnosing retransmission storms, and it is
+    /// only available in test builds and with the `tcp-diagnostics` feature, so that release builds do not pay for
+    /// tracking it.
+    #[cfg(any(test, feature = "tcp-diagnostics"))]
+    pub fn outstanding_segments(&self, qd: QDesc) -> Result<Vec<UnackedSegmentInfo>, Fail> {
+        self.get_shared_queue(&qd)?.outstanding_segments()
+    }
+
+    /// Reports whether operations on the socket `qd` would complete right away.
+    pub fn readiness(&self, qd: QDesc) -> Result<Readiness, Fail> {
+        Ok(self.get_shared_queue(&qd)?.readiness())
+    }
+
+    /// Retrieves and clears the error that a connect on the socket `qd` failed with, if any.
+    pub fn take_socket_error(&mut self, qd: QDesc) -> Result<Option<Fail>, Fail> {
+        Ok(self.get_shared_queue(&qd)?.take_error())
+    }
+
+    /// Closes the established connection `qd` once no data has been sent or received on it for `timeout`, or disables
+    /// the idle timeout if `timeout` is `None`.
+    pub fn set_idle_timeout(&mut self, qd: QDesc, timeout: Option<Duration>) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.set_idle_timeout(timeout)
+    }
+
+    /// Sets the transmit priority of the established connection `qd`. Higher values are more urgent. If `qd` is
+    /// listening, the connections that it accepts start with this priority.
+    pub fn set_tx_priority(&mut self, qd: QDesc, priority: u8) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.set_tx_priority(priority)
+    }
+
+    /// Sets whether the data of small pushes on the socket `qd` is sent right away (i.e. sets TCP_NODELAY). Connections
+    /// that are accepted on a listening socket inherit this option.
+    pub fn set_nodelay(&mut self, qd: QDesc, nodelay: bool) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.set_nodelay(nodelay);
+        Ok(())
+    }
+
+    /// Checks whether the data of small pushes on the socket `qd` is sent right away.
+    pub fn get_nodelay(&self, qd: QDesc) -> Result<bool, Fail> {
+        Ok(self.get_shared_queue(&qd)?.get_nodelay())
+    }
+
+    /// Sets the key that the segments of the socket `qd` are signed and checked with (TCP-MD5), or removes it if `key`
+    /// is `None`. Once a key is set, incoming segments that do not carry a valid signature are dropped. Connections that
+    /// are accepted on a listening socket inherit its key. Fails with `EINVAL` if the key is empty or too long.
+    #[cfg(feature = "tcp-md5")]
+    pub fn set_md5_key(&mut self, qd: QDesc, key: Option<&[u8]>) -> Result<(), Fail> {
+        let key: Option<TcpMd5Key> = key.map(TcpMd5Key::new).transpose()?;
+        self.get_shared_queue(&qd)?.set_md5_key(key);
+        Ok(())
+    }
+
+    /// Checks whether the socket `qd` is listening for incoming connections.
+    pub fn is_listening(&self, qd: QDesc) -> Result<bool, Fail> {
+        Ok(self.get_shared_queue(&qd)?.is_listening())
+    }
+
+    /// Sets whether urgent data received on the established connection `qd` is left in the ordinary stream.
+    pub fn set_oob_inline(&mut self, qd: QDesc, oob_inline: bool) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.set_oob_inline(oob_inline)
+    }
+
+    /// Sets whether data that arrives on the connection `qd` after it was closed locally is discarded.
+    pub fn set_discard_late_data(&mut self, qd: QDesc, discard: bool) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.set_discard_late_data(discard)
+    }
+
+    /// Sets the largest number of bytes held in the send buffer of the established connection `qd` (i.e. sets
+    /// SO_SNDBUF).
+    pub fn set_send_buffer_size(&mut self, qd: QDesc, size: usize) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.set_send_buffer_size(size)
+    }
+
+    /// Gets the largest number of bytes held in the send buffer of the established connection `qd`.
+    pub fn get_send_buffer_size(&self, qd: QDesc) -> Result<usize, Fail> {
+        self.get_shared_queue(&qd)?.get_send_buffer_size()
+    }
+
+    /// Sets the number of bytes that pops wait for on the established connection `qd` (i.e. sets SO_RCVLOWAT).
+    pub fn set_rcvlowat(&mut self, qd: QDesc, low_watermark: usize) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.set_rcvlowat(low_watermark)
+    }
+
+    /// Gets the number of bytes that pops wait for on the established connection `qd`.
+    pub fn get_rcvlowat(&self, qd: QDesc) -> Result<usize, Fail> {
+        self.get_shared_queue(&qd)?.get_rcvlowat()
+    }
+
+    /// Takes the urgent byte that was received out of band on the established connection `qd`.
+    pub fn recv_oob(&mut self, qd: QDesc) -> Result<u8, Fail> {
+        self.get_shared_queue(&qd)?.recv_oob()
+    }
+
+    /// Gets the TCP options that were negotiated during the handshake of the established connection `qd`.
+    pub fn tcp_negotiated_options(&self, qd: QDesc) -> Result<NegotiatedOptions, Fail> {
+        self.get_shared_queue(&qd)?.negotiated_options()
+    }
+
+    /// Returns statistics on the SYN cookies of the listening socket `qd`.
+    pub fn syn_cookie_stats(&self, qd: QDesc) -> Result<SynCookieStats, Fail> {
+        self.get_shared_queue(&qd)?.syn_cookie_stats()
+    }
+
+    /// Counts the TCP sockets of the stack in each state. Connections in TIME-WAIT keep their queue until the state
+    /// ends, so they are counted along with the other ones.
+    pub fn state_counts(&self) -> TcpStateCounts {
+        let mut counts: TcpStateCounts = TcpStateCounts::default();
+        for (_, boxed_queue) in self.runtime.get_qtable().get_values() {
+            if let Ok(queue) = downcast_queue_ptr::<SharedTcpQueue>(boxed_queue) {
+                queue.count_states(&mut counts);
+            }
+        }
+        counts
+    }
+
+    /// Returns the local address of a TCP socket, if it has one. This is the address of the socket for listening
+    /// sockets, and the local endpoint of the connection for the other ones.
+    pub fn local(&self, qd: QDesc) -> Result<Option<SocketAddrV4>, Fail> {
+        Ok(self.get_shared_queue(&qd)?.local())
+    }
+
     pub fn endpoints(&self, qd: QDesc) -> Result<(SocketAddrV4, SocketAddrV4), Fail> {
         self.get_shared_queue(&qd)?.endpoints()
     }
@@ -449,8 +1035,48 @@ impl SharedTcpPeer {
         self.runtime.get_shared_queue::<SharedTcpQueue>(qd)
     }
 
+    /// Lowers the MSS of the connection that sent the segment that triggered an ICMP Fragmentation Needed error, as per
+    /// RFC 1191. `datagram` holds the start of that segment, as embedded in the ICMP message, which is enough to find
+    /// its ports. Segments that are already in flight are not resegmented. They are retransmitted as they are, if lost.
+    pub fn receive_path_mtu(
+        &mut self,
+        src_addr: Ipv4Addr,
+        dst_addr: Ipv4Addr,
+        datagram: DemiBuffer,
+        next_hop_mtu: usize,
+    ) {
+        if datagram.len() < 4 {
+            let cause: String = format!("embedded segment is too small (len={:?})", datagram.len());
+            warn!("receive_path_mtu(): dropping ICMP error: {}", cause);
+            return;
+        }
+        let src_port: u16 = u16::from_be_bytes([datagram[0], datagram[1]]);
+        let dst_port: u16 = u16::from_be_bytes([datagram[2], datagram[3]]);
+        let local: SocketAddrV4 = SocketAddrV4::new(src_addr, src_port);
+        let remote: SocketAddrV4 = SocketAddrV4::new(dst_addr, dst_port);
+
+        match self.runtime.get_qd_from_socket_id(&SocketId::Active(local, remote)) {
+            Some(qd) => {
+                debug!(
+                    "receive_path_mtu(): local={:?} remote={:?} next_hop_mtu={:?}",
+                    local, remote, next_hop_mtu
+                );
+                self.get_shared_queue(&qd)
+                    .expect("queue should exist")
+                    .set_path_mtu(next_hop_mtu);
+            },
+            None => debug!(
+                "receive_path_mtu(): no connection (local={:?}, remote={:?})",
+                local, remote
+            ),
+        }
+    }
+
     /// Processes an incoming TCP segment.
     pub fn receive(&mut self, ip_hdr: Ipv4Header, buf: DemiBuffer) {
+        // Keep the raw segment around, as the signature covers it as it was sent.
+        #[cfg(feature = "tcp-md5")]
+        let segment: DemiBuffer = buf.clone();
         let (tcp_hdr, data): (TcpHeader, DemiBuffer) =
             match TcpHeader::parse(&ip_hdr, buf, self.tcp_config.get_rx_checksum_offload()) {
                 Ok(result) => result,
@@ -461,6 +1087,7 @@ impl SharedTcpPeer {
                 },
             };
         debug!("TCP received {:?}", tcp_hdr);
+        self.tracer.trace(TcpSegmentDirection::Received, &tcp_hdr, data.len());
         let local: SocketAddrV4 = SocketAddrV4::new(ip_hdr.get_dest_addr(), tcp_hdr.dst_port);
         let remote: SocketAddrV4 = SocketAddrV4::new(ip_hdr.get_src_addr(), tcp_hdr.src_port);
 
@@ -483,10 +1110,19 @@ impl SharedTcpPeer {
             },
         };
 
+        let mut queue: SharedTcpQueue = self.get_shared_queue(&qd).expect("queue should exist");
+
+        #[cfg(feature = "tcp-md5")]
+        if let Err(e) = md5::verify_signature(&ip_hdr, &tcp_hdr, &segment[..], queue.md5_key()) {
+            warn!(
+                "receive(): dropping segment (local={:?}, remote={:?}): {:?}",
+                local, remote, e
+            );
+            return;
+        }
+
         // Dispatch to further processing depending on the socket state.
-        self.get_shared_queue(&qd)
-            .expect("queue should exist")
-            .receive(ip_hdr, tcp_hdr, data)
+        queue.receive(ip_hdr, tcp_hdr, data)
     }
 }
 
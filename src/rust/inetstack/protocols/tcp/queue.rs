@@ -9,13 +9,30 @@ use crate::{
     collections::async_queue::SharedAsyncQueue,
     inetstack::{
         protocols::{
-            ipv4::Ipv4Header,
+            ipv4::{
+                Ipv4Header,
+                IPV4_HEADER_MIN_SIZE,
+            },
             tcp::{
                 active_open::SharedActiveOpenSocket,
-                established::EstablishedSocket,
+                established::{
+                    EstablishedSocket,
+                    SendTicket,
+                },
+                isn_generator::SharedIsnGenerator,
                 passive_open::SharedPassiveSocket,
-                segment::TcpHeader,
+                segment::{
+                    TcpHeader,
+                    MIN_TCP_HEADER_SIZE,
+                },
+                trace::SharedTcpTracer,
+                AcceptFilter,
+                NegotiatedOptions,
                 SeqNumber,
+                SynCookieStats,
+                TcpAcceptReadyHook,
+                TcpInfo,
+                TcpStateCounts,
             },
         },
         MacAddress,
@@ -31,6 +48,11 @@ use crate::{
                 state::SocketStateMachine,
                 SocketId,
             },
+            types::{
+                BufferUsage,
+                ErrorCounters,
+                Readiness,
+            },
             NetworkRuntime,
         },
         queue::{
@@ -58,9 +80,18 @@ use ::std::{
         Deref,
         DerefMut,
     },
-    time::Duration,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
+#[cfg(any(test, feature = "tcp-diagnostics"))]
+use crate::inetstack::protocols::tcp::UnackedSegmentInfo;
+
+#[cfg(feature = "tcp-md5")]
+use crate::inetstack::protocols::tcp::TcpMd5Key;
+
 //======================================================================================================================
 // Enumerations
 //======================================================================================================================
@@ -88,7 +119,20 @@ pub struct TcpQueue {
     local_link_addr: MacAddress,
     tcp_config: TcpConfig,
     arp: SharedArpPeer,
+    tracer: SharedTcpTracer,
     dead_socket_tx: mpsc::UnboundedSender<QDesc>,
+    /// Should the data of small pushes be sent right away (i.e. is TCP_NODELAY set)?
+    nodelay: bool,
+    /// Size of the receive buffer that is set on a listening socket, and inherited by the connections that it accepts.
+    receive_window: Option<u32>,
+    /// Transmit priority that is set on a listening socket, and inherited by the connections that it accepts.
+    tx_priority: Option<u8>,
+    /// Error that a connect failed with, which is yet to be retrieved (i.e. SO_ERROR).
+    pending_error: Option<Fail>,
+    /// Key that the segments of the connection are signed and checked with (TCP-MD5), if any. A listening socket
+    /// hands it down to the connections that it accepts.
+    #[cfg(feature = "tcp-md5")]
+    md5_key: Option<TcpMd5Key>,
 }
 
 #[derive(Clone)]
@@ -106,6 +150,7 @@ impl SharedTcpQueue {
         local_link_addr: MacAddress,
         tcp_config: TcpConfig,
         arp: SharedArpPeer,
+        tracer: SharedTcpTracer,
         dead_socket_tx: mpsc::UnboundedSender<QDesc>,
     ) -> Self {
         Self(SharedObject::<TcpQueue>::new(TcpQueue {
@@ -117,7 +162,14 @@ impl SharedTcpQueue {
             local_link_addr,
             tcp_config,
             arp,
+            tracer,
             dead_socket_tx,
+            nodelay: false,
+            receive_window: None,
+            tx_priority: None,
+            pending_error: None,
+            #[cfg(feature = "tcp-md5")]
+            md5_key: None,
         }))
     }
 
@@ -128,6 +180,7 @@ impl SharedTcpQueue {
         local_link_addr: MacAddress,
         tcp_config: TcpConfig,
         arp: SharedArpPeer,
+        tracer: SharedTcpTracer,
         dead_socket_tx: mpsc::UnboundedSender<QDesc>,
     ) -> Self {
         let recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)> = socket.get_recv_queue();
@@ -140,7 +193,14 @@ impl SharedTcpQueue {
             local_link_addr,
             tcp_config,
             arp,
+            tracer,
             dead_socket_tx,
+            nodelay: false,
+            receive_window: None,
+            tx_priority: None,
+            pending_error: None,
+            #[cfg(feature = "tcp-md5")]
+            md5_key: None,
         }))
     }
 
@@ -153,7 +213,7 @@ impl SharedTcpQueue {
     }
 
     /// Sets the target queue to listen for incoming connections.
-    pub fn listen(&mut self, backlog: usize, nonce: u32) -> Result<(), Fail> {
+    pub fn listen(&mut self, backlog: usize, isn_generator: SharedIsnGenerator) -> Result<(), Fail> {
         self.state_machine.prepare(SocketOp::Listen)?;
         let recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)> =
             SharedAsyncQueue::<(Ipv4Header, TcpHeader, DemiBuffer)>::default();
@@ -167,8 +227,9 @@ impl SharedTcpQueue {
             self.tcp_config.clone(),
             self.local_link_addr,
             self.arp.clone(),
+            self.tracer.clone(),
             self.dead_socket_tx.clone(),
-            nonce,
+            isn_generator,
         ) {
             Ok(socket) => {
                 self.socket = Socket::Listening(socket);
@@ -201,16 +262,46 @@ impl SharedTcpQueue {
             _ => unreachable!("State machine check should ensure that this socket is listening"),
         };
         let new_socket: EstablishedSocket = listening_socket.do_accept(yielder).await?;
-        // Insert queue into queue table and get new queue descriptor.
-        let new_queue = Self::new_established(
+        self.new_accepted_queue(new_socket)
+    }
+
+    /// Takes a connection that is ready to be accepted on this listening socket, without waiting. Returns `None` if
+    /// there is none.
+    pub fn try_accept(&mut self) -> Result<Option<SharedTcpQueue>, Fail> {
+        self.state_machine.may_accept()?;
+        let mut listening_socket: SharedPassiveSocket = match self.socket {
+            Socket::Listening(ref listening_socket) => listening_socket.clone(),
+            _ => unreachable!("State machine check should ensure that this socket is listening"),
+        };
+        match listening_socket.try_accept()? {
+            Some(new_socket) => Ok(Some(self.new_accepted_queue(new_socket)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Creates the queue of a connection that was accepted on this listening socket.
+    fn new_accepted_queue(&mut self, new_socket: EstablishedSocket) -> Result<SharedTcpQueue, Fail> {
+        let mut new_queue = Self::new_established(
             new_socket,
             self.runtime.clone(),
             self.transport.clone(),
             self.local_link_addr,
             self.tcp_config.clone(),
             self.arp.clone(),
+            self.tracer.clone(),
             self.dead_socket_tx.clone(),
         );
+        // The new connection inherits the options of the listening socket: TCP_NODELAY, the size of the receive buffer,
+        // the transmit priority and the TCP-MD5 key.
+        new_queue.set_nodelay(self.nodelay);
+        if let Some(window_size) = self.receive_window {
+            new_queue.set_receive_window(window_size)?;
+        }
+        if let Some(priority) = self.tx_priority {
+            new_queue.set_tx_priority(priority)?;
+        }
+        #[cfg(feature = "tcp-md5")]
+        new_queue.set_md5_key(self.md5_key.clone());
         Ok(new_queue)
     }
 
@@ -225,10 +316,55 @@ impl SharedTcpQueue {
         F: FnOnce() -> Result<TaskHandle, Fail>,
     {
         self.state_machine.prepare(SocketOp::Connect)?;
+        if let Err(e) = self.start_connect(local, remote, local_isn) {
+            self.state_machine.abort();
+            return Err(e);
+        }
+        Ok(self
+            .do_generic_sync_control_path_call(coroutine_constructor)?
+            .get_task_id()
+            .into())
+    }
+
+    /// Starts connecting without a local address yet, e.g. because the connect coroutine first waits for an ephemeral
+    /// port to become available. The coroutine must call [Self::start_connect] or [Self::abort_connect] afterwards.
+    pub fn connect_deferred<F>(&mut self, coroutine_constructor: F) -> Result<QToken, Fail>
+    where
+        F: FnOnce() -> Result<TaskHandle, Fail>,
+    {
+        self.state_machine.prepare(SocketOp::Connect)?;
+        Ok(self
+            .do_generic_sync_control_path_call(coroutine_constructor)?
+            .get_task_id()
+            .into())
+    }
+
+    /// Fails a connect that was started with [Self::connect_deferred] before it got a local address.
+    pub fn abort_connect(&mut self) -> Result<(), Fail> {
+        self.state_machine.prepare(SocketOp::Closed)?;
+        self.state_machine.commit();
+        Ok(())
+    }
+
+    /// Refuses a connection that was accepted but could not be handed to the application, by resetting it.
+    pub fn reset(&mut self, cause: Fail) {
+        if let Socket::Established(ref mut socket) = self.socket {
+            socket.reset(cause);
+        }
+    }
+
+    /// Creates the active socket for a connection from `local` to `remote`.
+    pub fn start_connect(
+        &mut self,
+        local: SocketAddrV4,
+        remote: SocketAddrV4,
+        local_isn: SeqNumber,
+    ) -> Result<(), Fail> {
         let recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)> =
             SharedAsyncQueue::<(Ipv4Header, TcpHeader, DemiBuffer)>::default();
         // Create active socket.
-        self.socket = Socket::Connecting(SharedActiveOpenSocket::new(
+        #[allow(unused_mut)]
+        let mut socket: SharedActiveOpenSocket = SharedActiveOpenSocket::new(
             local_isn,
             local,
             remote,
@@ -238,13 +374,14 @@ impl SharedTcpQueue {
             self.tcp_config.clone(),
             self.local_link_addr,
             self.arp.clone(),
+            self.tracer.clone(),
             self.dead_socket_tx.clone(),
-        )?);
+        )?;
+        #[cfg(feature = "tcp-md5")]
+        socket.set_md5_key(self.md5_key.clone());
+        self.socket = Socket::Connecting(socket);
         self.recv_queue = Some(recv_queue);
-        Ok(self
-            .do_generic_sync_control_path_call(coroutine_constructor)?
-            .get_task_id()
-            .into())
+        Ok(())
     }
 
     pub async fn connect_coroutine(&mut self, yielder: Yielder) -> Result<(), Fail> {
@@ -254,8 +391,9 @@ impl SharedTcpQueue {
             _ => unreachable!("State machine check should ensure that this socket is connecting"),
         };
         match connecting_socket.connect(yielder).await {
-            Ok(socket) => {
+            Ok(mut socket) => {
                 self.state_machine.prepare(SocketOp::Established)?;
+                socket.set_nodelay(self.nodelay);
                 self.socket = Socket::Established(socket);
                 self.state_machine.commit();
                 Ok(())
@@ -263,19 +401,46 @@ impl SharedTcpQueue {
             Err(e) => {
                 self.state_machine.prepare(SocketOp::Closed)?;
                 self.state_machine.commit();
+                self.pending_error = Some(e.clone());
                 Err(e)
             },
         }
     }
 
+    /// Retrieves and clears the error that a connect failed with, if any (i.e. gets SO_ERROR).
+    pub fn take_error(&mut self) -> Option<Fail> {
+        self.pending_error.take()
+    }
+
+    /// Pushes `buf` to the target queue. The data is sent synchronously if it fits in the send buffer. Otherwise, it is
+    /// handed to the push coroutine along with a ticket, so that it is sent in turn once there is room for it.
     pub fn push<F>(&mut self, buf: DemiBuffer, coroutine_constructor: F) -> Result<QToken, Fail>
+    where
+        F: FnOnce(Option<(SendTicket, DemiBuffer)>) -> Result<TaskHandle, Fail>,
+    {
+        self.state_machine.may_push()?;
+        let pending: Option<(SendTicket, DemiBuffer)> = match self.socket {
+            Socket::Established(ref mut socket) if socket.can_send_now(buf.len()) => {
+                socket.send(buf)?;
+                None
+            },
+            Socket::Established(ref mut socket) => Some((socket.take_send_ticket(), buf)),
+            _ => unreachable!("State machine check should ensure that this socket is connected"),
+        };
+        Ok(self
+            .do_generic_sync_data_path_call(|| coroutine_constructor(pending))?
+            .get_task_id()
+            .into())
+    }
+
+    /// Like [push](Self::push), but holds the data back until the next push, so that they get sent together.
+    pub fn push_more<F>(&mut self, buf: DemiBuffer, coroutine_constructor: F) -> Result<QToken, Fail>
     where
         F: FnOnce() -> Result<TaskHandle, Fail>,
     {
         self.state_machine.may_push()?;
-        // Send synchronously.
         match self.socket {
-            Socket::Established(ref mut socket) => socket.send(buf)?,
+            Socket::Established(ref mut socket) => socket.send_more(buf)?,
             _ => unreachable!("State machine check should ensure that this socket is connected"),
         };
         Ok(self
@@ -284,8 +449,52 @@ impl SharedTcpQueue {
             .into())
     }
 
-    pub async fn push_coroutine(&mut self, _yielder: Yielder) -> Result<(), Fail> {
-        Ok(())
+    /// Queues `buf` for sending right away, like [push](Self::push), but without creating a coroutine to report the
+    /// result, as TCP pushes complete as soon as their data is queued. Fails with `EAGAIN` if the data does not fit in
+    /// the send buffer.
+    pub fn try_push(&mut self, buf: DemiBuffer) -> Result<(), Fail> {
+        self.state_machine.may_push()?;
+        match self.socket {
+            Socket::Established(ref mut socket) if socket.can_send_now(buf.len()) => socket.send(buf),
+            Socket::Established(_) => Err(Fail::new(libc::EAGAIN, "send buffer is full")),
+            _ => unreachable!("State machine check should ensure that this socket is connected"),
+        }
+    }
+
+    /// Transmits the data that is buffered but not sent yet, including data held back by push_more or by Nagle's
+    /// algorithm, as far as the send and congestion windows allow. The connection is left open.
+    pub fn flush(&mut self) -> Result<(), Fail> {
+        self.state_machine.may_push()?;
+        match self.socket {
+            Socket::Established(ref mut socket) => socket.flush(),
+            _ => unreachable!("State machine check should ensure that this socket is connected"),
+        }
+    }
+
+    /// Completes a push. The data of a push that did not fit in the send buffer is `pending`, along with its ticket,
+    /// and it is sent once its turn comes and there is room for it.
+    pub async fn push_coroutine(
+        &mut self,
+        pending: Option<(SendTicket, DemiBuffer)>,
+        yielder: Yielder,
+    ) -> Result<(), Fail> {
+        // The ticket hands the turn over when it goes out of scope, even if this coroutine is cancelled.
+        let (ticket, buf): (SendTicket, DemiBuffer) = match pending {
+            Some(pending) => pending,
+            None => return Ok(()),
+        };
+        match self.socket {
+            Socket::Established(ref mut socket) | Socket::Closing(ref mut socket) => {
+                socket.wait_for_send_turn(&ticket, buf.len(), &yielder).await?
+            },
+            _ => return Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+        // The socket may have been closed while the push was waiting, in which case its data must not follow the FIN.
+        self.state_machine.may_push()?;
+        match self.socket {
+            Socket::Established(ref mut socket) => socket.send(buf),
+            _ => unreachable!("State machine check should ensure that this socket is connected"),
+        }
     }
 
     pub fn pop<F>(&mut self, coroutine_constructor: F) -> Result<QToken, Fail>
@@ -307,6 +516,23 @@ impl SharedTcpQueue {
         }
     }
 
+    /// Pops up to `size` bytes that were already received, without waiting. Returns `None` if there are none.
+    pub fn try_pop(&mut self, size: Option<usize>) -> Result<Option<DemiBuffer>, Fail> {
+        self.state_machine.may_pop()?;
+        match self.socket {
+            Socket::Established(ref mut socket) => socket.try_pop(size),
+            _ => unreachable!("State machine check should ensure that this socket is connected"),
+        }
+    }
+
+    pub async fn pop_until_coroutine(&mut self, size: usize, deadline: Instant) -> Result<DemiBuffer, Fail> {
+        self.state_machine.may_pop()?;
+        match self.socket {
+            Socket::Established(ref mut socket) => socket.pop_until(size, deadline).await,
+            _ => unreachable!("State machine check should ensure that this socket is connected"),
+        }
+    }
+
     pub fn async_close<F>(&mut self, coroutine_constructor: F) -> Result<QToken, Fail>
     where
         F: FnOnce() -> Result<TaskHandle, Fail>,
@@ -366,6 +592,22 @@ impl SharedTcpQueue {
         }
     }
 
+    /// Lowers the MSS of the connection to fit in `path_mtu`. This is ignored if the connection is not established.
+    pub fn set_path_mtu(&mut self, path_mtu: usize) {
+        if let Socket::Established(ref mut socket) | Socket::Closing(ref mut socket) = self.socket {
+            socket.set_path_mtu(path_mtu);
+        }
+    }
+
+    /// Gets the largest payload that a segment can carry without being fragmented on a link with the given `mtu`. For
+    /// sockets that are not connected yet, this only accounts for the headers, since the MSS is not known.
+    pub fn path_mss(&self, mtu: usize) -> Result<usize, Fail> {
+        match self.socket {
+            Socket::Established(ref socket) | Socket::Closing(ref socket) => Ok(socket.path_mss(mtu)),
+            _ => Ok(mtu.saturating_sub(IPV4_HEADER_MIN_SIZE as usize + MIN_TCP_HEADER_SIZE)),
+        }
+    }
+
     pub fn current_rto(&self) -> Result<Duration, Fail> {
         match self.socket {
             Socket::Established(ref socket) => Ok(socket.current_rto()),
@@ -373,6 +615,269 @@ impl SharedTcpQueue {
         }
     }
 
+    pub fn send_window(&self) -> Result<u32, Fail> {
+        match self.socket {
+            Socket::Established(ref socket) => Ok(socket.send_window()),
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    pub fn set_receive_window(&mut self, window_size: u32) -> Result<(), Fail> {
+        match self.socket {
+            Socket::Established(ref mut socket) => {
+                socket.set_receive_window(window_size);
+                Ok(())
+            },
+            Socket::Listening(_) => {
+                self.receive_window = Some(window_size);
+                Ok(())
+            },
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    pub fn buffer_usage(&self) -> Result<BufferUsage, Fail> {
+        match self.socket {
+            Socket::Established(ref socket) | Socket::Closing(ref socket) => Ok(socket.buffer_usage()),
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    pub fn error_counters(&mut self, reset: bool) -> Result<ErrorCounters, Fail> {
+        match self.socket {
+            Socket::Established(ref mut socket) | Socket::Closing(ref mut socket) => Ok(socket.error_counters(reset)),
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    pub fn tcp_info(&self) -> Result<TcpInfo, Fail> {
+        match self.socket {
+            Socket::Established(ref socket) | Socket::Closing(ref socket) => Ok(socket.tcp_info()),
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    #[cfg(any(test, feature = "tcp-diagnostics"))]
+    pub fn outstanding_segments(&self) -> Result<Vec<UnackedSegmentInfo>, Fail> {
+        match self.socket {
+            Socket::Established(ref socket) | Socket::Closing(ref socket) => Ok(socket.outstanding_segments()),
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    /// Adds the connections of the target queue to `counts`, along with the handshakes that are under way if it is
+    /// listening. Connects that failed already are left out.
+    pub fn count_states(&self, counts: &mut TcpStateCounts) {
+        match self.socket {
+            Socket::Listening(ref socket) => socket.count_states(counts),
+            Socket::Connecting(_) if self.state_machine.may_connect().is_ok() => counts.syn_sent += 1,
+            Socket::Established(ref socket) | Socket::Closing(ref socket) => counts.add(socket.state()),
+            _ => (),
+        }
+    }
+
+    pub fn link_addrs(&self) -> Result<(MacAddress, MacAddress), Fail> {
+        match self.socket {
+            Socket::Established(ref socket) | Socket::Closing(ref socket) => socket.link_addrs(),
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    /// Reports whether operations on this socket would complete right away. Listening sockets are readable when a
+    /// connection is ready to be accepted, and sockets that are not connected are never ready.
+    pub fn readiness(&self) -> Readiness {
+        match self.socket {
+            Socket::Established(ref socket) | Socket::Closing(ref socket) => socket.readiness(),
+            Socket::Listening(ref socket) => Readiness {
+                readable: socket.has_ready_connection(),
+                ..Readiness::default()
+            },
+            // A connect in progress is neither readable nor writable. Once it fails, the socket is hung up, and the
+            // error is reported until it is retrieved.
+            Socket::Connecting(_) => Readiness {
+                hangup: self.state_machine.may_connect().is_err(),
+                error: self.pending_error.is_some(),
+                ..Readiness::default()
+            },
+            _ => Readiness::default(),
+        }
+    }
+
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) -> Result<(), Fail> {
+        match self.socket {
+            Socket::Established(ref mut socket) => {
+                socket.set_idle_timeout(timeout);
+                Ok(())
+            },
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    pub fn set_tx_priority(&mut self, priority: u8) -> Result<(), Fail> {
+        match self.socket {
+            Socket::Established(ref mut socket) => {
+                socket.set_tx_priority(priority);
+                Ok(())
+            },
+            Socket::Listening(_) => {
+                self.tx_priority = Some(priority);
+                Ok(())
+            },
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    /// Sets whether the data of small pushes is sent right away (i.e. sets TCP_NODELAY). Otherwise, small pushes are
+    /// held back while data is in flight, so that they are coalesced into full-sized segments. The option may be set
+    /// in any state, and it is applied to the connection once it is established.
+    pub fn set_nodelay(&mut self, nodelay: bool) {
+        self.nodelay = nodelay;
+        if let Socket::Established(ref mut socket) | Socket::Closing(ref mut socket) = self.socket {
+            socket.set_nodelay(nodelay);
+        }
+    }
+
+    /// Checks whether the data of small pushes is sent right away.
+    pub fn get_nodelay(&self) -> bool {
+        self.nodelay
+    }
+
+    /// Sets the key that the segments of the connection are signed and checked with (i.e. sets TCP_MD5SIG). Passing
+    /// `None` removes the key. The key may be set in any state, although both ends should have it before the
+    /// connection is established, so that the handshake is signed too.
+    #[cfg(feature = "tcp-md5")]
+    pub fn set_md5_key(&mut self, key: Option<TcpMd5Key>) {
+        match self.socket {
+            Socket::Listening(ref mut socket) => socket.set_md5_key(key.clone()),
+            Socket::Connecting(ref mut socket) => socket.set_md5_key(key.clone()),
+            Socket::Established(ref mut socket) | Socket::Closing(ref mut socket) => socket.set_md5_key(key.clone()),
+            Socket::Unbound | Socket::Bound(_) => (),
+        }
+        self.md5_key = key;
+    }
+
+    /// Returns the key that the segments of the connection are signed and checked with, if any.
+    #[cfg(feature = "tcp-md5")]
+    pub fn md5_key(&self) -> Option<&TcpMd5Key> {
+        self.md5_key.as_ref()
+    }
+
+    /// Checks whether the target queue is listening for incoming connections (i.e. gets SO_ACCEPTCONN).
+    pub fn is_listening(&self) -> bool {
+        self.state_machine.is_listening()
+    }
+
+    pub fn set_oob_inline(&mut self, oob_inline: bool) -> Result<(), Fail> {
+        match self.socket {
+            Socket::Established(ref mut socket) => {
+                socket.set_oob_inline(oob_inline);
+                Ok(())
+            },
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    pub fn set_discard_late_data(&mut self, discard: bool) -> Result<(), Fail> {
+        match self.socket {
+            Socket::Established(ref mut socket) | Socket::Closing(ref mut socket) => {
+                socket.set_discard_late_data(discard);
+                Ok(())
+            },
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    pub fn recv_oob(&mut self) -> Result<u8, Fail> {
+        match self.socket {
+            Socket::Established(ref mut socket) => socket.recv_oob(),
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    /// Sets the largest number of bytes held in the send buffer (i.e. sets SO_SNDBUF).
+    pub fn set_send_buffer_size(&mut self, size: usize) -> Result<(), Fail> {
+        if size == 0 {
+            let cause: String = format!("send buffer cannot be empty");
+            error!("set_send_buffer_size(): {}", &cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        match self.socket {
+            Socket::Established(ref mut socket) => {
+                socket.set_send_buffer_size(size);
+                Ok(())
+            },
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    pub fn get_send_buffer_size(&self) -> Result<usize, Fail> {
+        match self.socket {
+            Socket::Established(ref socket) => Ok(socket.get_send_buffer_size()),
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    pub fn set_rcvlowat(&mut self, low_watermark: usize) -> Result<(), Fail> {
+        match self.socket {
+            Socket::Established(ref mut socket) => {
+                socket.set_rcvlowat(low_watermark);
+                Ok(())
+            },
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    pub fn get_rcvlowat(&self) -> Result<usize, Fail> {
+        match self.socket {
+            Socket::Established(ref socket) => Ok(socket.get_rcvlowat()),
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    pub fn negotiated_options(&self) -> Result<NegotiatedOptions, Fail> {
+        match self.socket {
+            Socket::Established(ref socket) => Ok(socket.negotiated_options()),
+            _ => Err(Fail::new(libc::EINVAL, "connection not established")),
+        }
+    }
+
+    pub fn set_backlog(&mut self, backlog: usize) -> Result<(), Fail> {
+        match self.socket {
+            Socket::Listening(ref mut socket) => {
+                socket.set_backlog(backlog);
+                Ok(())
+            },
+            _ => Err(Fail::new(libc::EINVAL, "socket is not listening")),
+        }
+    }
+
+    pub fn set_accept_ready_hook(&mut self, hook: Option<TcpAcceptReadyHook>) -> Result<(), Fail> {
+        match self.socket {
+            Socket::Listening(ref mut socket) => {
+                socket.set_accept_ready_hook(hook);
+                Ok(())
+            },
+            _ => Err(Fail::new(libc::EINVAL, "socket is not listening")),
+        }
+    }
+
+    pub fn set_accept_filter(&mut self, filter: Option<AcceptFilter>) -> Result<(), Fail> {
+        match self.socket {
+            Socket::Listening(ref mut socket) => {
+                socket.set_accept_filter(filter);
+                Ok(())
+            },
+            _ => Err(Fail::new(libc::EINVAL, "socket is not listening")),
+        }
+    }
+
+    pub fn syn_cookie_stats(&self) -> Result<SynCookieStats, Fail> {
+        match self.socket {
+            Socket::Listening(ref socket) => Ok(socket.syn_cookie_stats()),
+            _ => Err(Fail::new(libc::EINVAL, "socket is not listening")),
+        }
+    }
+
     pub fn endpoints(&self) -> Result<(SocketAddrV4, SocketAddrV4), Fail> {
         match self.socket {
             Socket::Established(ref socket) => Ok(socket.endpoints()),
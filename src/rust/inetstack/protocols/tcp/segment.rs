@@ -6,7 +6,13 @@ use crate::{
         ethernet2::Ethernet2Header,
         ip::IpProtocol,
         ipv4::Ipv4Header,
-        tcp::SeqNumber,
+        tcp::{
+            constants::{
+                MAX_MSS,
+                MIN_MSS,
+            },
+            SeqNumber,
+        },
     },
     runtime::{
         fail::Fail,
@@ -21,9 +27,18 @@ use ::std::{
         Cursor,
         Read,
     },
+    mem,
+    net::Ipv4Addr,
     slice::ChunksExact,
 };
 
+#[cfg(feature = "tcp-md5")]
+use crate::inetstack::protocols::tcp::md5::{
+    self,
+    TcpMd5Key,
+    TCP_MD5_DIGEST_LEN,
+};
+
 pub const MIN_TCP_HEADER_SIZE: usize = 20;
 pub const MAX_TCP_HEADER_SIZE: usize = 60;
 pub const MAX_TCP_OPTIONS: usize = 5;
@@ -37,6 +52,10 @@ pub struct TcpSegment {
 }
 
 impl PacketBuf for TcpSegment {
+    fn ipv4_dst_addr(&self) -> Option<Ipv4Addr> {
+        Some(self.ipv4_hdr.get_dest_addr())
+    }
+
     fn header_size(&self) -> usize {
         self.ethernet2_hdr.compute_size() + self.ipv4_hdr.compute_size() + self.tcp_hdr.compute_size()
     }
@@ -104,6 +123,8 @@ pub enum TcpOptions2 {
         sender_timestamp: u32,
         echo_timestamp: u32,
     },
+    /// TCP-MD5 signature of the segment (RFC 2385).
+    Md5Signature([u8; 16]),
 }
 
 impl TcpOptions2 {
@@ -117,6 +138,7 @@ impl TcpOptions2 {
             SelectiveAcknowlegementPermitted => 2,
             SelectiveAcknowlegement { num_sacks, .. } => 2 + 8 * num_sacks,
             Timestamp { .. } => 10,
+            Md5Signature(..) => 18,
         }
     }
 
@@ -164,6 +186,12 @@ impl TcpOptions2 {
                 buf[6..10].copy_from_slice(&echo_timestamp.to_be_bytes());
                 10
             },
+            Md5Signature(signature) => {
+                buf[0] = 19;
+                buf[1] = 18;
+                buf[2..18].copy_from_slice(signature);
+                18
+            },
         }
     }
 }
@@ -198,6 +226,10 @@ pub struct TcpHeader {
 
     pub num_options: usize,
     pub option_list: [TcpOptions2; MAX_TCP_OPTIONS],
+
+    // Key that the segment is signed with when it is serialized, if any.
+    #[cfg(feature = "tcp-md5")]
+    pub md5_key: Option<TcpMd5Key>,
 }
 
 impl TcpHeader {
@@ -222,6 +254,8 @@ impl TcpHeader {
             urgent_pointer: 0,
             num_options: 0,
             option_list: [TcpOptions2::NoOperation; MAX_TCP_OPTIONS],
+            #[cfg(feature = "tcp-md5")]
+            md5_key: None,
         }
     }
 
@@ -278,30 +312,47 @@ impl TcpHeader {
         let mut option_list: [TcpOptions2; MAX_TCP_OPTIONS] = [TcpOptions2::NoOperation; MAX_TCP_OPTIONS];
 
         if data_offset > MIN_TCP_HEADER_SIZE {
+            let options_len: usize = data_offset - MIN_TCP_HEADER_SIZE;
             let mut option_rdr: Cursor<&[u8]> = Cursor::new(&hdr_buf[MIN_TCP_HEADER_SIZE..data_offset]);
-            while (option_rdr.position() as usize) < data_offset - MIN_TCP_HEADER_SIZE {
+            while (option_rdr.position() as usize) < options_len {
                 let mut temp: [u8; 1] = [0; 1];
                 option_rdr.read_exact(&mut temp)?;
                 let option_kind: u8 = temp[0];
-                let option: TcpOptions2 = match option_kind {
+                match option_kind {
                     0 => break,
                     1 => continue,
+                    _ => (),
+                }
+
+                // Every other option has a length, which covers its kind and length bytes, and which must not run past
+                // the end of the header. Malformed lengths are never trusted to skip ahead.
+                if option_rdr.position() as usize == options_len {
+                    return Err(Fail::new(EBADMSG, "TCP option length is missing"));
+                }
+                option_rdr.read_exact(&mut temp)?;
+                let option_length: u8 = temp[0];
+                if option_length < 2 {
+                    return Err(Fail::new(EBADMSG, "TCP option length too small"));
+                }
+                if option_rdr.position() as usize + option_length as usize - 2 > options_len {
+                    return Err(Fail::new(EBADMSG, "TCP option runs past the header"));
+                }
+
+                let option: TcpOptions2 = match option_kind {
                     2 => {
-                        let mut temp: [u8; 1] = [0; 1];
-                        option_rdr.read_exact(&mut temp)?;
-                        let option_length: u8 = temp[0];
                         if option_length != 4 {
                             return Err(Fail::new(EBADMSG, "MSS size was not 4"));
                         }
                         let mut temp: [u8; 2] = [0; 2];
                         option_rdr.read_exact(&mut temp)?;
+                        // The MSS is only meaningful on SYN segments (RFC 9293), so it is ignored elsewhere.
+                        if !syn {
+                            continue;
+                        }
                         let mss: u16 = u16::from_be_bytes([temp[0], temp[1]]);
                         TcpOptions2::MaximumSegmentSize(mss)
                     },
                     3 => {
-                        let mut temp: [u8; 1] = [0; 1];
-                        option_rdr.read_exact(&mut temp)?;
-                        let option_length: u8 = temp[0];
                         if option_length != 3 {
                             return Err(Fail::new(EBADMSG, "window scale size was not 3"));
                         }
@@ -310,18 +361,12 @@ impl TcpHeader {
                         TcpOptions2::WindowScale(window_scale)
                     },
                     4 => {
-                        let mut temp: [u8; 1] = [0; 1];
-                        option_rdr.read_exact(&mut temp)?;
-                        let option_length: u8 = temp[0];
                         if option_length != 2 {
                             return Err(Fail::new(EBADMSG, "SACK permitted size was not 2"));
                         }
                         TcpOptions2::SelectiveAcknowlegementPermitted
                     },
                     5 => {
-                        let mut temp: [u8; 1] = [0; 1];
-                        option_rdr.read_exact(&mut temp)?;
-                        let option_length: u8 = temp[0];
                         let num_sacks: usize = match option_length {
                             10 | 18 | 26 | 34 => (option_length as usize - 2) / 8,
                             _ => return Err(Fail::new(EBADMSG, "invalid SACK size")),
@@ -340,9 +385,6 @@ impl TcpHeader {
                         TcpOptions2::SelectiveAcknowlegement { num_sacks, sacks }
                     },
                     8 => {
-                        let mut temp: [u8; 1] = [0; 1];
-                        option_rdr.read_exact(&mut temp)?;
-                        let option_length: u8 = temp[0];
                         if option_length != 10 {
                             return Err(Fail::new(EBADMSG, "TCP timestamp size was not 10"));
                         }
@@ -356,8 +398,26 @@ impl TcpHeader {
                             echo_timestamp,
                         }
                     },
-                    _ => return Err(Fail::new(EBADMSG, "invalid TCP option")),
+                    19 => {
+                        if option_length != 18 {
+                            return Err(Fail::new(EBADMSG, "TCP-MD5 signature size was not 18"));
+                        }
+                        let mut signature: [u8; 16] = [0; 16];
+                        option_rdr.read_exact(&mut signature)?;
+                        TcpOptions2::Md5Signature(signature)
+                    },
+                    _ => {
+                        // Options that we do not know are skipped, as RFC 9293 requires.
+                        option_rdr.set_position(option_rdr.position() + option_length as u64 - 2);
+                        continue;
+                    },
                 };
+                let is_duplicate: bool = option_list[..num_options]
+                    .iter()
+                    .any(|other| mem::discriminant(other) == mem::discriminant(&option));
+                if is_duplicate {
+                    return Err(Fail::new(EBADMSG, "duplicate TCP option"));
+                }
                 if num_options >= option_list.len() {
                     return Err(Fail::new(EBADMSG, "too many TCP options provided"));
                 }
@@ -385,6 +445,8 @@ impl TcpHeader {
 
             num_options,
             option_list,
+            #[cfg(feature = "tcp-md5")]
+            md5_key: None,
         };
         buf.adjust(data_offset)
             .expect("buf should contain at least 'data_offset' bytes");
@@ -434,7 +496,13 @@ impl TcpHeader {
         fixed_buf[18..20].copy_from_slice(&self.urgent_pointer.to_be_bytes());
 
         let mut cur_pos: usize = MIN_TCP_HEADER_SIZE;
+        #[cfg(feature = "tcp-md5")]
+        let mut md5_pos: Option<usize> = None;
         for i in 0..self.num_options {
+            #[cfg(feature = "tcp-md5")]
+            if let TcpOptions2::Md5Signature(_) = self.option_list[i] {
+                md5_pos = Some(cur_pos);
+            }
             let bytes_written = self.option_list[i].serialize(&mut buf[cur_pos..]);
             cur_pos += bytes_written;
         }
@@ -448,6 +516,13 @@ impl TcpHeader {
             *byte = 0;
         }
 
+        // Sign the segment before computing the checksum, as the checksum covers the signature.
+        #[cfg(feature = "tcp-md5")]
+        if let (Some(key), Some(pos)) = (self.md5_key.as_ref(), md5_pos) {
+            let digest: [u8; TCP_MD5_DIGEST_LEN] = md5::compute_digest(ipv4_hdr, &buf[..], data, key);
+            buf[(pos + 2)..(pos + 18)].copy_from_slice(&digest);
+        }
+
         // Alright, we've fully filled out the header, time to compute the checksum.
         if !tx_checksum_offload {
             let checksum: u16 = tcp_checksum(ipv4_hdr, &buf[..], data);
@@ -478,10 +553,26 @@ impl TcpHeader {
         (0..self.num_options).map(move |i| &self.option_list[i])
     }
 
+    /// Gets the MSS that the remote peer advertised in this segment, if any. The MSS is clamped to
+    /// [[MIN_MSS], [MAX_MSS]], so that a bogus advertisement (e.g. of zero) cannot make us send tiny segments.
+    pub fn get_mss(&self) -> Option<usize> {
+        self.iter_options().find_map(|option| match option {
+            TcpOptions2::MaximumSegmentSize(mss) => Some((*mss as usize).clamp(MIN_MSS, MAX_MSS)),
+            _ => None,
+        })
+    }
+
     pub fn push_option(&mut self, option: TcpOptions2) {
         self.option_list[self.num_options] = option;
         self.num_options += 1;
     }
+
+    /// Has the segment signed with `key` when it is serialized, by adding a TCP-MD5 signature option to it.
+    #[cfg(feature = "tcp-md5")]
+    pub fn sign_md5(&mut self, key: TcpMd5Key) {
+        self.push_option(TcpOptions2::Md5Signature([0; TCP_MD5_DIGEST_LEN]));
+        self.md5_key = Some(key);
+    }
 }
 
 fn tcp_checksum(ipv4_header: &Ipv4Header, header: &[u8], data: &[u8]) -> u16 {
@@ -0,0 +1,52 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::inetstack::protocols::tcp::TcpState;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Number of TCP sockets in each state, over all sockets of a stack, much like the summary that `ss -s` prints.
+/// Connections that are established but not accepted yet are counted too. Handshakes that are answered with a SYN
+/// cookie keep no state, so they are not counted as SYN-RECEIVED.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TcpStateCounts {
+    /// Sockets that listen for incoming connections.
+    pub listen: usize,
+    /// Connections that sent a SYN, and wait for the SYN+ACK.
+    pub syn_sent: usize,
+    /// Connections that received a SYN, and wait for the ACK of their SYN+ACK.
+    pub syn_received: usize,
+    pub established: usize,
+    pub fin_wait1: usize,
+    pub fin_wait2: usize,
+    pub closing: usize,
+    pub time_wait: usize,
+    pub close_wait: usize,
+    pub last_ack: usize,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl TcpStateCounts {
+    /// Counts one more connection in `state`. Connections that are closed already are left out.
+    pub fn add(&mut self, state: TcpState) {
+        match state {
+            TcpState::Established => self.established += 1,
+            TcpState::FinWait1 => self.fin_wait1 += 1,
+            TcpState::FinWait2 => self.fin_wait2 += 1,
+            TcpState::Closing => self.closing += 1,
+            TcpState::TimeWait => self.time_wait += 1,
+            TcpState::CloseWait => self.close_wait += 1,
+            TcpState::LastAck => self.last_ack += 1,
+            TcpState::Closed => (),
+        }
+    }
+}
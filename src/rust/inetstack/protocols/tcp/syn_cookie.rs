@@ -0,0 +1,241 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::inetstack::protocols::tcp::SeqNumber;
+use ::std::{
+    collections::hash_map::RandomState,
+    hash::{
+        BuildHasher,
+        Hasher,
+    },
+    net::SocketAddrV4,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// MSS values that a SYN cookie can encode. A cookie carries the index of the largest value that does not exceed the
+/// MSS advertised by the remote peer.
+const MSS_TABLE: [u16; 8] = [536, 1200, 1300, 1400, 1440, 1450, 1460, 8960];
+
+/// Number of bits of a SYN cookie that encode the MSS index.
+const MSS_BITS: u32 = 3;
+
+/// Number of bits of a SYN cookie that encode the time counter.
+const COUNTER_BITS: u32 = 5;
+
+/// Time it takes the counter encoded in SYN cookies to tick.
+const COUNTER_PERIOD: Duration = Duration::from_secs(64);
+
+/// Number of counter ticks, after the one in which it was issued, that a SYN cookie remains valid for.
+const MAX_COUNTER_AGE: u32 = 1;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Statistics on the SYN cookies of a listening socket.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SynCookieStats {
+    /// Number of SYN+ACK segments sent with a SYN cookie.
+    pub issued: usize,
+    /// Number of connections established from a valid SYN cookie.
+    pub validated: usize,
+    /// Number of ACK segments dropped because they did not carry a valid SYN cookie.
+    pub rejected: usize,
+}
+
+/// Generates and validates SYN cookies. A SYN cookie is the initial sequence number of a SYN+ACK segment, which
+/// encodes everything needed to establish the connection when the remote peer acknowledges it, so that no state is
+/// kept for connections that have not completed the three-way handshake. From the most to the least significant bits,
+/// a cookie holds a keyed hash of the connection, a coarse time counter and an index into [MSS_TABLE].
+pub struct SynCookieGenerator {
+    /// Secret key of the hash.
+    secret: RandomState,
+    /// Instant from which the time counter is computed.
+    epoch: Instant,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl SynCookieGenerator {
+    /// Creates a SYN cookie generator with a fresh secret key.
+    pub fn new(epoch: Instant) -> Self {
+        Self {
+            secret: RandomState::new(),
+            epoch,
+        }
+    }
+
+    /// Generates the SYN cookie that acknowledges a SYN with sequence number `remote_isn`, sent by `remote` to `local`
+    /// with an advertised MSS of `mss`.
+    pub fn generate(
+        &self,
+        local: &SocketAddrV4,
+        remote: &SocketAddrV4,
+        remote_isn: SeqNumber,
+        mss: usize,
+        now: Instant,
+    ) -> SeqNumber {
+        let mss_index: u32 = MSS_TABLE.iter().rposition(|m| *m as usize <= mss).unwrap_or(0) as u32;
+        let counter: u32 = self.counter(now);
+        let hash: u32 = self.hash(local, remote, remote_isn, counter, mss_index);
+        SeqNumber::from((hash << (COUNTER_BITS + MSS_BITS)) | (counter << MSS_BITS) | mss_index)
+    }
+
+    /// Validates the SYN cookie `cookie` that `remote` acknowledged for a SYN with sequence number `remote_isn`.
+    /// Returns the MSS encoded in the cookie, if it is valid and has not expired.
+    pub fn validate(
+        &self,
+        local: &SocketAddrV4,
+        remote: &SocketAddrV4,
+        remote_isn: SeqNumber,
+        cookie: SeqNumber,
+        now: Instant,
+    ) -> Option<usize> {
+        let cookie: u32 = cookie.into();
+        let mss_index: usize = (cookie & ((1 << MSS_BITS) - 1)) as usize;
+        let counter: u32 = (cookie >> MSS_BITS) & ((1 << COUNTER_BITS) - 1);
+        let age: u32 = self.counter(now).wrapping_sub(counter) & ((1 << COUNTER_BITS) - 1);
+        if age > MAX_COUNTER_AGE {
+            return None;
+        }
+        let hash: u32 =
+            self.hash(local, remote, remote_isn, counter, mss_index as u32) & (u32::MAX >> (COUNTER_BITS + MSS_BITS));
+        if (cookie >> (COUNTER_BITS + MSS_BITS)) != hash {
+            return None;
+        }
+        Some(MSS_TABLE[mss_index] as usize)
+    }
+
+    /// Computes the time counter at `now`.
+    fn counter(&self, now: Instant) -> u32 {
+        let ticks: u64 = now.saturating_duration_since(self.epoch).as_secs() / COUNTER_PERIOD.as_secs();
+        (ticks as u32) & ((1 << COUNTER_BITS) - 1)
+    }
+
+    /// Computes the keyed hash of a connection for the time counter `counter` and the MSS index `mss_index`, so that
+    /// neither can be changed without invalidating the cookie.
+    fn hash(
+        &self,
+        local: &SocketAddrV4,
+        remote: &SocketAddrV4,
+        remote_isn: SeqNumber,
+        counter: u32,
+        mss_index: u32,
+    ) -> u32 {
+        let mut hasher = self.secret.build_hasher();
+        hasher.write(&local.ip().octets());
+        hasher.write_u16(local.port());
+        hasher.write(&remote.ip().octets());
+        hasher.write_u16(remote.port());
+        hasher.write_u32(remote_isn.into());
+        hasher.write_u32(counter);
+        hasher.write_u32(mss_index);
+        hasher.finish() as u32
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        SynCookieGenerator,
+        COUNTER_PERIOD,
+    };
+    use crate::inetstack::protocols::tcp::SeqNumber;
+    use ::anyhow::Result;
+    use ::std::{
+        net::{
+            Ipv4Addr,
+            SocketAddrV4,
+        },
+        time::Instant,
+    };
+
+    const LOCAL: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 2), 80);
+    const REMOTE: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 1), 49152);
+
+    /// Tests that a SYN cookie validates for the connection it was generated for, and carries the MSS.
+    #[test]
+    fn test_syn_cookie_roundtrip() -> Result<()> {
+        let now: Instant = Instant::now();
+        let generator: SynCookieGenerator = SynCookieGenerator::new(now);
+        let remote_isn: SeqNumber = SeqNumber::from(12345);
+
+        let cookie: SeqNumber = generator.generate(&LOCAL, &REMOTE, remote_isn, 1450, now);
+        crate::ensure_eq!(generator.validate(&LOCAL, &REMOTE, remote_isn, cookie, now), Some(1450));
+
+        // The MSS is rounded down to a value that the cookie can encode.
+        let cookie: SeqNumber = generator.generate(&LOCAL, &REMOTE, remote_isn, 1000, now);
+        crate::ensure_eq!(generator.validate(&LOCAL, &REMOTE, remote_isn, cookie, now), Some(536));
+
+        Ok(())
+    }
+
+    /// Tests that a SYN cookie does not validate for another connection, or once tampered with.
+    #[test]
+    fn test_syn_cookie_rejects_mismatch() -> Result<()> {
+        let now: Instant = Instant::now();
+        let generator: SynCookieGenerator = SynCookieGenerator::new(now);
+        let remote_isn: SeqNumber = SeqNumber::from(12345);
+        let cookie: SeqNumber = generator.generate(&LOCAL, &REMOTE, remote_isn, 1450, now);
+
+        let other_remote: SocketAddrV4 = SocketAddrV4::new(*REMOTE.ip(), REMOTE.port() + 1);
+        crate::ensure_eq!(generator.validate(&LOCAL, &other_remote, remote_isn, cookie, now), None);
+        crate::ensure_eq!(
+            generator.validate(&LOCAL, &REMOTE, remote_isn + SeqNumber::from(1), cookie, now),
+            None
+        );
+        crate::ensure_eq!(
+            generator.validate(&LOCAL, &REMOTE, remote_isn, cookie + SeqNumber::from(1 << 8), now),
+            None
+        );
+
+        // The MSS index is covered by the hash, so another MSS cannot be picked.
+        let other_mss_cookie: SeqNumber = SeqNumber::from(u32::from(cookie) ^ 1);
+        crate::ensure_eq!(
+            generator.validate(&LOCAL, &REMOTE, remote_isn, other_mss_cookie, now),
+            None
+        );
+
+        // Cookies from another generator use another secret.
+        let other_generator: SynCookieGenerator = SynCookieGenerator::new(now);
+        crate::ensure_eq!(other_generator.validate(&LOCAL, &REMOTE, remote_isn, cookie, now), None);
+
+        Ok(())
+    }
+
+    /// Tests that a SYN cookie expires after two counter periods.
+    #[test]
+    fn test_syn_cookie_expires() -> Result<()> {
+        let now: Instant = Instant::now();
+        let generator: SynCookieGenerator = SynCookieGenerator::new(now);
+        let remote_isn: SeqNumber = SeqNumber::from(12345);
+        let cookie: SeqNumber = generator.generate(&LOCAL, &REMOTE, remote_isn, 1450, now);
+
+        let later: Instant = now + COUNTER_PERIOD;
+        crate::ensure_eq!(
+            generator.validate(&LOCAL, &REMOTE, remote_isn, cookie, later),
+            Some(1450)
+        );
+        let too_late: Instant = now + 2 * COUNTER_PERIOD;
+        crate::ensure_eq!(generator.validate(&LOCAL, &REMOTE, remote_isn, cookie, too_late), None);
+
+        Ok(())
+    }
+}
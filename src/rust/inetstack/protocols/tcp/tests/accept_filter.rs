@@ -0,0 +1,95 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::{
+            segment::TcpHeader,
+            tests::setup::{
+                answer_syn,
+                exchange,
+                new_listener,
+                parse_tcp_header,
+            },
+            AcceptFilter,
+            AcceptFilterPolicy,
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        OperationResult,
+        QDesc,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    },
+    time::Instant,
+};
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that a connection request from a denied prefix is refused with a RST, while one from an allowed prefix is
+/// accepted.
+#[test]
+fn test_accept_filter_refuses_denied_prefix() -> Result<()> {
+    let now: Instant = Instant::now();
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let (mut server, listen_qd): (SharedEngine, QDesc) = new_listener(now, listen_addr, 4)?;
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+
+    let deny: AcceptFilter = AcceptFilter::new(AcceptFilterPolicy::Deny, vec![(Ipv4Addr::new(192, 168, 1, 0), 24)])?;
+    server.tcp_set_accept_filter(listen_qd, Some(deny))?;
+    let answer: TcpHeader = parse_tcp_header(answer_syn(&mut server, &mut client, listen_addr)?)?;
+    crate::ensure_eq!(answer.rst, true);
+    crate::ensure_eq!(server.tcp_try_accept(listen_qd)?.is_none(), true);
+
+    let allow: AcceptFilter = AcceptFilter::new(AcceptFilterPolicy::Allow, vec![(test_helpers::ALICE_IPV4, 32)])?;
+    server.tcp_set_accept_filter(listen_qd, Some(allow))?;
+    let syn_ack: DemiBuffer = answer_syn(&mut server, &mut client, listen_addr)?;
+    crate::ensure_eq!(parse_tcp_header(syn_ack.clone())?.syn, true);
+    let ack: DemiBuffer = exchange(&mut client, syn_ack)?;
+    server.receive(ack)?;
+    server.get_test_rig().poll_scheduler();
+    match server.tcp_try_accept(listen_qd)? {
+        Some(OperationResult::Accept((_, remote))) => crate::ensure_eq!(*remote.ip(), test_helpers::ALICE_IPV4),
+        result => anyhow::bail!("try_accept should have taken the connection: {:?}", result),
+    }
+
+    Ok(())
+}
+
+/// Tests that a connection that is established, but not accepted yet, is reset once a filter that denies it is
+/// installed, so that it never reaches the application.
+#[test]
+fn test_accept_filter_resets_pending_connection() -> Result<()> {
+    let now: Instant = Instant::now();
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let (mut server, listen_qd): (SharedEngine, QDesc) = new_listener(now, listen_addr, 4)?;
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+
+    let syn_ack: DemiBuffer = answer_syn(&mut server, &mut client, listen_addr)?;
+    let ack: DemiBuffer = exchange(&mut client, syn_ack)?;
+    server.receive(ack)?;
+    server.get_test_rig().poll_scheduler();
+
+    let deny: AcceptFilter = AcceptFilter::new(AcceptFilterPolicy::Deny, vec![(test_helpers::ALICE_IPV4, 32)])?;
+    server.tcp_set_accept_filter(listen_qd, Some(deny))?;
+    crate::ensure_eq!(parse_tcp_header(server.get_test_rig().pop_frame())?.rst, true);
+    crate::ensure_eq!(server.tcp_try_accept(listen_qd)?.is_none(), true);
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::tests::setup::deliver_frames,
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        OperationResult,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    cell::Cell,
+    net::SocketAddrV4,
+    rc::Rc,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Number of connections opened by the client.
+const NUM_CONNECTIONS: usize = 3;
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Installs an accept readiness hook on the listening socket `qd` of `engine` that counts how often it is invoked.
+fn install_accept_ready_hook(engine: &mut SharedEngine, qd: QDesc) -> Result<Rc<Cell<usize>>> {
+    let count: Rc<Cell<usize>> = Rc::new(Cell::new(0));
+    let count_clone: Rc<Cell<usize>> = count.clone();
+    engine.tcp_set_accept_ready_hook(qd, Some(Box::new(move || count_clone.set(count_clone.get() + 1))))?;
+    Ok(count)
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that the accept readiness hook of a listener fires once per connection of a burst, without any outstanding
+/// accept, and that each signal is matched by an accept that completes right away.
+#[test]
+fn test_accept_ready_hook_burst() -> Result<()> {
+    let now: Instant = Instant::now();
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let listen_qd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(listen_qd, listen_addr)?;
+    server.tcp_listen(listen_qd, NUM_CONNECTIONS)?;
+    let count: Rc<Cell<usize>> = install_accept_ready_hook(&mut server, listen_qd)?;
+
+    // Open all connections at once, and run the handshakes to completion.
+    for _ in 0..NUM_CONNECTIONS {
+        let client_qd: QDesc = client.tcp_socket()?;
+        let _connect_qt: QToken = client.tcp_connect(client_qd, listen_addr)?;
+    }
+    for _ in 0..3 {
+        deliver_frames(&mut client, &mut server)?;
+        deliver_frames(&mut server, &mut client)?;
+    }
+    crate::ensure_eq!(count.get(), NUM_CONNECTIONS);
+
+    // A hook that is installed later is notified of the connections that are still pending.
+    let late_count: Rc<Cell<usize>> = install_accept_ready_hook(&mut server, listen_qd)?;
+    crate::ensure_eq!(late_count.get(), NUM_CONNECTIONS);
+
+    // Every signal is matched by an accept that completes right away, and accepting does not signal again.
+    for _ in 0..NUM_CONNECTIONS {
+        let accept_qt: QToken = server.tcp_accept(listen_qd)?;
+        server.get_test_rig().poll_scheduler();
+        match server
+            .get_test_rig()
+            .get_runtime()
+            .remove_coroutine_with_qtoken(accept_qt)
+            .get_result()
+        {
+            Some((_, OperationResult::Accept(_))) => (),
+            Some((_, result)) => anyhow::bail!("accept should have succeeded: {:?}", result),
+            None => anyhow::bail!("accept should have completed"),
+        }
+    }
+    crate::ensure_eq!(count.get(), NUM_CONNECTIONS);
+    crate::ensure_eq!(late_count.get(), NUM_CONNECTIONS);
+
+    Ok(())
+}
@@ -0,0 +1,151 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::{
+            ethernet2::Ethernet2Header,
+            ipv4::Ipv4Header,
+            tcp::{
+                segment::TcpHeader,
+                tests::setup::connect,
+            },
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        network::{
+            config::TcpConfig,
+            consts::DEFAULT_MSS,
+        },
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::time::{
+    Duration,
+    Instant,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Coalescing window of the client.
+const AUTOCORK_WINDOW: Duration = Duration::from_micros(50);
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Connects a client that autocorks its sends to a server, and returns both engines along with the queue descriptor of
+/// the client.
+fn connect_autocorking(now: &mut Instant) -> Result<(SharedEngine, SharedEngine, QDesc)> {
+    let tcp_config: TcpConfig = TcpConfig::default().set_autocork_window(AUTOCORK_WINDOW);
+    let mut server: SharedEngine = test_helpers::new_bob2(*now);
+    let mut client: SharedEngine = test_helpers::new_alice2_with_tcp_config(*now, tcp_config);
+    let (_, client_qd): (QDesc, QDesc) = connect(now, &mut server, &mut client)?;
+    Ok((server, client, client_qd))
+}
+
+/// Returns the payloads of all TCP segments sent by `engine`.
+fn sent_payloads(engine: &mut SharedEngine) -> Result<Vec<DemiBuffer>> {
+    let mut payloads: Vec<DemiBuffer> = Vec::new();
+    for frame in engine.get_test_rig().pop_all_frames() {
+        let (_, eth2_payload) = Ethernet2Header::parse(frame)?;
+        let (ipv4_header, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+        let (_, tcp_payload): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_header, ipv4_payload, false)?;
+        payloads.push(tcp_payload);
+    }
+    Ok(payloads)
+}
+
+/// Advances the clock of `engine` to `now`, and runs the coroutines whose timers expired.
+fn advance_to(engine: &mut SharedEngine, now: Instant) {
+    engine.advance_clock(now);
+    engine.get_test_rig().poll_scheduler();
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that small pushes issued within the coalescing window are held back, and sent in a single segment once the
+/// window is over.
+#[test]
+fn test_autocork_coalesces_within_window() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let (_server, mut client, client_qd): (SharedEngine, SharedEngine, QDesc) = connect_autocorking(&mut now)?;
+
+    let _first_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[1; 10])?)?;
+    client.get_test_rig().poll_scheduler();
+    advance_to(&mut client, now + AUTOCORK_WINDOW / 2);
+    let _second_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[2; 20])?)?;
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(sent_payloads(&mut client)?.len(), 0);
+
+    // The window is counted from the first push, so the second one does not extend it.
+    advance_to(&mut client, now + AUTOCORK_WINDOW);
+    let payloads: Vec<DemiBuffer> = sent_payloads(&mut client)?;
+    crate::ensure_eq!(payloads.len(), 1);
+    let expected: Vec<u8> = [vec![1; 10], vec![2; 20]].concat();
+    crate::ensure_eq!(&payloads[0][..], &expected[..]);
+
+    Ok(())
+}
+
+/// Tests that a push issued after the window of the previous ones is over opens a window of its own, and is flushed as
+/// soon as it expires, even though the previous data is not acknowledged yet.
+#[test]
+fn test_autocork_push_after_window_flushed() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let (_server, mut client, client_qd): (SharedEngine, SharedEngine, QDesc) = connect_autocorking(&mut now)?;
+
+    let _first_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[1; 10])?)?;
+    client.get_test_rig().poll_scheduler();
+    now += AUTOCORK_WINDOW;
+    advance_to(&mut client, now);
+    crate::ensure_eq!(sent_payloads(&mut client)?.len(), 1);
+
+    let _second_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[2; 20])?)?;
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(sent_payloads(&mut client)?.len(), 0);
+    now += AUTOCORK_WINDOW;
+    advance_to(&mut client, now);
+    let payloads: Vec<DemiBuffer> = sent_payloads(&mut client)?;
+    crate::ensure_eq!(payloads.len(), 1);
+    let expected: Vec<u8> = vec![2; 20];
+    crate::ensure_eq!(&payloads[0][..], &expected[..]);
+
+    Ok(())
+}
+
+/// Tests that a push that fills a segment goes out right away, along with the data that was corked before it.
+#[test]
+fn test_autocork_full_segment_sent_immediately() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let (_server, mut client, client_qd): (SharedEngine, SharedEngine, QDesc) = connect_autocorking(&mut now)?;
+
+    let _first_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[1; 10])?)?;
+    client.get_test_rig().poll_scheduler();
+    let _second_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[2; DEFAULT_MSS - 10])?)?;
+    client.get_test_rig().poll_scheduler();
+    let payloads: Vec<DemiBuffer> = sent_payloads(&mut client)?;
+    crate::ensure_eq!(payloads.len(), 1);
+    crate::ensure_eq!(payloads[0].len(), DEFAULT_MSS);
+
+    // Nothing is left for the timer to flush.
+    advance_to(&mut client, now + AUTOCORK_WINDOW);
+    crate::ensure_eq!(sent_payloads(&mut client)?.len(), 0);
+
+    Ok(())
+}
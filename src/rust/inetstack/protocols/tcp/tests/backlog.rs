@@ -0,0 +1,179 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::{
+            segment::TcpHeader,
+            tests::setup::{
+                answer_syn,
+                exchange,
+                parse_tcp_header,
+            },
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        network::config::TcpConfig,
+        OperationResult,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Number of SYN+ACK retransmissions before a half-open connection is evicted.
+const SYN_ACK_RETRIES: usize = 2;
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Sends a SYN from `client` to `server`, and returns the answer of the server along with its TCP header.
+fn try_connect(
+    server: &mut SharedEngine,
+    client: &mut SharedEngine,
+    listen_addr: SocketAddrV4,
+) -> Result<(TcpHeader, DemiBuffer)> {
+    let answer: DemiBuffer = answer_syn(server, client, listen_addr)?;
+    Ok((parse_tcp_header(answer.clone())?, answer))
+}
+
+/// Waits for `accept_qt` to complete on `server`.
+fn wait_for_accept(server: &mut SharedEngine, accept_qt: QToken) -> Result<()> {
+    server.get_test_rig().poll_scheduler();
+    match server
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(accept_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Accept(_))) => Ok(()),
+        Some((_, result)) => anyhow::bail!("accept should have succeeded: {:?}", result),
+        None => anyhow::bail!("accept should have completed"),
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that growing the backlog of a live listener lets it take more pending connections, and that shrinking it
+/// keeps the pending connections but refuses new ones.
+#[test]
+fn test_set_backlog_grow_and_shrink() -> Result<()> {
+    let now: Instant = Instant::now();
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let server_fd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(server_fd, listen_addr)?;
+    server.tcp_listen(server_fd, 1)?;
+
+    // The first connection fills the backlog, so the second one is refused.
+    let (first_hdr, first_syn_ack): (TcpHeader, DemiBuffer) = try_connect(&mut server, &mut client, listen_addr)?;
+    crate::ensure_eq!(first_hdr.syn && first_hdr.ack, true);
+    let (refused_hdr, _): (TcpHeader, DemiBuffer) = try_connect(&mut server, &mut client, listen_addr)?;
+    crate::ensure_eq!(refused_hdr.rst, true);
+
+    // Growing the backlog makes room for another connection.
+    server.tcp_set_backlog(server_fd, 2)?;
+    let (second_hdr, _): (TcpHeader, DemiBuffer) = try_connect(&mut server, &mut client, listen_addr)?;
+    crate::ensure_eq!(second_hdr.syn && second_hdr.ack, true);
+
+    // Shrinking the backlog below the number of pending connections refuses new ones.
+    server.tcp_set_backlog(server_fd, 1)?;
+    let (refused_hdr, _): (TcpHeader, DemiBuffer) = try_connect(&mut server, &mut client, listen_addr)?;
+    crate::ensure_eq!(refused_hdr.rst, true);
+
+    // Pending connections are kept, so the first one can still be established and accepted.
+    let accept_qt: QToken = server.tcp_accept(server_fd)?;
+    server.get_test_rig().poll_scheduler();
+    let ack: DemiBuffer = exchange(&mut client, first_syn_ack)?;
+    crate::ensure_eq!(parse_tcp_header(ack.clone())?.ack, true);
+    server.receive(ack)?;
+    wait_for_accept(&mut server, accept_qt)?;
+
+    Ok(())
+}
+
+/// Tests that the backlog can only be changed on listening sockets.
+#[test]
+fn test_set_backlog_invalid() -> Result<()> {
+    let now: Instant = Instant::now();
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let server_fd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(server_fd, listen_addr)?;
+    match server.tcp_set_backlog(server_fd, 1) {
+        Err(e) if e.errno == libc::EINVAL => {},
+        result => anyhow::bail!("set_backlog() should have failed: {:?}", result),
+    };
+
+    server.tcp_listen(server_fd, 1)?;
+    server.tcp_set_backlog(server_fd, 4)?;
+
+    Ok(())
+}
+
+/// Tests that a half-open connection whose handshake is never completed frees its slot of the backlog once the
+/// configured number of SYN+ACK retransmissions is exhausted.
+#[test]
+fn test_half_open_evicted_after_syn_ack_retries() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let tcp_config: TcpConfig = TcpConfig::default().set_syn_ack_retries(SYN_ACK_RETRIES);
+    let handshake_timeout: Duration = tcp_config.get_handshake_timeout();
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let mut server: SharedEngine = test_helpers::new_bob2_with_tcp_config(now, tcp_config);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let server_fd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(server_fd, listen_addr)?;
+    server.tcp_listen(server_fd, 1)?;
+
+    // The half-open connection fills the backlog, so the next one is refused.
+    let (half_open_hdr, _): (TcpHeader, DemiBuffer) = try_connect(&mut server, &mut client, listen_addr)?;
+    crate::ensure_eq!(half_open_hdr.syn && half_open_hdr.ack, true);
+    let (refused_hdr, _): (TcpHeader, DemiBuffer) = try_connect(&mut server, &mut client, listen_addr)?;
+    crate::ensure_eq!(refused_hdr.rst, true);
+
+    // The SYN+ACK is retransmitted the configured number of times, and never acknowledged.
+    let mut num_syn_acks: usize = 0;
+    for _ in 0..(SYN_ACK_RETRIES + 1) {
+        now += handshake_timeout;
+        server.advance_clock(now);
+        server.get_test_rig().poll_scheduler();
+        server.get_test_rig().poll_scheduler();
+        num_syn_acks += server.get_test_rig().pop_all_frames().len();
+    }
+    crate::ensure_eq!(num_syn_acks, SYN_ACK_RETRIES);
+
+    // The slot was freed, so a new connection is answered and can be accepted.
+    let (hdr, syn_ack): (TcpHeader, DemiBuffer) = try_connect(&mut server, &mut client, listen_addr)?;
+    crate::ensure_eq!(hdr.syn && hdr.ack, true);
+    let accept_qt: QToken = server.tcp_accept(server_fd)?;
+    server.get_test_rig().poll_scheduler();
+    let ack: DemiBuffer = exchange(&mut client, syn_ack)?;
+    server.receive(ack)?;
+    wait_for_accept(&mut server, accept_qt)?;
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::tests::setup::connection_setup,
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        network::types::BufferUsage,
+        OperationResult,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    collections::VecDeque,
+    net::SocketAddrV4,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that the buffer usage of a connection tracks pushed, sent, received and popped bytes.
+#[test]
+fn test_buffer_usage_tracks_push_and_pop() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(client.tcp_buffer_usage(client_qd)?, BufferUsage::default());
+    crate::ensure_eq!(server.tcp_buffer_usage(server_qd)?, BufferUsage::default());
+
+    // The first push fits in the window, so it is sent right away and held until acknowledged.
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x5a; 1000])?)?;
+    crate::ensure_eq!(
+        client.tcp_buffer_usage(client_qd)?,
+        BufferUsage {
+            send_queued: 0,
+            send_unacked: 1000,
+            recv_queued: 0,
+            recv_out_of_order: 0,
+        }
+    );
+
+    // The second push does not fit in the remaining window, so it is queued.
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&vec![0x5a; 65000][..])?)?;
+    crate::ensure_eq!(
+        client.tcp_buffer_usage(client_qd)?,
+        BufferUsage {
+            send_queued: 65000,
+            send_unacked: 1000,
+            recv_queued: 0,
+            recv_out_of_order: 0,
+        }
+    );
+
+    // The server should hold the received bytes until they are popped.
+    let frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    for frame in frames {
+        server.receive(frame)?;
+    }
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(server.tcp_buffer_usage(server_qd)?.recv_queued, 1000);
+
+    let pop_qt: QToken = server.tcp_pop(server_qd)?;
+    server.get_test_rig().poll_scheduler();
+    match server
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(pop_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Pop(_, buf))) => crate::ensure_eq!(buf.len(), 1000),
+        _ => anyhow::bail!("pop should have completed"),
+    };
+    crate::ensure_eq!(server.tcp_buffer_usage(server_qd)?.recv_queued, 0);
+
+    Ok(())
+}
+
+/// Tests that the buffer usage of a socket that is not connected cannot be queried.
+#[test]
+fn test_buffer_usage_not_connected() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let client_qd: QDesc = client.tcp_socket()?;
+
+    match client.tcp_buffer_usage(client_qd) {
+        Err(e) if e.errno == libc::ENOTCONN => Ok(()),
+        Err(e) => anyhow::bail!("buffer_usage should have failed with ENOTCONN: {:?}", e),
+        Ok(_) => anyhow::bail!("buffer_usage should have failed"),
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::{
+            ethernet2::Ethernet2Header,
+            ipv4::Ipv4Header,
+            tcp::{
+                segment::TcpHeader,
+                tests::setup::connection_setup,
+            },
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        network::consts::DEFAULT_MSS,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Number of pushes that the client issues.
+const NUM_PUSHES: usize = 10;
+
+/// Size of each push, which is well below the MSS.
+const PUSH_SIZE: usize = 100;
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Delivers the segments that `client` sent to `server`, and returns the payloads of the ones that carry data.
+fn deliver_payloads(client: &mut SharedEngine, server: &mut SharedEngine) -> Result<Vec<DemiBuffer>> {
+    let mut payloads: Vec<DemiBuffer> = Vec::new();
+    for frame in client.get_test_rig().pop_all_frames() {
+        server.receive(frame.clone())?;
+        let (_, eth2_payload) = Ethernet2Header::parse(frame)?;
+        let (ipv4_hdr, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+        let (_, data): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_hdr, ipv4_payload, false)?;
+        if data.len() > 0 {
+            payloads.push(data);
+        }
+    }
+    server.get_test_rig().poll_scheduler();
+    Ok(payloads)
+}
+
+/// Connects a client to a server, has the client issue [NUM_PUSHES] small pushes in a row, each with its own byte
+/// pattern, and returns the payloads of the data segments that the client sends until the server acknowledges them.
+fn do_test_small_pushes(nodelay: bool) -> Result<Vec<DemiBuffer>> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let (_, client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    client.tcp_set_nodelay(client_qd, nodelay)?;
+
+    for i in 0..NUM_PUSHES {
+        let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[i as u8; PUSH_SIZE])?)?;
+    }
+    client.get_test_rig().poll_scheduler();
+    let mut payloads: Vec<DemiBuffer> = deliver_payloads(&mut client, &mut server)?;
+
+    // Let the delayed acknowledgement of the server go out, which releases the data that waits for it.
+    now += Duration::from_millis(10);
+    server.advance_clock(now);
+    client.advance_clock(now);
+    for frame in server.get_test_rig().pop_all_frames() {
+        client.receive(frame)?;
+    }
+    client.get_test_rig().poll_scheduler();
+    payloads.extend(deliver_payloads(&mut client, &mut server)?);
+    Ok(payloads)
+}
+
+/// Builds the stream that the client sends in [do_test_small_pushes].
+fn expected_stream() -> Vec<u8> {
+    (0..NUM_PUSHES).flat_map(|i| [i as u8; PUSH_SIZE]).collect()
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that small pushes issued while data is in flight wait for it to be acknowledged, and are then coalesced into a
+/// single segment, without altering the stream.
+#[test]
+fn test_small_pushes_are_coalesced() -> Result<()> {
+    let payloads: Vec<DemiBuffer> = do_test_small_pushes(false)?;
+
+    // The first push goes out right away, as nothing is in flight yet.
+    crate::ensure_eq!(payloads.len(), 2);
+    crate::ensure_eq!(payloads[0].len(), PUSH_SIZE);
+    crate::ensure_eq!(payloads[1].len(), (NUM_PUSHES - 1) * PUSH_SIZE);
+    let stream: Vec<u8> = payloads.iter().flat_map(|payload| payload[..].to_vec()).collect();
+    crate::ensure_eq!(stream, expected_stream());
+
+    Ok(())
+}
+
+/// Tests that each small push goes out in its own segment when TCP_NODELAY is set.
+#[test]
+fn test_small_pushes_not_coalesced_with_nodelay() -> Result<()> {
+    let payloads: Vec<DemiBuffer> = do_test_small_pushes(true)?;
+    crate::ensure_eq!(payloads.len(), NUM_PUSHES);
+    let stream: Vec<u8> = payloads.iter().flat_map(|payload| payload[..].to_vec()).collect();
+    crate::ensure_eq!(stream, expected_stream());
+
+    Ok(())
+}
+
+/// Tests that a small push that is held back while data is in flight goes out with PSH as soon as the socket is closed,
+/// followed by the FIN, instead of waiting for the peer to acknowledge the data in flight.
+#[test]
+fn test_small_push_sent_on_close() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let (_, client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Fill the initial congestion window of three segments up to its last one, so that less than a full segment of
+    // room is left, and the small push is held back until the data in flight is acknowledged.
+    for i in 0..2 {
+        let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[i as u8; DEFAULT_MSS])?)?;
+    }
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 2);
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x5a; PUSH_SIZE])?)?;
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 0);
+
+    // Closing sends the small push and the FIN right away, without any ACK from the server.
+    let _close_qt: QToken = client.tcp_async_close(client_qd)?;
+    client.get_test_rig().poll_scheduler();
+    let mut segments: Vec<(TcpHeader, DemiBuffer)> = Vec::new();
+    for frame in client.get_test_rig().pop_all_frames() {
+        let (_, eth2_payload) = Ethernet2Header::parse(frame)?;
+        let (ipv4_hdr, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+        segments.push(TcpHeader::parse(&ipv4_hdr, ipv4_payload, false)?);
+    }
+    crate::ensure_eq!(segments.len(), 2);
+    crate::ensure_eq!(&segments[0].1[..], &[0x5a; PUSH_SIZE][..]);
+    crate::ensure_eq!(segments[0].0.psh, true);
+    crate::ensure_eq!(segments[0].0.fin, false);
+    crate::ensure_eq!(segments[1].1.len(), 0);
+    crate::ensure_eq!(segments[1].0.fin, true);
+
+    Ok(())
+}
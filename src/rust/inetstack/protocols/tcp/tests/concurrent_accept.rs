@@ -0,0 +1,141 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::tests::setup::{
+            exchange,
+            new_listener,
+            send_syn,
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        OperationResult,
+        QDesc,
+        QToken,
+        SharedDemiRuntime,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    collections::HashSet,
+    net::SocketAddrV4,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Number of accepts that are outstanding at once on the listener.
+const NUM_ACCEPTS: usize = 3;
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Runs the handshake of a new connection from `client` to `server`, up to the final ACK, which is returned instead of
+/// being delivered to `server`.
+fn handshake(server: &mut SharedEngine, client: &mut SharedEngine, listen_addr: SocketAddrV4) -> Result<DemiBuffer> {
+    let (_, _, syn): (QDesc, QToken, DemiBuffer) = send_syn(client, listen_addr)?;
+    let syn_ack: DemiBuffer = exchange(server, syn)?;
+    exchange(client, syn_ack)
+}
+
+/// Takes the result of the accept `qt` of `server`, if it completed. Otherwise, the accept is left waiting.
+fn take_accept(server: &mut SharedEngine, qt: QToken) -> Result<Option<OperationResult>> {
+    let mut runtime: SharedDemiRuntime = server.get_test_rig().get_runtime();
+    if !runtime.from_task_id(qt)?.has_completed() {
+        return Ok(None);
+    }
+    Ok(runtime
+        .remove_coroutine_with_qtoken(qt)
+        .get_result()
+        .map(|(_, result)| result))
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that several accepts that are outstanding on one listener each complete with a distinct connection.
+#[test]
+fn test_concurrent_accepts() -> Result<()> {
+    let now: Instant = Instant::now();
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let (mut server, listen_qd): (SharedEngine, QDesc) = new_listener(now, listen_addr, NUM_ACCEPTS)?;
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+
+    let accept_qts: Vec<QToken> = (0..NUM_ACCEPTS)
+        .map(|_| server.tcp_accept(listen_qd))
+        .collect::<Result<Vec<QToken>, _>>()?;
+    server.get_test_rig().poll_scheduler();
+
+    // Complete all connections before the server runs again, so that the accepts race on the pending connections.
+    for _ in 0..NUM_ACCEPTS {
+        let ack: DemiBuffer = handshake(&mut server, &mut client, listen_addr)?;
+        server.receive(ack)?;
+    }
+    server.get_test_rig().poll_scheduler();
+
+    let mut accepted_qds: HashSet<QDesc> = HashSet::new();
+    let mut remotes: HashSet<SocketAddrV4> = HashSet::new();
+    for qt in accept_qts {
+        match take_accept(&mut server, qt)? {
+            Some(OperationResult::Accept((qd, remote))) => {
+                accepted_qds.insert(qd);
+                remotes.insert(remote);
+            },
+            result => anyhow::bail!("accept should have succeeded: {:?}", result),
+        }
+    }
+    crate::ensure_eq!(accepted_qds.len(), NUM_ACCEPTS);
+    crate::ensure_eq!(remotes.len(), NUM_ACCEPTS);
+
+    Ok(())
+}
+
+/// Tests that an accept that was woken up for a connection that another accept took keeps waiting for the next one,
+/// instead of failing.
+#[test]
+fn test_accept_woken_for_taken_connection() -> Result<()> {
+    let now: Instant = Instant::now();
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let (mut server, listen_qd): (SharedEngine, QDesc) = new_listener(now, listen_addr, NUM_ACCEPTS)?;
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+
+    let accept_qt: QToken = server.tcp_accept(listen_qd)?;
+    server.get_test_rig().poll_scheduler();
+
+    // The connection wakes the outstanding accept up, but it is taken right away, before the accept gets to run.
+    let ack: DemiBuffer = handshake(&mut server, &mut client, listen_addr)?;
+    server.receive(ack)?;
+    let first_qd: QDesc = match server.tcp_try_accept(listen_qd)? {
+        Some(OperationResult::Accept((qd, _))) => qd,
+        result => anyhow::bail!("try_accept should have taken the connection: {:?}", result),
+    };
+    server.get_test_rig().poll_scheduler();
+    if let Some(result) = take_accept(&mut server, accept_qt)? {
+        anyhow::bail!("accept should still be waiting: {:?}", result);
+    }
+
+    // The next connection goes to the accept.
+    let ack: DemiBuffer = handshake(&mut server, &mut client, listen_addr)?;
+    server.receive(ack)?;
+    server.get_test_rig().poll_scheduler();
+    match take_accept(&mut server, accept_qt)? {
+        Some(OperationResult::Accept((qd, _))) => crate::ensure_neq!(qd, first_qd),
+        result => anyhow::bail!("accept should have succeeded: {:?}", result),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,205 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::tests::setup::connection_setup,
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        network::{
+            config::{
+                EphemeralPortConfig,
+                EphemeralPortExhaustion,
+            },
+            socket::SocketId,
+        },
+        OperationResult,
+        QDesc,
+        QToken,
+        SharedDemiRuntime,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    convert::TryFrom,
+    net::SocketAddrV4,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// First port of the tiny ephemeral port range used by these tests.
+const FIRST_EPHEMERAL_PORT: u16 = 50000;
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Registers a connection from `local` to `remote` on `engine`, as a connection lingering in TIME_WAIT would be.
+fn insert_lingering_connection(engine: &mut SharedEngine, local: SocketAddrV4, remote: SocketAddrV4) -> Result<QDesc> {
+    let lingering_qd: QDesc = QDesc::try_from(u32::MAX)?;
+    let mut runtime: SharedDemiRuntime = engine.get_test_rig().get_runtime();
+    crate::ensure_eq!(
+        runtime.insert_socket_id_to_qd(SocketId::Active(local, remote), lingering_qd),
+        None
+    );
+    Ok(lingering_qd)
+}
+
+/// Restricts the ephemeral port range of `engine` to `num_ports` ports, with the `exhaustion` behavior.
+fn set_tiny_ephemeral_range(
+    engine: &mut SharedEngine,
+    num_ports: u16,
+    exhaustion: EphemeralPortExhaustion,
+) -> Result<SharedDemiRuntime> {
+    let mut runtime: SharedDemiRuntime = engine.get_test_rig().get_runtime();
+    let last_port: u16 = FIRST_EPHEMERAL_PORT + num_ports - 1;
+    runtime.set_ephemeral_port_config(EphemeralPortConfig::new(
+        Some(FIRST_EPHEMERAL_PORT),
+        Some(last_port),
+        Some(exhaustion),
+    )?)?;
+    Ok(runtime)
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that connect() transparently picks another ephemeral port when the first one it allocates collides with an
+/// existing connection to the same remote address.
+#[test]
+fn test_connect_retries_on_ephemeral_port_collision() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+
+    // Make the first port of a two-port range collide.
+    let mut runtime: SharedDemiRuntime = set_tiny_ephemeral_range(&mut client, 2, EphemeralPortExhaustion::Fail)?;
+    let colliding_port: u16 = FIRST_EPHEMERAL_PORT;
+    let colliding_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, colliding_port);
+    let lingering_qd: QDesc = insert_lingering_connection(&mut client, colliding_addr, listen_addr)?;
+
+    let ((_, client_addr), _): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // The connection should use another port, and leave the lingering one untouched.
+    crate::ensure_eq!(client_addr.port(), colliding_port + 1);
+    crate::ensure_eq!(
+        runtime.get_qd_from_socket_id(&SocketId::Active(colliding_addr, listen_addr)),
+        Some(lingering_qd)
+    );
+
+    // The colliding port should have been returned to the pool, and is the next one in round-robin order.
+    crate::ensure_eq!(runtime.alloc_ephemeral_port()?, colliding_port);
+
+    Ok(())
+}
+
+/// Tests that connect() fails on a collision when the socket is bound, as the caller chose the local address.
+#[test]
+fn test_connect_fails_on_bound_port_collision() -> Result<()> {
+    let now: Instant = Instant::now();
+    let local_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 5000);
+    let remote_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+
+    let client_fd: QDesc = client.tcp_socket()?;
+    client.tcp_bind(client_fd, local_addr)?;
+    insert_lingering_connection(&mut client, local_addr, remote_addr)?;
+
+    match client.tcp_connect(client_fd, remote_addr) {
+        Err(e) if e.errno == libc::EADDRNOTAVAIL => Ok(()),
+        Err(e) => anyhow::bail!("connect should have failed with EADDRNOTAVAIL: {:?}", e),
+        Ok(_) => anyhow::bail!("connect should have failed"),
+    }
+}
+
+/// Tests that connect() fails right away when all ephemeral ports are in use and the runtime is configured to fail.
+#[test]
+fn test_connect_fails_on_ephemeral_port_exhaustion() -> Result<()> {
+    let now: Instant = Instant::now();
+    let remote_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let mut runtime: SharedDemiRuntime = set_tiny_ephemeral_range(&mut client, 1, EphemeralPortExhaustion::Fail)?;
+    crate::ensure_eq!(runtime.alloc_ephemeral_port()?, FIRST_EPHEMERAL_PORT);
+
+    let client_fd: QDesc = client.tcp_socket()?;
+    match client.tcp_connect(client_fd, remote_addr) {
+        Err(e) if e.errno == libc::EADDRNOTAVAIL => Ok(()),
+        Err(e) => anyhow::bail!("connect should have failed with EADDRNOTAVAIL: {:?}", e),
+        Ok(_) => anyhow::bail!("connect should have failed"),
+    }
+}
+
+/// Tests that connect() waits for an ephemeral port to be freed when the runtime is configured to wait.
+#[test]
+fn test_connect_waits_on_ephemeral_port_exhaustion() -> Result<()> {
+    let now: Instant = Instant::now();
+    let remote_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let exhaustion: EphemeralPortExhaustion = EphemeralPortExhaustion::Wait(Duration::from_secs(1));
+    let mut runtime: SharedDemiRuntime = set_tiny_ephemeral_range(&mut client, 1, exhaustion)?;
+    crate::ensure_eq!(runtime.alloc_ephemeral_port()?, FIRST_EPHEMERAL_PORT);
+
+    // No SYN should be sent while the only port is in use.
+    let client_fd: QDesc = client.tcp_socket()?;
+    let qt: QToken = client.tcp_connect(client_fd, remote_addr)?;
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(runtime.from_task_id(qt)?.has_completed(), false);
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 0);
+
+    // Once the port is freed, the connect should go ahead with it.
+    runtime.free_ephemeral_port(FIRST_EPHEMERAL_PORT)?;
+    client.get_test_rig().poll_scheduler();
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(runtime.from_task_id(qt)?.has_completed(), false);
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 1);
+    crate::ensure_eq!(runtime.has_free_ephemeral_ports(), false);
+
+    Ok(())
+}
+
+/// Tests that connect() fails with EADDRNOTAVAIL when no ephemeral port gets freed before the wait times out.
+#[test]
+fn test_connect_wait_on_ephemeral_port_exhaustion_times_out() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let remote_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let timeout: Duration = Duration::from_secs(1);
+    let mut runtime: SharedDemiRuntime =
+        set_tiny_ephemeral_range(&mut client, 1, EphemeralPortExhaustion::Wait(timeout))?;
+    crate::ensure_eq!(runtime.alloc_ephemeral_port()?, FIRST_EPHEMERAL_PORT);
+
+    let client_fd: QDesc = client.tcp_socket()?;
+    let qt: QToken = client.tcp_connect(client_fd, remote_addr)?;
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(runtime.from_task_id(qt)?.has_completed(), false);
+
+    now += timeout;
+    client.advance_clock(now);
+    client.get_test_rig().poll_scheduler();
+    match runtime.remove_coroutine_with_qtoken(qt).get_result() {
+        Some((_, OperationResult::Failed(e))) if e.errno == libc::EADDRNOTAVAIL => (),
+        Some((_, result)) => anyhow::bail!("connect should have failed with EADDRNOTAVAIL: {:?}", result),
+        None => anyhow::bail!("connect should have completed"),
+    }
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 0);
+
+    Ok(())
+}
@@ -0,0 +1,97 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::{
+            tests::setup::{
+                deliver_frames,
+                new_listener,
+            },
+            AcceptFilter,
+            AcceptFilterPolicy,
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        network::types::Readiness,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that a connecting socket is not writable until the connection is established, and that it is writable
+/// afterwards, without any pending error.
+#[test]
+fn test_connect_readiness_established() -> Result<()> {
+    let now: Instant = Instant::now();
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let (mut server, _): (SharedEngine, QDesc) = new_listener(now, listen_addr, 4)?;
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+
+    let client_qd: QDesc = client.tcp_socket()?;
+    let _connect_qt: QToken = client.tcp_connect(client_qd, listen_addr)?;
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(client.tcp_readiness(client_qd)?, Readiness::default());
+
+    // Deliver the SYN, and then the SYN+ACK.
+    deliver_frames(&mut client, &mut server)?;
+    deliver_frames(&mut server, &mut client)?;
+    let readiness: Readiness = client.tcp_readiness(client_qd)?;
+    crate::ensure_eq!(readiness.writable, true);
+    crate::ensure_eq!(readiness.error, false);
+    crate::ensure_eq!(readiness.hangup, false);
+    crate::ensure_eq!(client.tcp_take_socket_error(client_qd)?.is_none(), true);
+
+    Ok(())
+}
+
+/// Tests that a connect that is refused is reported as an error on a hung up socket, and that the error can be
+/// retrieved once.
+#[test]
+fn test_connect_readiness_refused() -> Result<()> {
+    let now: Instant = Instant::now();
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let (mut server, listen_qd): (SharedEngine, QDesc) = new_listener(now, listen_addr, 4)?;
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let deny: AcceptFilter = AcceptFilter::new(AcceptFilterPolicy::Deny, vec![(test_helpers::ALICE_IPV4, 32)])?;
+    server.tcp_set_accept_filter(listen_qd, Some(deny))?;
+
+    let client_qd: QDesc = client.tcp_socket()?;
+    let _connect_qt: QToken = client.tcp_connect(client_qd, listen_addr)?;
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(client.tcp_readiness(client_qd)?, Readiness::default());
+
+    // Deliver the SYN, and then the RST.
+    deliver_frames(&mut client, &mut server)?;
+    deliver_frames(&mut server, &mut client)?;
+    let readiness: Readiness = client.tcp_readiness(client_qd)?;
+    crate::ensure_eq!(readiness.writable, false);
+    crate::ensure_eq!(readiness.error, true);
+    crate::ensure_eq!(readiness.hangup, true);
+
+    match client.tcp_take_socket_error(client_qd)? {
+        Some(e) if e.errno == libc::ECONNREFUSED => {},
+        result => anyhow::bail!("the pending error should be ECONNREFUSED: {:?}", result),
+    }
+    crate::ensure_eq!(client.tcp_take_socket_error(client_qd)?.is_none(), true);
+    crate::ensure_eq!(client.tcp_readiness(client_qd)?.error, false);
+
+    Ok(())
+}
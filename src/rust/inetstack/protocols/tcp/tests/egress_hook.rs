@@ -0,0 +1,101 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::{
+            ipv4::egress::Ipv4EgressVerdict,
+            tcp::tests::setup::{
+                connection_setup,
+                deliver_frames,
+            },
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    cell::Cell,
+    net::SocketAddrV4,
+    rc::Rc,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Number of segments sent by the client.
+const NUM_SEGMENTS: usize = 4;
+
+/// Size of each segment.
+const SEGMENT_SIZE: usize = 100;
+
+/// Maximum number of retransmission timeouts to wait for before giving up.
+const MAX_TIMEOUTS: usize = 16;
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that an egress hook that drops every other packet sent by the client does not prevent the data from being
+/// delivered, as the lost segments are retransmitted.
+#[test]
+fn test_egress_hook_drop_every_other_packet() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    let num_dropped: Rc<Cell<usize>> = Rc::new(Cell::new(0));
+    let num_dropped_clone: Rc<Cell<usize>> = num_dropped.clone();
+    let drop_next: Rc<Cell<bool>> = Rc::new(Cell::new(false));
+    client.set_egress_hook(Some(Box::new(move |_, _| {
+        let drop: bool = drop_next.replace(!drop_next.get());
+        if drop {
+            num_dropped_clone.set(num_dropped_clone.get() + 1);
+            Ipv4EgressVerdict::Drop
+        } else {
+            Ipv4EgressVerdict::Pass
+        }
+    })));
+
+    for _ in 0..NUM_SEGMENTS {
+        let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x5a; SEGMENT_SIZE])?)?;
+    }
+
+    // Keep exchanging frames and expiring the retransmission timer of the client until all of the data is readable.
+    for _ in 0..MAX_TIMEOUTS {
+        deliver_frames(&mut client, &mut server)?;
+        deliver_frames(&mut server, &mut client)?;
+        if server.tcp_buffer_usage(server_qd)?.recv_queued == NUM_SEGMENTS * SEGMENT_SIZE {
+            break;
+        }
+        now += client.tcp_rto(client_qd)?;
+        client.advance_clock(now);
+        server.advance_clock(now);
+    }
+
+    crate::ensure_neq!(num_dropped.get(), 0);
+    crate::ensure_eq!(
+        server.tcp_buffer_usage(server_qd)?.recv_queued,
+        NUM_SEGMENTS * SEGMENT_SIZE
+    );
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::tests::setup::connection_setup,
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        network::types::ErrorCounters,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that a retransmission is counted, that reading the counters does not clear them unless asked to, and that
+/// clearing them starts counting from zero again.
+#[test]
+fn test_error_counters_retransmits() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let (_, client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(client.tcp_error_counters(client_qd, false)?, ErrorCounters::default());
+
+    // Data that the server never gets should be retransmitted once the retransmission timeout expires.
+    let rto: Duration = client.tcp_rto(client_qd)?;
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x5a; 1000])?)?;
+    client.get_test_rig().poll_scheduler();
+    client.get_test_rig().pop_all_frames();
+    now += rto + Duration::from_millis(1);
+    client.advance_clock(now);
+    client.get_test_rig().poll_scheduler();
+
+    let retransmitted: ErrorCounters = ErrorCounters {
+        retransmits: 1,
+        ..Default::default()
+    };
+    crate::ensure_eq!(client.tcp_error_counters(client_qd, false)?, retransmitted);
+    crate::ensure_eq!(client.tcp_error_counters(client_qd, true)?, retransmitted);
+    crate::ensure_eq!(client.tcp_error_counters(client_qd, false)?, ErrorCounters::default());
+
+    Ok(())
+}
+
+/// Tests that the error counters of a socket that is not connected cannot be queried.
+#[test]
+fn test_error_counters_not_connected() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let client_qd: QDesc = client.tcp_socket()?;
+
+    match client.tcp_error_counters(client_qd, false) {
+        Err(e) if e.errno == libc::ENOTCONN => Ok(()),
+        result => anyhow::bail!("error_counters should have failed with ENOTCONN: {:?}", result),
+    }
+}
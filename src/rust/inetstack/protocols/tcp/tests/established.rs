@@ -107,7 +107,7 @@ fn send_data(
         .remove_coroutine_with_qtoken(qt)
         .get_result()
     {
-        Some((_, OperationResult::Push)) => {
+        Some((_, OperationResult::Push(_))) => {
             trace!("send_data ====> push completed");
             Ok(outgoing_frames)
         },
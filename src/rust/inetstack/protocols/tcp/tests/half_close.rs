@@ -0,0 +1,292 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::{
+            ethernet2::Ethernet2Header,
+            ipv4::Ipv4Header,
+            tcp::{
+                segment::TcpHeader,
+                tests::setup::{
+                    connection_setup,
+                    deliver_frames,
+                },
+            },
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        network::types::Readiness,
+        OperationResult,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Pops from `qd` on `engine`, and returns the popped buffer.
+fn pop(engine: &mut SharedEngine, qd: QDesc) -> Result<DemiBuffer> {
+    let qt: QToken = engine.tcp_pop(qd)?;
+    engine.get_test_rig().poll_scheduler();
+    match engine
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Pop(_, buf))) => Ok(buf),
+        Some((_, result)) => anyhow::bail!("pop should have returned data: {:?}", result),
+        None => anyhow::bail!("pop should have completed"),
+    }
+}
+
+/// Returns the payload lengths of all TCP segments with data sent by `engine`.
+fn sent_payload_lengths(engine: &mut SharedEngine) -> Result<Vec<usize>> {
+    let mut lengths: Vec<usize> = Vec::new();
+    for frame in engine.get_test_rig().pop_all_frames() {
+        let (_, eth2_payload) = Ethernet2Header::parse(frame)?;
+        let (ipv4_header, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+        let (_, tcp_payload): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_header, ipv4_payload, false)?;
+        if tcp_payload.len() > 0 {
+            lengths.push(tcp_payload.len());
+        }
+    }
+    Ok(lengths)
+}
+
+/// Returns whether any of the TCP segments sent by `engine` has the RST flag set.
+fn sent_rst(engine: &mut SharedEngine) -> Result<bool> {
+    let mut rst: bool = false;
+    for frame in engine.get_test_rig().pop_all_frames() {
+        let (_, eth2_payload) = Ethernet2Header::parse(frame)?;
+        let (ipv4_header, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+        let (tcp_header, _): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_header, ipv4_payload, false)?;
+        rst |= tcp_header.rst;
+    }
+    Ok(rst)
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that once the remote peer has closed its side of the connection, pops return the pending data and then an
+/// empty buffer on every call, while pushes keep working.
+#[test]
+fn test_pop_returns_eof_after_remote_close() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Send some data and then a FIN: Client -> Server
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x5a; 100])?)?;
+    let _close_qt: QToken = client.tcp_async_close(client_qd)?;
+    deliver_frames(&mut client, &mut server)?;
+    server.get_test_rig().pop_all_frames();
+
+    // The data should come first, followed by end of stream on every later pop.
+    crate::ensure_eq!(pop(&mut server, server_qd)?.len(), 100);
+    crate::ensure_eq!(pop(&mut server, server_qd)?.len(), 0);
+    crate::ensure_eq!(pop(&mut server, server_qd)?.len(), 0);
+
+    // The write side should still be open.
+    let push_qt: QToken = server.tcp_push(server_qd, DemiBuffer::from_slice(&[0xa5; 50])?)?;
+    server.get_test_rig().poll_scheduler();
+    match server
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(push_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Push(_))) => {},
+        Some((_, result)) => anyhow::bail!("push should have succeeded: {:?}", result),
+        None => anyhow::bail!("push should have completed"),
+    };
+    crate::ensure_eq!(sent_payload_lengths(&mut server)?, vec![50]);
+
+    // The connection should still be serviced, so unacknowledged data gets retransmitted.
+    now += Duration::from_secs(10);
+    server.advance_clock(now);
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(sent_payload_lengths(&mut server)?, vec![50]);
+
+    Ok(())
+}
+
+/// Tests that a connection that was closed by the remote peer is reported as readable with end of stream, and still
+/// writable, so that the application can write a final response after seeing the end of the request.
+#[test]
+fn test_readiness_after_remote_close() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(
+        server.tcp_readiness(server_qd)?,
+        Readiness {
+            writable: true,
+            ..Readiness::default()
+        }
+    );
+
+    // Send a request and then a FIN: Client -> Server
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x5a; 100])?)?;
+    let _close_qt: QToken = client.tcp_async_close(client_qd)?;
+    deliver_frames(&mut client, &mut server)?;
+    let half_closed: Readiness = Readiness {
+        readable: true,
+        writable: true,
+        read_closed: true,
+        ..Readiness::default()
+    };
+    crate::ensure_eq!(server.tcp_readiness(server_qd)?, half_closed);
+
+    // Draining the request leaves the end of stream to be popped, so the connection stays readable.
+    crate::ensure_eq!(pop(&mut server, server_qd)?.len(), 100);
+    crate::ensure_eq!(server.tcp_readiness(server_qd)?, half_closed);
+    crate::ensure_eq!(pop(&mut server, server_qd)?.len(), 0);
+    crate::ensure_eq!(server.tcp_readiness(server_qd)?, half_closed);
+
+    // Write the final response: Server -> Client
+    server.get_test_rig().pop_all_frames();
+    let _push_qt: QToken = server.tcp_push(server_qd, DemiBuffer::from_slice(&[0xa5; 50])?)?;
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(sent_payload_lengths(&mut server)?, vec![50]);
+
+    Ok(())
+}
+
+/// Tests that a connection that was closed locally but not by the remote peer is no longer writable, yet is still
+/// reported as readable when data arrives.
+#[test]
+fn test_readiness_after_local_close() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Send a FIN and have it acknowledged: Client -> Server
+    let _close_qt: QToken = client.tcp_async_close(client_qd)?;
+    deliver_frames(&mut client, &mut server)?;
+    deliver_frames(&mut server, &mut client)?;
+    crate::ensure_eq!(client.tcp_readiness(client_qd)?, Readiness::default());
+
+    // Send a response: Server -> Client
+    let _push_qt: QToken = server.tcp_push(server_qd, DemiBuffer::from_slice(&[0xa5; 50])?)?;
+    deliver_frames(&mut server, &mut client)?;
+    crate::ensure_eq!(
+        client.tcp_readiness(client_qd)?,
+        Readiness {
+            readable: true,
+            ..Readiness::default()
+        }
+    );
+
+    Ok(())
+}
+
+/// Tests that data that arrives once a connection was closed locally, but before the remote peer closed its side, is
+/// delivered to a pop that was pending and acknowledged.
+#[test]
+fn test_data_delivered_after_local_close() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Keep reading, and send a FIN and have it acknowledged: Client -> Server
+    let pop_qt: QToken = client.tcp_pop(client_qd)?;
+    let _close_qt: QToken = client.tcp_async_close(client_qd)?;
+    deliver_frames(&mut client, &mut server)?;
+    deliver_frames(&mut server, &mut client)?;
+
+    // Send a response: Server -> Client
+    let _push_qt: QToken = server.tcp_push(server_qd, DemiBuffer::from_slice(&[0xa5; 50])?)?;
+    deliver_frames(&mut server, &mut client)?;
+    client.get_test_rig().poll_scheduler();
+    match client
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(pop_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Pop(_, buf))) => crate::ensure_eq!(&buf[..], &[0xa5; 50][..]),
+        Some((_, result)) => anyhow::bail!("pop should have returned data: {:?}", result),
+        None => anyhow::bail!("pop should have completed"),
+    }
+
+    // The response should be acknowledged: Client -> Server
+    deliver_frames(&mut client, &mut server)?;
+    crate::ensure_eq!(server.tcp_buffer_usage(server_qd)?.send_unacked, 0);
+
+    Ok(())
+}
+
+/// Tests that data that arrives once a connection was closed locally is discarded if so configured, which resets the
+/// connection and fails pending pops.
+#[test]
+fn test_data_discarded_after_local_close() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    client.tcp_set_discard_late_data(client_qd, true)?;
+
+    // Keep reading, and send a FIN and have it acknowledged: Client -> Server
+    let pop_qt: QToken = client.tcp_pop(client_qd)?;
+    let _close_qt: QToken = client.tcp_async_close(client_qd)?;
+    deliver_frames(&mut client, &mut server)?;
+    deliver_frames(&mut server, &mut client)?;
+
+    // Send a response, which the client should answer with a RST: Server -> Client
+    let _push_qt: QToken = server.tcp_push(server_qd, DemiBuffer::from_slice(&[0xa5; 50])?)?;
+    deliver_frames(&mut server, &mut client)?;
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(sent_rst(&mut client)?, true);
+    match client
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(pop_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Failed(e))) if e.errno == libc::ECONNRESET => Ok(()),
+        Some((_, result)) => anyhow::bail!("pop should have failed with ECONNRESET: {:?}", result),
+        None => anyhow::bail!("pop should have completed"),
+    }
+}
@@ -0,0 +1,136 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::{
+            ethernet2::Ethernet2Header,
+            ipv4::Ipv4Header,
+            tcp::{
+                segment::TcpHeader,
+                tests::setup::{
+                    connection_setup,
+                    deliver_frames,
+                },
+            },
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        OperationResult,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Idle timeout of the server side of the connection.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(5);
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Returns whether any of the TCP segments sent by `engine` has the FIN flag set.
+fn sent_fin(engine: &mut SharedEngine) -> Result<bool> {
+    let mut fin: bool = false;
+    for frame in engine.get_test_rig().pop_all_frames() {
+        let (_, eth2_payload) = Ethernet2Header::parse(frame)?;
+        let (ipv4_header, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+        let (tcp_header, _): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_header, ipv4_payload, false)?;
+        fin |= tcp_header.fin;
+    }
+    Ok(fin)
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that a connection on which no data is sent or received is gracefully closed once its idle timeout expires,
+/// and that a pending pop fails with ETIMEDOUT.
+#[test]
+fn test_idle_connection_is_closed() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), _client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    server.tcp_set_idle_timeout(server_qd, Some(IDLE_TIMEOUT))?;
+    let pop_qt: QToken = server.tcp_pop(server_qd)?;
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(sent_fin(&mut server)?, false);
+
+    // Nothing should happen before the timeout expires.
+    now += IDLE_TIMEOUT - Duration::from_secs(1);
+    server.advance_clock(now);
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(sent_fin(&mut server)?, false);
+
+    // Once it expires, the server should send a FIN and fail the pending pop.
+    now += Duration::from_secs(2);
+    server.advance_clock(now);
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(sent_fin(&mut server)?, true);
+    match server
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(pop_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Failed(e))) if e.errno == libc::ETIMEDOUT => Ok(()),
+        Some((_, result)) => anyhow::bail!("pop should have failed with ETIMEDOUT: {:?}", result),
+        None => anyhow::bail!("pop should have completed"),
+    }
+}
+
+/// Tests that a connection on which data keeps flowing is not closed, even if it lives longer than its idle timeout.
+#[test]
+fn test_active_connection_is_not_closed() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    server.tcp_set_idle_timeout(server_qd, Some(IDLE_TIMEOUT))?;
+
+    // Send some data a few times more often than the timeout, for several times its duration.
+    for _ in 0..4 {
+        now += IDLE_TIMEOUT / 2;
+        client.advance_clock(now);
+        server.advance_clock(now);
+        let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x5a; 100])?)?;
+        deliver_frames(&mut client, &mut server)?;
+        crate::ensure_eq!(sent_fin(&mut server)?, false);
+    }
+
+    // All of the data should be readable.
+    crate::ensure_eq!(server.tcp_buffer_usage(server_qd)?.recv_queued, 400);
+
+    Ok(())
+}
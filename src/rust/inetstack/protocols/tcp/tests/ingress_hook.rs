@@ -0,0 +1,124 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::{
+            ipv4::ingress::Ipv4IngressVerdict,
+            tcp::tests::setup::{
+                connection_setup,
+                deliver_frames,
+                pop_all,
+            },
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    cell::Cell,
+    net::SocketAddrV4,
+    rc::Rc,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Size of each segment sent by the client.
+const SEGMENT_SIZE: usize = 100;
+
+/// Time for which the ingress hook holds the first segment.
+const REORDER_DELAY: Duration = Duration::from_millis(1);
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that an ingress hook that swaps two segments makes the second one arrive out of order, and that the receiver
+/// reassembles the stream once the first one is released.
+#[test]
+fn test_ingress_hook_reorder() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Hold the first packet that the server receives, and let everything else through. Each push must go out in its
+    // own segment for that.
+    client.tcp_set_nodelay(client_qd, true)?;
+    let first: Rc<Cell<bool>> = Rc::new(Cell::new(true));
+    server.set_ingress_hook(Some(Box::new(move |_| {
+        if first.replace(false) {
+            Ipv4IngressVerdict::Delay(REORDER_DELAY)
+        } else {
+            Ipv4IngressVerdict::Pass
+        }
+    })));
+
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x11; SEGMENT_SIZE])?)?;
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x22; SEGMENT_SIZE])?)?;
+    deliver_frames(&mut client, &mut server)?;
+    crate::ensure_eq!(server.tcp_buffer_usage(server_qd)?.recv_out_of_order, SEGMENT_SIZE);
+    crate::ensure_eq!(server.tcp_buffer_usage(server_qd)?.recv_queued, 0);
+
+    // Once the first segment is released, the whole stream becomes readable, in order.
+    now += REORDER_DELAY;
+    server.advance_clock(now);
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(server.tcp_buffer_usage(server_qd)?.recv_out_of_order, 0);
+    crate::ensure_eq!(server.tcp_buffer_usage(server_qd)?.recv_queued, 2 * SEGMENT_SIZE);
+    let expected: Vec<u8> = [[0x11; SEGMENT_SIZE], [0x22; SEGMENT_SIZE]].concat();
+    crate::ensure_eq!(pop_all(&mut server, server_qd, 2 * SEGMENT_SIZE)?, expected);
+    crate::ensure_eq!(server.ipv4_filtered_packets(), 0);
+
+    Ok(())
+}
+
+/// Tests that packets dropped by an ingress hook never reach the connection, and are counted.
+#[test]
+fn test_ingress_hook_drop() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    server.set_ingress_hook(Some(Box::new(|_| Ipv4IngressVerdict::Drop)));
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x5a; SEGMENT_SIZE])?)?;
+    deliver_frames(&mut client, &mut server)?;
+    crate::ensure_eq!(server.ipv4_filtered_packets(), 1);
+    crate::ensure_eq!(server.tcp_buffer_usage(server_qd)?.recv_queued, 0);
+
+    // Once the hook is removed, the retransmitted segment gets through.
+    server.set_ingress_hook(None);
+    now += client.tcp_rto(client_qd)?;
+    client.advance_clock(now);
+    server.advance_clock(now);
+    deliver_frames(&mut client, &mut server)?;
+    crate::ensure_eq!(server.ipv4_filtered_packets(), 1);
+    crate::ensure_eq!(server.tcp_buffer_usage(server_qd)?.recv_queued, SEGMENT_SIZE);
+
+    Ok(())
+}
@@ -0,0 +1,112 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::tests::setup::deliver_frames,
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        OperationResult,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Size of the receive buffer that is set on the listening socket.
+const RECEIVE_WINDOW: u32 = 4096;
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Opens a connection from `client` to the listening socket `listen_qd` of `server`, and accepts it. Returns the
+/// descriptors of the accepted and connecting sockets.
+fn accept_connection(
+    server: &mut SharedEngine,
+    client: &mut SharedEngine,
+    listen_qd: QDesc,
+    listen_addr: SocketAddrV4,
+) -> Result<(QDesc, QDesc)> {
+    let accept_qt: QToken = server.tcp_accept(listen_qd)?;
+    let client_qd: QDesc = client.tcp_socket()?;
+    let _connect_qt: QToken = client.tcp_connect(client_qd, listen_addr)?;
+    for _ in 0..3 {
+        deliver_frames(client, server)?;
+        deliver_frames(server, client)?;
+    }
+    let server_qd: QDesc = match server
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(accept_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Accept((qd, _)))) => qd,
+        Some((_, result)) => anyhow::bail!("accept should have succeeded: {:?}", result),
+        None => anyhow::bail!("accept should have completed"),
+    };
+    Ok((server_qd, client_qd))
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that a connection that is accepted on a listening socket with TCP_NODELAY and a receive buffer size starts
+/// with those options.
+#[test]
+fn test_accepted_connection_inherits_options() -> Result<()> {
+    let now: Instant = Instant::now();
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let listen_qd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(listen_qd, listen_addr)?;
+    server.tcp_listen(listen_qd, 1)?;
+    server.tcp_set_nodelay(listen_qd, true)?;
+    server.tcp_set_receive_window(listen_qd, RECEIVE_WINDOW)?;
+
+    let (server_qd, client_qd): (QDesc, QDesc) = accept_connection(&mut server, &mut client, listen_qd, listen_addr)?;
+    crate::ensure_eq!(server.tcp_get_nodelay(server_qd)?, true);
+
+    // The accepted connection advertises the receive window of the listening socket right away.
+    deliver_frames(&mut server, &mut client)?;
+    crate::ensure_eq!(client.tcp_send_window(client_qd)?, RECEIVE_WINDOW);
+
+    Ok(())
+}
+
+/// Tests that a connection that is accepted on a listening socket without any options starts with the defaults.
+#[test]
+fn test_accepted_connection_default_options() -> Result<()> {
+    let now: Instant = Instant::now();
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let listen_qd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(listen_qd, listen_addr)?;
+    server.tcp_listen(listen_qd, 1)?;
+
+    let (server_qd, client_qd): (QDesc, QDesc) = accept_connection(&mut server, &mut client, listen_qd, listen_addr)?;
+    crate::ensure_eq!(server.tcp_get_nodelay(server_qd)?, false);
+    deliver_frames(&mut server, &mut client)?;
+    crate::ensure_neq!(client.tcp_send_window(client_qd)?, RECEIVE_WINDOW);
+
+    Ok(())
+}
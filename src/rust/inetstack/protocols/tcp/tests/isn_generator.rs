@@ -0,0 +1,151 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::{
+            isn_generator::{
+                DeterministicIsnGenerator,
+                HashedIsnGenerator,
+            },
+            segment::TcpHeader,
+            tests::setup::parse_tcp_header,
+            SeqNumber,
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        OperationResult,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    collections::VecDeque,
+    net::SocketAddrV4,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Polls `engine` and returns the only frame that it sent.
+fn pop_frame(engine: &mut SharedEngine) -> Result<DemiBuffer> {
+    engine.get_test_rig().poll_scheduler();
+    engine.get_test_rig().poll_scheduler();
+    let mut frames: VecDeque<DemiBuffer> = engine.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    Ok(frames.pop_front().expect("there should be one frame"))
+}
+
+/// Returns the ISN of the first connection that a fresh client opens with the default ISN generator, after reseeding
+/// the client with `rng_seed`, if any.
+fn first_isn(rng_seed: Option<[u8; 32]>) -> Result<SeqNumber> {
+    let now: Instant = Instant::now();
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    client.tcp_set_isn_generator(Box::new(HashedIsnGenerator::new(0)));
+    if let Some(rng_seed) = rng_seed {
+        client.reseed(rng_seed);
+    }
+    let client_fd: QDesc = client.tcp_socket()?;
+    let _connect_qt: QToken = client.tcp_connect(client_fd, SocketAddrV4::new(test_helpers::BOB_IPV4, 80))?;
+    Ok(parse_tcp_header(pop_frame(&mut client)?)?.seq_num)
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that deterministic ISN generators on both ends produce the expected handshake sequence numbers.
+#[test]
+fn test_deterministic_isn_generator_handshake() -> Result<()> {
+    let now: Instant = Instant::now();
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    client.tcp_set_isn_generator(Box::new(DeterministicIsnGenerator::new(1000, 1000)));
+
+    let server_fd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(server_fd, listen_addr)?;
+    server.tcp_listen(server_fd, 1)?;
+    let accept_qt: QToken = server.tcp_accept(server_fd)?;
+    server.get_test_rig().poll_scheduler();
+    // Listening sockets share the generator of the peer, so they pick up a generator installed after listen().
+    server.tcp_set_isn_generator(Box::new(DeterministicIsnGenerator::new(u32::MAX, 1)));
+
+    // SYN.
+    let client_fd: QDesc = client.tcp_socket()?;
+    let connect_qt: QToken = client.tcp_connect(client_fd, listen_addr)?;
+    let syn: DemiBuffer = pop_frame(&mut client)?;
+    let syn_hdr: TcpHeader = parse_tcp_header(syn.clone())?;
+    crate::ensure_eq!(syn_hdr.syn, true);
+    crate::ensure_eq!(syn_hdr.seq_num, SeqNumber::from(1000));
+
+    // SYN+ACK.
+    server.receive(syn)?;
+    let syn_ack: DemiBuffer = pop_frame(&mut server)?;
+    let syn_ack_hdr: TcpHeader = parse_tcp_header(syn_ack.clone())?;
+    crate::ensure_eq!(syn_ack_hdr.syn && syn_ack_hdr.ack, true);
+    crate::ensure_eq!(syn_ack_hdr.seq_num, SeqNumber::from(u32::MAX));
+    crate::ensure_eq!(syn_ack_hdr.ack_num, SeqNumber::from(1001));
+
+    // ACK. The sequence number space of the server wraps around.
+    client.receive(syn_ack)?;
+    let ack: DemiBuffer = pop_frame(&mut client)?;
+    let ack_hdr: TcpHeader = parse_tcp_header(ack.clone())?;
+    crate::ensure_eq!(ack_hdr.syn, false);
+    crate::ensure_eq!(ack_hdr.seq_num, SeqNumber::from(1001));
+    crate::ensure_eq!(ack_hdr.ack_num, SeqNumber::from(0));
+    match client
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(connect_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Connect)) => {},
+        _ => anyhow::bail!("connect should have completed"),
+    };
+    server.receive(ack)?;
+    server.get_test_rig().poll_scheduler();
+    match server
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(accept_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Accept(_))) => {},
+        _ => anyhow::bail!("accept should have completed"),
+    };
+
+    // The next connection should get the next ISN in the sequence.
+    let client_fd: QDesc = client.tcp_socket()?;
+    let _connect_qt: QToken = client.tcp_connect(client_fd, listen_addr)?;
+    crate::ensure_eq!(
+        parse_tcp_header(pop_frame(&mut client)?)?.seq_num,
+        SeqNumber::from(2000)
+    );
+
+    Ok(())
+}
+
+/// Tests that reseeding changes the ISNs of the connections that are opened afterwards, and that the same seed yields
+/// the same ISNs.
+#[test]
+fn test_reseed_changes_isn() -> Result<()> {
+    let isn: SeqNumber = first_isn(Some([1; 32]))?;
+    crate::ensure_neq!(isn, first_isn(None)?);
+    crate::ensure_neq!(isn, first_isn(Some([2; 32]))?);
+    crate::ensure_eq!(isn, first_isn(Some([1; 32]))?);
+
+    Ok(())
+}
@@ -0,0 +1,68 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::tests::setup::connection_setup,
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        network::types::MacAddress,
+        QDesc,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that the link addresses of an established connection are those of the local peer and of the ARP entry of the
+/// next hop, on both ends of the connection.
+#[test]
+fn test_link_addrs_established() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    let (local, remote): (MacAddress, MacAddress) = client.tcp_link_addrs(client_qd)?;
+    crate::ensure_eq!(local, test_helpers::ALICE_MAC);
+    crate::ensure_eq!(Some(&remote), client.export_arp_cache().get(&test_helpers::BOB_IPV4));
+    crate::ensure_eq!(remote, test_helpers::BOB_MAC);
+
+    let (local, remote): (MacAddress, MacAddress) = server.tcp_link_addrs(server_qd)?;
+    crate::ensure_eq!(local, test_helpers::BOB_MAC);
+    crate::ensure_eq!(Some(&remote), server.export_arp_cache().get(&test_helpers::ALICE_IPV4));
+
+    Ok(())
+}
+
+/// Tests that sockets that are not connected have no link addresses.
+#[test]
+fn test_link_addrs_not_connected() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let qd: QDesc = client.tcp_socket()?;
+
+    match client.tcp_link_addrs(qd) {
+        Err(e) if e.errno == libc::ENOTCONN => (),
+        result => anyhow::bail!("tcp_link_addrs() should have failed with ENOTCONN: {:?}", result),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,132 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::tests::setup::{
+            deliver_frames,
+            parse_tcp_header,
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        OperationResult,
+        QDesc,
+        QToken,
+        SharedDemiRuntime,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Maximum number of queues that may be open at the same time.
+const MAX_QUEUES: usize = 2;
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Limits the number of queues that may be open at the same time on `engine` to `max_queues`.
+fn set_max_queues(engine: &mut SharedEngine, max_queues: usize) {
+    let mut runtime: SharedDemiRuntime = engine.get_test_rig().get_runtime();
+    runtime.set_max_queues(Some(max_queues));
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that creating a socket past the maximum number of open queues fails with EMFILE, and that closing a socket
+/// makes room for a new one.
+#[test]
+fn test_socket_past_max_queues() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut engine: SharedEngine = test_helpers::new_alice2(now);
+    set_max_queues(&mut engine, MAX_QUEUES);
+
+    let mut qds: Vec<QDesc> = Vec::new();
+    for _ in 0..MAX_QUEUES {
+        qds.push(engine.tcp_socket()?);
+    }
+    match engine.tcp_socket() {
+        Err(e) if e.errno == libc::EMFILE => (),
+        Err(e) => anyhow::bail!("socket should have failed with EMFILE: {:?}", e),
+        Ok(_) => anyhow::bail!("socket should have failed"),
+    }
+
+    // Close one of the sockets.
+    let close_qt: QToken = engine.tcp_async_close(qds[0])?;
+    engine.get_test_rig().poll_scheduler();
+    match engine
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(close_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Close)) => (),
+        Some((_, result)) => anyhow::bail!("close should have succeeded: {:?}", result),
+        None => anyhow::bail!("close should have completed"),
+    }
+
+    // There is room for a new socket now.
+    let _qd: QDesc = engine.tcp_socket()?;
+
+    Ok(())
+}
+
+/// Tests that a connection that is accepted past the maximum number of open queues is refused with a RST, and that
+/// the accept fails with EMFILE.
+#[test]
+fn test_accept_past_max_queues() -> Result<()> {
+    let now: Instant = Instant::now();
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let listen_qd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(listen_qd, listen_addr)?;
+    server.tcp_listen(listen_qd, 1)?;
+    // Only the listening socket fits.
+    set_max_queues(&mut server, 1);
+    let accept_qt: QToken = server.tcp_accept(listen_qd)?;
+
+    // Run the handshake: SYN, SYN+ACK and ACK.
+    let client_qd: QDesc = client.tcp_socket()?;
+    let _connect_qt: QToken = client.tcp_connect(client_qd, listen_addr)?;
+    deliver_frames(&mut client, &mut server)?;
+    deliver_frames(&mut server, &mut client)?;
+    deliver_frames(&mut client, &mut server)?;
+
+    match server
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(accept_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Failed(e))) if e.errno == libc::EMFILE => (),
+        Some((_, result)) => anyhow::bail!("accept should have failed with EMFILE: {:?}", result),
+        None => anyhow::bail!("accept should have completed"),
+    }
+
+    // The connection should have been refused.
+    let mut refused: bool = false;
+    for frame in server.get_test_rig().pop_all_frames() {
+        refused |= parse_tcp_header(frame)?.rst;
+    }
+    crate::ensure_eq!(refused, true);
+
+    Ok(())
+}
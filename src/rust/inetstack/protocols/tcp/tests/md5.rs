@@ -0,0 +1,208 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::{
+            ethernet2::Ethernet2Header,
+            ipv4::Ipv4Header,
+            tcp::{
+                md5,
+                segment::{
+                    TcpHeader,
+                    TcpOptions2,
+                    TcpSegment,
+                },
+                tests::setup::{
+                    exchange,
+                    has_completed,
+                },
+                TcpMd5Key,
+            },
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        network::NetworkRuntime,
+        OperationResult,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Key that both ends of the connection are configured with.
+const KEY: &[u8] = b"bgp-session-key";
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Checks that `frame` carries a TCP segment that is signed with [KEY].
+fn check_signature(frame: DemiBuffer) -> Result<()> {
+    let (_, eth2_payload): (Ethernet2Header, DemiBuffer) = Ethernet2Header::parse(frame)?;
+    let (ipv4_hdr, ipv4_payload): (Ipv4Header, DemiBuffer) = Ipv4Header::parse(eth2_payload)?;
+    let segment: DemiBuffer = ipv4_payload.clone();
+    let (tcp_hdr, _): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_hdr, ipv4_payload, false)?;
+    md5::verify_signature(&ipv4_hdr, &tcp_hdr, &segment[..], Some(&TcpMd5Key::new(KEY)?))?;
+    Ok(())
+}
+
+/// Delivers `frame` to `receiver`, and returns the frame that it sends in response, after checking that it is signed.
+fn exchange_signed(receiver: &mut SharedEngine, frame: DemiBuffer) -> Result<DemiBuffer> {
+    let frame: DemiBuffer = exchange(receiver, frame)?;
+    check_signature(frame.clone())?;
+    Ok(frame)
+}
+
+/// Connects `client` to `server`, with [KEY] set on both ends before the handshake, and checks that every segment of
+/// the handshake is signed. Returns the queue descriptors of the server and of the client.
+fn connect(server: &mut SharedEngine, client: &mut SharedEngine) -> Result<(QDesc, QDesc)> {
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let listen_qd: QDesc = server.tcp_socket()?;
+    server.tcp_set_md5_key(listen_qd, Some(KEY))?;
+    server.tcp_bind(listen_qd, listen_addr)?;
+    server.tcp_listen(listen_qd, 1)?;
+    let accept_qt: QToken = server.tcp_accept(listen_qd)?;
+
+    let client_qd: QDesc = client.tcp_socket()?;
+    client.tcp_set_md5_key(client_qd, Some(KEY))?;
+    let connect_qt: QToken = client.tcp_connect(client_qd, listen_addr)?;
+    client.get_test_rig().poll_scheduler();
+    let syn: DemiBuffer = client.get_test_rig().pop_frame();
+    check_signature(syn.clone())?;
+
+    let syn_ack: DemiBuffer = exchange_signed(server, syn)?;
+    let ack: DemiBuffer = exchange_signed(client, syn_ack)?;
+    server.receive(ack)?;
+    server.get_test_rig().poll_scheduler();
+
+    let server_qd: QDesc = match server
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(accept_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Accept((qd, _)))) => qd,
+        result => anyhow::bail!("accept should have succeeded: {:?}", result),
+    };
+    match client
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(connect_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Connect)) => (),
+        result => anyhow::bail!("connect should have succeeded: {:?}", result),
+    }
+    Ok((server_qd, client_qd))
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that the segments of a connection whose ends share a key carry a valid signature, and that data goes through.
+#[test]
+fn test_md5_signed_connection() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let (server_qd, client_qd): (QDesc, QDesc) = connect(&mut server, &mut client)?;
+
+    let pop_qt: QToken = server.tcp_pop(server_qd)?;
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x5a; 100])?)?;
+    client.get_test_rig().poll_scheduler();
+    let request: DemiBuffer = client.get_test_rig().pop_frame();
+    check_signature(request.clone())?;
+    server.receive(request)?;
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(has_completed(&mut server, pop_qt)?, true);
+
+    // The accepted connection signs the segments that it sends too.
+    let _push_qt: QToken = server.tcp_push(server_qd, DemiBuffer::from_slice(&[0xa5; 100])?)?;
+    server.get_test_rig().poll_scheduler();
+    check_signature(server.get_test_rig().pop_frame())?;
+
+    Ok(())
+}
+
+/// Tests that a segment whose signature was tampered with is dropped, and that the connection carries on once the
+/// genuine segment arrives.
+#[test]
+fn test_md5_tampered_signature_rejected() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let (server_qd, client_qd): (QDesc, QDesc) = connect(&mut server, &mut client)?;
+
+    let pop_qt: QToken = server.tcp_pop(server_qd)?;
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x5a; 100])?)?;
+    client.get_test_rig().poll_scheduler();
+    let genuine: DemiBuffer = client.get_test_rig().pop_frame();
+
+    // Flip a bit of the signature, and serialize the segment again, so that only the signature is wrong.
+    let (ethernet2_hdr, eth2_payload) = Ethernet2Header::parse(genuine.clone())?;
+    let (ipv4_hdr, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+    let (mut tcp_hdr, data): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_hdr, ipv4_payload, false)?;
+    for i in 0..tcp_hdr.num_options {
+        if let TcpOptions2::Md5Signature(ref mut signature) = tcp_hdr.option_list[i] {
+            signature[0] ^= 1;
+        }
+    }
+    client.get_test_rig().transmit(Box::new(TcpSegment {
+        ethernet2_hdr,
+        ipv4_hdr,
+        tcp_hdr,
+        data: Some(data),
+        tx_checksum_offload: false,
+    }));
+    server.receive(client.get_test_rig().pop_frame())?;
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(has_completed(&mut server, pop_qt)?, false);
+    crate::ensure_eq!(server.get_test_rig().pop_all_frames().len(), 0);
+
+    server.receive(genuine)?;
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(has_completed(&mut server, pop_qt)?, true);
+
+    Ok(())
+}
+
+/// Tests that a listener with a key ignores connection requests that are not signed.
+#[test]
+fn test_md5_unsigned_syn_rejected() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let listen_qd: QDesc = server.tcp_socket()?;
+    server.tcp_set_md5_key(listen_qd, Some(KEY))?;
+    server.tcp_bind(listen_qd, listen_addr)?;
+    server.tcp_listen(listen_qd, 1)?;
+
+    let client_qd: QDesc = client.tcp_socket()?;
+    let _connect_qt: QToken = client.tcp_connect(client_qd, listen_addr)?;
+    client.get_test_rig().poll_scheduler();
+    server.receive(client.get_test_rig().pop_frame())?;
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(server.get_test_rig().pop_all_frames().len(), 0);
+
+    Ok(())
+}
@@ -5,10 +5,51 @@
 // Exports
 //======================================================================================================================
 
+mod accept_filter;
+mod accept_ready;
+mod autocork;
+mod backlog;
+mod buffer_usage;
+mod coalesce;
+mod concurrent_accept;
+mod connect;
+mod connect_readiness;
+mod egress_hook;
+mod error_counters;
 pub mod established;
+mod half_close;
+mod idle_timeout;
+mod ingress_hook;
+mod inherited_options;
+mod isn_generator;
+mod link_addrs;
+mod max_queues;
+#[cfg(feature = "tcp-md5")]
+mod md5;
+mod negotiated_options;
+mod option_parsing;
+mod out_of_order;
+mod outstanding_segments;
+mod path_mss;
+mod pending_operations;
+mod pop_into;
+mod pop_timeout;
+mod psh;
+mod push_more;
+mod retransmission;
+mod send_buffer;
 pub mod setup;
 #[cfg(debug_assertions)]
 mod simulator;
+mod state_counts;
+mod syn_cookie;
+mod tcp_info;
+mod time_wait;
+mod trace;
+mod try_operations;
+mod tx_priority;
+mod urgent;
+mod window;
 
 //======================================================================================================================
 // Imports
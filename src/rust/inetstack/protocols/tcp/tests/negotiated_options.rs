@@ -0,0 +1,113 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::{
+            tests::setup::connection_setup,
+            NegotiatedOptions,
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        network::config::TcpConfig,
+        QDesc,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Maximum segment size advertised by the client.
+const CLIENT_MSS: usize = 1200;
+
+/// Window scale advertised by the client.
+const CLIENT_WINDOW_SCALE: u8 = 3;
+
+/// Maximum segment size advertised by the server.
+const SERVER_MSS: usize = 1000;
+
+/// Window scale advertised by the server.
+const SERVER_WINDOW_SCALE: u8 = 5;
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that both ends of a connection report the options that each of them advertised during the handshake.
+#[test]
+fn test_negotiated_options() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let mut server: SharedEngine = test_helpers::new_bob2_with_tcp_config(
+        now,
+        TcpConfig::default()
+            .set_advertised_mss(SERVER_MSS)
+            .set_window_scale(SERVER_WINDOW_SCALE),
+    );
+    let mut client: SharedEngine = test_helpers::new_alice2_with_tcp_config(
+        now,
+        TcpConfig::default()
+            .set_advertised_mss(CLIENT_MSS)
+            .set_window_scale(CLIENT_WINDOW_SCALE),
+    );
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    crate::ensure_eq!(
+        client.tcp_negotiated_options(client_qd)?,
+        NegotiatedOptions {
+            mss: SERVER_MSS,
+            local_window_scale: CLIENT_WINDOW_SCALE,
+            remote_window_scale: SERVER_WINDOW_SCALE,
+            sack_permitted: false,
+            timestamps: false,
+        }
+    );
+    crate::ensure_eq!(
+        server.tcp_negotiated_options(server_qd)?,
+        NegotiatedOptions {
+            mss: CLIENT_MSS,
+            local_window_scale: SERVER_WINDOW_SCALE,
+            remote_window_scale: CLIENT_WINDOW_SCALE,
+            sack_permitted: false,
+            timestamps: false,
+        }
+    );
+
+    Ok(())
+}
+
+/// Tests that reading the negotiated options of a socket that is not connected fails.
+#[test]
+fn test_negotiated_options_not_established() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let listen_qd: QDesc = server.tcp_socket()?;
+    match server.tcp_negotiated_options(listen_qd) {
+        Err(e) if e.errno == libc::EINVAL => (),
+        result => anyhow::bail!("reading negotiated options should have failed: {:?}", result),
+    }
+    server.tcp_bind(listen_qd, SocketAddrV4::new(test_helpers::BOB_IPV4, 80))?;
+    server.tcp_listen(listen_qd, 1)?;
+    match server.tcp_negotiated_options(listen_qd) {
+        Err(e) if e.errno == libc::EINVAL => (),
+        result => anyhow::bail!("reading negotiated options should have failed: {:?}", result),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,209 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::{
+            ethernet2::Ethernet2Header,
+            ip::IpProtocol,
+            ipv4::Ipv4Header,
+            tcp::segment::{
+                TcpHeader,
+                TcpOptions2,
+                TcpSegment,
+                MIN_TCP_HEADER_SIZE,
+            },
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        fail::Fail,
+        memory::DemiBuffer,
+        network::consts::{
+            DEFAULT_MSS,
+            MAX_MSS,
+            MIN_MSS,
+        },
+        OperationResult,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Flags of a SYN segment, as found in the 14th byte of the TCP header.
+const SYN: u8 = 1 << 1;
+
+/// Flags of a segment that only acknowledges data.
+const ACK: u8 = 1 << 4;
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Parses a TCP header that has the given `flags` and carries `options`, which are padded to a multiple of 4 bytes.
+fn parse(flags: u8, options: &[u8]) -> Result<TcpHeader, Fail> {
+    let header_size: usize = (MIN_TCP_HEADER_SIZE + options.len() + 3) & !0x3;
+    let mut bytes: Vec<u8> = vec![0; header_size];
+    bytes[0..2].copy_from_slice(&12345u16.to_be_bytes());
+    bytes[2..4].copy_from_slice(&80u16.to_be_bytes());
+    bytes[12] = ((header_size / 4) as u8) << 4;
+    bytes[13] = flags;
+    bytes[14..16].copy_from_slice(&u16::MAX.to_be_bytes());
+    bytes[MIN_TCP_HEADER_SIZE..(MIN_TCP_HEADER_SIZE + options.len())].copy_from_slice(options);
+
+    let ipv4_hdr: Ipv4Header = Ipv4Header::new(test_helpers::ALICE_IPV4, test_helpers::BOB_IPV4, IpProtocol::TCP);
+    let buf: DemiBuffer = DemiBuffer::from_slice(&bytes).expect("header should fit in a buffer");
+    let (tcp_hdr, _): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_hdr, buf, true)?;
+    Ok(tcp_hdr)
+}
+
+/// Checks that a SYN segment that carries `options` is rejected as malformed.
+fn ensure_malformed(options: &[u8]) -> Result<()> {
+    match parse(SYN, options) {
+        Err(e) if e.errno == libc::EBADMSG => Ok(()),
+        result => anyhow::bail!("parsing {:?} should have failed with EBADMSG: {:?}", options, result),
+    }
+}
+
+/// Connects `client` to `server`, with the MSS advertised in the SYN of the client rewritten to `mss`, and returns the
+/// queue descriptor of the connection that the server accepts.
+fn accept_with_mss(server: &mut SharedEngine, client: &mut SharedEngine, mss: u16) -> Result<QDesc> {
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let listen_qd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(listen_qd, listen_addr)?;
+    server.tcp_listen(listen_qd, 1)?;
+    let accept_qt: QToken = server.tcp_accept(listen_qd)?;
+
+    let client_qd: QDesc = client.tcp_socket()?;
+    let _connect_qt: QToken = client.tcp_connect(client_qd, listen_addr)?;
+    client.get_test_rig().poll_scheduler();
+
+    // Rewrite the MSS of the SYN, and serialize it again.
+    let (ethernet2_hdr, eth2_payload) = Ethernet2Header::parse(client.get_test_rig().pop_frame())?;
+    let (ipv4_hdr, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+    let (mut tcp_hdr, data): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_hdr, ipv4_payload, false)?;
+    for i in 0..tcp_hdr.num_options {
+        if let TcpOptions2::MaximumSegmentSize(ref mut advertised) = tcp_hdr.option_list[i] {
+            *advertised = mss;
+        }
+    }
+    client.get_test_rig().transmit(Box::new(TcpSegment {
+        ethernet2_hdr,
+        ipv4_hdr,
+        tcp_hdr,
+        data: Some(data),
+        tx_checksum_offload: false,
+    }));
+    server.receive(client.get_test_rig().pop_frame())?;
+    server.get_test_rig().poll_scheduler();
+
+    // SYN+ACK: Server -> Client, and ACK: Client -> Server.
+    client.receive(server.get_test_rig().pop_frame())?;
+    client.get_test_rig().poll_scheduler();
+    server.receive(client.get_test_rig().pop_frame())?;
+    server.get_test_rig().poll_scheduler();
+
+    match server
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(accept_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Accept((qd, _)))) => Ok(qd),
+        result => anyhow::bail!("accept should have succeeded: {:?}", result),
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that options whose length is missing, too small or runs past the header are rejected.
+#[test]
+fn test_option_parsing_invalid_length() -> Result<()> {
+    // The length of the MSS option would be in the byte after the header.
+    ensure_malformed(&[1, 1, 1, 2])?;
+    ensure_malformed(&[2, 0, 0, 0])?;
+    ensure_malformed(&[2, 1, 0, 0])?;
+    ensure_malformed(&[2, 8, 0x05, 0xb4])?;
+    // Unknown options must have a valid length too, or there is no telling where the next option starts.
+    ensure_malformed(&[0xfe, 0, 0, 0])?;
+    ensure_malformed(&[0xfe, 12, 0, 0])?;
+
+    Ok(())
+}
+
+/// Tests that options that appear twice are rejected.
+#[test]
+fn test_option_parsing_duplicate() -> Result<()> {
+    ensure_malformed(&[2, 4, 0x05, 0xb4, 2, 4, 0x05, 0xb4])?;
+    ensure_malformed(&[3, 3, 7, 3, 3, 7])?;
+
+    Ok(())
+}
+
+/// Tests that unknown options with a valid length are skipped, along with the options that follow them.
+#[test]
+fn test_option_parsing_unknown_skipped() -> Result<()> {
+    let tcp_hdr: TcpHeader = parse(SYN, &[0xfe, 4, 0xaa, 0xbb, 2, 4, 0x05, 0xb4])?;
+    crate::ensure_eq!(tcp_hdr.num_options, 1);
+    crate::ensure_eq!(tcp_hdr.get_mss(), Some(1460));
+
+    Ok(())
+}
+
+/// Tests that the MSS is ignored on segments other than SYNs, and raised to the minimum on SYNs. Large advertisements
+/// are capped by the connection instead, see `test_option_parsing_large_mss_connection`.
+#[test]
+fn test_option_parsing_mss() -> Result<()> {
+    let tcp_hdr: TcpHeader = parse(ACK, &[2, 4, 0x05, 0xb4])?;
+    crate::ensure_eq!(tcp_hdr.num_options, 0);
+    crate::ensure_eq!(tcp_hdr.get_mss(), None);
+
+    crate::ensure_eq!(parse(SYN, &[2, 4, 0, 0])?.get_mss(), Some(MIN_MSS));
+    crate::ensure_eq!(parse(SYN, &[])?.get_mss(), None);
+
+    Ok(())
+}
+
+/// Tests that a connection request that advertises an MSS of zero gets a connection that uses the minimum MSS instead.
+#[test]
+fn test_option_parsing_zero_mss_connection() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let server_qd: QDesc = accept_with_mss(&mut server, &mut client, 0)?;
+    crate::ensure_eq!(server.tcp_mss(server_qd)?, MIN_MSS);
+
+    Ok(())
+}
+
+/// Tests that a connection request that advertises the largest MSS gets a connection that does not send segments
+/// larger than the MSS that the local peer advertises, even though the MTU would allow it.
+#[test]
+fn test_option_parsing_large_mss_connection() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let server_qd: QDesc = accept_with_mss(&mut server, &mut client, u16::MAX)?;
+    crate::ensure_eq!(server.tcp_mss(server_qd)?, MAX_MSS);
+    crate::ensure_eq!(server.tcp_path_mss(server_qd)?, DEFAULT_MSS);
+
+    Ok(())
+}
@@ -0,0 +1,236 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::{
+            ethernet2::Ethernet2Header,
+            ipv4::Ipv4Header,
+            tcp::{
+                segment::TcpHeader,
+                tests::setup::{
+                    connection_setup,
+                    deliver_frames,
+                    pop_all,
+                },
+            },
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        network::config::TcpConfig,
+        OperationResult,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    collections::VecDeque,
+    net::SocketAddrV4,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Number of segments sent by the client.
+const NUM_SEGMENTS: usize = 10;
+
+/// Size of each segment sent by the client.
+const SEGMENT_SIZE: usize = 100;
+
+/// Time that the server waits for a hole to be filled in the reassembly tests.
+const REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Has the client send [NUM_SEGMENTS] segments to a server with the given limits on out-of-order data, and delivers
+/// all of them but the first one. Checks that the server holds no more than `expected_out_of_order` bytes, and that
+/// the stream still completes once the first segment and the dropped ones are retransmitted.
+fn do_test_out_of_order_limits(
+    out_of_order_max_segments: usize,
+    out_of_order_max_bytes: usize,
+    expected_out_of_order: usize,
+) -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let tcp_config: TcpConfig = TcpConfig::default()
+        .set_out_of_order_max_segments(out_of_order_max_segments)
+        .set_out_of_order_max_bytes(out_of_order_max_bytes);
+    let mut server: SharedEngine = test_helpers::new_bob2_with_tcp_config(now, tcp_config);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Send each segment with its own byte pattern, so that the order of the stream can be checked. The segments must not
+    // be coalesced.
+    client.tcp_set_nodelay(client_qd, true)?;
+    for i in 0..NUM_SEGMENTS {
+        let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[i as u8; SEGMENT_SIZE])?)?;
+    }
+    client.get_test_rig().poll_scheduler();
+    let mut frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), NUM_SEGMENTS);
+
+    // Lose the first segment, so that every other one arrives out of order.
+    let first: DemiBuffer = frames.pop_front().expect("there should be a first segment");
+    for frame in frames {
+        server.receive(frame)?;
+    }
+    server.get_test_rig().poll_scheduler();
+    server.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(
+        server.tcp_buffer_usage(server_qd)?.recv_out_of_order,
+        expected_out_of_order
+    );
+    crate::ensure_eq!(server.tcp_buffer_usage(server_qd)?.recv_queued, 0);
+
+    // Once the hole is filled, the segments that were held become readable.
+    server.receive(first)?;
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(server.tcp_buffer_usage(server_qd)?.recv_out_of_order, 0);
+    crate::ensure_eq!(
+        server.tcp_buffer_usage(server_qd)?.recv_queued,
+        SEGMENT_SIZE + expected_out_of_order
+    );
+
+    // The client retransmits the segments that were dropped.
+    for _ in 0..NUM_SEGMENTS {
+        if server.tcp_buffer_usage(server_qd)?.recv_queued == NUM_SEGMENTS * SEGMENT_SIZE {
+            break;
+        }
+        deliver_frames(&mut server, &mut client)?;
+        now += client.tcp_rto(client_qd)?;
+        client.advance_clock(now);
+        server.advance_clock(now);
+        deliver_frames(&mut client, &mut server)?;
+    }
+
+    let expected: Vec<u8> = (0..NUM_SEGMENTS).flat_map(|i| [i as u8; SEGMENT_SIZE]).collect();
+    crate::ensure_eq!(pop_all(&mut server, server_qd, NUM_SEGMENTS * SEGMENT_SIZE)?, expected);
+
+    Ok(())
+}
+
+/// Sets up a connection to a server with a reassembly timeout of [REASSEMBLY_TIMEOUT], and has the client send two
+/// segments, of which only the second one reaches the server. Returns the server, its connected socket, and the first
+/// segment.
+fn send_with_hole() -> Result<(SharedEngine, QDesc, DemiBuffer)> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let tcp_config: TcpConfig = TcpConfig::default().set_reassembly_timeout(REASSEMBLY_TIMEOUT);
+    let mut server: SharedEngine = test_helpers::new_bob2_with_tcp_config(now, tcp_config);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    client.tcp_set_nodelay(client_qd, true)?;
+    for i in 0..2 {
+        let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[i as u8; SEGMENT_SIZE])?)?;
+    }
+    client.get_test_rig().poll_scheduler();
+    let mut frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 2);
+    let first: DemiBuffer = frames.pop_front().expect("there should be a first segment");
+    server.receive(frames.pop_front().expect("there should be a second segment"))?;
+    server.get_test_rig().poll_scheduler();
+    server.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(server.tcp_buffer_usage(server_qd)?.recv_out_of_order, SEGMENT_SIZE);
+
+    Ok((server, server_qd, first))
+}
+
+/// Advances the clock of `engine` by `duration`, and checks whether it reset the connection in the meantime.
+fn advance_and_check_reset(engine: &mut SharedEngine, duration: Duration) -> Result<bool> {
+    let now: Instant = engine.get_test_rig().get_runtime().get_now() + duration;
+    engine.advance_clock(now);
+    engine.get_test_rig().poll_scheduler();
+    let mut reset: bool = false;
+    for frame in engine.get_test_rig().pop_all_frames() {
+        let (_, eth2_payload) = Ethernet2Header::parse(frame)?;
+        let (ipv4_header, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+        let (tcp_header, _) = TcpHeader::parse(&ipv4_header, ipv4_payload, false)?;
+        reset |= tcp_header.rst;
+    }
+    Ok(reset)
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that the number of out-of-order segments held by a connection is bounded.
+#[test]
+fn test_out_of_order_max_segments() -> Result<()> {
+    do_test_out_of_order_limits(4, 64 * 1024, 4 * SEGMENT_SIZE)
+}
+
+/// Tests that the number of out-of-order bytes held by a connection is bounded.
+#[test]
+fn test_out_of_order_max_bytes() -> Result<()> {
+    do_test_out_of_order_limits(16, 3 * SEGMENT_SIZE + SEGMENT_SIZE / 2, 3 * SEGMENT_SIZE)
+}
+
+/// Tests that a connection is reset once a hole before out-of-order data stays unfilled for the reassembly timeout,
+/// and that the pending pop fails and the out-of-order data is released.
+#[test]
+fn test_reassembly_timeout() -> Result<()> {
+    let (mut server, server_qd, _first): (SharedEngine, QDesc, DemiBuffer) = send_with_hole()?;
+    let pop_qt: QToken = server.tcp_pop(server_qd)?;
+
+    crate::ensure_eq!(
+        advance_and_check_reset(&mut server, REASSEMBLY_TIMEOUT - Duration::from_secs(1))?,
+        false
+    );
+    crate::ensure_eq!(server.tcp_buffer_usage(server_qd)?.recv_out_of_order, SEGMENT_SIZE);
+
+    crate::ensure_eq!(advance_and_check_reset(&mut server, Duration::from_secs(1))?, true);
+    crate::ensure_eq!(server.tcp_buffer_usage(server_qd)?.recv_out_of_order, 0);
+    server.get_test_rig().poll_scheduler();
+    match server
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(pop_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Failed(e))) if e.errno == libc::ETIMEDOUT => Ok(()),
+        result => anyhow::bail!("pop should have failed with ETIMEDOUT: {:?}", result),
+    }
+}
+
+/// Tests that filling the hole before out-of-order data in time stops the reassembly timeout.
+#[test]
+fn test_reassembly_timeout_stopped_when_hole_filled() -> Result<()> {
+    let (mut server, server_qd, first): (SharedEngine, QDesc, DemiBuffer) = send_with_hole()?;
+
+    crate::ensure_eq!(
+        advance_and_check_reset(&mut server, REASSEMBLY_TIMEOUT - Duration::from_secs(1))?,
+        false
+    );
+    server.receive(first)?;
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(server.tcp_buffer_usage(server_qd)?.recv_out_of_order, 0);
+
+    crate::ensure_eq!(advance_and_check_reset(&mut server, REASSEMBLY_TIMEOUT)?, false);
+    let expected: Vec<u8> = (0..2).flat_map(|i| [i as u8; SEGMENT_SIZE]).collect();
+    crate::ensure_eq!(pop_all(&mut server, server_qd, 2 * SEGMENT_SIZE)?, expected);
+
+    Ok(())
+}
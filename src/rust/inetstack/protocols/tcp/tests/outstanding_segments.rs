@@ -0,0 +1,110 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::{
+            tests::setup::connection_setup,
+            SeqNumber,
+            UnackedSegmentInfo,
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Number of pushes that the client issues.
+const NUM_PUSHES: usize = 3;
+
+/// Size of each push.
+const PUSH_SIZE: usize = 500;
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that the segments of a stalled transfer are reported as outstanding, with contiguous sequence ranges, and that
+/// the retransmission of the oldest one is accounted for.
+#[test]
+fn test_outstanding_segments_stalled_transfer() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let (_, client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    client.tcp_set_nodelay(client_qd, true)?;
+    crate::ensure_eq!(client.tcp_outstanding_segments(client_qd)?.is_empty(), true);
+
+    // The server never gets the data, so all of it stays outstanding.
+    for _ in 0..NUM_PUSHES {
+        let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x5a; PUSH_SIZE])?)?;
+    }
+    client.get_test_rig().poll_scheduler();
+    client.get_test_rig().pop_all_frames();
+
+    let segments: Vec<UnackedSegmentInfo> = client.tcp_outstanding_segments(client_qd)?;
+    crate::ensure_eq!(segments.len(), NUM_PUSHES);
+    for (i, segment) in segments.iter().enumerate() {
+        crate::ensure_eq!(segment.end - segment.start, SeqNumber::from(PUSH_SIZE as u32));
+        crate::ensure_eq!(segment.last_tx, now);
+        crate::ensure_eq!(segment.retransmits, 0);
+        if i > 0 {
+            crate::ensure_eq!(segment.start, segments[i - 1].end);
+        }
+    }
+
+    // Only the oldest segment is retransmitted once the retransmission timeout expires.
+    now += client.tcp_info(client_qd)?.rto + Duration::from_millis(1);
+    client.advance_clock(now);
+    client.get_test_rig().poll_scheduler();
+
+    let retransmitted: Vec<UnackedSegmentInfo> = client.tcp_outstanding_segments(client_qd)?;
+    crate::ensure_eq!(retransmitted.len(), NUM_PUSHES);
+    crate::ensure_eq!(retransmitted[0].start, segments[0].start);
+    crate::ensure_eq!(retransmitted[0].last_tx, now);
+    crate::ensure_eq!(retransmitted[0].retransmits, 1);
+    crate::ensure_eq!(&retransmitted[1..], &segments[1..]);
+
+    Ok(())
+}
+
+/// Tests that the outstanding segments of a socket that is not connected cannot be queried.
+#[test]
+fn test_outstanding_segments_not_connected() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let client_qd: QDesc = client.tcp_socket()?;
+
+    match client.tcp_outstanding_segments(client_qd) {
+        Err(e) if e.errno == libc::ENOTCONN => Ok(()),
+        result => anyhow::bail!(
+            "querying the outstanding segments should have failed with ENOTCONN: {:?}",
+            result
+        ),
+    }
+}
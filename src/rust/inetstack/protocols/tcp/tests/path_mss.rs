@@ -0,0 +1,157 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::{
+            ethernet2::{
+                EtherType2,
+                Ethernet2Header,
+            },
+            icmpv4::{
+                Icmpv4Header,
+                Icmpv4Message,
+                Icmpv4Type2,
+            },
+            ip::IpProtocol,
+            ipv4::{
+                Ipv4Header,
+                IPV4_HEADER_MIN_SIZE,
+            },
+            tcp::tests::setup::connection_setup,
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        network::{
+            config::TcpConfig,
+            consts::{
+                DEFAULT_MSS,
+                DEFAULT_MTU,
+                MIN_MSS,
+            },
+            PacketBuf,
+        },
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Size of the IPv4 and TCP headers of a segment without options.
+const HEADERS_SIZE: usize = 20 + 20;
+
+/// Maximum segment size advertised by both ends, which is larger than what the MTU allows.
+const LARGE_MSS: usize = 9000;
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Has `client` send data on `client_qd`, and hands it an ICMP Fragmentation Needed error for the resulting segment,
+/// as a router whose next hop has the given `next_hop_mtu` would.
+fn receive_fragmentation_needed(client: &mut SharedEngine, client_qd: QDesc, next_hop_mtu: u16) -> Result<()> {
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x5a; 100])?)?;
+    client.get_test_rig().poll_scheduler();
+
+    // The message embeds the IPv4 header of the segment and the first 8 bytes of its payload.
+    let (_, mut embedded): (Ethernet2Header, DemiBuffer) = Ethernet2Header::parse(client.get_test_rig().pop_frame())?;
+    embedded.trim(embedded.len() - (IPV4_HEADER_MIN_SIZE as usize + 8))?;
+    let msg: Icmpv4Message = Icmpv4Message::new(
+        Ethernet2Header::new(test_helpers::ALICE_MAC, test_helpers::BOB_MAC, EtherType2::Ipv4),
+        Ipv4Header::new(test_helpers::BOB_IPV4, test_helpers::ALICE_IPV4, IpProtocol::ICMPv4),
+        Icmpv4Header::new(Icmpv4Type2::DestinationUnreachable { next_hop_mtu }, 4),
+        embedded,
+    );
+    let header_size: usize = msg.header_size();
+    let mut frame: DemiBuffer = DemiBuffer::new((header_size + msg.body_size()) as u16);
+    msg.write_header(&mut frame[..header_size]);
+    if let Some(body) = msg.take_body() {
+        frame[header_size..].copy_from_slice(&body[..]);
+    }
+    client.receive(frame)?;
+    client.get_test_rig().poll_scheduler();
+
+    Ok(())
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that the path MSS of a connection is the negotiated MSS, capped by what fits in the MTU after the headers.
+#[test]
+fn test_path_mss_bounded_by_mtu() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let mut server: SharedEngine =
+        test_helpers::new_bob2_with_tcp_config(now, TcpConfig::default().set_advertised_mss(LARGE_MSS));
+    let mut client: SharedEngine =
+        test_helpers::new_alice2_with_tcp_config(now, TcpConfig::default().set_advertised_mss(LARGE_MSS));
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Each end can only use as much of the MSS of the other as the MTU allows.
+    crate::ensure_eq!(client.tcp_mss(client_qd)?, LARGE_MSS);
+    crate::ensure_eq!(client.tcp_path_mss(client_qd)?, DEFAULT_MTU - HEADERS_SIZE);
+    crate::ensure_eq!(server.tcp_path_mss(server_qd)?, DEFAULT_MTU - HEADERS_SIZE);
+
+    Ok(())
+}
+
+/// Tests that the path MSS of a connection goes down when an ICMP error reports a smaller MTU on the path, and that it
+/// does not go back up afterwards.
+#[test]
+fn test_path_mss_lowered_by_path_mtu_discovery() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let (_, client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    receive_fragmentation_needed(&mut client, client_qd, 1000)?;
+    crate::ensure_eq!(client.tcp_path_mss(client_qd)?, 1000 - HEADERS_SIZE);
+
+    // A larger next-hop MTU should not raise the MSS again.
+    receive_fragmentation_needed(&mut client, client_qd, 1400)?;
+    crate::ensure_eq!(client.tcp_path_mss(client_qd)?, 1000 - HEADERS_SIZE);
+
+    // A tiny next-hop MTU should not lower the MSS below the minimum.
+    receive_fragmentation_needed(&mut client, client_qd, 68)?;
+    crate::ensure_eq!(client.tcp_path_mss(client_qd)?, MIN_MSS);
+
+    // The advertised MSS should be left untouched.
+    crate::ensure_eq!(client.tcp_mss(client_qd)?, DEFAULT_MSS);
+
+    Ok(())
+}
+
+/// Tests that the path MSS of a socket that is not connected only accounts for the headers.
+#[test]
+fn test_path_mss_not_connected() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let client_qd: QDesc = client.tcp_socket()?;
+    crate::ensure_eq!(client.tcp_path_mss(client_qd)?, DEFAULT_MTU - HEADERS_SIZE);
+
+    Ok(())
+}
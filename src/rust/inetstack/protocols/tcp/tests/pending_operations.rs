@@ -0,0 +1,91 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::tests::setup::connection_setup,
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        OperationResult,
+        QDesc,
+        QToken,
+        SharedDemiRuntime,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that the count of pending operations of a queue goes up as operations are issued, stays up once they complete,
+/// and goes down as their results are taken.
+#[test]
+fn test_pending_operations() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    let runtime: SharedDemiRuntime = server.get_test_rig().get_runtime();
+    crate::ensure_eq!(runtime.pending_operations(server_qd)?, 0);
+
+    let first_qt: QToken = server.tcp_pop(server_qd)?;
+    let second_qt: QToken = server.tcp_pop(server_qd)?;
+    crate::ensure_eq!(runtime.pending_operations(server_qd)?, 2);
+
+    // The first pop completes with the data, but it still counts until its result is taken.
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x5a; 100])?)?;
+    client.get_test_rig().poll_scheduler();
+    server.receive(client.get_test_rig().pop_frame())?;
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(runtime.from_task_id(first_qt)?.has_completed(), true);
+    crate::ensure_eq!(runtime.from_task_id(second_qt)?.has_completed(), false);
+    crate::ensure_eq!(runtime.pending_operations(server_qd)?, 2);
+
+    match server
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(first_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Pop(_, buf))) => crate::ensure_eq!(buf.len(), 100),
+        result => anyhow::bail!("pop should have succeeded: {:?}", result),
+    }
+    crate::ensure_eq!(runtime.pending_operations(server_qd)?, 1);
+
+    // Operations of other queues are not counted.
+    crate::ensure_eq!(client.get_test_rig().get_runtime().pending_operations(client_qd)?, 1);
+
+    Ok(())
+}
+
+/// Tests that counting the pending operations of a queue that does not exist fails.
+#[test]
+fn test_pending_operations_bad_queue() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    match server
+        .get_test_rig()
+        .get_runtime()
+        .pending_operations(QDesc::from(u32::MAX))
+    {
+        Err(e) if e.errno == libc::EBADF => Ok(()),
+        result => anyhow::bail!("counting should have failed with EBADF: {:?}", result),
+    }
+}
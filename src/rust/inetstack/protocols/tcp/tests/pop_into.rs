@@ -0,0 +1,135 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::tests::setup::{
+            connection_setup,
+            deliver_frames,
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        types::{
+            demi_sgarray_t,
+            demi_sgaseg_t,
+        },
+        OperationResult,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::libc::c_void;
+use ::std::{
+    mem,
+    net::SocketAddrV4,
+    ptr,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Builds a scatter-gather array that describes `buf`, which is owned by the caller.
+fn new_user_sgarray(buf: &mut [u8]) -> demi_sgarray_t {
+    demi_sgarray_t {
+        sga_buf: ptr::null_mut(),
+        sga_numsegs: 1,
+        sga_segs: [demi_sgaseg_t {
+            sgaseg_buf: buf.as_mut_ptr() as *mut c_void,
+            sgaseg_len: buf.len() as u32,
+        }],
+        sga_addr: unsafe { mem::zeroed() },
+    }
+}
+
+/// Polls `engine` and returns the number of bytes that the pop_into operation identified by `qt` copied.
+fn wait_pop_into(engine: &mut SharedEngine, qt: QToken) -> Result<usize> {
+    engine.get_test_rig().poll_scheduler();
+    match engine
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(qt)
+        .get_result()
+    {
+        Some((_, OperationResult::PopInto(None, _, nbytes))) => Ok(nbytes),
+        Some((_, result)) => anyhow::bail!("pop_into did not complete successfully: {:?}", result),
+        None => anyhow::bail!("pop_into should have completed"),
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that pop_into() fills caller-provided buffers from a stream, and partially fills them when less data is
+/// available than they can hold.
+#[test]
+fn test_pop_into_fills_user_buffers() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    let data: &[u8] = b"hello world";
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(data)?)?;
+    deliver_frames(&mut client, &mut server)?;
+
+    // The first buffer is smaller than the available data, so it should be completely filled.
+    let mut first: [u8; 6] = [0; 6];
+    let first_sga: demi_sgarray_t = new_user_sgarray(&mut first);
+    let qt: QToken = server.tcp_pop_into(server_qd, &first_sga)?;
+    crate::ensure_eq!(wait_pop_into(&mut server, qt)?, 6);
+    crate::ensure_eq!(&first, b"hello ");
+
+    // The second buffer is larger than the remaining data, so it should only be partially filled.
+    let mut second: [u8; 16] = [0xff; 16];
+    let second_sga: demi_sgarray_t = new_user_sgarray(&mut second);
+    let qt: QToken = server.tcp_pop_into(server_qd, &second_sga)?;
+    crate::ensure_eq!(wait_pop_into(&mut server, qt)?, 5);
+    crate::ensure_eq!(&second[..5], b"world");
+    crate::ensure_eq!(&second[5..], &[0xff; 11]);
+
+    Ok(())
+}
+
+/// Tests that pop_into() waits for data when none is available yet.
+#[test]
+fn test_pop_into_waits_for_data() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    let mut buf: [u8; 8] = [0; 8];
+    let sga: demi_sgarray_t = new_user_sgarray(&mut buf);
+    let qt: QToken = server.tcp_pop_into(server_qd, &sga)?;
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(
+        server.get_test_rig().get_runtime().from_task_id(qt)?.has_completed(),
+        false
+    );
+
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(b"abc")?)?;
+    deliver_frames(&mut client, &mut server)?;
+    crate::ensure_eq!(wait_pop_into(&mut server, qt)?, 3);
+    crate::ensure_eq!(&buf[..3], b"abc");
+
+    Ok(())
+}
@@ -0,0 +1,198 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::tests::setup::{
+            connection_setup,
+            has_completed,
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        OperationResult,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    collections::VecDeque,
+    net::SocketAddrV4,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Timeout of the pops in these tests.
+const POP_TIMEOUT: Duration = Duration::from_secs(1);
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Sets up a connection from a client to a server, with small pushes of the client sent right away, and returns both
+/// along with the queue descriptor of each end.
+fn setup(now: &mut Instant) -> Result<(SharedEngine, QDesc, SharedEngine, QDesc)> {
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(*now);
+    let mut client: SharedEngine = test_helpers::new_alice2(*now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(now, &mut server, &mut client, listen_port, listen_addr)?;
+    client.tcp_set_nodelay(client_qd, true)?;
+    Ok((server, server_qd, client, client_qd))
+}
+
+/// Pushes `data` from `client` and delivers it to `server`, without polling the latter.
+fn send_data(client: &mut SharedEngine, client_qd: QDesc, server: &mut SharedEngine, data: &[u8]) -> Result<()> {
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(data)?)?;
+    let frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    for frame in frames {
+        server.receive(frame)?;
+    }
+    Ok(())
+}
+
+/// Takes the result of the completed operation `qt` of `engine`.
+fn take_result(engine: &mut SharedEngine, qt: QToken) -> Result<OperationResult> {
+    match engine
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(qt)
+        .get_result()
+    {
+        Some((_, result)) => Ok(result),
+        None => anyhow::bail!("operation should have completed"),
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that a pop with a timeout gathers the data that arrives before the deadline, and returns it once the deadline
+/// expires even though less than the requested size arrived.
+#[test]
+fn test_pop_with_timeout_returns_partial_data() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let (mut server, server_qd, mut client, client_qd) = setup(&mut now)?;
+    let pop_qt: QToken = server.tcp_pop_with_timeout(server_qd, 1000, POP_TIMEOUT)?;
+    server.get_test_rig().poll_scheduler();
+
+    // Data arriving before the deadline should not complete the pop, as it is less than requested.
+    send_data(&mut client, client_qd, &mut server, &[0x1; 100])?;
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(has_completed(&mut server, pop_qt)?, false);
+
+    now += POP_TIMEOUT / 2;
+    server.advance_clock(now);
+    client.advance_clock(now);
+    send_data(&mut client, client_qd, &mut server, &[0x2; 200])?;
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(has_completed(&mut server, pop_qt)?, false);
+
+    // Once the deadline expires, the pop should return all the data that arrived, in order.
+    now += POP_TIMEOUT / 2;
+    server.advance_clock(now);
+    server.get_test_rig().poll_scheduler();
+    match take_result(&mut server, pop_qt)? {
+        OperationResult::Pop(_, buf) => {
+            crate::ensure_eq!(buf.len(), 300);
+            crate::ensure_eq!(buf[..100].iter().all(|b| *b == 0x1), true);
+            crate::ensure_eq!(buf[100..].iter().all(|b| *b == 0x2), true);
+        },
+        result => anyhow::bail!("pop should have returned data: {:?}", result),
+    };
+
+    Ok(())
+}
+
+/// Tests that a pop with a timeout returns as soon as the requested size has arrived, and that the data beyond it is
+/// left for the next pop.
+#[test]
+fn test_pop_with_timeout_returns_early_when_filled() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let (mut server, server_qd, mut client, client_qd) = setup(&mut now)?;
+    let pop_qt: QToken = server.tcp_pop_with_timeout(server_qd, 150, POP_TIMEOUT)?;
+    server.get_test_rig().poll_scheduler();
+
+    send_data(&mut client, client_qd, &mut server, &[0x1; 100])?;
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(has_completed(&mut server, pop_qt)?, false);
+    send_data(&mut client, client_qd, &mut server, &[0x2; 100])?;
+    server.get_test_rig().poll_scheduler();
+    match take_result(&mut server, pop_qt)? {
+        OperationResult::Pop(_, buf) => crate::ensure_eq!(buf.len(), 150),
+        result => anyhow::bail!("pop should have returned data: {:?}", result),
+    };
+
+    let pop_qt: QToken = server.tcp_pop(server_qd)?;
+    server.get_test_rig().poll_scheduler();
+    match take_result(&mut server, pop_qt)? {
+        OperationResult::Pop(_, buf) => {
+            crate::ensure_eq!(buf.len(), 50);
+            crate::ensure_eq!(buf.iter().all(|b| *b == 0x2), true);
+        },
+        result => anyhow::bail!("pop should have returned data: {:?}", result),
+    };
+
+    Ok(())
+}
+
+/// Tests that a pop with a timeout fails with ETIMEDOUT when no data arrives before the deadline.
+#[test]
+fn test_pop_with_timeout_fails_without_data() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let (mut server, server_qd, _, _) = setup(&mut now)?;
+    let pop_qt: QToken = server.tcp_pop_with_timeout(server_qd, 1000, POP_TIMEOUT)?;
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(has_completed(&mut server, pop_qt)?, false);
+
+    now += POP_TIMEOUT;
+    server.advance_clock(now);
+    server.get_test_rig().poll_scheduler();
+    match take_result(&mut server, pop_qt)? {
+        OperationResult::Failed(e) if e.errno == libc::ETIMEDOUT => Ok(()),
+        result => anyhow::bail!("pop should have failed with ETIMEDOUT: {:?}", result),
+    }
+}
+
+/// Tests that a pop with a zero timeout returns the data that is already queued without waiting for more, and fails
+/// right away when there is none.
+#[test]
+fn test_pop_with_zero_timeout() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let (mut server, server_qd, mut client, client_qd) = setup(&mut now)?;
+    let pop_qt: QToken = server.tcp_pop_with_timeout(server_qd, 1000, Duration::ZERO)?;
+    server.get_test_rig().poll_scheduler();
+    match take_result(&mut server, pop_qt)? {
+        OperationResult::Failed(e) if e.errno == libc::ETIMEDOUT => (),
+        result => anyhow::bail!("pop should have failed with ETIMEDOUT: {:?}", result),
+    };
+
+    send_data(&mut client, client_qd, &mut server, &[0x1; 100])?;
+    server.get_test_rig().poll_scheduler();
+    let pop_qt: QToken = server.tcp_pop_with_timeout(server_qd, 1000, Duration::ZERO)?;
+    server.get_test_rig().poll_scheduler();
+    match take_result(&mut server, pop_qt)? {
+        OperationResult::Pop(_, buf) => crate::ensure_eq!(buf.len(), 100),
+        result => anyhow::bail!("pop should have returned data: {:?}", result),
+    };
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::{
+            ethernet2::Ethernet2Header,
+            ipv4::Ipv4Header,
+            tcp::{
+                segment::{
+                    TcpHeader,
+                    TcpSegment,
+                },
+                tests::setup::{
+                    connect,
+                    has_completed,
+                    take_pop,
+                },
+            },
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        network::NetworkRuntime,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::time::Instant;
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Low watermark of the server, which is larger than any data that the client sends.
+const LOW_WATERMARK: usize = 1000;
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Connects `client` to `server`, with small pushes of the client sent right away, and returns the queue descriptors
+/// of the server and of the client.
+fn connect_nodelay(now: &mut Instant, server: &mut SharedEngine, client: &mut SharedEngine) -> Result<(QDesc, QDesc)> {
+    let (server_qd, client_qd): (QDesc, QDesc) = connect(now, server, client)?;
+    client.tcp_set_nodelay(client_qd, true)?;
+    Ok((server_qd, client_qd))
+}
+
+/// Has the client send `data` to the server in one segment, with PSH set if `psh` is.
+fn send(server: &mut SharedEngine, client: &mut SharedEngine, client_qd: QDesc, data: &[u8], psh: bool) -> Result<()> {
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(data)?)?;
+    client.get_test_rig().poll_scheduler();
+    let (ethernet2_hdr, eth2_payload) = Ethernet2Header::parse(client.get_test_rig().pop_frame())?;
+    let (ipv4_hdr, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+    let (mut tcp_hdr, data): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_hdr, ipv4_payload, false)?;
+    tcp_hdr.psh = psh;
+    client.get_test_rig().transmit(Box::new(TcpSegment {
+        ethernet2_hdr,
+        ipv4_hdr,
+        tcp_hdr,
+        data: Some(data),
+        tx_checksum_offload: false,
+    }));
+    server.receive(client.get_test_rig().pop_frame())?;
+    server.get_test_rig().poll_scheduler();
+    Ok(())
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that a pop waits for the low watermark while the data that arrives is not pushed, and that a segment with PSH
+/// completes it with all the data received so far, even below the low watermark.
+#[test]
+fn test_psh_completes_pop_below_low_watermark() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let (server_qd, client_qd): (QDesc, QDesc) = connect_nodelay(&mut now, &mut server, &mut client)?;
+    server.tcp_set_rcvlowat(server_qd, LOW_WATERMARK)?;
+
+    let pop_qt: QToken = server.tcp_pop(server_qd)?;
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(has_completed(&mut server, pop_qt)?, false);
+
+    send(&mut server, &mut client, client_qd, &[1; 10], false)?;
+    crate::ensure_eq!(has_completed(&mut server, pop_qt)?, false);
+
+    send(&mut server, &mut client, client_qd, &[2; 20], true)?;
+    crate::ensure_eq!(has_completed(&mut server, pop_qt)?, true);
+    let expected: Vec<u8> = [vec![1; 10], vec![2; 20]].concat();
+    crate::ensure_eq!(&take_pop(&mut server, pop_qt)?[..], &expected[..]);
+
+    Ok(())
+}
+
+/// Tests that data that is not pushed completes a pop once it reaches the low watermark.
+#[test]
+fn test_low_watermark_completes_pop() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let (server_qd, client_qd): (QDesc, QDesc) = connect_nodelay(&mut now, &mut server, &mut client)?;
+    server.tcp_set_rcvlowat(server_qd, 16)?;
+
+    let pop_qt: QToken = server.tcp_pop(server_qd)?;
+    send(&mut server, &mut client, client_qd, &[1; 10], false)?;
+    crate::ensure_eq!(has_completed(&mut server, pop_qt)?, false);
+
+    send(&mut server, &mut client, client_qd, &[2; 10], false)?;
+    crate::ensure_eq!(has_completed(&mut server, pop_qt)?, true);
+    crate::ensure_eq!(take_pop(&mut server, pop_qt)?.len(), 20);
+
+    Ok(())
+}
@@ -0,0 +1,152 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::{
+            ethernet2::Ethernet2Header,
+            ipv4::Ipv4Header,
+            tcp::{
+                segment::TcpHeader,
+                tests::setup::connect,
+            },
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        OperationResult,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::time::Instant;
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Polls `engine` and checks that the push `qt` completed successfully.
+fn wait_for_push(engine: &mut SharedEngine, qt: QToken) -> Result<()> {
+    engine.get_test_rig().poll_scheduler();
+    match engine
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Push(_))) => Ok(()),
+        Some((_, result)) => anyhow::bail!("push should have succeeded: {:?}", result),
+        None => anyhow::bail!("push should have completed"),
+    }
+}
+
+/// Returns the payloads and FIN flags of all TCP segments sent by `engine`.
+fn sent_segments(engine: &mut SharedEngine) -> Result<Vec<(DemiBuffer, bool)>> {
+    let mut segments: Vec<(DemiBuffer, bool)> = Vec::new();
+    for frame in engine.get_test_rig().pop_all_frames() {
+        let (_, eth2_payload) = Ethernet2Header::parse(frame)?;
+        let (ipv4_header, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+        let (tcp_header, tcp_payload): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_header, ipv4_payload, false)?;
+        segments.push((tcp_payload, tcp_header.fin));
+    }
+    Ok(segments)
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that data pushed with push_more() is held back and coalesced with the next normal push into one segment.
+#[test]
+fn test_push_more_coalesces_with_next_push() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let (_, client_qd): (QDesc, QDesc) = connect(&mut now, &mut server, &mut client)?;
+
+    // Data pushed with push_more() completes right away, but is not transmitted.
+    let first_qt: QToken = client.tcp_push_more(client_qd, DemiBuffer::from_slice(&[1; 10])?)?;
+    wait_for_push(&mut client, first_qt)?;
+    let second_qt: QToken = client.tcp_push_more(client_qd, DemiBuffer::from_slice(&[2; 20])?)?;
+    wait_for_push(&mut client, second_qt)?;
+    crate::ensure_eq!(sent_segments(&mut client)?.len(), 0);
+
+    // A normal push sends everything in a single segment.
+    let third_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[3; 30])?)?;
+    wait_for_push(&mut client, third_qt)?;
+    let segments: Vec<(DemiBuffer, bool)> = sent_segments(&mut client)?;
+    crate::ensure_eq!(segments.len(), 1);
+    let expected: Vec<u8> = [vec![1; 10], vec![2; 20], vec![3; 30]].concat();
+    crate::ensure_eq!(&segments[0].0[..], &expected[..]);
+
+    Ok(())
+}
+
+/// Tests that closing a socket sends the data held back by push_more() before the FIN.
+#[test]
+fn test_push_more_flushed_on_close() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let (_, client_qd): (QDesc, QDesc) = connect(&mut now, &mut server, &mut client)?;
+
+    let push_qt: QToken = client.tcp_push_more(client_qd, DemiBuffer::from_slice(&[7; 40])?)?;
+    wait_for_push(&mut client, push_qt)?;
+    let _close_qt: QToken = client.tcp_async_close(client_qd)?;
+    client.get_test_rig().poll_scheduler();
+
+    let segments: Vec<(DemiBuffer, bool)> = sent_segments(&mut client)?;
+    crate::ensure_eq!(segments.len(), 2);
+    crate::ensure_eq!(&segments[0].0[..], &[7; 40][..]);
+    crate::ensure_eq!(segments[0].1, false);
+    crate::ensure_eq!(segments[1].0.len(), 0);
+    crate::ensure_eq!(segments[1].1, true);
+
+    Ok(())
+}
+
+/// Tests that a flush sends a small push that Nagle's algorithm holds back while data is in flight, as well as data
+/// held back by push_more(), without waiting for the background sender.
+#[test]
+fn test_flush_sends_held_data() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let (_, client_qd): (QDesc, QDesc) = connect(&mut now, &mut server, &mut client)?;
+
+    // The first push goes out right away, and the second one is queued behind it, as it is smaller than the MSS.
+    let first_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[1; 10])?)?;
+    crate::ensure_eq!(sent_segments(&mut client)?.len(), 1);
+    let _second_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[2; 20])?)?;
+    crate::ensure_eq!(sent_segments(&mut client)?.len(), 0);
+    client.tcp_flush(client_qd)?;
+    let segments: Vec<(DemiBuffer, bool)> = sent_segments(&mut client)?;
+    crate::ensure_eq!(segments.len(), 1);
+    crate::ensure_eq!(&segments[0].0[..], &[2; 20][..]);
+    wait_for_push(&mut client, first_qt)?;
+
+    // Data held back by push_more() is sent as a partial segment, and the connection stays open.
+    let more_qt: QToken = client.tcp_push_more(client_qd, DemiBuffer::from_slice(&[3; 30])?)?;
+    wait_for_push(&mut client, more_qt)?;
+    crate::ensure_eq!(sent_segments(&mut client)?.len(), 0);
+    client.tcp_flush(client_qd)?;
+    let segments: Vec<(DemiBuffer, bool)> = sent_segments(&mut client)?;
+    crate::ensure_eq!(segments.len(), 1);
+    crate::ensure_eq!(&segments[0].0[..], &[3; 30][..]);
+    crate::ensure_eq!(segments[0].1, false);
+
+    // Flushing with nothing buffered sends nothing.
+    client.tcp_flush(client_qd)?;
+    crate::ensure_eq!(sent_segments(&mut client)?.len(), 0);
+
+    Ok(())
+}
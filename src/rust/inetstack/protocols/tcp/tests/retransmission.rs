@@ -0,0 +1,218 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::tests::setup::connection_setup,
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        network::config::TcpConfig,
+        OperationResult,
+        QDesc,
+        QToken,
+        SharedDemiRuntime,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Checks that the operation associated with `qt` has not completed yet.
+fn check_pending(engine: &mut SharedEngine, qt: QToken) -> Result<()> {
+    match engine.get_test_rig().get_runtime().from_task_id(qt) {
+        Ok(handle) if !handle.has_completed() => Ok(()),
+        Ok(_) => anyhow::bail!("operation should not have completed"),
+        Err(e) => anyhow::bail!("operation should exist: {:?}", e),
+    }
+}
+
+/// Checks that the operation associated with `qt` has failed with ETIMEDOUT.
+fn check_timed_out(engine: &mut SharedEngine, qt: QToken) -> Result<()> {
+    match engine
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Failed(e))) if e.errno == libc::ETIMEDOUT => Ok(()),
+        Some((_, result)) => anyhow::bail!("operation should have timed out: {:?}", result),
+        None => anyhow::bail!("operation should have completed"),
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that an active open gives up after the configured number of SYN retransmissions.
+#[test]
+fn test_syn_retransmission_limit() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let syn_retries: usize = 2;
+    let tcp_config: TcpConfig = TcpConfig::default()
+        .set_syn_retries(syn_retries)
+        .set_syn_ack_retries(0)
+        .set_data_retries(0);
+    let handshake_timeout: Duration = tcp_config.get_handshake_timeout();
+    let mut client: SharedEngine = test_helpers::new_alice2_with_tcp_config(now, tcp_config);
+    let remote: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+
+    let client_fd: QDesc = client.tcp_socket()?;
+    let connect_qt: QToken = client.tcp_connect(client_fd, remote)?;
+
+    // Drop the initial SYN and all of its retransmissions.
+    let mut num_syns: usize = 0;
+    for _ in 0..(syn_retries + 1) {
+        client.get_test_rig().poll_scheduler();
+        client.get_test_rig().poll_scheduler();
+        num_syns += client.get_test_rig().pop_all_frames().len();
+        check_pending(&mut client, connect_qt)?;
+        now += handshake_timeout;
+        client.advance_clock(now);
+    }
+    client.get_test_rig().poll_scheduler();
+
+    crate::ensure_eq!(num_syns, syn_retries + 1);
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 0);
+    check_timed_out(&mut client, connect_qt)
+}
+
+/// Tests that a passive open gives up after the configured number of SYN+ACK retransmissions.
+#[test]
+fn test_syn_ack_retransmission_limit() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let syn_ack_retries: usize = 3;
+    let tcp_config: TcpConfig = TcpConfig::default()
+        .set_syn_retries(0)
+        .set_syn_ack_retries(syn_ack_retries)
+        .set_data_retries(0);
+    let handshake_timeout: Duration = tcp_config.get_handshake_timeout();
+    let mut server: SharedEngine = test_helpers::new_bob2_with_tcp_config(now, tcp_config);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+
+    // Server: LISTEN.
+    let server_fd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(server_fd, listen_addr)?;
+    server.tcp_listen(server_fd, 1)?;
+    let accept_qt: QToken = server.tcp_accept(server_fd)?;
+    server.get_test_rig().poll_scheduler();
+
+    // Client: emit a SYN that reaches the server.
+    let client_fd: QDesc = client.tcp_socket()?;
+    let _connect_qt: QToken = client.tcp_connect(client_fd, listen_addr)?;
+    client.get_test_rig().poll_scheduler();
+    client.get_test_rig().poll_scheduler();
+    let syn: DemiBuffer = client.get_test_rig().pop_frame();
+    server.receive(syn)?;
+
+    // Drop the initial SYN+ACK and all of its retransmissions.
+    let mut num_syn_acks: usize = 0;
+    for _ in 0..(syn_ack_retries + 1) {
+        server.get_test_rig().poll_scheduler();
+        server.get_test_rig().poll_scheduler();
+        num_syn_acks += server.get_test_rig().pop_all_frames().len();
+        check_pending(&mut server, accept_qt)?;
+        now += handshake_timeout;
+        server.advance_clock(now);
+    }
+    server.get_test_rig().poll_scheduler();
+
+    crate::ensure_eq!(num_syn_acks, syn_ack_retries + 1);
+    crate::ensure_eq!(server.get_test_rig().pop_all_frames().len(), 0);
+    check_timed_out(&mut server, accept_qt)
+}
+
+/// Tests that an established connection is aborted after the configured number of data retransmissions, and that
+/// pending and subsequent operations fail.
+#[test]
+fn test_data_retransmission_limit() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let data_retries: usize = 3;
+    let tcp_config: TcpConfig = TcpConfig::default().set_data_retries(data_retries);
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2_with_tcp_config(now, tcp_config);
+
+    let ((_server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Leave a pop pending on the client, so we can check that it completes when the connection is aborted.
+    let pop_qt: QToken = client.tcp_pop(client_qd)?;
+    client.get_test_rig().poll_scheduler();
+
+    // Push some data and drop the segment and all of its retransmissions.
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::new(64))?;
+    let mut num_segments: usize = 0;
+    for _ in 0..(data_retries + 1) {
+        client.get_test_rig().poll_scheduler();
+        num_segments += client.get_test_rig().pop_all_frames().len();
+        check_pending(&mut client, pop_qt)?;
+        now += client.tcp_rto(client_qd)?;
+        client.advance_clock(now);
+    }
+    client.get_test_rig().poll_scheduler();
+
+    crate::ensure_eq!(num_segments, data_retries + 1);
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 0);
+    check_timed_out(&mut client, pop_qt)?;
+
+    // Further pushes on the connection should fail.
+    match client.tcp_push(client_qd, DemiBuffer::new(64)) {
+        Err(e) if e.errno == libc::ETIMEDOUT => Ok(()),
+        Err(e) => anyhow::bail!("push should have timed out: {:?}", e),
+        Ok(_) => anyhow::bail!("push should have failed"),
+    }
+}
+
+/// Tests that a wait on the virtual clock of the test runtime triggers a retransmission right away, by skipping ahead to
+/// the retransmission timeout instead of sleeping until it expires.
+#[test]
+fn test_retransmission_with_virtual_clock() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((_server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Push some data and drop the segment.
+    let pop_qt: QToken = client.tcp_pop(client_qd)?;
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::new(64))?;
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 1);
+
+    // Nothing completes the pop, so the wait times out once the retransmission timeout elapses on the virtual clock.
+    let rto: Duration = client.tcp_rto(client_qd)?;
+    let mut runtime: SharedDemiRuntime = client.get_test_rig().get_runtime();
+    let start: Instant = runtime.get_now();
+    match runtime.wait_any(&[pop_qt], Some(rto)) {
+        Err(e) if e.errno == libc::ETIMEDOUT => (),
+        Err(e) => anyhow::bail!("wait should have timed out: {:?}", e),
+        Ok(_) => anyhow::bail!("pop should not have completed"),
+    }
+    crate::ensure_eq!(runtime.get_now(), start + rto);
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 1);
+
+    Ok(())
+}
@@ -0,0 +1,189 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::tests::setup::{
+            connection_setup,
+            deliver_frames,
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        OperationResult,
+        QDesc,
+        QToken,
+        SharedDemiRuntime,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Size of the send buffer of the client.
+const SEND_BUFFER_SIZE: usize = 1000;
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Takes the result of the push `qt` of `client`, if it completed. Otherwise, the push is left waiting.
+fn take_push(client: &mut SharedEngine, qt: QToken) -> Result<Option<OperationResult>> {
+    let mut runtime: SharedDemiRuntime = client.get_test_rig().get_runtime();
+    if !runtime.from_task_id(qt)?.has_completed() {
+        return Ok(None);
+    }
+    Ok(runtime
+        .remove_coroutine_with_qtoken(qt)
+        .get_result()
+        .map(|(_, result)| result))
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that pushes wait for room once the send buffer is full, and go out in order once the data in flight is
+/// acknowledged.
+#[test]
+fn test_push_waits_for_send_buffer() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    client.tcp_set_send_buffer_size(client_qd, SEND_BUFFER_SIZE)?;
+    // The waiting pushes are smaller than the MSS, so keep Nagle's algorithm from holding the last one back.
+    client.tcp_set_nodelay(client_qd, true)?;
+
+    // The first push fills the send buffer up.
+    let first_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x11; SEND_BUFFER_SIZE])?)?;
+    client.get_test_rig().poll_scheduler();
+    match take_push(&mut client, first_qt)? {
+        Some(OperationResult::Push(len)) => crate::ensure_eq!(len, SEND_BUFFER_SIZE),
+        result => anyhow::bail!("first push should have completed: {:?}", result),
+    }
+
+    // Further data does not fit until the first push is acknowledged.
+    match client.tcp_try_push(client_qd, DemiBuffer::from_slice(&[0x22; 500])?) {
+        Err(e) if e.errno == libc::EAGAIN => {},
+        result => anyhow::bail!("try_push should have failed with EAGAIN: {:?}", result),
+    }
+    let second_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x33; 500])?)?;
+    let third_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x44; 500])?)?;
+    deliver_frames(&mut client, &mut server)?;
+    if let Some(result) = take_push(&mut client, second_qt)? {
+        anyhow::bail!("second push should still be waiting: {:?}", result);
+    }
+
+    // Let the delayed acknowledgement of the server go out, and deliver it.
+    now += Duration::from_millis(10);
+    server.advance_clock(now);
+    client.advance_clock(now);
+    deliver_frames(&mut server, &mut client)?;
+    for qt in [second_qt, third_qt] {
+        match take_push(&mut client, qt)? {
+            Some(OperationResult::Push(len)) => crate::ensure_eq!(len, 500),
+            result => anyhow::bail!("waiting push should have completed: {:?}", result),
+        }
+    }
+
+    // The server should get the data of the waiting pushes in the order they were issued.
+    deliver_frames(&mut client, &mut server)?;
+    let mut stream: Vec<u8> = Vec::new();
+    while let Some(OperationResult::Pop(_, buf)) = server.tcp_try_pop(server_qd)? {
+        stream.extend_from_slice(&buf[..]);
+    }
+    let expected: Vec<u8> = [[0x11; SEND_BUFFER_SIZE].as_slice(), &[0x33; 500], &[0x44; 500]].concat();
+    crate::ensure_eq!(stream, expected);
+
+    Ok(())
+}
+
+/// Tests that a push which is cancelled while it waits for room in the send buffer hands its turn over, so that the
+/// pushes issued after it still go out.
+#[test]
+fn test_cancelled_push_releases_send_turn() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    client.tcp_set_send_buffer_size(client_qd, SEND_BUFFER_SIZE)?;
+
+    // Fill the send buffer up, and queue two pushes behind it.
+    let first_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x11; SEND_BUFFER_SIZE])?)?;
+    client.get_test_rig().poll_scheduler();
+    match take_push(&mut client, first_qt)? {
+        Some(OperationResult::Push(len)) => crate::ensure_eq!(len, SEND_BUFFER_SIZE),
+        result => anyhow::bail!("first push should have completed: {:?}", result),
+    }
+    let second_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x33; 500])?)?;
+    let third_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x44; 500])?)?;
+    deliver_frames(&mut client, &mut server)?;
+
+    // Cancel the push that holds the turn.
+    let _ = client
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(second_qt);
+
+    // Once the first push is acknowledged, the third one should go out.
+    now += Duration::from_millis(10);
+    server.advance_clock(now);
+    client.advance_clock(now);
+    deliver_frames(&mut server, &mut client)?;
+    match take_push(&mut client, third_qt)? {
+        Some(OperationResult::Push(len)) => crate::ensure_eq!(len, 500),
+        result => anyhow::bail!("third push should have completed: {:?}", result),
+    }
+
+    deliver_frames(&mut client, &mut server)?;
+    let mut stream: Vec<u8> = Vec::new();
+    while let Some(OperationResult::Pop(_, buf)) = server.tcp_try_pop(server_qd)? {
+        stream.extend_from_slice(&buf[..]);
+    }
+    let expected: Vec<u8> = [[0x11; SEND_BUFFER_SIZE].as_slice(), &[0x44; 500]].concat();
+    crate::ensure_eq!(stream, expected);
+
+    Ok(())
+}
+
+/// Tests that the send buffer of a connection cannot be set to zero.
+#[test]
+fn test_zero_send_buffer_size() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let (_, client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    match client.tcp_set_send_buffer_size(client_qd, 0) {
+        Err(e) if e.errno == libc::EINVAL => Ok(()),
+        Err(e) => anyhow::bail!("setting the send buffer size should have failed with EINVAL: {:?}", e),
+        Ok(()) => anyhow::bail!("setting the send buffer size should have failed"),
+    }
+}
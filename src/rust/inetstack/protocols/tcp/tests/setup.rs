@@ -33,6 +33,7 @@ use crate::{
 };
 use ::anyhow::Result;
 use ::std::{
+    collections::VecDeque,
     net::{
         Ipv4Addr,
         SocketAddrV4,
@@ -288,3 +289,144 @@ pub fn connection_setup(
 
     Ok(((server_fd, addr), client_fd))
 }
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Delivers all frames sent by `sender` to `receiver`, and polls the latter.
+pub fn deliver_frames(sender: &mut SharedEngine, receiver: &mut SharedEngine) -> Result<()> {
+    sender.get_test_rig().poll_scheduler();
+    let frames: VecDeque<DemiBuffer> = sender.get_test_rig().pop_all_frames();
+    for frame in frames {
+        receiver.receive(frame)?;
+    }
+    receiver.get_test_rig().poll_scheduler();
+    Ok(())
+}
+
+/// Delivers the frames sent by `a` and by `b` to each other at the same time, and polls both of them.
+pub fn exchange_frames(a: &mut SharedEngine, b: &mut SharedEngine) -> Result<()> {
+    a.get_test_rig().poll_scheduler();
+    b.get_test_rig().poll_scheduler();
+    let a_frames: VecDeque<DemiBuffer> = a.get_test_rig().pop_all_frames();
+    let b_frames: VecDeque<DemiBuffer> = b.get_test_rig().pop_all_frames();
+    for frame in a_frames {
+        b.receive(frame)?;
+    }
+    for frame in b_frames {
+        a.receive(frame)?;
+    }
+    a.get_test_rig().poll_scheduler();
+    b.get_test_rig().poll_scheduler();
+    Ok(())
+}
+
+/// Delivers `frame` to `receiver`, and returns all frames that it sends in response.
+pub fn exchange_all(receiver: &mut SharedEngine, frame: DemiBuffer) -> Result<VecDeque<DemiBuffer>> {
+    receiver.receive(frame)?;
+    // Answers to handshake segments may be sent by coroutines that only run on the next poll.
+    receiver.get_test_rig().poll_scheduler();
+    receiver.get_test_rig().poll_scheduler();
+    Ok(receiver.get_test_rig().pop_all_frames())
+}
+
+/// Delivers `frame` to `receiver`, and returns the only frame that it sends in response.
+pub fn exchange(receiver: &mut SharedEngine, frame: DemiBuffer) -> Result<DemiBuffer> {
+    let mut frames: VecDeque<DemiBuffer> = exchange_all(receiver, frame)?;
+    crate::ensure_eq!(frames.len(), 1);
+    Ok(frames.pop_front().expect("there should be one frame"))
+}
+
+/// Parses the TCP header of `frame`.
+pub fn parse_tcp_header(frame: DemiBuffer) -> Result<TcpHeader> {
+    let (_, eth2_payload) = Ethernet2Header::parse(frame)?;
+    let (ipv4_header, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+    let (tcp_header, _): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_header, ipv4_payload, false)?;
+    Ok(tcp_header)
+}
+
+/// Connects a new socket of `client` to `remote`, and returns the socket, the connect operation and the SYN that it
+/// sends.
+pub fn send_syn(client: &mut SharedEngine, remote: SocketAddrV4) -> Result<(QDesc, QToken, DemiBuffer)> {
+    let client_fd: QDesc = client.tcp_socket()?;
+    let qt: QToken = client.tcp_connect(client_fd, remote)?;
+    client.get_test_rig().poll_scheduler();
+    client.get_test_rig().poll_scheduler();
+    Ok((client_fd, qt, client.get_test_rig().pop_frame()))
+}
+
+/// Sends a SYN from a new socket of `client` to `server`, and returns the answer of the server.
+pub fn answer_syn(
+    server: &mut SharedEngine,
+    client: &mut SharedEngine,
+    listen_addr: SocketAddrV4,
+) -> Result<DemiBuffer> {
+    let (_, _, syn): (QDesc, QToken, DemiBuffer) = send_syn(client, listen_addr)?;
+    exchange(server, syn)
+}
+
+/// Creates a server that listens on `listen_addr` with room for `backlog` pending connections, and returns it along
+/// with the listening socket.
+pub fn new_listener(now: Instant, listen_addr: SocketAddrV4, backlog: usize) -> Result<(SharedEngine, QDesc)> {
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let listen_qd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(listen_qd, listen_addr)?;
+    server.tcp_listen(listen_qd, backlog)?;
+    Ok((server, listen_qd))
+}
+
+/// Connects `client` to port 80 of `server`, and returns the accepted and the connected sockets.
+pub fn connect(now: &mut Instant, server: &mut SharedEngine, client: &mut SharedEngine) -> Result<(QDesc, QDesc)> {
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(now, server, client, listen_port, listen_addr)?;
+    Ok((server_qd, client_qd))
+}
+
+/// Checks whether the operation `qt` of `engine` has completed.
+pub fn has_completed(engine: &mut SharedEngine, qt: QToken) -> Result<bool> {
+    Ok(engine.get_test_rig().get_runtime().from_task_id(qt)?.has_completed())
+}
+
+/// Takes the data returned by the pop `qt` of `engine`, which should have completed.
+pub fn take_pop(engine: &mut SharedEngine, qt: QToken) -> Result<DemiBuffer> {
+    match engine
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Pop(_, buf))) => Ok(buf),
+        Some((_, result)) => anyhow::bail!("pop should have returned data: {:?}", result),
+        None => anyhow::bail!("pop should have completed"),
+    }
+}
+
+/// Checks that the close `qt` of `engine` has completed successfully.
+pub fn check_close(engine: &mut SharedEngine, qt: QToken) -> Result<()> {
+    match engine
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Close)) => Ok(()),
+        Some((_, result)) => anyhow::bail!("close should have succeeded: {:?}", result),
+        None => anyhow::bail!("close should have completed"),
+    }
+}
+
+/// Pops from `qd` on `engine` until `len` bytes have been received, and returns them.
+pub fn pop_all(engine: &mut SharedEngine, qd: QDesc, len: usize) -> Result<Vec<u8>> {
+    let mut data: Vec<u8> = Vec::new();
+    while data.len() < len {
+        let qt: QToken = engine.tcp_pop(qd)?;
+        engine.get_test_rig().poll_scheduler();
+        let buf: DemiBuffer = take_pop(engine, qt)?;
+        crate::ensure_neq!(buf.len(), 0);
+        data.extend_from_slice(&buf[..]);
+    }
+    Ok(data)
+}
@@ -663,7 +663,7 @@ impl Simulation {
                         eprintln!("pop completed as expected (qd={:?})", qd);
                         Ok(())
                     },
-                    crate::OperationResult::Push => {
+                    crate::OperationResult::Push(_) => {
                         eprintln!("push completed as expected (qd={:?})", qd);
                         Ok(())
                     },
@@ -761,6 +761,8 @@ impl Simulation {
             urgent_pointer: 0,
             num_options,
             option_list,
+            #[cfg(feature = "tcp-md5")]
+            md5_key: None,
         }
     }
 
@@ -813,7 +815,7 @@ impl Simulation {
     fn check_ipv4_header(&self, ipv4_header: &Ipv4Header) -> Result<()> {
         crate::ensure_eq!(ipv4_header.get_src_addr(), self.local_sockaddr.ip().to_owned());
         crate::ensure_eq!(ipv4_header.get_dest_addr(), self.remote_sockaddr.ip().to_owned());
-        crate::ensure_eq!(ipv4_header.get_protocol(), IpProtocol::TCP);
+        crate::ensure_eq!(ipv4_header.get_protocol()?, IpProtocol::TCP);
 
         Ok(())
     }
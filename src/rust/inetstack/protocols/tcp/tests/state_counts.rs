@@ -0,0 +1,152 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::{
+            tests::setup::deliver_frames,
+            TcpStateCounts,
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        OperationResult,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that the counts of both ends follow a connection through the handshake and the close protocol.
+#[test]
+fn test_state_counts() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let listen_qd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(listen_qd, listen_addr)?;
+    server.tcp_listen(listen_qd, 1)?;
+    let listening: TcpStateCounts = TcpStateCounts {
+        listen: 1,
+        ..Default::default()
+    };
+    crate::ensure_eq!(server.tcp_state_counts(), listening);
+    crate::ensure_eq!(client.tcp_state_counts(), TcpStateCounts::default());
+
+    // SYN: Client -> Server.
+    let client_qd: QDesc = client.tcp_socket()?;
+    let _connect_qt: QToken = client.tcp_connect(client_qd, listen_addr)?;
+    deliver_frames(&mut client, &mut server)?;
+    crate::ensure_eq!(
+        client.tcp_state_counts(),
+        TcpStateCounts {
+            syn_sent: 1,
+            ..Default::default()
+        }
+    );
+    crate::ensure_eq!(
+        server.tcp_state_counts(),
+        TcpStateCounts {
+            syn_received: 1,
+            ..listening
+        }
+    );
+
+    // SYN+ACK: Server -> Client, and ACK: Client -> Server. The connection of the server counts as established before
+    // it is accepted.
+    deliver_frames(&mut server, &mut client)?;
+    deliver_frames(&mut client, &mut server)?;
+    let established: TcpStateCounts = TcpStateCounts {
+        established: 1,
+        ..Default::default()
+    };
+    crate::ensure_eq!(client.tcp_state_counts(), established);
+    crate::ensure_eq!(
+        server.tcp_state_counts(),
+        TcpStateCounts {
+            established: 1,
+            ..listening
+        }
+    );
+    let server_qd: QDesc = match server.tcp_try_accept(listen_qd)? {
+        Some(OperationResult::Accept((qd, _))) => qd,
+        result => anyhow::bail!("try_accept should have taken the connection: {:?}", result),
+    };
+    crate::ensure_eq!(
+        server.tcp_state_counts(),
+        TcpStateCounts {
+            established: 1,
+            ..listening
+        }
+    );
+
+    // FIN: Client -> Server.
+    let _client_close_qt: QToken = client.tcp_async_close(client_qd)?;
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(
+        client.tcp_state_counts(),
+        TcpStateCounts {
+            fin_wait1: 1,
+            ..Default::default()
+        }
+    );
+    deliver_frames(&mut client, &mut server)?;
+    crate::ensure_eq!(
+        server.tcp_state_counts(),
+        TcpStateCounts {
+            close_wait: 1,
+            ..listening
+        }
+    );
+
+    // ACK: Server -> Client.
+    deliver_frames(&mut server, &mut client)?;
+    crate::ensure_eq!(
+        client.tcp_state_counts(),
+        TcpStateCounts {
+            fin_wait2: 1,
+            ..Default::default()
+        }
+    );
+
+    // FIN: Server -> Client. The client goes through TIME-WAIT, while the server waits for the ACK of its FIN.
+    let _server_close_qt: QToken = server.tcp_async_close(server_qd)?;
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(
+        server.tcp_state_counts(),
+        TcpStateCounts {
+            last_ack: 1,
+            ..listening
+        }
+    );
+    deliver_frames(&mut server, &mut client)?;
+    crate::ensure_eq!(
+        client.tcp_state_counts(),
+        TcpStateCounts {
+            time_wait: 1,
+            ..Default::default()
+        }
+    );
+
+    // ACK: Client -> Server.
+    deliver_frames(&mut client, &mut server)?;
+    crate::ensure_eq!(server.tcp_state_counts(), listening);
+
+    Ok(())
+}
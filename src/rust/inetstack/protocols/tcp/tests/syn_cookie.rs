@@ -0,0 +1,200 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::{
+            segment::{
+                TcpHeader,
+                TcpOptions2,
+            },
+            tests::setup::{
+                exchange_all,
+                parse_tcp_header,
+                send_syn,
+            },
+            SynCookieStats,
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        network::config::TcpConfig,
+        OperationResult,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    collections::VecDeque,
+    net::SocketAddrV4,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Builds a TCP configuration with SYN cookies enabled.
+fn new_syn_cookie_config() -> TcpConfig {
+    TcpConfig::default().set_syn_cookies(true)
+}
+
+/// Creates a socket on `server` that listens on `listen_addr` with a backlog of one connection.
+fn listen(server: &mut SharedEngine, listen_addr: SocketAddrV4) -> Result<QDesc> {
+    let server_fd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(server_fd, listen_addr)?;
+    server.tcp_listen(server_fd, 1)?;
+    Ok(server_fd)
+}
+
+/// Fills the backlog of `server` with a connection from `client` that never completes.
+fn fill_backlog(server: &mut SharedEngine, client: &mut SharedEngine, listen_addr: SocketAddrV4) -> Result<()> {
+    let (_, _, syn): (QDesc, QToken, DemiBuffer) = send_syn(client, listen_addr)?;
+    let syn_ack: TcpHeader = parse_tcp_header(exchange_all(server, syn)?.pop_front().expect("missing SYN+ACK"))?;
+    crate::ensure_eq!(syn_ack.syn && syn_ack.ack, true);
+    Ok(())
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that a connection completes through a SYN cookie when the backlog is full.
+#[test]
+fn test_syn_cookie_connection_with_full_backlog() -> Result<()> {
+    let now: Instant = Instant::now();
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let mut server: SharedEngine = test_helpers::new_bob2_with_tcp_config(now, new_syn_cookie_config());
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let server_fd: QDesc = listen(&mut server, listen_addr)?;
+    let accept_qt: QToken = server.tcp_accept(server_fd)?;
+    server.get_test_rig().poll_scheduler();
+    fill_backlog(&mut server, &mut client, listen_addr)?;
+
+    // The server should answer with a SYN cookie, and without offering window scaling.
+    let (_, connect_qt, syn): (QDesc, QToken, DemiBuffer) = send_syn(&mut client, listen_addr)?;
+    let mut frames: VecDeque<DemiBuffer> = exchange_all(&mut server, syn)?;
+    crate::ensure_eq!(frames.len(), 1);
+    let syn_ack: DemiBuffer = frames.pop_front().expect("missing SYN+ACK");
+    let syn_ack_hdr: TcpHeader = parse_tcp_header(syn_ack.clone())?;
+    crate::ensure_eq!(syn_ack_hdr.syn && syn_ack_hdr.ack, true);
+    crate::ensure_eq!(
+        syn_ack_hdr
+            .iter_options()
+            .any(|option| matches!(option, TcpOptions2::WindowScale(_))),
+        false
+    );
+    crate::ensure_eq!(
+        server.tcp_syn_cookie_stats(server_fd)?,
+        SynCookieStats {
+            issued: 1,
+            validated: 0,
+            rejected: 0,
+        }
+    );
+
+    // The ACK of the client should establish the connection.
+    let mut frames: VecDeque<DemiBuffer> = exchange_all(&mut client, syn_ack)?;
+    crate::ensure_eq!(frames.len(), 1);
+    match client
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(connect_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Connect)) => {},
+        _ => anyhow::bail!("connect should have completed"),
+    };
+    let ack: DemiBuffer = frames.pop_front().expect("missing ACK");
+    crate::ensure_eq!(exchange_all(&mut server, ack)?.len(), 0);
+    match server
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(accept_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Accept(_))) => {},
+        _ => anyhow::bail!("accept should have completed"),
+    };
+    crate::ensure_eq!(
+        server.tcp_syn_cookie_stats(server_fd)?,
+        SynCookieStats {
+            issued: 1,
+            validated: 1,
+            rejected: 0,
+        }
+    );
+
+    Ok(())
+}
+
+/// Tests that answering SYNs with SYN cookies does not keep any state, so nothing is ever retransmitted for them.
+#[test]
+fn test_syn_cookie_keeps_no_state() -> Result<()> {
+    let num_syns: usize = 16;
+    let mut now: Instant = Instant::now();
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let tcp_config: TcpConfig = new_syn_cookie_config();
+    let handshake_timeout: Duration = tcp_config.get_handshake_timeout();
+    let mut server: SharedEngine = test_helpers::new_bob2_with_tcp_config(now, tcp_config);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let server_fd: QDesc = listen(&mut server, listen_addr)?;
+    fill_backlog(&mut server, &mut client, listen_addr)?;
+
+    for _ in 0..num_syns {
+        let (_, _, syn): (QDesc, QToken, DemiBuffer) = send_syn(&mut client, listen_addr)?;
+        crate::ensure_eq!(exchange_all(&mut server, syn)?.len(), 1);
+    }
+    crate::ensure_eq!(server.tcp_syn_cookie_stats(server_fd)?.issued, num_syns);
+
+    // Only the connection that fills the backlog should retransmit its SYN+ACK.
+    now += handshake_timeout;
+    server.advance_clock(now);
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(server.get_test_rig().pop_all_frames().len(), 1);
+
+    Ok(())
+}
+
+/// Tests that an ACK that does not carry a valid SYN cookie is rejected and reset.
+#[test]
+fn test_syn_cookie_rejects_invalid_ack() -> Result<()> {
+    let now: Instant = Instant::now();
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let mut server: SharedEngine = test_helpers::new_bob2_with_tcp_config(now, new_syn_cookie_config());
+    let mut other_server: SharedEngine = test_helpers::new_bob2_with_tcp_config(now, new_syn_cookie_config());
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    listen(&mut server, listen_addr)?;
+    let other_server_fd: QDesc = listen(&mut other_server, listen_addr)?;
+    fill_backlog(&mut server, &mut client, listen_addr)?;
+
+    // Acknowledge a SYN cookie from a server that uses another secret.
+    let (_, _, syn): (QDesc, QToken, DemiBuffer) = send_syn(&mut client, listen_addr)?;
+    let syn_ack: DemiBuffer = exchange_all(&mut server, syn)?.pop_front().expect("missing SYN+ACK");
+    let ack: DemiBuffer = exchange_all(&mut client, syn_ack)?.pop_front().expect("missing ACK");
+    let mut frames: VecDeque<DemiBuffer> = exchange_all(&mut other_server, ack)?;
+    crate::ensure_eq!(frames.len(), 1);
+    crate::ensure_eq!(parse_tcp_header(frames.pop_front().expect("missing RST"))?.rst, true);
+    crate::ensure_eq!(
+        other_server.tcp_syn_cookie_stats(other_server_fd)?,
+        SynCookieStats {
+            issued: 0,
+            validated: 0,
+            rejected: 1,
+        }
+    );
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::{
+            tests::setup::{
+                connection_setup,
+                deliver_frames,
+            },
+            TcpInfo,
+            TcpState,
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that the summary of an active connection tracks the data that is in flight, acknowledged and retransmitted.
+#[test]
+fn test_tcp_info_active_connection() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    let info: TcpInfo = client.tcp_info(client_qd)?;
+    crate::ensure_eq!(info.state, TcpState::Established);
+    crate::ensure_eq!(info.unacked, 0);
+    crate::ensure_eq!(info.bytes_acked, 0);
+    crate::ensure_eq!(info.retransmits, 0);
+    crate::ensure_eq!(info.rto > Duration::ZERO, true);
+    crate::ensure_eq!(info.cwnd > 0, true);
+    crate::ensure_eq!(info.snd_wnd > 0, true);
+    crate::ensure_eq!(info.rcv_wnd > 0, true);
+
+    // The pushed data should be in flight until the server acknowledges it.
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x5a; 1000])?)?;
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(client.tcp_info(client_qd)?.unacked, 1);
+    deliver_frames(&mut client, &mut server)?;
+
+    // Let the delayed acknowledgement of the server go out, and deliver it.
+    now += Duration::from_millis(10);
+    server.advance_clock(now);
+    client.advance_clock(now);
+    deliver_frames(&mut server, &mut client)?;
+
+    let info: TcpInfo = client.tcp_info(client_qd)?;
+    crate::ensure_eq!(info.unacked, 0);
+    crate::ensure_eq!(info.bytes_acked, 1000);
+    crate::ensure_eq!(info.retransmits, 0);
+    crate::ensure_eq!(info.rtt > Duration::ZERO, true);
+
+    // Data that the server never gets should be retransmitted once the retransmission timeout expires.
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x5a; 1000])?)?;
+    client.get_test_rig().poll_scheduler();
+    client.get_test_rig().pop_all_frames();
+    now += info.rto + Duration::from_millis(1);
+    client.advance_clock(now);
+    client.get_test_rig().poll_scheduler();
+
+    let info: TcpInfo = client.tcp_info(client_qd)?;
+    crate::ensure_eq!(info.unacked, 1);
+    crate::ensure_eq!(info.bytes_acked, 1000);
+    crate::ensure_eq!(info.retransmits, 1);
+
+    Ok(())
+}
+
+/// Tests that the summary of a socket that is not connected cannot be queried.
+#[test]
+fn test_tcp_info_not_connected() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let client_qd: QDesc = client.tcp_socket()?;
+
+    match client.tcp_info(client_qd) {
+        Err(e) if e.errno == libc::ENOTCONN => Ok(()),
+        Err(e) => anyhow::bail!("tcp_info should have failed with ENOTCONN: {:?}", e),
+        Ok(_) => anyhow::bail!("tcp_info should have failed"),
+    }
+}
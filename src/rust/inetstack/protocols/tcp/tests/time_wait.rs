@@ -0,0 +1,186 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::tests::setup::{
+            check_close,
+            connection_setup,
+            deliver_frames,
+            exchange_frames,
+            has_completed,
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        network::config::TcpConfig,
+        OperationResult,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Sets up a connection, and closes both of its ends in the same poll, so that their FINs cross. Returns both
+/// engines, along with the queue tokens of the closes of the server and of the client.
+fn close_simultaneously(now: &mut Instant) -> Result<(SharedEngine, SharedEngine, QToken, QToken)> {
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(*now);
+    let mut client: SharedEngine = test_helpers::new_alice2(*now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Both ends send a FIN, and each one receives the FIN of the other before the ACK of its own.
+    let server_qt: QToken = server.tcp_async_close(server_qd)?;
+    let client_qt: QToken = client.tcp_async_close(client_qd)?;
+    exchange_frames(&mut server, &mut client)?;
+
+    Ok((server, client, server_qt, client_qt))
+}
+
+/// Advances the clocks of `a` and `b` to `now`, and polls both of them.
+fn advance_clocks(now: Instant, a: &mut SharedEngine, b: &mut SharedEngine) {
+    a.advance_clock(now);
+    b.advance_clock(now);
+    a.get_test_rig().poll_scheduler();
+    b.get_test_rig().poll_scheduler();
+}
+
+/// Closes a connection from the client side first, so that the client goes through TIME-WAIT, and returns how long it
+/// takes for the close of the client to complete once the close protocol is done.
+fn measure_time_wait(msl: Duration) -> Result<Duration> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2_with_tcp_config(now, TcpConfig::default().set_msl(msl));
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Send FIN: Client -> Server, and ACK it.
+    let client_qt: QToken = client.tcp_async_close(client_qd)?;
+    deliver_frames(&mut client, &mut server)?;
+    deliver_frames(&mut server, &mut client)?;
+
+    // Send FIN: Server -> Client, and ACK it. The client is now in TIME-WAIT, while the server is done.
+    let server_qt: QToken = server.tcp_async_close(server_qd)?;
+    deliver_frames(&mut server, &mut client)?;
+    deliver_frames(&mut client, &mut server)?;
+    crate::ensure_eq!(has_completed(&mut server, server_qt)?, true);
+
+    // Wait for TIME-WAIT to end.
+    let start: Instant = now;
+    let step: Duration = Duration::from_millis(100);
+    while !has_completed(&mut client, client_qt)? {
+        crate::ensure_eq!(now - start <= 2 * TcpConfig::default().get_msl(), true);
+        now += step;
+        client.advance_clock(now);
+        client.get_test_rig().poll_scheduler();
+    }
+    match client
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(client_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Close)) => Ok(now - start),
+        Some((_, result)) => anyhow::bail!("close should have succeeded: {:?}", result),
+        None => anyhow::bail!("close should have completed"),
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that a connection stays in TIME-WAIT for twice the maximum segment lifetime.
+#[test]
+fn test_time_wait_follows_msl() -> Result<()> {
+    crate::ensure_eq!(measure_time_wait(Duration::from_secs(1))?, Duration::from_secs(2));
+    crate::ensure_eq!(measure_time_wait(Duration::from_secs(5))?, Duration::from_secs(10));
+
+    Ok(())
+}
+
+/// Tests that when both ends close at the same time, they both go through CLOSING and TIME-WAIT, and their closes
+/// complete once TIME-WAIT ends.
+#[test]
+fn test_time_wait_after_simultaneous_close() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let (mut server, mut client, server_qt, client_qt): (SharedEngine, SharedEngine, QToken, QToken) =
+        close_simultaneously(&mut now)?;
+
+    // Both ends ACK the FIN of the other, which moves them from CLOSING to TIME-WAIT.
+    exchange_frames(&mut server, &mut client)?;
+    crate::ensure_eq!(has_completed(&mut server, server_qt)?, false);
+    crate::ensure_eq!(has_completed(&mut client, client_qt)?, false);
+
+    // Both ends linger in TIME-WAIT for twice the maximum segment lifetime.
+    let time_wait_timeout: Duration = TcpConfig::default().get_time_wait_timeout();
+    advance_clocks(
+        now + time_wait_timeout - Duration::from_millis(1),
+        &mut server,
+        &mut client,
+    );
+    crate::ensure_eq!(has_completed(&mut server, server_qt)?, false);
+    crate::ensure_eq!(has_completed(&mut client, client_qt)?, false);
+    advance_clocks(now + time_wait_timeout, &mut server, &mut client);
+    check_close(&mut server, server_qt)?;
+    check_close(&mut client, client_qt)?;
+
+    Ok(())
+}
+
+/// Tests that when both ends close at the same time, neither one leaves CLOSING before its FIN is acknowledged, and
+/// that a FIN that is retransmitted in CLOSING is acknowledged again.
+#[test]
+fn test_simultaneous_close_waits_for_ack_of_fin() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let (mut server, mut client, server_qt, client_qt): (SharedEngine, SharedEngine, QToken, QToken) =
+        close_simultaneously(&mut now)?;
+    let start: Instant = now;
+    let time_wait_timeout: Duration = TcpConfig::default().get_time_wait_timeout();
+
+    // Lose the ACKs of both FINs, along with the first retransmissions of the FINs.
+    server.get_test_rig().pop_all_frames();
+    client.get_test_rig().pop_all_frames();
+    now += TcpConfig::default().get_msl();
+    advance_clocks(now, &mut server, &mut client);
+    server.get_test_rig().pop_all_frames();
+    client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(has_completed(&mut server, server_qt)?, false);
+    crate::ensure_eq!(has_completed(&mut client, client_qt)?, false);
+
+    // The FINs are retransmitted again and acknowledged, and only then do both ends go through TIME-WAIT.
+    let step: Duration = Duration::from_millis(100);
+    while !has_completed(&mut server, server_qt)? || !has_completed(&mut client, client_qt)? {
+        crate::ensure_eq!(now - start < 2 * time_wait_timeout, true);
+        exchange_frames(&mut server, &mut client)?;
+        now += step;
+        advance_clocks(now, &mut server, &mut client);
+    }
+    crate::ensure_eq!(now - start > time_wait_timeout, true);
+    check_close(&mut server, server_qt)?;
+    check_close(&mut client, client_qt)?;
+
+    Ok(())
+}
@@ -0,0 +1,153 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::{
+            tests::setup::{
+                connection_setup,
+                deliver_frames,
+            },
+            trace::{
+                TcpSegmentDirection,
+                TcpSegmentRecord,
+            },
+            SeqNumber,
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    cell::RefCell,
+    net::SocketAddrV4,
+    rc::Rc,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Installs a trace hook on `engine` that appends every record to the returned list.
+fn install_trace_hook(engine: &mut SharedEngine) -> Rc<RefCell<Vec<TcpSegmentRecord>>> {
+    let records: Rc<RefCell<Vec<TcpSegmentRecord>>> = Rc::new(RefCell::new(Vec::new()));
+    let records_clone: Rc<RefCell<Vec<TcpSegmentRecord>>> = records.clone();
+    engine.tcp_set_trace_hook(Some(Box::new(move |record: &TcpSegmentRecord| {
+        records_clone.borrow_mut().push(*record)
+    })));
+    records
+}
+
+/// Summarizes a record as its direction and SYN, ACK and FIN flags.
+fn summarize(record: &TcpSegmentRecord) -> (TcpSegmentDirection, bool, bool, bool) {
+    (record.direction, record.syn, record.ack, record.fin)
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that the trace hook observes every segment of a connection setup and teardown, in order.
+#[test]
+fn test_trace_hook_connect_disconnect() -> Result<()> {
+    use TcpSegmentDirection::{
+        Received,
+        Sent,
+    };
+
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let server_records: Rc<RefCell<Vec<TcpSegmentRecord>>> = install_trace_hook(&mut server);
+    let client_records: Rc<RefCell<Vec<TcpSegmentRecord>>> = install_trace_hook(&mut client);
+
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Close the connection without advancing the clock, so that no segment gets retransmitted.
+    let _client_qt: QToken = client.tcp_async_close(client_qd)?;
+    deliver_frames(&mut client, &mut server)?;
+    deliver_frames(&mut server, &mut client)?;
+    let _server_qt: QToken = server.tcp_async_close(server_qd)?;
+    deliver_frames(&mut server, &mut client)?;
+    deliver_frames(&mut client, &mut server)?;
+
+    let client_records: Vec<TcpSegmentRecord> = client_records.borrow().clone();
+    let client_summary: Vec<(TcpSegmentDirection, bool, bool, bool)> = client_records.iter().map(summarize).collect();
+    crate::ensure_eq!(
+        client_summary,
+        vec![
+            (Sent, true, false, false),
+            (Received, true, true, false),
+            (Sent, false, true, false),
+            (Sent, false, true, true),
+            (Received, false, true, false),
+            (Received, false, true, true),
+            (Sent, false, true, false),
+        ]
+    );
+
+    let server_records: Vec<TcpSegmentRecord> = server_records.borrow().clone();
+    let server_summary: Vec<(TcpSegmentDirection, bool, bool, bool)> = server_records.iter().map(summarize).collect();
+    crate::ensure_eq!(
+        server_summary,
+        vec![
+            (Received, true, false, false),
+            (Sent, true, true, false),
+            (Received, false, true, false),
+            (Received, false, true, true),
+            (Sent, false, true, false),
+            (Sent, false, true, true),
+            (Received, false, true, false),
+        ]
+    );
+
+    // Records should carry the segment's addressing and sequencing information.
+    for record in client_records.iter() {
+        let (local_port, remote_port): (u16, u16) = match record.direction {
+            Sent => (record.src_port, record.dst_port),
+            Received => (record.dst_port, record.src_port),
+        };
+        crate::ensure_neq!(local_port, listen_port);
+        crate::ensure_eq!(remote_port, listen_port);
+        crate::ensure_eq!(record.len, 0);
+    }
+    let syn_ack: &TcpSegmentRecord = &client_records[1];
+    crate::ensure_eq!(syn_ack.ack_num, client_records[0].seq_num + SeqNumber::from(1));
+    let server_fin: &TcpSegmentRecord = &client_records[5];
+    crate::ensure_eq!(client_records[6].ack_num, server_fin.seq_num + SeqNumber::from(1));
+
+    Ok(())
+}
+
+/// Tests that removing the trace hook stops tracing.
+#[test]
+fn test_trace_hook_removed() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let records: Rc<RefCell<Vec<TcpSegmentRecord>>> = install_trace_hook(&mut client);
+    client.tcp_set_trace_hook(None);
+
+    let client_fd: QDesc = client.tcp_socket()?;
+    let _connect_qt: QToken = client.tcp_connect(client_fd, SocketAddrV4::new(test_helpers::BOB_IPV4, 80))?;
+    client.get_test_rig().poll_scheduler();
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 1);
+    crate::ensure_eq!(records.borrow().len(), 0);
+
+    Ok(())
+}
@@ -0,0 +1,177 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::tcp::tests::setup::{
+            connection_setup,
+            deliver_frames,
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        OperationResult,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::Instant,
+};
+use ::test::{
+    black_box,
+    Bencher,
+};
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Sets up a connection whose client side is closed, so that every pop on the server side completes right away with
+/// the end of the stream. Returns the server engine along with the descriptor of its side of the connection.
+fn setup_remotely_closed_connection() -> Result<(SharedEngine, QDesc)> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    let _close_qt: QToken = client.tcp_async_close(client_qd)?;
+    deliver_frames(&mut client, &mut server)?;
+    server.get_test_rig().pop_all_frames();
+    Ok((server, server_qd))
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that a try pop returns nothing until data arrives, and then returns the data right away.
+#[test]
+fn test_try_pop() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    match server.tcp_try_pop(server_qd)? {
+        None => (),
+        Some(result) => anyhow::bail!("try pop should not have returned a result: {:?}", result),
+    }
+
+    // A push completes right away, and its data can be popped right away once it arrives.
+    match client.tcp_try_push(client_qd, DemiBuffer::from_slice(&[0x5a; 100])?)? {
+        Some(OperationResult::Push(len)) => crate::ensure_eq!(len, 100),
+        result => anyhow::bail!("try push should have succeeded: {:?}", result),
+    }
+    deliver_frames(&mut client, &mut server)?;
+    match server.tcp_try_pop(server_qd)? {
+        Some(OperationResult::Pop(_, buf)) => crate::ensure_eq!(&buf[..], &[0x5a; 100][..]),
+        result => anyhow::bail!("try pop should have returned data: {:?}", result),
+    }
+    match server.tcp_try_pop(server_qd)? {
+        None => (),
+        Some(result) => anyhow::bail!("try pop should not have returned a result: {:?}", result),
+    }
+
+    Ok(())
+}
+
+/// Tests that a try accept returns nothing until a connection is ready, and then returns it right away.
+#[test]
+fn test_try_accept() -> Result<()> {
+    let now: Instant = Instant::now();
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let listen_qd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(listen_qd, listen_addr)?;
+    server.tcp_listen(listen_qd, 1)?;
+
+    match server.tcp_try_accept(listen_qd)? {
+        None => (),
+        Some(result) => anyhow::bail!("try accept should not have returned a result: {:?}", result),
+    }
+
+    let client_qd: QDesc = client.tcp_socket()?;
+    let _connect_qt: QToken = client.tcp_connect(client_qd, listen_addr)?;
+    for _ in 0..3 {
+        deliver_frames(&mut client, &mut server)?;
+        deliver_frames(&mut server, &mut client)?;
+    }
+    let server_qd: QDesc = match server.tcp_try_accept(listen_qd)? {
+        Some(OperationResult::Accept((qd, remote))) => {
+            crate::ensure_eq!(*remote.ip(), test_helpers::ALICE_IPV4);
+            qd
+        },
+        result => anyhow::bail!("try accept should have succeeded: {:?}", result),
+    };
+
+    // The accepted connection is usable.
+    match server.tcp_try_push(server_qd, DemiBuffer::from_slice(&[0xa5; 10])?)? {
+        Some(OperationResult::Push(len)) => crate::ensure_eq!(len, 10),
+        result => anyhow::bail!("try push should have succeeded: {:?}", result),
+    }
+
+    Ok(())
+}
+
+/// Tests that a try pop on a socket that is not connected fails right away.
+#[test]
+fn test_try_pop_not_connected() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let qd: QDesc = server.tcp_socket()?;
+    match server.tcp_try_pop(qd) {
+        Err(e) if e.errno == libc::ENOTCONN => Ok(()),
+        result => anyhow::bail!("try pop should have failed: {:?}", result),
+    }
+}
+
+//======================================================================================================================
+// Benchmarks
+//======================================================================================================================
+
+/// Measures a pop that is already ready, when it goes through the scheduler.
+#[bench]
+fn bench_pop_ready(b: &mut Bencher) {
+    let (mut server, server_qd): (SharedEngine, QDesc) =
+        setup_remotely_closed_connection().expect("connection should be set up");
+
+    b.iter(|| {
+        let qt: QToken = server.tcp_pop(server_qd).expect("pop should be issued");
+        server.get_test_rig().poll_scheduler();
+        let result: Option<(QDesc, OperationResult)> = server
+            .get_test_rig()
+            .get_runtime()
+            .remove_coroutine_with_qtoken(qt)
+            .get_result();
+        black_box(result);
+    });
+}
+
+/// Measures a pop that is already ready, when it takes the fast path that skips the scheduler.
+#[bench]
+fn bench_try_pop_ready(b: &mut Bencher) {
+    let (mut server, server_qd): (SharedEngine, QDesc) =
+        setup_remotely_closed_connection().expect("connection should be set up");
+
+    b.iter(|| {
+        let result: Option<OperationResult> = server.tcp_try_pop(server_qd).expect("pop should succeed");
+        black_box(result);
+    });
+}
@@ -0,0 +1,171 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::{
+            ethernet2::Ethernet2Header,
+            ipv4::Ipv4Header,
+            tcp::{
+                segment::TcpHeader,
+                tests::setup::connection_setup,
+            },
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    collections::VecDeque,
+    net::SocketAddrV4,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Port of the connection that carries the bulk flow.
+const BULK_PORT: u16 = 80;
+
+/// Port of the connection that carries the urgent flow.
+const URGENT_PORT: u16 = 81;
+
+/// Number of segments sent on the bulk connection.
+const NUM_BULK_SEGMENTS: usize = 4;
+
+/// Number of segments sent on the urgent connection.
+const NUM_URGENT_SEGMENTS: usize = 2;
+
+/// Size of each segment.
+const SEGMENT_SIZE: usize = 100;
+
+/// Maximum number of times that the scheduler is polled while waiting for segments.
+const MAX_POLLS: usize = 10;
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Opens a bulk and an urgent connection from the client, gives the urgent one `urgent_priority`, and has the client
+/// send on the bulk connection before the urgent one. Returns the destination ports of the data segments, in the order
+/// in which they reached the wire.
+fn do_test_tx_priority(urgent_priority: u8) -> Result<Vec<u16>> {
+    let mut now: Instant = Instant::now();
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let bulk_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, BULK_PORT);
+    let urgent_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, URGENT_PORT);
+    let (_, bulk_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, BULK_PORT, bulk_addr)?;
+    let (_, urgent_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, URGENT_PORT, urgent_addr)?;
+    client.tcp_set_tx_priority(urgent_qd, urgent_priority)?;
+    // Each push goes out in its own segment, so that the order of the segments on the wire can be checked.
+    client.tcp_set_nodelay(bulk_qd, true)?;
+    client.tcp_set_nodelay(urgent_qd, true)?;
+
+    for _ in 0..NUM_BULK_SEGMENTS {
+        let _push_qt: QToken = client.tcp_push(bulk_qd, DemiBuffer::from_slice(&[0xaa; SEGMENT_SIZE])?)?;
+    }
+    for _ in 0..NUM_URGENT_SEGMENTS {
+        let _push_qt: QToken = client.tcp_push(urgent_qd, DemiBuffer::from_slice(&[0x55; SEGMENT_SIZE])?)?;
+    }
+
+    let mut ports: Vec<u16> = Vec::new();
+    for _ in 0..MAX_POLLS {
+        if ports.len() == NUM_BULK_SEGMENTS + NUM_URGENT_SEGMENTS {
+            break;
+        }
+        client.get_test_rig().poll_scheduler();
+        for frame in client.get_test_rig().pop_all_frames() {
+            let (_, eth2_payload) = Ethernet2Header::parse(frame)?;
+            let (ipv4_hdr, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+            let (tcp_hdr, tcp_payload): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_hdr, ipv4_payload, false)?;
+            if !tcp_payload.is_empty() {
+                ports.push(tcp_hdr.dst_port);
+            }
+        }
+    }
+    crate::ensure_eq!(ports.len(), NUM_BULK_SEGMENTS + NUM_URGENT_SEGMENTS);
+
+    Ok(ports)
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that connections with the default priority send their segments in the order in which they were produced.
+#[test]
+fn test_tx_priority_default() -> Result<()> {
+    let ports: Vec<u16> = do_test_tx_priority(0)?;
+    crate::ensure_eq!(ports[..NUM_BULK_SEGMENTS].iter().all(|port| *port == BULK_PORT), true);
+    crate::ensure_eq!(ports[NUM_BULK_SEGMENTS..].iter().all(|port| *port == URGENT_PORT), true);
+
+    Ok(())
+}
+
+/// Tests that the segments of a high priority connection are sent ahead of those of a bulk flow that was queued first.
+#[test]
+fn test_tx_priority_urgent_ahead_of_bulk() -> Result<()> {
+    let ports: Vec<u16> = do_test_tx_priority(7)?;
+    crate::ensure_eq!(
+        ports[..NUM_URGENT_SEGMENTS].iter().all(|port| *port == URGENT_PORT),
+        true
+    );
+    crate::ensure_eq!(ports[NUM_URGENT_SEGMENTS..].iter().all(|port| *port == BULK_PORT), true);
+
+    Ok(())
+}
+
+/// Tests that once the segments of a high priority connection are flushed and that connection is closed, the segments
+/// of a connection with the default priority are transmitted inline again instead of waiting for a flush.
+#[test]
+fn test_tx_priority_default_inline_after_close() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let bulk_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, BULK_PORT);
+    let urgent_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, URGENT_PORT);
+    let (_, bulk_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, BULK_PORT, bulk_addr)?;
+    let (_, urgent_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, URGENT_PORT, urgent_addr)?;
+    client.tcp_set_tx_priority(urgent_qd, 7)?;
+    client.tcp_set_nodelay(bulk_qd, true)?;
+    client.tcp_set_nodelay(urgent_qd, true)?;
+
+    // Segments of the urgent connection are held until they are flushed.
+    let _push_qt: QToken = client.tcp_push(urgent_qd, DemiBuffer::from_slice(&[0x55; SEGMENT_SIZE])?)?;
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().is_empty(), true);
+    let _close_qt: QToken = client.tcp_async_close(urgent_qd)?;
+    for _ in 0..MAX_POLLS {
+        client.get_test_rig().poll_scheduler();
+    }
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().is_empty(), false);
+
+    // The bulk connection has the default priority, so its segment reaches the wire within the push.
+    let _push_qt: QToken = client.tcp_push(bulk_qd, DemiBuffer::from_slice(&[0xaa; SEGMENT_SIZE])?)?;
+    let mut frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    let (_, eth2_payload) = Ethernet2Header::parse(frames.pop_front().unwrap())?;
+    let (ipv4_hdr, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+    let (tcp_hdr, tcp_payload): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_hdr, ipv4_payload, false)?;
+    crate::ensure_eq!(tcp_hdr.dst_port, BULK_PORT);
+    crate::ensure_eq!(tcp_payload.len(), SEGMENT_SIZE);
+
+    Ok(())
+}
@@ -0,0 +1,132 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::{
+            ethernet2::Ethernet2Header,
+            ipv4::Ipv4Header,
+            tcp::{
+                segment::{
+                    TcpHeader,
+                    TcpSegment,
+                },
+                tests::setup::{
+                    connection_setup,
+                    pop_all,
+                },
+            },
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        network::NetworkRuntime,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Data sent by the client. Its urgent byte is the one at [URGENT_OFFSET].
+const DATA: &[u8] = b"hello!world";
+
+/// Offset of the urgent byte in [DATA].
+const URGENT_OFFSET: usize = 5;
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Rewrites the data segment `frame` sent by `engine` so that the byte at [URGENT_OFFSET] is urgent.
+fn mark_urgent(engine: &mut SharedEngine, frame: DemiBuffer) -> Result<DemiBuffer> {
+    let (ethernet2_hdr, eth2_payload) = Ethernet2Header::parse(frame)?;
+    let (ipv4_hdr, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+    let (mut tcp_hdr, data): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_hdr, ipv4_payload, false)?;
+    tcp_hdr.urg = true;
+    // The urgent pointer points at the byte that follows the urgent one.
+    tcp_hdr.urgent_pointer = (URGENT_OFFSET + 1) as u16;
+    engine.get_test_rig().transmit(Box::new(TcpSegment {
+        ethernet2_hdr,
+        ipv4_hdr,
+        tcp_hdr,
+        data: Some(data),
+        tx_checksum_offload: false,
+    }));
+    Ok(engine.get_test_rig().pop_frame())
+}
+
+/// Has the client send [DATA] with an urgent byte to a server that delivers urgent data inline if `oob_inline` is set,
+/// and returns the server engine along with its connection.
+fn do_test_urgent(oob_inline: bool) -> Result<(SharedEngine, QDesc)> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    if oob_inline {
+        server.tcp_set_oob_inline(server_qd, true)?;
+    }
+
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(DATA)?)?;
+    client.get_test_rig().poll_scheduler();
+    let frame: DemiBuffer = client.get_test_rig().pop_frame();
+    let frame: DemiBuffer = mark_urgent(&mut client, frame)?;
+    server.receive(frame)?;
+
+    Ok((server, server_qd))
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that the urgent byte is removed from the ordinary stream and delivered out of band by default.
+#[test]
+fn test_urgent_out_of_band() -> Result<()> {
+    let (mut server, server_qd) = do_test_urgent(false)?;
+
+    let mut expected: Vec<u8> = DATA.to_vec();
+    let urgent: u8 = expected.remove(URGENT_OFFSET);
+    crate::ensure_eq!(pop_all(&mut server, server_qd, expected.len())?, expected);
+    crate::ensure_eq!(server.tcp_recv_oob(server_qd)?, urgent);
+
+    // The urgent byte can only be read once.
+    match server.tcp_recv_oob(server_qd) {
+        Err(e) if e.errno == libc::EINVAL => (),
+        result => anyhow::bail!("reading urgent data again should have failed: {:?}", result),
+    }
+
+    Ok(())
+}
+
+/// Tests that the urgent byte is left in the ordinary stream when urgent data is delivered inline.
+#[test]
+fn test_urgent_inline() -> Result<()> {
+    let (mut server, server_qd) = do_test_urgent(true)?;
+
+    crate::ensure_eq!(pop_all(&mut server, server_qd, DATA.len())?, DATA.to_vec());
+    match server.tcp_recv_oob(server_qd) {
+        Err(e) if e.errno == libc::EINVAL => (),
+        result => anyhow::bail!("reading urgent data should have failed: {:?}", result),
+    }
+
+    Ok(())
+}
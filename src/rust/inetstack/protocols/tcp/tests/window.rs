@@ -0,0 +1,165 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        protocols::{
+            ethernet2::Ethernet2Header,
+            ipv4::Ipv4Header,
+            tcp::{
+                segment::TcpHeader,
+                tests::setup::{
+                    connection_setup,
+                    deliver_frames,
+                },
+            },
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
+    },
+    runtime::{
+        memory::DemiBuffer,
+        network::config::TcpConfig,
+        QDesc,
+        QToken,
+    },
+};
+use ::anyhow::Result;
+use ::std::{
+    collections::VecDeque,
+    net::SocketAddrV4,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Receive window forced on the server.
+const SMALL_WINDOW: u32 = 100;
+
+/// Receive window advertised in the SYN of the client.
+const CLIENT_INITIAL_WINDOW: u16 = 1024;
+
+/// Receive window advertised in the SYN+ACK of the server.
+const SERVER_INITIAL_WINDOW: u16 = 2048;
+
+//======================================================================================================================
+// Helper Functions
+//======================================================================================================================
+
+/// Pops the only frame sent by `engine`, and parses its TCP header.
+fn pop_tcp_header(engine: &mut SharedEngine) -> Result<(TcpHeader, DemiBuffer)> {
+    engine.get_test_rig().poll_scheduler();
+    let mut frames: VecDeque<DemiBuffer> = engine.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    let frame: DemiBuffer = frames.pop_front().expect("there should be one frame");
+    let (_, eth2_payload) = Ethernet2Header::parse(frame.clone())?;
+    let (ipv4_header, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+    let (tcp_header, _): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_header, ipv4_payload, false)?;
+    Ok((tcp_header, frame))
+}
+
+/// Returns the total number of payload bytes in the TCP segments sent by `engine`.
+fn sent_payload_bytes(engine: &mut SharedEngine) -> Result<usize> {
+    let mut bytes: usize = 0;
+    for frame in engine.get_test_rig().pop_all_frames() {
+        let (_, eth2_payload) = Ethernet2Header::parse(frame)?;
+        let (ipv4_header, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+        let (_, tcp_payload): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_header, ipv4_payload, false)?;
+        bytes += tcp_payload.len();
+    }
+    Ok(bytes)
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+/// Tests that forcing a small receive window on one side is advertised to the other one right away, and that the
+/// latter does not send more than the window allows.
+#[test]
+fn test_sender_respects_forced_receive_window() -> Result<()> {
+    let mut now: Instant = Instant::now();
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mut server: SharedEngine = test_helpers::new_bob2(now);
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Force a small window on the server, and have it advertised to the client.
+    server.tcp_set_receive_window(server_qd, SMALL_WINDOW)?;
+    deliver_frames(&mut server, &mut client)?;
+    crate::ensure_eq!(client.tcp_send_window(client_qd)?, SMALL_WINDOW);
+
+    // Push more data than the window allows: only the window should reach the wire.
+    let _push_qt: QToken = client.tcp_push(client_qd, DemiBuffer::from_slice(&[0x5a; 500])?)?;
+    client.get_test_rig().poll_scheduler();
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(sent_payload_bytes(&mut client)?, SMALL_WINDOW as usize);
+
+    Ok(())
+}
+
+/// Tests that the SYN and the SYN+ACK advertise the configured initial receive window rather than the full receive
+/// buffer, and that the sender starts with that window.
+#[test]
+fn test_handshake_advertises_initial_receive_window() -> Result<()> {
+    let now: Instant = Instant::now();
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let mut server: SharedEngine = test_helpers::new_bob2_with_tcp_config(
+        now,
+        TcpConfig::default().set_initial_receive_window(SERVER_INITIAL_WINDOW),
+    );
+    let mut client: SharedEngine = test_helpers::new_alice2_with_tcp_config(
+        now,
+        TcpConfig::default().set_initial_receive_window(CLIENT_INITIAL_WINDOW),
+    );
+    let server_qd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(server_qd, listen_addr)?;
+    server.tcp_listen(server_qd, 1)?;
+    let _accept_qt: QToken = server.tcp_accept(server_qd)?;
+    server.get_test_rig().poll_scheduler();
+
+    let client_qd: QDesc = client.tcp_socket()?;
+    let _connect_qt: QToken = client.tcp_connect(client_qd, listen_addr)?;
+    client.get_test_rig().poll_scheduler();
+    let (syn_hdr, syn): (TcpHeader, DemiBuffer) = pop_tcp_header(&mut client)?;
+    crate::ensure_eq!(syn_hdr.syn, true);
+    crate::ensure_eq!(syn_hdr.window_size, CLIENT_INITIAL_WINDOW);
+
+    server.receive(syn)?;
+    server.get_test_rig().poll_scheduler();
+    let (syn_ack_hdr, syn_ack): (TcpHeader, DemiBuffer) = pop_tcp_header(&mut server)?;
+    crate::ensure_eq!(syn_ack_hdr.syn && syn_ack_hdr.ack, true);
+    crate::ensure_eq!(syn_ack_hdr.window_size, SERVER_INITIAL_WINDOW);
+
+    client.receive(syn_ack)?;
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(client.tcp_send_window(client_qd)?, SERVER_INITIAL_WINDOW as u32);
+
+    Ok(())
+}
+
+/// Tests that the send window cannot be inspected on a connection that is not established.
+#[test]
+fn test_send_window_not_connected() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut client: SharedEngine = test_helpers::new_alice2(now);
+    let client_qd: QDesc = client.tcp_socket()?;
+
+    match client.tcp_send_window(client_qd) {
+        Err(e) if e.errno == libc::ENOTCONN => (),
+        Err(e) => anyhow::bail!("send_window should have failed with ENOTCONN: {:?}", e),
+        Ok(_) => anyhow::bail!("send_window should have failed"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,121 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::protocols::tcp::{
+        segment::TcpHeader,
+        SeqNumber,
+    },
+    runtime::SharedObject,
+};
+use ::std::ops::{
+    Deref,
+    DerefMut,
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Direction of a traced TCP segment, relative to the local host.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TcpSegmentDirection {
+    /// Segment handed to the transport for transmission.
+    Sent,
+    /// Segment successfully parsed on reception.
+    Received,
+}
+
+/// Compact description of a TCP segment, as reported to trace hooks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TcpSegmentRecord {
+    pub direction: TcpSegmentDirection,
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub seq_num: SeqNumber,
+    pub ack_num: SeqNumber,
+    pub syn: bool,
+    pub ack: bool,
+    pub fin: bool,
+    pub rst: bool,
+    pub psh: bool,
+    /// Window size, as carried in the header (i.e. not scaled).
+    pub window_size: u16,
+    /// Length of the segment's payload.
+    pub len: usize,
+}
+
+/// Callback that is invoked for every traced TCP segment.
+pub type TcpTraceHook = Box<dyn Fn(&TcpSegmentRecord)>;
+
+/// Trace hook shared by all TCP sockets of a peer.
+pub struct TcpTracer {
+    hook: Option<TcpTraceHook>,
+}
+
+#[derive(Clone)]
+pub struct SharedTcpTracer(SharedObject<TcpTracer>);
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl TcpSegmentRecord {
+    fn new(direction: TcpSegmentDirection, header: &TcpHeader, len: usize) -> Self {
+        Self {
+            direction,
+            src_port: header.src_port,
+            dst_port: header.dst_port,
+            seq_num: header.seq_num,
+            ack_num: header.ack_num,
+            syn: header.syn,
+            ack: header.ack,
+            fin: header.fin,
+            rst: header.rst,
+            psh: header.psh,
+            window_size: header.window_size,
+            len,
+        }
+    }
+}
+
+impl SharedTcpTracer {
+    pub fn new() -> Self {
+        Self(SharedObject::<TcpTracer>::new(TcpTracer { hook: None }))
+    }
+
+    /// Installs `hook`, replacing any previously installed one. Passing `None` disables tracing.
+    pub fn set_hook(&mut self, hook: Option<TcpTraceHook>) {
+        self.hook = hook;
+    }
+
+    /// Reports a segment to the installed hook. The record is only built if a hook is installed.
+    #[inline]
+    pub fn trace(&self, direction: TcpSegmentDirection, header: &TcpHeader, len: usize) {
+        if let Some(hook) = self.hook.as_ref() {
+            hook(&TcpSegmentRecord::new(direction, header, len));
+        }
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl Deref for SharedTcpTracer {
+    type Target = TcpTracer;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.deref()
+    }
+}
+
+impl DerefMut for SharedTcpTracer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0.deref_mut()
+    }
+}
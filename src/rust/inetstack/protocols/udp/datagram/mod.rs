@@ -17,12 +17,16 @@ use crate::{
         network::PacketBuf,
     },
 };
+use ::std::net::Ipv4Addr;
 
 //==============================================================================
 // Exports
 //==============================================================================
 
-pub use header::UdpHeader;
+pub use header::{
+    UdpHeader,
+    UDP_HEADER_SIZE,
+};
 
 //==============================================================================
 // Structures
@@ -73,6 +77,11 @@ impl UdpDatagram {
 
 /// Packet Buffer Trait Implementation for UDP Datagrams
 impl PacketBuf for UdpDatagram {
+    /// Returns the IPv4 destination address of the target UDP datagram.
+    fn ipv4_dst_addr(&self) -> Option<Ipv4Addr> {
+        Some(self.ipv4_hdr.get_dest_addr())
+    }
+
     /// Computes the header size of the target UDP datagram.
     fn header_size(&self) -> usize {
         self.ethernet2_hdr.compute_size() + self.ipv4_hdr.compute_size() + self.udp_hdr.size()
@@ -125,21 +134,41 @@ mod test {
     use self::header::UDP_HEADER_SIZE;
     use super::*;
     use crate::{
-        inetstack::protocols::{
-            ethernet2::{
-                EtherType2,
-                ETHERNET2_HEADER_SIZE,
+        inetstack::{
+            protocols::{
+                ethernet2::{
+                    padding::PaddingTransport,
+                    EtherType2,
+                    ETHERNET2_HEADER_SIZE,
+                    MIN_FRAME_SIZE,
+                },
+                ip::IpProtocol,
+                ipv4::IPV4_HEADER_MIN_SIZE,
+            },
+            test_helpers::{
+                self,
+                SharedTestRuntime,
             },
-            ip::IpProtocol,
-            ipv4::IPV4_HEADER_MIN_SIZE,
         },
         runtime::{
             memory::DemiBuffer,
-            network::types::MacAddress,
+            network::{
+                config::{
+                    ArpConfig,
+                    TcpConfig,
+                    UdpConfig,
+                },
+                types::MacAddress,
+                NetworkRuntime,
+            },
+            SharedBox,
         },
     };
     use ::anyhow::Result;
-    use ::std::net::Ipv4Addr;
+    use ::std::{
+        net::Ipv4Addr,
+        time::Instant,
+    };
 
     #[test]
     fn test_udp_datagram_header_serialization() -> Result<()> {
@@ -193,4 +222,47 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_udp_datagram_padding() -> Result<()> {
+        // Total header size.
+        const HEADER_SIZE: usize = ETHERNET2_HEADER_SIZE + (IPV4_HEADER_MIN_SIZE as usize) + UDP_HEADER_SIZE;
+
+        let mut test_rig: SharedTestRuntime = SharedTestRuntime::new(
+            Instant::now(),
+            ArpConfig::default(),
+            UdpConfig::default(),
+            TcpConfig::default(),
+            test_helpers::ALICE_MAC,
+            test_helpers::ALICE_IPV4,
+        );
+        let mut transport: PaddingTransport =
+            PaddingTransport::new(SharedBox::<dyn NetworkRuntime>::new(Box::new(test_rig.clone())));
+
+        // Build a datagram with a small payload.
+        let ethernet2_hdr: Ethernet2Header =
+            Ethernet2Header::new(test_helpers::BOB_MAC, test_helpers::ALICE_MAC, EtherType2::Ipv4);
+        let ipv4_hdr: Ipv4Header = Ipv4Header::new(test_helpers::ALICE_IPV4, test_helpers::BOB_IPV4, IpProtocol::UDP);
+        let udp_hdr: UdpHeader = UdpHeader::new(0x32, 0x45);
+        let bytes: [u8; 4] = [0xa, 0xb, 0xc, 0xd];
+        let data: DemiBuffer = DemiBuffer::from_slice(&bytes).expect("bytes should be shorter than u16::MAX");
+        transport.transmit(Box::new(UdpDatagram::new(
+            ethernet2_hdr,
+            ipv4_hdr,
+            udp_hdr,
+            data,
+            false,
+        )));
+
+        // The frame should be zero-padded, and the padding should not show up in the payload.
+        let frame: DemiBuffer = test_rig.pop_frame();
+        crate::ensure_eq!(frame.len(), MIN_FRAME_SIZE);
+        crate::ensure_eq!(frame[(HEADER_SIZE + bytes.len())..].iter().all(|b| *b == 0), true);
+        let (_, eth2_payload) = Ethernet2Header::parse(frame)?;
+        let (ipv4_hdr, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+        let (_, udp_payload) = UdpHeader::parse(&ipv4_hdr, ipv4_payload, false)?;
+        crate::ensure_eq!(&udp_payload[..], &bytes[..]);
+
+        Ok(())
+    }
 }
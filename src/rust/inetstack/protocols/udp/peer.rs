@@ -6,19 +6,32 @@
 //==============================================================================
 
 use super::{
-    datagram::UdpHeader,
+    datagram::{
+        UdpHeader,
+        UDP_HEADER_SIZE,
+    },
     queue::SharedUdpQueue,
 };
 use crate::{
     inetstack::protocols::{
         arp::SharedArpPeer,
-        ipv4::Ipv4Header,
+        ipv4::{
+            Ipv4Header,
+            IPV4_HEADER_MIN_SIZE,
+        },
     },
     runtime::{
         fail::Fail,
         memory::DemiBuffer,
         network::{
-            types::MacAddress,
+            config::UdpRecvQueueConfig,
+            types::{
+                BufferUsage,
+                ErrorCounters,
+                MacAddress,
+                PacketInfo,
+                Readiness,
+            },
             NetworkRuntime,
         },
         queue::{
@@ -53,6 +66,14 @@ use ::std::{
 #[cfg(feature = "profiler")]
 use crate::timer;
 
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Index of the loopback interface, as reported through IP_PKTINFO for datagrams that are sent to the loopback network.
+/// Hosts number it first.
+const LOOPBACK_INTERFACE_INDEX: u32 = 1;
+
 //======================================================================================================================
 // Structures
 //======================================================================================================================
@@ -72,6 +93,12 @@ pub struct UdpPeer {
     local_ipv4_addr: Ipv4Addr,
     /// Offload checksum to hardware?
     checksum_offload: bool,
+    /// Largest IPv4 packet that may be sent, in bytes.
+    mtu: usize,
+    /// Bounds on the receive queue of new sockets.
+    recv_queue_config: UdpRecvQueueConfig,
+    /// Number of sockets that were bound so far, which ranks them by when they were bound.
+    num_binds: u64,
 }
 
 #[derive(Clone)]
@@ -90,6 +117,8 @@ impl SharedUdpPeer {
         local_link_addr: MacAddress,
         local_ipv4_addr: Ipv4Addr,
         offload_checksum: bool,
+        mtu: usize,
+        recv_queue_config: UdpRecvQueueConfig,
         arp: SharedArpPeer,
     ) -> Result<Self, Fail> {
         Ok(Self(SharedObject::<UdpPeer>::new(UdpPeer {
@@ -99,9 +128,31 @@ impl SharedUdpPeer {
             local_link_addr,
             local_ipv4_addr,
             checksum_offload: offload_checksum,
+            mtu,
+            recv_queue_config,
+            num_binds: 0,
         })))
     }
 
+    /// Changes the addresses that new sockets of the target peer send from, and that binds and source addresses are
+    /// checked against.
+    pub fn set_local_addrs(&mut self, local_link_addr: MacAddress, local_ipv4_addr: Ipv4Addr) {
+        self.local_link_addr = local_link_addr;
+        self.local_ipv4_addr = local_ipv4_addr;
+    }
+
+    /// Gets the largest IPv4 packet that the target peer may send, in bytes.
+    pub fn get_mtu(&self) -> usize {
+        self.mtu
+    }
+
+    /// Gets the largest payload that a datagram of the socket `qd` can carry. Since fragmentation is not supported,
+    /// larger datagrams cannot be sent at all.
+    pub fn max_payload_size(&self, qd: QDesc) -> Result<usize, Fail> {
+        self.get_shared_queue(&qd)?;
+        Ok(self.mtu.saturating_sub(IPV4_HEADER_MIN_SIZE as usize + UDP_HEADER_SIZE))
+    }
+
     /// Opens a UDP socket.
     pub fn socket(&mut self) -> Result<QDesc, Fail> {
         let new_queue: SharedUdpQueue = SharedUdpQueue::new(
@@ -110,8 +161,9 @@ impl SharedUdpPeer {
             self.transport.clone(),
             self.arp.clone(),
             self.checksum_offload,
+            &self.recv_queue_config,
         )?;
-        let new_qd: QDesc = self.runtime.alloc_queue::<SharedUdpQueue>(new_queue);
+        let new_qd: QDesc = self.runtime.alloc_queue::<SharedUdpQueue>(new_queue)?;
         trace!("socket(): qd={:?}", new_qd);
         Ok(new_qd)
     }
@@ -136,8 +188,16 @@ impl SharedUdpPeer {
             return Err(Fail::new(libc::EADDRINUSE, &cause));
         }
 
-        // Check if this is an ephemeral port or a wildcard one.
-        if SharedDemiRuntime::is_private_ephemeral_port(addr.port()) {
+        // Sockets may only share an address if all of them set SO_REUSEADDR.
+        let sharing: Vec<SharedUdpQueue> = self.get_queues_from_addr(&addr);
+        if !sharing.is_empty() && !(queue.get_reuse_addr() && sharing.iter().all(|other| other.get_reuse_addr())) {
+            let cause: String = format!("address is already bound to another socket (addr={:?})", addr);
+            error!("bind(): {}", cause);
+            return Err(Fail::new(libc::EADDRINUSE, &cause));
+        }
+
+        // Check if this is an ephemeral port or a wildcard one. A shared port was reserved by the first socket already.
+        if sharing.is_empty() && self.runtime.is_private_ephemeral_port(addr.port()) {
             // Allocate ephemeral port from the pool, to leave  ephemeral port allocator in a consistent state.
             self.runtime.reserve_ephemeral_port(addr.port())?
         } else if addr.port() == 0 {
@@ -147,26 +207,152 @@ impl SharedUdpPeer {
             addr.set_port(new_port);
         }
 
-        queue.bind(addr)?;
+        // Claim the address on the transport, so that the datagrams destined to it are delivered to this stack.
+        self.transport.register_flow(addr)?;
+        self.num_binds += 1;
+        if let Err(e) = queue.bind(addr, self.num_binds) {
+            self.unregister_flow(addr);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Connects a UDP socket to a remote endpoint address. Sockets that are not bound yet are bound to an ephemeral
+    /// port on the wildcard address.
+    pub fn connect(&mut self, qd: QDesc, remote: SocketAddrV4) -> Result<(), Fail> {
+        trace!("connect(): qd={:?} remote={:?}", qd, remote);
+        let mut queue: SharedUdpQueue = self.get_shared_queue(&qd)?;
+        if remote.ip().is_unspecified() || remote.port() == 0 {
+            let cause: String = format!("invalid remote address (remote={:?})", remote);
+            error!("connect(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        if !queue.is_bound() {
+            self.bind(qd, SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0))?;
+        }
+        queue.connect(remote);
+        Ok(())
+    }
+
+    /// Allows or disallows a UDP socket to send to and receive from the broadcast address (i.e. sets SO_BROADCAST).
+    pub fn set_broadcast(&mut self, qd: QDesc, broadcast: bool) -> Result<(), Fail> {
+        trace!("set_broadcast(): qd={:?} broadcast={:?}", qd, broadcast);
+        let mut queue: SharedUdpQueue = self.get_shared_queue(&qd)?;
+        queue.set_broadcast(broadcast);
+        Ok(())
+    }
+
+    /// Checks whether a UDP socket may send to and receive from the broadcast address.
+    pub fn get_broadcast(&self, qd: QDesc) -> Result<bool, Fail> {
+        let queue: SharedUdpQueue = self.get_shared_queue(&qd)?;
+        Ok(queue.get_broadcast())
+    }
+
+    /// Sets whether pops on a UDP socket are handed the destination address and receiving interface of each datagram
+    /// (i.e. sets IP_PKTINFO).
+    pub fn set_pktinfo(&mut self, qd: QDesc, pktinfo: bool) -> Result<(), Fail> {
+        trace!("set_pktinfo(): qd={:?} pktinfo={:?}", qd, pktinfo);
+        let mut queue: SharedUdpQueue = self.get_shared_queue(&qd)?;
+        queue.set_pktinfo(pktinfo);
+        Ok(())
+    }
+
+    /// Checks whether pops on a UDP socket are handed the ancillary data of datagrams.
+    pub fn get_pktinfo(&self, qd: QDesc) -> Result<bool, Fail> {
+        let queue: SharedUdpQueue = self.get_shared_queue(&qd)?;
+        Ok(queue.get_pktinfo())
+    }
+
+    /// Sets whether a UDP socket resolves its destinations on the local link, instead of through the gateway or route
+    /// that covers them (i.e. sets SO_DONTROUTE). Datagrams to a destination that is not on the link then fail with
+    /// EHOSTUNREACH.
+    pub fn set_dontroute(&mut self, qd: QDesc, dontroute: bool) -> Result<(), Fail> {
+        trace!("set_dontroute(): qd={:?} dontroute={:?}", qd, dontroute);
+        let mut queue: SharedUdpQueue = self.get_shared_queue(&qd)?;
+        queue.set_dontroute(dontroute);
+        Ok(())
+    }
+
+    /// Checks whether a UDP socket bypasses routing when resolving its destinations.
+    pub fn get_dontroute(&self, qd: QDesc) -> Result<bool, Fail> {
+        let queue: SharedUdpQueue = self.get_shared_queue(&qd)?;
+        Ok(queue.get_dontroute())
+    }
+
+    /// Sets whether a UDP socket may share its local address with other sockets that set it too (i.e. sets
+    /// SO_REUSEADDR). It must be set before binding. This lets a socket that only sends use the same port as the socket
+    /// that receives on it: datagrams sent to a shared address are always delivered to the socket that was bound to it
+    /// first, and to the next one once that socket is closed, so the receiving socket should be bound first.
+    pub fn set_reuse_addr(&mut self, qd: QDesc, reuse_addr: bool) -> Result<(), Fail> {
+        trace!("set_reuse_addr(): qd={:?} reuse_addr={:?}", qd, reuse_addr);
+        let mut queue: SharedUdpQueue = self.get_shared_queue(&qd)?;
+        queue.set_reuse_addr(reuse_addr);
         Ok(())
     }
 
+    /// Checks whether a UDP socket may share its local address with other sockets.
+    pub fn get_reuse_addr(&self, qd: QDesc) -> Result<bool, Fail> {
+        let queue: SharedUdpQueue = self.get_shared_queue(&qd)?;
+        Ok(queue.get_reuse_addr())
+    }
+
+    /// Sets the largest number of bytes that a UDP socket holds in its receive queue (i.e. sets SO_RCVBUF).
+    pub fn set_recv_buffer_size(&mut self, qd: QDesc, size: usize) -> Result<(), Fail> {
+        trace!("set_recv_buffer_size(): qd={:?} size={:?}", qd, size);
+        let mut queue: SharedUdpQueue = self.get_shared_queue(&qd)?;
+        queue.set_recv_buffer_size(size)
+    }
+
+    /// Gets the largest number of bytes that a UDP socket holds in its receive queue.
+    pub fn get_recv_buffer_size(&self, qd: QDesc) -> Result<usize, Fail> {
+        let queue: SharedUdpQueue = self.get_shared_queue(&qd)?;
+        Ok(queue.get_recv_buffer_size())
+    }
+
+    /// Returns the number of datagrams that a UDP socket dropped because its receive queue was full.
+    pub fn recv_dropped(&self, qd: QDesc) -> Result<usize, Fail> {
+        let queue: SharedUdpQueue = self.get_shared_queue(&qd)?;
+        Ok(queue.get_recv_dropped())
+    }
+
+    /// Returns the local address to which a UDP socket is bound, if any.
+    pub fn local(&self, qd: QDesc) -> Result<Option<SocketAddrV4>, Fail> {
+        Ok(self.get_shared_queue(&qd)?.local())
+    }
+
+    /// Reports how many bytes are currently held in the buffers of a UDP socket.
+    pub fn buffer_usage(&self, qd: QDesc) -> Result<BufferUsage, Fail> {
+        let queue: SharedUdpQueue = self.get_shared_queue(&qd)?;
+        Ok(queue.buffer_usage())
+    }
+
+    /// Reads the error counters of a UDP socket, and clears them afterwards if `reset` is set.
+    pub fn error_counters(&self, qd: QDesc, reset: bool) -> Result<ErrorCounters, Fail> {
+        let mut queue: SharedUdpQueue = self.get_shared_queue(&qd)?;
+        Ok(queue.error_counters(reset))
+    }
+
+    /// Reports whether operations on a UDP socket would complete right away.
+    pub fn readiness(&self, qd: QDesc) -> Result<Readiness, Fail> {
+        let queue: SharedUdpQueue = self.get_shared_queue(&qd)?;
+        Ok(queue.readiness())
+    }
+
     /// Closes a UDP socket.
     pub fn close(&mut self, qd: QDesc) -> Result<(), Fail> {
         trace!("close(): qd={:?}", qd);
-        self.runtime.free_queue::<SharedUdpQueue>(&qd)?;
-        Ok(())
+        self.free_queue(qd)
     }
 
     /// Closes a UDP socket asynchronously.
     pub fn async_close(&mut self, qd: QDesc) -> Result<QToken, Fail> {
-        let mut runtime: SharedDemiRuntime = self.runtime.clone();
+        let mut peer: Self = self.clone();
         let task_name: String = format!("inetstack::udp::close for qd={:?}", qd);
         let coroutine_factory = |_yielder| -> Pin<Box<Operation>> {
             Box::pin(async move {
                 // Expect is safe here because we looked up the queue to schedule this coroutine and no
                 // other close coroutine should be able to run due to state machine checks.
-                runtime.free_queue::<SharedUdpQueue>(&qd).expect("queue should exist");
+                peer.free_queue(qd).expect("queue should exist");
                 (qd, OperationResult::Close)
             })
         };
@@ -184,6 +370,42 @@ impl SharedUdpPeer {
     /// Pushes data to a remote UDP peer.
     pub fn pushto(&mut self, qd: QDesc, buf: DemiBuffer, remote: SocketAddrV4) -> Result<Pin<Box<Operation>>, Fail> {
         trace!("pushto(): qd={:?} remote={:?} bytes={:?}", qd, remote, buf.len());
+        self.do_pushto(qd, buf, None, remote)
+    }
+
+    /// Pushes data to a remote UDP peer from the local address `src`, regardless of the address that the socket is
+    /// bound to. This lets sockets bound to the wildcard address reply from the address that a request was sent to.
+    /// Addresses of the loopback network are local too.
+    pub fn pushto_from(
+        &mut self,
+        qd: QDesc,
+        buf: DemiBuffer,
+        src: Ipv4Addr,
+        remote: SocketAddrV4,
+    ) -> Result<Pin<Box<Operation>>, Fail> {
+        trace!(
+            "pushto_from(): qd={:?} src={:?} remote={:?} bytes={:?}",
+            qd,
+            src,
+            remote,
+            buf.len()
+        );
+        if src != self.local_ipv4_addr && !src.is_loopback() {
+            let cause: String = format!("source is not a local address (src={:?})", src);
+            error!("pushto_from(): {}", &cause);
+            return Err(Fail::new(libc::EADDRNOTAVAIL, &cause));
+        }
+        self.do_pushto(qd, buf, Some(src), remote)
+    }
+
+    /// Builds the coroutine that sends `buf` to `remote`, from `src` if given.
+    fn do_pushto(
+        &mut self,
+        qd: QDesc,
+        buf: DemiBuffer,
+        src: Option<Ipv4Addr>,
+        remote: SocketAddrV4,
+    ) -> Result<Pin<Box<Operation>>, Fail> {
         let mut queue: SharedUdpQueue = self.get_shared_queue(&qd)?;
         // TODO: Allocate ephemeral port if not bound.
         // FIXME: https://github.com/microsoft/demikernel/issues/973
@@ -192,15 +414,44 @@ impl SharedUdpPeer {
             error!("pushto(): {}", &cause);
             return Err(Fail::new(libc::ENOTSUP, &cause));
         }
+        if remote.ip().is_broadcast() && !queue.get_broadcast() {
+            let cause: String = format!("cannot send to broadcast address unless SO_BROADCAST is set");
+            error!("pushto(): {}", &cause);
+            return Err(Fail::new(libc::EACCES, &cause));
+        }
+        // Fragmentation is not supported, so datagrams that do not fit in a single packet can never be sent.
+        let packet_size: usize = IPV4_HEADER_MIN_SIZE as usize + UDP_HEADER_SIZE + buf.len();
+        if packet_size > self.mtu {
+            let cause: String = format!(
+                "datagram is larger than the MTU (packet_size={:?}, mtu={:?})",
+                packet_size, self.mtu
+            );
+            error!("pushto(): {}", &cause);
+            return Err(Fail::new(libc::EMSGSIZE, &cause));
+        }
         let yielder: Yielder = Yielder::new();
+        let len: usize = buf.len();
         Ok(Box::pin(async move {
-            match queue.pushto(remote, buf, yielder).await {
-                Ok(()) => (qd, OperationResult::Push),
+            match queue.pushto(src, remote, buf, yielder).await {
+                Ok(()) => (qd, OperationResult::Push(len)),
                 Err(e) => (qd, OperationResult::Failed(e)),
             }
         }))
     }
 
+    /// Pushes data to the remote UDP peer that a socket is connected to.
+    pub fn push(&mut self, qd: QDesc, buf: DemiBuffer) -> Result<Pin<Box<Operation>>, Fail> {
+        let remote: SocketAddrV4 = match self.get_shared_queue(&qd)?.remote() {
+            Some(remote) => remote,
+            None => {
+                let cause: String = format!("socket is not connected (qd={:?})", qd);
+                error!("push(): {}", &cause);
+                return Err(Fail::new(libc::EDESTADDRREQ, &cause));
+            },
+        };
+        self.pushto(qd, buf, remote)
+    }
+
     /// Pops data from a socket.
     pub fn pop(&mut self, qd: QDesc, size: Option<usize>) -> Result<Pin<Box<Operation>>, Fail> {
         let yielder: Yielder = Yielder::new();
@@ -208,7 +459,8 @@ impl SharedUdpPeer {
 
         Ok(Box::pin(async move {
             match queue.pop(size, yielder).await {
-                Ok((addr, buf)) => (qd, OperationResult::Pop(Some(addr), buf)),
+                Ok((addr, info, buf)) if queue.get_pktinfo() => (qd, OperationResult::PopWithInfo(addr, info, buf)),
+                Ok((addr, _, buf)) => (qd, OperationResult::Pop(Some(addr), buf)),
                 Err(e) => (qd, OperationResult::Failed(e)),
             }
         }))
@@ -218,12 +470,20 @@ impl SharedUdpPeer {
     pub fn receive(&mut self, ipv4_hdr: Ipv4Header, buf: DemiBuffer) {
         #[cfg(feature = "profiler")]
         timer!("udp::receive");
-        // Parse datagram.
+        // Parse datagram. Malformed datagrams are counted against the socket bound to their destination port, if the
+        // port can be read at all.
+        let dest_port: Option<u16> = buf.get(2..4).map(|port| u16::from_be_bytes([port[0], port[1]]));
         let (hdr, data): (UdpHeader, DemiBuffer) = match UdpHeader::parse(&ipv4_hdr, buf, self.checksum_offload) {
             Ok(result) => result,
             Err(e) => {
                 let cause: String = format!("dropping packet: unable to parse UDP header");
                 warn!("{}: {:?}", cause, e);
+                if let Some(dest_port) = dest_port {
+                    let local: SocketAddrV4 = SocketAddrV4::new(ipv4_hdr.get_dest_addr(), dest_port);
+                    if let Some(mut queue) = self.get_receiving_queue(&local) {
+                        queue.count_malformed();
+                    }
+                }
                 return;
             },
         };
@@ -231,43 +491,156 @@ impl SharedUdpPeer {
 
         let local: SocketAddrV4 = SocketAddrV4::new(ipv4_hdr.get_dest_addr(), hdr.dest_port());
         let remote: SocketAddrV4 = SocketAddrV4::new(ipv4_hdr.get_src_addr(), hdr.src_port());
+        // Datagrams are received on the interface that the stack drives, unless they are looped back.
+        let ifindex: u32 = if ipv4_hdr.get_dest_addr().is_loopback() {
+            LOOPBACK_INTERFACE_INDEX
+        } else {
+            self.transport.get_ifindex()
+        };
+        let info: PacketInfo = PacketInfo {
+            dst_addr: ipv4_hdr.get_dest_addr(),
+            ifindex,
+        };
 
-        let mut queue: SharedUdpQueue = match self.get_queue_from_addr(&local) {
+        // Broadcast datagrams are delivered to the socket that is bound to the destination port, if it accepts them.
+        if local.ip().is_broadcast() {
+            let mut queue: SharedUdpQueue = match self.get_broadcast_queue(hdr.dest_port()) {
+                Some(queue) => queue,
+                None => {
+                    let cause: String = format!("dropping broadcast packet: port not bound or SO_BROADCAST not set");
+                    warn!("{}: {:?}", cause, local);
+                    return;
+                },
+            };
+            queue.receive(remote, info, data);
+            return;
+        }
+
+        let mut queue: SharedUdpQueue = match self.get_receiving_queue(&local) {
             Some(queue) => queue,
             None => {
-                // Handle wildcard address.
-                let local: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, hdr.dest_port());
-                match self.get_queue_from_addr(&local) {
-                    Some(queue) => queue,
-                    None => {
-                        let cause: String = format!("dropping packet: port not bound");
-                        warn!("{}: {:?}", cause, local);
-                        return;
-                    },
-                }
+                let cause: String = format!("dropping packet: port not bound");
+                warn!("{}: {:?}", cause, local);
+                return;
             },
         };
-        // TODO: Drop this packet if local address/port pair is not bound.
-        queue.receive(remote, data)
+        queue.receive(remote, info, data)
+    }
+
+    /// Reports `cause`, which was carried by an ICMP error, to the connected socket that sent the datagram that
+    /// triggered it. `datagram` holds the start of that datagram, as embedded in the ICMP message, which is enough to
+    /// find its ports. Errors for datagrams sent by sockets that are not connected are ignored, as POSIX does.
+    pub fn receive_icmp_error(&mut self, src_addr: Ipv4Addr, dst_addr: Ipv4Addr, datagram: DemiBuffer, cause: Fail) {
+        if datagram.len() < 4 {
+            let cause: String = format!("embedded datagram is too small (len={:?})", datagram.len());
+            warn!("receive_icmp_error(): dropping ICMP error: {}", cause);
+            return;
+        }
+        let src_port: u16 = u16::from_be_bytes([datagram[0], datagram[1]]);
+        let dst_port: u16 = u16::from_be_bytes([datagram[2], datagram[3]]);
+        let local: SocketAddrV4 = SocketAddrV4::new(src_addr, src_port);
+        let remote: SocketAddrV4 = SocketAddrV4::new(dst_addr, dst_port);
+
+        match self.get_connected_queue(&local, &remote) {
+            Some(mut queue) => {
+                debug!(
+                    "receive_icmp_error(): local={:?} remote={:?} cause={:?}",
+                    local, remote, cause
+                );
+                queue.receive_error(cause);
+            },
+            None => debug!(
+                "receive_icmp_error(): no connected socket (local={:?}, remote={:?})",
+                local, remote
+            ),
+        }
+    }
+
+    /// Looks up the queue that is connected to `remote` from `local`, possibly through the wildcard address.
+    fn get_connected_queue(&self, local: &SocketAddrV4, remote: &SocketAddrV4) -> Option<SharedUdpQueue> {
+        let wildcard: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, local.port());
+        for (_, boxed_queue) in self.runtime.get_qtable().get_values() {
+            match downcast_queue_ptr::<SharedUdpQueue>(boxed_queue) {
+                Ok(queue) => match (queue.local(), queue.remote()) {
+                    (Some(addr), Some(connected)) if (addr == *local || addr == wildcard) && connected == *remote => {
+                        return Some(queue.clone())
+                    },
+                    _ => continue,
+                },
+                Err(_) => continue,
+            }
+        }
+
+        None
+    }
+
+    /// Finds the socket that receives unicast datagrams sent to `local`. Sockets that are bound to that address take
+    /// precedence over those bound to the wildcard one, so that sockets bound to different local addresses on the same
+    /// port only receive their own datagrams.
+    fn get_receiving_queue(&self, local: &SocketAddrV4) -> Option<SharedUdpQueue> {
+        self.get_queue_from_addr(local)
+            .or_else(|| self.get_queue_from_addr(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, local.port())))
     }
 
+    /// Looks up the queue that receives the datagrams sent to `local`. When several sockets share the address, this is
+    /// the one that was bound to it first.
     fn get_queue_from_addr(&self, local: &SocketAddrV4) -> Option<SharedUdpQueue> {
+        self.get_queues_from_addr(local)
+            .into_iter()
+            .min_by_key(|queue| queue.get_bind_order())
+    }
+
+    /// Looks up all queues that are bound to `local`.
+    fn get_queues_from_addr(&self, local: &SocketAddrV4) -> Vec<SharedUdpQueue> {
+        let mut queues: Vec<SharedUdpQueue> = Vec::new();
         for (_, boxed_queue) in self.runtime.get_qtable().get_values() {
             match downcast_queue_ptr::<SharedUdpQueue>(boxed_queue) {
                 Ok(queue) => match queue.local() {
-                    Some(addr) if addr == *local => return Some(queue.clone()),
+                    Some(addr) if addr == *local => queues.push(queue.clone()),
                     _ => continue,
                 },
                 Err(_) => continue,
             }
         }
 
-        None
+        queues
+    }
+
+    /// Looks up the queue that is bound to `port`, either on the local address or on the wildcard one, and that accepts
+    /// broadcast datagrams.
+    fn get_broadcast_queue(&self, port: u16) -> Option<SharedUdpQueue> {
+        let candidates: [SocketAddrV4; 2] = [
+            SocketAddrV4::new(self.local_ipv4_addr, port),
+            SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, port),
+        ];
+        candidates
+            .iter()
+            .filter_map(|local| self.get_queue_from_addr(local))
+            .find(|queue| queue.get_broadcast())
     }
 
     fn get_shared_queue(&self, qd: &QDesc) -> Result<SharedUdpQueue, Fail> {
         Ok(self.runtime.get_shared_queue::<SharedUdpQueue>(qd)?.clone())
     }
+
+    /// Frees the queue of the socket `qd`, along with the claim on its local address.
+    fn free_queue(&mut self, qd: QDesc) -> Result<(), Fail> {
+        let queue: SharedUdpQueue = self.runtime.free_queue::<SharedUdpQueue>(&qd)?;
+        if let Some(local) = queue.local() {
+            self.unregister_flow(local);
+        }
+        Ok(())
+    }
+
+    /// Releases the claim on the local address `local` that was made on the transport.
+    fn unregister_flow(&mut self, local: SocketAddrV4) {
+        if let Err(e) = self.transport.unregister_flow(local) {
+            warn!(
+                "unregister_flow(): leaking claim on local address (local={:?}): {:?}",
+                local, e
+            );
+        }
+    }
 }
 
 //======================================================================================================================
@@ -24,7 +24,17 @@ use crate::{
         fail::Fail,
         memory::DemiBuffer,
         network::{
-            types::MacAddress,
+            config::{
+                UdpDropPolicy,
+                UdpRecvQueueConfig,
+            },
+            types::{
+                BufferUsage,
+                ErrorCounters,
+                MacAddress,
+                PacketInfo,
+                Readiness,
+            },
             NetworkRuntime,
         },
         queue::{
@@ -52,11 +62,6 @@ use ::std::{
 // Constants
 //======================================================================================================================
 
-// Maximum size for receive queues (in messages).
-// TODO: Support max size on async queues.
-#[allow(dead_code)]
-const RECV_QUEUE_MAX_SIZE: usize = 1024;
-
 // Maximum size for send queues (in messages).
 // TODO: Support max size on async queues.
 #[allow(dead_code)]
@@ -70,12 +75,38 @@ const SEND_QUEUE_MAX_SIZE: usize = 1024;
 pub struct UdpQueue {
     local_ipv4_addr: Ipv4Addr,
     bound: Option<SocketAddrV4>,
+    /// Rank of the socket among those that were bound, which orders the sockets that share an address.
+    bind_order: u64,
+    /// May the socket share its local address with other sockets that allow it too (i.e. is SO_REUSEADDR set)?
+    reuse_addr: bool,
+    /// Remote address to which the socket is connected, if any.
+    connected: Option<SocketAddrV4>,
     local_link_addr: MacAddress,
     transport: SharedBox<dyn NetworkRuntime>,
-    // A queue of incoming packets as remote address and data buffer pairs.
-    recv_queue: AsyncQueue<(SocketAddrV4, DemiBuffer)>,
+    // A queue of incoming packets, along with their remote address and ancillary data.
+    recv_queue: AsyncQueue<(SocketAddrV4, PacketInfo, DemiBuffer)>,
+    /// Number of bytes held in the receive queue.
+    recv_queued_bytes: usize,
+    /// Maximum number of datagrams held in the receive queue.
+    recv_max_datagrams: usize,
+    /// Maximum number of bytes held in the receive queue, if any (i.e. SO_RCVBUF).
+    recv_max_bytes: Option<usize>,
+    /// Datagram that is dropped when the receive queue is full.
+    recv_drop_policy: UdpDropPolicy,
+    /// Number of datagrams dropped because the receive queue was full.
+    recv_dropped: usize,
     arp: SharedArpPeer,
     checksum_offload: bool,
+    /// Can datagrams be sent to and received from the broadcast address (i.e. is SO_BROADCAST set)?
+    broadcast: bool,
+    /// Are pops handed the ancillary data of datagrams (i.e. is IP_PKTINFO set)?
+    pktinfo: bool,
+    /// Are destinations resolved on the local link, instead of through the gateway (i.e. is SO_DONTROUTE set)?
+    dontroute: bool,
+    /// Error reported by an ICMP message that is yet to be returned by an operation (i.e. SO_ERROR).
+    pending_error: Option<Fail>,
+    /// Cumulative counts of malformed datagrams and ICMP errors, which the application may reset.
+    error_counters: ErrorCounters,
 }
 #[derive(Clone)]
 pub struct SharedUdpQueue(SharedObject<UdpQueue>);
@@ -91,38 +122,90 @@ impl SharedUdpQueue {
         transport: SharedBox<dyn NetworkRuntime>,
         arp: SharedArpPeer,
         checksum_offload: bool,
+        recv_queue_config: &UdpRecvQueueConfig,
     ) -> Result<Self, Fail> {
         Ok(Self(SharedObject::new(UdpQueue {
             local_ipv4_addr,
             bound: None,
+            bind_order: 0,
+            reuse_addr: false,
+            connected: None,
             local_link_addr,
             transport,
-            recv_queue: AsyncQueue::<(SocketAddrV4, DemiBuffer)>::default(),
+            recv_queue: AsyncQueue::<(SocketAddrV4, PacketInfo, DemiBuffer)>::default(),
+            recv_queued_bytes: 0,
+            recv_max_datagrams: recv_queue_config.get_max_datagrams(),
+            recv_max_bytes: recv_queue_config.get_max_bytes(),
+            recv_drop_policy: recv_queue_config.get_drop_policy(),
+            recv_dropped: 0,
             arp,
             checksum_offload,
+            broadcast: false,
+            pktinfo: false,
+            dontroute: false,
+            pending_error: None,
+            error_counters: ErrorCounters::default(),
         })))
     }
 
-    pub fn bind(&mut self, local: SocketAddrV4) -> Result<(), Fail> {
+    /// Binds the target queue to `local`. Sockets that share the address are ranked by `bind_order`.
+    pub fn bind(&mut self, local: SocketAddrV4, bind_order: u64) -> Result<(), Fail> {
         self.bound = Some(local);
+        self.bind_order = bind_order;
         Ok(())
     }
 
-    pub async fn pushto(&mut self, remote: SocketAddrV4, buf: DemiBuffer, yielder: Yielder) -> Result<(), Fail> {
+    /// Gets the rank of the target queue among the sockets that were bound.
+    pub fn get_bind_order(&self) -> u64 {
+        self.bind_order
+    }
+
+    /// Connects the target queue to `remote`, so that it only receives datagrams from it, and that ICMP errors
+    /// triggered by the datagrams that it sends there are reported.
+    pub fn connect(&mut self, remote: SocketAddrV4) {
+        self.connected = Some(remote);
+    }
+
+    /// Sends `buf` to `remote`. The datagram is sent from `src`, if given, which overrides the address that the socket
+    /// is bound to.
+    pub async fn pushto(
+        &mut self,
+        src: Option<Ipv4Addr>,
+        remote: SocketAddrV4,
+        buf: DemiBuffer,
+        yielder: Yielder,
+    ) -> Result<(), Fail> {
+        if let Some(e) = self.pending_error.take() {
+            error!("pushto(): {:?}", e);
+            return Err(e);
+        }
         // Check that the socket is bound.
-        let port: u16 = if let Some(addr) = self.local() {
-            addr.port()
+        let local: SocketAddrV4 = if let Some(addr) = self.local() {
+            addr
         } else {
             let cause: String = format!("queue is not bound");
             error!("pushto(): {}", &cause);
             return Err(Fail::new(libc::ENOTSUP, &cause));
         };
-        let remote_link_addr: MacAddress = self.arp.query(remote.ip().clone(), &yielder).await?;
-        let udp_header: UdpHeader = UdpHeader::new(port, remote.port());
+        // Datagrams are sent from the address that the socket is bound to, unless it is the wildcard one.
+        let local_ipv4_addr: Ipv4Addr = match src {
+            Some(src) => src,
+            None if local.ip().is_unspecified() => self.local_ipv4_addr,
+            None => *local.ip(),
+        };
+        // Broadcast datagrams go to the broadcast link address, so there is nothing to resolve.
+        let remote_link_addr: MacAddress = if remote.ip().is_broadcast() {
+            MacAddress::broadcast()
+        } else if self.dontroute {
+            self.arp.query_direct(remote.ip().clone(), &yielder).await?
+        } else {
+            self.arp.query(remote.ip().clone(), &yielder).await?
+        };
+        let udp_header: UdpHeader = UdpHeader::new(local.port(), remote.port());
         debug!("UDP send {:?}", udp_header);
         let datagram = UdpDatagram::new(
             Ethernet2Header::new(remote_link_addr, self.local_link_addr, EtherType2::Ipv4),
-            Ipv4Header::new(self.local_ipv4_addr, remote.ip().clone(), IpProtocol::UDP),
+            Ipv4Header::new(local_ipv4_addr, remote.ip().clone(), IpProtocol::UDP),
             udp_header,
             buf,
             self.checksum_offload,
@@ -131,35 +214,190 @@ impl SharedUdpQueue {
         Ok(())
     }
 
-    pub async fn pop(&mut self, size: Option<usize>, yielder: Yielder) -> Result<(SocketAddrV4, DemiBuffer), Fail> {
+    pub async fn pop(
+        &mut self,
+        size: Option<usize>,
+        yielder: Yielder,
+    ) -> Result<(SocketAddrV4, PacketInfo, DemiBuffer), Fail> {
         const MAX_POP_SIZE: usize = 9000;
         let size: usize = size.unwrap_or(MAX_POP_SIZE);
+        if let Some(e) = self.pending_error.take() {
+            error!("pop(): {:?}", e);
+            return Err(e);
+        }
 
         loop {
             match self.recv_queue.pop(&yielder).await {
                 Ok(msg) => {
                     let remote: SocketAddrV4 = msg.0;
-                    let mut buf: DemiBuffer = msg.1;
+                    let info: PacketInfo = msg.1;
+                    let mut buf: DemiBuffer = msg.2;
+                    self.recv_queued_bytes -= buf.len();
                     // We got more bytes than expected, so we trim the buffer.
                     if size < buf.len() {
                         buf.trim(size - buf.len())?;
                     };
-                    return Ok((remote, buf));
+                    return Ok((remote, info, buf));
                 },
                 Err(e) => return Err(e),
             }
         }
     }
 
-    pub fn receive(&mut self, remote: SocketAddrV4, buf: DemiBuffer) {
+    pub fn receive(&mut self, remote: SocketAddrV4, info: PacketInfo, buf: DemiBuffer) {
+        // Connected sockets only receive datagrams from the remote address that they are connected to.
+        if self.connected.map_or(false, |connected| connected != remote) {
+            let cause: String = format!("dropping packet: socket is connected to another address");
+            warn!("{}: {:?}", cause, remote);
+            return;
+        }
+        // Make room for the datagram, or drop it, if the receive queue is full. Datagrams that would not fit even in an
+        // empty receive queue are dropped right away.
+        if self.is_recv_queue_full(buf.len()) {
+            let fits: bool = self.recv_max_bytes.map_or(true, |max_bytes| buf.len() <= max_bytes);
+            if fits && self.recv_drop_policy == UdpDropPolicy::Oldest {
+                while self.is_recv_queue_full(buf.len()) {
+                    match self.recv_queue.try_pop() {
+                        Some((_, _, oldest)) => {
+                            self.recv_queued_bytes -= oldest.len();
+                            self.recv_dropped += 1;
+                        },
+                        None => break,
+                    }
+                }
+            }
+            if self.is_recv_queue_full(buf.len()) {
+                let cause: String = format!("dropping packet: receive queue is full");
+                warn!("{}: {:?}", cause, remote);
+                self.recv_dropped += 1;
+                return;
+            }
+        }
         // Push data to the receiver-side shared queue. This will cause the
         // associated pool operation to be ready.
-        self.recv_queue.push((remote, buf));
+        self.recv_queued_bytes += buf.len();
+        self.recv_queue.push((remote, info, buf));
+    }
+
+    /// Checks whether the receive queue has no room left for a datagram of `len` bytes.
+    fn is_recv_queue_full(&self, len: usize) -> bool {
+        self.recv_queue.len() >= self.recv_max_datagrams
+            || self
+                .recv_max_bytes
+                .map_or(false, |max_bytes| self.recv_queued_bytes + len > max_bytes)
+    }
+
+    /// Sets the largest number of bytes held in the receive queue (i.e. sets SO_RCVBUF). Datagrams that are already
+    /// queued are kept, even past the new size.
+    pub fn set_recv_buffer_size(&mut self, size: usize) -> Result<(), Fail> {
+        if size == 0 {
+            let cause: String = format!("receive buffer cannot be empty");
+            error!("set_recv_buffer_size(): {}", &cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        self.recv_max_bytes = Some(size);
+        Ok(())
+    }
+
+    /// Gets the largest number of bytes held in the receive queue. The receive queue is only bounded by its number of
+    /// datagrams unless told otherwise.
+    pub fn get_recv_buffer_size(&self) -> usize {
+        self.recv_max_bytes.unwrap_or(usize::MAX)
+    }
+
+    /// Gets the number of datagrams that were dropped because the receive queue was full.
+    pub fn get_recv_dropped(&self) -> usize {
+        self.recv_dropped
+    }
+
+    /// Reports an error from an ICMP message. The error fails a pop that is waiting for data, if any, or else the
+    /// next operation on the target queue. Only the last error is kept.
+    pub fn receive_error(&mut self, cause: Fail) {
+        self.error_counters.icmp_errors += 1;
+        if self.recv_queue.has_waiters() {
+            self.recv_queue.cancel_waiters(cause);
+        } else {
+            self.pending_error = Some(cause);
+        }
+    }
+
+    /// Counts a datagram for this socket that was dropped because it was malformed or failed its checksum.
+    pub fn count_malformed(&mut self) {
+        self.error_counters.malformed += 1;
+    }
+
+    /// Reads the error counters of this socket, and clears them afterwards if `reset` is set.
+    pub fn error_counters(&mut self, reset: bool) -> ErrorCounters {
+        let counters: ErrorCounters = self.error_counters;
+        if reset {
+            self.error_counters = ErrorCounters::default();
+        }
+        counters
     }
 
     pub fn is_bound(&self) -> bool {
         self.bound.is_some()
     }
+
+    /// Reports how many bytes are currently held in the receive buffer. Datagrams are sent right away, so nothing is
+    /// ever held in the send buffer.
+    pub fn buffer_usage(&self) -> BufferUsage {
+        BufferUsage {
+            recv_queued: self.recv_queued_bytes,
+            ..BufferUsage::default()
+        }
+    }
+
+    /// Reports whether operations on this socket would complete right away. Datagrams are sent right away, so the
+    /// socket is always writable.
+    pub fn readiness(&self) -> Readiness {
+        Readiness {
+            readable: !self.recv_queue.is_empty(),
+            writable: true,
+            error: self.pending_error.is_some(),
+            ..Readiness::default()
+        }
+    }
+
+    /// Allows or disallows sending to and receiving from the broadcast address (i.e. sets SO_BROADCAST).
+    pub fn set_broadcast(&mut self, broadcast: bool) {
+        self.broadcast = broadcast;
+    }
+
+    /// Checks whether sending to and receiving from the broadcast address is allowed.
+    pub fn get_broadcast(&self) -> bool {
+        self.broadcast
+    }
+
+    /// Sets whether pops are handed the ancillary data of datagrams (i.e. sets IP_PKTINFO).
+    pub fn set_pktinfo(&mut self, pktinfo: bool) {
+        self.pktinfo = pktinfo;
+    }
+
+    /// Checks whether pops are handed the ancillary data of datagrams.
+    pub fn get_pktinfo(&self) -> bool {
+        self.pktinfo
+    }
+
+    /// Sets whether destinations are resolved on the local link, bypassing routing (i.e. sets SO_DONTROUTE).
+    pub fn set_dontroute(&mut self, dontroute: bool) {
+        self.dontroute = dontroute;
+    }
+
+    /// Checks whether destinations are resolved on the local link.
+    pub fn get_dontroute(&self) -> bool {
+        self.dontroute
+    }
+
+    /// Sets whether the local address may be shared with other sockets (i.e. sets SO_REUSEADDR).
+    pub fn set_reuse_addr(&mut self, reuse_addr: bool) {
+        self.reuse_addr = reuse_addr;
+    }
+
+    /// Checks whether the local address may be shared with other sockets.
+    pub fn get_reuse_addr(&self) -> bool {
+        self.reuse_addr
+    }
 }
 
 //======================================================================================================================
@@ -206,8 +444,7 @@ impl NetworkQueue for SharedUdpQueue {
     }
 
     /// Returns the remote address to which the target queue is connected to.
-    /// TODO: Add later if we support connected UDP sockets.
     fn remote(&self) -> Option<SocketAddrV4> {
-        None
+        self.connected
     }
 }
@@ -2,12 +2,30 @@
 // // Licensed under the MIT license.
 
 use crate::{
-    inetstack::test_helpers::{
-        self,
-        SharedEngine,
+    inetstack::{
+        protocols::{
+            ethernet2::Ethernet2Header,
+            ipv4::Ipv4Header,
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
     },
     runtime::{
         memory::DemiBuffer,
+        network::{
+            config::{
+                UdpConfig,
+                UdpDropPolicy,
+                UdpRecvQueueConfig,
+            },
+            consts::DEFAULT_MTU,
+            types::{
+                BufferUsage,
+                MacAddress,
+            },
+        },
         queue::{
             Operation,
             OperationResult,
@@ -21,8 +39,11 @@ use ::futures::task::{
     Context,
 };
 use ::libc::{
+    EACCES,
     EADDRINUSE,
+    EADDRNOTAVAIL,
     EBADF,
+    EMSGSIZE,
 };
 use ::std::{
     convert::TryFrom,
@@ -103,7 +124,7 @@ fn udp_push_pop() -> Result<()> {
     let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 32][..]).expect("slice should fit in DemiBuffer");
     let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf.clone(), bob_addr)?;
     match Future::poll(coroutine.as_mut(), &mut ctx) {
-        Poll::Ready((_, OperationResult::Push)) => {},
+        Poll::Ready((_, OperationResult::Push(_))) => {},
         _ => unreachable!("Push failed"),
     };
     alice.get_test_rig().poll_scheduler();
@@ -155,7 +176,7 @@ fn udp_push_pop_wildcard_address() -> Result<()> {
     let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 32][..]).expect("slice should fit in DemiBuffer");
     let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf.clone(), bob_addr)?;
     match Future::poll(coroutine.as_mut(), &mut ctx) {
-        Poll::Ready((_, OperationResult::Push)) => {},
+        Poll::Ready((_, OperationResult::Push(_))) => {},
         _ => unreachable!("Push failed"),
     };
     alice.get_test_rig().poll_scheduler();
@@ -179,6 +200,103 @@ fn udp_push_pop_wildcard_address() -> Result<()> {
     Ok(())
 }
 
+/// Tests that a socket bound to the wildcard address learns the concrete destination address of a datagram when
+/// IP_PKTINFO is set.
+#[test]
+fn udp_pop_with_pktinfo() -> Result<()> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    let now: Instant = Instant::now();
+
+    // Setup Alice.
+    let mut alice: SharedEngine = test_helpers::new_alice2(now);
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 80);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+
+    // Setup Bob.
+    let mut bob: SharedEngine = test_helpers::new_bob2(now);
+    let bob_port: u16 = 80;
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, bob_port))?;
+    bob.udp_set_pktinfo(bob_fd, true)?;
+
+    // Send data to Bob.
+    let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 32][..]).expect("slice should fit in DemiBuffer");
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, bob_port);
+    let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf.clone(), bob_addr)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Push(_))) => {},
+        _ => anyhow::bail!("push failed"),
+    };
+    bob.receive(alice.get_test_rig().pop_frame())?;
+
+    let mut coroutine: Pin<Box<Operation>> = bob.udp_pop(bob_fd)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::PopWithInfo(addr, info, received_buf))) => {
+            crate::ensure_eq!(addr, alice_addr);
+            crate::ensure_eq!(info.dst_addr, test_helpers::BOB_IPV4);
+            crate::ensure_eq!(info.ifindex, test_helpers::INTERFACE_INDEX);
+            crate::ensure_eq!(received_buf[..], buf[..]);
+        },
+        _ => anyhow::bail!("pop should have completed with the ancillary data of the datagram"),
+    };
+
+    // Close peers.
+    alice.udp_close(alice_fd)?;
+    bob.udp_close(bob_fd)?;
+
+    Ok(())
+}
+
+/// Tests that a reply from a socket bound to the wildcard address carries the source address that it was pushed from,
+/// and that only local addresses, including the ones of the loopback network, may be used as such.
+#[test]
+fn udp_pushto_from() -> Result<()> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    let now: Instant = Instant::now();
+
+    // Setup Bob.
+    let mut bob: SharedEngine = test_helpers::new_bob2(now);
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 80))?;
+
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 80);
+    let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 32][..]).expect("slice should fit in DemiBuffer");
+    let mut coroutine: Pin<Box<Operation>> =
+        bob.udp_pushto_from(bob_fd, buf.clone(), test_helpers::BOB_IPV4, alice_addr)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Push(_))) => {},
+        _ => anyhow::bail!("push failed"),
+    };
+    let (_, eth2_payload): (Ethernet2Header, DemiBuffer) = Ethernet2Header::parse(bob.get_test_rig().pop_frame())?;
+    let (ipv4_hdr, _): (Ipv4Header, DemiBuffer) = Ipv4Header::parse(eth2_payload)?;
+    crate::ensure_eq!(ipv4_hdr.get_src_addr(), test_helpers::BOB_IPV4);
+    crate::ensure_eq!(ipv4_hdr.get_dest_addr(), test_helpers::ALICE_IPV4);
+
+    // Addresses of the loopback network are local too.
+    let loopback_addr: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 81);
+    let mut coroutine: Pin<Box<Operation>> =
+        bob.udp_pushto_from(bob_fd, buf.clone(), Ipv4Addr::LOCALHOST, loopback_addr)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Push(_))) => {},
+        _ => anyhow::bail!("push from the loopback address failed"),
+    };
+    let (_, eth2_payload): (Ethernet2Header, DemiBuffer) = Ethernet2Header::parse(bob.get_test_rig().pop_frame())?;
+    let (ipv4_hdr, _): (Ipv4Header, DemiBuffer) = Ipv4Header::parse(eth2_payload)?;
+    crate::ensure_eq!(ipv4_hdr.get_src_addr(), Ipv4Addr::LOCALHOST);
+    crate::ensure_eq!(ipv4_hdr.get_dest_addr(), Ipv4Addr::LOCALHOST);
+
+    // A source address that the stack does not own should be rejected.
+    match bob.udp_pushto_from(bob_fd, buf, test_helpers::CARRIE_IPV4, alice_addr) {
+        Err(e) if e.errno == EADDRNOTAVAIL => {},
+        _ => anyhow::bail!("pushto from a foreign address should have failed with EADDRNOTAVAIL"),
+    };
+
+    bob.udp_close(bob_fd)?;
+
+    Ok(())
+}
+
 //==============================================================================
 // Ping Pong
 //==============================================================================
@@ -206,7 +324,7 @@ fn udp_ping_pong() -> Result<()> {
     let buf_a: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 32][..]).expect("slice should fit in DemiBuffer");
     let mut alice_coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf_a.clone(), bob_addr)?;
     match Future::poll(alice_coroutine.as_mut(), &mut ctx) {
-        Poll::Ready((_, OperationResult::Push)) => {},
+        Poll::Ready((_, OperationResult::Push(_))) => {},
         _ => unreachable!("Push failed"),
     };
     now += Duration::from_micros(1);
@@ -228,7 +346,7 @@ fn udp_ping_pong() -> Result<()> {
     let buf_b: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 32][..]).expect("slice should fit in DemiBuffer");
     let mut bob_coroutine2: Pin<Box<Operation>> = bob.udp_pushto(bob_fd, buf_b.clone(), alice_addr)?;
     match Future::poll(bob_coroutine2.as_mut(), &mut ctx) {
-        Poll::Ready((_, OperationResult::Push)) => {},
+        Poll::Ready((_, OperationResult::Push(_))) => {},
         _ => unreachable!("Push failed"),
     };
 
@@ -340,7 +458,7 @@ fn udp_loop2_push_pop() -> Result<()> {
         let buf: DemiBuffer = DemiBuffer::from_slice(&vec![(b % 256) as u8; 32][..]).expect("slice should fit");
         let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf.clone(), bob_addr)?;
         match Future::poll(coroutine.as_mut(), &mut ctx) {
-            Poll::Ready((_, OperationResult::Push)) => {},
+            Poll::Ready((_, OperationResult::Push(_))) => {},
             _ => unreachable!("Push failed"),
         };
 
@@ -404,7 +522,7 @@ fn udp_loop2_ping_pong() -> Result<()> {
         let buf_a: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 32][..]).expect("slice should fit in DemiBuffer");
         let mut alice_coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf_a.clone(), bob_addr)?;
         match Future::poll(alice_coroutine.as_mut(), &mut ctx) {
-            Poll::Ready((_, OperationResult::Push)) => {},
+            Poll::Ready((_, OperationResult::Push(_))) => {},
             _ => unreachable!("Push failed"),
         };
 
@@ -427,7 +545,7 @@ fn udp_loop2_ping_pong() -> Result<()> {
         let buf_b: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 32][..]).expect("slice should fit in DemiBuffer");
         let mut bob_coroutine2 = bob.udp_pushto(bob_fd, buf_b.clone(), alice_addr)?;
         match Future::poll(bob_coroutine2.as_mut(), &mut ctx) {
-            Poll::Ready((_, OperationResult::Push)) => {},
+            Poll::Ready((_, OperationResult::Push(_))) => {},
             _ => unreachable!("Push failed"),
         };
 
@@ -555,7 +673,7 @@ fn udp_pop_not_bound() -> Result<()> {
     let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 32][..]).expect("slice should fit in DemiBuffer");
     let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf, bob_addr)?;
     match Future::poll(coroutine.as_mut(), &mut ctx) {
-        Poll::Ready((_, OperationResult::Push)) => {},
+        Poll::Ready((_, OperationResult::Push(_))) => {},
         _ => unreachable!("Push failed"),
     };
 
@@ -609,3 +727,491 @@ fn udp_push_bad_file_descriptor() -> Result<()> {
 
     Ok(())
 }
+
+//==============================================================================
+// Broadcast
+//==============================================================================
+
+/// Tests that sending to the broadcast address requires SO_BROADCAST, and that broadcast datagrams are sent to the
+/// broadcast link address.
+#[test]
+fn udp_pushto_broadcast() -> Result<()> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    let now: Instant = Instant::now();
+
+    // Setup Alice.
+    let mut alice: SharedEngine = test_helpers::new_alice2(now);
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 80);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+    let broadcast_addr: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::BROADCAST, 80);
+
+    // Sending to the broadcast address should fail without SO_BROADCAST.
+    let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 32][..]).expect("slice should fit in DemiBuffer");
+    match alice.udp_pushto(alice_fd, buf.clone(), broadcast_addr) {
+        Err(e) if e.errno == EACCES => {},
+        _ => anyhow::bail!("pushto to broadcast address should have failed"),
+    };
+
+    // Sending to the broadcast address should succeed with SO_BROADCAST.
+    alice.udp_set_broadcast(alice_fd, true)?;
+    let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf, broadcast_addr)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Push(_))) => {},
+        _ => anyhow::bail!("pushto to broadcast address should have succeeded"),
+    };
+    let frame: DemiBuffer = alice.get_test_rig().pop_frame();
+    crate::ensure_eq!(MacAddress::from_bytes(&frame[0..6]), MacAddress::broadcast());
+
+    alice.udp_close(alice_fd)?;
+
+    Ok(())
+}
+
+/// Tests that a datagram that does not fit in the MTU is rejected right away, and that one that fits exactly is sent.
+/// The path MSS of the socket should report the size of the latter.
+#[test]
+fn udp_pushto_larger_than_mtu() -> Result<()> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    let now: Instant = Instant::now();
+
+    // Setup Alice.
+    let mut alice: SharedEngine = test_helpers::new_alice2(now);
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 80);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+
+    // Largest payload that fits in a single packet, after the IPv4 and UDP headers.
+    let max_size: usize = DEFAULT_MTU - 20 - 8;
+    crate::ensure_eq!(alice.udp_path_mss(alice_fd)?, max_size);
+
+    // An oversized datagram should fail before any operation is scheduled.
+    let buf: DemiBuffer =
+        DemiBuffer::from_slice(&vec![0x5a; max_size + 1][..]).expect("slice should fit in DemiBuffer");
+    match alice.udp_pushto(alice_fd, buf, bob_addr) {
+        Err(e) if e.errno == EMSGSIZE => {},
+        _ => anyhow::bail!("pushto of an oversized datagram should have failed"),
+    };
+    crate::ensure_eq!(alice.get_test_rig().pop_all_frames().len(), 0);
+
+    // A datagram that fits exactly should be sent.
+    let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; max_size][..]).expect("slice should fit in DemiBuffer");
+    let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf, bob_addr)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Push(len))) => crate::ensure_eq!(len, max_size),
+        _ => anyhow::bail!("pushto of a datagram that fits in the MTU should have succeeded"),
+    };
+
+    alice.udp_close(alice_fd)?;
+
+    Ok(())
+}
+
+/// Tests that broadcast datagrams are only delivered to sockets that have SO_BROADCAST set.
+#[test]
+fn udp_receive_broadcast() -> Result<()> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    let now: Instant = Instant::now();
+
+    // Setup Alice.
+    let mut alice: SharedEngine = test_helpers::new_alice2(now);
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 80);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+    alice.udp_set_broadcast(alice_fd, true)?;
+
+    // Setup Bob, without SO_BROADCAST.
+    let mut bob: SharedEngine = test_helpers::new_bob2(now);
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, bob_addr)?;
+
+    // Send a first broadcast datagram, which Bob should drop.
+    let broadcast_addr: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::BROADCAST, 80);
+    let dropped: DemiBuffer = DemiBuffer::from_slice(&vec![0x11; 32][..]).expect("slice should fit in DemiBuffer");
+    let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, dropped, broadcast_addr)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Push(_))) => {},
+        _ => anyhow::bail!("push failed"),
+    };
+    bob.receive(alice.get_test_rig().pop_frame())?;
+
+    // Send a second broadcast datagram, which Bob should receive.
+    bob.udp_set_broadcast(bob_fd, true)?;
+    let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 32][..]).expect("slice should fit in DemiBuffer");
+    let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf.clone(), broadcast_addr)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Push(_))) => {},
+        _ => anyhow::bail!("push failed"),
+    };
+    bob.receive(alice.get_test_rig().pop_frame())?;
+
+    let mut coroutine: Pin<Box<Operation>> = bob.udp_pop(bob_fd)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Pop(addr, received_buf))) => {
+            crate::ensure_eq!(addr, Some(alice_addr));
+            crate::ensure_eq!(received_buf[..], buf[..]);
+        },
+        _ => anyhow::bail!("pop should have completed with the second datagram"),
+    };
+    let mut coroutine: Pin<Box<Operation>> = bob.udp_pop(bob_fd)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Pending => {},
+        _ => anyhow::bail!("first datagram should have been dropped"),
+    };
+
+    // Close peers.
+    alice.udp_close(alice_fd)?;
+    bob.udp_close(bob_fd)?;
+
+    Ok(())
+}
+
+//==============================================================================
+// Buffer Usage
+//==============================================================================
+
+/// Tests that the buffer usage of a UDP socket tracks received and popped datagrams.
+#[test]
+fn udp_buffer_usage() -> Result<()> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    let now: Instant = Instant::now();
+
+    // Setup Alice.
+    let mut alice: SharedEngine = test_helpers::new_alice2(now);
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 80);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+
+    // Setup Bob.
+    let mut bob: SharedEngine = test_helpers::new_bob2(now);
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, bob_addr)?;
+    crate::ensure_eq!(bob.udp_buffer_usage(bob_fd)?, BufferUsage::default());
+
+    // Send two datagrams to Bob.
+    for len in [32, 16] {
+        let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; len][..]).expect("slice should fit in DemiBuffer");
+        let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf, bob_addr)?;
+        match Future::poll(coroutine.as_mut(), &mut ctx) {
+            Poll::Ready((_, OperationResult::Push(_))) => {},
+            _ => unreachable!("Push failed"),
+        };
+        // Datagrams are sent right away.
+        crate::ensure_eq!(alice.udp_buffer_usage(alice_fd)?, BufferUsage::default());
+        bob.receive(alice.get_test_rig().pop_frame())?;
+    }
+    crate::ensure_eq!(bob.udp_buffer_usage(bob_fd)?.recv_queued, 48);
+
+    // Popping a datagram should release its bytes.
+    let mut coroutine: Pin<Box<Operation>> = bob.udp_pop(bob_fd)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Pop(_, buf))) => crate::ensure_eq!(buf.len(), 32),
+        _ => unreachable!("Pop failed"),
+    };
+    crate::ensure_eq!(bob.udp_buffer_usage(bob_fd)?.recv_queued, 16);
+
+    // Close peers.
+    alice.udp_close(alice_fd)?;
+    bob.udp_close(bob_fd)?;
+
+    Ok(())
+}
+
+//==============================================================================
+// Receive Queue Bounds
+//==============================================================================
+
+/// Sends `count` datagrams of `len` bytes from `alice_fd` to `bob_addr`, each filled with its index, and delivers them
+/// to Bob without popping them.
+fn flood(
+    alice: &mut SharedEngine,
+    alice_fd: QDesc,
+    bob: &mut SharedEngine,
+    bob_addr: SocketAddrV4,
+    count: usize,
+    len: usize,
+) -> Result<()> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    for i in 0..count {
+        let buf: DemiBuffer = DemiBuffer::from_slice(&vec![i as u8; len][..]).expect("slice should fit in DemiBuffer");
+        let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf, bob_addr)?;
+        match Future::poll(coroutine.as_mut(), &mut ctx) {
+            Poll::Ready((_, OperationResult::Push(_))) => {},
+            _ => anyhow::bail!("push should have completed"),
+        };
+        bob.receive(alice.get_test_rig().pop_frame())?;
+    }
+    Ok(())
+}
+
+/// Pops a datagram from `bob_fd`, which should be queued already, and returns the index it was filled with.
+fn pop_index(bob: &mut SharedEngine, bob_fd: QDesc) -> Result<u8> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    let mut coroutine: Pin<Box<Operation>> = bob.udp_pop(bob_fd)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Pop(_, buf))) => Ok(buf[0]),
+        _ => anyhow::bail!("pop should have completed"),
+    }
+}
+
+/// Tests that a flood of datagrams on a socket that does not pop them is bounded by the maximum number of datagrams of
+/// its receive queue, and that the datagrams that arrive once it is full are dropped and counted.
+#[test]
+fn udp_recv_queue_max_datagrams() -> Result<()> {
+    let now: Instant = Instant::now();
+    let recv_queue: UdpRecvQueueConfig = UdpRecvQueueConfig::new(Some(8), None, None)?;
+
+    // Setup Alice.
+    let mut alice: SharedEngine = test_helpers::new_alice2(now);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, SocketAddrV4::new(test_helpers::ALICE_IPV4, 80))?;
+
+    // Setup Bob.
+    let mut bob: SharedEngine =
+        test_helpers::new_bob2_with_udp_config(now, UdpConfig::new(None, None, None, Some(recv_queue)));
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, bob_addr)?;
+
+    flood(&mut alice, alice_fd, &mut bob, bob_addr, 20, 100)?;
+    crate::ensure_eq!(bob.udp_buffer_usage(bob_fd)?.recv_queued, 800);
+    crate::ensure_eq!(bob.udp_recv_dropped(bob_fd)?, 12);
+
+    // The oldest datagrams were kept.
+    crate::ensure_eq!(pop_index(&mut bob, bob_fd)?, 0);
+
+    // Popping makes room for a new datagram.
+    flood(&mut alice, alice_fd, &mut bob, bob_addr, 2, 100)?;
+    crate::ensure_eq!(bob.udp_buffer_usage(bob_fd)?.recv_queued, 800);
+    crate::ensure_eq!(bob.udp_recv_dropped(bob_fd)?, 13);
+
+    // Close peers.
+    alice.udp_close(alice_fd)?;
+    bob.udp_close(bob_fd)?;
+
+    Ok(())
+}
+
+/// Tests that SO_RCVBUF bounds the number of bytes that a socket holds, and that the drop-oldest policy makes room
+/// for new datagrams by dropping the oldest ones.
+#[test]
+fn udp_recv_queue_max_bytes_drop_oldest() -> Result<()> {
+    let now: Instant = Instant::now();
+    let recv_queue: UdpRecvQueueConfig = UdpRecvQueueConfig::new(None, None, Some(UdpDropPolicy::Oldest))?;
+
+    // Setup Alice.
+    let mut alice: SharedEngine = test_helpers::new_alice2(now);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, SocketAddrV4::new(test_helpers::ALICE_IPV4, 80))?;
+
+    // Setup Bob.
+    let mut bob: SharedEngine =
+        test_helpers::new_bob2_with_udp_config(now, UdpConfig::new(None, None, None, Some(recv_queue)));
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, bob_addr)?;
+    match bob.udp_set_recv_buffer_size(bob_fd, 0) {
+        Err(e) if e.errno == libc::EINVAL => (),
+        result => anyhow::bail!("an empty receive buffer should be rejected: {:?}", result),
+    }
+    bob.udp_set_recv_buffer_size(bob_fd, 1000)?;
+
+    flood(&mut alice, alice_fd, &mut bob, bob_addr, 25, 100)?;
+    crate::ensure_eq!(bob.udp_buffer_usage(bob_fd)?.recv_queued, 1000);
+    crate::ensure_eq!(bob.udp_recv_dropped(bob_fd)?, 15);
+
+    // The newest datagrams were kept.
+    crate::ensure_eq!(pop_index(&mut bob, bob_fd)?, 15);
+
+    // A datagram that does not fit in the receive buffer on its own is dropped, without dropping older ones.
+    flood(&mut alice, alice_fd, &mut bob, bob_addr, 1, 1001)?;
+    crate::ensure_eq!(bob.udp_buffer_usage(bob_fd)?.recv_queued, 900);
+    crate::ensure_eq!(bob.udp_recv_dropped(bob_fd)?, 16);
+
+    // Close peers.
+    alice.udp_close(alice_fd)?;
+    bob.udp_close(bob_fd)?;
+
+    Ok(())
+}
+
+//==============================================================================
+// Multi-Homed Hosts
+//==============================================================================
+
+/// Tests that sockets bound to different local addresses on the same port only receive the datagrams destined to their
+/// own address, and that they send from it.
+#[test]
+fn udp_bind_different_local_addresses() -> Result<()> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    let now: Instant = Instant::now();
+
+    // Setup Alice.
+    let mut alice: SharedEngine = test_helpers::new_alice2(now);
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 80);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+
+    // Setup Bob, with one socket on each of his addresses, and another one to send on the loopback network.
+    let mut bob: SharedEngine = test_helpers::new_bob2(now);
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, bob_addr)?;
+    let loopback_addr: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 80);
+    let loopback_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(loopback_fd, loopback_addr)?;
+    let sender_addr: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 81);
+    let sender_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(sender_fd, sender_addr)?;
+
+    // Send a datagram to Bob's address, which only the socket bound to it should receive.
+    let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 32][..]).expect("slice should fit in DemiBuffer");
+    let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf.clone(), bob_addr)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Push(_))) => {},
+        _ => anyhow::bail!("push failed"),
+    };
+    bob.receive(alice.get_test_rig().pop_frame())?;
+    let mut loopback_pop: Pin<Box<Operation>> = bob.udp_pop(loopback_fd)?;
+    match Future::poll(loopback_pop.as_mut(), &mut ctx) {
+        Poll::Pending => {},
+        _ => anyhow::bail!("datagram should not have been delivered to the loopback address"),
+    };
+    let mut coroutine: Pin<Box<Operation>> = bob.udp_pop(bob_fd)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Pop(addr, received_buf))) => {
+            crate::ensure_eq!(addr, Some(alice_addr));
+            crate::ensure_eq!(received_buf[..], buf[..]);
+        },
+        _ => anyhow::bail!("pop should have completed"),
+    };
+
+    // Send a datagram to the loopback address, which should be sourced from the address of the sending socket.
+    let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0xa5; 16][..]).expect("slice should fit in DemiBuffer");
+    let mut coroutine: Pin<Box<Operation>> = bob.udp_pushto(sender_fd, buf.clone(), loopback_addr)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Push(_))) => {},
+        _ => anyhow::bail!("push failed"),
+    };
+    let frame: DemiBuffer = bob.get_test_rig().pop_frame();
+    let (_, eth2_payload) = Ethernet2Header::parse(frame.clone())?;
+    let (ipv4_hdr, _) = Ipv4Header::parse(eth2_payload)?;
+    crate::ensure_eq!(ipv4_hdr.get_src_addr(), Ipv4Addr::LOCALHOST);
+    bob.receive(frame)?;
+    match Future::poll(loopback_pop.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Pop(addr, received_buf))) => {
+            crate::ensure_eq!(addr, Some(sender_addr));
+            crate::ensure_eq!(received_buf[..], buf[..]);
+        },
+        _ => anyhow::bail!("pop should have completed"),
+    };
+    let mut coroutine: Pin<Box<Operation>> = bob.udp_pop(bob_fd)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Pending => {},
+        _ => anyhow::bail!("datagram should not have been delivered to Bob's address"),
+    };
+
+    // Close peers.
+    alice.udp_close(alice_fd)?;
+    bob.udp_close(bob_fd)?;
+    bob.udp_close(loopback_fd)?;
+    bob.udp_close(sender_fd)?;
+
+    Ok(())
+}
+
+/// Tests that a socket that only sends may share its port with the socket that receives on it, when both set
+/// SO_REUSEADDR, and that the datagrams sent to the port are delivered to the receiving socket, which was bound first.
+#[test]
+fn udp_bind_shared_port_send_and_receive() -> Result<()> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    let now: Instant = Instant::now();
+
+    // Setup Alice.
+    let mut alice: SharedEngine = test_helpers::new_alice2(now);
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 80);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+
+    // Setup Bob, with a receiving socket and a sending one on the same port.
+    let mut bob: SharedEngine = test_helpers::new_bob2(now);
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let recv_fd: QDesc = bob.udp_socket()?;
+    bob.udp_set_reuse_addr(recv_fd, true)?;
+    bob.udp_bind(recv_fd, bob_addr)?;
+    let send_fd: QDesc = bob.udp_socket()?;
+    bob.udp_set_reuse_addr(send_fd, true)?;
+    bob.udp_bind(send_fd, bob_addr)?;
+
+    // A socket that does not set SO_REUSEADDR may not join them.
+    let other_fd: QDesc = bob.udp_socket()?;
+    match bob.udp_bind(other_fd, bob_addr) {
+        Err(e) if e.errno == EADDRINUSE => {},
+        _ => anyhow::bail!("bind should have failed"),
+    };
+
+    // The sending socket sends from the shared port.
+    let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 32][..]).expect("slice should fit in DemiBuffer");
+    let mut coroutine: Pin<Box<Operation>> = bob.udp_pushto(send_fd, buf.clone(), alice_addr)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Push(_))) => {},
+        _ => anyhow::bail!("push failed"),
+    };
+    alice.receive(bob.get_test_rig().pop_frame())?;
+    let mut coroutine: Pin<Box<Operation>> = alice.udp_pop(alice_fd)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Pop(addr, received_buf))) => {
+            crate::ensure_eq!(addr, Some(bob_addr));
+            crate::ensure_eq!(received_buf[..], buf[..]);
+        },
+        _ => anyhow::bail!("pop should have completed"),
+    };
+
+    // The reply is delivered to the receiving socket only.
+    let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0xa5; 16][..]).expect("slice should fit in DemiBuffer");
+    let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf.clone(), bob_addr)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Push(_))) => {},
+        _ => anyhow::bail!("push failed"),
+    };
+    bob.receive(alice.get_test_rig().pop_frame())?;
+    let mut send_pop: Pin<Box<Operation>> = bob.udp_pop(send_fd)?;
+    match Future::poll(send_pop.as_mut(), &mut ctx) {
+        Poll::Pending => {},
+        _ => anyhow::bail!("datagram should not have been delivered to the sending socket"),
+    };
+    let mut coroutine: Pin<Box<Operation>> = bob.udp_pop(recv_fd)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Pop(addr, received_buf))) => {
+            crate::ensure_eq!(addr, Some(alice_addr));
+            crate::ensure_eq!(received_buf[..], buf[..]);
+        },
+        _ => anyhow::bail!("pop should have completed"),
+    };
+
+    // Once the receiving socket is closed, the sending one takes over.
+    bob.udp_close(recv_fd)?;
+    let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf.clone(), bob_addr)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Push(_))) => {},
+        _ => anyhow::bail!("push failed"),
+    };
+    bob.receive(alice.get_test_rig().pop_frame())?;
+    match Future::poll(send_pop.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Pop(addr, received_buf))) => {
+            crate::ensure_eq!(addr, Some(alice_addr));
+            crate::ensure_eq!(received_buf[..], buf[..]);
+        },
+        _ => anyhow::bail!("pop should have completed"),
+    };
+
+    // Close peers.
+    alice.udp_close(alice_fd)?;
+    bob.udp_close(send_fd)?;
+    bob.udp_close(other_fd)?;
+
+    Ok(())
+}
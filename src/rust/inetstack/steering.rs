@@ -0,0 +1,568 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::protocols::{
+        ethernet2::{
+            EtherType2,
+            ETHERNET2_HEADER_SIZE,
+        },
+        ip::IpProtocol,
+    },
+    runtime::{
+        fail::Fail,
+        memory::DemiBuffer,
+        network::{
+            consts::RECEIVE_BATCH_SIZE,
+            NetworkRuntime,
+            PacketBuf,
+        },
+        SharedBox,
+        SharedObject,
+    },
+};
+use ::arrayvec::ArrayVec;
+use ::std::{
+    cmp::min,
+    collections::{
+        HashMap,
+        VecDeque,
+    },
+    net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    },
+    ops::{
+        Deref,
+        DerefMut,
+    },
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Offset of the EtherType field in an Ethernet frame.
+const ETHERTYPE_OFFSET: usize = 12;
+
+/// Offset of the flags and fragment offset field in an IPv4 header.
+const IPV4_FRAGMENT_OFFSET: usize = 6;
+
+/// Offset of the protocol field in an IPv4 header.
+const IPV4_PROTOCOL_OFFSET: usize = 9;
+
+/// Offset of the destination address in an IPv4 header. It does not depend on the options of the header.
+const IPV4_DST_ADDR_OFFSET: usize = 16;
+
+/// Offset of the destination port in both TCP and UDP headers.
+const DST_PORT_OFFSET: usize = 2;
+
+/// Maximum number of frames that are held for an instance that does not receive them. Frames that arrive beyond this
+/// limit are dropped, so that an idle instance cannot exhaust memory.
+const MAX_QUEUED_FRAMES: usize = 1024;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Demultiplexes the frames received by a NIC that is shared by several inetstack instances. Each instance is handed
+/// its own [FlowSteeringTransport], through which it registers the local flows, that is, the (local IP, port) pairs, of
+/// its sockets. Incoming TCP and UDP packets are delivered to the instance that owns their destination flow, and
+/// dropped when no instance does. Frames that do not belong to any flow, such as ARP requests and ICMP messages, are
+/// delivered to every instance, which filter them as they would on a dedicated NIC. Transmitted frames go straight to
+/// the NIC.
+pub struct FlowSteering {
+    transport: SharedBox<dyn NetworkRuntime>,
+    /// Instance that owns each local flow, along with how many times it registered the flow.
+    flows: HashMap<SocketAddrV4, (usize, usize)>,
+    /// Frames that were received for each instance and have yet to be handed to it.
+    queues: Vec<VecDeque<DemiBuffer>>,
+}
+
+#[derive(Clone)]
+pub struct SharedFlowSteering(SharedObject<FlowSteering>);
+
+/// Network runtime of a single inetstack instance on a shared NIC.
+#[derive(Clone)]
+pub struct FlowSteeringTransport {
+    steering: SharedFlowSteering,
+    instance: usize,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl SharedFlowSteering {
+    pub fn new(transport: SharedBox<dyn NetworkRuntime>) -> Self {
+        Self(SharedObject::<FlowSteering>::new(FlowSteering {
+            transport,
+            flows: HashMap::new(),
+            queues: Vec::new(),
+        }))
+    }
+
+    /// Attaches a new instance to the shared NIC, and returns the transport that the instance should use.
+    pub fn attach(&mut self) -> FlowSteeringTransport {
+        self.queues.push(VecDeque::new());
+        FlowSteeringTransport {
+            steering: self.clone(),
+            instance: self.queues.len() - 1,
+        }
+    }
+
+    /// Returns the destination flow of `frame`, if it carries a TCP or UDP packet.
+    fn parse_flow(frame: &DemiBuffer) -> Option<SocketAddrV4> {
+        let ipv4_hdr: usize = ETHERNET2_HEADER_SIZE;
+        if frame.len() < ipv4_hdr + IPV4_DST_ADDR_OFFSET + 4 {
+            return None;
+        }
+        let ether_type: u16 = u16::from_be_bytes([frame[ETHERTYPE_OFFSET], frame[ETHERTYPE_OFFSET + 1]]);
+        if ether_type != EtherType2::Ipv4 as u16 {
+            return None;
+        }
+        let protocol: u8 = frame[ipv4_hdr + IPV4_PROTOCOL_OFFSET];
+        if protocol != IpProtocol::TCP as u8 && protocol != IpProtocol::UDP as u8 {
+            return None;
+        }
+        // Only the first fragment of a datagram carries the transport header.
+        let fragment_offset: u16 = u16::from_be_bytes([
+            frame[ipv4_hdr + IPV4_FRAGMENT_OFFSET],
+            frame[ipv4_hdr + IPV4_FRAGMENT_OFFSET + 1],
+        ]) & 0x1fff;
+        if fragment_offset != 0 {
+            return None;
+        }
+        let transport_hdr: usize = ipv4_hdr + ((frame[ipv4_hdr] & 0x0f) as usize) * 4;
+        if frame.len() < transport_hdr + DST_PORT_OFFSET + 2 {
+            return None;
+        }
+        let dst_addr: Ipv4Addr = Ipv4Addr::new(
+            frame[ipv4_hdr + IPV4_DST_ADDR_OFFSET],
+            frame[ipv4_hdr + IPV4_DST_ADDR_OFFSET + 1],
+            frame[ipv4_hdr + IPV4_DST_ADDR_OFFSET + 2],
+            frame[ipv4_hdr + IPV4_DST_ADDR_OFFSET + 3],
+        );
+        let dst_port: u16 = u16::from_be_bytes([
+            frame[transport_hdr + DST_PORT_OFFSET],
+            frame[transport_hdr + DST_PORT_OFFSET + 1],
+        ]);
+        Some(SocketAddrV4::new(dst_addr, dst_port))
+    }
+
+    /// Receives a batch of frames from the NIC, and queues each of them for the instances that it is destined to.
+    fn steer(&mut self) {
+        for frame in self.transport.receive() {
+            match Self::parse_flow(&frame) {
+                Some(flow) => match self.lookup(flow) {
+                    Some(instance) => self.enqueue(instance, frame),
+                    None => debug!("steer(): dropping frame for unknown flow (flow={:?})", flow),
+                },
+                None => {
+                    for instance in 0..self.queues.len() {
+                        self.enqueue(instance, frame.clone());
+                    }
+                },
+            }
+        }
+    }
+
+    /// Returns the instance that owns `flow`, either directly or through a socket bound to the wildcard address.
+    fn lookup(&self, flow: SocketAddrV4) -> Option<usize> {
+        self.flows
+            .get(&flow)
+            .or_else(|| self.flows.get(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, flow.port())))
+            .map(|(instance, _)| *instance)
+    }
+
+    /// Queues `frame` for `instance`.
+    fn enqueue(&mut self, instance: usize, frame: DemiBuffer) {
+        let queue: &mut VecDeque<DemiBuffer> = &mut self.queues[instance];
+        if queue.len() >= MAX_QUEUED_FRAMES {
+            warn!("enqueue(): dropping frame for idle instance (instance={:?})", instance);
+            return;
+        }
+        queue.push_back(frame);
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl NetworkRuntime for FlowSteeringTransport {
+    fn transmit(&mut self, pkt: Box<dyn PacketBuf>) {
+        self.steering.transport.transmit(pkt)
+    }
+
+    fn receive(&mut self) -> ArrayVec<DemiBuffer, RECEIVE_BATCH_SIZE> {
+        // Frames received from the NIC may be destined to other instances, which get them the next time they receive.
+        self.steering.steer();
+        let queue: &mut VecDeque<DemiBuffer> = &mut self.steering.queues[self.instance];
+        let batch_size: usize = min(queue.len(), RECEIVE_BATCH_SIZE);
+        queue.drain(..batch_size).collect()
+    }
+
+    /// Registers the local flow `local` as owned by the target instance, so that packets destined to it are delivered
+    /// to the instance. The stack registers the local endpoint of each of its sockets, and a flow stays registered
+    /// until all of them are closed. Fails if another instance already owns the flow.
+    fn register_flow(&mut self, local: SocketAddrV4) -> Result<(), Fail> {
+        let instance: usize = self.instance;
+        match self.steering.flows.get_mut(&local) {
+            Some((owner, _)) if *owner != instance => {
+                let cause: String = format!("flow is owned by another instance (local={:?})", local);
+                error!("register_flow(): {}", cause);
+                Err(Fail::new(libc::EADDRINUSE, &cause))
+            },
+            Some((_, count)) => {
+                *count += 1;
+                Ok(())
+            },
+            None => {
+                self.steering.flows.insert(local, (instance, 1));
+                Ok(())
+            },
+        }
+    }
+
+    /// Unregisters the local flow `local`, which the target instance should own. Packets destined to it are dropped
+    /// once it is unregistered as many times as it was registered.
+    fn unregister_flow(&mut self, local: SocketAddrV4) -> Result<(), Fail> {
+        let instance: usize = self.instance;
+        match self.steering.flows.get_mut(&local) {
+            Some((owner, count)) if *owner == instance => {
+                *count -= 1;
+                if *count == 0 {
+                    self.steering.flows.remove(&local);
+                }
+                Ok(())
+            },
+            _ => {
+                let cause: String = format!("flow is not owned by this instance (local={:?})", local);
+                error!("unregister_flow(): {}", cause);
+                Err(Fail::new(libc::ENOENT, &cause))
+            },
+        }
+    }
+
+    fn get_ifindex(&self) -> u32 {
+        self.steering.transport.get_ifindex()
+    }
+}
+
+impl Deref for SharedFlowSteering {
+    type Target = FlowSteering;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.deref()
+    }
+}
+
+impl DerefMut for SharedFlowSteering {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0.deref_mut()
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        FlowSteeringTransport,
+        SharedFlowSteering,
+    };
+    use crate::{
+        inetstack::{
+            protocols::ethernet2::{
+                EtherType2,
+                Ethernet2Header,
+            },
+            test_helpers::{
+                self,
+                SharedEngine,
+                SharedTestRuntime,
+            },
+            SharedInetStack,
+        },
+        pal::constants::{
+            AF_INET_VALUE,
+            SOCK_DGRAM,
+            SOCK_STREAM,
+        },
+        runtime::{
+            memory::DemiBuffer,
+            network::{
+                config::{
+                    ArpConfig,
+                    Ethernet2Config,
+                    PcapConfig,
+                    RoutingConfig,
+                    TcpConfig,
+                    UdpConfig,
+                },
+                unwrap_socketaddr,
+                NetworkRuntime,
+            },
+            scheduler::TaskHandle,
+            Operation,
+            OperationResult,
+            QDesc,
+            QToken,
+            SharedBox,
+            SharedDemiRuntime,
+        },
+    };
+    use ::anyhow::Result;
+    use ::futures::task::noop_waker_ref;
+    use ::std::{
+        future::Future,
+        net::{
+            SocketAddr,
+            SocketAddrV4,
+        },
+        pin::Pin,
+        task::{
+            Context,
+            Poll,
+        },
+        time::Instant,
+    };
+
+    /// Maximum number of times that the scheduler is polled while waiting for an operation.
+    const MAX_POLLS: usize = 100;
+
+    /// Port of the flow owned by the first instance.
+    const FIRST_PORT: u16 = 80;
+
+    /// Port of the flow owned by the second instance.
+    const SECOND_PORT: u16 = 81;
+
+    /// Port that no instance owns.
+    const UNKNOWN_PORT: u16 = 82;
+
+    /// Builds a shared NIC with Bob's addresses, and attaches two instances to it.
+    fn new_shared_nic() -> (SharedTestRuntime, FlowSteeringTransport, FlowSteeringTransport) {
+        let nic: SharedTestRuntime = SharedTestRuntime::new(
+            Instant::now(),
+            ArpConfig::default(),
+            UdpConfig::default(),
+            TcpConfig::default(),
+            test_helpers::BOB_MAC,
+            test_helpers::BOB_IPV4,
+        );
+        let mut steering: SharedFlowSteering =
+            SharedFlowSteering::new(SharedBox::<dyn NetworkRuntime>::new(Box::new(nic.clone())));
+        let first: FlowSteeringTransport = steering.attach();
+        let second: FlowSteeringTransport = steering.attach();
+        (nic, first, second)
+    }
+
+    /// Has Alice send a UDP datagram carrying `port` to Bob's `port`, and returns the frame that went on the wire.
+    fn new_udp_frame(alice: &mut SharedEngine, alice_qd: QDesc, port: u16) -> Result<DemiBuffer> {
+        let mut ctx: Context = Context::from_waker(noop_waker_ref());
+        let buf: DemiBuffer = DemiBuffer::from_slice(&port.to_be_bytes())?;
+        let to: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, port);
+        let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_qd, buf, to)?;
+        match Future::poll(coroutine.as_mut(), &mut ctx) {
+            Poll::Ready((_, OperationResult::Push(_))) => (),
+            _ => anyhow::bail!("push should have succeeded"),
+        }
+        alice.get_test_rig().poll_scheduler();
+        Ok(alice.get_test_rig().pop_frame())
+    }
+
+    /// Builds a broadcast ARP frame. Its payload does not matter, as it is not parsed by the steering layer.
+    fn new_arp_frame() -> Result<DemiBuffer> {
+        let mut frame: Vec<u8> = vec![0; 60];
+        Ethernet2Header::new(test_helpers::BOB_MAC, test_helpers::ALICE_MAC, EtherType2::Arp).serialize(&mut frame);
+        Ok(DemiBuffer::from_slice(&frame)?)
+    }
+
+    /// Receives every frame destined to `transport`. As the NIC hands out one frame at a time, and frames for other
+    /// instances are set aside, this keeps receiving even after an empty batch.
+    fn receive_all(transport: &mut FlowSteeringTransport) -> Vec<DemiBuffer> {
+        let mut frames: Vec<DemiBuffer> = Vec::new();
+        for _ in 0..MAX_POLLS {
+            frames.extend(transport.receive());
+        }
+        frames
+    }
+
+    /// Builds a stack with Bob's addresses on the shared NIC, which receives through `transport`.
+    fn new_stack(nic: &SharedTestRuntime, transport: &FlowSteeringTransport) -> Result<SharedInetStack> {
+        Ok(SharedInetStack::new(
+            nic.get_runtime(),
+            SharedBox::<dyn NetworkRuntime>::new(Box::new(transport.clone())),
+            test_helpers::BOB_MAC,
+            test_helpers::BOB_IPV4,
+            UdpConfig::default(),
+            TcpConfig::default(),
+            [0; 32],
+            ArpConfig::default(),
+            PcapConfig::default(),
+            Ethernet2Config::default(),
+            RoutingConfig::default(),
+        )?)
+    }
+
+    /// Polls `nic` until the operation `qt` of `stack` completes, and returns its result.
+    fn wait(stack: &mut SharedInetStack, nic: &mut SharedTestRuntime, qt: QToken) -> Result<OperationResult> {
+        let runtime: SharedDemiRuntime = nic.get_runtime();
+        let handle: TaskHandle = runtime.from_task_id(qt)?;
+        for _ in 0..MAX_POLLS {
+            if handle.has_completed() {
+                return Ok(stack.take_operation(handle).1);
+            }
+            nic.poll_scheduler();
+        }
+        anyhow::bail!("operation should have completed")
+    }
+
+    /// Polls `nic` until the operation `qt` of `stack` completes, while relaying the frames that go on the wire
+    /// between the NIC and Alice, and returns its result.
+    fn relay(
+        stack: &mut SharedInetStack,
+        nic: &mut SharedTestRuntime,
+        alice: &mut SharedEngine,
+        qt: QToken,
+    ) -> Result<OperationResult> {
+        let runtime: SharedDemiRuntime = nic.get_runtime();
+        let handle: TaskHandle = runtime.from_task_id(qt)?;
+        for _ in 0..MAX_POLLS {
+            if handle.has_completed() {
+                return Ok(stack.take_operation(handle).1);
+            }
+            nic.poll_scheduler();
+            for frame in nic.pop_all_frames() {
+                alice.receive(frame)?;
+            }
+            for frame in alice.get_test_rig().pop_all_frames() {
+                nic.push_frame(frame);
+            }
+        }
+        anyhow::bail!("operation should have completed")
+    }
+
+    /// Tests that frames are delivered to the instance that owns their flow, that frames of unknown flows are dropped,
+    /// and that frames that do not belong to any flow are delivered to every instance.
+    #[test]
+    fn test_flow_steering_demultiplex() -> Result<()> {
+        let (mut nic, mut first, mut second) = new_shared_nic();
+        first.register_flow(SocketAddrV4::new(test_helpers::BOB_IPV4, FIRST_PORT))?;
+        second.register_flow(SocketAddrV4::new(test_helpers::BOB_IPV4, SECOND_PORT))?;
+
+        // A flow cannot be owned by two instances.
+        match second.register_flow(SocketAddrV4::new(test_helpers::BOB_IPV4, FIRST_PORT)) {
+            Err(e) if e.errno == libc::EADDRINUSE => (),
+            result => anyhow::bail!("register should have failed: {:?}", result),
+        }
+
+        let mut alice: SharedEngine = test_helpers::new_alice2(Instant::now());
+        let alice_qd: QDesc = alice.udp_socket()?;
+        alice.udp_bind(alice_qd, SocketAddrV4::new(test_helpers::ALICE_IPV4, 49152))?;
+        for port in [FIRST_PORT, SECOND_PORT, UNKNOWN_PORT] {
+            let frame: DemiBuffer = new_udp_frame(&mut alice, alice_qd, port)?;
+            nic.push_frame(frame);
+        }
+        nic.push_frame(new_arp_frame()?);
+
+        let first_frames: Vec<DemiBuffer> = receive_all(&mut first);
+        let second_frames: Vec<DemiBuffer> = receive_all(&mut second);
+        crate::ensure_eq!(first_frames.len(), 2);
+        crate::ensure_eq!(second_frames.len(), 2);
+        crate::ensure_eq!(
+            SharedFlowSteering::parse_flow(&first_frames[0]),
+            Some(SocketAddrV4::new(test_helpers::BOB_IPV4, FIRST_PORT))
+        );
+        crate::ensure_eq!(
+            SharedFlowSteering::parse_flow(&second_frames[0]),
+            Some(SocketAddrV4::new(test_helpers::BOB_IPV4, SECOND_PORT))
+        );
+        crate::ensure_eq!(SharedFlowSteering::parse_flow(&first_frames[1]), None);
+        crate::ensure_eq!(SharedFlowSteering::parse_flow(&second_frames[1]), None);
+
+        // Once unregistered, the flow is no longer delivered.
+        first.unregister_flow(SocketAddrV4::new(test_helpers::BOB_IPV4, FIRST_PORT))?;
+        let frame: DemiBuffer = new_udp_frame(&mut alice, alice_qd, FIRST_PORT)?;
+        nic.push_frame(frame);
+        crate::ensure_eq!(receive_all(&mut first).len(), 0);
+        crate::ensure_eq!(receive_all(&mut second).len(), 0);
+
+        Ok(())
+    }
+
+    /// Tests that two inetstack instances on one NIC each receive only the datagrams destined to the sockets that they
+    /// bound, until they close them.
+    #[test]
+    fn test_flow_steering_two_instances() -> Result<()> {
+        let (mut nic, first, second) = new_shared_nic();
+        let mut stacks: Vec<(SharedInetStack, QDesc)> = Vec::new();
+        for (transport, port) in [(&first, FIRST_PORT), (&second, SECOND_PORT)] {
+            let local: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, port);
+            let mut stack: SharedInetStack = new_stack(&nic, transport)?;
+            let qd: QDesc = stack.socket(AF_INET_VALUE, SOCK_DGRAM, 0)?;
+            stack.bind(qd, SocketAddr::V4(local))?;
+            stacks.push((stack, qd));
+        }
+
+        let mut alice: SharedEngine = test_helpers::new_alice2(Instant::now());
+        let alice_qd: QDesc = alice.udp_socket()?;
+        alice.udp_bind(alice_qd, SocketAddrV4::new(test_helpers::ALICE_IPV4, 49152))?;
+        for port in [UNKNOWN_PORT, SECOND_PORT, FIRST_PORT] {
+            let frame: DemiBuffer = new_udp_frame(&mut alice, alice_qd, port)?;
+            nic.push_frame(frame);
+        }
+
+        // Each instance gets the datagram for its own port, regardless of which one drains the NIC first.
+        for ((stack, qd), port) in stacks.iter_mut().zip([FIRST_PORT, SECOND_PORT]) {
+            let pop_qt: QToken = stack.pop(*qd, None)?;
+            match wait(stack, &mut nic, pop_qt)? {
+                OperationResult::Pop(_, buf) => crate::ensure_eq!(&buf[..], &port.to_be_bytes()[..]),
+                result => anyhow::bail!("pop should have returned data: {:?}", result),
+            }
+        }
+
+        // Closing the sockets releases their flows.
+        for (stack, qd) in stacks.iter_mut() {
+            let close_qt: QToken = stack.async_close(*qd)?;
+            match wait(stack, &mut nic, close_qt)? {
+                OperationResult::Close => (),
+                result => anyhow::bail!("close should have succeeded: {:?}", result),
+            }
+        }
+        crate::ensure_eq!(first.steering.flows.is_empty(), true);
+
+        Ok(())
+    }
+
+    /// Tests that a client on the shared NIC connects to Alice, which requires its stack to claim the ephemeral local
+    /// address of the connection, so that the SYN+ACK of Alice is steered back to it.
+    #[test]
+    fn test_flow_steering_connect() -> Result<()> {
+        let (mut nic, first, _second) = new_shared_nic();
+        let mut stack: SharedInetStack = new_stack(&nic, &first)?;
+        let mut alice: SharedEngine = test_helpers::new_alice2(Instant::now());
+        let remote: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, FIRST_PORT);
+        let listen_qd: QDesc = alice.tcp_socket()?;
+        alice.tcp_bind(listen_qd, remote)?;
+        alice.tcp_listen(listen_qd, 1)?;
+
+        let qd: QDesc = stack.socket(AF_INET_VALUE, SOCK_STREAM, 0)?;
+        let connect_qt: QToken = stack.connect(qd, SocketAddr::V4(remote))?;
+        let local: SocketAddrV4 = unwrap_socketaddr(stack.getsockname(qd)?)?;
+        crate::ensure_eq!(first.steering.flows.get(&local), Some(&(0, 1)));
+        match relay(&mut stack, &mut nic, &mut alice, connect_qt)? {
+            OperationResult::Connect => (),
+            result => anyhow::bail!("connect should have succeeded: {:?}", result),
+        }
+
+        Ok(())
+    }
+}
@@ -9,10 +9,34 @@ use crate::{
                 EtherType2,
                 Ethernet2Header,
             },
+            ipv4::{
+                egress::{
+                    Ipv4EgressHook,
+                    SharedEgressTransport,
+                },
+                ingress::Ipv4IngressHook,
+            },
+            raw::SharedRawPeer,
+            tcp::{
+                isn_generator::{
+                    DeterministicIsnGenerator,
+                    IsnGenerator,
+                },
+                trace::TcpTraceHook,
+                AcceptFilter,
+                NegotiatedOptions,
+                SynCookieStats,
+                TcpAcceptReadyHook,
+                TcpInfo,
+                TcpStateCounts,
+                UnackedSegmentInfo,
+            },
             udp::SharedUdpPeer,
             Peer,
+            ProtocolHandler,
         },
         ArpConfig,
+        RoutingConfig,
         TcpConfig,
         UdpConfig,
     },
@@ -20,11 +44,18 @@ use crate::{
         fail::Fail,
         memory::DemiBuffer,
         network::{
-            types::MacAddress,
+            types::{
+                BufferUsage,
+                ErrorCounters,
+                MacAddress,
+                Readiness,
+            },
             NetworkRuntime,
         },
         scheduler::Yielder,
+        types::demi_sgarray_t,
         Operation,
+        OperationResult,
         QDesc,
         QToken,
         SharedBox,
@@ -53,6 +84,7 @@ use super::SharedTestRuntime;
 
 pub struct Engine {
     test_rig: SharedTestRuntime,
+    egress: SharedEgressTransport,
     arp: SharedArpPeer,
     ipv4: Peer,
 }
@@ -62,22 +94,29 @@ pub struct SharedEngine(SharedObject<Engine>);
 
 impl SharedEngine {
     pub fn new(test_rig: SharedTestRuntime) -> Result<Self, Fail> {
+        Self::new_with_routing_config(test_rig, RoutingConfig::default())
+    }
+
+    pub fn new_with_routing_config(test_rig: SharedTestRuntime, routing_config: RoutingConfig) -> Result<Self, Fail> {
         let link_addr: MacAddress = test_rig.get_link_addr();
         let ipv4_addr: Ipv4Addr = test_rig.get_ip_addr();
         let arp_config: ArpConfig = test_rig.get_arp_config();
         let udp_config: UdpConfig = test_rig.get_udp_config();
         let tcp_config: TcpConfig = test_rig.get_tcp_config();
 
-        let boxed_test_rig: SharedBox<dyn NetworkRuntime> = SharedBox::new(Box::new(test_rig.clone()));
+        let egress: SharedEgressTransport =
+            SharedEgressTransport::new(test_rig.get_runtime(), SharedBox::new(Box::new(test_rig.clone())));
+        let boxed_test_rig: SharedBox<dyn NetworkRuntime> = SharedBox::new(Box::new(egress.clone()));
         let arp = SharedArpPeer::new(
             test_rig.get_runtime(),
             boxed_test_rig.clone(),
             link_addr,
             ipv4_addr,
             arp_config,
+            routing_config,
         )?;
         let rng_seed: [u8; 32] = [0; 32];
-        let ipv4 = Peer::new(
+        let mut ipv4 = Peer::new(
             test_rig.get_runtime(),
             boxed_test_rig.clone(),
             link_addr,
@@ -87,11 +126,21 @@ impl SharedEngine {
             arp.clone(),
             rng_seed,
         )?;
-        Ok(Self(SharedObject::<Engine>::new(Engine { test_rig, arp, ipv4 })))
+        // Tests, including the simulator scripts, expect every connection to start at sequence number zero.
+        ipv4.tcp
+            .set_isn_generator(Box::new(DeterministicIsnGenerator::new(0, 0)));
+        Ok(Self(SharedObject::<Engine>::new(Engine {
+            test_rig,
+            egress,
+            arp,
+            ipv4,
+        })))
     }
 
     pub fn advance_clock(&mut self, now: Instant) {
-        self.test_rig.advance_clock(now)
+        self.test_rig.advance_clock(now);
+        // Receive the packets that the ingress hook delayed until now, if any.
+        self.ipv4.poll_ingress();
     }
 
     pub fn receive(&mut self, bytes: DemiBuffer) -> Result<(), Fail> {
@@ -111,15 +160,58 @@ impl SharedEngine {
         Ok(())
     }
 
+    pub fn set_egress_hook(&mut self, hook: Option<Ipv4EgressHook>) {
+        self.egress.set_hook(hook)
+    }
+
+    pub fn set_ingress_hook(&mut self, hook: Option<Ipv4IngressHook>) {
+        self.ipv4.set_ingress_hook(hook)
+    }
+
+    pub fn ipv4_filtered_packets(&self) -> usize {
+        self.ipv4.get_filtered_packets()
+    }
+
+    pub fn ipv4_header_errors(&self) -> usize {
+        self.ipv4.get_header_errors()
+    }
+
+    pub fn register_protocol(&mut self, protocol: u8, handler: Box<dyn ProtocolHandler>) -> Result<(), Fail> {
+        self.ipv4.register_protocol(protocol, handler)
+    }
+
     pub async fn ipv4_ping(&mut self, dest_ipv4_addr: Ipv4Addr, timeout: Option<Duration>) -> Result<Duration, Fail> {
         self.ipv4.ping(dest_ipv4_addr, timeout).await
     }
 
+    pub async fn ipv4_ping_with_payload(
+        &mut self,
+        dest_ipv4_addr: Ipv4Addr,
+        payload: Option<DemiBuffer>,
+        id_seq_num: Option<(u16, u16)>,
+        timeout: Option<Duration>,
+    ) -> Result<(Duration, DemiBuffer), Fail> {
+        self.ipv4
+            .ping_with_payload(dest_ipv4_addr, payload, id_seq_num, timeout)
+            .await
+    }
+
     pub fn udp_pushto(&self, qd: QDesc, buf: DemiBuffer, to: SocketAddrV4) -> Result<Pin<Box<Operation>>, Fail> {
         let mut udp: SharedUdpPeer = self.ipv4.udp.clone();
         udp.pushto(qd, buf, to)
     }
 
+    pub fn udp_pushto_from(
+        &self,
+        qd: QDesc,
+        buf: DemiBuffer,
+        from: Ipv4Addr,
+        to: SocketAddrV4,
+    ) -> Result<Pin<Box<Operation>>, Fail> {
+        let mut udp: SharedUdpPeer = self.ipv4.udp.clone();
+        udp.pushto_from(qd, buf, from, to)
+    }
+
     pub fn udp_pop(&self, qd: QDesc) -> Result<Pin<Box<Operation>>, Fail> {
         let mut udp: SharedUdpPeer = self.ipv4.udp.clone();
         udp.pop(qd, None)
@@ -133,10 +225,84 @@ impl SharedEngine {
         self.ipv4.udp.bind(socket_fd, endpoint)
     }
 
+    pub fn udp_connect(&mut self, socket_fd: QDesc, remote: SocketAddrV4) -> Result<(), Fail> {
+        self.ipv4.udp.connect(socket_fd, remote)
+    }
+
+    pub fn udp_readiness(&self, socket_fd: QDesc) -> Result<Readiness, Fail> {
+        self.ipv4.udp.readiness(socket_fd)
+    }
+
+    pub fn udp_path_mss(&self, socket_fd: QDesc) -> Result<usize, Fail> {
+        self.ipv4.udp.max_payload_size(socket_fd)
+    }
+
+    pub fn udp_set_broadcast(&mut self, socket_fd: QDesc, broadcast: bool) -> Result<(), Fail> {
+        self.ipv4.udp.set_broadcast(socket_fd, broadcast)
+    }
+
+    pub fn udp_set_pktinfo(&mut self, socket_fd: QDesc, pktinfo: bool) -> Result<(), Fail> {
+        self.ipv4.udp.set_pktinfo(socket_fd, pktinfo)
+    }
+
+    pub fn udp_set_dontroute(&mut self, socket_fd: QDesc, dontroute: bool) -> Result<(), Fail> {
+        self.ipv4.udp.set_dontroute(socket_fd, dontroute)
+    }
+
+    pub fn udp_set_reuse_addr(&mut self, socket_fd: QDesc, reuse_addr: bool) -> Result<(), Fail> {
+        self.ipv4.udp.set_reuse_addr(socket_fd, reuse_addr)
+    }
+
+    pub fn udp_buffer_usage(&self, socket_fd: QDesc) -> Result<BufferUsage, Fail> {
+        self.ipv4.udp.buffer_usage(socket_fd)
+    }
+
+    pub fn udp_error_counters(&self, socket_fd: QDesc, reset: bool) -> Result<ErrorCounters, Fail> {
+        self.ipv4.udp.error_counters(socket_fd, reset)
+    }
+
+    pub fn udp_set_recv_buffer_size(&mut self, socket_fd: QDesc, size: usize) -> Result<(), Fail> {
+        self.ipv4.udp.set_recv_buffer_size(socket_fd, size)
+    }
+
+    pub fn udp_recv_dropped(&self, socket_fd: QDesc) -> Result<usize, Fail> {
+        self.ipv4.udp.recv_dropped(socket_fd)
+    }
+
     pub fn udp_close(&mut self, socket_fd: QDesc) -> Result<(), Fail> {
         self.ipv4.udp.close(socket_fd)
     }
 
+    pub fn raw_socket(&mut self, protocol: u8) -> Result<QDesc, Fail> {
+        self.ipv4.raw.socket(protocol)
+    }
+
+    pub fn raw_pushto(&self, qd: QDesc, buf: DemiBuffer, to: Ipv4Addr) -> Result<Pin<Box<Operation>>, Fail> {
+        let mut raw: SharedRawPeer = self.ipv4.raw.clone();
+        raw.pushto(qd, buf, SocketAddrV4::new(to, 0))
+    }
+
+    pub fn raw_pop(&self, qd: QDesc) -> Result<Pin<Box<Operation>>, Fail> {
+        let mut raw: SharedRawPeer = self.ipv4.raw.clone();
+        raw.pop(qd, None)
+    }
+
+    pub fn raw_close(&mut self, socket_fd: QDesc) -> Result<(), Fail> {
+        self.ipv4.raw.close(socket_fd)
+    }
+
+    pub fn tcp_set_trace_hook(&mut self, hook: Option<TcpTraceHook>) {
+        self.ipv4.tcp.set_trace_hook(hook)
+    }
+
+    pub fn tcp_set_isn_generator(&mut self, generator: Box<dyn IsnGenerator>) {
+        self.ipv4.tcp.set_isn_generator(generator)
+    }
+
+    pub fn reseed(&mut self, rng_seed: [u8; 32]) {
+        self.ipv4.reseed(rng_seed)
+    }
+
     pub fn tcp_socket(&mut self) -> Result<QDesc, Fail> {
         self.ipv4.tcp.socket()
     }
@@ -153,14 +319,42 @@ impl SharedEngine {
         self.ipv4.tcp.accept(fd)
     }
 
+    pub fn tcp_try_accept(&mut self, fd: QDesc) -> Result<Option<OperationResult>, Fail> {
+        self.ipv4.tcp.try_accept(fd)
+    }
+
     pub fn tcp_push(&mut self, socket_fd: QDesc, buf: DemiBuffer) -> Result<QToken, Fail> {
         self.ipv4.tcp.push(socket_fd, buf)
     }
 
+    pub fn tcp_try_push(&mut self, socket_fd: QDesc, buf: DemiBuffer) -> Result<Option<OperationResult>, Fail> {
+        self.ipv4.tcp.try_push(socket_fd, buf)
+    }
+
+    pub fn tcp_push_more(&mut self, socket_fd: QDesc, buf: DemiBuffer) -> Result<QToken, Fail> {
+        self.ipv4.tcp.push_more(socket_fd, buf)
+    }
+
+    pub fn tcp_flush(&mut self, socket_fd: QDesc) -> Result<(), Fail> {
+        self.ipv4.tcp.flush(socket_fd)
+    }
+
     pub fn tcp_pop(&mut self, socket_fd: QDesc) -> Result<QToken, Fail> {
         self.ipv4.tcp.pop(socket_fd, None)
     }
 
+    pub fn tcp_try_pop(&mut self, socket_fd: QDesc) -> Result<Option<OperationResult>, Fail> {
+        self.ipv4.tcp.try_pop(socket_fd, None)
+    }
+
+    pub fn tcp_pop_into(&mut self, socket_fd: QDesc, sga: &demi_sgarray_t) -> Result<QToken, Fail> {
+        self.ipv4.tcp.pop_into(socket_fd, sga)
+    }
+
+    pub fn tcp_pop_with_timeout(&mut self, socket_fd: QDesc, size: usize, timeout: Duration) -> Result<QToken, Fail> {
+        self.ipv4.tcp.pop_with_timeout(socket_fd, size, timeout)
+    }
+
     pub fn tcp_async_close(&mut self, socket_fd: QDesc) -> Result<QToken, Fail> {
         self.ipv4.tcp.async_close(socket_fd)
     }
@@ -169,18 +363,131 @@ impl SharedEngine {
         self.ipv4.tcp.listen(socket_fd, backlog)
     }
 
+    pub fn tcp_set_backlog(&mut self, socket_fd: QDesc, backlog: usize) -> Result<(), Fail> {
+        self.ipv4.tcp.set_backlog(socket_fd, backlog)
+    }
+
+    pub fn tcp_set_idle_timeout(&mut self, socket_fd: QDesc, timeout: Option<Duration>) -> Result<(), Fail> {
+        self.ipv4.tcp.set_idle_timeout(socket_fd, timeout)
+    }
+
+    pub fn tcp_set_tx_priority(&mut self, socket_fd: QDesc, priority: u8) -> Result<(), Fail> {
+        self.ipv4.tcp.set_tx_priority(socket_fd, priority)
+    }
+
+    #[cfg(feature = "tcp-md5")]
+    pub fn tcp_set_md5_key(&mut self, socket_fd: QDesc, key: Option<&[u8]>) -> Result<(), Fail> {
+        self.ipv4.tcp.set_md5_key(socket_fd, key)
+    }
+
+    pub fn tcp_set_nodelay(&mut self, socket_fd: QDesc, nodelay: bool) -> Result<(), Fail> {
+        self.ipv4.tcp.set_nodelay(socket_fd, nodelay)
+    }
+
+    pub fn tcp_get_nodelay(&self, socket_fd: QDesc) -> Result<bool, Fail> {
+        self.ipv4.tcp.get_nodelay(socket_fd)
+    }
+
+    pub fn tcp_set_oob_inline(&mut self, socket_fd: QDesc, oob_inline: bool) -> Result<(), Fail> {
+        self.ipv4.tcp.set_oob_inline(socket_fd, oob_inline)
+    }
+
+    pub fn tcp_set_discard_late_data(&mut self, socket_fd: QDesc, discard: bool) -> Result<(), Fail> {
+        self.ipv4.tcp.set_discard_late_data(socket_fd, discard)
+    }
+
+    pub fn tcp_recv_oob(&mut self, socket_fd: QDesc) -> Result<u8, Fail> {
+        self.ipv4.tcp.recv_oob(socket_fd)
+    }
+
+    pub fn tcp_set_send_buffer_size(&mut self, socket_fd: QDesc, size: usize) -> Result<(), Fail> {
+        self.ipv4.tcp.set_send_buffer_size(socket_fd, size)
+    }
+
+    pub fn tcp_set_rcvlowat(&mut self, socket_fd: QDesc, low_watermark: usize) -> Result<(), Fail> {
+        self.ipv4.tcp.set_rcvlowat(socket_fd, low_watermark)
+    }
+
+    pub fn tcp_negotiated_options(&self, socket_fd: QDesc) -> Result<NegotiatedOptions, Fail> {
+        self.ipv4.tcp.tcp_negotiated_options(socket_fd)
+    }
+
+    pub fn tcp_info(&self, socket_fd: QDesc) -> Result<TcpInfo, Fail> {
+        self.ipv4.tcp.tcp_info(socket_fd)
+    }
+
+    pub fn tcp_outstanding_segments(&self, socket_fd: QDesc) -> Result<Vec<UnackedSegmentInfo>, Fail> {
+        self.ipv4.tcp.outstanding_segments(socket_fd)
+    }
+
+    pub fn tcp_buffer_usage(&self, socket_fd: QDesc) -> Result<BufferUsage, Fail> {
+        self.ipv4.tcp.buffer_usage(socket_fd)
+    }
+
+    pub fn tcp_error_counters(&self, socket_fd: QDesc, reset: bool) -> Result<ErrorCounters, Fail> {
+        self.ipv4.tcp.error_counters(socket_fd, reset)
+    }
+
+    pub fn tcp_link_addrs(&self, socket_fd: QDesc) -> Result<(MacAddress, MacAddress), Fail> {
+        self.ipv4.tcp.link_addrs(socket_fd)
+    }
+
+    pub fn tcp_readiness(&self, socket_fd: QDesc) -> Result<Readiness, Fail> {
+        self.ipv4.tcp.readiness(socket_fd)
+    }
+
+    pub fn tcp_take_socket_error(&mut self, socket_fd: QDesc) -> Result<Option<Fail>, Fail> {
+        self.ipv4.tcp.take_socket_error(socket_fd)
+    }
+
+    pub fn tcp_set_accept_ready_hook(
+        &mut self,
+        socket_fd: QDesc,
+        hook: Option<TcpAcceptReadyHook>,
+    ) -> Result<(), Fail> {
+        self.ipv4.tcp.set_accept_ready_hook(socket_fd, hook)
+    }
+
+    pub fn tcp_set_accept_filter(&mut self, socket_fd: QDesc, filter: Option<AcceptFilter>) -> Result<(), Fail> {
+        self.ipv4.tcp.set_accept_filter(socket_fd, filter)
+    }
+
+    pub fn tcp_syn_cookie_stats(&self, socket_fd: QDesc) -> Result<SynCookieStats, Fail> {
+        self.ipv4.tcp.syn_cookie_stats(socket_fd)
+    }
+
+    pub fn tcp_state_counts(&self) -> TcpStateCounts {
+        self.ipv4.tcp.state_counts()
+    }
+
     pub async fn arp_query(&mut self, ipv4_addr: Ipv4Addr) -> Result<MacAddress, Fail> {
         self.arp.query(ipv4_addr, &Yielder::new()).await
     }
 
+    pub async fn arp_resolve(&mut self, ipv4_addr: Ipv4Addr) -> Result<MacAddress, Fail> {
+        self.arp.resolve(ipv4_addr, &Yielder::new()).await
+    }
+
     pub fn tcp_mss(&self, handle: QDesc) -> Result<usize, Fail> {
         self.ipv4.tcp_mss(handle)
     }
 
+    pub fn tcp_path_mss(&self, handle: QDesc) -> Result<usize, Fail> {
+        self.ipv4.tcp_path_mss(handle)
+    }
+
     pub fn tcp_rto(&self, handle: QDesc) -> Result<Duration, Fail> {
         self.ipv4.tcp_rto(handle)
     }
 
+    pub fn tcp_send_window(&self, handle: QDesc) -> Result<u32, Fail> {
+        self.ipv4.tcp_send_window(handle)
+    }
+
+    pub fn tcp_set_receive_window(&mut self, handle: QDesc, window_size: u32) -> Result<(), Fail> {
+        self.ipv4.tcp_set_receive_window(handle, window_size)
+    }
+
     pub fn export_arp_cache(&self) -> HashMap<Ipv4Addr, MacAddress> {
         self.arp.export_cache()
     }
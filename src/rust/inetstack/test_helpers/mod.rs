@@ -36,6 +36,7 @@ pub const BOB_MAC: MacAddress = MacAddress::new([0xab, 0x89, 0x67, 0x45, 0x23, 0
 pub const BOB_IPV4: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 2);
 pub const CARRIE_MAC: MacAddress = MacAddress::new([0xef, 0xcd, 0xab, 0x89, 0x67, 0x45]);
 pub const CARRIE_IPV4: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 3);
+pub const INTERFACE_INDEX: u32 = 2;
 
 //==============================================================================
 // Standalone Functions
@@ -71,6 +72,10 @@ pub fn new_bob(now: Instant) -> SharedEngine {
 }
 
 pub fn new_alice2(now: Instant) -> SharedEngine {
+    new_alice2_with_tcp_config(now, TcpConfig::default())
+}
+
+pub fn new_alice2_with_tcp_config(now: Instant, tcp_config: TcpConfig) -> SharedEngine {
     let mut arp: HashMap<Ipv4Addr, MacAddress> = HashMap::<Ipv4Addr, MacAddress>::new();
     arp.insert(ALICE_IPV4, ALICE_MAC);
     arp.insert(BOB_IPV4, BOB_MAC);
@@ -82,12 +87,23 @@ pub fn new_alice2(now: Instant) -> SharedEngine {
         Some(false),
     );
     let udp_config = UdpConfig::default();
-    let tcp_config = TcpConfig::default();
     let test_rig = SharedTestRuntime::new(now, arp_config, udp_config, tcp_config, ALICE_MAC, ALICE_IPV4);
     SharedEngine::new(test_rig).unwrap()
 }
 
 pub fn new_bob2(now: Instant) -> SharedEngine {
+    new_bob2_with_tcp_config(now, TcpConfig::default())
+}
+
+pub fn new_bob2_with_tcp_config(now: Instant, tcp_config: TcpConfig) -> SharedEngine {
+    new_bob2_with_configs(now, UdpConfig::default(), tcp_config)
+}
+
+pub fn new_bob2_with_udp_config(now: Instant, udp_config: UdpConfig) -> SharedEngine {
+    new_bob2_with_configs(now, udp_config, TcpConfig::default())
+}
+
+fn new_bob2_with_configs(now: Instant, udp_config: UdpConfig, tcp_config: TcpConfig) -> SharedEngine {
     let mut arp: HashMap<Ipv4Addr, MacAddress> = HashMap::<Ipv4Addr, MacAddress>::new();
     arp.insert(BOB_IPV4, BOB_MAC);
     arp.insert(ALICE_IPV4, ALICE_MAC);
@@ -98,8 +114,6 @@ pub fn new_bob2(now: Instant) -> SharedEngine {
         Some(arp),
         Some(false),
     );
-    let udp_config = UdpConfig::default();
-    let tcp_config = TcpConfig::default();
     let test_rig = SharedTestRuntime::new(now, arp_config, udp_config, tcp_config, BOB_MAC, BOB_IPV4);
     SharedEngine::new(test_rig).unwrap()
 }
@@ -71,7 +71,7 @@ impl SharedTestRuntime {
             ipv4_addr,
             incoming: VecDeque::new(),
             outgoing: VecDeque::new(),
-            runtime: SharedDemiRuntime::new(now),
+            runtime: SharedDemiRuntime::new_with_virtual_clock(now),
             arp_config,
             udp_config,
             tcp_config,
@@ -177,6 +177,10 @@ impl NetworkRuntime for SharedTestRuntime {
         }
         out
     }
+
+    fn get_ifindex(&self) -> u32 {
+        super::INTERFACE_INDEX
+    }
 }
 
 //======================================================================================================================
@@ -23,9 +23,48 @@ pub const SOCK_STREAM: i32 = WinSock::SOCK_STREAM.0 as i32;
 #[cfg(target_os = "windows")]
 pub const SOCK_DGRAM: i32 = WinSock::SOCK_DGRAM.0 as i32;
 
+#[cfg(target_os = "windows")]
+pub const SOCK_RAW: i32 = WinSock::SOCK_RAW.0 as i32;
+
 #[cfg(target_os = "windows")]
 pub const SOMAXCONN: i32 = WinSock::SOMAXCONN as i32;
 
+#[cfg(target_os = "windows")]
+pub const SOL_SOCKET: i32 = WinSock::SOL_SOCKET as i32;
+
+#[cfg(target_os = "windows")]
+pub const SO_BROADCAST: i32 = WinSock::SO_BROADCAST as i32;
+
+#[cfg(target_os = "windows")]
+pub const SO_RCVLOWAT: i32 = WinSock::SO_RCVLOWAT as i32;
+
+#[cfg(target_os = "windows")]
+pub const SO_RCVBUF: i32 = WinSock::SO_RCVBUF as i32;
+
+#[cfg(target_os = "windows")]
+pub const SO_SNDBUF: i32 = WinSock::SO_SNDBUF as i32;
+
+#[cfg(target_os = "windows")]
+pub const SO_ACCEPTCONN: i32 = WinSock::SO_ACCEPTCONN as i32;
+
+#[cfg(target_os = "windows")]
+pub const SO_DONTROUTE: i32 = WinSock::SO_DONTROUTE as i32;
+
+#[cfg(target_os = "windows")]
+pub const SO_REUSEADDR: i32 = WinSock::SO_REUSEADDR as i32;
+
+#[cfg(target_os = "windows")]
+pub const IPPROTO_IP: i32 = WinSock::IPPROTO_IP.0 as i32;
+
+#[cfg(target_os = "windows")]
+pub const IP_PKTINFO: i32 = WinSock::IP_PKTINFO as i32;
+
+#[cfg(target_os = "windows")]
+pub const IPPROTO_TCP: i32 = WinSock::IPPROTO_TCP.0 as i32;
+
+#[cfg(target_os = "windows")]
+pub const TCP_NODELAY: i32 = WinSock::TCP_NODELAY as i32;
+
 //==============================================================================
 // Linux constants
 //==============================================================================
@@ -45,5 +84,44 @@ pub const SOCK_STREAM: i32 = libc::SOCK_STREAM;
 #[cfg(target_os = "linux")]
 pub const SOCK_DGRAM: i32 = libc::SOCK_DGRAM;
 
+#[cfg(target_os = "linux")]
+pub const SOCK_RAW: i32 = libc::SOCK_RAW;
+
 #[cfg(target_os = "linux")]
 pub const SOMAXCONN: i32 = libc::SOMAXCONN;
+
+#[cfg(target_os = "linux")]
+pub const SOL_SOCKET: i32 = libc::SOL_SOCKET;
+
+#[cfg(target_os = "linux")]
+pub const SO_BROADCAST: i32 = libc::SO_BROADCAST;
+
+#[cfg(target_os = "linux")]
+pub const SO_RCVLOWAT: i32 = libc::SO_RCVLOWAT;
+
+#[cfg(target_os = "linux")]
+pub const SO_RCVBUF: i32 = libc::SO_RCVBUF;
+
+#[cfg(target_os = "linux")]
+pub const SO_SNDBUF: i32 = libc::SO_SNDBUF;
+
+#[cfg(target_os = "linux")]
+pub const SO_ACCEPTCONN: i32 = libc::SO_ACCEPTCONN;
+
+#[cfg(target_os = "linux")]
+pub const SO_DONTROUTE: i32 = libc::SO_DONTROUTE;
+
+#[cfg(target_os = "linux")]
+pub const SO_REUSEADDR: i32 = libc::SO_REUSEADDR;
+
+#[cfg(target_os = "linux")]
+pub const IPPROTO_IP: i32 = libc::IPPROTO_IP;
+
+#[cfg(target_os = "linux")]
+pub const IP_PKTINFO: i32 = libc::IP_PKTINFO;
+
+#[cfg(target_os = "linux")]
+pub const IPPROTO_TCP: i32 = libc::IPPROTO_TCP;
+
+#[cfg(target_os = "linux")]
+pub const TCP_NODELAY: i32 = libc::TCP_NODELAY;
@@ -0,0 +1,238 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::runtime::{
+    limits,
+    memory::DemiBuffer,
+    SharedObject,
+};
+use ::std::ops::{
+    Deref,
+    DerefMut,
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Pool of recycled heap-allocated buffers, which pops draw their results from instead of allocating fresh buffers.
+/// Buffers come back to the pool once the application frees them, as long as the pool holds fewer than its maximum
+/// number of buffers. When the pool is exhausted, buffers are freshly allocated. A pool that holds no buffers at most
+/// is disabled, and allocates every buffer to fit.
+pub struct BufferPool {
+    /// Capacity of the buffers in the pool.
+    buffer_size: u16,
+    /// Maximum number of buffers that the pool holds.
+    max_buffers: usize,
+    /// Buffers that are ready for reuse.
+    buffers: Vec<DemiBuffer>,
+    /// Number of buffers that were freshly allocated.
+    num_allocations: usize,
+    /// Number of buffers that were reused from the pool.
+    num_reuses: usize,
+}
+
+/// Buffer pool that is shared between the runtime and the transports that receive data into its buffers.
+#[derive(Clone)]
+pub struct SharedBufferPool(SharedObject<BufferPool>);
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl BufferPool {
+    /// Creates a pool that holds up to `max_buffers` buffers of `buffer_size` bytes.
+    pub fn new(buffer_size: u16, max_buffers: usize) -> Self {
+        Self {
+            buffer_size,
+            max_buffers,
+            buffers: Vec::new(),
+            num_allocations: 0,
+            num_reuses: 0,
+        }
+    }
+
+    /// Allocates a buffer of `size` bytes, out of the pool if it has any buffer left.
+    pub fn alloc(&mut self, size: u16) -> DemiBuffer {
+        if self.max_buffers == 0 || size > self.buffer_size {
+            self.num_allocations += 1;
+            return DemiBuffer::new(size);
+        }
+        let mut buf: DemiBuffer = match self.buffers.pop() {
+            Some(buf) => {
+                self.num_reuses += 1;
+                buf
+            },
+            None => {
+                // Allocate a buffer of the size of the pool, so that it can be recycled once it is freed.
+                self.num_allocations += 1;
+                DemiBuffer::new(self.buffer_size)
+            },
+        };
+        buf.trim((self.buffer_size - size) as usize)
+            .expect("buffers of the pool should be big enough");
+        buf
+    }
+
+    /// Gives `buf` back to the pool, if it has room for it and if the buffer can be reused. Otherwise, it is freed.
+    pub fn recycle(&mut self, buf: DemiBuffer) {
+        if self.buffers.len() >= self.max_buffers {
+            return;
+        }
+        if let Ok(buf) = buf.reclaim(self.buffer_size) {
+            self.buffers.push(buf);
+        }
+    }
+
+    /// Sets the maximum number of buffers that the pool holds. Buffers in excess are freed.
+    pub fn set_max_buffers(&mut self, max_buffers: usize) {
+        self.max_buffers = max_buffers;
+        self.buffers.truncate(max_buffers);
+    }
+
+    /// Gets the maximum number of buffers that the pool holds.
+    pub fn get_max_buffers(&self) -> usize {
+        self.max_buffers
+    }
+
+    /// Gets the number of buffers that are ready for reuse.
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Checks whether the pool has no buffer ready for reuse.
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty()
+    }
+
+    /// Gets the number of buffers that were freshly allocated so far.
+    pub fn get_num_allocations(&self) -> usize {
+        self.num_allocations
+    }
+
+    /// Gets the number of buffers that were reused from the pool so far.
+    pub fn get_num_reuses(&self) -> usize {
+        self.num_reuses
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl Default for BufferPool {
+    /// Creates a disabled pool, for buffers of the largest pop.
+    fn default() -> Self {
+        Self::new(limits::RECVBUF_SIZE_MAX as u16, 0)
+    }
+}
+
+impl Default for SharedBufferPool {
+    /// Creates a shared disabled pool, for buffers of the largest pop.
+    fn default() -> Self {
+        Self(SharedObject::new(BufferPool::default()))
+    }
+}
+
+impl Deref for SharedBufferPool {
+    type Target = BufferPool;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.deref()
+    }
+}
+
+impl DerefMut for SharedBufferPool {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0.deref_mut()
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::BufferPool;
+    use crate::runtime::memory::DemiBuffer;
+    use ::anyhow::Result;
+
+    /// Size of the buffers of the pools under test.
+    const BUFFER_SIZE: u16 = 1024;
+
+    /// Maximum number of buffers of the pools under test.
+    const MAX_BUFFERS: usize = 4;
+
+    /// Simulates a receive-heavy workload on `pool`, where the application holds on to `batch_size` popped buffers at
+    /// a time before freeing them, for `num_batches` batches.
+    fn receive(pool: &mut BufferPool, num_batches: usize, batch_size: usize) {
+        for _ in 0..num_batches {
+            let bufs: Vec<DemiBuffer> = (0..batch_size).map(|_| pool.alloc(BUFFER_SIZE / 2)).collect();
+            for buf in bufs {
+                pool.recycle(buf);
+            }
+        }
+    }
+
+    /// Tests that a disabled pool allocates every buffer, while an enabled one reuses them.
+    #[test]
+    fn test_buffer_pool_reduces_allocations() -> Result<()> {
+        let mut disabled: BufferPool = BufferPool::new(BUFFER_SIZE, 0);
+        receive(&mut disabled, 100, MAX_BUFFERS);
+        crate::ensure_eq!(disabled.get_num_allocations(), 100 * MAX_BUFFERS);
+        crate::ensure_eq!(disabled.get_num_reuses(), 0);
+        crate::ensure_eq!(disabled.len(), 0);
+
+        let mut enabled: BufferPool = BufferPool::new(BUFFER_SIZE, MAX_BUFFERS);
+        receive(&mut enabled, 100, MAX_BUFFERS);
+        crate::ensure_eq!(enabled.get_num_allocations(), MAX_BUFFERS);
+        crate::ensure_eq!(enabled.get_num_reuses(), 99 * MAX_BUFFERS);
+        crate::ensure_eq!(enabled.len(), MAX_BUFFERS);
+
+        Ok(())
+    }
+
+    /// Tests that the pool holds no more than its maximum number of buffers, and falls back to fresh allocations once
+    /// it is exhausted.
+    #[test]
+    fn test_buffer_pool_bounded() -> Result<()> {
+        let mut pool: BufferPool = BufferPool::new(BUFFER_SIZE, MAX_BUFFERS);
+        receive(&mut pool, 10, 2 * MAX_BUFFERS);
+        crate::ensure_eq!(pool.len(), MAX_BUFFERS);
+        crate::ensure_eq!(pool.get_num_allocations(), 2 * MAX_BUFFERS + 9 * MAX_BUFFERS);
+        crate::ensure_eq!(pool.get_num_reuses(), 9 * MAX_BUFFERS);
+
+        pool.set_max_buffers(1);
+        crate::ensure_eq!(pool.len(), 1);
+
+        Ok(())
+    }
+
+    /// Tests that recycled buffers come back at the requested size, and that buffers that cannot be reused are not
+    /// recycled.
+    #[test]
+    fn test_buffer_pool_recycle() -> Result<()> {
+        let mut pool: BufferPool = BufferPool::new(BUFFER_SIZE, MAX_BUFFERS);
+        let mut buf: DemiBuffer = pool.alloc(100);
+        crate::ensure_eq!(buf.len(), 100);
+        buf.adjust(10)?;
+        pool.recycle(buf);
+        crate::ensure_eq!(pool.len(), 1);
+        crate::ensure_eq!(pool.alloc(200).len(), 200);
+        crate::ensure_eq!(pool.len(), 0);
+
+        // Buffers that are still referred to by a clone, or that are too big for the pool, are freed instead.
+        let buf: DemiBuffer = pool.alloc(100);
+        let _clone: DemiBuffer = buf.clone();
+        pool.recycle(buf);
+        pool.recycle(DemiBuffer::new(BUFFER_SIZE + 1));
+        crate::ensure_eq!(pool.len(), 0);
+
+        Ok(())
+    }
+}
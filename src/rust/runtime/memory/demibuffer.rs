@@ -4,7 +4,8 @@
 // This DemiBuffer type is designed to be a common abstraction defining the behavior of data buffers in Demikernel.
 // It currently supports two underlying types of buffers: heap-allocated and DPDK-allocated.  The basic operations on
 // DemiBuffers are designed to have equivalent behavior (effects on the data), regardless of the underlying buffer type.
-// In particular, len(), adjust(), trim(), clone(), and split_back() are designed to behave the same regardless.
+// In particular, len(), adjust(), trim(), clone(), split_back() and split_off() are designed to behave the same
+// regardless.
 //
 // The constructors/destructors, however, are necessarily different.  For DPDK-allocated buffers, a MBuf is expected
 // to be allocated externally and provided to the DemiBuffer's "from_mbuf" constructor.  A MBuf can also be extracted
@@ -308,6 +309,23 @@ impl DemiBuffer {
         slice.try_into()
     }
 
+    /// Create a new Heap-allocated `DemiBuffer` that holds the concatenation of several byte slices.
+    pub fn from_slices(slices: &[&[u8]]) -> Result<Self, Fail> {
+        // Check the total size of the slices to ensure a single DemiBuffer can hold them.
+        let total: usize = slices.iter().map(|slice| slice.len()).sum();
+        if total >= u16::MAX as usize {
+            return Err(Fail::new(libc::EINVAL, "slices are larger than a DemiBuffer can hold"));
+        }
+
+        let mut buf: DemiBuffer = DemiBuffer::new(total as u16);
+        let mut offset: usize = 0;
+        for slice in slices.iter().filter(|slice| !slice.is_empty()) {
+            buf[offset..(offset + slice.len())].copy_from_slice(slice);
+            offset += slice.len();
+        }
+        Ok(buf)
+    }
+
     /// Creates a `DemiBuffer` from a raw pointer.
     pub unsafe fn from_raw(token: NonNull<u8>) -> Self {
         DemiBuffer {
@@ -347,6 +365,23 @@ impl DemiBuffer {
         self.get_tag() == Tag::Dpdk
     }
 
+    /// Turns the target `DemiBuffer` back into a buffer that holds `capacity` bytes, as if it was just allocated with
+    /// [new](Self::new), so that it can be reused. This only succeeds for single-segment heap-allocated buffers that
+    /// directly hold `capacity` bytes of data, and that no clone refers to. Other buffers are handed back untouched.
+    pub fn reclaim(self, capacity: u16) -> Result<Self, Self> {
+        if self.get_tag() != Tag::Heap || self.is_multi_segment() {
+            return Err(self);
+        }
+        let metadata: &mut MetaData = self.as_metadata();
+        if metadata.ol_flags & METADATA_F_INDIRECT != 0 || metadata.refcnt != 1 || metadata.buf_len != capacity {
+            return Err(self);
+        }
+        metadata.data_off = 0;
+        metadata.data_len = capacity;
+        metadata.pkt_len = capacity as u32;
+        Ok(self)
+    }
+
     /// Returns the length of the data stored in the `DemiBuffer`.
     // Note that while we return a usize here (for convenience), the value is guaranteed to never exceed u16::MAX.
     pub fn len(&self) -> usize {
@@ -428,6 +463,51 @@ impl DemiBuffer {
         Ok(())
     }
 
+    /// Advances the start of the `DemiBuffer` by `nbytes` bytes, without copying any data.
+    ///
+    /// This is equivalent to `adjust()`.  Advancing by zero bytes is a no-op, and advancing by more than the length of
+    /// the first segment fails with `EINVAL`.
+    pub fn advance(&mut self, nbytes: usize) -> Result<(), Fail> {
+        self.adjust(nbytes)
+    }
+
+    /// Shortens the `DemiBuffer` to `len` bytes, without copying any data.
+    ///
+    /// As with `Vec::truncate()`, this has no effect if `len` is greater than or equal to the current length.
+    // Note: This is built on trim(), so it fails if the bytes to be removed span more than the last segment of a chain.
+    pub fn truncate(&mut self, len: usize) -> Result<(), Fail> {
+        let current_len: usize = self.len();
+        if len >= current_len {
+            return Ok(());
+        }
+        self.trim(current_len - len)
+    }
+
+    ///
+    /// **Description**
+    ///
+    /// Splits the target [DemiBuffer] at the given `at` offset, without copying any data.  The target [DemiBuffer]
+    /// retains the bytes in `[0, at)` and the returned [DemiBuffer] holds the bytes in `[at, len)`.  Both halves are
+    /// views into the same underlying allocation, which is only freed once both of them are dropped.
+    ///
+    /// Splitting at `0` leaves the target [DemiBuffer] empty and returns all the data.  Splitting at `len` leaves the
+    /// target [DemiBuffer] untouched and returns an empty [DemiBuffer].
+    ///
+    /// **Return Value**
+    ///
+    /// On successful completion, a new [DemiBuffer] containing the data after the split point is returned.  On failure,
+    /// a [Fail] structure encoding the failure condition is returned instead.
+    ///
+    /// **Notes**
+    ///
+    /// - This is equivalent to [DemiBuffer::split_back], and named after `Vec::split_off()`.
+    /// - The target [DemiBuffer] must be a single buffer segment (not a chain).
+    /// - `at` must not be greater than the length of the target [DemiBuffer].
+    ///
+    pub fn split_off(&mut self, at: usize) -> Result<Self, Fail> {
+        self.split(false, at)
+    }
+
     ///
     /// **Description**
     ///
@@ -1167,4 +1247,134 @@ mod tests {
 
         Ok(())
     }
+
+    // Tests that split_off shares the underlying allocation between both halves, and that it is freed correctly.
+    #[test]
+    fn split_off() -> Result<()> {
+        let str: String = String::from("word one two three four five six seven eight nine");
+        let mut buf: DemiBuffer = DemiBuffer::from_slice(str.as_bytes())?;
+        let base: *const u8 = buf.as_ptr();
+
+        // Both halves should be views into the same memory, and hold a reference on it.
+        let mut split_buf: DemiBuffer = buf.split_off(24)?;
+        crate::ensure_eq!(&buf[..], &str.as_bytes()[..24]);
+        crate::ensure_eq!(&split_buf[..], &str.as_bytes()[24..]);
+        crate::ensure_eq!(buf.as_ptr(), base);
+        crate::ensure_eq!(split_buf.as_ptr(), base.wrapping_add(24));
+        crate::ensure_eq!(buf.as_metadata().refcnt, 2);
+
+        // Writes through one half should not be visible in the other.
+        split_buf[0] = b'X';
+        crate::ensure_eq!(&buf[..], &str.as_bytes()[..24]);
+
+        // Dropping one half releases its reference, but keeps the data around for the other one.
+        drop(split_buf);
+        crate::ensure_eq!(buf.as_metadata().refcnt, 1);
+        crate::ensure_eq!(&buf[..], &str.as_bytes()[..24]);
+
+        // Splitting at the length returns an empty buffer and leaves the target untouched.
+        let empty: DemiBuffer = buf.split_off(24)?;
+        crate::ensure_eq!(empty.len(), 0);
+        crate::ensure_eq!(&buf[..], &str.as_bytes()[..24]);
+        drop(empty);
+
+        // Splitting at zero moves all the data into the returned buffer.
+        let all: DemiBuffer = buf.split_off(0)?;
+        crate::ensure_eq!(buf.len(), 0);
+        crate::ensure_eq!(&all[..], &str.as_bytes()[..24]);
+        crate::ensure_eq!(all.as_ptr(), base);
+
+        // Splitting past the end should fail.
+        crate::ensure_eq!(buf.split_off(1).is_err(), true);
+
+        Ok(())
+    }
+
+    // Tests advance and truncate.
+    #[test]
+    fn advance_truncate() -> Result<()> {
+        let str: String = String::from("word one two three four five six seven eight nine");
+        let mut buf: DemiBuffer = DemiBuffer::from_slice(str.as_bytes())?;
+        let base: *const u8 = buf.as_ptr();
+
+        // Advancing moves the start of the view, without copying.
+        buf.advance(0)?;
+        crate::ensure_eq!(buf.as_ptr(), base);
+        buf.advance(5)?;
+        crate::ensure_eq!(buf.as_ptr(), base.wrapping_add(5));
+        crate::ensure_eq!(&buf[..], &str.as_bytes()[5..]);
+
+        // Truncating to a larger or equal length is a no-op.
+        buf.truncate(buf.len())?;
+        buf.truncate(usize::MAX)?;
+        crate::ensure_eq!(&buf[..], &str.as_bytes()[5..]);
+
+        // Truncating shortens the view, without copying.
+        buf.truncate(7)?;
+        crate::ensure_eq!(buf.as_ptr(), base.wrapping_add(5));
+        crate::ensure_eq!(&buf[..], &str.as_bytes()[5..12]);
+
+        // Both work on clones without affecting the original view.
+        let mut clone: DemiBuffer = buf.clone();
+        clone.advance(4)?;
+        clone.truncate(2)?;
+        crate::ensure_eq!(&clone[..], &str.as_bytes()[9..11]);
+        crate::ensure_eq!(&buf[..], &str.as_bytes()[5..12]);
+
+        // Advancing past the end should fail.
+        crate::ensure_eq!(buf.advance(8).is_err(), true);
+        buf.advance(7)?;
+        crate::ensure_eq!(buf.len(), 0);
+
+        Ok(())
+    }
+
+    // Tests reclaim.
+    #[test]
+    fn reclaim() -> Result<()> {
+        // A buffer that was adjusted and trimmed is reclaimed at its full capacity.
+        let mut buf: DemiBuffer = DemiBuffer::new(42);
+        let base: *const u8 = buf.as_ptr();
+        buf.adjust(7)?;
+        buf.trim(7)?;
+        let buf: DemiBuffer = match buf.reclaim(42) {
+            Ok(buf) => buf,
+            Err(_) => anyhow::bail!("buffer should have been reclaimed"),
+        };
+        crate::ensure_eq!(buf.len(), 42);
+        crate::ensure_eq!(buf.as_ptr(), base);
+
+        // Buffers of another capacity, and buffers that a clone refers to, are not reclaimed.
+        let buf: DemiBuffer = match buf.reclaim(43) {
+            Ok(_) => anyhow::bail!("buffer of another capacity should not have been reclaimed"),
+            Err(buf) => buf,
+        };
+        let clone: DemiBuffer = buf.clone();
+        crate::ensure_eq!(buf.reclaim(42).is_err(), true);
+        crate::ensure_eq!(clone.reclaim(42).is_err(), true);
+
+        Ok(())
+    }
+
+    // Tests from_slices.
+    #[test]
+    fn from_slices() -> Result<()> {
+        // The slices are concatenated in order, and empty slices are skipped.
+        let buf: DemiBuffer = DemiBuffer::from_slices(&[b"header:", &[], b"body"])?;
+        crate::ensure_eq!(&buf[..], b"header:body");
+
+        // An empty set of slices gives an empty buffer.
+        let buf: DemiBuffer = DemiBuffer::from_slices(&[])?;
+        crate::ensure_eq!(buf.len(), 0);
+
+        // Slices that are too large for a single buffer altogether should be rejected, even if each of them fits.
+        let half: Vec<u8> = vec![0; u16::MAX as usize / 2 + 1];
+        match DemiBuffer::from_slices(&[&half, &half]) {
+            Err(e) if e.errno == libc::EINVAL => Ok(()),
+            result => anyhow::bail!(
+                "from_slices should have failed with EINVAL: {:?}",
+                result.map(|buf| buf.len())
+            ),
+        }
+    }
 }
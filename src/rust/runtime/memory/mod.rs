@@ -1,6 +1,7 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
+mod buffer_pool;
 mod demibuffer;
 
 //==============================================================================
@@ -12,6 +13,7 @@ use crate::runtime::{
     types::{
         demi_sgarray_t,
         demi_sgaseg_t,
+        DEMI_SGARRAY_MAXLEN,
     },
 };
 use ::libc::c_void;
@@ -22,11 +24,18 @@ use ::std::{
         NonNull,
     },
 };
+
 //==============================================================================
 // Exports
 //==============================================================================
 
-pub use self::demibuffer::*;
+pub use self::{
+    buffer_pool::{
+        BufferPool,
+        SharedBufferPool,
+    },
+    demibuffer::*,
+};
 
 //==============================================================================
 // Traits
@@ -101,11 +110,17 @@ pub trait MemoryRuntime {
         // Safety: The `DemiBuffer::from_raw()` call *should* be safe, as the `sga_buf` field in the `demi_sgarray_t`
         // contained a valid `DemiBuffer` token when we provided it to the user (and the user shouldn't change it).
         let buf: DemiBuffer = unsafe { DemiBuffer::from_raw(token) };
-        drop(buf);
+        self.recycle_buffer(buf);
 
         Ok(())
     }
 
+    /// Disposes of a buffer that the application freed. By default, the buffer is dropped, but memory runtimes that
+    /// pool buffers may reuse it.
+    fn recycle_buffer(&self, buf: DemiBuffer) {
+        drop(buf);
+    }
+
     /// Clones a scatter-gather array.
     fn clone_sgarray(&self, sga: &demi_sgarray_t) -> Result<DemiBuffer, Fail> {
         // Check arguments.
@@ -165,3 +180,115 @@ pub trait MemoryRuntime {
         Ok(clone)
     }
 }
+
+//==============================================================================
+// Standalone Functions
+//==============================================================================
+
+/// Returns the total length of the segments described by a caller-provided scatter-gather array.
+///
+/// Unlike [MemoryRuntime::clone_sgarray], this does not require the scatter-gather array to be backed by a
+/// [DemiBuffer], so applications may describe their own memory with it.
+pub fn sgarray_capacity(sga: &demi_sgarray_t) -> Result<usize, Fail> {
+    let numsegs: usize = sga.sga_numsegs as usize;
+    if numsegs == 0 || numsegs > DEMI_SGARRAY_MAXLEN {
+        return Err(Fail::new(libc::EINVAL, "demi_sgarray_t has invalid segment count"));
+    }
+
+    let segs: [demi_sgaseg_t; DEMI_SGARRAY_MAXLEN] = sga.sga_segs;
+    let mut capacity: usize = 0;
+    for seg in &segs[..numsegs] {
+        let seg_len: usize = seg.sgaseg_len as usize;
+        if seg_len > 0 && seg.sgaseg_buf == ptr::null_mut() {
+            return Err(Fail::new(libc::EINVAL, "demi_sgarray_t has invalid segment buffer"));
+        }
+        capacity += seg_len;
+    }
+
+    Ok(capacity)
+}
+
+/// Copies `data` into the segments described by a caller-provided scatter-gather array, filling them in order. At most
+/// [sgarray_capacity] bytes are copied, and the number of bytes that were actually copied is returned.
+pub fn fill_sgarray(sga: &demi_sgarray_t, data: &[u8]) -> usize {
+    let numsegs: usize = (sga.sga_numsegs as usize).min(DEMI_SGARRAY_MAXLEN);
+    let segs: [demi_sgaseg_t; DEMI_SGARRAY_MAXLEN] = sga.sga_segs;
+    fill_sgasegs(&segs[..numsegs], data)
+}
+
+/// Copies `data` into `segs`, filling them in order, and returns the number of bytes that were copied.
+fn fill_sgasegs(segs: &[demi_sgaseg_t], data: &[u8]) -> usize {
+    let mut nbytes: usize = 0;
+    for seg in segs {
+        if nbytes == data.len() {
+            break;
+        }
+        let len: usize = (seg.sgaseg_len as usize).min(data.len() - nbytes);
+        // Safety: The caller guarantees that each segment describes writable memory of at least `sgaseg_len` bytes,
+        // which does not overlap with `data`.
+        unsafe { ptr::copy_nonoverlapping(data[nbytes..].as_ptr(), seg.sgaseg_buf as *mut u8, len) };
+        nbytes += len;
+    }
+    nbytes
+}
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::fill_sgasegs;
+    use crate::runtime::types::demi_sgaseg_t;
+    use ::anyhow::Result;
+    use ::libc::c_void;
+
+    fn new_sgaseg(buf: &mut [u8]) -> demi_sgaseg_t {
+        demi_sgaseg_t {
+            sgaseg_buf: buf.as_mut_ptr() as *mut c_void,
+            sgaseg_len: buf.len() as u32,
+        }
+    }
+
+    // Tests that data gets spread across two segments, in order.
+    #[test]
+    fn fill_two_segments() -> Result<()> {
+        let mut first: [u8; 4] = [0; 4];
+        let mut second: [u8; 8] = [0; 8];
+        let segs: [demi_sgaseg_t; 2] = [new_sgaseg(&mut first), new_sgaseg(&mut second)];
+
+        crate::ensure_eq!(fill_sgasegs(&segs, b"hello world"), 11);
+        crate::ensure_eq!(&first, b"hell");
+        crate::ensure_eq!(&second, b"o world\0");
+
+        Ok(())
+    }
+
+    // Tests that only as many bytes as the segments can hold get copied.
+    #[test]
+    fn fill_two_segments_overflow() -> Result<()> {
+        let mut first: [u8; 2] = [0; 2];
+        let mut second: [u8; 3] = [0; 3];
+        let segs: [demi_sgaseg_t; 2] = [new_sgaseg(&mut first), new_sgaseg(&mut second)];
+
+        crate::ensure_eq!(fill_sgasegs(&segs, b"hello world"), 5);
+        crate::ensure_eq!(&first, b"he");
+        crate::ensure_eq!(&second, b"llo");
+
+        Ok(())
+    }
+
+    // Tests that segments are left untouched when there is nothing to copy.
+    #[test]
+    fn fill_two_segments_empty() -> Result<()> {
+        let mut first: [u8; 2] = [0xff; 2];
+        let mut second: [u8; 3] = [0xff; 3];
+        let segs: [demi_sgaseg_t; 2] = [new_sgaseg(&mut first), new_sgaseg(&mut second)];
+
+        crate::ensure_eq!(fill_sgasegs(&segs, &[]), 0);
+        crate::ensure_eq!(first, [0xff; 2]);
+        crate::ensure_eq!(second, [0xff; 3]);
+
+        Ok(())
+    }
+}
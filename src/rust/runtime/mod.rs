@@ -23,6 +23,7 @@ pub use queue::{
     QDesc,
     QToken,
     QType,
+    QueueInfo,
 };
 
 #[cfg(feature = "liburing")]
@@ -39,8 +40,17 @@ use crate::{
     pal::data_structures::SockAddr,
     runtime::{
         fail::Fail,
-        memory::MemoryRuntime,
+        limits,
+        memory::{
+            DemiBuffer,
+            MemoryRuntime,
+            SharedBufferPool,
+        },
         network::{
+            config::{
+                EphemeralPortConfig,
+                EphemeralPortExhaustion,
+            },
             ephemeral::EphemeralPorts,
             socket::SocketId,
             NetworkQueueTable,
@@ -74,7 +84,10 @@ use ::std::{
     },
     pin::Pin,
     rc::Rc,
-    time::Instant,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 #[cfg(target_os = "windows")]
@@ -90,6 +103,7 @@ use self::{
     },
     types::{
         demi_accept_result_t,
+        demi_pop_info_result_t,
         demi_qr_value_t,
         demi_qresult_t,
     },
@@ -105,6 +119,9 @@ const TIMER_RESOLUTION: usize = 64;
 // Structures
 //======================================================================================================================
 
+/// Hook that blocks until pollers may have new external input, or the given timeout, if any, expires.
+pub type IdleHook = Box<dyn FnMut(Option<Duration>)>;
+
 /// Demikernel Runtime
 #[derive(Default)]
 pub struct DemiRuntime {
@@ -114,6 +131,12 @@ pub struct DemiRuntime {
     qtable: IoQueueTable,
     /// Shared ephemeral port allocator.
     ephemeral_ports: EphemeralPorts,
+    /// Configuration of the shared ephemeral port allocator.
+    ephemeral_port_config: EphemeralPortConfig,
+    /// Maximum number of queues that may be open at the same time, if any.
+    max_queues: Option<usize>,
+    /// Pool of buffers that pops draw their results from.
+    buffer_pool: SharedBufferPool,
     /// Shared timer for periodic triggering of coroutines and time outs.
     timer: SharedTimer,
     /// Shared table for mapping from underlying transport identifiers to queue descriptors.
@@ -121,6 +144,13 @@ pub struct DemiRuntime {
     /// Currently running coroutines.
     pending_ops: HashMap<QDesc, HashMap<TaskHandle, YielderHandle>>,
     ts_iters: usize,
+    /// Hook for blocking on the readiness primitive of the pollers, if any.
+    idle_hook: Option<IdleHook>,
+    /// Number of pollers that have no readiness primitive behind the idle hook.
+    num_pollers_without_idle_hook: usize,
+    /// Does time only move when it is advanced explicitly, rather than following the wall clock?
+    #[cfg(any(test, feature = "virtual-clock"))]
+    virtual_clock: bool,
 }
 
 #[derive(Clone)]
@@ -152,13 +182,30 @@ impl SharedDemiRuntime {
             scheduler: Scheduler::default(),
             qtable: IoQueueTable::default(),
             ephemeral_ports: EphemeralPorts::default(),
+            ephemeral_port_config: EphemeralPortConfig::default(),
+            max_queues: None,
+            buffer_pool: SharedBufferPool::default(),
             timer: SharedTimer::new(now),
             network_table: NetworkQueueTable::default(),
             pending_ops: HashMap::<QDesc, HashMap<TaskHandle, YielderHandle>>::new(),
             ts_iters: 0,
+            idle_hook: None,
+            num_pollers_without_idle_hook: 0,
+            #[cfg(any(test, feature = "virtual-clock"))]
+            virtual_clock: false,
         }))
     }
 
+    /// Creates a runtime whose clock starts at `now` and never reads the wall clock. Time only moves forward through
+    /// [advance_clock](Self::advance_clock) and [advance_virtual_clock](Self::advance_virtual_clock), and waits jump
+    /// straight to the next timer that expires instead of sleeping until then, so that timeouts fire deterministically.
+    #[cfg(any(test, feature = "virtual-clock"))]
+    pub fn new_with_virtual_clock(now: Instant) -> Self {
+        let mut runtime: Self = Self::new(now);
+        runtime.virtual_clock = true;
+        runtime
+    }
+
     /// Inserts the `coroutine` named `task_name` into the scheduler.
     pub fn insert_coroutine(&mut self, task_name: &str, coroutine: Pin<Box<Operation>>) -> Result<TaskHandle, Fail> {
         trace!("Inserting coroutine: {:?}", task_name);
@@ -184,7 +231,26 @@ impl SharedDemiRuntime {
     where
         F: FnOnce(Yielder) -> Pin<Box<dyn Future<Output = (QDesc, OperationResult)>>>,
     {
-        let yielder: Yielder = Yielder::new();
+        self.insert_coroutine_with_deadline(task_name, coroutine_factory, qd, None)
+    }
+
+    /// Same as [insert_coroutine_with_tracking](Self::insert_coroutine_with_tracking), but the yielder that is handed
+    /// to the coroutine factory carries `deadline`, if any. Every operation that the coroutine awaits is then bounded
+    /// by the deadline as a whole, and fails with `ETIMEDOUT` once it expires.
+    pub fn insert_coroutine_with_deadline<F>(
+        &mut self,
+        task_name: &str,
+        coroutine_factory: F,
+        qd: QDesc,
+        deadline: Option<Instant>,
+    ) -> Result<TaskHandle, Fail>
+    where
+        F: FnOnce(Yielder) -> Pin<Box<dyn Future<Output = (QDesc, OperationResult)>>>,
+    {
+        let yielder: Yielder = match deadline {
+            Some(deadline) => Yielder::new().with_deadline(deadline),
+            None => Yielder::new(),
+        };
         let yielder_handle: YielderHandle = yielder.get_handle();
         let coroutine: Pin<Box<dyn Future<Output = (QDesc, OperationResult)>>> = coroutine_factory(yielder);
         match self.insert_coroutine(task_name, coroutine) {
@@ -209,7 +275,20 @@ impl SharedDemiRuntime {
             .expect("Removing task that does not exist (either was previously removed or never inserted");
         // 2. Cast to void and then downcast to operation task.
         trace!("Removing coroutine: {:?}", boxed_task.get_name());
-        OperationTask::from(boxed_task.as_any())
+        let operation_task: OperationTask = OperationTask::from(boxed_task.as_any());
+        // 3. Stop tracking the operation, as its result was taken.
+        if let Some((qd, _)) = operation_task.get_result() {
+            self.cancel_pending_op(&qd, handle);
+        }
+        operation_task
+    }
+
+    /// Counts the operations that were issued on the queue `qd` and whose result is yet to be taken, whether they
+    /// completed or not. A count that keeps growing hints at queue tokens that are never waited on.
+    pub fn pending_operations(&self, qd: QDesc) -> Result<usize, Fail> {
+        // Fail on queues that do not exist, rather than reporting that they have nothing pending.
+        self.get_queue_type(&qd)?;
+        Ok(self.pending_ops.get(&qd).map_or(0, |ops| ops.len()))
     }
 
     /// Removes a coroutine from the underlying scheduler given its associated [QToken] `qt`.
@@ -281,6 +360,58 @@ impl SharedDemiRuntime {
         }
     }
 
+    /// Inserts the poller `coroutine` named `task_name` into the scheduler. A poller runs as long as the runtime does
+    /// and checks for external input on every poll, so it does not keep the runtime from being quiescent. This poller
+    /// has no readiness primitive to block on, so waits from a quiescent state keep polling while it runs.
+    pub fn insert_poller_coroutine(
+        &mut self,
+        task_name: &str,
+        coroutine: Pin<Box<dyn Future<Output = ()>>>,
+    ) -> Result<TaskHandle, Fail> {
+        let handle: TaskHandle = self.do_insert_poller_coroutine(task_name, coroutine)?;
+        self.num_pollers_without_idle_hook += 1;
+        Ok(handle)
+    }
+
+    /// Like [insert_poller_coroutine](Self::insert_poller_coroutine), but `idle_hook` blocks on the readiness
+    /// primitive behind the poller, and [wait_for_input](Self::wait_for_input) calls it unless other pollers lack one.
+    /// Only one idle hook may be set, so if there is one already, the poller is inserted without `idle_hook`.
+    pub fn insert_poller_coroutine_with_idle_hook(
+        &mut self,
+        task_name: &str,
+        coroutine: Pin<Box<dyn Future<Output = ()>>>,
+        idle_hook: IdleHook,
+    ) -> Result<TaskHandle, Fail> {
+        if self.idle_hook.is_some() {
+            warn!(
+                "insert_poller_coroutine_with_idle_hook(): idle hook already set, inserting poller without it \
+                 (task_name={:?})",
+                task_name
+            );
+            return self.insert_poller_coroutine(task_name, coroutine);
+        }
+        let handle: TaskHandle = self.do_insert_poller_coroutine(task_name, coroutine)?;
+        self.idle_hook = Some(idle_hook);
+        Ok(handle)
+    }
+
+    fn do_insert_poller_coroutine(
+        &mut self,
+        task_name: &str,
+        coroutine: Pin<Box<dyn Future<Output = ()>>>,
+    ) -> Result<TaskHandle, Fail> {
+        trace!("Inserting poller coroutine: {:?}", task_name);
+        let task: BackgroundTask = BackgroundTask::new(task_name.to_string(), coroutine);
+        match self.scheduler.insert_poller(task) {
+            Some(handle) => Ok(handle),
+            None => {
+                let cause: String = format!("cannot schedule coroutine (task_name={:?})", &task_name);
+                error!("insert_poller_coroutine(): {}", cause);
+                Err(Fail::new(libc::EAGAIN, &cause))
+            },
+        }
+    }
+
     /// Removes the background `coroutine` associated with `handle`. Since background coroutines do not return a result
     /// there is no need to cast it.
     pub fn remove_background_coroutine(&mut self, handle: &TaskHandle) -> Result<(), Fail> {
@@ -297,19 +428,129 @@ impl SharedDemiRuntime {
         }
     }
 
-    pub fn poll_and_advance_clock(&mut self) {
+    pub fn poll_and_advance_clock(&mut self) -> usize {
         if self.ts_iters == 0 {
-            self.advance_clock(Instant::now());
+            let now: Instant = self.clock_now();
+            self.advance_clock(now);
         }
         self.ts_iters = (self.ts_iters + 1) % TIMER_RESOLUTION;
         self.poll()
     }
 
-    /// Performs a single pool on the underlying scheduler.
-    pub fn poll(&mut self) {
+    /// Performs a single pool on the underlying scheduler. Returns the number of tasks that were polled.
+    pub fn poll(&mut self) -> usize {
         self.scheduler.poll()
     }
 
+    /// Polls the underlying scheduler at most `max_iterations` times, advancing the clock as
+    /// [poll_and_advance_clock](Self::poll_and_advance_clock) does. Polling stops early once an iteration finds no
+    /// runnable task, so this returns quickly when there is nothing to do. Returns the total number of tasks that were
+    /// polled.
+    pub fn poll_bounded(&mut self, max_iterations: usize) -> usize {
+        let mut num_polled: usize = 0;
+        for _ in 0..max_iterations {
+            let num_polled_in_iteration: usize = self.poll_and_advance_clock();
+            if num_polled_in_iteration == 0 {
+                break;
+            }
+            num_polled += num_polled_in_iteration;
+        }
+        num_polled
+    }
+
+    /// Checks whether the target runtime is quiescent, that is, whether no coroutine other than a poller is runnable
+    /// and no timer is pending. From a quiescent state, only external input that pollers pick up can make progress, so
+    /// polling the runtime again would just spin.
+    pub fn is_quiescent(&self) -> bool {
+        !self.scheduler.has_runnable_foreground_tasks() && !self.timer.has_pending_timers()
+    }
+
+    /// Waits for external input from a quiescent state, for at most `timeout`, if any. This blocks on the idle hook
+    /// if every poller has a readiness primitive behind it, and returns right away otherwise, since blocking would keep
+    /// the other pollers from picking up their input. This fails with `EDEADLK` if no poller is running, because
+    /// nothing can make progress then.
+    pub fn wait_for_input(&mut self, timeout: Option<Duration>) -> Result<(), Fail> {
+        if !self.scheduler.has_pollers() {
+            let cause: &str = "no poller can pick up external input";
+            warn!("wait_for_input(): {}", cause);
+            return Err(Fail::new(libc::EDEADLK, cause));
+        }
+        if self.num_pollers_without_idle_hook > 0 {
+            return Ok(());
+        }
+        if let Some(idle_hook) = self.idle_hook.as_mut() {
+            idle_hook(timeout);
+        }
+        Ok(())
+    }
+
+    /// Waits for any of the operations of `qts` to complete, and returns the offset of the first completed one along
+    /// with its [TaskHandle]. The operation is not removed from the scheduler, so its result is still to be taken by
+    /// the caller. If `timeout` is set and expires before any operation completes, this fails with `ETIMEDOUT` and
+    /// leaves all operations pending. The timeout is measured by the runtime timer, and a timeout of zero polls the
+    /// scheduler exactly once.
+    pub fn wait_any(&mut self, qts: &[QToken], timeout: Option<Duration>) -> Result<(usize, TaskHandle), Fail> {
+        let mut handles: Vec<TaskHandle> = Vec::with_capacity(qts.len());
+        for qt in qts {
+            handles.push(self.from_task_id(*qt)?);
+        }
+
+        // Refresh the clock, so that the deadline does not start from a stale time.
+        let deadline: Option<Instant> = match timeout {
+            Some(timeout) => {
+                let now: Instant = self.clock_now();
+                self.advance_clock(now);
+                Some(self.get_now() + timeout)
+            },
+            None => None,
+        };
+
+        loop {
+            // Poll first, so as to give pending operations a chance to complete.
+            self.poll_and_advance_clock();
+
+            if let Some(i) = handles.iter().position(|handle| handle.has_completed()) {
+                return Ok((i, handles[i].clone()));
+            }
+
+            if let Some(deadline) = deadline {
+                // Do not wait for the next periodic clock update, so that the deadline is noticed right away.
+                let now: Instant = self.clock_now();
+                self.advance_clock(now);
+                if self.get_now() >= deadline {
+                    return Err(Fail::new(libc::ETIMEDOUT, "timer expired"));
+                }
+            }
+
+            // Nothing but the pollers runs until a timer or the deadline expires, so skip ahead in time instead of
+            // waiting for it.
+            #[cfg(any(test, feature = "virtual-clock"))]
+            {
+                if self.virtual_clock && !self.scheduler.has_runnable_foreground_tasks() {
+                    let next: Option<Instant> = match (self.timer.next_expiry(), deadline) {
+                        (Some(expiry), Some(deadline)) => Some(expiry.min(deadline)),
+                        (expiry, deadline) => expiry.or(deadline),
+                    };
+                    if let Some(next) = next {
+                        self.advance_clock(next);
+                        continue;
+                    }
+                }
+            }
+
+            // Only external input can complete any of the operations, so do not spin until it arrives.
+            if self.is_quiescent() {
+                let timeout: Option<Duration> =
+                    deadline.map(|deadline| deadline.saturating_duration_since(self.clock_now()));
+                if let Err(e) = self.wait_for_input(timeout) {
+                    let cause: String = format!("operations cannot make progress (qts={:?})", qts);
+                    warn!("wait_any(): {}", cause);
+                    return Err(Fail::new(e.errno, &cause));
+                }
+            }
+        }
+    }
+
     /// Retrieves the [TaskHandle] associated with the given [QToken] `qt`.
     pub fn from_task_id(&self, qt: QToken) -> Result<TaskHandle, Fail> {
         match self.scheduler.from_task_id(qt.into()) {
@@ -322,11 +563,42 @@ impl SharedDemiRuntime {
         }
     }
 
-    /// Allocates a queue of type `T` and returns the associated queue descriptor.
-    pub fn alloc_queue<T: IoQueue>(&mut self, queue: T) -> QDesc {
+    /// Allocates a queue of type `T` and returns the associated queue descriptor. This fails with `EMFILE` if the
+    /// maximum number of open queues has been reached, in which case `queue` is dropped.
+    pub fn alloc_queue<T: IoQueue>(&mut self, queue: T) -> Result<QDesc, Fail> {
+        if let Some(max_queues) = self.max_queues {
+            if self.qtable.len() >= max_queues {
+                let cause: String = format!("too many open queues (max_queues={:?})", max_queues);
+                warn!("alloc_queue(): {}", cause);
+                return Err(Fail::new(libc::EMFILE, &cause));
+            }
+        }
         let qd: QDesc = self.qtable.alloc::<T>(queue);
         trace!("Allocating new queue: qd={:?}", qd);
-        qd
+        Ok(qd)
+    }
+
+    /// Sets the maximum number of queues that may be open at the same time, or removes the limit if `max_queues` is
+    /// `None`. Queues that are already open are kept, even if they exceed the new limit.
+    pub fn set_max_queues(&mut self, max_queues: Option<usize>) {
+        self.max_queues = max_queues;
+    }
+
+    /// Sets the maximum number of buffers that are kept for reuse by pops once the application frees them. Pops
+    /// allocate fresh buffers when none is left, and buffers are not kept at all if `max_buffers` is zero.
+    pub fn set_buffer_pool_size(&mut self, max_buffers: usize) {
+        self.buffer_pool.set_max_buffers(max_buffers);
+    }
+
+    /// Allocates a buffer of `size` bytes for the result of a pop, out of the buffer pool if it has any buffer left.
+    pub fn alloc_pop_buffer(&mut self, size: usize) -> DemiBuffer {
+        debug_assert!(size <= limits::POP_SIZE_MAX);
+        self.buffer_pool.alloc(size as u16)
+    }
+
+    /// Returns the buffer pool, which transports receive data into, so that pops hand it over without copying it.
+    pub fn get_buffer_pool(&self) -> SharedBufferPool {
+        self.buffer_pool.clone()
     }
 
     /// Returns a reference to the I/O queue table.
@@ -400,9 +672,33 @@ impl SharedDemiRuntime {
         }
     }
 
+    /// Checks whether the shared ephemeral port allocator has any free port left.
+    pub fn has_free_ephemeral_ports(&self) -> bool {
+        self.ephemeral_ports.has_free_ports()
+    }
+
     /// Checks if a port is private.
-    pub fn is_private_ephemeral_port(port: u16) -> bool {
-        EphemeralPorts::is_private(port)
+    pub fn is_private_ephemeral_port(&self, port: u16) -> bool {
+        self.ephemeral_ports.is_private(port)
+    }
+
+    /// Replaces the shared ephemeral port allocator with one configured by `config`. This fails if any ephemeral port
+    /// is currently allocated.
+    pub fn set_ephemeral_port_config(&mut self, config: EphemeralPortConfig) -> Result<(), Fail> {
+        if self.ephemeral_ports.has_allocated_ports() {
+            let cause: String = format!("cannot change the ephemeral port range while ports are in use");
+            error!("set_ephemeral_port_config(): {}", &cause);
+            return Err(Fail::new(libc::EBUSY, &cause));
+        }
+        self.ephemeral_ports = EphemeralPorts::new(&config);
+        self.ephemeral_port_config = config;
+        Ok(())
+    }
+
+    /// Gets the behavior of the shared ephemeral port allocator when all ports are in use. Operations that allocate
+    /// ports synchronously (e.g. binding to port zero) always fail right away.
+    pub fn get_ephemeral_port_exhaustion(&self) -> EphemeralPortExhaustion {
+        self.ephemeral_port_config.get_exhaustion()
     }
 
     /// Returns a reference to the shared timer.
@@ -415,6 +711,26 @@ impl SharedDemiRuntime {
         self.timer.advance_clock(now)
     }
 
+    /// Moves the clock of a runtime created with [new_with_virtual_clock](Self::new_with_virtual_clock) forward by
+    /// `duration`, firing the timers that expire on the way.
+    #[cfg(any(test, feature = "virtual-clock"))]
+    pub fn advance_virtual_clock(&mut self, duration: Duration) {
+        debug_assert!(self.virtual_clock);
+        let now: Instant = self.get_now() + duration;
+        self.advance_clock(now)
+    }
+
+    /// Reads the time that the clock should be advanced to: the wall clock, unless the clock is virtual.
+    fn clock_now(&self) -> Instant {
+        #[cfg(any(test, feature = "virtual-clock"))]
+        {
+            if self.virtual_clock {
+                return self.get_now();
+            }
+        }
+        Instant::now()
+    }
+
     /// Gets the current time according to our internal timer.
     pub fn get_now(&self) -> Instant {
         self.timer.now()
@@ -487,13 +803,29 @@ impl SharedDemiRuntime {
                     qr_value,
                 }
             },
-            OperationResult::Push => demi_qresult_t {
+            OperationResult::Push(nbytes) => demi_qresult_t {
                 qr_opcode: demi_opcode_t::DEMI_OPC_PUSH,
                 qr_qd: qd.into(),
                 qr_qt: qt,
-                qr_ret: 0,
+                // Report how many bytes were accepted, as write() does.
+                qr_ret: nbytes as i64,
                 qr_value: unsafe { mem::zeroed() },
             },
+            // The ancillary data of the datagram follows the scatter-gather array, which is packed as for any other pop.
+            OperationResult::PopWithInfo(addr, info, bytes) => {
+                let mut qr: demi_qresult_t = self.pack_result(OperationResult::Pop(Some(addr), bytes), qd, qt);
+                if qr.qr_opcode == demi_opcode_t::DEMI_OPC_POP {
+                    let dst_addr: SocketAddrV4 = SocketAddrV4::new(info.dst_addr, 0);
+                    qr.qr_value = demi_qr_value_t {
+                        pinfo: demi_pop_info_result_t {
+                            sga: unsafe { qr.qr_value.sga },
+                            dst_addr: socketaddrv4_to_sockaddr(&dst_addr),
+                            ifindex: info.ifindex,
+                        },
+                    };
+                }
+                qr
+            },
             OperationResult::Pop(addr, bytes) => match self.into_sgarray(bytes) {
                 Ok(mut sga) => {
                     if let Some(addr) = addr {
@@ -519,6 +851,21 @@ impl SharedDemiRuntime {
                     }
                 },
             },
+            OperationResult::PopInto(addr, mut sga, nbytes) => {
+                // The scatter-gather array belongs to the caller, so hand it back as is and report the number of bytes
+                // that were copied into it instead.
+                if let Some(addr) = addr {
+                    sga.sga_addr = socketaddrv4_to_sockaddr(&addr);
+                }
+                let qr_value: demi_qr_value_t = demi_qr_value_t { sga };
+                demi_qresult_t {
+                    qr_opcode: demi_opcode_t::DEMI_OPC_POP,
+                    qr_qd: qd.into(),
+                    qr_qt: qt,
+                    qr_ret: nbytes as i64,
+                    qr_value,
+                }
+            },
             OperationResult::Close => demi_qresult_t {
                 qr_opcode: demi_opcode_t::DEMI_OPC_CLOSE,
                 qr_qd: qd.into(),
@@ -526,6 +873,14 @@ impl SharedDemiRuntime {
                 qr_ret: 0,
                 qr_value: unsafe { mem::zeroed() },
             },
+            // Resolutions are not associated with any queue, and there is nothing to hand back beyond their completion.
+            OperationResult::Resolve(_) => demi_qresult_t {
+                qr_opcode: demi_opcode_t::DEMI_OPC_RESOLVE,
+                qr_qd: qd.into(),
+                qr_qt: qt,
+                qr_ret: 0,
+                qr_value: unsafe { mem::zeroed() },
+            },
             OperationResult::Failed(e) => {
                 warn!("Operation Failed: {:?}", e);
                 demi_qresult_t {
@@ -557,7 +912,12 @@ impl<T: ?Sized> SharedBox<T> {
 //======================================================================================================================
 
 /// Memory Runtime Trait Implementation for POSIX Runtime
-impl MemoryRuntime for SharedDemiRuntime {}
+impl MemoryRuntime for SharedDemiRuntime {
+    /// Gives buffers that the application frees back to the buffer pool.
+    fn recycle_buffer(&self, buf: DemiBuffer) {
+        self.clone().buffer_pool.recycle(buf);
+    }
+}
 
 impl Default for SharedDemiRuntime {
     fn default() -> Self {
@@ -651,3 +1011,463 @@ impl DerefMut for SharedDemiRuntime {
 
 /// Demikernel Runtime
 pub trait Runtime: Clone + Unpin + 'static {}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::socketaddrv4_to_sockaddr;
+    use crate::{
+        pal::data_structures::SockAddr,
+        runtime::{
+            memory::{
+                DemiBuffer,
+                MemoryRuntime,
+            },
+            network::types::PacketInfo,
+            scheduler::{
+                TaskHandle,
+                Yielder,
+                YielderHandle,
+            },
+            timer::SharedTimer,
+            types::{
+                demi_opcode_t,
+                demi_pop_info_result_t,
+                demi_qresult_t,
+                demi_sgarray_t,
+                demi_sgaseg_t,
+            },
+            IdleHook,
+            OperationResult,
+            QDesc,
+            QToken,
+            SharedDemiRuntime,
+        },
+    };
+    use ::anyhow::Result;
+    use ::futures::future::poll_fn;
+    use ::std::{
+        cell::Cell,
+        net::{
+            Ipv4Addr,
+            SocketAddrV4,
+        },
+        rc::Rc,
+        task::Poll,
+        thread,
+        time::{
+            Duration,
+            Instant,
+        },
+    };
+
+    /// Inserts a background coroutine that needs `num_polls` polls to complete, and returns a counter of the number of
+    /// times that it was polled.
+    fn insert_counting_coroutine(runtime: &mut SharedDemiRuntime, num_polls: usize) -> Result<Rc<Cell<usize>>> {
+        let counter: Rc<Cell<usize>> = Rc::new(Cell::new(0));
+        let counter_clone: Rc<Cell<usize>> = counter.clone();
+        let coroutine = poll_fn(move |ctx| {
+            counter_clone.set(counter_clone.get() + 1);
+            if counter_clone.get() >= num_polls {
+                Poll::Ready(())
+            } else {
+                ctx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        });
+        runtime.insert_background_coroutine("counting coroutine", Box::pin(coroutine))?;
+        Ok(counter)
+    }
+
+    /// Inserts a poller that never completes, and returns a counter of the number of times that it was polled.
+    fn insert_counting_poller(runtime: &mut SharedDemiRuntime) -> Result<Rc<Cell<usize>>> {
+        let counter: Rc<Cell<usize>> = Rc::new(Cell::new(0));
+        let counter_clone: Rc<Cell<usize>> = counter.clone();
+        let poller = poll_fn(move |ctx| {
+            counter_clone.set(counter_clone.get() + 1);
+            ctx.waker().wake_by_ref();
+            Poll::<()>::Pending
+        });
+        runtime.insert_poller_coroutine("counting poller", Box::pin(poller))?;
+        Ok(counter)
+    }
+
+    /// Inserts a poller that never completes along with an idle hook that sleeps until the timeout expires, and returns
+    /// a counter of the number of times that the hook was called.
+    fn insert_poller_with_idle_hook(runtime: &mut SharedDemiRuntime) -> Result<Rc<Cell<usize>>> {
+        let num_idle_waits: Rc<Cell<usize>> = Rc::new(Cell::new(0));
+        let num_idle_waits_clone: Rc<Cell<usize>> = num_idle_waits.clone();
+        let poller = poll_fn(move |ctx| {
+            ctx.waker().wake_by_ref();
+            Poll::<()>::Pending
+        });
+        let idle_hook: IdleHook = Box::new(move |timeout: Option<Duration>| {
+            num_idle_waits_clone.set(num_idle_waits_clone.get() + 1);
+            if let Some(timeout) = timeout {
+                thread::sleep(timeout);
+            }
+        });
+        runtime.insert_poller_coroutine_with_idle_hook("idle poller", Box::pin(poller), idle_hook)?;
+        Ok(num_idle_waits)
+    }
+
+    /// Inserts an operation that needs `num_polls` polls to complete, and returns its queue token along with a counter
+    /// of the number of times that it was polled.
+    fn insert_counting_operation(
+        runtime: &mut SharedDemiRuntime,
+        num_polls: usize,
+    ) -> Result<(QToken, Rc<Cell<usize>>)> {
+        let counter: Rc<Cell<usize>> = Rc::new(Cell::new(0));
+        let counter_clone: Rc<Cell<usize>> = counter.clone();
+        let coroutine = poll_fn(move |ctx| {
+            counter_clone.set(counter_clone.get() + 1);
+            if counter_clone.get() >= num_polls {
+                Poll::Ready((QDesc::from(0), OperationResult::Close))
+            } else {
+                ctx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        });
+        let handle: TaskHandle = runtime.insert_coroutine("counting operation", Box::pin(coroutine))?;
+        Ok((handle.get_task_id().into(), counter))
+    }
+
+    /// Inserts an operation that completes once `timeout` elapses, and returns its queue token.
+    fn insert_timer_operation(runtime: &mut SharedDemiRuntime, timeout: Duration) -> Result<QToken> {
+        let timer: SharedTimer = runtime.get_timer();
+        let coroutine = async move {
+            let yielder: Yielder = Yielder::new();
+            let _ = timer.wait(timeout, &yielder).await;
+            (QDesc::from(0), OperationResult::Close)
+        };
+        let handle: TaskHandle = runtime.insert_coroutine("timer operation", Box::pin(coroutine))?;
+        Ok(handle.get_task_id().into())
+    }
+
+    /// Tests that bounded polling returns right away when there is nothing to run.
+    #[test]
+    fn poll_bounded_without_tasks() -> Result<()> {
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::new(Instant::now());
+        crate::ensure_eq!(runtime.poll_bounded(1000), 0);
+
+        Ok(())
+    }
+
+    /// Tests that bounded polling does not poll the scheduler more than the given number of times.
+    #[test]
+    fn poll_bounded_respects_iteration_cap() -> Result<()> {
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::new(Instant::now());
+        let first: Rc<Cell<usize>> = insert_counting_coroutine(&mut runtime, usize::MAX)?;
+        let second: Rc<Cell<usize>> = insert_counting_coroutine(&mut runtime, usize::MAX)?;
+
+        crate::ensure_eq!(runtime.poll_bounded(3), 6);
+        crate::ensure_eq!(first.get(), 3);
+        crate::ensure_eq!(second.get(), 3);
+
+        crate::ensure_eq!(runtime.poll_bounded(0), 0);
+        crate::ensure_eq!(first.get(), 3);
+
+        Ok(())
+    }
+
+    /// Tests that bounded polling stops early once no task is runnable.
+    #[test]
+    fn poll_bounded_stops_when_idle() -> Result<()> {
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::new(Instant::now());
+        let counter: Rc<Cell<usize>> = insert_counting_coroutine(&mut runtime, 2)?;
+
+        crate::ensure_eq!(runtime.poll_bounded(100), 2);
+        crate::ensure_eq!(counter.get(), 2);
+        crate::ensure_eq!(runtime.poll_bounded(100), 0);
+
+        Ok(())
+    }
+
+    /// Tests that a runtime whose only coroutine waits for a wake up that never comes is quiescent, and that polling it
+    /// does no work.
+    #[test]
+    fn quiescent_with_idle_coroutine() -> Result<()> {
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::new(Instant::now());
+        crate::ensure_eq!(runtime.is_quiescent(), true);
+
+        let yielder: Yielder = Yielder::new();
+        let mut handle: YielderHandle = yielder.get_handle();
+        let coroutine = async move {
+            let _ = yielder.yield_until_wake().await;
+        };
+        runtime.insert_background_coroutine("idle coroutine", Box::pin(coroutine))?;
+        crate::ensure_eq!(runtime.is_quiescent(), false);
+
+        // Once the coroutine blocks, nothing can make progress.
+        crate::ensure_eq!(runtime.poll(), 1);
+        crate::ensure_eq!(runtime.is_quiescent(), true);
+        crate::ensure_eq!(runtime.poll_bounded(1000), 0);
+
+        // Waking the coroutine makes the runtime runnable again.
+        handle.wake_with(Ok(()));
+        crate::ensure_eq!(runtime.is_quiescent(), false);
+        crate::ensure_eq!(runtime.poll(), 1);
+        crate::ensure_eq!(runtime.is_quiescent(), true);
+
+        Ok(())
+    }
+
+    /// Tests that a runtime with a runnable coroutine or a pending timer is not quiescent.
+    #[test]
+    fn not_quiescent_with_pending_work() -> Result<()> {
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::new(Instant::now());
+        let counter: Rc<Cell<usize>> = insert_counting_coroutine(&mut runtime, 2)?;
+        runtime.poll();
+        crate::ensure_eq!(runtime.is_quiescent(), false);
+        runtime.poll();
+        crate::ensure_eq!(counter.get(), 2);
+        crate::ensure_eq!(runtime.is_quiescent(), true);
+
+        let now: Instant = runtime.get_now();
+        let timer_runtime: SharedDemiRuntime = runtime.clone();
+        let coroutine = async move {
+            let yielder: Yielder = Yielder::new();
+            let _ = timer_runtime.get_timer().wait(Duration::from_secs(1), &yielder).await;
+        };
+        runtime.insert_background_coroutine("timer coroutine", Box::pin(coroutine))?;
+        runtime.poll();
+        crate::ensure_eq!(runtime.is_quiescent(), false);
+
+        // The timer expires and the coroutine completes.
+        runtime.advance_clock(now + Duration::from_secs(1));
+        crate::ensure_eq!(runtime.poll(), 1);
+        crate::ensure_eq!(runtime.is_quiescent(), true);
+
+        Ok(())
+    }
+
+    /// Tests that a poller does not keep the runtime from being quiescent, and that waiting from a quiescent state fails
+    /// with EDEADLK if there is no poller, and blocks on the idle hook otherwise.
+    #[test]
+    fn wait_any_from_quiescent_state() -> Result<()> {
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::new(Instant::now());
+        let yielder: Yielder = Yielder::new();
+        let coroutine = async move {
+            let _ = yielder.yield_until_wake().await;
+            (QDesc::from(0), OperationResult::Close)
+        };
+        let handle: TaskHandle = runtime.insert_coroutine("idle operation", Box::pin(coroutine))?;
+        let qt: QToken = handle.get_task_id().into();
+
+        match runtime.wait_any(&[qt], None) {
+            Err(e) if e.errno == libc::EDEADLK => (),
+            Err(e) => anyhow::bail!("wait should have failed with EDEADLK: {:?}", e),
+            Ok(_) => anyhow::bail!("operation should not have completed"),
+        }
+
+        // The poller is runnable on every poll, but the runtime is still quiescent.
+        let num_idle_waits: Rc<Cell<usize>> = insert_poller_with_idle_hook(&mut runtime)?;
+        crate::ensure_eq!(runtime.poll(), 1);
+        crate::ensure_eq!(runtime.poll(), 1);
+        crate::ensure_eq!(runtime.is_quiescent(), true);
+
+        // The wait blocks on the idle hook until the timeout expires.
+        match runtime.wait_any(&[qt], Some(Duration::from_millis(10))) {
+            Err(e) if e.errno == libc::ETIMEDOUT => (),
+            Err(e) => anyhow::bail!("wait should have timed out: {:?}", e),
+            Ok(_) => anyhow::bail!("operation should not have completed"),
+        }
+        crate::ensure_eq!(num_idle_waits.get() > 0, true);
+
+        Ok(())
+    }
+
+    /// Inserts an operation that completes only when woken, and returns its queue token.
+    fn insert_idle_operation(runtime: &mut SharedDemiRuntime) -> Result<QToken> {
+        let yielder: Yielder = Yielder::new();
+        let coroutine = async move {
+            let _ = yielder.yield_until_wake().await;
+            (QDesc::from(0), OperationResult::Close)
+        };
+        let handle: TaskHandle = runtime.insert_coroutine("idle operation", Box::pin(coroutine))?;
+        Ok(handle.get_task_id().into())
+    }
+
+    /// Tests that waiting from a quiescent state does not block on the idle hook while a poller without a readiness
+    /// primitive runs, and keeps polling it instead.
+    #[test]
+    fn wait_any_does_not_block_past_poller_without_idle_hook() -> Result<()> {
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::new(Instant::now());
+        let qt: QToken = insert_idle_operation(&mut runtime)?;
+        let num_idle_waits: Rc<Cell<usize>> = insert_poller_with_idle_hook(&mut runtime)?;
+        let counter: Rc<Cell<usize>> = insert_counting_poller(&mut runtime)?;
+
+        match runtime.wait_any(&[qt], Some(Duration::from_millis(10))) {
+            Err(e) if e.errno == libc::ETIMEDOUT => (),
+            Err(e) => anyhow::bail!("wait should have timed out: {:?}", e),
+            Ok(_) => anyhow::bail!("operation should not have completed"),
+        }
+        crate::ensure_eq!(num_idle_waits.get(), 0);
+        crate::ensure_eq!(counter.get() > 1, true);
+
+        Ok(())
+    }
+
+    /// Tests that a second idle hook does not replace the first one, and that its poller is treated as having no
+    /// readiness primitive, so that neither hook is blocked on.
+    #[test]
+    fn insert_poller_keeps_first_idle_hook() -> Result<()> {
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::new(Instant::now());
+        let qt: QToken = insert_idle_operation(&mut runtime)?;
+        let first_num_idle_waits: Rc<Cell<usize>> = insert_poller_with_idle_hook(&mut runtime)?;
+        let second_num_idle_waits: Rc<Cell<usize>> = insert_poller_with_idle_hook(&mut runtime)?;
+
+        match runtime.wait_any(&[qt], Some(Duration::from_millis(10))) {
+            Err(e) if e.errno == libc::ETIMEDOUT => (),
+            Err(e) => anyhow::bail!("wait should have timed out: {:?}", e),
+            Ok(_) => anyhow::bail!("operation should not have completed"),
+        }
+        crate::ensure_eq!(first_num_idle_waits.get(), 0);
+        crate::ensure_eq!(second_num_idle_waits.get(), 0);
+
+        Ok(())
+    }
+
+    /// Tests that waiting fails with ETIMEDOUT once the timeout expires, and that the operation is left pending.
+    #[test]
+    fn wait_any_times_out() -> Result<()> {
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::new(Instant::now());
+        let (qt, counter): (QToken, Rc<Cell<usize>>) = insert_counting_operation(&mut runtime, usize::MAX)?;
+
+        let start: Instant = Instant::now();
+        match runtime.wait_any(&[qt], Some(Duration::from_millis(10))) {
+            Err(e) if e.errno == libc::ETIMEDOUT => (),
+            Err(e) => anyhow::bail!("wait should have timed out: {:?}", e),
+            Ok(_) => anyhow::bail!("operation should not have completed"),
+        }
+        crate::ensure_eq!(Instant::now().duration_since(start) >= Duration::from_millis(10), true);
+        crate::ensure_eq!(counter.get() > 0, true);
+
+        // The operation is still there, and can be waited on again.
+        let handle: TaskHandle = runtime.from_task_id(qt)?;
+        crate::ensure_eq!(handle.has_completed(), false);
+        let num_polls: usize = counter.get();
+        runtime.poll();
+        crate::ensure_eq!(counter.get(), num_polls + 1);
+
+        Ok(())
+    }
+
+    /// Tests that a zero timeout polls exactly once, and that an operation that completes within that poll is
+    /// returned.
+    #[test]
+    fn wait_any_zero_timeout_polls_once() -> Result<()> {
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::new(Instant::now());
+        let (qt, counter): (QToken, Rc<Cell<usize>>) = insert_counting_operation(&mut runtime, 2)?;
+
+        match runtime.wait_any(&[qt], Some(Duration::ZERO)) {
+            Err(e) if e.errno == libc::ETIMEDOUT => (),
+            Err(e) => anyhow::bail!("wait should have timed out: {:?}", e),
+            Ok(_) => anyhow::bail!("operation should not have completed"),
+        }
+        crate::ensure_eq!(counter.get(), 1);
+
+        // The operation completes on the next poll.
+        let (offset, handle): (usize, TaskHandle) = runtime.wait_any(&[qt], Some(Duration::ZERO))?;
+        crate::ensure_eq!(offset, 0);
+        crate::ensure_eq!(handle.has_completed(), true);
+        crate::ensure_eq!(counter.get(), 2);
+        match runtime.remove_coroutine(&handle).get_result() {
+            Some((_, OperationResult::Close)) => (),
+            _ => anyhow::bail!("operation should have completed"),
+        }
+
+        Ok(())
+    }
+
+    /// Tests that waiting on a runtime with a virtual clock skips ahead to the timer that completes the operation,
+    /// instead of sleeping until it expires.
+    #[test]
+    fn wait_any_virtual_clock_skips_to_timer() -> Result<()> {
+        let start: Instant = Instant::now();
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::new_with_virtual_clock(start);
+        let qt: QToken = insert_timer_operation(&mut runtime, Duration::from_secs(10))?;
+
+        let (_, handle): (usize, TaskHandle) = runtime.wait_any(&[qt], None)?;
+        crate::ensure_eq!(handle.has_completed(), true);
+        crate::ensure_eq!(runtime.get_now(), start + Duration::from_secs(10));
+
+        Ok(())
+    }
+
+    /// Tests that a poller, which is runnable on every poll, does not keep a wait on a runtime with a virtual clock from
+    /// skipping ahead to the next timer.
+    #[test]
+    fn wait_any_virtual_clock_skips_past_poller() -> Result<()> {
+        let start: Instant = Instant::now();
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::new_with_virtual_clock(start);
+        let counter: Rc<Cell<usize>> = insert_counting_poller(&mut runtime)?;
+        let qt: QToken = insert_timer_operation(&mut runtime, Duration::from_secs(10))?;
+
+        let (_, handle): (usize, TaskHandle) = runtime.wait_any(&[qt], None)?;
+        crate::ensure_eq!(handle.has_completed(), true);
+        crate::ensure_eq!(runtime.get_now(), start + Duration::from_secs(10));
+        crate::ensure_eq!(counter.get() > 0, true);
+
+        Ok(())
+    }
+
+    /// Tests that a wait with a timeout on a runtime with a virtual clock expires at the exact virtual deadline, and
+    /// that the clock only moves when advanced.
+    #[test]
+    fn wait_any_virtual_clock_times_out() -> Result<()> {
+        let start: Instant = Instant::now();
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::new_with_virtual_clock(start);
+        let qt: QToken = insert_timer_operation(&mut runtime, Duration::from_secs(60))?;
+
+        match runtime.wait_any(&[qt], Some(Duration::from_secs(1))) {
+            Err(e) if e.errno == libc::ETIMEDOUT => (),
+            Err(e) => anyhow::bail!("wait should have timed out: {:?}", e),
+            Ok(_) => anyhow::bail!("operation should not have completed"),
+        }
+        crate::ensure_eq!(runtime.get_now(), start + Duration::from_secs(1));
+        runtime.poll_and_advance_clock();
+        crate::ensure_eq!(runtime.get_now(), start + Duration::from_secs(1));
+
+        // Advancing the clock manually completes the operation.
+        runtime.advance_virtual_clock(Duration::from_secs(59));
+        runtime.poll();
+        crate::ensure_eq!(runtime.from_task_id(qt)?.has_completed(), true);
+
+        Ok(())
+    }
+
+    /// Tests that the result of a pop that carries the ancillary data of a datagram hands it to the application, along
+    /// with the popped scatter-gather array.
+    #[test]
+    fn pack_result_with_pktinfo() -> Result<()> {
+        let runtime: SharedDemiRuntime = SharedDemiRuntime::new(Instant::now());
+        let src_addr: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 1), 8080);
+        let info: PacketInfo = PacketInfo {
+            dst_addr: Ipv4Addr::new(192, 168, 1, 2),
+            ifindex: 7,
+        };
+        let buf: DemiBuffer = DemiBuffer::from_slice(&[0x5a; 32])?;
+        let result: OperationResult = OperationResult::PopWithInfo(src_addr, info, buf);
+
+        let qr: demi_qresult_t = runtime.pack_result(result, QDesc::from(0), 0);
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_POP);
+        let pinfo: demi_pop_info_result_t = unsafe { qr.qr_value.pinfo };
+        let ifindex: u32 = pinfo.ifindex;
+        crate::ensure_eq!(ifindex, 7);
+        let dst_addr: SockAddr = pinfo.dst_addr;
+        let expected: SockAddr = socketaddrv4_to_sockaddr(&SocketAddrV4::new(info.dst_addr, 0));
+        crate::ensure_eq!(dst_addr.sa_data, expected.sa_data);
+        // The scatter-gather array is the same as for any other pop.
+        let sga: demi_sgarray_t = pinfo.sga;
+        let segs: [demi_sgaseg_t; 1] = sga.sga_segs;
+        let sga_len: u32 = segs[0].sgaseg_len;
+        crate::ensure_eq!(sga_len, 32);
+        runtime.sgafree(sga)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,174 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::runtime::fail::Fail;
+use ::std::time::Duration;
+
+//==============================================================================
+// Constants & Structures
+//==============================================================================
+
+/// Default First Ephemeral Port. See https://datatracker.ietf.org/doc/html/rfc6335 for details.
+const DEFAULT_FIRST_PORT: u16 = 49152;
+/// Default Last Ephemeral Port. See https://datatracker.ietf.org/doc/html/rfc6335 for details.
+const DEFAULT_LAST_PORT: u16 = 65535;
+
+/// Behavior When All Ephemeral Ports Are in Use
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EphemeralPortExhaustion {
+    /// Fail Immediately With EADDRNOTAVAIL
+    Fail,
+    /// Wait Up to the Given Duration for a Port to be Freed
+    Wait(Duration),
+}
+
+/// Ephemeral Port Configuration Descriptor
+#[derive(Clone, Debug)]
+pub struct EphemeralPortConfig {
+    /// First Port in the Ephemeral Port Range
+    first_port: u16,
+    /// Last Port in the Ephemeral Port Range (Inclusive)
+    last_port: u16,
+    /// Behavior When All Ephemeral Ports Are in Use
+    exhaustion: EphemeralPortExhaustion,
+}
+
+//==============================================================================
+// Associate Functions
+//==============================================================================
+
+/// Associate functions for Ephemeral Port Configuration Descriptor
+impl EphemeralPortConfig {
+    /// Creates an Ephemeral Port Configuration Descriptor. Fails if the resulting port range is empty or includes port
+    /// zero.
+    pub fn new(
+        first_port: Option<u16>,
+        last_port: Option<u16>,
+        exhaustion: Option<EphemeralPortExhaustion>,
+    ) -> Result<Self, Fail> {
+        let mut config = Self::default();
+        if let Some(first_port) = first_port {
+            config.set_first_port(first_port);
+        }
+        if let Some(last_port) = last_port {
+            config.set_last_port(last_port);
+        }
+        if let Some(exhaustion) = exhaustion {
+            config.set_exhaustion(exhaustion);
+        }
+
+        if config.first_port == 0 || config.first_port > config.last_port {
+            let cause: String = format!(
+                "invalid ephemeral port range (first_port={:?}, last_port={:?})",
+                config.first_port, config.last_port
+            );
+            error!("new(): {}", &cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+
+        Ok(config)
+    }
+
+    /// Gets the first port of the ephemeral port range in the target [EphemeralPortConfig].
+    pub fn get_first_port(&self) -> u16 {
+        self.first_port
+    }
+
+    /// Gets the last port of the ephemeral port range in the target [EphemeralPortConfig]. This port is part of the
+    /// range.
+    pub fn get_last_port(&self) -> u16 {
+        self.last_port
+    }
+
+    /// Gets the behavior when all ephemeral ports are in use in the target [EphemeralPortConfig].
+    pub fn get_exhaustion(&self) -> EphemeralPortExhaustion {
+        self.exhaustion
+    }
+
+    /// Sets the first port of the ephemeral port range in the target [EphemeralPortConfig].
+    fn set_first_port(&mut self, first_port: u16) {
+        self.first_port = first_port;
+    }
+
+    /// Sets the last port of the ephemeral port range in the target [EphemeralPortConfig].
+    fn set_last_port(&mut self, last_port: u16) {
+        self.last_port = last_port;
+    }
+
+    /// Sets the behavior when all ephemeral ports are in use in the target [EphemeralPortConfig].
+    fn set_exhaustion(&mut self, exhaustion: EphemeralPortExhaustion) {
+        self.exhaustion = exhaustion;
+    }
+}
+
+//==============================================================================
+// Trait Implementations
+//==============================================================================
+
+/// Default Trait Implementation for Ephemeral Port Configuration Descriptor
+impl Default for EphemeralPortConfig {
+    /// Creates an Ephemeral Port Configuration Descriptor with the default values.
+    fn default() -> Self {
+        EphemeralPortConfig {
+            first_port: DEFAULT_FIRST_PORT,
+            last_port: DEFAULT_LAST_PORT,
+            exhaustion: EphemeralPortExhaustion::Fail,
+        }
+    }
+}
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        EphemeralPortConfig,
+        EphemeralPortExhaustion,
+        DEFAULT_FIRST_PORT,
+        DEFAULT_LAST_PORT,
+    };
+    use ::anyhow::Result;
+    use ::std::time::Duration;
+
+    /// Tests default instantiation for [EphemeralPortConfig].
+    #[test]
+    fn test_ephemeral_port_config_default() -> Result<()> {
+        let config: EphemeralPortConfig = EphemeralPortConfig::default();
+        crate::ensure_eq!(config.get_first_port(), DEFAULT_FIRST_PORT);
+        crate::ensure_eq!(config.get_last_port(), DEFAULT_LAST_PORT);
+        crate::ensure_eq!(config.get_exhaustion(), EphemeralPortExhaustion::Fail);
+
+        Ok(())
+    }
+
+    /// Tests custom instantiation for [EphemeralPortConfig].
+    #[test]
+    fn test_ephemeral_port_config_custom() -> Result<()> {
+        let exhaustion: EphemeralPortExhaustion = EphemeralPortExhaustion::Wait(Duration::from_secs(1));
+        let config: EphemeralPortConfig = EphemeralPortConfig::new(Some(1), Some(1), Some(exhaustion))?;
+        crate::ensure_eq!(config.get_first_port(), 1);
+        crate::ensure_eq!(config.get_last_port(), 1);
+        crate::ensure_eq!(config.get_exhaustion(), exhaustion);
+
+        Ok(())
+    }
+
+    /// Tests that invalid port ranges are rejected.
+    #[test]
+    fn test_ephemeral_port_config_invalid_range() -> Result<()> {
+        crate::ensure_eq!(EphemeralPortConfig::new(Some(0), Some(10), None).is_err(), true);
+        crate::ensure_eq!(EphemeralPortConfig::new(Some(10), Some(9), None).is_err(), true);
+        crate::ensure_eq!(
+            EphemeralPortConfig::new(Some(DEFAULT_LAST_PORT), None, None).is_ok(),
+            true
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::runtime::fail::Fail;
+
+//==============================================================================
+// Constants & Structures
+//==============================================================================
+
+/// Ethernet Configuration Descriptor
+#[derive(Clone, Debug)]
+pub struct Ethernet2Config {
+    /// Pad Outgoing Frames to the Minimum Frame Size?
+    pad_frames: bool,
+    /// VLAN Identifier of Tagged Frames
+    vlan_id: Option<u16>,
+}
+
+//==============================================================================
+// Associate Functions
+//==============================================================================
+
+/// Associate functions for Ethernet Configuration Descriptor
+impl Ethernet2Config {
+    /// Creates an Ethernet Configuration Descriptor. Fails with `EINVAL` if the VLAN identifier is reserved.
+    pub fn new(pad_frames: Option<bool>, vlan_id: Option<u16>) -> Result<Self, Fail> {
+        let mut config = Self::default();
+        if let Some(pad_frames) = pad_frames {
+            config.set_pad_frames(pad_frames);
+        }
+        if let Some(vlan_id) = vlan_id {
+            config.set_vlan_id(vlan_id)?;
+        }
+        Ok(config)
+    }
+
+    /// Gets whether outgoing frames that are shorter than the minimum Ethernet frame size are zero-padded in the
+    /// target [Ethernet2Config]. Padding should only be disabled if the device pads frames on its own.
+    pub fn get_pad_frames(&self) -> bool {
+        self.pad_frames
+    }
+
+    /// Sets whether outgoing frames are padded in the target [Ethernet2Config].
+    fn set_pad_frames(&mut self, pad_frames: bool) {
+        self.pad_frames = pad_frames;
+    }
+
+    /// Gets the VLAN identifier in the target [Ethernet2Config]. When set, outgoing frames are tagged with it
+    /// (802.1Q), and incoming frames that are untagged or carry another identifier are dropped.
+    pub fn get_vlan_id(&self) -> Option<u16> {
+        self.vlan_id
+    }
+
+    /// Sets the VLAN identifier in the target [Ethernet2Config]. Identifiers 0 and 4095 are reserved.
+    fn set_vlan_id(&mut self, vlan_id: u16) -> Result<(), Fail> {
+        if !(1..=4094).contains(&vlan_id) {
+            let cause: String = format!("invalid VLAN identifier (vlan_id={:?})", vlan_id);
+            error!("set_vlan_id(): {}", &cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        self.vlan_id = Some(vlan_id);
+        Ok(())
+    }
+}
+
+//==============================================================================
+// Trait Implementations
+//==============================================================================
+
+/// Default Trait Implementation for Ethernet Configuration Descriptor
+impl Default for Ethernet2Config {
+    /// Creates an Ethernet Configuration Descriptor with the default values.
+    fn default() -> Self {
+        Ethernet2Config {
+            pad_frames: true,
+            vlan_id: None,
+        }
+    }
+}
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::Ethernet2Config;
+    use ::anyhow::Result;
+
+    /// Tests default instantiation for [Ethernet2Config].
+    #[test]
+    fn test_ethernet2_config_default() -> Result<()> {
+        let config: Ethernet2Config = Ethernet2Config::default();
+        crate::ensure_eq!(config.get_pad_frames(), true);
+        crate::ensure_eq!(config.get_vlan_id(), None);
+
+        Ok(())
+    }
+
+    /// Tests custom instantiation for [Ethernet2Config].
+    #[test]
+    fn test_ethernet2_config_custom() -> Result<()> {
+        let config: Ethernet2Config = Ethernet2Config::new(Some(false), Some(42))?;
+        crate::ensure_eq!(config.get_pad_frames(), false);
+        crate::ensure_eq!(config.get_vlan_id(), Some(42));
+
+        Ok(())
+    }
+
+    /// Tests that reserved VLAN identifiers are rejected.
+    #[test]
+    fn test_ethernet2_config_reserved_vlan_id() -> Result<()> {
+        for vlan_id in [0, 4095, u16::MAX] {
+            match Ethernet2Config::new(None, Some(vlan_id)) {
+                Err(e) if e.errno == libc::EINVAL => (),
+                result => anyhow::bail!(
+                    "vlan_id={} should have been rejected with EINVAL: {:?}",
+                    vlan_id,
+                    result
+                ),
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -2,6 +2,10 @@
 // Licensed under the MIT license.
 
 mod arp;
+mod ephemeral;
+mod ethernet2;
+mod pcap;
+mod routing;
 mod tcp;
 mod udp;
 
@@ -11,6 +15,20 @@ mod udp;
 
 pub use self::{
     arp::ArpConfig,
+    ephemeral::{
+        EphemeralPortConfig,
+        EphemeralPortExhaustion,
+    },
+    ethernet2::Ethernet2Config,
+    pcap::PcapConfig,
+    routing::{
+        Ipv4Route,
+        RoutingConfig,
+    },
     tcp::TcpConfig,
-    udp::UdpConfig,
+    udp::{
+        UdpConfig,
+        UdpDropPolicy,
+        UdpRecvQueueConfig,
+    },
 };
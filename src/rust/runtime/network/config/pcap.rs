@@ -0,0 +1,106 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Constants & Structures
+//==============================================================================
+
+/// Default Maximum Size of a Capture File (in bytes)
+const DEFAULT_MAX_SIZE: usize = 64 * 1024 * 1024;
+
+/// Packet Capture Configuration Descriptor
+#[derive(Clone, Debug)]
+pub struct PcapConfig {
+    /// Path to the Capture File (Capture is Disabled if Unset)
+    path: Option<String>,
+    /// Maximum Size of the Capture File (in bytes)
+    max_size: usize,
+}
+
+//==============================================================================
+// Associate Functions
+//==============================================================================
+
+/// Associate functions for Packet Capture Configuration Descriptor
+impl PcapConfig {
+    /// Creates a Packet Capture Configuration Descriptor.
+    pub fn new(path: Option<String>, max_size: Option<usize>) -> Self {
+        let mut config = Self::default();
+        if let Some(path) = path {
+            config.set_path(path);
+        }
+        if let Some(max_size) = max_size {
+            config.set_max_size(max_size);
+        }
+        config
+    }
+
+    /// Gets the path to the capture file in the target [PcapConfig], if capture is enabled.
+    pub fn get_path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// Gets the maximum size of the capture file in the target [PcapConfig]. Packets that would grow the capture file
+    /// beyond this size are not captured.
+    pub fn get_max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Sets the path to the capture file in the target [PcapConfig].
+    fn set_path(&mut self, path: String) {
+        self.path = Some(path);
+    }
+
+    /// Sets the maximum size of the capture file in the target [PcapConfig].
+    fn set_max_size(&mut self, max_size: usize) {
+        self.max_size = max_size;
+    }
+}
+
+//==============================================================================
+// Trait Implementations
+//==============================================================================
+
+/// Default Trait Implementation for Packet Capture Configuration Descriptor
+impl Default for PcapConfig {
+    /// Creates a Packet Capture Configuration Descriptor with the default values.
+    fn default() -> Self {
+        PcapConfig {
+            path: None,
+            max_size: DEFAULT_MAX_SIZE,
+        }
+    }
+}
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        PcapConfig,
+        DEFAULT_MAX_SIZE,
+    };
+    use ::anyhow::Result;
+
+    /// Tests default instantiation for [PcapConfig].
+    #[test]
+    fn test_pcap_config_default() -> Result<()> {
+        let config: PcapConfig = PcapConfig::default();
+        crate::ensure_eq!(config.get_path(), None);
+        crate::ensure_eq!(config.get_max_size(), DEFAULT_MAX_SIZE);
+
+        Ok(())
+    }
+
+    /// Tests custom instantiation for [PcapConfig].
+    #[test]
+    fn test_pcap_config_custom() -> Result<()> {
+        let config: PcapConfig = PcapConfig::new(Some("capture.pcap".to_string()), Some(4096));
+        crate::ensure_eq!(config.get_path(), Some("capture.pcap"));
+        crate::ensure_eq!(config.get_max_size(), 4096);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,279 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::runtime::fail::Fail;
+use ::std::net::Ipv4Addr;
+
+//==============================================================================
+// Constants & Structures
+//==============================================================================
+
+/// Route towards the destinations of an IPv4 prefix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ipv4Route {
+    /// Destination prefix.
+    destination: Ipv4Addr,
+    /// Length of the destination prefix.
+    prefix_len: u8,
+    /// Next hop towards the destinations of the prefix. It must be on the local subnet.
+    next_hop: Ipv4Addr,
+}
+
+/// Routing Configuration Descriptor. By default, all destinations are on the local subnet.
+#[derive(Clone, Debug, Default)]
+pub struct RoutingConfig {
+    /// Length of the prefix of the local subnet. All destinations are on the local subnet if unset.
+    subnet_prefix_len: Option<u8>,
+    /// Next hop towards destinations that are off the local subnet and that no route covers.
+    default_gateway: Option<Ipv4Addr>,
+    /// Routes towards specific prefixes.
+    routes: Vec<Ipv4Route>,
+}
+
+//==============================================================================
+// Associate Functions
+//==============================================================================
+
+/// Associate functions for IPv4 routes.
+impl Ipv4Route {
+    /// Creates a route that sends packets for the destinations of `destination`/`prefix_len` through `next_hop`.
+    /// Fails with `EINVAL` if the prefix is longer than 32 bits.
+    pub fn new(destination: Ipv4Addr, prefix_len: u8, next_hop: Ipv4Addr) -> Result<Self, Fail> {
+        check_prefix_len(prefix_len)?;
+        Ok(Self {
+            destination,
+            prefix_len,
+            next_hop,
+        })
+    }
+
+    /// Gets the length of the destination prefix of the target [Ipv4Route].
+    pub fn get_prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Gets the next hop of the target [Ipv4Route].
+    pub fn get_next_hop(&self) -> Ipv4Addr {
+        self.next_hop
+    }
+
+    /// Checks whether `addr` is covered by the target [Ipv4Route].
+    pub fn contains(&self, addr: Ipv4Addr) -> bool {
+        same_prefix(self.destination, addr, self.prefix_len)
+    }
+}
+
+/// Associate functions for Routing Configuration Descriptor
+impl RoutingConfig {
+    /// Creates a Routing Configuration Descriptor. A default gateway can only be given along with the local subnet,
+    /// so this fails with `EINVAL` otherwise, or if the prefix of the local subnet is longer than 32 bits.
+    pub fn new(
+        subnet_prefix_len: Option<u8>,
+        default_gateway: Option<Ipv4Addr>,
+        routes: Option<Vec<Ipv4Route>>,
+    ) -> Result<Self, Fail> {
+        let mut config = Self::default();
+        if let Some(subnet_prefix_len) = subnet_prefix_len {
+            config.set_subnet_prefix_len(subnet_prefix_len)?;
+        }
+        if let Some(default_gateway) = default_gateway {
+            if config.subnet_prefix_len.is_none() {
+                let cause: String = format!(
+                    "default gateway requires a local subnet (gateway={:?})",
+                    default_gateway
+                );
+                error!("new(): {}", &cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            }
+            config.set_default_gateway(default_gateway);
+        }
+        if let Some(routes) = routes {
+            config.set_routes(routes);
+        }
+        Ok(config)
+    }
+
+    /// Gets the length of the prefix of the local subnet in the target [RoutingConfig].
+    pub fn get_subnet_prefix_len(&self) -> Option<u8> {
+        self.subnet_prefix_len
+    }
+
+    /// Sets the length of the prefix of the local subnet in the target [RoutingConfig].
+    fn set_subnet_prefix_len(&mut self, subnet_prefix_len: u8) -> Result<(), Fail> {
+        check_prefix_len(subnet_prefix_len)?;
+        self.subnet_prefix_len = Some(subnet_prefix_len);
+        Ok(())
+    }
+
+    /// Gets the default gateway in the target [RoutingConfig].
+    pub fn get_default_gateway(&self) -> Option<Ipv4Addr> {
+        self.default_gateway
+    }
+
+    /// Sets the default gateway in the target [RoutingConfig].
+    fn set_default_gateway(&mut self, default_gateway: Ipv4Addr) {
+        self.default_gateway = Some(default_gateway);
+    }
+
+    /// Gets the routes in the target [RoutingConfig].
+    pub fn get_routes(&self) -> &Vec<Ipv4Route> {
+        &self.routes
+    }
+
+    /// Sets the routes in the target [RoutingConfig].
+    fn set_routes(&mut self, routes: Vec<Ipv4Route>) {
+        self.routes = routes;
+    }
+
+    /// Picks the next hop of a packet that `local_ipv4_addr` sends to `dst_ipv4_addr`. Destinations on the local
+    /// subnet are reached directly, unless a more specific route covers them. Other destinations go through the
+    /// longest route that covers them, then through the default gateway. Destinations without any route, as well as
+    /// broadcast, multicast and link-local (169.254.0.0/16) ones, are reached directly. Link-local destinations are
+    /// never forwarded by routers (RFC 3927), so no route or gateway applies to them.
+    pub fn next_hop(&self, local_ipv4_addr: Ipv4Addr, dst_ipv4_addr: Ipv4Addr) -> Ipv4Addr {
+        if dst_ipv4_addr.is_broadcast() || dst_ipv4_addr.is_multicast() || dst_ipv4_addr.is_link_local() {
+            return dst_ipv4_addr;
+        }
+        let subnet_prefix_len: u8 = self.subnet_prefix_len.unwrap_or(0);
+        let on_subnet: bool = same_prefix(local_ipv4_addr, dst_ipv4_addr, subnet_prefix_len);
+        let route: Option<&Ipv4Route> = self
+            .routes
+            .iter()
+            .filter(|route| route.contains(dst_ipv4_addr))
+            .filter(|route| !on_subnet || route.get_prefix_len() > subnet_prefix_len)
+            .max_by_key(|route| route.get_prefix_len());
+        match (route, on_subnet, self.default_gateway) {
+            (Some(route), _, _) => route.get_next_hop(),
+            (None, true, _) => dst_ipv4_addr,
+            (None, false, Some(default_gateway)) => default_gateway,
+            (None, false, None) => dst_ipv4_addr,
+        }
+    }
+}
+
+//==============================================================================
+// Standalone Functions
+//==============================================================================
+
+/// Checks that `prefix_len` is a valid length for the prefix of an IPv4 address.
+fn check_prefix_len(prefix_len: u8) -> Result<(), Fail> {
+    if prefix_len > 32 {
+        let cause: String = format!("invalid prefix length (prefix_len={:?})", prefix_len);
+        error!("check_prefix_len(): {}", &cause);
+        return Err(Fail::new(libc::EINVAL, &cause));
+    }
+    Ok(())
+}
+
+/// Checks whether the first `prefix_len` bits of `a` and `b` match.
+fn same_prefix(a: Ipv4Addr, b: Ipv4Addr, prefix_len: u8) -> bool {
+    let mask: u32 = u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0);
+    (u32::from(a) & mask) == (u32::from(b) & mask)
+}
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Ipv4Route,
+        RoutingConfig,
+    };
+    use ::anyhow::Result;
+    use ::std::net::Ipv4Addr;
+
+    const LOCAL: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 1);
+    const NEIGHBOR: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 2);
+    const GATEWAY: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 254);
+    const ROUTER: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 253);
+
+    /// Tests default instantiation for [RoutingConfig].
+    #[test]
+    fn test_routing_config_default() -> Result<()> {
+        let config: RoutingConfig = RoutingConfig::default();
+        crate::ensure_eq!(config.get_subnet_prefix_len(), None);
+        crate::ensure_eq!(config.get_default_gateway(), None);
+        crate::ensure_eq!(config.get_routes().is_empty(), true);
+        crate::ensure_eq!(
+            config.next_hop(LOCAL, Ipv4Addr::new(10, 0, 0, 1)),
+            Ipv4Addr::new(10, 0, 0, 1)
+        );
+
+        Ok(())
+    }
+
+    /// Tests that destinations on the local subnet are reached directly, and other ones through the default gateway.
+    #[test]
+    fn test_routing_config_default_gateway() -> Result<()> {
+        let config: RoutingConfig = RoutingConfig::new(Some(24), Some(GATEWAY), None)?;
+        crate::ensure_eq!(config.next_hop(LOCAL, NEIGHBOR), NEIGHBOR);
+        crate::ensure_eq!(config.next_hop(LOCAL, Ipv4Addr::new(10, 0, 0, 1)), GATEWAY);
+        crate::ensure_eq!(config.next_hop(LOCAL, Ipv4Addr::BROADCAST), Ipv4Addr::BROADCAST);
+
+        Ok(())
+    }
+
+    /// Tests that the longest route that covers a destination takes precedence over shorter ones and over the default
+    /// gateway.
+    #[test]
+    fn test_routing_config_longest_prefix() -> Result<()> {
+        let routes: Vec<Ipv4Route> = vec![
+            Ipv4Route::new(Ipv4Addr::new(10, 0, 0, 0), 8, ROUTER)?,
+            Ipv4Route::new(Ipv4Addr::new(10, 1, 0, 0), 16, NEIGHBOR)?,
+        ];
+        let config: RoutingConfig = RoutingConfig::new(Some(24), Some(GATEWAY), Some(routes))?;
+        crate::ensure_eq!(config.next_hop(LOCAL, Ipv4Addr::new(10, 2, 0, 1)), ROUTER);
+        crate::ensure_eq!(config.next_hop(LOCAL, Ipv4Addr::new(10, 1, 0, 1)), NEIGHBOR);
+        crate::ensure_eq!(config.next_hop(LOCAL, Ipv4Addr::new(172, 16, 0, 1)), GATEWAY);
+
+        Ok(())
+    }
+
+    /// Tests that link-local destinations are reached directly, even when they are off the local subnet and a route
+    /// or a default gateway would otherwise cover them.
+    #[test]
+    fn test_routing_config_link_local() -> Result<()> {
+        let link_local: Ipv4Addr = Ipv4Addr::new(169, 254, 7, 7);
+        let routes: Vec<Ipv4Route> = vec![Ipv4Route::new(Ipv4Addr::new(169, 254, 0, 0), 16, ROUTER)?];
+        let config: RoutingConfig = RoutingConfig::new(Some(24), Some(GATEWAY), Some(routes))?;
+        crate::ensure_eq!(config.next_hop(LOCAL, link_local), link_local);
+        let config: RoutingConfig = RoutingConfig::new(Some(24), Some(GATEWAY), None)?;
+        crate::ensure_eq!(config.next_hop(LOCAL, link_local), link_local);
+
+        Ok(())
+    }
+
+    /// Tests that invalid prefix lengths, and a default gateway without a local subnet, are rejected.
+    #[test]
+    fn test_routing_config_invalid() -> Result<()> {
+        match RoutingConfig::new(Some(33), None, None) {
+            Err(e) if e.errno == libc::EINVAL => (),
+            result => anyhow::bail!(
+                "a prefix length of 33 should have been rejected with EINVAL: {:?}",
+                result
+            ),
+        }
+        match RoutingConfig::new(None, Some(GATEWAY), None) {
+            Err(e) if e.errno == libc::EINVAL => (),
+            result => anyhow::bail!(
+                "a gateway without a subnet should have been rejected with EINVAL: {:?}",
+                result
+            ),
+        }
+        match Ipv4Route::new(Ipv4Addr::new(10, 0, 0, 0), 33, ROUTER) {
+            Err(e) if e.errno == libc::EINVAL => (),
+            result => anyhow::bail!(
+                "a route prefix length of 33 should have been rejected with EINVAL: {:?}",
+                result
+            ),
+        }
+
+        Ok(())
+    }
+}
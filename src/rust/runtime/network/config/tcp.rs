@@ -21,8 +21,12 @@ use ::std::time::Duration;
 pub struct TcpConfig {
     /// Advertised Maximum Segment Size
     advertised_mss: usize,
-    /// Number of Retries for TCP Handshake Algorithm
-    handshake_retries: usize,
+    /// Number of SYN Retransmissions Before an Active Open Fails
+    syn_retries: usize,
+    /// Number of SYN+ACK Retransmissions Before a Passive Open Fails
+    syn_ack_retries: usize,
+    /// Number of Consecutive Data Retransmissions Before an Established Connection Fails
+    data_retries: usize,
     /// Timeout for TCP Handshake Algorithm
     handshake_timeout: Duration,
     /// Window Size
@@ -35,6 +39,22 @@ pub struct TcpConfig {
     rx_checksum_offload: bool,
     /// Offload Checksum to Hardware When Sending?
     tx_checksum_offload: bool,
+    /// Answer SYNs With SYN Cookies When the Backlog is Full?
+    syn_cookies: bool,
+    /// Maximum Number of Out-of-Order Segments Held for Reassembly
+    out_of_order_max_segments: usize,
+    /// Maximum Number of Out-of-Order Bytes Held for Reassembly
+    out_of_order_max_bytes: usize,
+    /// Maximum Segment Lifetime
+    msl: Duration,
+    /// Initial Slow Start Threshold
+    initial_ssthresh: u32,
+    /// Receive Window Advertised in SYN and SYN+ACK Segments, if Different From the Window Size
+    initial_receive_window: Option<u16>,
+    /// Timeout for Filling the Hole Before Out-of-Order Segments
+    reassembly_timeout: Duration,
+    /// Coalescing Window for Small Sends, if Autocorking is Enabled
+    autocork_window: Option<Duration>,
 }
 
 //==============================================================================
@@ -42,56 +62,33 @@ pub struct TcpConfig {
 //==============================================================================
 
 /// Associate Functions for TCP Configuration Descriptor
+///
+/// A TCP Configuration Descriptor is built by overriding the default values with its setters, which consume the
+/// descriptor and hand it back, so that they can be chained.
 impl TcpConfig {
-    /// Creates a TCP Configuration Descriptor.
-    pub fn new(
-        advertised_mss: Option<usize>,
-        handshake_retries: Option<usize>,
-        handshake_timeout: Option<Duration>,
-        receive_window_size: Option<u16>,
-        window_scale: Option<u8>,
-        ack_delay_timeout: Option<Duration>,
-        rx_checksum_offload: Option<bool>,
-        tx_checksum_offload: Option<bool>,
-    ) -> Self {
-        let mut options = Self::default();
-
-        if let Some(value) = advertised_mss {
-            options = options.set_advertised_mss(value);
-        }
-        if let Some(value) = handshake_retries {
-            options = options.set_handshake_retries(value);
-        }
-        if let Some(value) = handshake_timeout {
-            options = options.set_handshake_timeout(value);
-        }
-        if let Some(value) = receive_window_size {
-            options = options.set_receive_window_size(value);
-        }
-        if let Some(value) = window_scale {
-            options = options.set_window_scale(value);
-        }
-        if let Some(value) = ack_delay_timeout {
-            options = options.set_ack_delay_timeout(value);
-        }
-        if let Some(value) = rx_checksum_offload {
-            options.rx_checksum_offload = value;
-        }
-        if let Some(value) = tx_checksum_offload {
-            options.tx_checksum_offload = value;
-        }
-
-        options
-    }
-
     /// Gets the advertised maximum segment size in the target [TcpConfig].
     pub fn get_advertised_mss(&self) -> usize {
         self.advertised_mss
     }
 
-    /// Gets the number of TCP handshake retries in the target [TcpConfig].
-    pub fn get_handshake_retries(&self) -> usize {
-        self.handshake_retries
+    /// Gets the number of SYN retransmissions in the target [TcpConfig]. Retransmissions are spaced by a fixed
+    /// handshake timeout, so an active open gives up after `(syn_retries + 1) * handshake_timeout`.
+    pub fn get_syn_retries(&self) -> usize {
+        self.syn_retries
+    }
+
+    /// Gets the number of SYN+ACK retransmissions in the target [TcpConfig]. Retransmissions are spaced by a fixed
+    /// handshake timeout, so a passive open gives up after `(syn_ack_retries + 1) * handshake_timeout`, and the
+    /// half-open connection then frees its slot of the backlog.
+    pub fn get_syn_ack_retries(&self) -> usize {
+        self.syn_ack_retries
+    }
+
+    /// Gets the number of consecutive data retransmissions in the target [TcpConfig]. Data retransmissions follow the
+    /// RFC 6298 schedule: the RTO doubles on every retransmission (capped at 60 seconds) and is reset once new data is
+    /// acknowledged.
+    pub fn get_data_retries(&self) -> usize {
+        self.data_retries
     }
 
     /// Gets the handshake TCP timeout in the target [TcpConfig].
@@ -124,47 +121,202 @@ impl TcpConfig {
         self.rx_checksum_offload
     }
 
+    /// Gets the SYN cookies option in the target [TcpConfig]. When set, listening sockets answer SYNs that overflow
+    /// their backlog with a SYN cookie instead of resetting the connection.
+    pub fn get_syn_cookies(&self) -> bool {
+        self.syn_cookies
+    }
+
+    /// Gets the maximum number of out-of-order segments that a connection holds for reassembly in the target
+    /// [TcpConfig]. Segments past this limit are dropped, starting with the ones furthest away from the next expected
+    /// sequence number, and must be retransmitted by the remote peer.
+    pub fn get_out_of_order_max_segments(&self) -> usize {
+        self.out_of_order_max_segments
+    }
+
+    /// Gets the maximum number of out-of-order bytes that a connection holds for reassembly in the target [TcpConfig].
+    /// Segments past this limit are dropped in the same way as segments past the maximum number of segments.
+    pub fn get_out_of_order_max_bytes(&self) -> usize {
+        self.out_of_order_max_bytes
+    }
+
+    /// Gets the maximum segment lifetime in the target [TcpConfig], which is how long a segment is assumed to be able
+    /// to stay in the network.
+    pub fn get_msl(&self) -> Duration {
+        self.msl
+    }
+
+    /// Gets how long a connection stays in the TIME-WAIT state in the target [TcpConfig], which is twice the maximum
+    /// segment lifetime (RFC 793).
+    pub fn get_time_wait_timeout(&self) -> Duration {
+        2 * self.msl
+    }
+
+    /// Gets how long a closed connection waits in the other closing states for the remote peer to finish closing in
+    /// the target [TcpConfig], which is also twice the maximum segment lifetime. Once it expires, the connection is
+    /// dropped.
+    pub fn get_orphan_timeout(&self) -> Duration {
+        2 * self.msl
+    }
+
+    /// Gets the initial slow start threshold in the target [TcpConfig], in bytes. Congestion controllers stay in slow
+    /// start until the congestion window reaches it. It defaults to an arbitrarily high value (RFC 5681), so that
+    /// slow start only ends on the first congestion event.
+    pub fn get_initial_ssthresh(&self) -> u32 {
+        self.initial_ssthresh
+    }
+
+    /// Gets the receive window that is advertised in SYN and SYN+ACK segments in the target [TcpConfig], in bytes. It
+    /// bounds how much the remote peer may send before the first window update, and defaults to the window size. The
+    /// receive buffer of the connection is still sized after the window size and scale.
+    pub fn get_initial_receive_window(&self) -> u16 {
+        self.initial_receive_window.unwrap_or(self.receive_window_size)
+    }
+
+    /// Gets how long a connection waits for the hole before its out-of-order segments to be filled in the target
+    /// [TcpConfig]. If the hole is still there once it expires, the remote peer is assumed to be gone: the connection
+    /// is reset, and the segments that were held are released. A live peer retransmits the missing data at least once
+    /// a minute (the largest RTO), so the default of two minutes leaves it a couple of chances.
+    pub fn get_reassembly_timeout(&self) -> Duration {
+        self.reassembly_timeout
+    }
+
+    /// Gets the coalescing window of autocorking in the target [TcpConfig], if it is enabled. A send smaller than the
+    /// MSS is then held back for up to this long, and the small sends that follow it within the window are aggregated
+    /// into the same segment. This trades a little latency for fewer segments when an application issues many small
+    /// writes in a row. Sends that fill a segment, and explicit flushes, go out right away. Autocorking is disabled by
+    /// default.
+    pub fn get_autocork_window(&self) -> Option<Duration> {
+        self.autocork_window
+    }
+
     /// Sets the advertised maximum segment size in the target [TcpConfig].
-    fn set_advertised_mss(mut self, value: usize) -> Self {
+    pub fn set_advertised_mss(mut self, value: usize) -> Self {
         assert!(value >= MIN_MSS);
         assert!(value <= MAX_MSS);
         self.advertised_mss = value;
         self
     }
 
-    /// Sets the number of TCP handshake retries in the target [TcpConfig].
-    fn set_handshake_retries(mut self, value: usize) -> Self {
-        assert!(value > 0);
-        self.handshake_retries = value;
+    /// Sets the number of SYN retransmissions in the target [TcpConfig].
+    pub fn set_syn_retries(mut self, value: usize) -> Self {
+        self.syn_retries = value;
+        self
+    }
+
+    /// Sets the number of SYN+ACK retransmissions in the target [TcpConfig].
+    pub fn set_syn_ack_retries(mut self, value: usize) -> Self {
+        self.syn_ack_retries = value;
+        self
+    }
+
+    /// Sets the number of consecutive data retransmissions in the target [TcpConfig].
+    pub fn set_data_retries(mut self, value: usize) -> Self {
+        self.data_retries = value;
         self
     }
 
     /// Sets the handshake TCP timeout in the target [TcpConfig].
-    fn set_handshake_timeout(mut self, value: Duration) -> Self {
+    pub fn set_handshake_timeout(mut self, value: Duration) -> Self {
         assert!(value > Duration::new(0, 0));
         self.handshake_timeout = value;
         self
     }
 
     /// Sets the receiver window size in the target [TcpConfig].
-    fn set_receive_window_size(mut self, value: u16) -> Self {
+    pub fn set_receive_window_size(mut self, value: u16) -> Self {
         assert!(value > 0);
         self.receive_window_size = value;
         self
     }
 
-    /// Gets the window scale in the target [TcpConfig]
-    fn set_window_scale(mut self, value: u8) -> Self {
+    /// Sets the window scale in the target [TcpConfig].
+    pub fn set_window_scale(mut self, value: u8) -> Self {
         self.window_scale = value;
         self
     }
 
     /// Sets the acknowledgement delay timeout in the target [TcpConfig].
-    fn set_ack_delay_timeout(mut self, value: Duration) -> Self {
+    pub fn set_ack_delay_timeout(mut self, value: Duration) -> Self {
         assert!(value <= Duration::from_millis(500));
         self.ack_delay_timeout = value;
         self
     }
+
+    /// Sets the RX hardware checksum offload option in the target [TcpConfig].
+    pub fn set_rx_checksum_offload(mut self, value: bool) -> Self {
+        self.rx_checksum_offload = value;
+        self
+    }
+
+    /// Sets the TX hardware checksum offload option in the target [TcpConfig].
+    pub fn set_tx_checksum_offload(mut self, value: bool) -> Self {
+        self.tx_checksum_offload = value;
+        self
+    }
+
+    /// Sets the SYN cookies option in the target [TcpConfig].
+    pub fn set_syn_cookies(mut self, value: bool) -> Self {
+        self.syn_cookies = value;
+        self
+    }
+
+    /// Sets the maximum number of out-of-order segments that a connection holds for reassembly in the target
+    /// [TcpConfig].
+    pub fn set_out_of_order_max_segments(mut self, value: usize) -> Self {
+        self.out_of_order_max_segments = value;
+        self
+    }
+
+    /// Sets the maximum number of out-of-order bytes that a connection holds for reassembly in the target
+    /// [TcpConfig].
+    pub fn set_out_of_order_max_bytes(mut self, value: usize) -> Self {
+        self.out_of_order_max_bytes = value;
+        self
+    }
+
+    /// Sets the maximum segment lifetime in the target [TcpConfig]. RFC 793 suggests 2 minutes, so larger values are
+    /// rejected.
+    pub fn set_msl(mut self, value: Duration) -> Self {
+        assert!(value > Duration::new(0, 0));
+        assert!(value <= Duration::from_secs(120));
+        self.msl = value;
+        self
+    }
+
+    /// Sets the initial slow start threshold in the target [TcpConfig].
+    pub fn set_initial_ssthresh(mut self, value: u32) -> Self {
+        assert!(value > 0);
+        self.initial_ssthresh = value;
+        self
+    }
+
+    /// Sets the reassembly timeout in the target [TcpConfig].
+    pub fn set_reassembly_timeout(mut self, value: Duration) -> Self {
+        assert!(value > Duration::new(0, 0));
+        self.reassembly_timeout = value;
+        self
+    }
+
+    /// Sets the coalescing window of autocorking in the target [TcpConfig]. It is meant to be short, so windows past
+    /// the delayed ACK limit of 500 ms are rejected.
+    pub fn set_autocork_window(mut self, value: Duration) -> Self {
+        assert!(value > Duration::new(0, 0));
+        assert!(value <= Duration::from_millis(500));
+        self.autocork_window = Some(value);
+        self
+    }
+
+    /// Sets the receive window that is advertised in SYN and SYN+ACK segments in the target [TcpConfig]. Windows are
+    /// never scaled in these segments (RFC 7323), so it is limited to 65535 bytes, and it may not exceed the receive
+    /// buffer, whose size is the window size shifted by the window scale, so those must be set first.
+    pub fn set_initial_receive_window(mut self, value: u16) -> Self {
+        let receive_buffer_size: u32 = (self.receive_window_size as u32) << self.window_scale;
+        assert!(value > 0);
+        assert!(value as u32 <= receive_buffer_size);
+        self.initial_receive_window = Some(value);
+        self
+    }
 }
 
 //==============================================================================
@@ -177,13 +329,23 @@ impl Default for TcpConfig {
     fn default() -> Self {
         TcpConfig {
             advertised_mss: DEFAULT_MSS,
-            handshake_retries: 5,
+            syn_retries: 4,
+            syn_ack_retries: 5,
+            data_retries: 15,
             handshake_timeout: Duration::from_secs(3),
             receive_window_size: 0xffff,
             ack_delay_timeout: Duration::from_millis(5),
             window_scale: 0,
             rx_checksum_offload: false,
             tx_checksum_offload: false,
+            syn_cookies: false,
+            out_of_order_max_segments: 16,
+            out_of_order_max_bytes: 64 * 1024,
+            msl: Duration::from_secs(30),
+            initial_ssthresh: u32::MAX,
+            initial_receive_window: None,
+            reassembly_timeout: Duration::from_secs(120),
+            autocork_window: None,
         }
     }
 }
@@ -206,12 +368,45 @@ mod tests {
     fn test_tcp_config_default() -> Result<()> {
         let config: TcpConfig = TcpConfig::default();
         crate::ensure_eq!(config.get_advertised_mss(), DEFAULT_MSS);
-        crate::ensure_eq!(config.get_handshake_retries(), 5);
+        crate::ensure_eq!(config.get_syn_retries(), 4);
+        crate::ensure_eq!(config.get_syn_ack_retries(), 5);
+        crate::ensure_eq!(config.get_data_retries(), 15);
         crate::ensure_eq!(config.get_handshake_timeout(), Duration::from_secs(3));
         crate::ensure_eq!(config.get_receive_window_size(), 0xffff);
         crate::ensure_eq!(config.get_window_scale(), 0);
         crate::ensure_eq!(config.get_rx_checksum_offload(), false);
         crate::ensure_eq!(config.get_tx_checksum_offload(), false);
+        crate::ensure_eq!(config.get_syn_cookies(), false);
+        crate::ensure_eq!(config.get_out_of_order_max_segments(), 16);
+        crate::ensure_eq!(config.get_out_of_order_max_bytes(), 64 * 1024);
+        crate::ensure_eq!(config.get_msl(), Duration::from_secs(30));
+        crate::ensure_eq!(config.get_time_wait_timeout(), Duration::from_secs(60));
+        crate::ensure_eq!(config.get_initial_ssthresh(), u32::MAX);
+        crate::ensure_eq!(config.get_initial_receive_window(), 0xffff);
+        crate::ensure_eq!(config.get_reassembly_timeout(), Duration::from_secs(120));
+        crate::ensure_eq!(config.get_autocork_window(), None);
+
+        Ok(())
+    }
+
+    /// Tests that the initial receive window follows the window size unless it is set, and may not exceed it.
+    #[test]
+    fn test_tcp_config_initial_receive_window() -> Result<()> {
+        let config: TcpConfig = TcpConfig::default().set_receive_window_size(0x4000);
+        crate::ensure_eq!(config.get_initial_receive_window(), 0x4000);
+
+        let config: TcpConfig = TcpConfig::default()
+            .set_receive_window_size(0x4000)
+            .set_initial_receive_window(0x1000);
+        crate::ensure_eq!(config.get_initial_receive_window(), 0x1000);
+        crate::ensure_eq!(config.get_receive_window_size(), 0x4000);
+
+        let result = std::panic::catch_unwind(|| {
+            TcpConfig::default()
+                .set_receive_window_size(0x4000)
+                .set_initial_receive_window(0x8000)
+        });
+        crate::ensure_eq!(result.is_err(), true);
 
         Ok(())
     }
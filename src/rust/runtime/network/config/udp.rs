@@ -1,10 +1,46 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::runtime::{
+    fail::Fail,
+    network::consts::DEFAULT_MTU,
+};
+use ::std::cmp;
+
 //==============================================================================
 // Constants & Structures
 //==============================================================================
 
+/// Smallest MTU That Every IPv4 Link Must Support (RFC 791)
+const MIN_MTU: usize = 68;
+
+/// Default Maximum Number of Datagrams Held in the Receive Queue of a UDP Socket
+const DEFAULT_RECV_QUEUE_MAX_DATAGRAMS: usize = 1024;
+
+/// Datagram That Is Dropped When the Receive Queue of a UDP Socket Is Full
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UdpDropPolicy {
+    /// Drop the Incoming Datagram
+    Newest,
+    /// Drop the Oldest Queued Datagrams to Make Room for the Incoming One
+    Oldest,
+}
+
+/// UDP Receive Queue Configuration Descriptor
+#[derive(Clone, Debug)]
+pub struct UdpRecvQueueConfig {
+    /// Maximum number of datagrams held in the receive queue of a socket.
+    max_datagrams: usize,
+    /// Maximum number of bytes held in the receive queue of a socket, if any (i.e. SO_RCVBUF).
+    max_bytes: Option<usize>,
+    /// Datagram that is dropped when the receive queue of a socket is full.
+    drop_policy: UdpDropPolicy,
+}
+
 /// UDP Configuration Descriptor
 #[derive(Clone, Debug)]
 pub struct UdpConfig {
@@ -12,16 +48,87 @@ pub struct UdpConfig {
     rx_checksum: bool,
     /// Offload Checksum to Hardware When Sending?
     tx_checksum: bool,
+    /// Largest IPv4 packet that may be sent, in bytes.
+    mtu: usize,
+    /// Bounds on the receive queue of each socket.
+    recv_queue: UdpRecvQueueConfig,
 }
 
 //==============================================================================
 // Associate Functions
 //==============================================================================
 
+/// Associate functions for UDP Receive Queue Configuration Descriptor
+impl UdpRecvQueueConfig {
+    /// Creates a UDP Receive Queue Configuration Descriptor. Fails if the receive queue cannot hold any datagram.
+    pub fn new(
+        max_datagrams: Option<usize>,
+        max_bytes: Option<usize>,
+        drop_policy: Option<UdpDropPolicy>,
+    ) -> Result<Self, Fail> {
+        let mut config = Self::default();
+        if let Some(max_datagrams) = max_datagrams {
+            config.set_max_datagrams(max_datagrams);
+        }
+        if let Some(max_bytes) = max_bytes {
+            config.set_max_bytes(max_bytes);
+        }
+        if let Some(drop_policy) = drop_policy {
+            config.set_drop_policy(drop_policy);
+        }
+
+        if config.max_datagrams == 0 || config.max_bytes == Some(0) {
+            let cause: String = format!(
+                "invalid receive queue bounds (max_datagrams={:?}, max_bytes={:?})",
+                config.max_datagrams, config.max_bytes
+            );
+            error!("new(): {}", &cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+
+        Ok(config)
+    }
+
+    /// Gets the maximum number of datagrams held in a receive queue in the target [UdpRecvQueueConfig].
+    pub fn get_max_datagrams(&self) -> usize {
+        self.max_datagrams
+    }
+
+    /// Gets the maximum number of bytes held in a receive queue in the target [UdpRecvQueueConfig], if any.
+    pub fn get_max_bytes(&self) -> Option<usize> {
+        self.max_bytes
+    }
+
+    /// Gets the datagram that is dropped when a receive queue is full in the target [UdpRecvQueueConfig].
+    pub fn get_drop_policy(&self) -> UdpDropPolicy {
+        self.drop_policy
+    }
+
+    /// Sets the maximum number of datagrams held in a receive queue in the target [UdpRecvQueueConfig].
+    fn set_max_datagrams(&mut self, max_datagrams: usize) {
+        self.max_datagrams = max_datagrams;
+    }
+
+    /// Sets the maximum number of bytes held in a receive queue in the target [UdpRecvQueueConfig].
+    fn set_max_bytes(&mut self, max_bytes: usize) {
+        self.max_bytes = Some(max_bytes);
+    }
+
+    /// Sets the datagram that is dropped when a receive queue is full in the target [UdpRecvQueueConfig].
+    fn set_drop_policy(&mut self, drop_policy: UdpDropPolicy) {
+        self.drop_policy = drop_policy;
+    }
+}
+
 /// Associate functions for UDP Configuration Descriptor
 impl UdpConfig {
     /// Creates a UDP Configuration Descriptor.
-    pub fn new(rx_checksum: Option<bool>, tx_checksum: Option<bool>) -> Self {
+    pub fn new(
+        rx_checksum: Option<bool>,
+        tx_checksum: Option<bool>,
+        mtu: Option<usize>,
+        recv_queue: Option<UdpRecvQueueConfig>,
+    ) -> Self {
         let mut config = Self::default();
         if let Some(rx_checksum) = rx_checksum {
             config.set_rx_checksum_offload(rx_checksum);
@@ -29,6 +136,12 @@ impl UdpConfig {
         if let Some(tx_checksum) = tx_checksum {
             config.set_tx_checksum_offload(tx_checksum);
         }
+        if let Some(mtu) = mtu {
+            config.set_mtu(mtu);
+        }
+        if let Some(recv_queue) = recv_queue {
+            config.set_recv_queue_config(recv_queue);
+        }
         config
     }
 
@@ -42,6 +155,16 @@ impl UdpConfig {
         self.tx_checksum
     }
 
+    /// Gets the MTU in the target [UdpConfig].
+    pub fn get_mtu(&self) -> usize {
+        self.mtu
+    }
+
+    /// Gets the bounds on the receive queue of each socket in the target [UdpConfig].
+    pub fn get_recv_queue_config(&self) -> UdpRecvQueueConfig {
+        self.recv_queue.clone()
+    }
+
     /// Sets the RX hardware checksum offload option in the target [UdpConfig].
     fn set_rx_checksum_offload(&mut self, rx_checksum: bool) {
         self.rx_checksum = rx_checksum;
@@ -51,12 +174,35 @@ impl UdpConfig {
     fn set_tx_checksum_offload(&mut self, tx_checksum: bool) {
         self.tx_checksum = tx_checksum;
     }
+
+    /// Sets the MTU in the target [UdpConfig]. MTUs that are smaller than what every IPv4 link supports are raised to
+    /// that minimum, so that there is always room for the headers.
+    fn set_mtu(&mut self, mtu: usize) {
+        self.mtu = cmp::max(mtu, MIN_MTU);
+    }
+
+    /// Sets the bounds on the receive queue of each socket in the target [UdpConfig].
+    fn set_recv_queue_config(&mut self, recv_queue: UdpRecvQueueConfig) {
+        self.recv_queue = recv_queue;
+    }
 }
 
 //==============================================================================
 // Trait Implementations
 //==============================================================================
 
+/// Default Trait Implementation for UDP Receive Queue Configuration Descriptor
+impl Default for UdpRecvQueueConfig {
+    /// Creates a UDP Receive Queue Configuration Descriptor with the default values.
+    fn default() -> Self {
+        UdpRecvQueueConfig {
+            max_datagrams: DEFAULT_RECV_QUEUE_MAX_DATAGRAMS,
+            max_bytes: None,
+            drop_policy: UdpDropPolicy::Newest,
+        }
+    }
+}
+
 /// Default Trait Implementation for UDP Configuration Descriptor
 impl Default for UdpConfig {
     /// Creates a UDP Configuration Descriptor with the default values.
@@ -64,6 +210,8 @@ impl Default for UdpConfig {
         UdpConfig {
             rx_checksum: false,
             tx_checksum: false,
+            mtu: DEFAULT_MTU,
+            recv_queue: UdpRecvQueueConfig::default(),
         }
     }
 }
@@ -74,7 +222,13 @@ impl Default for UdpConfig {
 
 #[cfg(test)]
 mod tests {
-    use super::UdpConfig;
+    use super::{
+        UdpConfig,
+        UdpDropPolicy,
+        UdpRecvQueueConfig,
+        DEFAULT_RECV_QUEUE_MAX_DATAGRAMS,
+    };
+    use crate::runtime::network::consts::DEFAULT_MTU;
     use ::anyhow::Result;
 
     /// Tests default instantiation for [UdpConfig].
@@ -83,6 +237,13 @@ mod tests {
         let config: UdpConfig = UdpConfig::default();
         crate::ensure_eq!(config.get_rx_checksum_offload(), false);
         crate::ensure_eq!(config.get_tx_checksum_offload(), false);
+        crate::ensure_eq!(config.get_mtu(), DEFAULT_MTU);
+        crate::ensure_eq!(
+            config.get_recv_queue_config().get_max_datagrams(),
+            DEFAULT_RECV_QUEUE_MAX_DATAGRAMS
+        );
+        crate::ensure_eq!(config.get_recv_queue_config().get_max_bytes(), None);
+        crate::ensure_eq!(config.get_recv_queue_config().get_drop_policy(), UdpDropPolicy::Newest);
 
         Ok(())
     }
@@ -90,9 +251,33 @@ mod tests {
     /// Tests custom instantiation for [UdpConfig].
     #[test]
     fn test_udp_config_custom() -> Result<()> {
-        let config: UdpConfig = UdpConfig::new(Some(true), Some(true));
+        let recv_queue: UdpRecvQueueConfig =
+            UdpRecvQueueConfig::new(Some(64), Some(65536), Some(UdpDropPolicy::Oldest))?;
+        let config: UdpConfig = UdpConfig::new(Some(true), Some(true), Some(9000), Some(recv_queue));
         crate::ensure_eq!(config.get_rx_checksum_offload(), true);
         crate::ensure_eq!(config.get_tx_checksum_offload(), true);
+        crate::ensure_eq!(config.get_mtu(), 9000);
+        crate::ensure_eq!(config.get_recv_queue_config().get_max_datagrams(), 64);
+        crate::ensure_eq!(config.get_recv_queue_config().get_max_bytes(), Some(65536));
+        crate::ensure_eq!(config.get_recv_queue_config().get_drop_policy(), UdpDropPolicy::Oldest);
+
+        Ok(())
+    }
+
+    /// Tests that an MTU that is too small for the headers is raised to the IPv4 minimum.
+    #[test]
+    fn test_udp_config_min_mtu() -> Result<()> {
+        let config: UdpConfig = UdpConfig::new(None, None, Some(20), None);
+        crate::ensure_eq!(config.get_mtu(), 68);
+
+        Ok(())
+    }
+
+    /// Tests that a receive queue that cannot hold any datagram is rejected.
+    #[test]
+    fn test_udp_recv_queue_config_empty() -> Result<()> {
+        crate::ensure_eq!(UdpRecvQueueConfig::new(Some(0), None, None).is_err(), true);
+        crate::ensure_eq!(UdpRecvQueueConfig::new(None, Some(0), None).is_err(), true);
 
         Ok(())
     }
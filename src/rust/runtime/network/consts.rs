@@ -19,6 +19,9 @@ pub const MAX_MSS: usize = u16::max_value() as usize;
 /// TODO: Auto-Discovery MTU Size
 pub const DEFAULT_MSS: usize = 1450;
 
+/// Default MTU of the link, in bytes.
+pub const DEFAULT_MTU: usize = 1500;
+
 /// Length of a [crate::memory::DemiBuffer] batch.
 ///
 /// TODO: This Should be Generic
@@ -5,11 +5,14 @@
 // Imports
 //======================================================================================================================
 
-use crate::runtime::fail::Fail;
+use crate::runtime::{
+    fail::Fail,
+    network::config::EphemeralPortConfig,
+};
 #[cfg(not(debug_assertions))]
 use ::rand::prelude::{
+    Rng,
     SeedableRng,
-    SliceRandom,
     SmallRng,
 };
 
@@ -17,10 +20,6 @@ use ::rand::prelude::{
 // Constants
 //======================================================================================================================
 
-/// First private port. See https://datatracker.ietf.org/doc/html/rfc6335 for details.
-const FIRST_PRIVATE_PORT: u16 = 49152;
-/// Last private port. See https://datatracker.ietf.org/doc/html/rfc6335 for details.
-const LAST_PRIVATE_PORT: u16 = 65535;
 /// Seed number for ephemeral port allocator.
 #[cfg(not(debug_assertions))]
 const EPHEMERAL_PORT_SEED: u64 = 12345;
@@ -29,8 +28,19 @@ const EPHEMERAL_PORT_SEED: u64 = 12345;
 // Structures
 //======================================================================================================================
 
+/// Ephemeral port allocator. Ports are handed out round-robin within the configured range, so that a port that was
+/// just freed is not reused right away, which reduces 4-tuple collisions with connections that still linger.
 pub struct EphemeralPorts {
-    ports: Vec<u16>,
+    /// First port in the range.
+    first_port: u16,
+    /// Last port in the range (inclusive).
+    last_port: u16,
+    /// Offset from `first_port` of the next port to consider for allocation.
+    next: usize,
+    /// Whether each port in the range is currently allocated, indexed by offset from `first_port`.
+    in_use: Vec<bool>,
+    /// Number of ports that are currently allocated.
+    num_in_use: usize,
 }
 
 //======================================================================================================================
@@ -38,28 +48,74 @@ pub struct EphemeralPorts {
 //======================================================================================================================
 
 impl EphemeralPorts {
+    /// Creates a new ephemeral port allocator for the range in `config`.
+    pub fn new(config: &EphemeralPortConfig) -> Self {
+        let first_port: u16 = config.get_first_port();
+        let last_port: u16 = config.get_last_port();
+        let num_ports: usize = (last_port - first_port) as usize + 1;
+        #[cfg(debug_assertions)]
+        let next: usize = 0;
+        #[cfg(not(debug_assertions))]
+        let next: usize = SmallRng::seed_from_u64(EPHEMERAL_PORT_SEED).gen_range(0..num_ports);
+        Self {
+            first_port,
+            last_port,
+            next,
+            in_use: vec![false; num_ports],
+            num_in_use: 0,
+        }
+    }
+
     /// Asserts wether a port is in the ephemeral port range.
-    pub fn is_private(port: u16) -> bool {
-        port >= FIRST_PRIVATE_PORT
+    pub fn is_private(&self, port: u16) -> bool {
+        port >= self.first_port && port <= self.last_port
+    }
+
+    /// Checks whether any port is currently allocated.
+    pub fn has_allocated_ports(&self) -> bool {
+        self.num_in_use > 0
+    }
+
+    /// Checks whether any port is currently free.
+    pub fn has_free_ports(&self) -> bool {
+        self.num_in_use < self.in_use.len()
     }
 
     /// Allocates any ephemeral port from the pool.
     pub fn alloc(&mut self) -> Result<u16, Fail> {
-        self.ports.pop().ok_or(Fail::new(
-            libc::EADDRINUSE,
-            "all port numbers in the ephemeral port range are currently in use",
-        ))
+        let num_ports: usize = self.in_use.len();
+        if !self.has_free_ports() {
+            return Err(Fail::new(
+                libc::EADDRNOTAVAIL,
+                "all port numbers in the ephemeral port range are currently in use",
+            ));
+        }
+
+        // Look for the next free port, starting after the one that was allocated last.
+        for i in 0..num_ports {
+            let offset: usize = (self.next + i) % num_ports;
+            if !self.in_use[offset] {
+                self.in_use[offset] = true;
+                self.num_in_use += 1;
+                self.next = (offset + 1) % num_ports;
+                return Ok(self.first_port + offset as u16);
+            }
+        }
+
+        unreachable!("there should be a free port (num_in_use={:?})", self.num_in_use)
     }
 
     /// Allocates the specified port from the pool.
     pub fn reserve(&mut self, port: u16) -> Result<(), Fail> {
         // Check if port is not in the pool.
-        if !self.ports.contains(&port) {
+        if !self.is_private(port) || self.in_use[self.offset(port)] {
             return Err(Fail::new(libc::ENOENT, "port number not found"));
         }
 
         // Remove port from the pool.
-        self.ports.retain(|&p| p != port);
+        let offset: usize = self.offset(port);
+        self.in_use[offset] = true;
+        self.num_in_use += 1;
 
         Ok(())
     }
@@ -67,23 +123,31 @@ impl EphemeralPorts {
     /// Releases a ephemeral port.
     pub fn free(&mut self, port: u16) -> Result<(), Fail> {
         // Check if port is in the valid range.
-        if !Self::is_private(port) {
+        if !self.is_private(port) {
             let cause: String = format!("port {} is not in the ephemeral port range", port);
             error!("free(): {}", &cause);
             return Err(Fail::new(libc::EINVAL, &cause));
         }
 
         // Check if port is already in the pool.
-        if self.ports.contains(&port) {
+        let offset: usize = self.offset(port);
+        if !self.in_use[offset] {
             let cause: String = format!("port {} is already in the pool", port);
             error!("free(): {}", &cause);
             return Err(Fail::new(libc::EFAULT, &cause));
         }
 
-        self.ports.push(port);
+        self.in_use[offset] = false;
+        self.num_in_use -= 1;
 
         Ok(())
     }
+
+    /// Gets the offset of `port` from the first port in the range.
+    fn offset(&self, port: u16) -> usize {
+        debug_assert!(self.is_private(port));
+        (port - self.first_port) as usize
+    }
 }
 
 //======================================================================================================================
@@ -91,18 +155,9 @@ impl EphemeralPorts {
 //======================================================================================================================
 
 impl Default for EphemeralPorts {
-    /// Creates a new ephemeral port allocator.
+    /// Creates a new ephemeral port allocator for the default range.
     fn default() -> Self {
-        let mut ports: Vec<u16> = Vec::<u16>::new();
-        for port in (FIRST_PRIVATE_PORT..=LAST_PRIVATE_PORT).rev() {
-            ports.push(port);
-        }
-        #[cfg(not(debug_assertions))]
-        {
-            let mut rng: SmallRng = SmallRng::seed_from_u64(EPHEMERAL_PORT_SEED);
-            ports.shuffle(&mut rng);
-        }
-        Self { ports }
+        Self::new(&EphemeralPortConfig::default())
     }
 }
 
@@ -112,13 +167,18 @@ impl Default for EphemeralPorts {
 
 #[cfg(test)]
 mod test {
-    use super::{
-        EphemeralPorts,
-        FIRST_PRIVATE_PORT,
-        LAST_PRIVATE_PORT,
+    use super::EphemeralPorts;
+    use crate::runtime::network::config::{
+        EphemeralPortConfig,
+        EphemeralPortExhaustion,
     };
     use ::anyhow::Result;
 
+    /// First port of the default ephemeral port range.
+    const FIRST_PRIVATE_PORT: u16 = 49152;
+    /// Last port of the default ephemeral port range.
+    const LAST_PRIVATE_PORT: u16 = 65535;
+
     /// Attempts to allocate any ephemeral port and then release it.
     #[test]
     fn test_alloc_any_and_free() -> Result<()> {
@@ -235,4 +295,54 @@ mod test {
 
         Ok(())
     }
+
+    /// Attempts to exhaust a tiny configured range.
+    #[test]
+    fn test_alloc_exhausts_configured_range() -> Result<()> {
+        let config: EphemeralPortConfig = EphemeralPortConfig::new(Some(5000), Some(5001), None)?;
+        let mut ports: EphemeralPorts = EphemeralPorts::new(&config);
+
+        // Both ports in the range should be handed out, and nothing else.
+        let mut allocated: Vec<u16> = vec![ports.alloc()?, ports.alloc()?];
+        allocated.sort();
+        crate::ensure_eq!(allocated, vec![5000, 5001]);
+        match ports.alloc() {
+            Err(e) if e.errno == libc::EADDRNOTAVAIL => {},
+            Err(e) => anyhow::bail!("alloc should have failed with EADDRNOTAVAIL: {:?}", e),
+            Ok(port) => anyhow::bail!("alloc should have failed, but got port {:?}", port),
+        }
+
+        // Ports outside of the range are not managed by the allocator.
+        crate::ensure_eq!(ports.reserve(FIRST_PRIVATE_PORT).is_err(), true);
+        crate::ensure_eq!(ports.free(FIRST_PRIVATE_PORT).is_err(), true);
+
+        // A freed port becomes available again.
+        ports.free(5001)?;
+        crate::ensure_eq!(ports.alloc()?, 5001);
+
+        Ok(())
+    }
+
+    /// Attempts to allocate ports round-robin, so that a freed port is not reused right away.
+    #[test]
+    fn test_alloc_round_robin() -> Result<()> {
+        let exhaustion: EphemeralPortExhaustion = EphemeralPortExhaustion::Fail;
+        let config: EphemeralPortConfig = EphemeralPortConfig::new(Some(5000), Some(5002), Some(exhaustion))?;
+        let mut ports: EphemeralPorts = EphemeralPorts::new(&config);
+
+        let first: u16 = ports.alloc()?;
+        ports.free(first)?;
+        let second: u16 = ports.alloc()?;
+        crate::ensure_neq!(second, first);
+        ports.free(second)?;
+        let third: u16 = ports.alloc()?;
+        crate::ensure_neq!(third, first);
+        crate::ensure_neq!(third, second);
+        ports.free(third)?;
+
+        // The range wraps around once every port was handed out.
+        crate::ensure_eq!(ports.alloc()?, first);
+
+        Ok(())
+    }
 }
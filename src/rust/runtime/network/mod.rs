@@ -30,6 +30,7 @@ use ::arrayvec::ArrayVec;
 use ::std::{
     collections::HashMap,
     net::{
+        Ipv4Addr,
         SocketAddr,
         SocketAddrV4,
     },
@@ -113,6 +114,15 @@ pub trait PacketBuf {
     fn body_size(&self) -> usize;
     /// Consumes and returns the body of the target [PacketBuf].
     fn take_body(&self) -> Option<DemiBuffer>;
+    /// Returns the IPv4 destination address of the target [PacketBuf], if it carries an IPv4 packet. This allows the
+    /// packet to be steered without serializing it first.
+    fn ipv4_dst_addr(&self) -> Option<Ipv4Addr> {
+        None
+    }
+    /// Returns the transmit priority of the target [PacketBuf]. Higher values are more urgent, and 0 is the default.
+    fn tx_priority(&self) -> u8 {
+        0
+    }
 }
 
 /// Network Runtime
@@ -122,4 +132,24 @@ pub trait NetworkRuntime {
 
     /// Receives a batch of [DemiBuffer].
     fn receive(&mut self) -> ArrayVec<DemiBuffer, RECEIVE_BATCH_SIZE>;
+
+    /// Claims the local endpoint `local` for the stack that owns the target [NetworkRuntime], so that runtimes which
+    /// share a NIC between several stacks deliver the packets destined to it there. An endpoint may be claimed several
+    /// times by the same stack, such as by connections accepted on a listening socket, and it is released once every
+    /// claim is. Runtimes that own their NIC have nothing to do.
+    fn register_flow(&mut self, _local: SocketAddrV4) -> Result<(), Fail> {
+        Ok(())
+    }
+
+    /// Releases a claim on the local endpoint `local` that [register_flow](Self::register_flow) made.
+    fn unregister_flow(&mut self, _local: SocketAddrV4) -> Result<(), Fail> {
+        Ok(())
+    }
+
+    /// Returns the index of the network interface that the target [NetworkRuntime] drives, which UDP sockets report
+    /// through IP_PKTINFO. Runtimes that do not know it, such as the ones that drive a NIC which the host has no
+    /// interface for, return zero.
+    fn get_ifindex(&self) -> u32 {
+        0
+    }
 }
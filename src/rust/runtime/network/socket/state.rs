@@ -75,6 +75,11 @@ impl SocketStateMachine {
         Ok(())
     }
 
+    /// Checks whether the target [SocketState] is listening for incoming connections.
+    pub fn is_listening(&self) -> bool {
+        self.current == SocketState::PassiveListening
+    }
+
     /// Asserts whether the target may continue connecting to a remote.
     pub fn may_connect(&self) -> Result<(), Fail> {
         self.ensure_not_closing()?;
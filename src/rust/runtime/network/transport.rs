@@ -4,6 +4,11 @@ use crate::{
     runtime::{
         fail::Fail,
         memory::DemiBuffer,
+        network::types::{
+            PacketInfo,
+            SocketOption,
+            SocketOptionName,
+        },
         scheduler::Yielder,
         SharedDemiRuntime,
     },
@@ -14,7 +19,10 @@ use ::socket2::{
 };
 use ::std::{
     fmt::Debug,
-    net::SocketAddr,
+    net::{
+        Ipv4Addr,
+        SocketAddr,
+    },
 };
 
 //======================================================================================================================
@@ -26,8 +34,8 @@ use ::std::{
 pub trait NetworkTransport: Clone + 'static {
     type SocketDescriptor: Debug;
 
-    /// Create a new network transport.
-    fn new(config: &Config, runtime: &mut SharedDemiRuntime) -> Self;
+    /// Create a new network transport. Fails if `config` is invalid.
+    fn new(config: &Config, runtime: &mut SharedDemiRuntime) -> Result<Self, Fail>;
 
     /// Create a socket using the network transport layer.
     fn socket(&mut self, domain: Domain, typ: Type) -> Result<Self::SocketDescriptor, Fail>;
@@ -42,12 +50,13 @@ pub trait NetworkTransport: Clone + 'static {
     /// internal functions, never exposed to the application.
     fn hard_close(&mut self, sd: &mut Self::SocketDescriptor) -> Result<(), Fail>;
 
-    /// Asynchronously accept a new connection on a listening socket.
+    /// Asynchronously accept a new connection on a listening socket. Returns the new socket along with the (local,
+    /// remote) address pair of the connection.
     fn accept(
         &mut self,
         sd: &mut Self::SocketDescriptor,
         yielder: Yielder,
-    ) -> impl std::future::Future<Output = Result<(Self::SocketDescriptor, SocketAddr), Fail>>;
+    ) -> impl std::future::Future<Output = Result<(Self::SocketDescriptor, SocketAddr, SocketAddr), Fail>>;
 
     /// Asynchronously connect this socket to [remote].
     fn connect(
@@ -57,23 +66,43 @@ pub trait NetworkTransport: Clone + 'static {
         yielder: Yielder,
     ) -> impl std::future::Future<Output = Result<(), Fail>>;
 
-    /// Push data to a connected socket.
+    /// Push data to a connected socket. Transports may accept only part of `buf`, in which case the data that was not
+    /// accepted is left in it. On datagram sockets, `src` is the local address that the datagram goes out from instead
+    /// of the address that the socket is bound to, as with IP_PKTINFO.
     fn push(
         &mut self,
         sd: &mut Self::SocketDescriptor,
         buf: &mut DemiBuffer,
         addr: Option<SocketAddr>,
+        src: Option<Ipv4Addr>,
         yielder: Yielder,
     ) -> impl std::future::Future<Output = Result<(), Fail>>;
 
-    /// Pop data from a connected socket.
+    /// Pop up to `size` bytes of data from a connected socket. Returns the buffer that holds the data, which transports
+    /// should draw from the buffer pool of the runtime, along with the address of the sender, if known, and the
+    /// ancillary data of the datagram, if the socket asked for it with IP_PKTINFO.
     fn pop(
         &mut self,
         sd: &mut Self::SocketDescriptor,
-        buf: &mut DemiBuffer,
         size: usize,
         yielder: Yielder,
-    ) -> impl std::future::Future<Output = Result<Option<SocketAddr>, Fail>>;
+    ) -> impl std::future::Future<Output = Result<(Option<SocketAddr>, Option<PacketInfo>, DemiBuffer), Fail>>;
+
+    /// Sets the socket option `option` on a socket. Transports fail with ENOPROTOOPT on options that they do not
+    /// support, which is all of them by default.
+    fn set_socket_option(&mut self, _sd: &mut Self::SocketDescriptor, _option: SocketOption) -> Result<(), Fail> {
+        Err(Fail::new(libc::ENOPROTOOPT, "unsupported socket option"))
+    }
+
+    /// Gets the value of the socket option `name` of a socket. Transports fail with ENOPROTOOPT on options that they
+    /// do not support, which is all of them by default.
+    fn get_socket_option(
+        &mut self,
+        _sd: &mut Self::SocketDescriptor,
+        _name: SocketOptionName,
+    ) -> Result<SocketOption, Fail> {
+        Err(Fail::new(libc::ENOPROTOOPT, "unsupported socket option"))
+    }
 
     /// Asynchronously close a socket.
     fn close(
@@ -0,0 +1,20 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Instantaneous Occupancy of the Buffers of a Queue
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BufferUsage {
+    /// Bytes pushed by the application that have not been sent yet.
+    pub send_queued: usize,
+    /// Bytes sent but not yet acknowledged, which are held for retransmission. Acknowledged bytes are freed right
+    /// away, so they are never accounted for.
+    pub send_unacked: usize,
+    /// Bytes received that are waiting to be popped by the application.
+    pub recv_queued: usize,
+    /// Bytes received out of order that are held until the missing data before them arrives.
+    pub recv_out_of_order: usize,
+}
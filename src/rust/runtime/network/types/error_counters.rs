@@ -0,0 +1,22 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Cumulative Error Counters of a Queue
+///
+/// These only ever go up, until they are reset by the application. Counters that do not apply to the protocol of a
+/// queue stay at zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ErrorCounters {
+    /// Segments that were sent again because they were not acknowledged in time.
+    pub retransmits: u64,
+    /// Segments that were received ahead of data that is still missing.
+    pub out_of_order: u64,
+    /// Incoming packets that were dropped because they were malformed or failed their checksum.
+    pub malformed: u64,
+    /// ICMP errors that were reported for packets sent by the queue.
+    pub icmp_errors: u64,
+}
@@ -1,14 +1,27 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
+mod buffer_usage;
+mod error_counters;
 mod macaddr;
+mod packet_info;
 mod portnum;
+mod readiness;
+mod socket_option;
 
 //==============================================================================
 // Exports
 //==============================================================================
 
 pub use self::{
+    buffer_usage::BufferUsage,
+    error_counters::ErrorCounters,
     macaddr::MacAddress,
+    packet_info::PacketInfo,
     portnum::Port16,
+    readiness::Readiness,
+    socket_option::{
+        SocketOption,
+        SocketOptionName,
+    },
 };
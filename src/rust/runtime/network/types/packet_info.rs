@@ -0,0 +1,22 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use ::std::net::Ipv4Addr;
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Ancillary Data of a Received Datagram (i.e. IP_PKTINFO)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PacketInfo {
+    /// Destination address in the IPv4 header of the datagram. For sockets bound to the wildcard address, this tells
+    /// which of the local addresses the datagram was sent to.
+    pub dst_addr: Ipv4Addr,
+    /// Index of the interface on which the datagram was received.
+    pub ifindex: u32,
+}
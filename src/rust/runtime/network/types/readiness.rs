@@ -0,0 +1,23 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Readiness of a Queue, as Reported to Pollers
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Readiness {
+    /// A pop would complete right away, either with data, with the end of the stream or with an error. On listening
+    /// sockets, an accept would complete right away.
+    pub readable: bool,
+    /// A push would be accepted right away.
+    pub writable: bool,
+    /// The remote peer closed its side of the connection (i.e. POLLRDHUP). Pops return the end of the stream once the
+    /// data received before it is drained, but pushes are still accepted until the connection is closed locally.
+    pub read_closed: bool,
+    /// Both sides of the connection are closed, or it was reset (i.e. POLLHUP).
+    pub hangup: bool,
+    /// An error is pending on the connection (i.e. POLLERR).
+    pub error: bool,
+}
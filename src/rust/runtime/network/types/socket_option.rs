@@ -0,0 +1,68 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Socket Option, Along With Its Value
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SocketOption {
+    /// Sends the data of small pushes right away on a TCP socket, rather than coalescing it (i.e. TCP_NODELAY).
+    NoDelay(bool),
+    /// Allows a UDP socket to send to and receive from the broadcast address (i.e. SO_BROADCAST).
+    Broadcast(bool),
+    /// Number of bytes that pops wait for on a TCP socket, unless the remote peer pushed fewer (i.e. SO_RCVLOWAT).
+    RcvLowat(usize),
+    /// Number of bytes that a UDP socket holds in its receive queue before dropping datagrams (i.e. SO_RCVBUF).
+    RcvBuf(usize),
+    /// Number of bytes that a TCP socket holds in its send buffer, whether sent or not, before pushes have to wait for
+    /// acknowledgements to free room (i.e. SO_SNDBUF).
+    SndBuf(usize),
+    /// Whether a socket is listening for incoming connections (i.e. SO_ACCEPTCONN). This option is read-only.
+    AcceptConn(bool),
+    /// Reports the destination address and receiving interface of each datagram that a UDP socket pops, on top of
+    /// its source address (i.e. IP_PKTINFO).
+    PktInfo(bool),
+    /// Resolves the destinations of a UDP socket on the local link, instead of sending through the gateway or route
+    /// that covers them (i.e. SO_DONTROUTE).
+    DontRoute(bool),
+    /// Lets a UDP socket share its local address with other sockets that set it too (i.e. SO_REUSEADDR). Datagrams sent
+    /// to a shared address are delivered to the socket that was bound to it first.
+    ReuseAddr(bool),
+}
+
+/// Name of a Socket Option, to Look Up Its Value
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SocketOptionName {
+    NoDelay,
+    Broadcast,
+    RcvLowat,
+    RcvBuf,
+    SndBuf,
+    AcceptConn,
+    PktInfo,
+    DontRoute,
+    ReuseAddr,
+}
+
+//==============================================================================
+// Associated Functions
+//==============================================================================
+
+impl SocketOption {
+    /// Returns the name of the target option.
+    pub fn name(&self) -> SocketOptionName {
+        match self {
+            SocketOption::NoDelay(_) => SocketOptionName::NoDelay,
+            SocketOption::Broadcast(_) => SocketOptionName::Broadcast,
+            SocketOption::RcvLowat(_) => SocketOptionName::RcvLowat,
+            SocketOption::RcvBuf(_) => SocketOptionName::RcvBuf,
+            SocketOption::SndBuf(_) => SocketOptionName::SndBuf,
+            SocketOption::AcceptConn(_) => SocketOptionName::AcceptConn,
+            SocketOption::PktInfo(_) => SocketOptionName::PktInfo,
+            SocketOption::DontRoute(_) => SocketOptionName::DontRoute,
+            SocketOption::ReuseAddr(_) => SocketOptionName::ReuseAddr,
+        }
+    }
+}
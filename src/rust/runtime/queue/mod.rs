@@ -12,7 +12,12 @@ mod qtype;
 
 use crate::runtime::{
     fail::Fail,
+    memory::{
+        fill_sgarray,
+        SharedBufferPool,
+    },
     scheduler::TaskWithResult,
+    types::demi_sgarray_t,
 };
 use ::slab::{
     Iter,
@@ -58,6 +63,15 @@ pub trait NetworkQueue: IoQueue {
     fn remote(&self) -> Option<SocketAddrV4>;
 }
 
+/// Summary of an I/O queue: its descriptor and type, along with the local and remote addresses of its socket, if any.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueInfo {
+    pub qd: QDesc,
+    pub qtype: QType,
+    pub local: Option<SocketAddrV4>,
+    pub remote: Option<SocketAddrV4>,
+}
+
 /// I/O queue descriptors table.
 pub struct IoQueueTable {
     table: Slab<Box<dyn IoQueue>>,
@@ -165,15 +179,47 @@ impl IoQueueTable {
         Ok(downcast_queue::<T>(self.table.remove(index as usize))?)
     }
 
+    /// Gets the number of queues that are currently allocated.
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Checks whether no queue is currently allocated.
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
     /// Gets an iterator over all registered queues.
     pub fn get_values(&self) -> Iter<'_, Box<dyn IoQueue>> {
         self.table.iter()
     }
 
+    /// Gets an iterator over all registered queues, along with their I/O queue descriptors.
+    pub fn iter(&self) -> impl Iterator<Item = (QDesc, &Box<dyn IoQueue>)> {
+        self.table
+            .iter()
+            .map(|(index, boxed_queue)| (QDesc::from((index as u32) + Self::BASE_QD), boxed_queue))
+    }
+
     pub fn drain(&mut self) -> slab::Drain<'_, Box<dyn IoQueue>> {
         self.table.drain()
     }
 
+    /// Releases all queues of type `T`, and leaves queues of other types in place. This lets each of the LibOSes that
+    /// share a runtime release its own queues only.
+    pub fn drain_type<T: IoQueue>(&mut self) -> Vec<T> {
+        let indices: Vec<usize> = self
+            .table
+            .iter()
+            .filter(|(_, boxed_queue)| boxed_queue.as_any_ref().is::<T>())
+            .map(|(index, _)| index)
+            .collect();
+        indices
+            .into_iter()
+            .filter_map(|index| downcast_queue::<T>(self.table.remove(index)).ok())
+            .collect()
+    }
+
     /// Gets the index in the I/O queue descriptors table to which a given I/O queue descriptor refers to.
     fn get_index(&self, qd: &QDesc) -> Option<u32> {
         if Into::<u32>::into(*qd) < Self::BASE_QD {
@@ -238,6 +284,30 @@ pub fn downcast_queue<T: IoQueue>(boxed_queue: Box<dyn IoQueue>) -> Result<T, Fa
     }
 }
 
+/// Runs a pop `coroutine` to completion and copies the data that it pops into the caller-provided `sga`. The size of
+/// the pop should not exceed the capacity of `sga`, otherwise the remaining data is dropped. The popped buffer is given
+/// back to `buffer_pool` once copied.
+pub async fn pop_into_coroutine<F: Future<Output = (QDesc, OperationResult)>>(
+    coroutine: F,
+    sga: demi_sgarray_t,
+    mut buffer_pool: SharedBufferPool,
+) -> (QDesc, OperationResult) {
+    match coroutine.await {
+        (qd, OperationResult::Pop(addr, buf)) => {
+            // A zero-length buffer signals end of stream, in which case nothing gets copied.
+            let nbytes: usize = fill_sgarray(&sga, &buf);
+            buffer_pool.recycle(buf);
+            (qd, OperationResult::PopInto(addr, sga, nbytes))
+        },
+        (qd, OperationResult::PopWithInfo(addr, _, buf)) => {
+            let nbytes: usize = fill_sgarray(&sga, &buf);
+            buffer_pool.recycle(buf);
+            (qd, OperationResult::PopInto(Some(addr), sga, nbytes))
+        },
+        (qd, result) => (qd, result),
+    }
+}
+
 //======================================================================================================================
 // Trait Implementations
 //======================================================================================================================
@@ -8,6 +8,11 @@
 use crate::runtime::{
     fail::Fail,
     memory::DemiBuffer,
+    network::types::{
+        MacAddress,
+        PacketInfo,
+    },
+    types::demi_sgarray_t,
     QDesc,
 };
 use ::std::{
@@ -23,9 +28,18 @@ use ::std::{
 pub enum OperationResult {
     Connect,
     Accept((QDesc, SocketAddrV4)),
-    Push,
+    /// Push, along with the number of bytes that were accepted. This may be fewer than were submitted, in which case
+    /// the rest of the data was not sent and may be pushed again.
+    Push(usize),
     Pop(Option<SocketAddrV4>, DemiBuffer),
+    /// Pop from a UDP socket on which IP_PKTINFO is set, along with the source address and the ancillary data of the
+    /// datagram.
+    PopWithInfo(SocketAddrV4, PacketInfo, DemiBuffer),
+    /// Pop into a caller-provided scatter-gather array, along with the number of bytes that were copied into it.
+    PopInto(Option<SocketAddrV4>, demi_sgarray_t, usize),
     Close,
+    /// Resolution of the link address of a remote host, along with the link address of its next hop.
+    Resolve(MacAddress),
     Failed(Fail),
 }
 
@@ -38,9 +52,12 @@ impl fmt::Debug for OperationResult {
         match self {
             OperationResult::Connect => write!(f, "Connect"),
             OperationResult::Accept(..) => write!(f, "Accept"),
-            OperationResult::Push => write!(f, "Push"),
+            OperationResult::Push(..) => write!(f, "Push"),
             OperationResult::Pop(..) => write!(f, "Pop"),
+            OperationResult::PopWithInfo(..) => write!(f, "PopWithInfo"),
+            OperationResult::PopInto(..) => write!(f, "PopInto"),
             OperationResult::Close => write!(f, "Close"),
+            OperationResult::Resolve(..) => write!(f, "Resolve"),
             OperationResult::Failed(ref e) => write!(f, "Failed({:?})", e),
         }
     }
@@ -97,6 +97,12 @@ impl YielderHandle {
         self.result_handle.borrow_mut().take()
     }
 
+    /// Checks whether nothing but this handle refers to the yielded coroutine anymore, which happens once the coroutine
+    /// gave up waiting (e.g. because it only polled once) and completed.
+    pub fn is_abandoned(&self) -> bool {
+        Rc::strong_count(&self.result_handle) == 1
+    }
+
     /// Set the waker for this Yielder and return a reference to it.
     pub fn set_waker(&mut self, waker: Waker) {
         *self.waker_handle.borrow_mut() = Some(waker);
@@ -64,6 +64,17 @@ impl WakerPage {
         notified
     }
 
+    /// Queries whether or not any future in the target [WakerPage] that has not completed yet is notified.
+    pub fn has_notified(&self) -> bool {
+        self.peek_notified() != 0
+    }
+
+    /// Reads the notification flags of the futures in the target [WakerPage] that have not completed yet.
+    /// Notification flags are left unmodified by this operation.
+    pub fn peek_notified(&self) -> u64 {
+        self.notified.load() & !self.completed.load()
+    }
+
     /// Queries whether or not the completed flag for the `ix` future in the target [WakerPage] is set.
     pub fn has_completed(&self, ix: usize) -> bool {
         debug_assert!(ix < WAKER_BIT_LENGTH);
@@ -67,6 +67,8 @@ pub struct Scheduler {
     task_ids: HashMap<u64, usize>,
     /// Holds the waker bits for controlling task scheduling.
     waker_page_refs: Vec<WakerPageRef>,
+    /// Flags, for each waker page, the tasks that are pollers.
+    pollers: Vec<u64>,
     /// Small random number generator for tokens.
     rng: SmallRng,
 }
@@ -85,11 +87,10 @@ impl Scheduler {
             .task_ids
             .remove(&task_id)
             .expect("Token should be in the token table");
-        let (waker_page_ref, waker_page_offset): (&WakerPageRef, usize) = {
-            let (waker_page_index, waker_page_offset) = self.get_waker_page_index_and_offset(pin_slab_index);
-            (&self.waker_page_refs[waker_page_index], waker_page_offset)
-        };
-        waker_page_ref.clear(waker_page_offset);
+        let (waker_page_index, waker_page_offset): (usize, usize) =
+            self.get_waker_page_index_and_offset(pin_slab_index);
+        self.waker_page_refs[waker_page_index].clear(waker_page_offset);
+        self.pollers[waker_page_index] &= !(1 << waker_page_offset);
         if let Some(task) = self.tasks.remove_unpin(pin_slab_index) {
             trace!(
                 "remove(): name={:?}, id={:?}, pin_slab_index={:?}",
@@ -147,6 +148,21 @@ impl Scheduler {
         Some(TaskHandle::new(task_id, waker_page_ref.clone(), waker_page_offset))
     }
 
+    /// Inserts a poller into our scheduler returning a handle corresponding to it. A poller is a task that never
+    /// completes and is runnable on every poll, because it checks for external input, such as incoming packets. So
+    /// pollers are not accounted for by [has_runnable_foreground_tasks](Self::has_runnable_foreground_tasks).
+    pub fn insert_poller<F: Task>(&mut self, future: F) -> Option<TaskHandle> {
+        let handle: TaskHandle = self.insert(future)?;
+        let pin_slab_index: usize = *self
+            .task_ids
+            .get(&handle.get_task_id())
+            .expect("Token should be in the token table");
+        let (waker_page_index, waker_page_offset): (usize, usize) =
+            self.get_waker_page_index_and_offset(pin_slab_index);
+        self.pollers[waker_page_index] |= 1 << waker_page_offset;
+        Some(handle)
+    }
+
     /// Generate a new id. If the id is currently in use, keep generating until we find an unused id.
     fn get_new_task_id(&mut self, pin_slab_index: usize) -> u64 {
         let new_task_id: u64 = 'get_id: {
@@ -182,18 +198,42 @@ impl Scheduler {
     fn add_new_pages_up_to_pin_slab_index(&mut self, pin_slab_index: usize) {
         while pin_slab_index >= (self.waker_page_refs.len() << WAKER_BIT_LENGTH_SHIFT) {
             self.waker_page_refs.push(WakerPageRef::default());
+            self.pollers.push(0);
         }
     }
 
     /// Poll all futures which are ready to run again. Tasks in our scheduler are notified when
     /// relevant data or events happen. The relevant event have callback function (the waker) which
-    /// they can invoke to notify the scheduler that future should be polled again.
-    pub fn poll(&mut self) {
+    /// they can invoke to notify the scheduler that future should be polled again. Returns the number of futures that
+    /// were polled.
+    pub fn poll(&mut self) -> usize {
+        let mut num_polled: usize = 0;
         let num_waker_pages = self.get_num_waker_pages();
         for waker_page_index in 0..num_waker_pages {
             let notified_offsets: u64 = self.get_offsets_for_ready_tasks(waker_page_index);
-            self.poll_notified_tasks(waker_page_index, notified_offsets);
+            num_polled += self.poll_notified_tasks(waker_page_index, notified_offsets);
         }
+        num_polled
+    }
+
+    /// Checks whether any future in the target scheduler is ready to be polled.
+    pub fn has_runnable_tasks(&self) -> bool {
+        self.waker_page_refs
+            .iter()
+            .any(|waker_page_ref| waker_page_ref.has_notified())
+    }
+
+    /// Checks whether any future in the target scheduler other than a poller is ready to be polled.
+    pub fn has_runnable_foreground_tasks(&self) -> bool {
+        self.waker_page_refs
+            .iter()
+            .zip(self.pollers.iter())
+            .any(|(waker_page_ref, pollers)| waker_page_ref.peek_notified() & !pollers != 0)
+    }
+
+    /// Checks whether the target scheduler holds any poller.
+    pub fn has_pollers(&self) -> bool {
+        self.pollers.iter().any(|pollers| *pollers != 0)
     }
 
     fn get_num_waker_pages(&self) -> usize {
@@ -205,8 +245,10 @@ impl Scheduler {
         waker_page_ref.take_notified()
     }
 
-    fn poll_notified_tasks(&mut self, waker_page_index: usize, notified_offsets: u64) {
+    fn poll_notified_tasks(&mut self, waker_page_index: usize, notified_offsets: u64) -> usize {
+        let mut num_polled: usize = 0;
         for waker_page_offset in BitIter::from(notified_offsets) {
+            num_polled += 1;
             // Get the pinned ref.
             let pinned_ptr = {
                 let pin_slab_index: usize = Scheduler::get_pin_slab_index(waker_page_index, waker_page_offset);
@@ -233,6 +275,7 @@ impl Scheduler {
                 self.waker_page_refs[waker_page_index].mark_completed(waker_page_offset)
             }
         }
+        num_polled
     }
 
     fn get_waker_page_offset(pin_slab_index: usize) -> usize {
@@ -256,6 +299,7 @@ impl Default for Scheduler {
             tasks: PinSlab::new(),
             task_ids: HashMap::<u64, usize>::new(),
             waker_page_refs: vec![],
+            pollers: vec![],
             #[cfg(debug_assertions)]
             rng: SmallRng::seed_from_u64(SCHEDULER_SEED),
             #[cfg(not(debug_assertions))]
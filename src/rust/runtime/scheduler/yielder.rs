@@ -17,6 +17,7 @@ use ::std::{
         Context,
         Poll,
     },
+    time::Instant,
 };
 
 //======================================================================================================================
@@ -35,9 +36,17 @@ struct Yield {
     yielder_handle: YielderHandle,
 }
 
-/// Yielder lets a single coroutine yield to the scheduler. The yield handle can be used to wake the coroutine.
+/// Yielder lets a single coroutine yield to the scheduler. The yield handle can be used to wake the coroutine. Clones
+/// share the same handle, so they may only be used by the coroutine that owns the original one, one at a time.
+///
+/// A yielder may also carry a deadline that bounds all the operations of its coroutine, so that a chain of awaits is
+/// bounded as a whole rather than one at a time. Timers consult it: waiting past the deadline fails with `ETIMEDOUT`
+/// once the deadline expires. Operations that have a timeout of their own end at whichever expires first.
+#[derive(Clone)]
 pub struct Yielder {
     yielder_handle: YielderHandle,
+    /// Deadline of the coroutine, if any.
+    deadline: Option<Instant>,
 }
 
 //======================================================================================================================
@@ -60,9 +69,34 @@ impl Yielder {
     pub fn new() -> Self {
         Self {
             yielder_handle: YielderHandle::new(),
+            deadline: None,
         }
     }
 
+    /// Creates a Yielder for a sub-operation of the coroutine that owns the target one. It has a handle of its own, so
+    /// that both can wait at the same time, and it carries the same deadline.
+    pub fn new_child(&self) -> Self {
+        Self {
+            yielder_handle: YielderHandle::new(),
+            deadline: self.deadline,
+        }
+    }
+
+    /// Bounds the target Yielder by `deadline`. If it carries a deadline already, the earlier of both is kept, so that
+    /// nested operations may shorten the deadline of their coroutine, but never extend it.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(match self.deadline {
+            Some(current) => current.min(deadline),
+            None => deadline,
+        });
+        self
+    }
+
+    /// Returns the deadline that the target Yielder carries, if any.
+    pub fn get_deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
     /// Return a handle to this Yielder for waking the yielded coroutine.
     pub fn get_handle(&self) -> YielderHandle {
         self.yielder_handle.clone()
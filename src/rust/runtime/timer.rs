@@ -19,8 +19,10 @@ use ::futures::{
     future::FusedFuture,
     FutureExt,
 };
+use ::libc::ETIMEDOUT;
 use ::std::{
     collections::BinaryHeap,
+    fmt,
     future::Future,
     ops::{
         Deref,
@@ -84,18 +86,66 @@ impl SharedTimer {
         self.now
     }
 
+    /// Gets the time at which the next pending timer expires, if any.
+    pub fn next_expiry(&self) -> Option<Instant> {
+        self.heap.peek().map(|Reverse(entry)| entry.expiry)
+    }
+
+    /// Checks whether any coroutine is waiting for the target timer to expire.
+    pub fn has_pending_timers(&self) -> bool {
+        !self.heap.is_empty()
+    }
+
     pub async fn wait(self, timeout: Duration, yielder: &Yielder) -> Result<(), Fail> {
         let now: Instant = self.now;
         self.wait_until(now + timeout, &yielder).await
     }
 
+    /// Waits until `expiry`. If `yielder` carries a deadline that comes first, this waits until the deadline instead,
+    /// and then fails with `ETIMEDOUT`.
     pub async fn wait_until(mut self, expiry: Instant, yielder: &Yielder) -> Result<(), Fail> {
+        let (expiry, cut_short): (Instant, bool) = match yielder.get_deadline() {
+            Some(deadline) if deadline < expiry => (deadline, true),
+            _ => (expiry, false),
+        };
+        if cut_short && expiry <= self.now {
+            return Err(Fail::new(ETIMEDOUT, "deadline expired"));
+        }
         let entry = TimerQueueEntry {
             expiry,
             yielder: yielder.get_handle(),
         };
         self.heap.push(Reverse(entry));
-        yielder.yield_until_wake().await
+        yielder.yield_until_wake().await?;
+        if cut_short {
+            return Err(Fail::new(ETIMEDOUT, "deadline expired"));
+        }
+        Ok(())
+    }
+
+    /// Runs `future`, an operation of the coroutine that owns `yielder`, within the deadline that `yielder` carries,
+    /// if any. Fails with `ETIMEDOUT` if the deadline expires before the operation completes, in which case the
+    /// operation is dropped.
+    pub async fn within_deadline<F: Future>(&self, future: F, yielder: &Yielder) -> Result<F::Output, Fail> {
+        let deadline: Instant = match yielder.get_deadline() {
+            Some(deadline) => deadline,
+            None => return Ok(future.await),
+        };
+        if deadline <= self.now {
+            return Err(Fail::new(ETIMEDOUT, "deadline expired"));
+        }
+        let timer_yielder: Yielder = yielder.new_child();
+        let deadline_future = self.clone().wait_until(deadline, &timer_yielder).fuse();
+        let future = future.fuse();
+        futures::pin_mut!(deadline_future);
+        futures::pin_mut!(future);
+        futures::select_biased! {
+            result = future => Ok(result),
+            result = deadline_future => match result {
+                Ok(()) => Err(Fail::new(ETIMEDOUT, "deadline expired")),
+                Err(e) => Err(e),
+            },
+        }
     }
 }
 
@@ -152,6 +202,15 @@ impl DerefMut for SharedTimer {
     }
 }
 
+impl fmt::Debug for SharedTimer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedTimer")
+            .field("now", &self.now)
+            .field("num_pending_timers", &self.heap.len())
+            .finish()
+    }
+}
+
 impl PartialEq for TimerQueueEntry {
     fn eq(&self, other: &TimerQueueEntry) -> bool {
         // This is technically not correct. However for the usage in this module
@@ -184,11 +243,17 @@ mod tests {
     use super::SharedTimer;
     use crate::runtime::scheduler::Yielder;
     use ::anyhow::Result;
-    use futures::task::noop_waker_ref;
+    use futures::{
+        future,
+        task::noop_waker_ref,
+    };
     use std::{
         future::Future,
         pin::Pin,
-        task::Context,
+        task::{
+            Context,
+            Poll,
+        },
         time::{
             Duration,
             Instant,
@@ -240,4 +305,38 @@ mod tests {
 
         Ok(())
     }
+
+    /// Tests that an operation that is not a timer is bounded by the deadline of the yielder of its coroutine, and that
+    /// a nested deadline may only shorten it.
+    #[test]
+    fn test_timer_within_deadline() -> Result<()> {
+        let mut ctx = Context::from_waker(noop_waker_ref());
+        let now = Instant::now();
+        let mut timer: SharedTimer = SharedTimer::new(now);
+        let timer_ref: SharedTimer = timer.clone();
+        let deadline: Instant = now + Duration::from_secs(2);
+
+        // Operations run as they are without a deadline.
+        let unbounded = timer_ref.within_deadline(future::ready(7), &Yielder::new());
+        futures::pin_mut!(unbounded);
+        match Future::poll(unbounded.as_mut(), &mut ctx) {
+            Poll::Ready(Ok(7)) => (),
+            result => anyhow::bail!("operation should have completed: {:?}", result),
+        }
+
+        let yielder: Yielder = Yielder::new().with_deadline(deadline);
+        let child: Yielder = yielder.new_child().with_deadline(now + Duration::from_secs(5));
+        crate::ensure_eq!(child.get_deadline(), Some(deadline));
+
+        let bounded = timer_ref.within_deadline(future::pending::<()>(), &child);
+        futures::pin_mut!(bounded);
+        crate::ensure_eq!(Future::poll(bounded.as_mut(), &mut ctx).is_pending(), true);
+        timer.advance_clock(deadline);
+        match Future::poll(bounded.as_mut(), &mut ctx) {
+            Poll::Ready(Err(e)) if e.errno == libc::ETIMEDOUT => (),
+            result => anyhow::bail!("operation should have timed out: {:?}", result),
+        }
+
+        Ok(())
+    }
 }
@@ -18,6 +18,7 @@ pub use self::{
     ops::{
         demi_accept_result_t,
         demi_opcode_t,
+        demi_pop_info_result_t,
         demi_qr_value_t,
         demi_qresult_t,
     },
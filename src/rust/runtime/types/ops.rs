@@ -30,6 +30,7 @@ pub enum demi_opcode_t {
     DEMI_OPC_CONNECT,
     DEMI_OPC_CLOSE,
     DEMI_OPC_FAILED,
+    DEMI_OPC_RESOLVE,
 }
 
 /// Result for `accept()`
@@ -40,10 +41,21 @@ pub struct demi_accept_result_t {
     pub addr: SockAddr,
 }
 
+/// Result for `pop()` on a UDP socket on which IP_PKTINFO is set. The popped scatter-gather array comes first, so that
+/// it is found at the same place as for any other pop.
+#[repr(C, packed)]
+#[derive(Copy, Clone)]
+pub struct demi_pop_info_result_t {
+    pub sga: demi_sgarray_t,
+    pub dst_addr: SockAddr,
+    pub ifindex: u32,
+}
+
 #[repr(C)]
 pub union demi_qr_value_t {
     pub sga: demi_sgarray_t,
     pub ares: demi_accept_result_t,
+    pub pinfo: demi_pop_info_result_t,
 }
 
 /// Result
@@ -74,6 +86,23 @@ mod test {
         Ok(())
     }
 
+    /// Tests if `demi_pop_info_result_t` has the expected size.
+    #[test]
+    fn test_size_demi_pop_info_result_t() -> Result<(), anyhow::Error> {
+        // Size of a demi_sgarray_t structure.
+        const SGA_SIZE: usize = mem::size_of::<demi_sgarray_t>();
+        // Size of a sockaddr structure.
+        const ADDR_SIZE: usize = 16;
+        // Size of a u32.
+        const IFINDEX_SIZE: usize = 4;
+        // Size of a demi_pop_info_result_t structure.
+        crate::ensure_eq!(
+            mem::size_of::<demi_pop_info_result_t>(),
+            SGA_SIZE + ADDR_SIZE + IFINDEX_SIZE
+        );
+        Ok(())
+    }
+
     /// Tests if `demi_qr_value_t` has the expected size.
     #[test]
     fn test_size_demi_qr_value_t() -> Result<(), anyhow::Error> {
@@ -81,8 +110,13 @@ mod test {
         const SGA_SIZE: usize = mem::size_of::<demi_sgarray_t>();
         // Size of a demi_accept_result_t structure.
         const ARES_SIZE: usize = mem::size_of::<demi_accept_result_t>();
+        // Size of a demi_pop_info_result_t structure.
+        const PINFO_SIZE: usize = mem::size_of::<demi_pop_info_result_t>();
         // Size of a demi_qr_value_t structure.
-        crate::ensure_eq!(mem::size_of::<demi_qr_value_t>(), std::cmp::max(SGA_SIZE, ARES_SIZE));
+        crate::ensure_eq!(
+            mem::size_of::<demi_qr_value_t>(),
+            std::cmp::max(std::cmp::max(SGA_SIZE, ARES_SIZE), PINFO_SIZE)
+        );
         Ok(())
     }
 
@@ -112,6 +112,21 @@ static bool inval_pushto(void)
     return (demi_pushto(qt, qd, sga, saddr, size) != 0);
 }
 
+/**
+ * @brief Issues an invalid call to demi_pushto_from().
+ */
+static bool inval_pushto_from(void)
+{
+    demi_qtoken_t *qt = NULL;
+    int qd = -1;
+    demi_sgarray_t *sga = NULL;
+    struct sockaddr *src = NULL;
+    struct sockaddr *dest = NULL;
+    socklen_t size = -1;
+
+    return (demi_pushto_from(qt, qd, sga, src, size, dest, size) != 0);
+}
+
 /**
  * @brief Issues an invalid call to demi_pop().
  */
@@ -210,7 +225,8 @@ static struct test tests_libos[] = {{inval_socket, "invalid demi_socket()"},   {
                                     {inval_bind, "invalid demi_bind()"},       {inval_close, "invalid_demi_close()"},
                                     {inval_connect, "invalid demi_connect()"}, {inval_listen, "invalid demi_listen()"},
                                     {inval_pop, "invalid demi_pop()"},         {inval_push, "invalid demi_push()"},
-                                    {inval_pushto, "invalid demi_pushto()"}};
+                                    {inval_pushto, "invalid demi_pushto()"},
+                                    {inval_pushto_from, "invalid demi_pushto_from()"}};
 
 /**
  * @brief Tests for system calls in demi/sga.h
@@ -15,6 +15,9 @@ use ::demikernel::{
         network::{
             config::{
                 ArpConfig,
+                Ethernet2Config,
+                PcapConfig,
+                RoutingConfig,
                 TcpConfig,
                 UdpConfig,
             },
@@ -53,6 +56,18 @@ impl DummyLibOS {
         tx: Sender<DemiBuffer>,
         rx: Receiver<DemiBuffer>,
         arp: HashMap<Ipv4Addr, MacAddress>,
+    ) -> Result<SharedInetStack, Fail> {
+        Self::new_with_pcap_config(link_addr, ipv4_addr, tx, rx, arp, PcapConfig::default())
+    }
+
+    /// Initializes the libOS, capturing packets as specified in `pcap_config`.
+    pub fn new_with_pcap_config(
+        link_addr: MacAddress,
+        ipv4_addr: Ipv4Addr,
+        tx: Sender<DemiBuffer>,
+        rx: Receiver<DemiBuffer>,
+        arp: HashMap<Ipv4Addr, MacAddress>,
+        pcap_config: PcapConfig,
     ) -> Result<SharedInetStack, Fail> {
         let runtime: SharedDemiRuntime = SharedDemiRuntime::default();
         let transport: SharedDummyRuntime = SharedDummyRuntime::new(rx, tx);
@@ -64,7 +79,8 @@ impl DummyLibOS {
             Some(false),
         );
         let udp_config: UdpConfig = UdpConfig::default();
-        let tcp_config: TcpConfig = TcpConfig::default();
+        // Use a short maximum segment lifetime, so that closing a connection does not linger in TIME-WAIT for long.
+        let tcp_config: TcpConfig = TcpConfig::default().set_msl(Duration::from_millis(500));
         let rng_seed: [u8; 32] = [0; 32];
         logging::initialize();
         SharedInetStack::new(
@@ -76,6 +92,9 @@ impl DummyLibOS {
             tcp_config,
             rng_seed,
             arp_config,
+            pcap_config,
+            Ethernet2Config::default(),
+            RoutingConfig::default(),
         )
     }
 
@@ -12,6 +12,7 @@ use ::demikernel::{
     inetstack::SharedInetStack,
     runtime::{
         memory::DemiBuffer,
+        network::config::PcapConfig,
         OperationResult,
         QDesc,
         QToken,
@@ -47,6 +48,8 @@ pub const AF_INET: i32 = libc::AF_INET;
 pub const SOCK_STREAM: i32 = libc::SOCK_STREAM;
 
 use std::{
+    env,
+    fs,
     net::{
         IpAddr,
         Ipv4Addr,
@@ -54,6 +57,8 @@ use std::{
         SocketAddr,
         SocketAddrV6,
     },
+    path::PathBuf,
+    process,
     thread::{
         self,
         JoinHandle,
@@ -251,6 +256,147 @@ fn tcp_establish_connection_bound() -> Result<()> {
     Ok(())
 }
 
+/// Tests if a connection produces a readable packet capture with the connection setup segments.
+#[test]
+fn tcp_establish_connection_pcap() -> Result<()> {
+    const TCP_FLAG_SYN: u8 = 0x02;
+    const TCP_FLAG_ACK: u8 = 0x10;
+
+    let (alice_tx, alice_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+    let (bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+    let path: PathBuf = env::temp_dir().join(format!("demikernel-tcp-{}.pcap", process::id()));
+    let alice_path: PathBuf = path.clone();
+
+    let alice: JoinHandle<Result<()>> = thread::spawn(move || {
+        let pcap_config: PcapConfig = PcapConfig::new(Some(alice_path.to_string_lossy().to_string()), None);
+        let mut libos: SharedInetStack =
+            match DummyLibOS::new_with_pcap_config(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp(), pcap_config) {
+                Ok(libos) => libos,
+                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+            };
+
+        let local: SocketAddr = SocketAddr::new(ALICE_IP, PORT_BASE);
+
+        // Open connection.
+        let sockqd: QDesc = safe_socket(&mut libos)?;
+        safe_bind(&mut libos, sockqd, local)?;
+        safe_listen(&mut libos, sockqd)?;
+        let qt: QToken = safe_accept(&mut libos, sockqd)?;
+        let (_, qr): (QDesc, OperationResult) = safe_wait2(&mut libos, qt)?;
+
+        let qd: QDesc = match qr {
+            OperationResult::Accept((qd, addr)) if addr.ip() == &BOB_IPV4 => qd,
+            _ => {
+                // Close socket on error.
+                // FIXME: https://github.com/demikernel/demikernel/issues/633
+                anyhow::bail!("accept() has failed")
+            },
+        };
+
+        // Close connection.
+        safe_close_active(&mut libos, qd)?;
+        safe_close_passive(&mut libos, sockqd)?;
+
+        Ok(())
+    });
+
+    let bob: JoinHandle<Result<()>> = thread::spawn(move || {
+        let mut libos: SharedInetStack = match DummyLibOS::new(BOB_MAC, BOB_IPV4, bob_tx, alice_rx, arp()) {
+            Ok(libos) => libos,
+            Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+        };
+
+        let remote: SocketAddr = SocketAddr::new(ALICE_IP, PORT_BASE);
+
+        // Open connection.
+        let sockqd: QDesc = safe_socket(&mut libos)?;
+        let qt: QToken = safe_connect(&mut libos, sockqd, remote)?;
+        let (_, qr): (QDesc, OperationResult) = safe_wait2(&mut libos, qt)?;
+        match qr {
+            OperationResult::Connect => (),
+            _ => {
+                // Close socket on error.
+                // FIXME: https://github.com/demikernel/demikernel/issues/633
+                anyhow::bail!("connect() has failed")
+            },
+        }
+
+        // Close connection.
+        safe_close_active(&mut libos, sockqd)?;
+
+        Ok(())
+    });
+
+    // It is safe to use unwrap here because there should not be any reason that we can't join the thread and if there
+    // is, there is nothing to clean up here on the main thread.
+    alice.join().unwrap()?;
+    bob.join().unwrap()?;
+
+    let capture: Vec<u8> = fs::read(&path)?;
+    fs::remove_file(&path)?;
+    let flags: Vec<u8> = parse_pcap_tcp_flags(&capture)?;
+
+    // The capture should hold the three-way handshake, in order.
+    let handshake: [u8; 3] = [TCP_FLAG_SYN, TCP_FLAG_SYN | TCP_FLAG_ACK, TCP_FLAG_ACK];
+    let mask: u8 = TCP_FLAG_SYN | TCP_FLAG_ACK;
+    let summary: Vec<u8> = flags.iter().map(|flags| flags & mask).collect();
+    if summary.len() < handshake.len() || summary[..handshake.len()] != handshake {
+        anyhow::bail!("capture should start with a three-way handshake (flags={:?})", flags);
+    }
+
+    Ok(())
+}
+
+/// Parses a libpcap capture of Ethernet frames and returns the flags of every TCP segment in it.
+fn parse_pcap_tcp_flags(capture: &[u8]) -> Result<Vec<u8>> {
+    const PCAP_GLOBAL_HEADER_SIZE: usize = 24;
+    const PCAP_RECORD_HEADER_SIZE: usize = 16;
+    const ETHERNET_HEADER_SIZE: usize = 14;
+    const ETHERTYPE_IPV4: u16 = 0x0800;
+    const IPPROTO_TCP: u8 = 6;
+
+    let read_u32 = |offset: usize| -> u32 {
+        u32::from_le_bytes([
+            capture[offset],
+            capture[offset + 1],
+            capture[offset + 2],
+            capture[offset + 3],
+        ])
+    };
+
+    if capture.len() < PCAP_GLOBAL_HEADER_SIZE || read_u32(0) != 0xa1b2c3d4 || read_u32(20) != 1 {
+        anyhow::bail!("bad pcap global header");
+    }
+
+    let mut flags: Vec<u8> = Vec::new();
+    let mut offset: usize = PCAP_GLOBAL_HEADER_SIZE;
+    while offset < capture.len() {
+        if offset + PCAP_RECORD_HEADER_SIZE > capture.len() {
+            anyhow::bail!("truncated pcap record header");
+        }
+        let caplen: usize = read_u32(offset + 8) as usize;
+        let frame_start: usize = offset + PCAP_RECORD_HEADER_SIZE;
+        if frame_start + caplen > capture.len() {
+            anyhow::bail!("truncated pcap record");
+        }
+        let frame: &[u8] = &capture[frame_start..(frame_start + caplen)];
+        offset = frame_start + caplen;
+
+        if frame.len() < ETHERNET_HEADER_SIZE || u16::from_be_bytes([frame[12], frame[13]]) != ETHERTYPE_IPV4 {
+            continue;
+        }
+        let ipv4: &[u8] = &frame[ETHERNET_HEADER_SIZE..];
+        let ihl: usize = ((ipv4[0] & 0x0f) as usize) * 4;
+        if ipv4[9] != IPPROTO_TCP {
+            continue;
+        }
+        let tcp: &[u8] = &ipv4[ihl..];
+        flags.push(tcp[13]);
+    }
+
+    Ok(flags)
+}
+
 //======================================================================================================================
 // Push
 //======================================================================================================================
@@ -333,7 +479,7 @@ fn tcp_push_remote() -> Result<()> {
         let qt: QToken = safe_push2(&mut libos, sockqd, &bytes)?;
         let (_, qr): (QDesc, OperationResult) = safe_wait2(&mut libos, qt)?;
         match qr {
-            OperationResult::Push => (),
+            OperationResult::Push(_) => (),
             _ => {
                 // Close socket on error.
                 // FIXME: https://github.com/demikernel/demikernel/issues/633
@@ -924,7 +1070,7 @@ fn tcp_bad_push() -> Result<()> {
         let qt: QToken = safe_push2(&mut libos, sockqd, &bytes)?;
         let (_, qr): (QDesc, OperationResult) = safe_wait2(&mut libos, qt)?;
         match qr {
-            OperationResult::Push => (),
+            OperationResult::Push(_) => (),
             _ => {
                 // Close socket if error.
                 // FIXME: https://github.com/demikernel/demikernel/issues/633
@@ -1038,7 +1184,7 @@ fn tcp_bad_pop() -> Result<()> {
         let qt: QToken = safe_push2(&mut libos, sockqd, &bytes)?;
         let (_, qr): (QDesc, OperationResult) = safe_wait2(&mut libos, qt)?;
         match qr {
-            OperationResult::Push => (),
+            OperationResult::Push(_) => (),
             _ => {
                 // Close socket if error.
                 // FIXME: https://github.com/demikernel/demikernel/issues/633
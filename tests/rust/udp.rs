@@ -12,6 +12,7 @@ use ::demikernel::{
     inetstack::SharedInetStack,
     runtime::{
         memory::DemiBuffer,
+        network::types::MacAddress,
         OperationResult,
         QDesc,
         QToken,
@@ -47,7 +48,10 @@ pub const AF_INET: i32 = libc::AF_INET;
 pub const SOCK_DGRAM: i32 = libc::SOCK_DGRAM;
 
 use std::{
-    net::SocketAddr,
+    net::{
+        Ipv4Addr,
+        SocketAddr,
+    },
     thread::{
         self,
         JoinHandle,
@@ -235,7 +239,7 @@ fn udp_push_remote() -> Result<()> {
         };
         let (_, qr): (QDesc, OperationResult) = safe_wait2(&mut libos, qt)?;
         match qr {
-            OperationResult::Push => (),
+            OperationResult::Push(_) => (),
             _ => {
                 // Close socket on error.
                 // FIXME: https://github.com/demikernel/demikernel/issues/633
@@ -322,7 +326,7 @@ fn udp_push_remote() -> Result<()> {
         };
         let (_, qr): (QDesc, OperationResult) = safe_wait2(&mut libos, qt)?;
         match qr {
-            OperationResult::Push => (),
+            OperationResult::Push(_) => (),
             _ => {
                 // Close socket on error.
                 // FIXME: https://github.com/demikernel/demikernel/issues/633
@@ -392,7 +396,7 @@ fn udp_loopback() -> Result<()> {
         };
         let (_, qr): (QDesc, OperationResult) = safe_wait2(&mut libos, qt)?;
         match qr {
-            OperationResult::Push => (),
+            OperationResult::Push(_) => (),
             _ => {
                 // Close socket on error.
                 // FIXME: https://github.com/demikernel/demikernel/issues/633
@@ -471,7 +475,7 @@ fn udp_loopback() -> Result<()> {
         let qt: QToken = libos.pushto2(sockfd, &bytes, alice_addr).unwrap();
         let (_, qr): (QDesc, OperationResult) = safe_wait2(&mut libos, qt)?;
         match qr {
-            OperationResult::Push => (),
+            OperationResult::Push(_) => (),
             _ => {
                 // Close socket on error.
                 // FIXME: https://github.com/demikernel/demikernel/issues/633
@@ -497,6 +501,92 @@ fn udp_loopback() -> Result<()> {
     Ok(())
 }
 
+//======================================================================================================================
+// Local Address Configuration
+//======================================================================================================================
+
+/// Tests if local addresses that are set at runtime are used in emitted headers, and that they cannot be changed once
+/// sockets exist.
+#[test]
+fn udp_set_local_addrs() -> Result<()> {
+    const ETHERNET_HEADER_SIZE: usize = 14;
+    let (alice_tx, alice_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+    let (_bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+
+    // Start with placeholder addresses, as a deployment that learns its addressing at startup would.
+    let placeholder_mac: MacAddress = MacAddress::new([0x02, 0x00, 0x00, 0x00, 0x00, 0x01]);
+    let placeholder_ipv4: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+    let mut libos: SharedInetStack = match DummyLibOS::new(placeholder_mac, placeholder_ipv4, alice_tx, bob_rx, arp()) {
+        Ok(libos) => libos,
+        Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+    };
+    if let Err(e) = libos.set_local_addrs(ALICE_MAC, ALICE_IPV4) {
+        anyhow::bail!("set_local_addrs() failed: {:?}", e)
+    }
+    if libos.get_local_link_addr() != ALICE_MAC || libos.get_local_ipv4_addr() != ALICE_IPV4 {
+        anyhow::bail!("local addresses should have been updated");
+    }
+
+    // Send a datagram and check the addresses in its headers.
+    let sockfd: QDesc = match libos.socket(AF_INET, SOCK_DGRAM, 0) {
+        Ok(qd) => qd,
+        Err(e) => anyhow::bail!("failed to create socket: {:?}", e),
+    };
+    if let Err(e) = libos.bind(sockfd, SocketAddr::new(ALICE_IP, PORT_BASE)) {
+        anyhow::bail!("bind() failed: {:?}", e)
+    }
+    let qt: QToken = match libos.pushto2(sockfd, &[0x5a; 32], SocketAddr::new(BOB_IP, PORT_BASE)) {
+        Ok(qt) => qt,
+        Err(e) => anyhow::bail!("pushto() failed: {:?}", e),
+    };
+    match safe_wait2(&mut libos, qt)? {
+        (_, OperationResult::Push(_)) => (),
+        (_, qr) => anyhow::bail!("pushto() has failed: {:?}", qr),
+    }
+    let frame: DemiBuffer = match alice_rx.try_recv() {
+        Ok(frame) => frame,
+        Err(e) => anyhow::bail!("no frame was emitted: {:?}", e),
+    };
+    if MacAddress::from_bytes(&frame[6..12]) != ALICE_MAC {
+        anyhow::bail!("frame has wrong source link address");
+    }
+    let ipv4_src: Ipv4Addr = Ipv4Addr::new(
+        frame[ETHERNET_HEADER_SIZE + 12],
+        frame[ETHERNET_HEADER_SIZE + 13],
+        frame[ETHERNET_HEADER_SIZE + 14],
+        frame[ETHERNET_HEADER_SIZE + 15],
+    );
+    if ipv4_src != ALICE_IPV4 {
+        anyhow::bail!("datagram has wrong source IPv4 address (src={:?})", ipv4_src);
+    }
+
+    // Addresses must not change while a socket exists.
+    match libos.set_local_addrs(placeholder_mac, placeholder_ipv4) {
+        Err(e) if e.errno == libc::EBUSY => (),
+        Err(e) => anyhow::bail!("set_local_addrs() failed with wrong error: {:?}", e),
+        Ok(()) => anyhow::bail!("set_local_addrs() should fail while sockets exist"),
+    }
+
+    match libos.async_close(sockfd) {
+        Ok(qt) => {
+            safe_wait2(&mut libos, qt)?;
+        },
+        Err(e) => anyhow::bail!("close() failed: {:?}", e),
+    }
+
+    // Invalid addresses should be rejected.
+    match libos.set_local_addrs(MacAddress::broadcast(), ALICE_IPV4) {
+        Err(e) if e.errno == libc::EINVAL => (),
+        _ => anyhow::bail!("set_local_addrs() should reject a broadcast link address"),
+    }
+    match libos.set_local_addrs(ALICE_MAC, Ipv4Addr::UNSPECIFIED) {
+        Err(e) if e.errno == libc::EINVAL => (),
+        _ => anyhow::bail!("set_local_addrs() should reject an unspecified IPv4 address"),
+    }
+
+    Ok(())
+}
+
 //======================================================================================================================
 // Standalone Functions
 //======================================================================================================================